@@ -2,7 +2,8 @@
 
 import type { Product, Invoice } from '@/types';
 import { useEffect, useState, useMemo } from 'react';
-import { productCommands, customerCommands, invoiceCommands, settingsCommands } from '@/lib/tauri';
+import { productCommands, customerCommands, invoiceCommands, settingsCommands, parseCreditLimitError } from '@/lib/tauri';
+import { useAuth } from '@/contexts/AuthContext';
 import { useQueryClient, useQuery, useInfiniteQuery, keepPreviousData } from '@tanstack/react-query';
 import { LocationSelector } from '@/components/shared/LocationSelector';
 import { useLocationDefaults } from '@/hooks/useLocationDefaults';
@@ -22,6 +23,7 @@ type CartItem = {
 
 export default function Billing() {
   const queryClient = useQueryClient();
+  const { user } = useAuth();
   const [pdfUrl, setPdfUrl] = useState<string | null>(null);
   const [showPdfPreview, setShowPdfPreview] = useState(false);
   const [pdfFileName, setPdfFileName] = useState('');
@@ -322,7 +324,7 @@ export default function Billing() {
         ...cart,
         {
           product_id: product.id,
-          name: product.name,
+          name: product.display_name ?? product.name,
           unit_price: product.selling_price || product.price,
           cost_price: product.price, // Store cost price for profit-weighted discount
           quantity: 1,
@@ -438,7 +440,20 @@ export default function Billing() {
         initial_paid: paymentMethod === 'Credit' ? initialPaid : undefined,
       };
 
-      await invoiceCommands.create(invoiceInput);
+      try {
+        await invoiceCommands.create(invoiceInput);
+      } catch (error) {
+        const overLimit = parseCreditLimitError(error);
+        if (!overLimit) throw error;
+
+        const summary = `Credit limit ₹${overLimit.credit_limit.toFixed(2)}, outstanding ₹${overLimit.current_outstanding.toFixed(2)}, over by ₹${overLimit.overflow.toFixed(2)}.`;
+        if (user?.role !== 'admin') {
+          alert(`Credit limit exceeded. ${summary} A manager must approve this sale.`);
+          return;
+        }
+        if (!window.confirm(`Credit limit exceeded. ${summary} Approve this sale as ${user.username}?`)) return;
+        await invoiceCommands.create({ ...invoiceInput, allow_over_limit: true, approved_by: user.username });
+      }
 
       // Record location selection for smart defaults
       if (location.state && location.district && location.town) {
@@ -802,7 +817,7 @@ export default function Billing() {
                       size="md"
                     />
                     <div className="flex-1 min-w-0">
-                      <div className="font-semibold truncate">{product.name}</div>
+                      <div className="font-semibold truncate">{product.display_name ?? product.name}</div>
                       <div className="text-muted-foreground text-sm">SKU: {product.sku}</div>
                       <div className="flex justify-between mt-2 text-sm">
                         <span>₹{(product.selling_price || product.price).toFixed(0)}</span>
@@ -888,7 +903,7 @@ export default function Billing() {
                         size="md"
                       />
                       <div className="flex-1 min-w-0 pr-6">
-                        <div className="font-semibold truncate">{product.name}</div>
+                        <div className="font-semibold truncate">{product.display_name ?? product.name}</div>
                         <div className="text-muted-foreground text-sm">SKU: {product.sku}</div>
                         <div className="flex justify-between mt-2 text-sm">
                           <span>₹{(product.selling_price || product.price).toFixed(0)}</span>
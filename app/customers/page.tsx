@@ -81,7 +81,7 @@ export default function Customers() {
   } = useInfiniteQuery({
     queryKey: ['customers', debouncedSearch],
     queryFn: async ({ pageParam = 1 }) => {
-      return await customerCommands.getAll(pageParam, pageSize, debouncedSearch || undefined);
+      return await customerCommands.getAll(pageParam, pageSize, debouncedSearch || undefined, false, 'recent', true);
     },
     initialPageParam: 1,
     getNextPageParam: (lastPage, allPages) => {
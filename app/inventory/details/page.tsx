@@ -154,7 +154,7 @@ function InventoryDetailsContent() {
 
     type PurchaseRow = {
         key: string;
-        type: 'initial' | 'po';
+        type: 'initial' | 'po' | 'return';
         date: string;
         quantity: number;
         unit_cost: number;
@@ -167,8 +167,8 @@ function InventoryDetailsContent() {
     };
 
     const displayPurchaseHistory: PurchaseRow[] = purchaseHistory.map((purchase) => ({
-        key: purchase.po_id ? `po-${purchase.id}` : 'initial-stock', // Use unique key
-        type: purchase.po_id ? 'po' : 'initial',
+        key: purchase.is_return ? `return-${purchase.id}` : purchase.po_id ? `po-${purchase.id}` : 'initial-stock', // Use unique key
+        type: purchase.is_return ? 'return' : purchase.po_id ? 'po' : 'initial',
         id: purchase.id,
         date: purchase.created_at,
         quantity: purchase.quantity,
@@ -342,7 +342,7 @@ function InventoryDetailsContent() {
                                             day: 'numeric'
                                         })}
                                     </div>
-                                    <div className={`font-medium text-center ${purchase.type === 'po' ? 'text-blue-600' : 'text-slate-600'}`}>
+                                    <div className={`font-medium text-center ${purchase.type === 'po' ? 'text-blue-600' : purchase.type === 'return' ? 'text-red-600' : 'text-slate-600'}`}>
                                         {purchase.po_number || (purchase.type === 'po' && purchase.po_id
                                             ? `PO-${purchase.po_id.toString().padStart(3, '0')}`
                                             : 'Initial Stock')}
@@ -27,6 +27,7 @@ import { EntityImagePreviewModal } from '@/components/shared/ImagePreviewModal';
 import { EntityImageUpload } from '@/components/shared/EntityImageUpload';
 import { imageCommands } from '@/lib/tauri';
 import { useAuth } from '@/contexts/AuthContext';
+import { withBiometricApproval } from '@/lib/biometric';
 
 type NewProductFormState = {
   name: string;
@@ -224,7 +225,7 @@ export default function Inventory() {
     e.preventDefault();
     if (!editProduct) return;
     try {
-      await productCommands.update({
+      const input = {
         id: editProduct.id,
         name: editProduct.name,
         sku: editProduct.sku,
@@ -233,7 +234,10 @@ export default function Inventory() {
         stock_quantity: editProduct.stock_quantity,
         supplier_id: editProduct.supplier_id,
         category: editProduct.category || null,
-      });
+      };
+      await withBiometricApproval(user?.username, (approvalToken) =>
+        productCommands.update(input, user?.username, approvalToken)
+      );
       setEditProduct(null);
       invalidateProducts();
     } catch (error) {
@@ -309,10 +313,14 @@ export default function Inventory() {
             variant="outline"
             onClick={async () => {
               try {
-                // Fetch all products for the report
-                // Note: This might be heavy if thousands of products.
-                const allProducts = await productCommands.getAll(1, 10000); // Hacky all
-                const url = await generateInventoryReportPDF(allProducts.items);
+                // Fetch all products for the report, one max-size page at a time
+                const allProducts: Awaited<ReturnType<typeof productCommands.getAll>>['items'] = [];
+                for (let page = 1; ; page++) {
+                  const { items, total_count } = await productCommands.getAll(page, 500);
+                  allProducts.push(...items);
+                  if (items.length === 0 || allProducts.length >= total_count) break;
+                }
+                const url = await generateInventoryReportPDF(allProducts);
                 setPdfUrl(url);
                 setPdfFileName(`Inventory_Report_${new Date().toISOString().split('T')[0]}.pdf`);
                 setShowPdfPreview(true);
@@ -78,13 +78,9 @@ export default function LoginPage() {
             try {
                 // Only check if device supports it
                 if (biometricCapability?.isAvailable) {
-                    // Check Backend (is enabled in DB?)
-                    const enabledInDb = await invoke<boolean>('get_biometric_status_by_username', { username });
-
-                    // Check Frontend (do we have a token on THIS device?)
-                    const hasLocalToken = hasLocalBiometricEnrollment(username);
-
-                    setHasBiometricEnrollment(enabledInDb && hasLocalToken);
+                    // Enrollment status isn't available before login; a token on THIS device is
+                    // enough to offer the button, and the backend rejects it if it's no longer valid
+                    setHasBiometricEnrollment(hasLocalBiometricEnrollment(username));
                 }
             } catch (err) {
                 console.error('Failed to check user biometric status:', err);
@@ -247,6 +247,11 @@ export default function Dashboard() {
         <div>
           <h1 className="text-xl font-bold text-slate-900 dark:text-white">Dashboard</h1>
           <p className="text-xs text-slate-500 dark:text-slate-400">Business analytics overview</p>
+          {dashboardStats && dashboardStats.overdue_total > 0 && (
+            <p className="text-xs font-semibold text-red-600 dark:text-red-400">
+              Overdue credit: {formatCurrency(dashboardStats.overdue_total)}
+            </p>
+          )}
         </div>
         <DateRangeFilter value={dateRange} onChange={handleDateRangeChange} />
       </div>
@@ -340,9 +345,10 @@ export default function Dashboard() {
               compact
             />
             <KPICard
-              title="Avg Lifetime Value"
-              value={customerAnalytics?.avg_lifetime_value ?? 0}
+              title="Avg Customer Spend"
+              value={customerAnalytics?.avg_period_value ?? 0}
               format="currency"
+              subtitle={`${customerAnalytics?.churned_customers ?? 0} churned (${(customerAnalytics?.churn_rate ?? 0).toFixed(1)}%)`}
               compact
             />
           </>
@@ -18,6 +18,7 @@ import { Select } from '@/components/ui/select';
 import { Tabs, TabsContent, TabsList, TabsTrigger } from '@/components/ui/tabs';
 
 import { useAuth } from '@/contexts/AuthContext';
+import { withBiometricApproval } from '@/lib/biometric';
 import { InvoicePreview, type InvoiceLayoutSettings } from '@/components/shared/InvoicePreview';
 
 type InvoiceItem = {
@@ -116,7 +117,7 @@ export default function Sales() {
   // Fetch customers for edit dropdown
   const { data: customersData } = useQuery({
     queryKey: ['customers-list'],
-    queryFn: () => customerCommands.getAll(1, 1000), // Fetch all reasonable amount
+    queryFn: () => customerCommands.getAll(1, 500), // Largest page the backend allows
   });
   const customers = customersData?.items ?? [];
 
@@ -294,7 +295,9 @@ export default function Sales() {
   const handleDeleteInvoice = async () => {
     if (!selected?.id) return;
     try {
-      await invoiceCommands.delete(selected.id, user?.username);
+      await withBiometricApproval(user?.username, (approvalToken) =>
+        invoiceCommands.delete(selected.id, user?.username, approvalToken)
+      );
       await queryClient.invalidateQueries({ queryKey: ['sales'] });
       setSelectedId(null);
       setIsDeleteOpen(false);
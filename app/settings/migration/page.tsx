@@ -1,17 +1,28 @@
 'use client';
 
-import { useState } from 'react';
+import { useEffect, useState } from 'react';
 import { Button } from '@/components/ui/button';
 import { Card } from '@/components/ui/card';
+import { useAuth } from '@/contexts/AuthContext';
 import { migrationCommands } from '@/lib/tauri';
-import type { MigrationResult, MigrationStatus, ValidationResult } from '@/lib/tauri';
+import type { MigrationProgress, MigrationResult, MigrationStatus, RepairResult, StockIssue, ValidationResult } from '@/lib/tauri';
 import { AlertCircle, CheckCircle, Info, Loader2 } from 'lucide-react';
 
 export default function MigrationPage() {
   const [status, setStatus] = useState<MigrationStatus | null>(null);
   const [migrationResult, setMigrationResult] = useState<MigrationResult | null>(null);
   const [validationResult, setValidationResult] = useState<ValidationResult | null>(null);
+  const [repairResult, setRepairResult] = useState<RepairResult | null>(null);
+  const [progress, setProgress] = useState<MigrationProgress | null>(null);
   const [loading, setLoading] = useState<boolean>(false);
+  const { user } = useAuth();
+
+  useEffect(() => {
+    const unlisten = migrationCommands.onProgress(setProgress);
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, []);
 
   const checkStatus = async () => {
     setLoading(true);
@@ -26,22 +37,26 @@ export default function MigrationPage() {
     }
   };
 
-  const runMigration = async () => {
+  const runMigration = async (restart = false) => {
     if (!confirm('This will create Purchase Orders and inventory batches for all existing products with stock. Continue?')) {
       return;
     }
 
     setLoading(true);
+    setProgress(null);
     try {
-      const result = await migrationCommands.migrateExistingProducts();
+      const result = await migrationCommands.migrateExistingProducts({ restart });
       setMigrationResult(result);
       await checkStatus(); // Refresh status
       alert(`Migration completed! ${result.products_migrated} products migrated.`);
     } catch (error) {
       console.error('Error running migration:', error);
+      // Chunks committed before the failure stay; the next run continues after them
+      await checkStatus();
       alert(`Migration failed: ${error}`);
     } finally {
       setLoading(false);
+      setProgress(null);
     }
   };
 
@@ -55,9 +70,37 @@ export default function MigrationPage() {
       alert(`Validation failed: ${error}`);
     } finally {
       setLoading(false);
+      setProgress(null);
+    }
+  };
+
+  const repairData = async () => {
+    if (!validationResult) return;
+    const ids = validationResult.inconsistent_products.map((product) => product.id);
+    if (!confirm(`Adjust inventory batches of ${ids.length} product(s) to match their stock quantity?`)) {
+      return;
+    }
+
+    setLoading(true);
+    try {
+      const result = await migrationCommands.repairMigration(ids, user?.username ?? null);
+      setRepairResult(result);
+      setValidationResult(await migrationCommands.validateMigration());
+    } catch (error) {
+      console.error('Error repairing data:', error);
+      alert(`Repair failed: ${error}`);
+    } finally {
+      setLoading(false);
+      setProgress(null);
     }
   };
 
+  const issueLabels: Record<StockIssue, string> = {
+    missing_batches: 'No batches',
+    batch_shortfall: 'Batches short',
+    batch_excess: 'Batches over',
+  };
+
   return (
     <div className="space-y-5">
       <h1 className="page-title">Data Migration</h1>
@@ -115,6 +158,15 @@ export default function MigrationPage() {
               </div>
             )}
 
+            {status.resume_after !== null && (
+              <div className="flex items-center gap-2 p-3 bg-yellow-50 border border-yellow-200 rounded-md">
+                <AlertCircle className="w-5 h-5 text-yellow-600" />
+                <p className="text-sm text-yellow-800">
+                  A previous migration was interrupted. Running it again continues after product #{status.resume_after}.
+                </p>
+              </div>
+            )}
+
             {status.migration_supplier_exists && (
               <p className="text-sm text-gray-600">
                 ℹ️ Migration supplier exists in the system
@@ -132,16 +184,39 @@ export default function MigrationPage() {
             This will create Purchase Orders with the format "PO-MIGRATED-XXXXXX" for each product with stock.
             A "Data Migration" supplier will be created for products without suppliers.
           </p>
-          <Button onClick={runMigration} disabled={loading}>
-            {loading ? (
-              <>
-                <Loader2 className="w-4 h-4 mr-2 animate-spin" />
-                Migrating...
-              </>
-            ) : (
-              `Migrate ${status.products_needing_migration} Product(s)`
+          <div className="flex gap-2">
+            <Button onClick={() => void runMigration()} disabled={loading}>
+              {loading ? (
+                <>
+                  <Loader2 className="w-4 h-4 mr-2 animate-spin" />
+                  Migrating...
+                </>
+              ) : status.resume_after !== null ? (
+                'Continue Migration'
+              ) : (
+                `Migrate ${status.products_needing_migration} Product(s)`
+              )}
+            </Button>
+            {status.resume_after !== null && (
+              <Button onClick={() => void runMigration(true)} disabled={loading} variant="outline">
+                Start Over
+              </Button>
             )}
-          </Button>
+          </div>
+        </Card>
+      )}
+
+      {progress && loading && (
+        <Card className="p-5">
+          <div className="flex justify-between text-sm text-gray-600 mb-2">
+            <span className="capitalize">{progress.phase}...</span>
+            <span>
+              {progress.processed} / {progress.total}
+            </span>
+          </div>
+          <div className="h-2 bg-gray-100 rounded">
+            <div className="h-2 bg-blue-600 rounded" style={{ width: `${progress.percentage}%` }} />
+          </div>
         </Card>
       )}
 
@@ -235,6 +310,7 @@ export default function MigrationPage() {
                       <tr className="border-b border-red-200">
                         <th className="text-left py-2">Product</th>
                         <th className="text-center py-2">SKU</th>
+                        <th className="text-center py-2">Issue</th>
                         <th className="text-center py-2">Stock Qty</th>
                         <th className="text-center py-2">Batch Total</th>
                         <th className="text-center py-2">Difference</th>
@@ -245,6 +321,7 @@ export default function MigrationPage() {
                         <tr key={product.id} className="border-b border-red-100">
                           <td className="py-2">{product.name}</td>
                           <td className="text-center">{product.sku}</td>
+                          <td className="text-center">{issueLabels[product.issue]}</td>
                           <td className="text-center">{product.stock_quantity}</td>
                           <td className="text-center">{product.batch_total}</td>
                           <td className="text-center font-semibold text-red-600">
@@ -256,6 +333,9 @@ export default function MigrationPage() {
                     </tbody>
                   </table>
                 </div>
+                <Button onClick={() => void repairData()} disabled={loading} className="mt-3">
+                  Repair {validationResult.inconsistent_products.length} Product(s)
+                </Button>
               </div>
             ) : (
               <div className="flex items-center gap-2 p-3 bg-green-50 border border-green-200 rounded-md">
@@ -265,6 +345,22 @@ export default function MigrationPage() {
             )}
           </div>
         )}
+
+        {repairResult && (
+          <div className="mt-3 p-3 bg-gray-50 rounded-md text-sm text-gray-700">
+            <p>
+              Repaired {repairResult.repaired.length} product(s)
+              {repairResult.already_consistent.length > 0 && `, ${repairResult.already_consistent.length} already consistent`}.
+            </p>
+            {repairResult.errors.length > 0 && (
+              <ul className="text-red-800 mt-2 space-y-1">
+                {repairResult.errors.map((error, idx) => (
+                  <li key={idx}>• {error}</li>
+                ))}
+              </ul>
+            )}
+          </div>
+        )}
       </Card>
     </div>
   );
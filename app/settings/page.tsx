@@ -53,6 +53,7 @@ import {
   type BiometricCapability,
   hasLocalBiometricEnrollment,
   authenticateWithBiometric,
+  withBiometricApproval,
 } from '@/lib/biometric';
 import { Fingerprint, AlertTriangle, Lock } from 'lucide-react';
 import { LocationSelector } from '@/components/shared/LocationSelector';
@@ -130,6 +131,7 @@ export default function SettingsPage() {
 
   // Google API Settings
   const [googleApiKey, setGoogleApiKey] = useState('');
+  const [googleKeyStored, setGoogleKeyStored] = useState(false);
   const [googleCxId, setGoogleCxId] = useState('');
   const [showApiKey, setShowApiKey] = useState(false);
   const [savingSettings, setSavingSettings] = useState(false);
@@ -319,8 +321,13 @@ export default function SettingsPage() {
 
   async function fetchGoogleSettings() {
     try {
-      const settings = await settingsCommands.getAll();
+      const [settings, searchStatus] = await Promise.all([
+        settingsCommands.getAll(),
+        imageCommands.getImageSearchStatus(),
+      ]);
+      // Keys saved in the keychain are never sent back; older installs may still have one in settings
       setGoogleApiKey(settings['google_api_key'] || '');
+      setGoogleKeyStored(searchStatus.some((p) => p.provider === 'google' && p.key_in_keyring));
       setGoogleCxId(settings['google_cx_id'] || '');
     } catch (err) {
       console.error('Error fetching settings:', err);
@@ -360,13 +367,17 @@ export default function SettingsPage() {
     setSettingsSuccess(false);
     setApiVerified(null);
     try {
-      await settingsCommands.set('google_api_key', googleApiKey);
+      if (googleApiKey) {
+        await imageCommands.setImageSearchKey('google', googleApiKey);
+        setGoogleApiKey('');
+        setGoogleKeyStored(true);
+      }
       await settingsCommands.set('google_cx_id', googleCxId);
       setSettingsSuccess(true);
       setTimeout(() => setSettingsSuccess(false), 3000);
 
       // Auto-verify if credentials changed
-      if (googleApiKey && googleCxId) {
+      if ((googleApiKey || googleKeyStored) && googleCxId) {
         void handleVerifyApiConnection();
       }
     } catch (err) {
@@ -378,7 +389,7 @@ export default function SettingsPage() {
   };
 
   const handleVerifyApiConnection = async () => {
-    if (!googleApiKey || !googleCxId) return;
+    if ((!googleApiKey && !googleKeyStored) || !googleCxId) return;
 
     setVerifyingApi(true);
     setApiVerified(null);
@@ -429,9 +440,29 @@ export default function SettingsPage() {
       if (filePath && typeof filePath === 'string') {
         setLoading(true);
         const jsonContent = await readTextFile(filePath);
-        const count = await settingsCommands.importJson(jsonContent);
+        const preview = await settingsCommands.importJson(jsonContent);
+
+        if (preview.invalid_count > 0) {
+          const problems = preview.entries
+            .filter((e) => e.status === 'invalid_value')
+            .map((e) => `${e.key}: ${e.reason}`)
+            .join('\n');
+          alert(`Settings file has invalid values:\n${problems}`);
+          return;
+        }
 
-        setImportingInfo(`Successfully imported ${count} settings. Please refresh the page to see changes.`);
+        const secrets = preview.entries.filter((e) => e.secret && e.status === 'will_import').map((e) => e.key);
+        const summary = [
+          `${preview.importable_count} settings will be imported.`,
+          preview.skipped_count > 0 ? `${preview.skipped_count} unknown settings will be skipped.` : '',
+          secrets.length > 0 ? `Includes credentials: ${secrets.join(', ')}` : '',
+        ].filter(Boolean).join('\n');
+        const confirmed = await ask(summary, { title: 'Import Settings', kind: 'info' });
+        if (!confirmed) return;
+
+        const report = await settingsCommands.importJson(jsonContent, true);
+
+        setImportingInfo(`Successfully imported ${report.importable_count} settings. Please refresh the page to see changes.`);
 
         // Refresh values if we are on the page
         void fetchGoogleSettings();
@@ -474,8 +505,8 @@ export default function SettingsPage() {
       setLoading(true);
       setError(null);
       const { invoke } = await import('@tauri-apps/api/core');
-      const items = await invoke<DeletedItem[]>('get_deleted_items');
-      setDeletedItems(items);
+      const result = await invoke<{ items: DeletedItem[]; total_count: number }>('get_deleted_items');
+      setDeletedItems(result.items);
     } catch (err) {
       console.error('Error fetching deleted items:', err);
       setError(err instanceof Error ? err.message : String(err));
@@ -489,7 +520,7 @@ export default function SettingsPage() {
       setLoading(true);
       setError(null);
       const data = await settingsCommands.getAllModifications();
-      setModifications(data);
+      setModifications(data.items);
     } catch (err) {
       console.error('Error fetching modifications:', err);
       setError(err instanceof Error ? err.message : String(err));
@@ -555,14 +586,15 @@ export default function SettingsPage() {
   };
 
   const handleClearModificationsHistory = async () => {
-    const confirmed = await ask('Permanently delete ALL modification history?\n\nThis action cannot be undone and will remove all tracked changes.', {
-      title: 'Clear All Modifications',
-      kind: 'warning',
-    });
-    if (!confirmed) return;
-
     try {
-      const count = await settingsCommands.clearModificationsHistory();
+      const total = await settingsCommands.clearModificationsHistory({ dryRun: true });
+      const confirmed = await ask(`Permanently delete ALL ${total} modification records?\n\nThis action cannot be undone and will remove all tracked changes.`, {
+        title: 'Clear All Modifications',
+        kind: 'warning',
+      });
+      if (!confirmed) return;
+
+      const count = await settingsCommands.clearModificationsHistory({ clearedBy: user?.username ?? null });
       await fetchModifications();
       alert(`Cleared ${count} modification records.`);
     } catch (err) {
@@ -669,13 +701,16 @@ export default function SettingsPage() {
 
       switch (item.entity_type) {
         case 'customer':
-          await invoke('restore_customer', { deletedItemId: item.id });
+          await invoke('restore_customer', { deletedItemId: item.id, restoredBy: user?.username ?? null });
           break;
         case 'product':
-          await invoke('restore_product', { deletedItemId: item.id });
+          await invoke('restore_product', { deletedItemId: item.id, restoredBy: user?.username ?? null });
           break;
         case 'supplier':
-          await invoke('restore_supplier', { deletedItemId: item.id });
+          await invoke('restore_supplier', { deletedItemId: item.id, restoredBy: user?.username ?? null });
+          break;
+        case 'invoice':
+          await invoke('restore_invoice', { deletedItemId: item.id, restoredBy: user?.username ?? null });
           break;
         default:
           throw new Error(`Unknown entity type: ${item.entity_type}`);
@@ -683,6 +718,11 @@ export default function SettingsPage() {
 
       void fetchDeletedItems();
     } catch (err) {
+      // Already restored (e.g. a double click) or purged meanwhile: just refresh the list
+      if (String(err).startsWith('ALREADY_RESTORED:')) {
+        void fetchDeletedItems();
+        return;
+      }
       console.error('Error restoring item:', err);
       alert('Error restoring item: ' + (err instanceof Error ? err.message : String(err)));
     }
@@ -703,7 +743,7 @@ export default function SettingsPage() {
       if (!confirmed) return;
 
       const { invoke } = await import('@tauri-apps/api/core');
-      await invoke('permanently_delete_item', { deletedItemId: item.id });
+      await invoke('permanently_delete_item', { deletedItemId: item.id, deletedBy: user?.username ?? null });
       void fetchDeletedItems();
     } catch (err) {
       console.error('Error deleting item:', err);
@@ -726,7 +766,9 @@ export default function SettingsPage() {
       if (!confirmed) return;
 
       const { invoke } = await import('@tauri-apps/api/core');
-      const count = await invoke<number>('clear_trash');
+      const count = await withBiometricApproval(user?.username, (approvalToken) =>
+        invoke<number>('clear_trash', { deletedBy: user?.username ?? null, approvalToken: approvalToken ?? null })
+      );
       alert(`Successfully deleted ${count} items from trash`);
       void fetchDeletedItems();
     } catch (err) {
@@ -1172,7 +1214,7 @@ export default function SettingsPage() {
               <div className="flex items-center gap-2">
                 <button
                   onClick={() => void handleVerifyApiConnection()}
-                  disabled={verifyingApi || !googleApiKey}
+                  disabled={verifyingApi || (!googleApiKey && !googleKeyStored)}
                   className={`btn h-8 text-xs ${apiVerified === true ? 'btn-success bg-green-50 text-green-700 hover:bg-green-100 border-green-200' : 'btn-secondary'}`}
                   title="Verify connection"
                 >
@@ -1211,7 +1253,7 @@ export default function SettingsPage() {
                     className="flex h-8 w-full rounded-md border border-input bg-background px-2 py-1 text-xs shadow-sm focus-visible:ring-1 focus-visible:ring-primary pr-8"
                     value={googleApiKey}
                     onChange={(e) => setGoogleApiKey(e.target.value)}
-                    placeholder="Enter API Key"
+                    placeholder={googleKeyStored ? 'Saved in system keychain' : 'Enter API Key'}
                   />
                   <button
                     type="button"
@@ -2,7 +2,7 @@
 
 import { useEffect, useState, Suspense } from 'react';
 import { useSearchParams, useRouter } from 'next/navigation';
-import { supplierCommands, productCommands, type Supplier, type Product, type SupplierPaymentSummary } from '@/lib/tauri';
+import { supplierCommands, productCommands, type Supplier, type Product, type SupplierPaymentSummary, type SupplierReport } from '@/lib/tauri';
 import { generateSupplierDetailPDF } from '@/lib/pdf-generator';
 import { PDFPreviewDialog } from '@/components/shared/PDFPreviewDialog';
 import { Card } from '@/components/ui/card';
@@ -18,6 +18,7 @@ function SupplierDetailsContent() {
     const id = Number(searchParams.get('id'));
 
     const [supplier, setSupplier] = useState<Supplier | null>(null);
+    const [report, setReport] = useState<SupplierReport | null>(null);
     const [products, setProducts] = useState<Product[]>([]);
     const [paymentSummaries, setPaymentSummaries] = useState<Record<number, SupplierPaymentSummary | null>>({});
     const [selectedProduct, setSelectedProduct] = useState<Product | null>(null);
@@ -37,11 +38,12 @@ function SupplierDetailsContent() {
     const loadData = async () => {
         try {
             setLoading(true);
-            const [supplierData, productsData] = await Promise.all([
-                supplierCommands.getById(id),
+            const [reportData, productsData] = await Promise.all([
+                supplierCommands.getReport(id),
                 productCommands.getBySupplier(id),
             ]);
-            setSupplier(supplierData);
+            setSupplier(reportData.supplier);
+            setReport(reportData);
             setProducts(productsData);
 
             if (productsData.length > 0) {
@@ -87,15 +89,9 @@ function SupplierDetailsContent() {
         );
     }
 
-    const totalStock = products.reduce((acc, p) => acc + (p.total_purchased_quantity ?? 0), 0);
-    const totalValue = products.reduce(
-        (acc, p) => acc + p.price * (p.total_purchased_quantity ?? 0),
-        0,
-    );
-    const totalPending = Object.values(paymentSummaries).reduce((sum, summary) => {
-        if (!summary) return sum;
-        return sum + Math.max(0, summary.pending_amount);
-    }, 0);
+    const totalStock = report?.products.reduce((acc, p) => acc + p.purchased_quantity, 0) ?? 0;
+    const totalValue = report?.stats.total_purchased ?? 0;
+    const totalPending = report?.stats.pending_amount ?? 0;
 
     return (
         <div className="-mt-8 pt-2.5 px-6 pb-6 space-y-6 max-w-7xl mx-auto">
@@ -204,6 +200,44 @@ function SupplierDetailsContent() {
                 </Card>
             </div>
 
+            {/* Purchase Orders */}
+            {report && report.purchase_orders.length > 0 && (
+                <div className="space-y-4">
+                    <div className="flex items-baseline justify-between">
+                        <h2 className="text-xl font-semibold text-slate-900">Purchase Orders</h2>
+                        {report.stats.average_days_between_orders !== null && (
+                            <span className="text-sm text-slate-500">
+                                Orders every {report.stats.average_days_between_orders} days on average
+                            </span>
+                        )}
+                    </div>
+                    <div className="bg-white rounded-xl border border-slate-200 shadow-sm overflow-hidden">
+                        <div className="grid grid-cols-6 gap-4 p-4 bg-slate-50 border-b border-slate-200 text-xs font-bold text-black uppercase tracking-wider text-center">
+                            <div>PO Number</div>
+                            <div>Date</div>
+                            <div>Status</div>
+                            <div>Total</div>
+                            <div>Paid</div>
+                            <div>Pending</div>
+                        </div>
+                        <div className="divide-y divide-slate-100">
+                            {report.purchase_orders.map((po) => (
+                                <div key={po.id} className="grid grid-cols-6 gap-4 p-4 text-sm text-center">
+                                    <div className="font-medium text-slate-900">{po.po_number}</div>
+                                    <div className="text-slate-500">{new Date(po.order_date).toLocaleDateString()}</div>
+                                    <div className="text-slate-500 capitalize">{po.status.replace('_', ' ')}</div>
+                                    <div>₹{po.total_amount.toFixed(0)}</div>
+                                    <div className="text-emerald-600">₹{po.total_paid.toFixed(0)}</div>
+                                    <div className={po.total_pending > 0 ? 'text-red-600 font-semibold' : 'text-slate-400'}>
+                                        ₹{po.total_pending.toFixed(0)}
+                                    </div>
+                                </div>
+                            ))}
+                        </div>
+                    </div>
+                </div>
+            )}
+
             {/* Products Section */}
             <div className="space-y-4">
                 <h2 className="text-xl font-semibold text-slate-900">Supplied Products</h2>
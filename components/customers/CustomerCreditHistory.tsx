@@ -14,6 +14,8 @@ import {
 import { ask } from '@tauri-apps/plugin-dialog';
 import { CreditCard, ChevronDown, ChevronUp, Plus, Trash2, Receipt, CheckCircle2, Clock } from 'lucide-react';
 
+const HISTORY_PAGE_SIZE = 50;
+
 interface CustomerCreditHistoryProps {
     customerId: number;
     onPaymentUpdate?: () => void;
@@ -24,6 +26,9 @@ export function CustomerCreditHistory({
     onPaymentUpdate
 }: CustomerCreditHistoryProps) {
     const [creditHistory, setCreditHistory] = useState<CustomerInvoiceCreditSummary[]>([]);
+    const [historyTotal, setHistoryTotal] = useState(0);
+    const [historyPage, setHistoryPage] = useState(1);
+    const [loadingMore, setLoadingMore] = useState(false);
     const [summary, setSummary] = useState<CustomerCreditSummary | null>(null);
     const [loading, setLoading] = useState(false);
 
@@ -44,14 +49,31 @@ export function CustomerCreditHistory({
         loadData();
     }, [customerId]);
 
+    const loadMoreHistory = async () => {
+        try {
+            setLoadingMore(true);
+            const nextPage = historyPage + 1;
+            const historyData = await customerPaymentCommands.getCreditHistory(customerId, nextPage, HISTORY_PAGE_SIZE);
+            setCreditHistory((prev) => [...prev, ...historyData.items]);
+            setHistoryTotal(historyData.total_count);
+            setHistoryPage(nextPage);
+        } catch (error) {
+            console.error('Failed to load more credit history:', error);
+        } finally {
+            setLoadingMore(false);
+        }
+    };
+
     const loadData = async () => {
         try {
             setLoading(true);
             const [historyData, summaryData] = await Promise.all([
-                customerPaymentCommands.getCreditHistory(customerId),
+                customerPaymentCommands.getCreditHistory(customerId, 1, HISTORY_PAGE_SIZE),
                 customerPaymentCommands.getCreditSummary(customerId),
             ]);
-            setCreditHistory(historyData);
+            setCreditHistory(historyData.items);
+            setHistoryTotal(historyData.total_count);
+            setHistoryPage(1);
             setSummary(summaryData);
         } catch (error) {
             console.error('Failed to load credit history:', error);
@@ -350,6 +372,13 @@ export function CustomerCreditHistory({
                             No credit invoices found for this customer.
                         </div>
                     )}
+                    {creditHistory.length < historyTotal && (
+                        <div className="p-4 text-center">
+                            <Button variant="outline" size="sm" onClick={loadMoreHistory} disabled={loadingMore}>
+                                {loadingMore ? 'Loading...' : `Load more (${historyTotal - creditHistory.length} remaining)`}
+                            </Button>
+                        </div>
+                    )}
                 </div>
             </div>
         </div>
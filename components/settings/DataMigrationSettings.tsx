@@ -226,17 +226,19 @@ export function DataMigrationSettings() {
                 const result = await invoke<{
                     processed: number;
                     success: number;
+                    updated: number;
+                    skipped: number;
                     errors: string[];
                     duplicate_found: boolean;
                     added_items: InsertedItem[];
                 }>(
                     'import_csv_chunk',
-                    { entityType: importing, data: batch }
+                    { entityType: importing, data: batch, rowOffset: i, totalRows: total }
                 );
 
                 processed += result.processed;
                 newCount += result.success;
-                skippedCount += (result.processed - result.success - result.errors.length);
+                skippedCount += result.skipped;
                 if (result.errors.length > 0) {
                     errorsCount += result.errors.length;
                     details.push(...result.errors);
@@ -18,7 +18,7 @@ import {
   DialogFooter,
 } from '@/components/ui/dialog';
 import { EntityThumbnail, EntityType } from './EntityThumbnail';
-import { imageCommands, settingsCommands, GoogleImageResult } from '@/lib/tauri';
+import { imageCommands, GoogleImageResult } from '@/lib/tauri';
 import { cn } from '@/lib/utils';
 
 interface EntityImageUploadProps {
@@ -61,8 +61,8 @@ export function EntityImageUpload({
 
   // Check if Google API is configured
   useEffect(() => {
-    settingsCommands.get('google_api_key').then((key) => {
-      setHasGoogleApi(!!key);
+    imageCommands.getImageSearchStatus().then((providers) => {
+      setHasGoogleApi(providers.some((p) => p.enabled && p.configured));
     });
   }, []);
 
@@ -5,7 +5,7 @@ import { useEffect, useRef, useState } from 'react';
 import { createPortal } from 'react-dom';
 import { Check, ChevronDown, Loader2, Search, SquareArrowOutUpRight } from 'lucide-react';
 
-type CategoryId = 'customers' | 'items' | 'suppliers' | 'invoices' | 'sales' | 'inventory';
+type CategoryId = 'customers' | 'items' | 'suppliers' | 'invoices' | 'challans' | 'sales' | 'inventory';
 
 type Category = {
   id: CategoryId;
@@ -27,6 +27,7 @@ import {
   productCommands,
   supplierCommands,
   invoiceCommands,
+  challanCommands,
   searchCommands,
 } from '@/lib/tauri';
 
@@ -54,7 +55,7 @@ const categories: Category[] = [
       const data = await productCommands.getAll(1, 10, q);
       return data.items.map((p) => ({
         id: p.id,
-        title: p.name,
+        title: p.display_name ?? p.name,
         subtitle: `SKU: ${p.sku} • Stock: ${p.stock_quantity}`,
         meta: `₹${p.price.toFixed(0)}`,
         href: `/inventory/details?id=${p.id}`,
@@ -91,6 +92,21 @@ const categories: Category[] = [
       }));
     },
   },
+  {
+    id: 'challans',
+    label: 'Challans',
+    placeholder: 'Search in Delivery Challans ( / )',
+    fetcher: async (q) => {
+      const res = await searchCommands.omnisearch(q);
+      return res.challans.map((dc) => ({
+        id: dc.id,
+        title: dc.challan_number,
+        subtitle: dc.customer_name ?? 'Delivery challan',
+        meta: dc.status.charAt(0).toUpperCase() + dc.status.slice(1),
+        href: '/billing',
+      }));
+    },
+  },
   {
     id: 'sales',
     label: 'Sales',
@@ -114,7 +130,7 @@ const categories: Category[] = [
       const data = await productCommands.getAll(1, 10, q);
       return data.items.map((p) => ({
         id: p.id,
-        title: p.name,
+        title: p.display_name ?? p.name,
         subtitle: `SKU: ${p.sku} • Stock: ${p.stock_quantity}`,
         meta: `₹${p.price.toFixed(0)}`,
         href: `/inventory/details?id=${p.id}`,
@@ -223,6 +239,41 @@ export function OmniSearch() {
         return;
       }
 
+      if (activeCategory.id === 'challans') {
+        const challan = await challanCommands.getById(Number(item.id));
+
+        setDetail({
+          title: item.title,
+          content: (
+            <div className="space-y-2 text-sm">
+              <p className="font-semibold">{challan.customer_name ?? 'No customer'}</p>
+              <p className="text-muted-foreground">
+                {challan.status === 'invoiced' && challan.invoice_number
+                  ? `Invoiced as ${challan.invoice_number}`
+                  : challan.status === 'open'
+                    ? 'Open'
+                    : 'Returned'}{' '}
+                • Value ₹{challan.total_value.toFixed(2)}
+              </p>
+              <div className="mt-2">
+                <p className="text-xs text-muted-foreground">Items</p>
+                <div className="divide-y border rounded-lg max-h-48 overflow-auto">
+                  {challan.items.map((it) => (
+                    <div key={it.id} className="px-3 py-2 flex justify-between">
+                      <span>{it.product_name}</span>
+                      <span className="text-xs text-muted-foreground">
+                        {it.quantity} × ₹{it.unit_price.toFixed(0)}
+                      </span>
+                    </div>
+                  ))}
+                </div>
+              </div>
+            </div>
+          ),
+        });
+        return;
+      }
+
       if (activeCategory.id === 'inventory' || activeCategory.id === 'items') {
         setDetail({
           title: item.title,
@@ -20,7 +20,7 @@ import {
   DialogFooter,
 } from '@/components/ui/dialog';
 import { ProductThumbnail } from './ProductThumbnail';
-import { imageCommands, settingsCommands, GoogleImageResult } from '@/lib/tauri';
+import { imageCommands, GoogleImageResult } from '@/lib/tauri';
 import { cn } from '@/lib/utils';
 
 interface ProductImageUploadProps {
@@ -61,8 +61,8 @@ export function ProductImageUpload({
 
   // Check if Google API is configured
   useEffect(() => {
-    settingsCommands.get('google_api_key').then((key) => {
-      setHasGoogleApi(!!key);
+    imageCommands.getImageSearchStatus().then((providers) => {
+      setHasGoogleApi(providers.some((p) => p.enabled && p.configured));
     });
   }, []);
 
@@ -62,6 +62,7 @@ export function AuthProvider({ children }: { children: React.ReactNode }) {
         } catch (e) {
             console.error('Failed to clear session:', e);
         }
+        invoke('logout').catch((e) => console.error('Failed to end backend session:', e));
         setUser(null);
         router.push('/login');
     };
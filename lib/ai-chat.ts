@@ -34,6 +34,8 @@ export interface DownloadProgress {
 export interface SidecarDownloadProgress {
     downloaded_bytes: number;
     total_bytes: number;
+    processed: number;
+    total: number;
     percentage: number;
     speed_mbps: number;
 }
@@ -53,6 +55,34 @@ export interface ChatMessage {
     error?: string;
 }
 
+export interface AiContextSnapshot {
+    as_of: string;
+    month_start: string;
+    month_revenue: number;
+    month_orders: number;
+    top_products_this_month: {
+        product_id: number;
+        product_name: string;
+        sku: string;
+        revenue: number;
+        quantity_sold: number;
+        order_count: number;
+    }[];
+    low_stock: { id: number; name: string; sku: string; stock_quantity: number }[];
+    pending_credit_total: number;
+    overdue_credit_total: number;
+    customers_with_credit: number;
+}
+
+export interface AiQueryResult {
+    columns: string[];
+    rows: unknown[][];
+    truncated: boolean;
+}
+
+/** Error prefix returned by the AI data commands until access is unlocked */
+export const AI_DATA_LOCKED = 'AI_DATA_LOCKED:';
+
 export const aiChatApi = {
     /**
      * Start the AI sidecar server
@@ -76,19 +106,52 @@ export const aiChatApi = {
     },
 
     /**
-     * Check if the sidecar binary is downloaded
+     * Check if the sidecar binary is downloaded and passes its checksum
      */
     checkSidecarDownloaded: async (): Promise<boolean> => {
         return await invoke('check_sidecar_downloaded');
     },
 
     /**
-     * Download the AI sidecar binary (emits 'sidecar-download-progress' events)
+     * Download the AI sidecar binary (emits 'sidecar-download-progress' events).
+     * Interrupted downloads resume where they stopped; the binary is checksum-verified before use.
      */
     downloadSidecar: async (): Promise<void> => {
         await invoke('download_ai_sidecar');
     },
 
+    /**
+     * Unlock AI data access (same password/biometric check as settings) for 15 minutes
+     */
+    unlockData: async (username: string, credentials: { password?: string; biometricToken?: string }): Promise<void> => {
+        await invoke('unlock_ai_data', {
+            username,
+            password: credentials.password ?? null,
+            biometricToken: credentials.biometricToken ?? null,
+        });
+    },
+
+    /**
+     * Lock AI data access again
+     */
+    lockData: async (): Promise<void> => {
+        await invoke('lock_ai_data');
+    },
+
+    /**
+     * Get this month's sales, low stock and pending credit as chat context
+     */
+    getContextSnapshot: async (): Promise<AiContextSnapshot> => {
+        return await invoke('get_ai_context_snapshot');
+    },
+
+    /**
+     * Run a SELECT-only query on a read-only connection (max 500 rows)
+     */
+    runReadonlySql: async (sql: string, limit?: number): Promise<AiQueryResult> => {
+        return await invoke('run_ai_sql_readonly', { sql, limit: limit ?? null });
+    },
+
     /**
      * Get the current setup status
      */
@@ -91,7 +91,7 @@ export async function enrollBiometric(userId: number, username: string): Promise
         // Store token locally - security comes from:
         // 1. Biometric auth required before we return the token for verification
         // 2. Token is hashed in database, so raw token alone isn't useful without the app
-        // 3. Token is per-device, doesn't transfer
+        // 3. Token is bound to this device's id on the backend and expires
         localStorage.setItem(getStorageKey(username), JSON.stringify({ token, userId }));
 
         return true;
@@ -155,6 +155,67 @@ export async function authenticateWithBiometric(username: string): Promise<User
     }
 }
 
+/** Error prefix protected commands use when they need a biometric approval token */
+export const BIOMETRIC_APPROVAL_REQUIRED = 'BIOMETRIC_APPROVAL_REQUIRED:';
+
+interface ActionApproval {
+    token: string;
+    action: string;
+    approved_by: string;
+    expires_at: string;
+}
+
+/**
+ * Prompt for biometric authentication and exchange the user's stored token for a
+ * single-use approval of a protected action (valid for about 60 seconds).
+ * @returns The approval token to pass to the protected command
+ */
+export async function approveActionWithBiometric(username: string, action: string): Promise<string> {
+    const stored = localStorage.getItem(getStorageKey(username));
+    if (!stored) {
+        throw new Error('biometryNotEnrolled');
+    }
+    const { token } = JSON.parse(stored);
+
+    await authenticate(`Approve ${action.replace(/_/g, ' ')}`, {
+        allowDeviceCredential: false,
+        cancelTitle: 'Cancel',
+    });
+
+    const approval = await invoke<ActionApproval>('verify_action_biometric', { action, token });
+    return approval.token;
+}
+
+/**
+ * Run a protected command; if the backend asks for a biometric approval, prompt for one
+ * and run the command again with the approval token.
+ */
+export async function withBiometricApproval<T>(
+    username: string | undefined,
+    run: (approvalToken?: string) => Promise<T>,
+): Promise<T> {
+    try {
+        return await run();
+    } catch (error) {
+        const message = String(error);
+        if (!username || !message.startsWith(BIOMETRIC_APPROVAL_REQUIRED)) throw error;
+
+        const approvalToken = await approveActionWithBiometric(username, message.slice(BIOMETRIC_APPROVAL_REQUIRED.length));
+        return await run(approvalToken);
+    }
+}
+
+/**
+ * Replace the list of actions that need a biometric approval. Removing an entry (or raising a
+ * price_change threshold) prompts for a manager's approval.
+ * @returns The saved entries
+ */
+export async function setProtectedActions(username: string | undefined, actions: string[]): Promise<string[]> {
+    return withBiometricApproval(username, (approvalToken) =>
+        invoke<string[]>('set_protected_actions', { actions, approvalToken: approvalToken ?? null }),
+    );
+}
+
 /**
  * Disable biometric for a user
  * @param userId The user ID to disable biometric for
@@ -210,6 +271,12 @@ export function getBiometricErrorMessage(error: unknown): string {
     if (errorStr.includes('passcodeNotSet')) {
         return 'Device passcode required for fingerprint login';
     }
+    if (errorStr.includes('has expired')) {
+        return 'Fingerprint login has expired. Please log in with your password and enable it again in Settings.';
+    }
+    if (errorStr.includes('different device')) {
+        return 'Fingerprint login was set up on a different device. Please use your password.';
+    }
     if (errorStr.includes('itemNotFound')) {
         return 'Biometric data not found. Please re-enable fingerprint login in Settings.';
     }
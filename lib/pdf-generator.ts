@@ -1,7 +1,7 @@
 import jsPDF from 'jspdf';
 import autoTable, { type UserOptions, type CellHookData } from 'jspdf-autotable';
-import type { Invoice, InvoiceItem, Customer, Product, Supplier, PurchaseOrderComplete, CustomerInvoice, SupplierPaymentSummary } from './tauri';
-import { settingsCommands, imageCommands } from './tauri'; // Import commands to fetch settings/images
+import type { Invoice, InvoiceItem, Customer, Product, Supplier, PurchaseOrderComplete, CustomerInvoice, SupplierPaymentSummary, DeliveryChallan } from './tauri';
+import { settingsCommands, imageCommands, purchaseOrderCommands } from './tauri'; // Import commands to fetch settings/images
 import { readFile } from '@tauri-apps/plugin-fs';
 
 // Define the autoTable type extension
@@ -478,13 +478,27 @@ export const generateInvoicePDF = async (invoice: Invoice, items: InvoiceItem[],
     return { url, size, duration };
 };
 
-export const generatePurchaseOrderPDF = async (poComplete: PurchaseOrderComplete): Promise<string> => {
-    console.log("Generating Purchase Order PDF...");
+const addDraftWatermark = (doc: jsPDF) => {
+    const pageWidth = doc.internal.pageSize.width;
+    const pageHeight = doc.internal.pageSize.height;
+
+    for (let i = 1; i <= doc.getNumberOfPages(); i++) {
+        doc.setPage(i);
+        doc.setFont('helvetica', 'bold');
+        doc.setFontSize(90);
+        doc.setTextColor(230, 230, 230);
+        doc.text('DRAFT', pageWidth / 2, pageHeight / 2, { align: 'center', baseline: 'middle', angle: 45 });
+    }
+    doc.setTextColor(0, 0, 0);
+};
+
+// Shared by the preview and the saved copy sent to suppliers
+const buildPurchaseOrderDoc = async (poComplete: PurchaseOrderComplete): Promise<jsPDF> => {
     const doc = new jsPDF();
     const po = poComplete.purchase_order;
     const supplier = poComplete.supplier;
 
-    await addHeader(doc, 'PURCHASE ORDER');
+    const startY = await addHeader(doc, 'PURCHASE ORDER');
 
     // PO Details
     doc.setFontSize(10);
@@ -492,28 +506,38 @@ export const generatePurchaseOrderPDF = async (poComplete: PurchaseOrderComplete
     doc.setTextColor(0, 0, 0);
 
     const pageWidth = doc.internal.pageSize.width;
+    const pageHeight = doc.internal.pageSize.height;
     const rightColX = pageWidth - 14;
 
-    doc.text(`PO #: ${po.po_number}`, rightColX, 50, { align: 'right' });
-    doc.text(`Date: ${formatDate(po.order_date)}`, rightColX, 55, { align: 'right' });
-    doc.text(`Status: ${po.status.toUpperCase()}`, rightColX, 60, { align: 'right' });
+    doc.text(`PO #: ${po.po_number}`, rightColX, startY, { align: 'right' });
+    doc.text(`Date: ${formatDate(po.order_date)}`, rightColX, startY + 5, { align: 'right' });
+    doc.text(`Status: ${po.status.toUpperCase()}`, rightColX, startY + 10, { align: 'right' });
+    if (po.expected_delivery_date) {
+        doc.text(`Expected: ${formatDate(po.expected_delivery_date)}`, rightColX, startY + 15, { align: 'right' });
+    }
 
     // Supplier Details
     doc.setFontSize(11);
     doc.setFont('helvetica', 'bold');
-    doc.text('Vendor:', 14, 50);
+    doc.text('Vendor:', 14, startY);
     doc.setFont('helvetica', 'normal');
     doc.setFontSize(10);
 
-    doc.text(supplier.name, 14, 56);
-    if (supplier.contact_info) doc.text(supplier.contact_info, 14, 61);
-    if (supplier.email) doc.text(supplier.email, 14, 66);
+    let vendorY = startY + 6;
+    doc.text(supplier.name, 14, vendorY);
+    for (const line of [supplier.contact_info, supplier.email]) {
+        if (line) {
+            vendorY += 5;
+            doc.text(line, 14, vendorY);
+        }
+    }
     if (supplier.address) {
         const splitAddress = doc.splitTextToSize(supplier.address, 80);
-        doc.text(splitAddress, 14, 71);
+        doc.text(splitAddress, 14, vendorY + 5);
+        vendorY += 5 * splitAddress.length;
     }
 
-    // Items Table
+    // Items Table - the header repeats on every page for long orders
     const tableColumn = ["Item", "SKU", "Qty", "Unit Cost", "Total"];
     const tableRows = poComplete.items.map(item => [
         item.product_name,
@@ -522,22 +546,34 @@ export const generatePurchaseOrderPDF = async (poComplete: PurchaseOrderComplete
         formatCurrency(item.unit_cost),
         formatCurrency(item.total_cost)
     ]);
+    const totalQuantity = poComplete.items.reduce((sum, item) => sum + item.quantity, 0);
 
     autoTable(doc, {
-        startY: 85,
+        startY: Math.max(vendorY, startY + 15) + 10,
         head: [tableColumn],
         body: tableRows,
+        foot: [["", "Total", totalQuantity, "", formatCurrency(po.total_amount)]],
+        showHead: 'everyPage',
+        showFoot: 'lastPage',
         theme: 'grid',
         headStyles: { fillColor: [66, 66, 66] },
+        footStyles: { fillColor: [240, 240, 240], textColor: [0, 0, 0] },
         styles: { fontSize: 9, font: 'helvetica' },
+        margin: { bottom: 20 },
         columnStyles: {
+            2: { halign: 'right' },
             3: { halign: 'right' },
             4: { halign: 'right' }
         }
     });
 
     // Totals
-    const finalY = doc.lastAutoTable.finalY + 10;
+    let finalY = doc.lastAutoTable.finalY + 10;
+    const notes = po.notes ? doc.splitTextToSize(po.notes, pageWidth - 28) : [];
+    if (finalY + 10 + notes.length * 5 > pageHeight - 20) {
+        doc.addPage();
+        finalY = 20;
+    }
     const summaryX = pageWidth - 70;
     const valueX = pageWidth - 14;
 
@@ -546,6 +582,131 @@ export const generatePurchaseOrderPDF = async (poComplete: PurchaseOrderComplete
     doc.text(`Total Amount:`, summaryX, finalY);
     doc.text(formatCurrency(po.total_amount), valueX, finalY, { align: 'right' });
 
+    if (notes.length > 0) {
+        doc.setFontSize(10);
+        doc.text('Notes:', 14, finalY + 10);
+        doc.setFont('helvetica', 'normal');
+        doc.text(notes, 14, finalY + 15);
+    }
+
+    if (po.status === 'draft') addDraftWatermark(doc);
+    addFooter(doc);
+    return doc;
+};
+
+export const generatePurchaseOrderPDF = async (poComplete: PurchaseOrderComplete): Promise<string> => {
+    console.log("Generating Purchase Order PDF...");
+    const doc = await buildPurchaseOrderDoc(poComplete);
+    return createBlobUrl(doc);
+};
+
+/**
+ * Generate the PO PDF and save it next to the database for sending to the supplier.
+ * Returns the saved file path.
+ */
+export const savePurchaseOrderPDF = async (poId: number): Promise<string> => {
+    const poComplete = await purchaseOrderCommands.getById(poId);
+    const doc = await buildPurchaseOrderDoc(poComplete);
+    const bytes = new Uint8Array(doc.output('arraybuffer'));
+    return await purchaseOrderCommands.savePdf(poId, Array.from(bytes));
+};
+
+/**
+ * Delivery challan to travel with the goods; it shows values for reference but is not a bill,
+ * and leaves space for the receiver to sign.
+ */
+export const generateChallanPDF = async (challan: DeliveryChallan): Promise<string> => {
+    console.log("Generating Delivery Challan PDF...");
+    const doc = new jsPDF();
+
+    const startY = await addHeader(doc, 'DELIVERY CHALLAN');
+
+    doc.setFontSize(10);
+    doc.setFont('helvetica', 'normal');
+    doc.setTextColor(0, 0, 0);
+
+    const pageWidth = doc.internal.pageSize.width;
+    const pageHeight = doc.internal.pageSize.height;
+    const rightColX = pageWidth - 14;
+
+    doc.text(`Challan #: ${challan.challan_number}`, rightColX, startY, { align: 'right' });
+    doc.text(`Date: ${formatDate(challan.created_at)}`, rightColX, startY + 5, { align: 'right' });
+    if (challan.invoice_number) {
+        doc.text(`Invoice #: ${challan.invoice_number}`, rightColX, startY + 10, { align: 'right' });
+    }
+
+    // Consignee
+    doc.setFontSize(11);
+    doc.setFont('helvetica', 'bold');
+    doc.text('Deliver To:', 14, startY);
+    doc.setFont('helvetica', 'normal');
+    doc.setFontSize(10);
+
+    let consigneeY = startY + 6;
+    doc.text(challan.customer_name ?? '-', 14, consigneeY);
+    if (challan.customer_phone) {
+        consigneeY += 5;
+        doc.text(challan.customer_phone, 14, consigneeY);
+    }
+
+    const tableColumn = ["Item", "SKU", "Qty", "Rate", "Value"];
+    const tableRows = challan.items.map(item => [
+        item.product_name,
+        item.sku ?? '-',
+        item.quantity,
+        formatCurrency(item.unit_price),
+        formatCurrency(item.quantity * item.unit_price)
+    ]);
+    const totalQuantity = challan.items.reduce((sum, item) => sum + item.quantity, 0);
+
+    autoTable(doc, {
+        startY: Math.max(consigneeY, startY + 10) + 10,
+        head: [tableColumn],
+        body: tableRows,
+        foot: [["", "Total", totalQuantity, "", formatCurrency(challan.total_value)]],
+        showHead: 'everyPage',
+        showFoot: 'lastPage',
+        theme: 'grid',
+        headStyles: { fillColor: [66, 66, 66] },
+        footStyles: { fillColor: [240, 240, 240], textColor: [0, 0, 0] },
+        styles: { fontSize: 9, font: 'helvetica' },
+        margin: { bottom: 20 },
+        columnStyles: {
+            2: { halign: 'right' },
+            3: { halign: 'right' },
+            4: { halign: 'right' }
+        }
+    });
+
+    let finalY = doc.lastAutoTable.finalY + 10;
+    const notes = challan.notes ? doc.splitTextToSize(challan.notes, pageWidth - 28) : [];
+    if (finalY + 35 + notes.length * 5 > pageHeight - 20) {
+        doc.addPage();
+        finalY = 20;
+    }
+
+    doc.setFontSize(9);
+    doc.setFont('helvetica', 'italic');
+    doc.text('Goods sent on approval / for delivery. This is not a tax invoice.', 14, finalY);
+
+    if (notes.length > 0) {
+        doc.setFontSize(10);
+        doc.setFont('helvetica', 'bold');
+        doc.text('Notes:', 14, finalY + 8);
+        doc.setFont('helvetica', 'normal');
+        doc.text(notes, 14, finalY + 13);
+        finalY += 8 + notes.length * 5;
+    }
+
+    // Receiver's signature
+    const signatureY = finalY + 25;
+    doc.setFont('helvetica', 'normal');
+    doc.setFontSize(10);
+    doc.line(14, signatureY, 84, signatureY);
+    doc.text("Receiver's Signature", 14, signatureY + 5);
+    doc.line(pageWidth - 84, signatureY, rightColX, signatureY);
+    doc.text('Authorised Signatory', rightColX, signatureY + 5, { align: 'right' });
+
     addFooter(doc);
     return createBlobUrl(doc);
 };
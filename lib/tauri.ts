@@ -4,12 +4,96 @@
  */
 
 import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 
 export interface PaginatedResult<T> {
   items: T[];
   total_count: number;
 }
 
+export interface BillingProduct {
+  id: number;
+  name: string;
+  sku: string;
+  selling_price: number | null;
+  stock_quantity: number;
+  /** Stock less active reservations */
+  available_quantity: number;
+  /** Units on open purchase orders still to arrive */
+  on_order_quantity: number;
+}
+
+export interface SoldPrice {
+  unit_price: number;
+  quantity: number;
+  discount_amount: number;
+  invoice_id: number;
+  invoice_number: string;
+  sold_at: string;
+}
+
+export interface LastSoldPrice {
+  product_id: number;
+  last_sale: SoldPrice | null;
+  last_sale_to_customer: SoldPrice | null;
+}
+
+export interface PriceHistoryEntry {
+  unit_price: number;
+  invoice_count: number;
+  quantity_sold: number;
+  first_sold_at: string;
+  last_sold_at: string;
+}
+
+export interface PriceChange {
+  id: number;
+  field: 'price' | 'selling_price';
+  old_value: number | null;
+  new_value: number | null;
+  changed_at: string;
+  changed_by: string | null;
+  /** Set when the change came from a scheduled price change */
+  scheduled_change_id: number | null;
+}
+
+export interface ScheduledPriceChange {
+  id: number;
+  product_id: number;
+  price: number | null;
+  selling_price: number | null;
+  /** UTC "YYYY-MM-DD HH:MM:SS" */
+  effective_at: string;
+  scheduled_by: string | null;
+  created_at: string;
+  applied_at: string | null;
+}
+
+export interface SchedulePriceInput {
+  product_id: number;
+  price?: number | null;
+  selling_price?: number | null;
+  /** "YYYY-MM-DD" in the business timezone; applies immediately when omitted */
+  effective_from?: string | null;
+  scheduled_by?: string | null;
+}
+
+export interface PriceTimeline {
+  product_id: number;
+  price: number;
+  selling_price: number | null;
+  /** Oldest first */
+  changes: PriceChange[];
+  pending: ScheduledPriceChange[];
+}
+
+export interface BillingSnapshot {
+  as_of: string;
+  products: BillingProduct[];
+  removed_product_ids: number[];
+  frequent_products: BillingProduct[];
+}
+
 export interface Product {
   id: number;
   name: string;
@@ -23,11 +107,49 @@ export interface Product {
   updated_at: string;
   image_path: string | null;
   category: string | null;
+  category_id?: number;
   total_sold?: number;
   initial_stock_sold?: number;
   total_purchased_cost?: number;
   total_purchased_quantity?: number;
   total_sold_amount?: number;
+  parent_product_id?: number;
+  variant_attributes?: string; // JSON object, e.g. {"size":"L","color":"Blue"}
+  display_name?: string;       // "Parent — L / Blue" for variants
+  variant_count?: number;      // Set when variants are rolled up into this product
+  available_quantity?: number; // Stock not held by reservations (get_product only)
+  on_order_quantity?: number;  // Units on open POs still to arrive (get_product only)
+  unit: string;                // "pcs", "kg", "g", "m" or "L"
+  allow_fractional: boolean;   // Stock and sales may be part of a unit, e.g. 3.5 m
+}
+
+export interface StockReservation {
+  id: number;
+  product_id: number;
+  product_name: string;
+  quantity: number;
+  reference: string;
+  /** UTC "YYYY-MM-DD HH:MM:SS" */
+  expires_at: string;
+  created_by: string | null;
+  created_at: string;
+}
+
+export interface ReserveStockInput {
+  product_id: number;
+  quantity: number;
+  reference: string;
+  /** RFC 3339, or "YYYY-MM-DD HH:MM" in the business timezone */
+  expires_at: string;
+  created_by?: string | null;
+}
+
+export interface VariantInput {
+  attributes: { name: string; value: string }[];
+  sku?: string | null;
+  price?: number | null;
+  selling_price?: number | null;
+  stock_quantity?: number | null;
 }
 
 export interface User {
@@ -47,6 +169,8 @@ export interface CreateProductInput {
   supplier_id: number | null;
   amount_paid?: number | null;
   category?: string | null;
+  unit?: string | null;
+  allow_fractional?: boolean;
 }
 
 export interface UpdateProductInput {
@@ -58,6 +182,8 @@ export interface UpdateProductInput {
   stock_quantity: number;
   supplier_id: number | null;
   category?: string | null;
+  unit?: string | null;
+  allow_fractional?: boolean;
 }
 
 export interface Supplier {
@@ -97,6 +223,61 @@ export interface SupplierPaymentSummary {
   total_payable: number;
   total_paid: number;
   pending_amount: number;
+  refundable_credit: number;
+}
+
+/** Part of a supplier payment set against one PO line */
+export interface PaymentAllocation {
+  po_item_id: number;
+  amount: number;
+}
+
+export interface SupplierPayablesAging {
+  supplier_id: number;
+  supplier_name: string;
+  days_0_30: number;
+  days_31_60: number;
+  days_61_90: number;
+  days_over_90: number;
+  total: number;
+}
+
+export interface SupplierReportPurchaseOrder {
+  id: number;
+  po_number: string;
+  order_date: string;
+  status: string;
+  total_amount: number;
+  total_paid: number; // Payments against the PO plus allocations to its lines
+  total_pending: number;
+}
+
+export interface SupplierReportProduct {
+  product_id: number;
+  name: string;
+  sku: string;
+  unit: string;
+  purchased_quantity: number; // Received PO quantity plus initial stock for the primary supplier
+  purchased_value: number;
+  amount_paid: number; // Direct payments, share of PO payments and allocations
+  current_stock: number; // Units still in batches bought from this supplier
+}
+
+export interface SupplierReportStats {
+  total_purchased: number;
+  total_returned: number; // Returns set off against what is owed
+  total_paid: number;
+  pending_amount: number; // Opening balance + purchased - returned - paid
+  purchase_order_count: number;
+  average_days_between_orders: number | null;
+}
+
+export interface SupplierReport {
+  supplier: Supplier;
+  purchase_orders: SupplierReportPurchaseOrder[]; // Newest first
+  payments: SupplierPayment[]; // Newest first
+  products: SupplierReportProduct[];
+  stats: SupplierReportStats;
 }
 
 // Customer Payment (Accounts Receivable) Types
@@ -128,6 +309,53 @@ export interface CustomerCreditSummary {
   total_credit_amount: number;
   total_paid: number;
   pending_amount: number;
+  current_amount: number; // Pending but not yet due
+  overdue_amount: number; // Pending past its due date
+}
+
+export interface OverdueInvoice {
+  invoice_id: number;
+  invoice_number: string;
+  invoice_date: string;
+  due_date: string;
+  days_overdue: number;
+  bill_amount: number;
+  total_paid: number;
+  balance_remaining: number;
+  customer_id: number;
+  customer_name: string;
+  customer_phone: string | null;
+  customer_email: string | null;
+  customer_address: string | null;
+}
+
+export type CreditPaymentStatus = 'paid' | 'partial' | 'unpaid';
+
+export interface CreditInvoice {
+  invoice_id: number;
+  invoice_number: string;
+  invoice_date: string;
+  due_date: string | null;
+  days_outstanding: number; // Business days since the invoice date
+  customer_id: number | null;
+  customer_name: string | null;
+  customer_phone: string | null;
+  bill_amount: number;
+  initial_paid: number;
+  total_paid: number; // Includes the amount paid at billing
+  balance_remaining: number;
+  payment_status: CreditPaymentStatus;
+}
+
+export interface CreditInvoiceFilters {
+  customer_id?: number;
+  status?: CreditPaymentStatus;
+  sort?: 'age' | 'balance';
+  descending?: boolean; // Defaults to oldest/largest first
+}
+
+export interface CreditInvoicePage extends PaginatedResult<CreditInvoice> {
+  total_balance: number; // Balance across every page for the filters
 }
 
 export interface CreateSupplierInput {
@@ -166,10 +394,38 @@ export interface Customer {
   created_at: string;
   updated_at: string;
   image_path: string | null;
+  credit_limit: number | null; // null = unlimited
+  is_archived: boolean; // Deleted with invoice/payment history; hidden from the list by default
+  // Only present when the list was fetched with includeStats
   invoice_count?: number;
   last_billed?: string | null;
+  total_spent?: number;
+  outstanding_balance?: number;
+}
+
+/** Order of the customer list; 'recent' is latest invoice first, 'outstanding' most credit owed first */
+export type CustomerSort = 'name' | 'recent' | 'outstanding';
+
+/** Error prefix create_customer uses when the phone belongs to an archived customer */
+export const ARCHIVED_CUSTOMER_EXISTS = 'ARCHIVED_CUSTOMER_EXISTS:';
+
+export interface ArchivedCustomerConflict {
+  customer_id: number; // Pass to customerCommands.unarchive
+  name: string;
+  phone: string;
 }
 
+/** Details of an archived-customer conflict from create_customer, or null for any other error */
+export const parseArchivedCustomerError = (error: unknown): ArchivedCustomerConflict | null => {
+  const message = String(error);
+  if (!message.startsWith(ARCHIVED_CUSTOMER_EXISTS)) return null;
+  try {
+    return JSON.parse(message.slice(ARCHIVED_CUSTOMER_EXISTS.length)) as ArchivedCustomerConflict;
+  } catch {
+    return null;
+  }
+};
+
 export interface CreateCustomerInput {
   name: string;
   email: string | null;
@@ -179,6 +435,7 @@ export interface CreateCustomerInput {
   state: string | null;
   district: string | null;
   town: string | null;
+  credit_limit?: number | null;
 }
 
 export interface UpdateCustomerInput {
@@ -191,6 +448,7 @@ export interface UpdateCustomerInput {
   state: string | null;
   district: string | null;
   town: string | null;
+  credit_limit?: number | null;
 }
 
 export interface DashboardSale {
@@ -206,6 +464,7 @@ export interface DashboardStats {
   total_orders: number;
   low_stock_count: number;
   total_valuation: number;
+  overdue_total: number; // Unpaid credit past its due date
   recent_sales: DashboardSale[];
 }
 
@@ -249,6 +508,18 @@ export interface TopProduct {
   order_count: number;
 }
 
+export interface CategorySales {
+  category_id: number | null;
+  category_name: string;
+  parent_id: number | null;
+  revenue: number;
+  quantity_sold: number;
+  order_count: number;
+  revenue_share: number;          // percent of the period's item revenue
+  gross_profit: number | null;    // null when some lines predate cost tracking
+  previous_revenue: number | null; // set when compared with the previous period
+}
+
 export interface PaymentMethodBreakdown {
   payment_method: string;
   total_amount: number;
@@ -269,7 +540,10 @@ export interface CustomerAnalytics {
   new_customers: number;
   repeat_customers: number;
   repeat_rate: number;
+  avg_period_value: number;
   avg_lifetime_value: number;
+  churned_customers: number;
+  churn_rate: number;
 }
 
 export interface TopCustomer {
@@ -292,10 +566,35 @@ export interface InventoryHealth {
   low_stock_count: number;
   out_of_stock_count: number;
   healthy_stock_count: number;
-  total_valuation: number;
+  total_valuation: number; // price * stock, or batch cost when inventory_valuation_method is "fifo"
   avg_stock_level: number;
 }
 
+export interface ProductValuation {
+  product_id: number;
+  name: string;
+  sku: string;
+  category: string | null;
+  supplier_name: string | null;
+  stock_quantity: number;
+  batch_quantity: number;
+  cost_value: number; // Remaining batches at purchase cost
+  selling_price: number | null;
+  retail_value: number | null;
+  potential_margin: number | null;
+  quantity_mismatch: boolean; // Batches don't add up to stock_quantity
+}
+
+export interface InventoryValuation {
+  products: ProductValuation[];
+  total_cost_value: number;
+  total_retail_value: number;
+  total_potential_margin: number;
+  margin_percent: number;
+  mismatch_count: number;
+  unpriced_count: number; // In stock with no selling price; left out of retail totals
+}
+
 export interface LowStockAlert {
   id: number;
   name: string;
@@ -304,20 +603,34 @@ export interface LowStockAlert {
   selling_price: number | null;
   avg_daily_sales: number;
   days_until_stockout: number | null;
+  on_order_quantity: number;
 }
 
 export interface PurchaseAnalytics {
   total_purchases: number;
   total_paid: number;
+  total_returns: number;
   pending_payments: number;
   active_suppliers: number;
   purchase_order_count: number;
 }
 
+export interface SalesSummaryRebuild {
+  days: number;
+  first_date: string | null;
+  last_date: string | null;
+}
+
+/** A cashflow series. Sales and customer payments (or purchases and supplier payments) overlap, so they can't be combined. */
+export type CashEvent = 'sales' | 'purchases' | 'purchase_returns' | 'customer_payments' | 'supplier_payments';
+
 export interface CashflowPoint {
   date: string;
   sales: number;
   purchases: number;
+  purchase_returns: number;
+  customer_payments: number;
+  supplier_payments: number;
   net: number;
 }
 
@@ -341,6 +654,14 @@ export interface TaxSummary {
   sgst_total: number;
   igst_total: number;
   by_state: StateTax[];
+  by_rate: RateTax[];
+}
+
+export interface RateTax {
+  tax_rate: number;
+  taxable_value: number;
+  tax_amount: number;
+  item_count: number;
 }
 
 export interface DiscountAnalysis {
@@ -364,6 +685,8 @@ export interface Invoice {
   gst_rate: number | null;
   igst_amount: number | null;
   sgst_amount: number | null;
+  round_off: number; // Rounding adjustment included in total_amount
+  due_date: string | null; // YYYY-MM-DD, credit invoices only
   state: string | null;
   district: string | null;
   town: string | null;
@@ -382,6 +705,23 @@ export interface ProductPurchaseSummary {
   total_quantity: number;
   total_value: number;
   purchase_orders: number;
+  /** Ordered and partially received POs still to arrive, not part of the totals above */
+  on_order_quantity: number;
+  on_order_value: number;
+}
+
+export interface IncomingStock {
+  po_id: number;
+  po_item_id: number;
+  po_number: string;
+  supplier_id: number;
+  supplier_name: string;
+  status: 'ordered' | 'partially_received';
+  order_date: string;
+  expected_delivery_date: string | null;
+  ordered_quantity: number;
+  received_quantity: number;
+  remaining_quantity: number;
 }
 
 export interface InvoiceItemWithProduct {
@@ -393,13 +733,48 @@ export interface InvoiceItemWithProduct {
   quantity: number;
   unit_price: number;
   discount_amount: number; // Per-item weighted discount
+  tax_rate: number | null;
+  tax_amount: number | null;
+  backordered_quantity: number; // Sold beyond stock while negative stock was allowed
 }
 
 export type InvoiceItem = InvoiceItemWithProduct;
 
+export interface StockReconciliation {
+  product_id: number;
+  recorded_quantity: number;
+  batch_quantity: number;
+  backordered_quantity: number; // Sold past stock and not covered by any batch
+  corrected: boolean;
+  reconciled_quantity: number; // stock_quantity after reconciling
+}
+
+export interface InventoryBatchReport {
+  product_id: number;
+  /** Oldest first */
+  batches: InventoryBatchWithDetails[];
+  total_remaining: number;
+  total_value: number;
+  weighted_average_cost: number;
+  stock_quantity: number;
+  /** stock_quantity disagrees with the batches; reconcileStock can fix it */
+  stock_mismatch: boolean;
+}
+
 export interface InvoiceWithItems {
   invoice: Invoice;
   items: InvoiceItemWithProduct[];
+  payments: CustomerPayment[];
+  initial_paid: number;
+  credit_amount: number;
+  amount_paid: number; // non-credit invoices count as fully paid
+  balance_due: number;
+  tenders: InvoiceTender[]; // empty unless the invoice was paid with several methods
+}
+
+export interface InvoiceTender {
+  method: string;
+  amount: number;
 }
 
 export interface DeletedInvoice {
@@ -434,11 +809,37 @@ export interface EntityModification {
   modified_at: string;
 }
 
+export interface ModificationFilters {
+  entity_type?: string;
+  entity_id?: number;
+  modified_by?: string;
+  start_date?: string; // YYYY-MM-DD, business-local, inclusive
+  end_date?: string; // YYYY-MM-DD, business-local, inclusive
+}
+
+export interface FieldChange {
+  field: string;
+  old: unknown;
+  new: unknown;
+}
+
+export interface TimelineEntry {
+  source: 'modification' | 'trash' | 'invoice_modification';
+  source_id: number;
+  action: string;
+  summary: string | null;
+  changes: FieldChange[];
+  modified_by: string | null;
+  modified_at: string; // UTC "YYYY-MM-DD HH:MM:SS"
+}
+
 export interface CreateInvoiceItemInput {
   product_id: number;
   quantity: number;
   unit_price: number;
   discount_amount?: number; // Per-item weighted discount
+  tax_rate?: number; // GST % for this line; falls back to the invoice gst_rate
+  reservation_id?: number; // Stock reservation this line fulfils; released once the invoice is saved
 }
 
 export interface CreateInvoiceInput {
@@ -451,6 +852,148 @@ export interface CreateInvoiceInput {
   district?: string;
   town?: string;
   initial_paid?: number; // For credit payments - amount paid at checkout
+  price_tier_id?: number; // Defaults to the customer's current price tier
+  gst_rate?: number; // GST % for items without their own tax_rate
+  allow_over_limit?: boolean; // Manager override for a credit sale over the customer's limit
+  approved_by?: string; // Required with allow_over_limit
+  due_date?: string; // YYYY-MM-DD; defaults to today + the credit_period_days setting
+  payments?: InvoiceTender[]; // Split payment; any shortfall goes on the customer's credit
+  price_override_reason?: string; // Required when a line is priced past price_override_tolerance_percent
+  price_override_approval?: string; // Approval token when require_biometric_for lists price_override
+  created_by?: string; // Cashier, recorded with price overrides
+}
+
+export interface DuplicateInvoiceWarning {
+  product_id: number | null;
+  name: string; // Name on the source invoice
+  issue: 'customer_deleted' | 'product_deleted' | 'out_of_stock' | 'quantity_reduced' | 'price_changed';
+  message: string;
+}
+
+export interface DuplicatedInvoice {
+  source_invoice_id: number;
+  source_invoice_number: string;
+  draft: CreateInvoiceInput; // Unsaved; bill it with invoiceCommands.create
+  warnings: DuplicateInvoiceWarning[];
+}
+
+export interface InvoiceListFilters {
+  start_date?: string; // YYYY-MM-DD, business-local, inclusive
+  end_date?: string; // YYYY-MM-DD, business-local, inclusive
+  payment_method?: string; // Also matches split invoices partly paid this way
+  min_amount?: number;
+  max_amount?: number;
+  credit_status?: 'paid' | 'partial' | 'unpaid';
+  sort?: 'date' | 'amount' | 'customer';
+  descending?: boolean; // Defaults to true for date and amount, false for customer
+}
+
+/** Error prefix create_invoice uses when a credit sale would exceed the customer's limit */
+export const CREDIT_LIMIT_EXCEEDED = 'CREDIT_LIMIT_EXCEEDED:';
+
+export interface CreditLimitExceeded {
+  customer_id: number;
+  credit_limit: number;
+  current_outstanding: number;
+  invoice_credit: number;
+  overflow: number;
+}
+
+/** Details of a credit limit rejection from create_invoice, or null for any other error */
+export const parseCreditLimitError = (error: unknown): CreditLimitExceeded | null => {
+  const message = String(error);
+  if (!message.startsWith(CREDIT_LIMIT_EXCEEDED)) return null;
+  try {
+    return JSON.parse(message.slice(CREDIT_LIMIT_EXCEEDED.length)) as CreditLimitExceeded;
+  } catch {
+    return null;
+  }
+};
+
+/** Error prefix create_invoice uses when lines are priced under list without a reason */
+export const PRICE_OVERRIDE_REASON_REQUIRED = 'PRICE_OVERRIDE_REASON_REQUIRED:';
+
+export interface OverriddenLine {
+  line: number; // index into the sale's items
+  product_id: number;
+  list_price: number; // tier price when the sale is priced with a tier
+  unit_price: number;
+}
+
+/** Lines that need a price override reason, or null for any other error */
+export const parsePriceOverrideError = (error: unknown): OverriddenLine[] | null => {
+  const message = String(error);
+  if (!message.startsWith(PRICE_OVERRIDE_REASON_REQUIRED)) return null;
+  try {
+    return JSON.parse(message.slice(PRICE_OVERRIDE_REASON_REQUIRED.length)) as OverriddenLine[];
+  } catch {
+    return null;
+  }
+};
+
+export interface PriceOverride {
+  id: number;
+  invoice_id: number | null; // null once the invoice is deleted
+  invoice_number: string;
+  product_id: number;
+  product_name: string;
+  quantity: number;
+  list_price: number;
+  charged_price: number;
+  discount: number;
+  reason: string;
+  cashier: string | null;
+  approved_by: string | null;
+  created_at: string;
+}
+
+export interface CashierOverrides {
+  cashier: string | null;
+  override_count: number;
+  invoice_count: number;
+  total_discount: number;
+}
+
+export interface PriceOverrideReport {
+  start_date: string;
+  end_date: string;
+  overrides: PriceOverride[];
+  by_cashier: CashierOverrides[];
+  total_discount: number;
+}
+
+export interface PriceTier {
+  id: number;
+  name: string;
+  discount_percent: number;
+  override_count: number;
+  customer_count: number;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface PriceTierInput {
+  name: string;
+  discount_percent: number;
+}
+
+export interface EffectivePrice {
+  product_id: number;
+  list_price: number;
+  unit_price: number;
+  tier_id: number | null;
+  tier_name: string | null;
+  source: 'list' | 'tier_discount' | 'tier_override';
+}
+
+export interface PriceListEntry {
+  product_id: number;
+  name: string;
+  sku: string;
+  category: string | null;
+  list_price: number;
+  tier_price: number;
+  is_override: boolean;
 }
 
 export interface UpdateInvoiceInput {
@@ -459,6 +1002,8 @@ export interface UpdateInvoiceInput {
   payment_method?: string | null;
   created_at?: string | null;
   status?: string | null;
+  modified_by?: string | null;
+  closed_day_override?: string | null; // Manager approving a change to a closed business day
 }
 
 /**
@@ -466,13 +1011,31 @@ export interface UpdateInvoiceInput {
  */
 export const productCommands = {
   /**
-   * Get all products, optionally filtered by search query
+   * Get all products, optionally filtered by search query.
+   * Pass expandVariants = false to list parents only, with variant stock and sales rolled up.
    */
+  getAll: async (
+    page: number = 1,
+    pageSize: number = 50,
+    search?: string,
+    expandVariants?: boolean,
+    categoryId?: number,
+  ): Promise<PaginatedResult<Product>> => {
+    return await invoke<PaginatedResult<Product>>('get_products', { search, page, pageSize, expandVariants, categoryId });
+  },
+
+  /**
+   * Create size/color variants of a product
+   */
+  createVariants: async (parentId: number, variants: VariantInput[]): Promise<Product[]> => {
+    return await invoke<Product[]>('create_product_variants', { parentId, variants });
+  },
+
   /**
-   * Get all products, optionally filtered by search query
+   * Get the variants of a product
    */
-  getAll: async (page: number = 1, pageSize: number = 50, search?: string): Promise<PaginatedResult<Product>> => {
-    return await invoke<PaginatedResult<Product>>('get_products', { search, page, pageSize });
+  getVariants: async (parentId: number): Promise<Product[]> => {
+    return await invoke<Product[]>('get_product_variants', { parentId });
   },
 
   /**
@@ -499,8 +1062,8 @@ export const productCommands = {
   /**
    * Update an existing product
    */
-  update: async (input: UpdateProductInput): Promise<Product> => {
-    return await invoke<Product>('update_product', { input });
+  update: async (input: UpdateProductInput, username?: string, approvalToken?: string): Promise<Product> => {
+    return await invoke<Product>('update_product', { input, modifiedBy: username ?? null, approvalToken: approvalToken ?? null });
   },
 
   /**
@@ -510,50 +1073,183 @@ export const productCommands = {
     return await invoke<void>('delete_product', { id, deleted_by: username ?? null });
   },
 
+  /**
+   * Reset a product's stock to the sum of its remaining batches
+   */
+  reconcileStock: async (productId: number, username?: string): Promise<StockReconciliation> => {
+    return await invoke<StockReconciliation>('reconcile_product_stock', { productId, modifiedBy: username ?? null });
+  },
+
+  /**
+   * FIFO batches behind a product's stock, oldest first, with totals
+   */
+  getInventoryBatches: async (productId: number): Promise<InventoryBatchReport> => {
+    return await invoke<InventoryBatchReport>('get_inventory_batches', { productId });
+  },
+
   /**
    * Add mock product data for testing
    */
   addMockData: async (): Promise<string> => {
     return await invoke<string>('add_mock_products');
   },
-  getTopSelling: async (limit: number, page: number = 1, category?: string): Promise<PaginatedResult<Product>> => {
-    return await invoke<PaginatedResult<Product>>('get_top_selling_products', { limit, page, category: category ?? null });
+  getTopSelling: async (limit: number, page: number = 1, category?: string, supplierId?: number, categoryId?: number): Promise<PaginatedResult<Product>> => {
+    return await invoke<PaginatedResult<Product>>('get_top_selling_products', {
+      limit,
+      page,
+      category: category ?? null,
+      supplierId: supplierId ?? null,
+      categoryId: categoryId ?? null,
+    });
   },
   getByIds: async (ids: number[]): Promise<Product[]> => {
     return await invoke<Product[]>('get_products_by_ids', { ids });
   },
-  getAllCategories: async (): Promise<string[]> => {
-    return await invoke<string[]>('get_unique_categories');
-  }
-};
-
-
-
-/**
- * Supplier Commands
- */
-export const supplierCommands = {
   /**
-   * Get all suppliers, optionally filtered by search query
+   * Billing product snapshot; pass the previous snapshot's as_of to get only changes
    */
+  getBillingSnapshot: async (since?: string): Promise<BillingSnapshot> => {
+    return await invoke<BillingSnapshot>('get_billing_snapshot', { since: since ?? null });
+  },
   /**
-   * Get all suppliers, optionally filtered by search query
+   * Most recent price the product was sold at, overall and to the given customer
    */
-  getAll: async (page: number = 1, pageSize: number = 50, search?: string): Promise<PaginatedResult<Supplier>> => {
-    return await invoke<PaginatedResult<Supplier>>('get_suppliers', { search, page, pageSize });
+  getLastSoldPrice: async (productId: number, customerId?: number | null): Promise<LastSoldPrice> => {
+    return await invoke<LastSoldPrice>('get_last_sold_price', { productId, customerId: customerId ?? null });
   },
-
   /**
-   * Get a single supplier by ID
+   * Distinct unit prices the product was sold at, most recently used first
    */
-  getById: async (id: number): Promise<Supplier> => {
-    return await invoke<Supplier>('get_supplier', { id });
+  getPriceHistory: async (productId: number, customerId?: number | null): Promise<PriceHistoryEntry[]> => {
+    return await invoke<PriceHistoryEntry[]>('get_product_price_history', { productId, customerId: customerId ?? null });
   },
-
   /**
-   * Create a new supplier
+   * Recorded cost and selling price changes plus pending scheduled changes
    */
-  create: async (input: CreateSupplierInput): Promise<Supplier> => {
+  getPriceTimeline: async (productId: number): Promise<PriceTimeline> => {
+    return await invoke<PriceTimeline>('get_product_price_timeline', { productId });
+  },
+  /**
+   * Schedule a price change (or apply it now when effective_from is omitted).
+   * Large changes need a biometric approval token, as with update.
+   */
+  schedulePrice: async (input: SchedulePriceInput, approvalToken?: string): Promise<ScheduledPriceChange> => {
+    return await invoke<ScheduledPriceChange>('set_scheduled_price', { input, approvalToken: approvalToken ?? null });
+  },
+  /**
+   * Cancel a scheduled price change that hasn't been applied yet
+   */
+  cancelScheduledPrice: async (id: number): Promise<void> => {
+    return await invoke<void>('cancel_scheduled_price', { id });
+  },
+  /**
+   * Hold stock for an unpaid order until the expiry
+   */
+  reserveStock: async (input: ReserveStockInput): Promise<StockReservation> => {
+    return await invoke<StockReservation>('reserve_stock', { input });
+  },
+  /**
+   * Active reservations, soonest to expire first
+   */
+  getReservations: async (productId?: number | null): Promise<StockReservation[]> => {
+    return await invoke<StockReservation[]>('get_reservations', { productId: productId ?? null });
+  },
+  /**
+   * Release a reservation, making its stock available again
+   */
+  releaseReservation: async (id: number): Promise<void> => {
+    return await invoke<void>('release_reservation', { id });
+  },
+  getAllCategories: async (): Promise<string[]> => {
+    return await invoke<string[]>('get_unique_categories');
+  }
+};
+
+export interface Category {
+  id: number;
+  name: string;
+  parent_id: number | null;
+  sort_order: number;
+  product_count: number;
+  created_at: string;
+  updated_at: string;
+}
+
+export interface CreateCategoryInput {
+  name: string;
+  parent_id?: number | null;
+  sort_order?: number | null;
+}
+
+export interface UpdateCategoryInput {
+  id: number;
+  name: string;
+  parent_id: number | null;
+  sort_order: number;
+}
+
+/**
+ * Category Commands (one level of subcategories)
+ */
+export const categoryCommands = {
+  getAll: async (): Promise<Category[]> => {
+    return await invoke<Category[]>('get_categories');
+  },
+
+  create: async (input: CreateCategoryInput): Promise<Category> => {
+    return await invoke<Category>('create_category', { input });
+  },
+
+  update: async (input: UpdateCategoryInput): Promise<Category> => {
+    return await invoke<Category>('update_category', { input });
+  },
+
+  /**
+   * Delete a category, moving its products to reassignTo (or leaving them uncategorized)
+   */
+  delete: async (id: number, reassignTo?: number | null): Promise<void> => {
+    return await invoke<void>('delete_category', { id, reassignTo: reassignTo ?? null });
+  },
+
+  reassignProducts: async (productIds: number[], categoryId: number | null): Promise<number> => {
+    return await invoke<number>('reassign_products_category', { productIds, categoryId });
+  },
+
+  /**
+   * Merge a duplicate category into another, repointing its products
+   */
+  merge: async (sourceId: number, targetId: number): Promise<Category> => {
+    return await invoke<Category>('merge_categories', { sourceId, targetId });
+  },
+};
+
+
+
+/**
+ * Supplier Commands
+ */
+export const supplierCommands = {
+  /**
+   * Get all suppliers, optionally filtered by search query
+   */
+  /**
+   * Get all suppliers, optionally filtered by search query
+   */
+  getAll: async (page: number = 1, pageSize: number = 50, search?: string): Promise<PaginatedResult<Supplier>> => {
+    return await invoke<PaginatedResult<Supplier>>('get_suppliers', { search, page, pageSize });
+  },
+
+  /**
+   * Get a single supplier by ID
+   */
+  getById: async (id: number): Promise<Supplier> => {
+    return await invoke<Supplier>('get_supplier', { id });
+  },
+
+  /**
+   * Create a new supplier
+   */
+  create: async (input: CreateSupplierInput): Promise<Supplier> => {
     return await invoke<Supplier>('create_supplier', { input });
   },
 
@@ -602,6 +1298,7 @@ export const supplierCommands = {
     payment_method?: string | null;
     note?: string | null;
     paid_at?: string | null;
+    allocations?: PaymentAllocation[] | null;
   }): Promise<SupplierPayment> => {
     return await invoke<SupplierPayment>('create_supplier_payment', {
       input: {
@@ -609,6 +1306,7 @@ export const supplierCommands = {
         payment_method: input.payment_method ?? null,
         note: input.note ?? null,
         paid_at: input.paid_at ?? null,
+        allocations: input.allocations ?? null,
       },
     });
   },
@@ -639,6 +1337,20 @@ export const supplierCommands = {
     });
   },
 
+  /**
+   * Get unpaid PO balances per supplier, bucketed by age (oldest debt first)
+   */
+  getPayablesAging: async (): Promise<SupplierPayablesAging[]> => {
+    return await invoke<SupplierPayablesAging[]>('get_supplier_payables_aging');
+  },
+
+  /**
+   * Get a supplier's POs, payments, products supplied and headline totals in one call
+   */
+  getReport: async (supplierId: number): Promise<SupplierReport> => {
+    return await invoke<SupplierReport>('get_supplier_report', { supplierId });
+  },
+
   /**
    * Get purchase history (PO items) for a specific product and supplier
    */
@@ -658,8 +1370,22 @@ export const customerCommands = {
   /**
    * Get all customers, optionally filtered by search query
    */
-  getAll: async (page: number = 1, pageSize: number = 50, search?: string): Promise<PaginatedResult<Customer>> => {
-    return await invoke<PaginatedResult<Customer>>('get_customers', { search, page, pageSize });
+  getAll: async (
+    page: number = 1,
+    pageSize: number = 50,
+    search?: string,
+    includeArchived: boolean = false,
+    sortBy: CustomerSort = 'name',
+    includeStats: boolean = false
+  ): Promise<PaginatedResult<Customer>> => {
+    return await invoke<PaginatedResult<Customer>>('get_customers', {
+      search,
+      page,
+      pageSize,
+      includeArchived,
+      sortBy,
+      includeStats,
+    });
   },
 
   /**
@@ -694,12 +1420,19 @@ export const customerCommands = {
   },
 
   /**
-   * Delete a customer by ID
+   * Delete a customer by ID (archived instead when they have invoices or payments)
    */
   delete: async (id: number, username?: string): Promise<void> => {
     return await invoke<void>('delete_customer', { id, deleted_by: username ?? null });
   },
 
+  /**
+   * Bring an archived customer back into the customer list
+   */
+  unarchive: async (customerId: number, username?: string): Promise<void> => {
+    return await invoke<void>('restore_customer', { customerId, restoredBy: username ?? null });
+  },
+
   /**
    * Add mock customer data for testing
    */
@@ -727,10 +1460,23 @@ export const customerPaymentCommands = {
   },
 
   /**
-   * Get all payments for a customer
+   * Get a page of a customer's payments, newest first, optionally within a date range
+   * (YYYY-MM-DD, inclusive) and for one payment method
    */
-  getByCustomer: async (customerId: number): Promise<CustomerPayment[]> => {
-    return await invoke<CustomerPayment[]>('get_customer_payments', { customerId });
+  getByCustomer: async (
+    customerId: number,
+    page: number = 1,
+    pageSize: number = 50,
+    filters: { startDate?: string; endDate?: string; paymentMethod?: string } = {}
+  ): Promise<PaginatedResult<CustomerPayment>> => {
+    return await invoke<PaginatedResult<CustomerPayment>>('get_customer_payments', {
+      customerId,
+      page,
+      pageSize,
+      startDate: filters.startDate ?? null,
+      endDate: filters.endDate ?? null,
+      paymentMethod: filters.paymentMethod ?? null,
+    });
   },
 
   /**
@@ -741,10 +1487,22 @@ export const customerPaymentCommands = {
   },
 
   /**
-   * Get credit history for a customer (all invoices with credit)
+   * Get a page of a customer's credit history (invoices with credit), newest first,
+   * optionally for invoices within a date range (YYYY-MM-DD, inclusive)
    */
-  getCreditHistory: async (customerId: number): Promise<CustomerInvoiceCreditSummary[]> => {
-    return await invoke<CustomerInvoiceCreditSummary[]>('get_customer_credit_history', { customerId });
+  getCreditHistory: async (
+    customerId: number,
+    page: number = 1,
+    pageSize: number = 50,
+    filters: { startDate?: string; endDate?: string } = {}
+  ): Promise<PaginatedResult<CustomerInvoiceCreditSummary>> => {
+    return await invoke<PaginatedResult<CustomerInvoiceCreditSummary>>('get_customer_credit_history', {
+      customerId,
+      page,
+      pageSize,
+      startDate: filters.startDate ?? null,
+      endDate: filters.endDate ?? null,
+    });
   },
 
   /**
@@ -754,6 +1512,24 @@ export const customerPaymentCommands = {
     return await invoke<CustomerCreditSummary>('get_customer_credit_summary', { customerId });
   },
 
+  /**
+   * Get credit invoices with an unpaid balance past their due date, optionally for one customer
+   */
+  getOverdueInvoices: async (customerId?: number): Promise<OverdueInvoice[]> => {
+    return await invoke<OverdueInvoice[]>('get_overdue_invoices', { customerId: customerId ?? null });
+  },
+
+  /**
+   * Get a page of credit invoices with paid, balance and age, plus the total balance for the filters
+   */
+  getCreditInvoices: async (
+    page: number,
+    pageSize: number,
+    filters: CreditInvoiceFilters = {}
+  ): Promise<CreditInvoicePage> => {
+    return await invoke<CreditInvoicePage>('get_credit_invoices', { page, pageSize, filters });
+  },
+
   /**
    * Delete a customer payment
    */
@@ -808,8 +1584,8 @@ export const analyticsCommands = {
   /**
    * Search for customers and get detailed reports
    */
-  customerSearch: async (query: string): Promise<CustomerReport[]> => {
-    return await invoke<CustomerReport[]>('customer_search', { query });
+  customerSearch: async (query: string, limit?: number): Promise<CustomerReport[]> => {
+    return await invoke<CustomerReport[]>('customer_search', { query, limit: limit ?? null });
   },
 
   /**
@@ -855,6 +1631,13 @@ export const analyticsCommands = {
     return await invoke<PaymentMethodBreakdown[]>('get_sales_by_payment_method', { startDate, endDate });
   },
 
+  /**
+   * Get sales breakdown by product category
+   */
+  getSalesByCategory: async (startDate: string, endDate: string, comparePrevious?: boolean): Promise<CategorySales[]> => {
+    return await invoke<CategorySales[]>('get_sales_by_category', { startDate, endDate, comparePrevious: comparePrevious ?? null });
+  },
+
   /**
    * Get sales by region (state)
    */
@@ -894,6 +1677,13 @@ export const analyticsCommands = {
     return await invoke<InventoryHealth>('get_inventory_health');
   },
 
+  /**
+   * Value stock at FIFO batch cost and at selling price, optionally for one category or supplier
+   */
+  getInventoryValuation: async (categoryId?: number, supplierId?: number): Promise<InventoryValuation> => {
+    return await invoke<InventoryValuation>('get_inventory_valuation', { categoryId, supplierId });
+  },
+
   /**
    * Get low stock alerts with sales velocity
    */
@@ -909,14 +1699,15 @@ export const analyticsCommands = {
   },
 
   /**
-   * Get cashflow trend (sales vs purchases)
+   * Get cashflow trend; events defaults to sales, purchases and purchase returns
    */
   getCashflowTrend: async (
     startDate: string,
     endDate: string,
-    granularity: 'daily' | 'weekly' | 'monthly' = 'daily'
+    granularity: 'daily' | 'weekly' | 'monthly' = 'daily',
+    events?: CashEvent[]
   ): Promise<CashflowPoint[]> => {
-    return await invoke<CashflowPoint[]>('get_cashflow_trend', { startDate, endDate, granularity });
+    return await invoke<CashflowPoint[]>('get_cashflow_trend', { startDate, endDate, granularity, events: events ?? null });
   },
 
   /**
@@ -939,6 +1730,54 @@ export const analyticsCommands = {
   getDiscountAnalysis: async (startDate: string, endDate: string): Promise<DiscountAnalysis> => {
     return await invoke<DiscountAnalysis>('get_discount_analysis', { startDate, endDate });
   },
+
+  /**
+   * Recompute the cached daily sales totals from invoices
+   */
+  rebuildSalesSummary: async (): Promise<SalesSummaryRebuild> => {
+    return await invoke<SalesSummaryRebuild>('rebuild_sales_summary');
+  },
+};
+
+export interface SalesTargetProgress {
+  id: number;
+  period_type: 'monthly' | 'quarterly';
+  period_key: string; // "YYYY-MM" or "YYYY-Qn"
+  start_date: string;
+  end_date: string;
+  target_amount: number;
+  actual_revenue: number;
+  invoice_count: number;
+  percent_achieved: number;
+  days_total: number;
+  days_elapsed: number; // Days before today
+  days_remaining: number; // Today included
+  projected_revenue: number; // At the pace so far; the actual once the period is over
+  required_daily_amount: number;
+}
+
+/**
+ * Sales Target Commands (revenue goals per month or quarter)
+ */
+export const salesTargetCommands = {
+  set: async (periodType: 'monthly' | 'quarterly', periodKey: string, amount: number): Promise<SalesTargetProgress> => {
+    return await invoke<SalesTargetProgress>('set_sales_target', { periodType, periodKey, amount });
+  },
+
+  /**
+   * Target vs actual for a period; null when no target is set
+   */
+  getProgress: async (periodKey: string): Promise<SalesTargetProgress | null> => {
+    return await invoke<SalesTargetProgress | null>('get_sales_target_progress', { periodKey });
+  },
+
+  getAll: async (periodType?: 'monthly' | 'quarterly'): Promise<SalesTargetProgress[]> => {
+    return await invoke<SalesTargetProgress[]>('get_sales_targets', { periodType: periodType ?? null });
+  },
+
+  delete: async (id: number): Promise<void> => {
+    return await invoke<void>('delete_sales_target', { id });
+  },
 };
 
 /**
@@ -949,10 +1788,16 @@ export const invoiceCommands = {
    * Get all invoices, optionally filtered by customer
    */
   /**
-   * Get all invoices with pagination, search, and optional customer filter
+   * Get all invoices with pagination, search, optional customer filter and list filters
    */
-  getAll: async (page: number = 1, pageSize: number = 50, search?: string, customerId?: number): Promise<PaginatedResult<Invoice>> => {
-    return await invoke<PaginatedResult<Invoice>>('get_invoices', { page, pageSize, search, customer_id: customerId });
+  getAll: async (
+    page: number = 1,
+    pageSize: number = 50,
+    search?: string,
+    customerId?: number,
+    filters?: InvoiceListFilters
+  ): Promise<PaginatedResult<Invoice>> => {
+    return await invoke<PaginatedResult<Invoice>>('get_invoices', { page, pageSize, search, customer_id: customerId, filters });
   },
 
   /**
@@ -962,6 +1807,14 @@ export const invoiceCommands = {
     return await invoke<Invoice[]>('get_invoices_by_product', { productId });
   },
 
+  /**
+   * Get invoices with an item named like namePattern as printed on the bill; works for renamed
+   * and deleted products
+   */
+  getByProductName: async (namePattern: string, page: number, pageSize: number): Promise<PaginatedResult<Invoice>> => {
+    return await invoke<PaginatedResult<Invoice>>('get_invoices_by_product_name', { namePattern, page, pageSize });
+  },
+
   /**
    * Get a single invoice with its items
    */
@@ -969,6 +1822,17 @@ export const invoiceCommands = {
     return await invoke<InvoiceWithItems>('get_invoice', { id });
   },
 
+  /**
+   * Draft a repeat order from an earlier invoice (nothing is saved); bill it with create.
+   * Prices are refreshed to the current tier or list price unless keepHistoricalPrices.
+   */
+  duplicate: async (sourceInvoiceId: number, keepHistoricalPrices?: boolean): Promise<DuplicatedInvoice> => {
+    return await invoke<DuplicatedInvoice>('duplicate_invoice', {
+      sourceInvoiceId,
+      keepHistoricalPrices: keepHistoricalPrices ?? null,
+    });
+  },
+
   /**
    * Get aggregated sales summary for a product
    */
@@ -983,6 +1847,13 @@ export const invoiceCommands = {
     return await invoke<ProductPurchaseSummary>('get_product_purchase_summary', { productId });
   },
 
+  /**
+   * Get open PO lines still to arrive for a product, soonest due first
+   */
+  getIncomingStock: async (productId: number): Promise<IncomingStock[]> => {
+    return await invoke<IncomingStock[]>('get_incoming_stock', { productId });
+  },
+
   /**
    * Create a new invoice with items (updates stock)
    */
@@ -993,8 +1864,13 @@ export const invoiceCommands = {
   /**
    * Delete an invoice (restores stock)
    */
-  delete: async (id: number, username?: string): Promise<void> => {
-    return await invoke<void>('delete_invoice', { id, deleted_by: username ?? null });
+  delete: async (id: number, username?: string, approvalToken?: string, closedDayOverride?: string): Promise<void> => {
+    return await invoke<void>('delete_invoice', {
+      id,
+      deleted_by: username ?? null,
+      approvalToken: approvalToken ?? null,
+      closedDayOverride: closedDayOverride ?? null,
+    });
   },
 
   /**
@@ -1005,14 +1881,24 @@ export const invoiceCommands = {
   },
 
   /**
-   * Update invoice items (add/remove products with stock adjustment)
+   * Update invoice items (add/remove products with stock adjustment). The total is recomputed
+   * as in create; tax and discount keep their current values unless given in `financials`.
    */
-  updateItems: async (invoiceId: number, items: CreateInvoiceItemInput[], modifiedBy?: string): Promise<Invoice> => {
+  updateItems: async (
+    invoiceId: number,
+    items: CreateInvoiceItemInput[],
+    modifiedBy?: string,
+    closedDayOverride?: string,
+    financials?: { tax_amount?: number | null; discount_amount?: number | null }
+  ): Promise<Invoice> => {
     return await invoke<Invoice>('update_invoice_items', {
       input: {
         invoice_id: invoiceId,
         items,
+        tax_amount: financials?.tax_amount ?? null,
+        discount_amount: financials?.discount_amount ?? null,
         modified_by: modifiedBy ?? null,
+        closed_day_override: closedDayOverride ?? null,
       },
     });
   },
@@ -1040,6 +1926,7 @@ export interface SearchProduct {
   sku: string;
   price: number;
   stock_quantity: number;
+  display_name: string;
 }
 
 export interface SearchCustomer {
@@ -1068,96 +1955,377 @@ export interface SearchInvoice {
   created_at: string;
 }
 
+export interface SearchChallan {
+  id: number;
+  challan_number: string;
+  customer_name: string | null;
+  status: 'open' | 'invoiced' | 'returned';
+  created_at: string;
+}
+
 export interface SearchResult {
   products: SearchProduct[];
   customers: SearchCustomer[];
   suppliers: SearchSupplier[];
   invoices: SearchInvoice[];
+  challans: SearchChallan[];
+}
+
+export interface HeldSale {
+  id: number;
+  label: string;
+  item_count: number;
+  subtotal: number;
+  held_by: string | null;
+  created_at: string;
+  age_minutes: number;
 }
 
 /**
- * Search Commands
+ * Held Sale Commands (parked bills)
  */
-export const searchCommands = {
+export const heldSaleCommands = {
+  hold: async (draft: CreateInvoiceInput, label: string, heldBy?: string): Promise<HeldSale> => {
+    return await invoke<HeldSale>('hold_sale', { draft, label, heldBy: heldBy ?? null });
+  },
+
+  getAll: async (): Promise<HeldSale[]> => {
+    return await invoke<HeldSale[]>('get_held_sales');
+  },
+
   /**
-   * OmniSearch: Search across all entities
+   * Returns the parked draft and removes the hold
    */
-  omnisearch: async (query: string): Promise<SearchResult> => {
-    return await invoke<SearchResult>('omnisearch', { query });
+  resume: async (id: number): Promise<CreateInvoiceInput> => {
+    return await invoke<CreateInvoiceInput>('resume_held_sale', { id });
+  },
+
+  discard: async (id: number): Promise<void> => {
+    return await invoke<void>('discard_held_sale', { id });
+  },
+};
+
+export interface RecurringInvoiceTemplate {
+  id: number;
+  name: string;
+  customer_id: number | null;
+  customer_name: string | null;
+  input: CreateInvoiceInput;
+  frequency: 'weekly' | 'monthly';
+  next_run_date: string; // YYYY-MM-DD
+  active: boolean;
+  last_generated_date: string | null;
+  last_invoice_id: number | null;
+  last_error: string | null; // Why the last due run was skipped
+  created_by: string | null;
+  created_at: string;
+}
+
+export interface CreateRecurringTemplateInput {
+  name: string;
+  input: CreateInvoiceInput;
+  frequency: 'weekly' | 'monthly';
+  next_run_date?: string | null; // Defaults to today
+  created_by?: string | null;
+}
+
+export interface RecurringRunSummary {
+  generated: { template_id: number; invoice_id: number; invoice_number: string }[];
+  skipped: { template_id: number; name: string; reason: string }[];
+}
+
+/**
+ * Recurring Invoice Commands (templates raised weekly or monthly)
+ */
+export const recurringInvoiceCommands = {
+  create: async (input: CreateRecurringTemplateInput): Promise<RecurringInvoiceTemplate> => {
+    return await invoke<RecurringInvoiceTemplate>('create_recurring_template', { input });
+  },
+
+  getAll: async (): Promise<RecurringInvoiceTemplate[]> => {
+    return await invoke<RecurringInvoiceTemplate[]>('get_recurring_templates');
   },
 
   /**
-   * Export products to CSV format
+   * Pause (active = false) or resume a template
    */
-  exportProductsCSV: async (): Promise<string> => {
-    return await invoke<string>('export_products_csv');
+  setActive: async (id: number, active: boolean): Promise<RecurringInvoiceTemplate> => {
+    return await invoke<RecurringInvoiceTemplate>('set_recurring_template_active', { id, active });
+  },
+
+  delete: async (id: number): Promise<void> => {
+    return await invoke<void>('delete_recurring_template', { id });
   },
 
   /**
-   * Export customers to CSV format
+   * Raise every due template's invoice now (also runs on app startup)
    */
-  exportCustomersCSV: async (): Promise<string> => {
-    return await invoke<string>('export_customers_csv');
+  processDue: async (): Promise<RecurringRunSummary> => {
+    return await invoke<RecurringRunSummary>('process_due_recurring_invoices');
   },
 };
 
-// =============================================
-// PURCHASE ORDER TYPES
-// =============================================
+export type ChallanStatus = 'open' | 'invoiced' | 'returned';
 
-export interface PurchaseOrder {
-  id: number;
-  po_number: string;
-  supplier_id: number;
-  order_date: string;
-  expected_delivery_date: string | null;
-  received_date: string | null;
-  status: 'draft' | 'ordered' | 'received' | 'cancelled';
-  total_amount: number;
-  notes: string | null;
-  created_at: string;
-  updated_at: string;
+export interface ChallanItemInput {
+  product_id: number;
+  quantity: number;
+  unit_price?: number | null; // Defaults to the customer's tier price, else the list price
 }
 
-export interface PurchaseOrderWithDetails {
-  id: number;
-  po_number: string;
-  supplier_id: number;
-  supplier_name: string;
-  order_date: string;
-  expected_delivery_date: string | null;
-  received_date: string | null;
-  status: 'draft' | 'ordered' | 'received' | 'cancelled';
-  total_amount: number;
-  notes: string | null;
-  created_at: string;
-  updated_at: string;
-  items_count: number;
-  quantity?: number; // Quantity of specific product (context-dependent)
-  total_paid: number;
-  total_pending: number;
+export interface CreateChallanInput {
+  customer_id: number;
+  items: ChallanItemInput[];
+  notes?: string | null;
+  created_by?: string | null;
 }
 
-export interface PurchaseOrderItemWithProduct {
+export interface ConvertChallanInput {
+  challan_id: number;
+  payment_method?: string | null;
+  initial_paid?: number | null;
+  payments?: InvoiceTender[] | null;
+  gst_rate?: number | null;
+  allow_over_limit?: boolean;
+  approved_by?: string | null;
+  price_override_reason?: string | null;
+  created_by?: string | null;
+}
+
+export interface DeliveryChallanItem {
   id: number;
-  po_id: number | null;
-  po_number: string | null;
   product_id: number;
   product_name: string;
-  sku: string;
+  sku: string | null;
   quantity: number;
-  unit_cost: number;
-  total_cost: number;
-  selling_price: number | null;
-  quantity_sold: number | null;
-  sold_revenue: number | null;
+  unit_price: number;
+  reservation_id: number | null; // Holds the line's stock while a 'reserve' challan is open
+}
+
+export interface DeliveryChallan {
+  id: number;
+  challan_number: string; // DC-YYYY-NNN
+  customer_id: number | null;
+  customer_name: string | null;
+  customer_phone: string | null;
+  status: ChallanStatus;
+  stock_mode: 'reserve' | 'deduct'; // From the challan_stock_mode setting when created
+  notes: string | null;
+  invoice_id: number | null;
+  invoice_number: string | null;
+  total_value: number;
+  created_by: string | null;
   created_at: string;
+  closed_at: string | null; // When invoiced or returned
+  items: DeliveryChallanItem[];
 }
 
-export interface PurchaseOrderComplete {
-  purchase_order: PurchaseOrder;
-  supplier: Supplier;
-  items: PurchaseOrderItemWithProduct[];
+/**
+ * Delivery Challan Commands (goods sent ahead of the invoice)
+ */
+export const challanCommands = {
+  create: async (input: CreateChallanInput): Promise<DeliveryChallan> => {
+    return await invoke<DeliveryChallan>('create_challan', { input });
+  },
+
+  getAll: async (status?: ChallanStatus, customerId?: number): Promise<DeliveryChallan[]> => {
+    return await invoke<DeliveryChallan[]>('get_challans', {
+      status: status ?? null,
+      customerId: customerId ?? null,
+    });
+  },
+
+  getById: async (id: number): Promise<DeliveryChallan> => {
+    return await invoke<DeliveryChallan>('get_challan', { id });
+  },
+
+  /**
+   * Bill an open challan; its held stock is what the invoice takes
+   */
+  convertToInvoice: async (input: ConvertChallanInput): Promise<Invoice> => {
+    return await invoke<Invoice>('convert_challan_to_invoice', { input });
+  },
+
+  /**
+   * Take the goods back, returning the challan's stock
+   */
+  return: async (id: number, returnedBy?: string): Promise<DeliveryChallan> => {
+    return await invoke<DeliveryChallan>('return_challan', { id, returnedBy: returnedBy ?? null });
+  },
+};
+
+/** Prefix of the error from invoice edits in a closed business day; the date follows it */
+export const DAY_CLOSED = 'DAY_CLOSED:';
+
+export interface DayClosure {
+  id: number;
+  business_date: string; // YYYY-MM-DD
+  invoice_count: number;
+  revenue: number;
+  payment_methods: PaymentMethodBreakdown[];
+  cash_sales: number;
+  credit_collections: number; // Cash received from credit customers
+  cash_expenses: number;
+  expected_cash: number;
+  counted_cash: number;
+  difference: number; // Counted minus expected
+  notes: string | null;
+  closed_by: string | null;
+  closed_at: string;
+}
+
+/**
+ * Day Closure Commands (Z-reports)
+ */
+export const dayClosureCommands = {
+  /**
+   * Close a business day; its invoices can't be edited afterwards without a manager override
+   */
+  close: async (date: string, countedCash: number, notes?: string, closedBy?: string): Promise<DayClosure> => {
+    return await invoke<DayClosure>('close_business_day', {
+      date,
+      countedCash,
+      notes: notes ?? null,
+      closedBy: closedBy ?? null,
+    });
+  },
+
+  get: async (date: string): Promise<DayClosure | null> => {
+    return await invoke<DayClosure | null>('get_day_closure', { date });
+  },
+
+  getHistory: async (page: number = 1, pageSize: number = 30): Promise<PaginatedResult<DayClosure>> => {
+    return await invoke<PaginatedResult<DayClosure>>('get_closure_history', { page, pageSize });
+  },
+};
+
+/**
+ * Search Commands
+ */
+export type ProductExportColumn =
+  | 'id'
+  | 'name'
+  | 'sku'
+  | 'category'
+  | 'supplier'
+  | 'price'
+  | 'selling_price'
+  | 'initial_stock'
+  | 'stock_quantity'
+  | 'total_sold'
+  | 'total_sold_amount'
+  | 'total_purchased_quantity'
+  | 'total_purchased_cost'
+  | 'stock_value'
+  | 'created_at'
+  | 'updated_at';
+
+export interface ProductExportOptions {
+  columns?: ProductExportColumn[]; // Output order; defaults to id, name, sku, prices, stock, supplier
+  sort?: { column: ProductExportColumn; descending?: boolean }[]; // Defaults to name
+  format?: 'csv' | 'xlsx';
+}
+
+export const searchCommands = {
+  /**
+   * OmniSearch: Search across all entities
+   */
+  omnisearch: async (query: string): Promise<SearchResult> => {
+    return await invoke<SearchResult>('omnisearch', { query });
+  },
+
+  /**
+   * Export products to CSV format
+   */
+  exportProductsCSV: async (): Promise<string> => {
+    return await invoke<string>('export_products_csv');
+  },
+
+  /**
+   * Export products with chosen columns and sort to a CSV or XLSX file; resolves to the row count.
+   * Progress arrives on "data-transfer-progress" like the other exports.
+   */
+  exportProducts: async (options: ProductExportOptions, filePath: string): Promise<number> => {
+    return await invoke<number>('export_products', { options, filePath });
+  },
+
+  /**
+   * Export customers to CSV format
+   */
+  exportCustomersCSV: async (): Promise<string> => {
+    return await invoke<string>('export_customers_csv');
+  },
+};
+
+// =============================================
+// PURCHASE ORDER TYPES
+// =============================================
+
+export interface PurchaseOrder {
+  id: number;
+  po_number: string;
+  supplier_id: number;
+  order_date: string;
+  expected_delivery_date: string | null;
+  received_date: string | null;
+  status: 'draft' | 'ordered' | 'partially_received' | 'received' | 'cancelled';
+  total_amount: number;
+  notes: string | null;
+  created_at: string;
+  updated_at: string;
+  currency: string;
+  exchange_rate: number;
+  foreign_total_amount: number;
+}
+
+export interface PurchaseOrderSend {
+  po_id: number;
+  po_number: string;
+  channel: 'whatsapp' | 'email';
+  contact: string;
+  file_path: string | null;
+}
+
+export interface PurchaseOrderWithDetails {
+  id: number;
+  po_number: string;
+  supplier_id: number;
+  supplier_name: string;
+  order_date: string;
+  expected_delivery_date: string | null;
+  received_date: string | null;
+  status: 'draft' | 'ordered' | 'partially_received' | 'received' | 'cancelled';
+  total_amount: number;
+  notes: string | null;
+  created_at: string;
+  updated_at: string;
+  items_count: number;
+  quantity?: number; // Quantity of specific product (context-dependent)
+  total_paid: number;
+  total_pending: number;
+}
+
+export interface PurchaseOrderItemWithProduct {
+  id: number;
+  po_id: number | null;
+  po_number: string | null;
+  product_id: number;
+  product_name: string;
+  sku: string;
+  quantity: number;
+  unit_cost: number;
+  total_cost: number;
+  selling_price: number | null;
+  quantity_sold: number | null;
+  sold_revenue: number | null;
+  created_at: string;
+  is_return: boolean;
+}
+
+export interface PurchaseOrderComplete {
+  purchase_order: PurchaseOrder;
+  supplier: Supplier;
+  items: PurchaseOrderItemWithProduct[];
   payments: SupplierPayment[];
   total_paid: number;
   total_pending: number;
@@ -1176,6 +2344,48 @@ export interface CreatePurchaseOrderInput {
   expected_delivery_date?: string | null;
   notes?: string | null;
   initial_payment?: number | null;
+  currency?: string | null;
+  exchange_rate?: number | null;
+}
+
+export interface PurchaseReturnItemInput {
+  product_id: number;
+  quantity: number;
+  unit_cost: number;
+}
+
+export interface CreatePurchaseReturnInput {
+  po_id?: number | null;
+  supplier_id?: number | null;
+  product_id?: number | null;
+  items: PurchaseReturnItemInput[];
+  reason: string;
+  reduces_payable: boolean;
+  return_date?: string | null;
+  created_by?: string | null;
+}
+
+export interface PurchaseReturnItem {
+  id: number;
+  product_id: number;
+  po_item_id: number | null;
+  quantity: number;
+  unit_cost: number;
+  total_cost: number;
+}
+
+export interface PurchaseReturn {
+  id: number;
+  return_number: string;
+  supplier_id: number;
+  po_id: number | null;
+  reason: string;
+  reduces_payable: boolean;
+  total_amount: number;
+  return_date: string;
+  created_by: string | null;
+  created_at: string;
+  items: PurchaseReturnItem[];
 }
 
 // =============================================
@@ -1197,10 +2407,14 @@ export interface InventoryBatchWithDetails {
   product_id: number;
   po_item_id: number | null;
   po_number: string | null;
+  source: 'purchase_order' | 'initial_stock';
+  /** null for batches written before original quantities were tracked */
+  original_quantity: number | null;
   quantity_remaining: number;
   unit_cost: number;
   batch_value: number;
   purchase_date: string;
+  age_days: number;
   created_at: string;
 }
 
@@ -1268,7 +2482,7 @@ export const purchaseOrderCommands = {
    */
   updateStatus: async (
     poId: number,
-    status: 'draft' | 'ordered' | 'received' | 'cancelled',
+    status: 'draft' | 'ordered' | 'partially_received' | 'received' | 'cancelled',
     receivedDate?: string | null
   ): Promise<PurchaseOrder> => {
     return await invoke<PurchaseOrder>('update_purchase_order_status', {
@@ -1287,6 +2501,7 @@ export const purchaseOrderCommands = {
     payment_method?: string | null;
     note?: string | null;
     paid_at?: string | null;
+    allocations?: PaymentAllocation[] | null;
   }): Promise<number> => {
     return await invoke<number>('add_payment_to_purchase_order', {
       poId: input.po_id,
@@ -1294,6 +2509,7 @@ export const purchaseOrderCommands = {
       paymentMethod: input.payment_method ?? null,
       note: input.note ?? null,
       paidAt: input.paid_at ?? null,
+      allocations: input.allocations ?? null,
     });
   },
 
@@ -1305,6 +2521,46 @@ export const purchaseOrderCommands = {
       productId,
     });
   },
+
+  /**
+   * Record goods returned to a supplier, against a PO or a product's opening stock
+   */
+  createReturn: async (input: CreatePurchaseReturnInput): Promise<PurchaseReturn> => {
+    return await invoke<PurchaseReturn>('create_purchase_return', { input });
+  },
+
+  /**
+   * Get purchase returns for a supplier, newest first
+   */
+  getReturns: async (supplierId: number): Promise<PurchaseReturn[]> => {
+    return await invoke<PurchaseReturn[]>('get_purchase_returns', { supplierId });
+  },
+
+  /**
+   * Save a generated PO PDF next to the database; returns the file path
+   */
+  savePdf: async (poId: number, pdfBytes: number[]): Promise<string> => {
+    return await invoke<string>('save_purchase_order_pdf', { poId, pdfBytes });
+  },
+
+  /**
+   * Record that a PO was sent to its supplier; contact defaults to the supplier's phone/email
+   */
+  markSent: async (
+    poId: number,
+    channel: 'whatsapp' | 'email',
+    contact?: string,
+    filePath?: string,
+    sentBy?: string
+  ): Promise<PurchaseOrderSend> => {
+    return await invoke<PurchaseOrderSend>('mark_purchase_order_sent', {
+      poId,
+      channel,
+      contact: contact ?? null,
+      filePath: filePath ?? null,
+      sentBy: sentBy ?? null,
+    });
+  },
 };
 
 // =============================================
@@ -1318,6 +2574,7 @@ export interface MigrationResult {
   transactions_created: number;
   errors: string[];
   details: string[];
+  resumed_after: number | null; // Last product committed by the interrupted run this one continued
 }
 
 export interface MigrationStatus {
@@ -1326,21 +2583,66 @@ export interface MigrationStatus {
   products_needing_migration: number;
   migration_supplier_exists: boolean;
   migration_required: boolean;
+  resume_after: number | null; // Set while an interrupted run is waiting to be continued
+}
+
+export type MigrationPhase = 'migrating' | 'validating' | 'repairing';
+
+/** Payload of the "product-migration-progress" event */
+export interface MigrationProgress {
+  phase: MigrationPhase;
+  processed: number;
+  total: number;
+  percentage: number;
 }
 
+export const MIGRATION_PROGRESS_EVENT = 'product-migration-progress';
+
+export type StockIssue = 'missing_batches' | 'batch_shortfall' | 'batch_excess';
+
 export interface InconsistentProduct {
   id: number;
   name: string;
   sku: string;
   stock_quantity: number;
+  expected_batch_total: number; // The stock quantity, 0 for negative stock
   batch_total: number;
-  difference: number;
+  batch_count: number;
+  difference: number; // expected_batch_total - batch_total
+  issue: StockIssue;
 }
 
 export interface ValidationResult {
   total_products_checked: number;
   consistent_products: number;
   inconsistent_products: InconsistentProduct[];
+  checked_at: string; // RFC 3339
+}
+
+export interface RepairedProduct {
+  id: number;
+  name: string;
+  issue: StockIssue;
+  batch_total_before: number;
+  batch_total_after: number;
+}
+
+export interface RepairResult {
+  repaired: RepairedProduct[];
+  already_consistent: number[];
+  errors: string[];
+}
+
+export interface AppliedMigration {
+  version: number;
+  description: string;
+  applied_at: string;
+}
+
+export interface SchemaVersionInfo {
+  current_version: number;
+  latest_version: number;
+  applied: AppliedMigration[];
 }
 
 /**
@@ -1350,10 +2652,14 @@ export interface ValidationResult {
 export const migrationCommands = {
   /**
    * Migrate existing products with stock to the new PO/FIFO system
-   * Creates migration POs and inventory batches for products with stock
+   * Creates migration POs and inventory batches for products with stock, chunkSize products
+   * per transaction. Continues an interrupted run unless restart is set.
    */
-  migrateExistingProducts: async (): Promise<MigrationResult> => {
-    return await invoke<MigrationResult>('migrate_existing_products');
+  migrateExistingProducts: async (options: { chunkSize?: number; restart?: boolean } = {}): Promise<MigrationResult> => {
+    return await invoke<MigrationResult>('migrate_existing_products', {
+      chunkSize: options.chunkSize ?? null,
+      restart: options.restart ?? false,
+    });
   },
 
   /**
@@ -1370,6 +2676,28 @@ export const migrationCommands = {
   validateMigration: async (): Promise<ValidationResult> => {
     return await invoke<ValidationResult>('validate_migration');
   },
+
+  /**
+   * Fix the batches of products from a validation report so they match stock quantities.
+   * Products already consistent again are left alone.
+   */
+  repairMigration: async (productIds: number[], repairedBy?: string | null): Promise<RepairResult> => {
+    return await invoke<RepairResult>('repair_migration', { productIds, repairedBy: repairedBy ?? null });
+  },
+
+  /**
+   * Subscribe to migration, validation and repair progress. Returns the unlisten function.
+   */
+  onProgress: async (handler: (progress: MigrationProgress) => void): Promise<UnlistenFn> => {
+    return await listen<MigrationProgress>(MIGRATION_PROGRESS_EVENT, (event) => handler(event.payload));
+  },
+
+  /**
+   * Get the database schema version and applied migrations
+   */
+  getSchemaVersion: async (): Promise<SchemaVersionInfo> => {
+    return await invoke<SchemaVersionInfo>('get_schema_version');
+  },
 };
 
 // =============================================
@@ -1381,6 +2709,36 @@ export interface GoogleImageResult {
   link: string;           // Full-size image URL
   thumbnail_link: string; // Small preview from Google
   display_link: string;   // Source website
+  provider: string;       // "google", "bing" or "searxng"
+}
+
+export interface ImageSearchProviderStatus {
+  provider: string;
+  enabled: boolean;
+  configured: boolean;
+  key_in_keyring: boolean;
+}
+
+export interface ImageOrphanReport {
+  unreferenced_files: { path: string; bytes: number }[];
+  dangling_paths: { entity_type: string; entity_id: number; entity_name: string; image_path: string }[];
+  reclaimable_bytes: number;
+}
+
+export interface ImageCompressionReport {
+  files_checked: number;
+  files_compressed: number;
+  bytes_before: number;
+  bytes_after: number;
+  bytes_saved: number;
+  errors: string[];
+}
+
+export interface ImageCleanupResult {
+  files_moved: number;
+  bytes_moved: number;
+  paths_cleared: number;
+  trash_folder: string | null;
 }
 
 /**
@@ -1459,6 +2817,20 @@ export const imageCommands = {
     });
   },
 
+  /**
+   * Which image search providers are enabled and configured
+   */
+  getImageSearchStatus: async (): Promise<ImageSearchProviderStatus[]> => {
+    return await invoke<ImageSearchProviderStatus[]>('get_image_search_status');
+  },
+
+  /**
+   * Save an image search provider's API key in the system keychain (empty string removes it)
+   */
+  setImageSearchKey: async (provider: string, apiKey: string): Promise<void> => {
+    return await invoke<void>('set_image_search_key', { provider, apiKey });
+  },
+
   /**
    * Get the pictures directory path
    */
@@ -1473,6 +2845,27 @@ export const imageCommands = {
     return await invoke<string>('migrate_images');
   },
 
+  /**
+   * Find image files nothing references and image paths whose files are missing
+   */
+  scanImageOrphans: async (): Promise<ImageOrphanReport> => {
+    return await invoke<ImageOrphanReport>('scan_image_orphans');
+  },
+
+  /**
+   * Move unreferenced image files to the pictures .trash folder and clear dangling paths
+   */
+  cleanupImageOrphans: async (): Promise<ImageCleanupResult> => {
+    return await invoke<ImageCleanupResult>('cleanup_image_orphans');
+  },
+
+  /**
+   * Shrink existing images to the image_max_dimension / image_quality settings
+   */
+  compressExistingImages: async (): Promise<ImageCompressionReport> => {
+    return await invoke<ImageCompressionReport>('migrate_compress_existing_images');
+  },
+
   /**
    * Save a cropped image
    */
@@ -1515,7 +2908,7 @@ export const biometricCommands = {
   },
 
   /**
-   * Verify a token (used for login)
+   * Verify a token (used for login); fails for expired tokens or tokens from another device
    */
   verifyToken: async (token: string): Promise<User | null> => {
     // Note: Rust returns User or Error. We might want to catch error here?
@@ -1538,14 +2931,14 @@ export const biometricCommands = {
   },
 
   /**
-   * Check if biometric is enabled for a username (for login screen)
+   * Check if biometric is enabled for a username (needs a logged-in session)
    */
   getStatusByUsername: async (username: string): Promise<boolean> => {
     return await invoke<boolean>('get_biometric_status_by_username', { username });
   },
 
   /**
-   * Check if ANY user has enrolled (to show/hide UI elements)
+   * Check if ANY user has a valid enrollment on this device (to show/hide UI elements)
    */
   hasAnyEnrollment: async (): Promise<boolean> => {
     return await invoke<boolean>('has_any_biometric_enrollment');
@@ -1556,10 +2949,92 @@ export const biometricCommands = {
 // SETTINGS COMMANDS
 // =============================================
 
+export interface SettingImportEntry {
+  key: string;
+  status: 'will_import' | 'imported' | 'skipped_unknown' | 'not_importable' | 'invalid_value';
+  reason: string | null;
+  secret: boolean;
+}
+
+export interface SettingsImportReport {
+  applied: boolean;
+  importable_count: number;
+  skipped_count: number;
+  invalid_count: number;
+  entries: SettingImportEntry[];
+}
+
 /**
  * Settings Commands
  * For managing app settings like Google API credentials
  */
+export interface BillingDefaults {
+  gst_rate: number | null;
+  payment_method: string;
+  state: string | null;
+  district: string | null;
+  town: string | null;
+  home_state: string | null;
+  credit_period_days: number;
+  round_off: 'nearest' | 'down' | 'off';
+}
+
+export type WebhookDeliveryStatus = 'pending' | 'delivered' | 'failed';
+
+export interface WebhookDelivery {
+  id: number;
+  /** "invoice.created", "invoice.deleted" or "payment.created" */
+  event_type: string;
+  entity_id: number | null;
+  /** The JSON body sent */
+  payload: string;
+  status: WebhookDeliveryStatus;
+  attempts: number;
+  next_attempt_at: string;
+  last_attempt_at: string | null;
+  /** HTTP status of the last attempt; null when it got no response */
+  response_code: number | null;
+  last_error: string | null;
+  created_at: string;
+  delivered_at: string | null;
+}
+
+export interface IntegrationStatus {
+  /** integration_enabled is set */
+  enabled: boolean;
+  running: boolean;
+  port: number;
+  /** "127.0.0.1:<port>" while running */
+  address: string | null;
+  token_configured: boolean;
+  /** Why the listener last failed to start */
+  last_error: string | null;
+}
+
+export type CostingMethod = 'fifo' | 'weighted_average';
+
+export interface CostingProductValue {
+  product_id: number;
+  name: string;
+  /** Units left in batches */
+  quantity: number;
+  unit_cost_before: number;
+  value_before: number;
+  unit_cost_after: number;
+  value_after: number;
+}
+
+export interface CostingMethodChange {
+  id: number;
+  from_method: CostingMethod;
+  to_method: CostingMethod;
+  value_before: number;
+  value_after: number;
+  products: CostingProductValue[];
+  changed_by: string | null;
+  changed_at: string;
+}
+
 export const settingsCommands = {
   /**
    * Get a single setting value by key
@@ -1587,6 +3062,68 @@ export const settingsCommands = {
     return await invoke<Record<string, string>>('get_all_settings');
   },
 
+  /**
+   * Defaults create_invoice applies when the input leaves a field out
+   */
+  getBillingDefaults: async (): Promise<BillingDefaults> => {
+    return await invoke<BillingDefaults>('get_billing_defaults');
+  },
+
+  /**
+   * Validate and save billing defaults; returns the saved values
+   */
+  setBillingDefaults: async (defaults: BillingDefaults): Promise<BillingDefaults> => {
+    return await invoke<BillingDefaults>('set_billing_defaults', { defaults });
+  },
+
+  /**
+   * Whether the local integration listener is running, and on which port
+   */
+  getIntegrationStatus: async (): Promise<IntegrationStatus> => {
+    return await invoke<IntegrationStatus>('get_integration_status');
+  },
+
+  /**
+   * Save the integration listener settings and start or stop it to match
+   * @param token - Bearer token (at least 16 characters); keeps the stored one when left out
+   */
+  setIntegrationSettings: async (enabled: boolean, port?: number, token?: string): Promise<IntegrationStatus> => {
+    return await invoke<IntegrationStatus>('set_integration_settings', { enabled, port, token });
+  },
+
+  /**
+   * Outbound webhook deliveries, newest first
+   */
+  getWebhookDeliveries: async (
+    page: number,
+    pageSize: number,
+    status?: WebhookDeliveryStatus
+  ): Promise<PaginatedResult<WebhookDelivery>> => {
+    return await invoke<PaginatedResult<WebhookDelivery>>('get_webhook_deliveries', { page, pageSize, status });
+  },
+
+  /**
+   * Send a failed or pending webhook delivery again now
+   */
+  retryWebhookDelivery: async (id: number): Promise<WebhookDelivery> => {
+    return await invoke<WebhookDelivery>('retry_webhook_delivery', { id });
+  },
+
+  /**
+   * Active costing method for stock and cost of goods sold
+   */
+  getCostingMethod: async (): Promise<CostingMethod> => {
+    return await invoke<CostingMethod>('get_costing_method');
+  },
+
+  /**
+   * Switch the costing method; snapshots and logs the stock values it changes.
+   * costing_method can't be written with set()
+   */
+  convertCostingMethod: async (method: CostingMethod, convertedBy?: string): Promise<CostingMethodChange> => {
+    return await invoke<CostingMethodChange>('convert_costing_method', { method, convertedBy });
+  },
+
   /**
    * Delete a setting by key
    * @param key - Setting key to delete
@@ -1597,26 +3134,50 @@ export const settingsCommands = {
 
   /**
    * Export all settings as JSON string
+   * @param includeUserPreferences - Also export every user's preferences under "user_preferences"
    */
-  exportJson: async (): Promise<string> => {
-    return await invoke<string>('export_settings_json');
+  exportJson: async (includeUserPreferences = false): Promise<string> => {
+    return await invoke<string>('export_settings_json', { includeUserPreferences });
   },
 
   /**
    * Import settings from JSON string
    * @param jsonContent - The JSON string to import
-   * @returns Number of settings imported
-   */
-  importJson: async (jsonContent: string): Promise<number> => {
-    return await invoke<number>('import_settings_json', { jsonContent });
+   * @param confirm - Write the settings; otherwise only validate (dry run)
+   * @param allowUnknown - Keep keys that are not known settings
+   * @returns Per-key validation report
+   */
+  importJson: async (
+    jsonContent: string,
+    confirm = false,
+    allowUnknown = false
+  ): Promise<SettingsImportReport> => {
+    return await invoke<SettingsImportReport>('import_settings_json', { jsonContent, confirm, allowUnknown });
+  },
+
+  /**
+   * Get entity modifications (audit trail), newest first
+   * @param filters - Entity, user and date range filters
+   * @param page - 1-based page (default 1)
+   * @param pageSize - Rows per page (default 100)
+   */
+  getAllModifications: async (
+    filters?: ModificationFilters,
+    page?: number,
+    pageSize?: number
+  ): Promise<PaginatedResult<EntityModification>> => {
+    return await invoke<PaginatedResult<EntityModification>>('get_all_modifications', {
+      filters: filters ?? null,
+      page: page ?? null,
+      pageSize: pageSize ?? null,
+    });
   },
 
   /**
-   * Get all entity modifications (audit trail)
-   * @returns List of all modifications across all entity types
+   * One entity's history, oldest first: edits, trash entries and invoice edits
    */
-  getAllModifications: async (): Promise<EntityModification[]> => {
-    return await invoke<EntityModification[]>('get_all_modifications');
+  getEntityTimeline: async (entityType: string, entityId: number): Promise<TimelineEntry[]> => {
+    return await invoke<TimelineEntry[]>('get_entity_timeline', { entityType, entityId });
   },
 
   /**
@@ -1636,10 +3197,481 @@ export const settingsCommands = {
   },
 
   /**
-   * Clear all modification history (Master Admin only)
-   * @returns Number of records deleted
+   * Clear modification history (Master Admin only). Without filters everything is deleted.
+   * @param options.olderThanDays - Only delete records older than this many days
+   * @param options.entityType - Only delete records of this entity type
+   * @param options.dryRun - Count the records that would be deleted without deleting them
+   * @param options.clearedBy - Username recorded on the purge's activity log entry
+   * @returns Number of records deleted (or that would be, for a dry run)
+   */
+  clearModificationsHistory: async (options: {
+    olderThanDays?: number | null;
+    entityType?: string | null;
+    dryRun?: boolean;
+    clearedBy?: string | null;
+  } = {}): Promise<number> => {
+    return await invoke<number>('clear_modifications_history', {
+      olderThanDays: options.olderThanDays ?? null,
+      entityType: options.entityType ?? null,
+      dryRun: options.dryRun ?? false,
+      clearedBy: options.clearedBy ?? null,
+    });
+  },
+};
+
+/**
+ * User Preference Commands
+ * Per-user UI state (column layouts, filters) stored server-side. Values are any JSON up to 64 KB.
+ */
+export const userPreferenceCommands = {
+  /**
+   * Get one preference; null when it has never been set
+   */
+  get: async <T = unknown>(username: string, key: string): Promise<T | null> => {
+    return await invoke<T | null>('get_user_preference', { username, key });
+  },
+
+  /**
+   * Save a preference (replaces any previous value)
+   */
+  set: async (username: string, key: string, value: unknown): Promise<void> => {
+    return await invoke<void>('set_user_preference', { username, key, jsonValue: JSON.stringify(value) });
+  },
+
+  /**
+   * Every preference the user has set, for loading at app start
+   */
+  getAll: async (username: string): Promise<Record<string, unknown>> => {
+    return await invoke<Record<string, unknown>>('get_all_user_preferences', { username });
+  },
+};
+
+/**
+ * Price Tier Commands
+ */
+export const priceTierCommands = {
+  getAll: async (): Promise<PriceTier[]> => {
+    return await invoke<PriceTier[]>('get_price_tiers');
+  },
+
+  create: async (input: PriceTierInput): Promise<PriceTier> => {
+    return await invoke<PriceTier>('create_price_tier', { input });
+  },
+
+  update: async (id: number, input: PriceTierInput): Promise<PriceTier> => {
+    return await invoke<PriceTier>('update_price_tier', { id, input });
+  },
+
+  delete: async (id: number): Promise<void> => {
+    return await invoke<void>('delete_price_tier', { id });
+  },
+
+  /**
+   * Set a per-product price for a tier; pass null to fall back to the tier discount
+   */
+  setOverride: async (tierId: number, productId: number, price: number | null): Promise<void> => {
+    return await invoke<void>('set_price_tier_override', { tierId, productId, price });
+  },
+
+  setCustomerTier: async (customerId: number, tierId: number | null, modifiedBy?: string): Promise<void> => {
+    return await invoke<void>('set_customer_price_tier', { customerId, tierId, modifiedBy: modifiedBy ?? null });
+  },
+
+  /**
+   * Unit price to prefill on the billing screen for a product and customer
+   */
+  getEffectivePrice: async (productId: number, customerId: number | null): Promise<EffectivePrice> => {
+    return await invoke<EffectivePrice>('get_effective_price', { productId, customerId });
+  },
+
+  getPriceList: async (tierId: number): Promise<PriceListEntry[]> => {
+    return await invoke<PriceListEntry[]>('get_price_list', { tierId });
+  },
+
+  /**
+   * Lines billed under their list or tier price over a date range (YYYY-MM-DD, inclusive)
+   */
+  getPriceOverrides: async (startDate: string, endDate: string, cashier?: string): Promise<PriceOverrideReport> => {
+    return await invoke<PriceOverrideReport>('get_price_overrides', { startDate, endDate, cashier: cashier ?? null });
+  },
+};
+
+export interface BundleComponent {
+  component_id: number;
+  name: string;
+  sku: string;
+  quantity: number;
+  stock_quantity: number;
+}
+
+export interface BundleDetails {
+  product_id: number;
+  components: BundleComponent[];
+  max_assemblable: number;
+}
+
+export interface BundleComponentUsage {
+  component_id: number;
+  name: string;
+  sku: string;
+  quantity_consumed: number;
+  bundles_sold: number;
+  stock_quantity: number;
+}
+
+/**
+ * Bundle Commands
+ */
+export const bundleCommands = {
+  get: async (productId: number): Promise<BundleDetails> => {
+    return await invoke<BundleDetails>('get_bundle', { productId });
+  },
+
+  /**
+   * Set a bundle's components; an empty list turns the product back into a regular product
+   */
+  setComponents: async (
+    productId: number,
+    components: { component_id: number; quantity: number }[]
+  ): Promise<BundleDetails> => {
+    return await invoke<BundleDetails>('set_bundle_components', { productId, components });
+  },
+
+  getComponentUsage: async (startDate: string, endDate: string): Promise<BundleComponentUsage[]> => {
+    return await invoke<BundleComponentUsage[]>('get_bundle_component_usage', { startDate, endDate });
+  },
+};
+
+export interface DemoDataOptions {
+  products?: number;
+  suppliers?: number;
+  customers?: number;
+  purchase_orders?: number;
+  invoices?: number;
+  months?: number;
+  seed?: number;
+}
+
+export interface DemoDataCounts {
+  suppliers: number;
+  customers: number;
+  products: number;
+  categories: number;
+  purchase_orders: number;
+  invoices: number;
+  customer_payments: number;
+}
+
+export interface ClearDemoDataResult {
+  removed: DemoDataCounts;
+  kept: number;
+}
+
+/**
+ * Demo Data Commands
+ */
+export const demoDataCommands = {
+  /**
+   * Generate linked demo suppliers, customers, products, purchase orders and invoices
+   */
+  generate: async (options?: DemoDataOptions): Promise<DemoDataCounts> => {
+    return await invoke<DemoDataCounts>('generate_demo_data', { options: options ?? null });
+  },
+
+  /**
+   * Remove generated demo rows; rows real data depends on are kept
+   */
+  clear: async (): Promise<ClearDemoDataResult> => {
+    return await invoke<ClearDemoDataResult>('clear_demo_data');
+  },
+};
+
+export type DataEntity = 'invoice' | 'product' | 'customer' | 'supplier' | 'purchase_order';
+export type DataOperation = 'created' | 'updated' | 'deleted' | 'restored' | 'imported';
+
+/**
+ * Payload of the "data-changed" event emitted after a command commits a change.
+ * id is null for batch operations (imports, bulk creates); count is the number of records changed.
+ */
+export interface DataChanged {
+  entity: DataEntity;
+  operation: DataOperation;
+  id: number | null;
+  count: number;
+}
+
+export const DATA_CHANGED_EVENT = 'data-changed';
+
+/**
+ * Subscribe to data-changed events, optionally only for the given entities.
+ * Returns the unlisten function.
+ */
+export const onDataChanged = async (
+  handler: (change: DataChanged) => void,
+  entities?: DataEntity[]
+): Promise<UnlistenFn> => {
+  return await listen<DataChanged>(DATA_CHANGED_EVENT, (event) => {
+    if (!entities || entities.includes(event.payload.entity)) {
+      handler(event.payload);
+    }
+  });
+};
+
+export interface LowStockItem {
+  product_id: number;
+  name: string;
+  sku: string;
+  stock_quantity: number;
+  reorder_level: number; // The product's own, or the low_stock_threshold setting
+  on_order_quantity: number;
+}
+
+/**
+ * Payload of the "stock-alert" event emitted after an invoice leaves products below their
+ * reorder level. Each product alerts at most once per stock_alert_cooldown_hours.
+ */
+export interface StockAlert {
+  invoice_id: number;
+  products: LowStockItem[];
+}
+
+export const STOCK_ALERT_EVENT = 'stock-alert';
+
+/**
+ * Subscribe to stock-alert events. Returns the unlisten function.
+ */
+export const onStockAlert = async (handler: (alert: StockAlert) => void): Promise<UnlistenFn> => {
+  return await listen<StockAlert>(STOCK_ALERT_EVENT, (event) => handler(event.payload));
+};
+
+export interface StockDigest {
+  id: number;
+  digest_date: string; // YYYY-MM-DD
+  low_stock_count: number;
+  out_of_stock_count: number;
+  products: LowStockItem[];
+  created_at: string;
+}
+
+/**
+ * Stock Alert Commands (reorder levels and the daily low-stock digest)
+ */
+export const stockAlertCommands = {
+  /**
+   * null falls back to the low_stock_threshold setting
+   */
+  setReorderLevel: async (productId: number, reorderLevel: number | null): Promise<void> => {
+    return await invoke<void>('set_product_reorder_level', { productId, reorderLevel });
+  },
+
+  /**
+   * Regenerate today's digest (it is also generated on startup)
+   */
+  generateDigest: async (): Promise<StockDigest> => {
+    return await invoke<StockDigest>('generate_daily_stock_digest');
+  },
+
+  getDigests: async (limit?: number): Promise<StockDigest[]> => {
+    return await invoke<StockDigest[]>('get_stock_digests', { limit: limit ?? null });
+  },
+
+  /**
+   * Render a digest with a share template (default key "stock_digest") for WhatsApp or email
+   */
+  renderDigestMessage: async (digestId: number, templateKey?: string): Promise<string> => {
+    return await invoke<string>('render_stock_digest_message', { digestId, templateKey: templateKey ?? null });
+  },
+};
+
+/**
+ * Share Commands (invoice messages for WhatsApp; templates are saved as share_template_<key> settings)
+ */
+export const shareCommands = {
+  /**
+   * Saved templates keyed by template name, without the share_template_ prefix
+   */
+  getTemplates: async (): Promise<Record<string, string>> => {
+    return await invoke<Record<string, string>>('get_share_templates');
+  },
+
+  /**
+   * Fill a template (default key "default") with the invoice's {name}, {invoice_number}, {total}, {balance}, ...
+   */
+  renderMessage: async (invoiceId: number, templateKey?: string): Promise<string> => {
+    return await invoke<string>('render_share_message', { invoiceId, templateKey: templateKey ?? null });
+  },
+
+  /**
+   * Open a wa.me chat in the browser; 10-digit numbers get the 91 country code. Returns the URL opened.
+   */
+  openWhatsAppChat: async (phone: string, message?: string): Promise<string> => {
+    return await invoke<string>('open_whatsapp_chat', { phone, message: message ?? null });
+  },
+};
+
+export interface IntegrityOffender {
+  table: string;
+  id: number;
+  detail: string;
+}
+
+export interface IntegrityCheck {
+  key: 'batch_stock' | 'invoice_totals' | 'customer_payments' | 'supplier_payments' | 'orphan_rows' | 'sequences';
+  description: string;
+  passed: boolean;
+  offending_count: number;
+  offenders: IntegrityOffender[]; // The first 100 offending rows
+}
+
+export interface IntegrityReport {
+  id: number;
+  source: 'manual' | 'scheduled';
+  business_date: string; // YYYY-MM-DD
+  passed: boolean;
+  failed_count: number;
+  checks: IntegrityCheck[];
+  duration_ms: number;
+  created_at: string;
+}
+
+/**
+ * Payload of the "integrity-warning" event emitted when an integrity run has failed checks
+ */
+export interface IntegrityWarning {
+  report_id: number;
+  source: 'manual' | 'scheduled';
+  failed_checks: string[];
+}
+
+export const INTEGRITY_WARNING_EVENT = 'integrity-warning';
+
+/**
+ * Subscribe to integrity-warning events. Returns the unlisten function.
+ */
+export const onIntegrityWarning = async (handler: (warning: IntegrityWarning) => void): Promise<UnlistenFn> => {
+  return await listen<IntegrityWarning>(INTEGRITY_WARNING_EVENT, (event) => handler(event.payload));
+};
+
+/**
+ * Data Integrity Commands (also run nightly at backup_time when integrity_checks_nightly is on)
+ */
+export const integrityCommands = {
+  run: async (): Promise<IntegrityReport> => {
+    return await invoke<IntegrityReport>('run_integrity_checks');
+  },
+
+  getLatest: async (): Promise<IntegrityReport | null> => {
+    return await invoke<IntegrityReport | null>('get_latest_integrity_report');
+  },
+};
+
+export type MaintenanceOperation = 'restore_backup' | 'csv_import' | 'database_maintenance';
+
+export interface MaintenanceStatus {
+  locked: boolean;
+  operation: MaintenanceOperation | null;
+  started_at: string | null;
+}
+
+export interface TableRowCount {
+  table: string;
+  rows: number;
+}
+
+export interface DatabaseSnapshot {
+  file_path: string;
+  checksum: string;
+  schema_version: number;
+  created_at: string;
+  size_bytes: number;
+  tables: TableRowCount[];
+}
+
+export interface SnapshotImportResult {
+  checksum: string;
+  snapshot_schema_version: number;
+  snapshot_created_at: string;
+  tables: TableRowCount[];
+  total_rows: number;
+}
+
+export interface SafetySnapshot {
+  file_name: string;
+  file_path: string;
+  /** Command or event the snapshot was taken before */
+  operation: string;
+  created_at: string;
+  size_bytes: number;
+}
+
+export interface CommandStats {
+  command: string;
+  count: number;
+  /** Invocations rejected before running (unknown command, maintenance lock) */
+  errors: number;
+  error_rate: number;
+  p50_ms: number;
+  p95_ms: number;
+  max_ms: number;
+  total_ms: number;
+}
+
+export interface PerformanceMetrics {
+  since: string;
+  slow_command_threshold_ms: number;
+  /** Slowest p95 first */
+  commands: CommandStats[];
+}
+
+/**
+ * Prefix of the error writes fail with while the maintenance lock is held;
+ * the rest of the message is the MaintenanceOperation holding it
+ */
+export const MAINTENANCE_IN_PROGRESS = 'MAINTENANCE_IN_PROGRESS:';
+
+/**
+ * Maintenance Commands
+ */
+export const maintenanceCommands = {
+  /**
+   * Whether a restore, import or maintenance run currently blocks writes
+   */
+  getStatus: async (): Promise<MaintenanceStatus> => {
+    return await invoke<MaintenanceStatus>('get_maintenance_status');
+  },
+
+  /**
+   * Write the whole database to one self-contained snapshot file
+   */
+  exportSnapshot: async (filePath: string): Promise<DatabaseSnapshot> => {
+    return await invoke<DatabaseSnapshot>('export_database_snapshot', { filePath });
+  },
+
+  /**
+   * Verify a snapshot file and replace all current data with it
+   */
+  importSnapshot: async (filePath: string): Promise<SnapshotImportResult> => {
+    return await invoke<SnapshotImportResult>('import_database_snapshot', { filePath });
+  },
+
+  /**
+   * Automatic snapshots taken before destructive operations, newest first
+   */
+  listSafetySnapshots: async (): Promise<SafetySnapshot[]> => {
+    return await invoke<SafetySnapshot[]>('list_safety_snapshots');
+  },
+
+  /**
+   * Replace all current data with a safety snapshot (by file name)
+   */
+  restoreSafetySnapshot: async (fileName: string): Promise<SnapshotImportResult> => {
+    return await invoke<SnapshotImportResult>('restore_safety_snapshot', { fileName });
+  },
+
+  /**
+   * Per-command timings since app start; pass reset to clear them afterwards
    */
-  clearModificationsHistory: async (): Promise<number> => {
-    return await invoke<number>('clear_modifications_history');
+  getPerformanceMetrics: async (reset = false): Promise<PerformanceMetrics> => {
+    return await invoke<PerformanceMetrics>('get_performance_metrics', { reset });
   },
 };
@@ -1,14 +1,19 @@
 use tauri::Manager;
 use tauri::Emitter;
 use std::sync::Mutex;
-use std::path::PathBuf;
-use std::io::Write;
-use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::io::{Read, Write};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::process::{Child, Command, Stdio};
 
 /// Base GitHub release URL for sidecar downloads
 const SIDECAR_RELEASE_BASE: &str = "https://github.com/zubair78600/inventory_tauri/releases/download/v1.0.5";
 
+/// Release asset listing the SHA-256 (and size) of each platform binary
+const SIDECAR_MANIFEST_NAME: &str = "sidecar-manifest.json";
+
 /// Get platform-specific binary name
 fn get_sidecar_binary_name() -> &'static str {
     #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
@@ -55,6 +60,9 @@ impl Default for AiSidecarState {
 pub struct SidecarDownloadProgress {
     pub downloaded_bytes: u64,
     pub total_bytes: u64,
+    /// Same as downloaded_bytes/total_bytes, matching the data transfer progress events
+    pub processed: u64,
+    pub total: u64,
     pub percentage: f32,
     pub speed_mbps: f32,
 }
@@ -73,17 +81,36 @@ fn get_sidecar_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     { Ok(bin_dir.join("db-ai-server")) }
 }
 
-/// Check if AI sidecar is downloaded
+/// Check that the AI sidecar is downloaded and matches the checksum it was verified against.
+/// A binary that fails the check is deleted so the next download starts clean.
 #[tauri::command]
 pub async fn check_sidecar_downloaded(app: tauri::AppHandle) -> Result<bool, String> {
     let path = get_sidecar_path(&app)?;
-    Ok(path.exists())
+
+    tauri::async_runtime::spawn_blocking(move || {
+        if !path.exists() {
+            return Ok(false);
+        }
+        if verify_sidecar(&path)? {
+            return Ok(true);
+        }
+
+        log::warn!("AI sidecar at {:?} failed checksum verification, removing it", path);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(checksum_path(&path));
+        Ok(false)
+    })
+    .await
+    .map_err(|e| format!("Failed to verify sidecar: {}", e))?
 }
 
-/// Download the AI sidecar binary with progress events
+/// Download the AI sidecar binary with progress events.
+/// Bytes go to a `.part` file that later calls resume with an HTTP Range request; the file is
+/// only moved into place once its SHA-256 matches the release manifest.
 #[tauri::command]
 pub async fn download_ai_sidecar(app: tauri::AppHandle) -> Result<(), String> {
     let sidecar_path = get_sidecar_path(&app)?;
+    let part_path = sidecar_path.with_extension("part");
     
     // Create directory if needed
     if let Some(parent) = sidecar_path.parent() {
@@ -93,59 +120,107 @@ pub async fn download_ai_sidecar(app: tauri::AppHandle) -> Result<(), String> {
     
     log::info!("Downloading AI sidecar to: {:?}", sidecar_path);
     
+    let client = reqwest::Client::new();
+    let entry = fetch_sidecar_manifest_entry(&client).await?;
+
     // Download with progress
     let download_url = get_sidecar_download_url();
     log::info!("Download URL: {}", download_url);
-    
-    let client = reqwest::Client::new();
-    let response = client.get(&download_url)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to start download: {}", e))?;
-    
-    if !response.status().is_success() {
-        return Err(format!("Download failed with status: {}. Is the repo public?", response.status()));
+
+    let mut downloaded = std::fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+    if entry.size > 0 && downloaded > entry.size {
+        // Leftover from a different release
+        downloaded = 0;
     }
-    
-    let total_size = response.content_length().unwrap_or(0);
-    let mut downloaded: u64 = 0;
-    let mut file = std::fs::File::create(&sidecar_path)
-        .map_err(|e| format!("Failed to create file: {}", e))?;
-    
-    let start_time = std::time::Instant::now();
-    let mut last_emit_time = start_time;
-    let mut last_downloaded: u64 = 0;
-    
-    let mut stream = response.bytes_stream();
-    use futures_util::StreamExt;
-    
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result.map_err(|e| format!("Download error: {}", e))?;
-        file.write_all(&chunk).map_err(|e| format!("Write error: {}", e))?;
-        downloaded += chunk.len() as u64;
-        
-        // Emit progress every 500ms
-        let now = std::time::Instant::now();
-        if now.duration_since(last_emit_time).as_millis() >= 500 {
-            let elapsed = now.duration_since(last_emit_time).as_secs_f32();
-            let bytes_since = downloaded - last_downloaded;
-            let speed_mbps = (bytes_since as f32 / elapsed) / (1024.0 * 1024.0);
-            
-            let progress = SidecarDownloadProgress {
-                downloaded_bytes: downloaded,
-                total_bytes: total_size,
-                percentage: if total_size > 0 { (downloaded as f32 / total_size as f32) * 100.0 } else { 0.0 },
-                speed_mbps,
-            };
-            
-            let _ = app.emit("sidecar-download-progress", progress);
-            last_emit_time = now;
-            last_downloaded = downloaded;
+
+    if entry.size == 0 || downloaded < entry.size {
+        let mut request = client.get(&download_url);
+        if downloaded > 0 {
+            log::info!("Resuming AI sidecar download from byte {}", downloaded);
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", downloaded));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to start download: {}", e))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+            // The part file already holds the whole binary; the checksum below decides
+            log::info!("Server reports the partial download is already complete");
+        } else {
+            if !status.is_success() {
+                return Err(format!("Download failed with status: {}. Is the repo public?", status));
+            }
+            if status != reqwest::StatusCode::PARTIAL_CONTENT {
+                // Server ignored the Range header and is sending the whole file
+                downloaded = 0;
+            }
+
+            let total_size = response
+                .content_length()
+                .map(|len| len + downloaded)
+                .unwrap_or(entry.size);
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(downloaded > 0)
+                .write(true)
+                .truncate(downloaded == 0)
+                .open(&part_path)
+                .map_err(|e| format!("Failed to create file: {}", e))?;
+
+            let start_time = std::time::Instant::now();
+            let mut last_emit_time = start_time;
+            let mut last_downloaded = downloaded;
+
+            let mut stream = response.bytes_stream();
+            use futures_util::StreamExt;
+
+            while let Some(chunk_result) = stream.next().await {
+                let chunk = chunk_result.map_err(|e| format!("Download interrupted, it will resume on retry: {}", e))?;
+                file.write_all(&chunk).map_err(|e| format!("Write error: {}", e))?;
+                downloaded += chunk.len() as u64;
+
+                // Emit progress every 500ms
+                let now = std::time::Instant::now();
+                if now.duration_since(last_emit_time).as_millis() >= 500 {
+                    let elapsed = now.duration_since(last_emit_time).as_secs_f32();
+                    let bytes_since = downloaded - last_downloaded;
+                    let speed_mbps = (bytes_since as f32 / elapsed) / (1024.0 * 1024.0);
+
+                    emit_download_progress(&app, downloaded, total_size, speed_mbps);
+                    last_emit_time = now;
+                    last_downloaded = downloaded;
+                }
+            }
+
+            file.flush().map_err(|e| format!("Write error: {}", e))?;
+            drop(file);
+            emit_download_progress(&app, downloaded, total_size.max(downloaded), 0.0);
         }
     }
-    
-    drop(file);
-    
+
+    // Verify before the binary can ever be started
+    let actual = {
+        let part_path = part_path.clone();
+        tauri::async_runtime::spawn_blocking(move || sha256_file(&part_path))
+            .await
+            .map_err(|e| format!("Failed to verify download: {}", e))??
+    };
+    if !actual.eq_ignore_ascii_case(&entry.sha256) {
+        let _ = std::fs::remove_file(&part_path);
+        return Err(format!(
+            "Checksum mismatch for downloaded AI sidecar (expected {}, got {}). The file was removed, please download again.",
+            entry.sha256, actual
+        ));
+    }
+
+    std::fs::rename(&part_path, &sidecar_path)
+        .map_err(|e| format!("Failed to move sidecar into place: {}", e))?;
+    std::fs::write(checksum_path(&sidecar_path), entry.sha256.to_lowercase())
+        .map_err(|e| format!("Failed to save sidecar checksum: {}", e))?;
+
     // Make executable on Unix
     #[cfg(unix)]
     {
@@ -154,10 +229,93 @@ pub async fn download_ai_sidecar(app: tauri::AppHandle) -> Result<(), String> {
             .map_err(|e| format!("Failed to set permissions: {}", e))?;
     }
     
-    log::info!("AI sidecar downloaded successfully");
+    log::info!("AI sidecar downloaded and verified successfully");
     Ok(())
 }
 
+/// Checksum and size of one platform binary in the release manifest
+#[derive(Debug, Deserialize)]
+struct SidecarManifestEntry {
+    sha256: String,
+    /// Expected size in bytes, 0 when the manifest doesn't say
+    #[serde(default)]
+    size: u64,
+}
+
+/// Fetch the release manifest and pick the entry for this platform's binary
+async fn fetch_sidecar_manifest_entry(client: &reqwest::Client) -> Result<SidecarManifestEntry, String> {
+    let url = format!("{}/{}", SIDECAR_RELEASE_BASE, SIDECAR_MANIFEST_NAME);
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch sidecar manifest: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Failed to fetch sidecar manifest: status {}", response.status()));
+    }
+
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read sidecar manifest: {}", e))?;
+    parse_sidecar_manifest(&body, get_sidecar_binary_name())
+}
+
+fn parse_sidecar_manifest(body: &str, binary_name: &str) -> Result<SidecarManifestEntry, String> {
+    let mut manifest: HashMap<String, SidecarManifestEntry> =
+        serde_json::from_str(body).map_err(|e| format!("Invalid sidecar manifest: {}", e))?;
+    let entry = manifest
+        .remove(binary_name)
+        .ok_or_else(|| format!("Sidecar manifest has no entry for {}", binary_name))?;
+
+    if entry.sha256.len() != 64 || !entry.sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid checksum in sidecar manifest for {}", binary_name));
+    }
+    Ok(entry)
+}
+
+/// Where the verified checksum of the installed binary is kept
+fn checksum_path(sidecar_path: &Path) -> PathBuf {
+    sidecar_path.with_extension("sha256")
+}
+
+/// Whether the installed binary still hashes to the checksum saved when it was downloaded.
+/// Binaries installed before checksums were saved have nothing to compare against and fail.
+fn verify_sidecar(sidecar_path: &Path) -> Result<bool, String> {
+    let expected = match std::fs::read_to_string(checksum_path(sidecar_path)) {
+        Ok(expected) => expected,
+        Err(_) => return Ok(false),
+    };
+    Ok(sha256_file(sidecar_path)?.eq_ignore_ascii_case(expected.trim()))
+}
+
+/// Hex SHA-256 of a file, read in 1 MB chunks
+fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn emit_download_progress(app: &tauri::AppHandle, downloaded: u64, total: u64, speed_mbps: f32) {
+    let progress = SidecarDownloadProgress {
+        downloaded_bytes: downloaded,
+        total_bytes: total,
+        processed: downloaded,
+        total,
+        percentage: if total > 0 { (downloaded as f32 / total as f32) * 100.0 } else { 0.0 },
+        speed_mbps,
+    };
+    let _ = app.emit("sidecar-download-progress", progress);
+}
+
 /// Start the AI sidecar server from downloaded location
 #[tauri::command]
 pub async fn start_ai_sidecar(app: tauri::AppHandle) -> Result<(), String> {
@@ -262,3 +420,39 @@ pub async fn check_ai_sidecar_status(app: tauri::AppHandle) -> Result<bool, Stri
     Ok(process_guard.is_some())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_checksum_verification() {
+        let dir = std::env::temp_dir().join(format!("sidecar_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("db-ai-server");
+        std::fs::write(&binary, b"hello").unwrap();
+
+        // No saved checksum yet
+        assert!(!verify_sidecar(&binary).unwrap());
+
+        let hash = sha256_file(&binary).unwrap();
+        assert_eq!(hash, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+        std::fs::write(checksum_path(&binary), &hash).unwrap();
+        assert!(verify_sidecar(&binary).unwrap());
+
+        std::fs::write(&binary, b"hell").unwrap();
+        assert!(!verify_sidecar(&binary).unwrap());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_sidecar_manifest() {
+        let body = format!(r#"{{"db-ai-server-x86_64-unknown-linux-gnu": {{"sha256": "{}", "size": 5}}}}"#, "a".repeat(64));
+        let entry = parse_sidecar_manifest(&body, "db-ai-server-x86_64-unknown-linux-gnu").unwrap();
+        assert_eq!(entry.size, 5);
+
+        assert!(parse_sidecar_manifest(&body, "db-ai-server-aarch64-apple-darwin").is_err());
+        assert!(parse_sidecar_manifest(r#"{"x": {"sha256": "abc"}}"#, "x").is_err());
+    }
+}
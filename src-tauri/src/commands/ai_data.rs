@@ -0,0 +1,420 @@
+/// AI Data Access
+/// Read-only data providers for the AI chat: a compact business snapshot and a guarded
+/// SELECT-only query runner. Both need the AI data unlock (password or biometrics, like the
+/// settings screen) and every call is written to the activity log.
+
+use crate::commands::analytics::{business_today, get_low_stock_products_internal, get_sales_analytics_internal, get_top_products_internal, LowStockProduct, TopProduct};
+use crate::commands::biometric::user_for_biometric_token;
+use crate::commands::customer_payments::get_overdue_invoices_internal;
+use crate::commands::MAX_PAGE_SIZE;
+use crate::db::Database;
+use crate::services::{device, money};
+use chrono::Datelike;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+/// Error prefix returned while AI data access is locked
+pub const AI_DATA_LOCKED: &str = "AI_DATA_LOCKED:";
+
+/// How long an unlock lasts
+const UNLOCK_TTL: Duration = Duration::from_secs(15 * 60);
+
+/// Rows returned by run_ai_sql_readonly when no limit is given
+const DEFAULT_AI_ROW_LIMIT: i32 = 100;
+
+/// Wall-clock limit for a single AI query
+const AI_QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Keywords that can modify the database or connection; any of them rejects the query
+const FORBIDDEN_KEYWORDS: &[&str] = &[
+    "insert", "update", "delete", "replace", "upsert", "drop", "alter", "create", "truncate",
+    "attach", "detach", "pragma", "vacuum", "reindex", "analyze", "begin", "commit", "rollback",
+    "savepoint", "release", "load_extension",
+];
+
+/// Tables holding credentials or secrets, never exposed to the AI
+const FORBIDDEN_TABLES: &[&str] = &["users", "action_approvals", "app_settings"];
+
+/// Who unlocked AI data access, and until when
+#[derive(Default)]
+pub struct AiDataAccess {
+    unlocked: Mutex<Option<(String, Instant)>>,
+}
+
+impl AiDataAccess {
+    fn unlock(&self, username: String) -> Result<(), String> {
+        let mut unlocked = self.unlocked.lock().map_err(|e| e.to_string())?;
+        *unlocked = Some((username, Instant::now() + UNLOCK_TTL));
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<(), String> {
+        let mut unlocked = self.unlocked.lock().map_err(|e| e.to_string())?;
+        *unlocked = None;
+        Ok(())
+    }
+
+    /// The user who unlocked access, or an AI_DATA_LOCKED error once the unlock has expired
+    fn unlocked_by(&self) -> Result<String, String> {
+        let mut unlocked = self.unlocked.lock().map_err(|e| e.to_string())?;
+        match unlocked.as_ref() {
+            Some((username, expires)) if Instant::now() < *expires => Ok(username.clone()),
+            _ => {
+                *unlocked = None;
+                Err(format!("{}AI data access is locked. Unlock it with your password or biometrics.", AI_DATA_LOCKED))
+            }
+        }
+    }
+}
+
+/// Compact business summary for the AI chat context
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AiContextSnapshot {
+    /// Business-timezone date the snapshot was taken, "YYYY-MM-DD"
+    pub as_of: String,
+    pub month_start: String,
+    pub month_revenue: f64,
+    pub month_orders: i32,
+    pub top_products_this_month: Vec<TopProduct>,
+    pub low_stock: Vec<LowStockProduct>,
+    pub pending_credit_total: f64,
+    pub overdue_credit_total: f64,
+    pub customers_with_credit: i32,
+}
+
+/// Result of a read-only AI query
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AiQueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// True when more rows matched than the row limit allowed
+    pub truncated: bool,
+}
+
+/// Unlock AI data access with the user's password or enrolled biometric token
+#[tauri::command]
+pub fn unlock_ai_data(
+    username: String,
+    password: Option<String>,
+    biometric_token: Option<String>,
+    access: State<AiDataAccess>,
+    db: State<Database>,
+) -> Result<(), String> {
+    log::info!("unlock_ai_data called for user: {}", username);
+
+    let conn = db.get_conn()?;
+    let verified = verify_unlock(&conn, &username, password.as_deref(), biometric_token.as_deref())?;
+    access.unlock(verified)
+}
+
+/// Lock AI data access again before the unlock expires
+#[tauri::command]
+pub fn lock_ai_data(access: State<AiDataAccess>) -> Result<(), String> {
+    log::info!("lock_ai_data called");
+    access.lock()
+}
+
+/// Check the credentials the settings screen accepts and return the canonical username
+fn verify_unlock(
+    conn: &Connection,
+    username: &str,
+    password: Option<&str>,
+    biometric_token: Option<&str>,
+) -> Result<String, String> {
+    if let Some(token) = biometric_token {
+        let user = user_for_biometric_token(conn, token, &device::device_id()?)?;
+        if !user.username.eq_ignore_ascii_case(username) {
+            return Err("Biometric token does not belong to this user".to_string());
+        }
+        return Ok(user.username);
+    }
+
+    let password = password.ok_or_else(|| "Password or biometric token is required".to_string())?;
+    conn.query_row(
+        "SELECT username FROM users WHERE LOWER(username) = LOWER(?1) AND password = ?2",
+        [username, password],
+        |row| row.get(0),
+    )
+    .map_err(|_| "Invalid username or password".to_string())
+}
+
+/// Get a compact summary of this month's sales, low stock and pending credit for the AI chat
+#[tauri::command]
+pub fn get_ai_context_snapshot(
+    access: State<AiDataAccess>,
+    db: State<Database>,
+) -> Result<AiContextSnapshot, String> {
+    log::info!("get_ai_context_snapshot called");
+
+    let username = access.unlocked_by()?;
+    let conn = db.get_conn()?;
+
+    let snapshot = get_ai_context_snapshot_internal(&conn);
+    log_ai_query(&conn, "context snapshot", &snapshot.as_ref().map(|_| 1), &username)?;
+    snapshot
+}
+
+pub(crate) fn get_ai_context_snapshot_internal(conn: &Connection) -> Result<AiContextSnapshot, String> {
+    let today = business_today(conn);
+    let month_start = today.with_day(1).unwrap_or(today);
+    let (start, end) = (month_start.format("%Y-%m-%d").to_string(), today.format("%Y-%m-%d").to_string());
+
+    let sales = get_sales_analytics_internal(conn, &start, &end)?;
+    let top_products_this_month = get_top_products_internal(conn, &start, &end, 5)?;
+
+    let mut low_stock = get_low_stock_products_internal(conn)?;
+    low_stock.truncate(20);
+
+    // Unpaid balance per credit invoice, matching the reminders and credit summary
+    let (pending_credit_total, customers_with_credit): (f64, i32) = conn
+        .query_row(
+            "SELECT COALESCE(SUM(balance), 0), COUNT(DISTINCT customer_id)
+             FROM (
+                SELECT i.customer_id,
+                       i.total_amount - COALESCE((SELECT SUM(cp.amount) FROM customer_payments cp WHERE cp.invoice_id = i.id), 0) AS balance
+                FROM invoices i
+                WHERE i.customer_id IS NOT NULL AND (i.credit_amount > 0 OR i.payment_method = 'Credit')
+             )
+             WHERE balance > 0.005",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to total pending credit: {}", e))?;
+
+    let overdue_credit_total = money::sum(
+        get_overdue_invoices_internal(conn, None)?
+            .iter()
+            .map(|invoice| invoice.balance_remaining),
+    );
+
+    Ok(AiContextSnapshot {
+        as_of: end,
+        month_start: start,
+        month_revenue: sales.total_revenue,
+        month_orders: sales.total_orders,
+        top_products_this_month,
+        low_stock,
+        pending_credit_total: money::round_money(pending_credit_total),
+        overdue_credit_total,
+        customers_with_credit,
+    })
+}
+
+/// Run a single SELECT (or WITH ... SELECT) on a read-only connection, returning at most
+/// `limit` rows (default 100, max 500). Queries that mention write keywords, PRAGMA or the
+/// credential tables are rejected, and queries running longer than a few seconds are stopped.
+#[tauri::command]
+pub fn run_ai_sql_readonly(
+    sql: String,
+    limit: Option<i32>,
+    access: State<AiDataAccess>,
+    db: State<Database>,
+) -> Result<AiQueryResult, String> {
+    log::info!("run_ai_sql_readonly called: {}", sql);
+
+    let username = access.unlocked_by()?;
+    let conn = db.get_conn()?;
+
+    let result = run_ai_sql_readonly_internal(&conn, &sql, limit);
+    log_ai_query(&conn, &sql, &result.as_ref().map(|r| r.rows.len()), &username)?;
+    result
+}
+
+pub(crate) fn run_ai_sql_readonly_internal(
+    conn: &Connection,
+    sql: &str,
+    limit: Option<i32>,
+) -> Result<AiQueryResult, String> {
+    let sql = validate_ai_sql(sql)?;
+    let limit = limit.unwrap_or(DEFAULT_AI_ROW_LIMIT).clamp(1, MAX_PAGE_SIZE) as usize;
+
+    let path = conn
+        .path()
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| "Database file is not available for read-only queries".to_string())?;
+    let readonly = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+        .map_err(|e| format!("Failed to open read-only connection: {}", e))?;
+
+    with_deadline(&readonly, AI_QUERY_TIMEOUT, || {
+        let mut stmt = readonly.prepare(sql).map_err(|e| format!("Invalid query: {}", e))?;
+        if !stmt.readonly() {
+            return Err("Only read-only queries are allowed".to_string());
+        }
+
+        let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+        let mut rows = stmt.query([]).map_err(|e| format!("Query failed: {}", e))?;
+
+        let mut result = AiQueryResult { columns, rows: Vec::new(), truncated: false };
+        while let Some(row) = rows.next().map_err(|e| query_error(&e))? {
+            if result.rows.len() == limit {
+                result.truncated = true;
+                break;
+            }
+            let values = (0..result.columns.len())
+                .map(|i| row.get_ref(i).map(json_value))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            result.rows.push(values);
+        }
+
+        Ok(result)
+    })
+}
+
+/// Reject anything other than one SELECT/WITH statement over non-credential tables.
+/// Returns the statement without its trailing semicolon.
+fn validate_ai_sql(sql: &str) -> Result<&str, String> {
+    let sql = sql.trim();
+    let sql = sql.strip_suffix(';').unwrap_or(sql).trim_end();
+    if sql.is_empty() {
+        return Err("Query is empty".to_string());
+    }
+    if sql.contains(';') {
+        return Err("Only a single statement is allowed".to_string());
+    }
+
+    let lower = sql.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    if !matches!(words.first(), Some(&"select") | Some(&"with")) {
+        return Err("Only SELECT queries are allowed".to_string());
+    }
+    if let Some(word) = words.iter().find(|w| FORBIDDEN_KEYWORDS.contains(*w)) {
+        return Err(format!("Query contains forbidden keyword '{}'", word.to_uppercase()));
+    }
+    if let Some(table) = words.iter().find(|w| FORBIDDEN_TABLES.contains(*w)) {
+        return Err(format!("Table '{}' is not available to the AI", table));
+    }
+
+    Ok(sql)
+}
+
+/// Run `f`, interrupting any statement on `conn` still running after `timeout`
+fn with_deadline<T>(conn: &Connection, timeout: Duration, f: impl FnOnce() -> T) -> T {
+    let interrupt = conn.get_interrupt_handle();
+    let (done, finished) = mpsc::channel::<()>();
+    let watchdog = std::thread::spawn(move || {
+        if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+            interrupt.interrupt();
+        }
+    });
+
+    let result = f();
+    let _ = done.send(());
+    let _ = watchdog.join();
+    result
+}
+
+fn query_error(e: &rusqlite::Error) -> String {
+    match e.sqlite_error_code() {
+        Some(rusqlite::ErrorCode::OperationInterrupted) => format!(
+            "Query took longer than {} seconds and was stopped",
+            AI_QUERY_TIMEOUT.as_secs()
+        ),
+        _ => format!("Query failed: {}", e),
+    }
+}
+
+fn json_value(value: ValueRef) -> serde_json::Value {
+    match value {
+        ValueRef::Null => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => serde_json::Number::from_f64(f).map(serde_json::Value::Number).unwrap_or(serde_json::Value::Null),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned().into(),
+        ValueRef::Blob(b) => format!("<{} byte blob>", b.len()).into(),
+    }
+}
+
+/// Write an activity log entry (entity_modifications, entity_type "ai_query") for an AI data call
+fn log_ai_query<T>(
+    conn: &Connection,
+    query: &str,
+    outcome: &Result<usize, T>,
+    username: &str,
+) -> Result<(), String>
+where
+    T: std::fmt::Display,
+{
+    let (action, details) = match outcome {
+        Ok(rows) => ("queried", serde_json::json!({ "rows": rows })),
+        Err(e) => ("rejected", serde_json::json!({ "error": e.to_string() })),
+    };
+
+    conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES ('ai_query', 0, ?1, ?2, ?3, ?4)",
+        (query, action, details.to_string(), username),
+    )
+    .map_err(|e| format!("Failed to log AI query: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_customer, TestDb};
+
+    #[test]
+    fn test_ai_sql_rejects_writes_and_secrets() {
+        assert!(validate_ai_sql("SELECT name FROM products;").is_ok());
+        assert!(validate_ai_sql("WITH t AS (SELECT 1) SELECT * FROM t").is_ok());
+        assert!(validate_ai_sql("SELECT created_at, updated_at FROM customers").is_ok());
+
+        for sql in [
+            "DELETE FROM products",
+            "SELECT 1; DROP TABLE products",
+            "WITH x AS (SELECT 1) INSERT INTO products (name) SELECT 'x'",
+            "PRAGMA table_info(products)",
+            "SELECT * FROM users",
+            "SELECT value FROM app_settings",
+            "",
+        ] {
+            assert!(validate_ai_sql(sql).is_err(), "accepted: {}", sql);
+        }
+    }
+
+    #[test]
+    fn test_ai_sql_runs_read_only_with_row_limit() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        for name in ["A", "B", "C"] {
+            insert_customer(&conn, name);
+        }
+
+        let result = run_ai_sql_readonly_internal(&conn, "SELECT id, name FROM customers ORDER BY name", Some(2)).unwrap();
+        assert_eq!(result.columns, vec!["id", "name"]);
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.rows[0][1], "A");
+        assert!(result.truncated);
+
+        let snapshot = get_ai_context_snapshot_internal(&conn).unwrap();
+        assert_eq!(snapshot.month_orders, 0);
+        assert_eq!(snapshot.pending_credit_total, 0.0);
+
+        log_ai_query(&conn, "SELECT 1", &Err::<usize, _>("Only SELECT queries are allowed"), "admin").unwrap();
+        let action: String = conn
+            .query_row("SELECT action FROM entity_modifications WHERE entity_type = 'ai_query'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(action, "rejected");
+    }
+
+    #[test]
+    fn test_unlock_expires() {
+        let access = AiDataAccess::default();
+        assert!(access.unlocked_by().unwrap_err().starts_with(AI_DATA_LOCKED));
+
+        access.unlock("admin".to_string()).unwrap();
+        assert_eq!(access.unlocked_by().unwrap(), "admin");
+
+        *access.unlocked.lock().unwrap() = Some(("admin".to_string(), Instant::now()));
+        assert!(access.unlocked_by().is_err());
+    }
+}
@@ -1,7 +1,24 @@
 use crate::db::{Database, Customer};
+use crate::commands::clamp_limit;
+use crate::commands::customer_payments::get_overdue_invoices_internal;
+use crate::commands::purchase_orders::ON_ORDER_QUANTITY_SQL;
+use crate::commands::stock_alerts::counts_on_order;
+use crate::commands::sales_summary::{daily_totals, DayTotals};
+use crate::services::{money, quantity};
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use tauri::State;
 
+/// app_settings key for the business timezone as a UTC offset, e.g. "+05:30"
+const BUSINESS_UTC_OFFSET_KEY: &str = "business_utc_offset";
+const DEFAULT_BUSINESS_UTC_OFFSET: &str = "+05:30";
+
+/// app_settings key for how get_inventory_health values stock: "last_price" (price * stock, the
+/// default) or "fifo" (remaining batches at cost, under the active costing method)
+const INVENTORY_VALUATION_METHOD_KEY: &str = "inventory_valuation_method";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DashboardSale {
     pub id: i32,
@@ -17,6 +34,8 @@ pub struct DashboardStats {
     pub total_orders: i32,
     pub low_stock_count: i32,
     pub total_valuation: f64,
+    /// Unpaid balance on credit invoices past their due date
+    pub overdue_total: f64,
     pub recent_sales: Vec<DashboardSale>,
 }
 
@@ -25,7 +44,7 @@ pub struct LowStockProduct {
     pub id: i32,
     pub name: String,
     pub sku: String,
-    pub stock_quantity: i32,
+    pub stock_quantity: f64,
 }
 
 // ============== New Analytics Types ==============
@@ -58,7 +77,7 @@ pub struct TopProduct {
     pub product_name: String,
     pub sku: String,
     pub revenue: f64,
-    pub quantity_sold: i32,
+    pub quantity_sold: f64,
     pub order_count: i32,
 }
 
@@ -70,6 +89,23 @@ pub struct PaymentMethodBreakdown {
     pub percentage: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CategorySales {
+    /// None for items whose product has no category ("Uncategorized")
+    pub category_id: Option<i32>,
+    pub category_name: String,
+    pub parent_id: Option<i32>,
+    pub revenue: f64,
+    pub quantity_sold: f64,
+    pub order_count: i64,
+    /// Percent of the period's item revenue
+    pub revenue_share: f64,
+    /// Revenue less FIFO cost; None when some lines predate cost tracking
+    pub gross_profit: Option<f64>,
+    /// Revenue in the preceding period of equal length, when requested
+    pub previous_revenue: Option<f64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RegionSales {
     pub state: String,
@@ -85,7 +121,14 @@ pub struct CustomerAnalytics {
     pub new_customers: i32,
     pub repeat_customers: i32,
     pub repeat_rate: f64,
+    /// Average spend in the period per customer who bought in the period
+    pub avg_period_value: f64,
+    /// Average all-time spend of the customers who bought in the period
     pub avg_lifetime_value: f64,
+    /// Bought in the previous equal-length period but not in this one
+    pub churned_customers: i32,
+    /// churned_customers as a percentage of the previous period's customers
+    pub churn_rate: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -115,20 +158,94 @@ pub struct InventoryHealth {
     pub avg_stock_level: f64,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProductValuation {
+    pub product_id: i32,
+    pub name: String,
+    pub sku: String,
+    pub category: Option<String>,
+    pub supplier_name: Option<String>,
+    pub stock_quantity: f64,
+    /// Units left in inventory batches
+    pub batch_quantity: f64,
+    /// Remaining batches at the cost they were bought at
+    pub cost_value: f64,
+    pub selling_price: Option<f64>,
+    /// stock_quantity at the current selling price; None without a selling price
+    pub retail_value: Option<f64>,
+    pub potential_margin: Option<f64>,
+    /// Batch units don't add up to stock_quantity, so cost_value can't be trusted
+    pub quantity_mismatch: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InventoryValuation {
+    pub products: Vec<ProductValuation>,
+    pub total_cost_value: f64,
+    /// Retail value of the products that have a selling price
+    pub total_retail_value: f64,
+    /// Retail value less cost value, over the products that have a selling price
+    pub total_potential_margin: f64,
+    /// Potential margin as a percentage of that retail value
+    pub margin_percent: f64,
+    pub mismatch_count: i32,
+    /// Products with stock but no selling price, left out of the retail totals
+    pub unpriced_count: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PurchaseAnalytics {
     pub total_purchases: f64,
     pub total_paid: f64,
+    /// Goods returned to suppliers, set off against or refunded on the purchases
+    pub total_returns: f64,
     pub pending_payments: f64,
     pub active_suppliers: i32,
     pub purchase_order_count: i32,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExpenseCategoryTotal {
+    pub category: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProfitAndLoss {
+    /// Sales after discounts, excluding tax collected
+    pub revenue: f64,
+    pub discounts: f64,
+    pub tax_collected: f64,
+    /// Cost of goods sold from the FIFO costs stored on sale transactions
+    pub cogs: f64,
+    pub gross_profit: f64,
+    pub gross_margin_percent: f64,
+    pub expenses_by_category: Vec<ExpenseCategoryTotal>,
+    pub total_expenses: f64,
+    pub net_profit: f64,
+    pub net_margin_percent: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HeatmapCell {
+    /// 0 = Monday ... 6 = Sunday, in the business timezone
+    pub weekday: i32,
+    /// Hour of day 0-23; None when collapsed to day-of-week totals
+    pub hour: Option<i32>,
+    pub orders: i32,
+    pub revenue: f64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CashflowPoint {
     pub date: String,
+    /// Amounts per cash event, as positive figures; events left out of the filter stay 0
     pub sales: f64,
     pub purchases: f64,
+    pub purchase_returns: f64,
+    pub customer_payments: f64,
+    pub supplier_payments: f64,
+    /// Money in less money out over the included events
     pub net: f64,
 }
 
@@ -148,6 +265,14 @@ pub struct StateTax {
     pub invoice_count: i32,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RateTax {
+    pub tax_rate: f64,
+    pub taxable_value: f64,
+    pub tax_amount: f64,
+    pub item_count: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TaxSummary {
     pub total_tax: f64,
@@ -155,6 +280,8 @@ pub struct TaxSummary {
     pub sgst_total: f64,
     pub igst_total: f64,
     pub by_state: Vec<StateTax>,
+    /// Item-level tax grouped by rate; invoices taxed only at invoice level are not included
+    pub by_rate: Vec<RateTax>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -170,10 +297,58 @@ pub struct LowStockAlert {
     pub id: i32,
     pub name: String,
     pub sku: String,
-    pub stock_quantity: i32,
+    pub stock_quantity: f64,
     pub selling_price: Option<f64>,
     pub avg_daily_sales: f64,
     pub days_until_stockout: Option<i32>,
+    /// Units on open purchase orders still to arrive
+    pub on_order_quantity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlowMovingProduct {
+    pub product_id: i32,
+    pub name: String,
+    pub sku: String,
+    pub category: Option<String>,
+    pub stock_quantity: f64,
+    pub unit_cost: f64,
+    pub stock_value: f64,
+    /// UTC "YYYY-MM-DD HH:MM:SS"; None when the product has never sold
+    pub last_sale_date: Option<String>,
+    pub days_since_last_sale: Option<i64>,
+    pub last_purchase_date: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AbcProduct {
+    pub product_id: i32,
+    pub name: String,
+    pub sku: String,
+    pub revenue: f64,
+    pub share_percent: f64,
+    pub cumulative_percent: f64,
+    pub class: String, // "A", "B", "C"
+    pub stock_quantity: f64,
+    pub stock_value: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AbcClassSummary {
+    pub class: String,
+    pub product_count: i32,
+    pub revenue: f64,
+    pub revenue_share_percent: f64,
+    pub stock_value: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AbcAnalysis {
+    pub a_cutoff_percent: f64,
+    pub b_cutoff_percent: f64,
+    pub total_revenue: f64,
+    pub products: Vec<AbcProduct>,
+    pub classes: Vec<AbcClassSummary>,
 }
 
 /// Get dashboard statistics
@@ -215,6 +390,13 @@ pub fn get_dashboard_stats(db: State<Database>) -> Result<DashboardStats, String
         )
         .map_err(|e| e.to_string())?;
 
+    // Unpaid credit past its due date
+    let overdue_total = money::sum(
+        get_overdue_invoices_internal(&conn, None)?
+            .iter()
+            .map(|invoice| invoice.balance_remaining),
+    );
+
     // Recent sales (last 5 invoices)
     let mut stmt = conn
         .prepare(
@@ -245,6 +427,7 @@ pub fn get_dashboard_stats(db: State<Database>) -> Result<DashboardStats, String
         total_orders,
         low_stock_count,
         total_valuation,
+        overdue_total,
         recent_sales,
     };
 
@@ -258,7 +441,13 @@ pub fn get_low_stock_products(db: State<Database>) -> Result<Vec<LowStockProduct
     log::info!("get_low_stock_products called");
 
     let conn = db.get_conn()?;
+    let products = get_low_stock_products_internal(&conn)?;
 
+    log::info!("Returning {} low stock products", products.len());
+    Ok(products)
+}
+
+pub(crate) fn get_low_stock_products_internal(conn: &Connection) -> Result<Vec<LowStockProduct>, String> {
     let mut stmt = conn
         .prepare("SELECT id, name, sku, stock_quantity FROM products WHERE stock_quantity < 10 ORDER BY stock_quantity ASC")
         .map_err(|e| e.to_string())?;
@@ -279,7 +468,6 @@ pub fn get_low_stock_products(db: State<Database>) -> Result<Vec<LowStockProduct
         products.push(product.map_err(|e| e.to_string())?);
     }
 
-    log::info!("Returning {} low stock products", products.len());
     Ok(products)
 }
 
@@ -296,7 +484,7 @@ pub struct CustomerInvoice {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CustomerProductStat {
     pub name: String,
-    pub total_qty: i32,
+    pub total_qty: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -314,119 +502,69 @@ pub struct CustomerReport {
     pub stats: CustomerStats,
 }
 
-/// Search for customers and get detailed report
+/// Columns read by `customer_from_row`, in order
+const CUSTOMER_COLUMNS: &str =
+    "id, name, email, phone, address, place, state, district, town, created_at, updated_at, image_path, credit_limit, is_archived";
+
+fn customer_from_row(row: &rusqlite::Row) -> rusqlite::Result<Customer> {
+    Ok(Customer {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        email: row.get(2)?,
+        phone: row.get(3)?,
+        address: row.get(4)?,
+        place: row.get(5)?,
+        state: row.get(6)?,
+        district: row.get(7)?,
+        town: row.get(8)?,
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+        image_path: row.get(11)?,
+        credit_limit: row.get(12)?,
+        is_archived: row.get(13)?,
+    })
+}
+
+/// Search for customers by name, phone, place or town and get a detailed report for each
 #[tauri::command]
-pub fn customer_search(query: String, db: State<Database>) -> Result<Vec<CustomerReport>, String> {
-    log::info!("customer_search called with query: {}", query);
+pub fn customer_search(query: String, limit: Option<i32>, db: State<Database>) -> Result<Vec<CustomerReport>, String> {
+    log::info!("customer_search called with query: {}, limit: {:?}", query, limit);
 
     let conn = db.get_conn()?;
+    let reports = customer_search_internal(&conn, &query, limit)?;
 
+    log::info!("Returning {} customer reports", reports.len());
+    Ok(reports)
+}
+
+pub(crate) fn customer_search_internal(
+    conn: &Connection,
+    query: &str,
+    limit: Option<i32>,
+) -> Result<Vec<CustomerReport>, String> {
     let search_pattern = format!("%{}%", query);
 
-    // Search for customers
     let mut stmt = conn
-        .prepare(
-            "SELECT id, name, email, phone, address, place, created_at, updated_at
+        .prepare(&format!(
+            "SELECT {}
              FROM customers
-             WHERE name LIKE ?1 OR phone LIKE ?1
+             WHERE name LIKE ?1 OR phone LIKE ?1 OR place LIKE ?1 OR town LIKE ?1
              ORDER BY name
-             LIMIT 10"
-        )
+             LIMIT ?2",
+            CUSTOMER_COLUMNS
+        ))
         .map_err(|e| e.to_string())?;
 
-    let customer_iter = stmt
-        .query_map([&search_pattern], |row| {
-            Ok(Customer {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                email: row.get(2)?,
-                phone: row.get(3)?,
-                address: row.get(4)?,
-                place: row.get(5)?,
-                state: None, // Not fetched in this query
-                district: None,
-                town: None,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
-            })
-        })
+    let customers = stmt
+        .query_map(rusqlite::params![search_pattern, clamp_limit(limit.unwrap_or(10))], customer_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    let mut reports = Vec::new();
-
-    for customer_result in customer_iter {
-        let customer = customer_result.map_err(|e| e.to_string())?;
-        let customer_id = customer.id;
-
-        // Get invoices for this customer
-        let mut invoice_stmt = conn
-            .prepare(
-                "SELECT i.id, i.invoice_number, i.total_amount, i.discount_amount, i.created_at,
-                 (SELECT COUNT(*) FROM invoice_items WHERE invoice_id = i.id) as item_count
-                 FROM invoices i
-                 WHERE i.customer_id = ?1
-                 ORDER BY i.created_at DESC"
-            )
-            .map_err(|e| e.to_string())?;
-
-        let invoices: Vec<CustomerInvoice> = invoice_stmt
-            .query_map([customer_id], |row| {
-                Ok(CustomerInvoice {
-                    id: row.get(0)?,
-                    invoice_number: row.get(1)?,
-                    total_amount: row.get(2)?,
-                    discount_amount: row.get(3)?,
-                    created_at: row.get(4)?,
-                    item_count: row.get(5)?,
-                })
-            })
-            .map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| e.to_string())?;
-
-        // Get product statistics for this customer
-        let mut product_stmt = conn
-            .prepare(
-                "SELECT p.name, SUM(ii.quantity) as total_qty
-                 FROM invoice_items ii
-                 JOIN invoices i ON ii.invoice_id = i.id
-                 JOIN products p ON ii.product_id = p.id
-                 WHERE i.customer_id = ?1
-                 GROUP BY p.id, p.name
-                 ORDER BY total_qty DESC"
-            )
-            .map_err(|e| e.to_string())?;
-
-        let products: Vec<CustomerProductStat> = product_stmt
-            .query_map([customer_id], |row| {
-                Ok(CustomerProductStat {
-                    name: row.get(0)?,
-                    total_qty: row.get(1)?,
-                })
-            })
-            .map_err(|e| e.to_string())?
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| e.to_string())?;
-
-        // Calculate stats
-        let total_spent: f64 = invoices.iter().map(|i| i.total_amount).sum();
-        let total_discount: f64 = invoices.iter().map(|i| i.discount_amount).sum();
-        let invoice_count = invoices.len() as i32;
-
-        reports.push(CustomerReport {
-            customer,
-            invoices,
-            products,
-            stats: CustomerStats {
-                total_spent,
-                total_discount,
-                invoice_count,
-            },
-        });
-    }
-
-    log::info!("Returning {} customer reports", reports.len());
-    Ok(reports)
+    customers
+        .into_iter()
+        .map(|customer| build_customer_report(conn, customer))
+        .collect()
 }
 
 /// Get detailed report for a single customer by ID
@@ -435,32 +573,28 @@ pub fn get_customer_report(id: i32, db: State<Database>) -> Result<CustomerRepor
     log::info!("get_customer_report called with id: {}", id);
 
     let conn = db.get_conn()?;
+    let report = get_customer_report_internal(&conn, id)?;
 
-    // Get customer details
+    log::info!("Returning report for customer id: {}", id);
+    Ok(report)
+}
+
+pub(crate) fn get_customer_report_internal(conn: &Connection, id: i32) -> Result<CustomerReport, String> {
     let customer = conn
         .query_row(
-            "SELECT id, name, email, phone, address, place, created_at, updated_at
-             FROM customers
-             WHERE id = ?1",
+            &format!("SELECT {} FROM customers WHERE id = ?1", CUSTOMER_COLUMNS),
             [id],
-            |row| {
-                Ok(Customer {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    email: row.get(2)?,
-                    phone: row.get(3)?,
-                    address: row.get(4)?,
-                    place: row.get(5)?,
-                state: None,
-                district: None,
-                town: None,
-                created_at: row.get(6)?,
-                updated_at: row.get(7)?,
-                })
-            },
+            customer_from_row,
         )
         .map_err(|e| format!("Customer not found: {}", e))?;
 
+    build_customer_report(conn, customer)
+}
+
+/// Invoices, products bought and totals for one customer
+fn build_customer_report(conn: &Connection, customer: Customer) -> Result<CustomerReport, String> {
+    let customer_id = customer.id;
+
     // Get invoices for this customer
     let mut invoice_stmt = conn
         .prepare(
@@ -473,7 +607,7 @@ pub fn get_customer_report(id: i32, db: State<Database>) -> Result<CustomerRepor
         .map_err(|e| e.to_string())?;
 
     let invoices: Vec<CustomerInvoice> = invoice_stmt
-        .query_map([id], |row| {
+        .query_map([customer_id], |row| {
             Ok(CustomerInvoice {
                 id: row.get(0)?,
                 invoice_number: row.get(1)?,
@@ -501,10 +635,10 @@ pub fn get_customer_report(id: i32, db: State<Database>) -> Result<CustomerRepor
         .map_err(|e| e.to_string())?;
 
     let products: Vec<CustomerProductStat> = product_stmt
-        .query_map([id], |row| {
+        .query_map([customer_id], |row| {
             Ok(CustomerProductStat {
                 name: row.get(0)?,
-                total_qty: row.get(1)?,
+                total_qty: quantity::round_quantity(row.get(1)?),
             })
         })
         .map_err(|e| e.to_string())?
@@ -516,7 +650,7 @@ pub fn get_customer_report(id: i32, db: State<Database>) -> Result<CustomerRepor
     let total_discount: f64 = invoices.iter().map(|i| i.discount_amount).sum();
     let invoice_count = invoices.len() as i32;
 
-    let report = CustomerReport {
+    Ok(CustomerReport {
         customer,
         invoices,
         products,
@@ -525,10 +659,233 @@ pub fn get_customer_report(id: i32, db: State<Database>) -> Result<CustomerRepor
             total_discount,
             invoice_count,
         },
+    })
+}
+
+// ============== Report Date Ranges ==============
+
+/// Parse a "+05:30" / "-04:00" / "+0530" / "Z" style UTC offset into minutes
+pub(crate) fn parse_utc_offset(offset: &str) -> Option<i32> {
+    let offset = offset.trim();
+    if offset.eq_ignore_ascii_case("z") || offset.eq_ignore_ascii_case("utc") {
+        return Some(0);
+    }
+    let (sign, rest) = match offset.chars().next()? {
+        '+' => (1, &offset[1..]),
+        '-' => (-1, &offset[1..]),
+        _ => return None,
     };
+    let digits: String = rest.chars().filter(|c| *c != ':').collect();
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let hours: i32 = digits[..2].parse().ok()?;
+    let minutes: i32 = digits[2..].parse().ok()?;
+    if hours > 14 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 60 + minutes))
+}
 
-    log::info!("Returning report for customer id: {}", id);
-    Ok(report)
+/// Business timezone offset in minutes from app_settings (defaults to IST)
+pub(crate) fn business_offset_minutes(conn: &Connection) -> i32 {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        [BUSINESS_UTC_OFFSET_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|v| parse_utc_offset(&v))
+    .or_else(|| parse_utc_offset(DEFAULT_BUSINESS_UTC_OFFSET))
+    .unwrap_or(0)
+}
+
+/// Today's date in the business timezone
+pub(crate) fn business_today(conn: &Connection) -> NaiveDate {
+    (chrono::Utc::now().naive_utc() + Duration::minutes(business_offset_minutes(conn) as i64)).date()
+}
+
+/// Parse a report date; anything after "YYYY-MM-DD" is ignored
+pub(crate) fn parse_report_date(date: &str) -> Result<NaiveDate, String> {
+    let day = date.get(..10).unwrap_or(date);
+    NaiveDate::parse_from_str(day, "%Y-%m-%d").map_err(|_| format!("Invalid date '{}', expected YYYY-MM-DD", date))
+}
+
+/// A local (business timezone) date range converted to UTC boundaries.
+/// invoices.created_at is stored as UTC RFC 3339, so queries compare `datetime(created_at)`
+/// (normalized UTC) against `start_utc`/`end_utc` and group with `strftime(fmt, created_at, local_modifier)`.
+pub(crate) struct ReportRange {
+    pub(crate) start_date: NaiveDate,
+    pub(crate) end_date: NaiveDate,
+    pub(crate) offset_minutes: i32,
+    /// Inclusive UTC start, "YYYY-MM-DD HH:MM:SS"
+    pub(crate) start_utc: String,
+    /// Exclusive UTC end (local midnight after end_date)
+    pub(crate) end_utc: String,
+    /// SQLite modifier shifting UTC into local time, e.g. "+330 minutes"
+    pub(crate) local_modifier: String,
+}
+
+impl ReportRange {
+    pub(crate) fn new(start_date: NaiveDate, end_date: NaiveDate, offset_minutes: i32) -> Self {
+        let to_utc = |date: NaiveDate| {
+            let local_midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+            (local_midnight - Duration::minutes(offset_minutes as i64))
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        };
+        ReportRange {
+            start_date,
+            end_date,
+            offset_minutes,
+            start_utc: to_utc(start_date),
+            end_utc: to_utc(end_date + Duration::days(1)),
+            local_modifier: format!("{:+} minutes", offset_minutes),
+        }
+    }
+
+    pub(crate) fn load(conn: &Connection, start_date: &str, end_date: &str) -> Result<Self, String> {
+        Ok(Self::new(
+            parse_report_date(start_date)?,
+            parse_report_date(end_date)?,
+            business_offset_minutes(conn),
+        ))
+    }
+
+    /// Every date a record could carry, for all-time totals
+    pub(crate) fn all_time(offset_minutes: i32) -> Self {
+        Self::new(
+            NaiveDate::from_ymd_opt(1900, 1, 1).unwrap_or_default(),
+            NaiveDate::from_ymd_opt(2999, 12, 31).unwrap_or_default(),
+            offset_minutes,
+        )
+    }
+
+    /// The same number of days immediately before this range
+    fn previous(&self) -> Self {
+        let days = (self.end_date - self.start_date).num_days() + 1;
+        Self::new(
+            self.start_date - Duration::days(days),
+            self.start_date - Duration::days(1),
+            self.offset_minutes,
+        )
+    }
+}
+
+/// CTE `batch_costs(product_id, cost, qty)`: value and quantity of remaining batches, priced
+/// by the active costing method
+pub(crate) const BATCH_COSTS_CTE: &str = "batch_costs AS (
+    SELECT product_id, SUM(quantity_remaining * unit_cost) AS cost, SUM(quantity_remaining) AS qty
+    FROM inventory_batch_costs
+    WHERE quantity_remaining > 0
+    GROUP BY product_id
+)";
+
+/// Unit cost from remaining batches, falling back to the product cost price (needs `batch_costs bc`)
+pub(crate) const UNIT_COST_SQL: &str = "CASE WHEN bc.qty > 0 THEN bc.cost / bc.qty ELSE COALESCE(p.price, 0.0) END";
+
+/// Invoice totals for a report range
+struct RevenueTotals {
+    /// Sum of invoice totals (after discounts, including tax)
+    gross_sales: f64,
+    tax: f64,
+    discount: f64,
+    orders: i32,
+}
+
+/// Sales totals for a range. Revenue figures in the analytics reports go through here (and the
+/// per-day aggregation in sales_summary) so that voided invoices and returns only need to be
+/// excluded/netted in one place.
+fn revenue_totals(conn: &Connection, range: &ReportRange) -> Result<RevenueTotals, String> {
+    let mut totals = DayTotals::default();
+    for day in daily_totals(conn, range)?.values() {
+        totals.add(day);
+    }
+    Ok(RevenueTotals {
+        gross_sales: totals.revenue,
+        tax: totals.tax,
+        discount: totals.discount,
+        orders: totals.orders as i32,
+    })
+}
+
+/// Trend bucket size. Weekly buckets are labelled with the Monday of the ISO week
+/// ("2024-01-29") and monthly ones as "YYYY-MM", so labels sort chronologically.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TrendGranularity {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl TrendGranularity {
+    fn parse(granularity: &str) -> Self {
+        match granularity {
+            "weekly" => TrendGranularity::Weekly,
+            "monthly" => TrendGranularity::Monthly,
+            _ => TrendGranularity::Daily,
+        }
+    }
+
+    /// SQL expression giving the bucket label of `column`, shifted by `modifier` when set
+    fn bucket_sql(self, column: &str, modifier: Option<&str>) -> String {
+        let value = match modifier {
+            Some(modifier) => format!("{}, {}", column, modifier),
+            None => column.to_string(),
+        };
+        match self {
+            TrendGranularity::Daily => format!("date({})", value),
+            // 'weekday 0' moves forward to Sunday (or stays), then back to that week's Monday
+            TrendGranularity::Weekly => format!("date({}, 'weekday 0', '-6 days')", value),
+            TrendGranularity::Monthly => format!("strftime('%Y-%m', {})", value),
+        }
+    }
+
+    /// First day of the bucket containing `date`
+    fn bucket_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            TrendGranularity::Daily => date,
+            TrendGranularity::Weekly => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+            TrendGranularity::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    fn next_bucket(self, bucket: NaiveDate) -> NaiveDate {
+        match self {
+            TrendGranularity::Daily => bucket + Duration::days(1),
+            TrendGranularity::Weekly => bucket + Duration::days(7),
+            TrendGranularity::Monthly => bucket
+                .checked_add_months(Months::new(1))
+                .unwrap_or(bucket + Duration::days(31)),
+        }
+    }
+
+    fn label(self, bucket: NaiveDate) -> String {
+        match self {
+            TrendGranularity::Monthly => bucket.format("%Y-%m").to_string(),
+            _ => bucket.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// Every bucket label overlapping `start..=end`, in chronological order
+    fn periods(self, start: NaiveDate, end: NaiveDate) -> Vec<String> {
+        let mut periods = Vec::new();
+        let mut bucket = self.bucket_start(start);
+        while bucket <= end {
+            periods.push(self.label(bucket));
+            bucket = self.next_bucket(bucket);
+        }
+        periods
+    }
+
+    /// Sum per-day sales totals into this granularity's buckets, keyed by label
+    fn bucket_totals(self, days: &BTreeMap<NaiveDate, DayTotals>) -> HashMap<String, DayTotals> {
+        let mut buckets: HashMap<String, DayTotals> = HashMap::new();
+        for (day, totals) in days {
+            buckets.entry(self.label(self.bucket_start(*day))).or_default().add(totals);
+        }
+        buckets
+    }
 }
 
 // ============== New Analytics Commands ==============
@@ -543,22 +900,21 @@ pub fn get_sales_analytics(
     log::info!("get_sales_analytics called: {} to {}", start_date, end_date);
 
     let conn = db.get_conn()?;
+    get_sales_analytics_internal(&conn, &start_date, &end_date)
+}
+
+pub(crate) fn get_sales_analytics_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<SalesAnalytics, String> {
+    let range = ReportRange::load(conn, start_date, end_date)?;
+    let previous = range.previous();
 
     // Current period stats
-    let (total_revenue, total_orders, total_tax, total_discount): (f64, i32, f64, f64) = conn
-        .query_row(
-            "SELECT
-                COALESCE(SUM(total_amount), 0.0),
-                COUNT(*),
-                COALESCE(SUM(tax_amount), 0.0),
-                COALESCE(SUM(discount_amount), 0.0)
-             FROM invoices
-             WHERE created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')",
-            [&start_date, &end_date],
-            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
-        )
-        .map_err(|e| e.to_string())?;
+    let current = revenue_totals(conn, &range)?;
+    let (total_revenue, total_orders, total_tax, total_discount) =
+        (current.gross_sales, current.orders, current.tax, current.discount);
 
     let avg_order_value = if total_orders > 0 {
         total_revenue / total_orders as f64
@@ -567,21 +923,8 @@ pub fn get_sales_analytics(
     };
 
     // Calculate previous period (same duration before start_date)
-    let (prev_revenue, prev_orders): (f64, i32) = conn
-        .query_row(
-            "WITH date_diff AS (
-                SELECT julianday(?2) - julianday(?1) AS days
-            )
-            SELECT
-                COALESCE(SUM(total_amount), 0.0),
-                COUNT(*)
-             FROM invoices, date_diff
-             WHERE created_at >= datetime(?1, '-' || (days + 1) || ' days')
-               AND created_at < datetime(?1)",
-            [&start_date, &end_date],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        )
-        .map_err(|e| e.to_string())?;
+    let prev = revenue_totals(conn, &previous)?;
+    let (prev_revenue, prev_orders) = (prev.gross_sales, prev.orders);
 
     let revenue_change = if prev_revenue > 0.0 {
         ((total_revenue - prev_revenue) / prev_revenue) * 100.0
@@ -606,9 +949,9 @@ pub fn get_sales_analytics(
              FROM invoice_items ii
              JOIN invoices i ON ii.invoice_id = i.id
              JOIN products p ON ii.product_id = p.id
-             WHERE i.created_at >= datetime(?1)
-               AND i.created_at < datetime(?2, '+1 day')",
-            [&start_date, &end_date],
+             WHERE datetime(i.created_at) >= ?1
+               AND datetime(i.created_at) < ?2",
+            [&range.start_utc, &range.end_utc],
             |row| row.get(0),
         )
         .unwrap_or(0.0);
@@ -638,42 +981,35 @@ pub fn get_revenue_trend(
     log::info!("get_revenue_trend called: {} to {} ({})", start_date, end_date, granularity);
 
     let conn = db.get_conn()?;
+    get_revenue_trend_internal(&conn, &start_date, &end_date, &granularity)
+}
 
-    let date_format = match granularity.as_str() {
-        "weekly" => "%Y-W%W",
-        "monthly" => "%Y-%m",
-        _ => "%Y-%m-%d", // daily
-    };
+fn get_revenue_trend_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    granularity: &str,
+) -> Result<Vec<RevenueTrendPoint>, String> {
+    let range = ReportRange::load(conn, start_date, end_date)?;
+    let granularity = TrendGranularity::parse(granularity);
 
-    let mut stmt = conn
-        .prepare(&format!(
-            "SELECT
-                strftime('{}', created_at) as period,
-                COALESCE(SUM(total_amount), 0.0) as revenue,
-                COUNT(*) as order_count
-             FROM invoices
-             WHERE created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')
-             GROUP BY period
-             ORDER BY period ASC",
-            date_format
-        ))
-        .map_err(|e| e.to_string())?;
+    let by_period = granularity.bucket_totals(&daily_totals(conn, &range)?);
 
-    let results = stmt
-        .query_map([&start_date, &end_date], |row| {
-            let revenue: f64 = row.get(1)?;
-            let order_count: i32 = row.get(2)?;
-            Ok(RevenueTrendPoint {
-                date: row.get(0)?,
+    // One point per period in the range, including periods with no sales
+    let results = granularity
+        .periods(range.start_date, range.end_date)
+        .into_iter()
+        .map(|date| {
+            let totals = by_period.get(&date).copied().unwrap_or_default();
+            let (revenue, order_count) = (totals.revenue, totals.orders as i32);
+            RevenueTrendPoint {
+                date,
                 revenue,
                 order_count,
                 avg_order_value: if order_count > 0 { revenue / order_count as f64 } else { 0.0 },
-            })
+            }
         })
-        .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?;
+        .collect();
 
     Ok(results)
 }
@@ -689,9 +1025,12 @@ pub fn get_top_products(
     log::info!("get_top_products called: {} to {}, limit {}", start_date, end_date, limit);
 
     let conn = db.get_conn()?;
+    get_top_products_internal(&conn, &start_date, &end_date, limit)
+}
 
-    let query = format!(
-        "SELECT
+/// Best sellers by revenue in a UTC range (?1..?2), top ?3. The date filter is served by
+/// idx_invoices_created_utc (see db/query_plans.rs).
+pub(crate) const TOP_PRODUCTS_SQL: &str = "SELECT
             p.id,
             p.name,
             p.sku,
@@ -701,24 +1040,30 @@ pub fn get_top_products(
          FROM products p
          JOIN invoice_items ii ON p.id = ii.product_id
          JOIN invoices i ON ii.invoice_id = i.id
-         WHERE i.created_at >= datetime(?1)
-           AND i.created_at < datetime(?2, '+1 day')
+         WHERE datetime(i.created_at) >= ?1
+           AND datetime(i.created_at) < ?2
          GROUP BY p.id
          ORDER BY revenue DESC
-         LIMIT {}",
-        limit
-    );
+         LIMIT ?3";
 
-    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+pub(crate) fn get_top_products_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    limit: i32,
+) -> Result<Vec<TopProduct>, String> {
+    let range = ReportRange::load(conn, start_date, end_date)?;
+
+    let mut stmt = conn.prepare(TOP_PRODUCTS_SQL).map_err(|e| e.to_string())?;
 
     let results = stmt
-        .query_map([&start_date, &end_date], |row| {
+        .query_map(rusqlite::params![&range.start_utc, &range.end_utc, clamp_limit(limit)], |row| {
             Ok(TopProduct {
                 product_id: row.get(0)?,
                 product_name: row.get(1)?,
                 sku: row.get(2)?,
                 revenue: row.get(3)?,
-                quantity_sold: row.get(4)?,
+                quantity_sold: quantity::round_quantity(row.get(4)?),
                 order_count: row.get(5)?,
             })
         })
@@ -740,34 +1085,51 @@ pub fn get_sales_by_payment_method(
     log::info!("get_sales_by_payment_method called: {} to {}", start_date, end_date);
 
     let conn = db.get_conn()?;
+    get_sales_by_payment_method_internal(&conn, &start_date, &end_date)
+}
+
+fn get_sales_by_payment_method_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<PaymentMethodBreakdown>, String> {
+    let range = ReportRange::load(conn, start_date, end_date)?;
+    payment_method_breakdown(conn, &range)
+}
 
+/// Sales per payment method over a range, largest first. Split payments count each part under
+/// its own method (and what they left on credit under Credit), so an invoice can count towards
+/// several methods; older invoices count in full under their payment_method. The day closure's
+/// Z-report uses this for its single day.
+pub(crate) fn payment_method_breakdown(conn: &Connection, range: &ReportRange) -> Result<Vec<PaymentMethodBreakdown>, String> {
     // Get total for percentage calculation
-    let total: f64 = conn
-        .query_row(
-            "SELECT COALESCE(SUM(total_amount), 0.0) FROM invoices
-             WHERE created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')",
-            [&start_date, &end_date],
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+    let total = revenue_totals(conn, range)?.gross_sales;
 
     let mut stmt = conn
         .prepare(
-            "SELECT
-                COALESCE(payment_method, 'Unknown') as method,
-                COALESCE(SUM(total_amount), 0.0) as total,
-                COUNT(*) as count
-             FROM invoices
-             WHERE created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')
-             GROUP BY payment_method
+            "WITH ranged AS (
+                SELECT id, payment_method, total_amount, COALESCE(credit_amount, 0) AS credit_amount,
+                       EXISTS (SELECT 1 FROM invoice_tenders t WHERE t.invoice_id = invoices.id) AS split
+                FROM invoices
+                WHERE datetime(created_at) >= ?1
+                  AND datetime(created_at) < ?2
+             ),
+             tenders AS (
+                SELECT r.id AS invoice_id, t.method, t.amount FROM ranged r JOIN invoice_tenders t ON t.invoice_id = r.id
+                UNION ALL
+                SELECT id, 'Credit', credit_amount FROM ranged WHERE split AND credit_amount > 0
+                UNION ALL
+                SELECT id, COALESCE(payment_method, 'Unknown'), total_amount FROM ranged WHERE NOT split
+             )
+             SELECT method, COALESCE(SUM(amount), 0.0) as total, COUNT(DISTINCT invoice_id) as count
+             FROM tenders
+             GROUP BY method
              ORDER BY total DESC"
         )
         .map_err(|e| e.to_string())?;
 
     let results = stmt
-        .query_map([&start_date, &end_date], |row| {
+        .query_map([&range.start_utc, &range.end_utc], |row| {
             let amount: f64 = row.get(1)?;
             Ok(PaymentMethodBreakdown {
                 payment_method: row.get(0)?,
@@ -783,36 +1145,150 @@ pub fn get_sales_by_payment_method(
     Ok(results)
 }
 
-/// Get sales by region (grouped by town)
-#[tauri::command]
-pub fn get_sales_by_region(
-    start_date: String,
-    end_date: String,
-    db: State<Database>,
-) -> Result<Vec<RegionSales>, String> {
-    log::info!("get_sales_by_region called: {} to {}", start_date, end_date);
-
-    let conn = db.get_conn()?;
-
+/// Per-category totals for one period, keyed by category id (None = Uncategorized)
+fn category_totals(conn: &Connection, range: &ReportRange) -> Result<Vec<CategorySales>, String> {
+    // Line cost comes from the FIFO batches each line consumed; lines sold before consumptions
+    // were recorded have none, and a category with any such line reports no gross profit
     let mut stmt = conn
         .prepare(
             "SELECT
-                COALESCE(state, 'Unknown') as state,
-                district,
-                COALESCE(town, 'Unknown') as town,
-                COALESCE(SUM(total_amount), 0.0) as revenue,
-                COUNT(*) as order_count
-             FROM invoices
-             WHERE created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')
-               AND town IS NOT NULL AND town != ''
-             GROUP BY town
-             ORDER BY revenue DESC"
+                c.id,
+                COALESCE(c.name, 'Uncategorized'),
+                c.parent_id,
+                COALESCE(SUM(ii.quantity * ii.unit_price - COALESCE(ii.discount_amount, 0)), 0.0) as revenue,
+                COALESCE(SUM(ii.quantity), 0),
+                COUNT(DISTINCT ii.invoice_id),
+                COALESCE(SUM((SELECT SUM(bc.quantity * bc.unit_cost) FROM batch_consumptions bc
+                              WHERE bc.invoice_item_id = ii.id)), 0.0),
+                SUM(CASE WHEN EXISTS (SELECT 1 FROM batch_consumptions bc WHERE bc.invoice_item_id = ii.id)
+                         THEN 0 ELSE 1 END)
+             FROM invoice_items ii
+             JOIN invoices i ON ii.invoice_id = i.id
+             LEFT JOIN products p ON ii.product_id = p.id
+             LEFT JOIN categories c ON p.category_id = c.id
+             WHERE datetime(i.created_at) >= ?1
+               AND datetime(i.created_at) < ?2
+             GROUP BY c.id
+             ORDER BY revenue DESC",
         )
         .map_err(|e| e.to_string())?;
 
     let results = stmt
-        .query_map([&start_date, &end_date], |row| {
+        .query_map([&range.start_utc, &range.end_utc], |row| {
+            let revenue = money::round_money(row.get(3)?);
+            let cogs: f64 = row.get(6)?;
+            let uncosted_lines: i64 = row.get(7)?;
+            Ok(CategorySales {
+                category_id: row.get(0)?,
+                category_name: row.get(1)?,
+                parent_id: row.get(2)?,
+                revenue,
+                quantity_sold: quantity::round_quantity(row.get(4)?),
+                order_count: row.get(5)?,
+                revenue_share: 0.0,
+                gross_profit: (uncosted_lines == 0).then(|| money::sub(revenue, cogs)),
+                previous_revenue: None,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(results)
+}
+
+/// Get sales per product category, highest revenue first. Revenue is net of line discounts;
+/// items whose product has no category are reported as "Uncategorized". With
+/// `compare_previous`, each entry also carries the revenue of the same number of days before
+/// the range, and categories sold only in that earlier period are included with zero revenue.
+#[tauri::command]
+pub fn get_sales_by_category(
+    start_date: String,
+    end_date: String,
+    compare_previous: Option<bool>,
+    db: State<Database>,
+) -> Result<Vec<CategorySales>, String> {
+    log::info!(
+        "get_sales_by_category called: {} to {}, compare_previous: {:?}",
+        start_date, end_date, compare_previous
+    );
+
+    let conn = db.get_conn()?;
+    get_sales_by_category_internal(&conn, &start_date, &end_date, compare_previous.unwrap_or(false))
+}
+
+pub(crate) fn get_sales_by_category_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    compare_previous: bool,
+) -> Result<Vec<CategorySales>, String> {
+    let range = ReportRange::load(conn, start_date, end_date)?;
+    let mut results = category_totals(conn, &range)?;
+
+    let total = money::sum(results.iter().map(|c| c.revenue));
+    for category in results.iter_mut() {
+        category.revenue_share = if total > 0.0 { category.revenue / total * 100.0 } else { 0.0 };
+    }
+
+    if compare_previous {
+        let previous = category_totals(conn, &range.previous())?;
+        for category in results.iter_mut() {
+            category.previous_revenue = Some(
+                previous
+                    .iter()
+                    .find(|p| p.category_id == category.category_id)
+                    .map_or(0.0, |p| p.revenue),
+            );
+        }
+        for earlier in previous {
+            if !results.iter().any(|c| c.category_id == earlier.category_id) {
+                results.push(CategorySales {
+                    revenue: 0.0,
+                    quantity_sold: 0.0,
+                    order_count: 0,
+                    revenue_share: 0.0,
+                    gross_profit: None,
+                    previous_revenue: Some(earlier.revenue),
+                    ..earlier
+                });
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+/// Get sales by region (grouped by town)
+#[tauri::command]
+pub fn get_sales_by_region(
+    start_date: String,
+    end_date: String,
+    db: State<Database>,
+) -> Result<Vec<RegionSales>, String> {
+    log::info!("get_sales_by_region called: {} to {}", start_date, end_date);
+
+    let conn = db.get_conn()?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT
+                COALESCE(state, 'Unknown') as state,
+                district,
+                COALESCE(town, 'Unknown') as town,
+                COALESCE(SUM(total_amount), 0.0) as revenue,
+                COUNT(*) as order_count
+             FROM invoices
+             WHERE created_at >= datetime(?1)
+               AND created_at < datetime(?2, '+1 day')
+               AND town IS NOT NULL AND town != ''
+             GROUP BY town
+             ORDER BY revenue DESC"
+        )
+        .map_err(|e| e.to_string())?;
+
+    let results = stmt
+        .query_map([&start_date, &end_date], |row| {
             Ok(RegionSales {
                 state: row.get(0)?,
                 district: row.get(1)?,
@@ -838,51 +1314,50 @@ pub fn get_customer_analytics(
     log::info!("get_customer_analytics called: {} to {}", start_date, end_date);
 
     let conn = db.get_conn()?;
+    let range = ReportRange::load(&conn, &start_date, &end_date)?;
+    let previous = range.previous();
+
+    let count = |sql: &str, params: &[&String]| -> Result<i32, String> {
+        conn.query_row(sql, rusqlite::params_from_iter(params), |row| row.get(0))
+            .map_err(|e| format!("Failed to calculate customer analytics: {}", e))
+    };
+    let current_params = [&range.start_utc, &range.end_utc];
 
     // Total customers with orders in period
-    let total_customers: i32 = conn
-        .query_row(
-            "SELECT COUNT(DISTINCT customer_id) FROM invoices
-             WHERE customer_id IS NOT NULL
-               AND created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')",
-            [&start_date, &end_date],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
+    let total_customers = count(
+        "SELECT COUNT(DISTINCT customer_id) FROM invoices
+         WHERE customer_id IS NOT NULL
+           AND datetime(created_at) >= ?1
+           AND datetime(created_at) < ?2",
+        &current_params,
+    )?;
 
     // New customers (first order in this period)
-    let new_customers: i32 = conn
-        .query_row(
-            "SELECT COUNT(DISTINCT customer_id) FROM invoices i1
-             WHERE customer_id IS NOT NULL
-               AND created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')
-               AND NOT EXISTS (
-                   SELECT 1 FROM invoices i2
-                   WHERE i2.customer_id = i1.customer_id
-                     AND i2.created_at < datetime(?1)
-               )",
-            [&start_date, &end_date],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
-
-    // Repeat customers (more than 1 order ever)
-    let repeat_customers: i32 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM (
-                SELECT customer_id FROM invoices
-                WHERE customer_id IS NOT NULL
-                  AND created_at >= datetime(?1)
-                  AND created_at < datetime(?2, '+1 day')
-                GROUP BY customer_id
-                HAVING COUNT(*) > 1
-             )",
-            [&start_date, &end_date],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
+    let new_customers = count(
+        "SELECT COUNT(DISTINCT customer_id) FROM invoices i1
+         WHERE customer_id IS NOT NULL
+           AND datetime(created_at) >= ?1
+           AND datetime(created_at) < ?2
+           AND NOT EXISTS (
+               SELECT 1 FROM invoices i2
+               WHERE i2.customer_id = i1.customer_id
+                 AND datetime(i2.created_at) < ?1
+           )",
+        &current_params,
+    )?;
+
+    // Repeat customers (more than 1 order in the period)
+    let repeat_customers = count(
+        "SELECT COUNT(*) FROM (
+            SELECT customer_id FROM invoices
+            WHERE customer_id IS NOT NULL
+              AND datetime(created_at) >= ?1
+              AND datetime(created_at) < ?2
+            GROUP BY customer_id
+            HAVING COUNT(*) > 1
+         )",
+        &current_params,
+    )?;
 
     let repeat_rate = if total_customers > 0 {
         (repeat_customers as f64 / total_customers as f64) * 100.0
@@ -890,26 +1365,63 @@ pub fn get_customer_analytics(
         0.0
     };
 
-    // Average lifetime value
-    let avg_lifetime_value: f64 = conn
+    // Period and lifetime spend, both averaged over the customers active in the period
+    let (avg_period_value, avg_lifetime_value): (f64, f64) = conn
         .query_row(
-            "SELECT COALESCE(AVG(total), 0.0) FROM (
-                SELECT SUM(total_amount) as total
+            "WITH active AS (
+                SELECT customer_id, SUM(total_amount) AS period_total
                 FROM invoices
                 WHERE customer_id IS NOT NULL
+                  AND datetime(created_at) >= ?1
+                  AND datetime(created_at) < ?2
                 GROUP BY customer_id
-             )",
-            [],
-            |row| row.get(0),
+            )
+            SELECT
+                COALESCE(AVG(a.period_total), 0.0),
+                COALESCE(AVG((SELECT SUM(total_amount) FROM invoices i WHERE i.customer_id = a.customer_id)), 0.0)
+            FROM active a",
+            [&range.start_utc, &range.end_utc],
+            |row| Ok((row.get(0)?, row.get(1)?)),
         )
-        .unwrap_or(0.0);
+        .map_err(|e| format!("Failed to calculate customer value: {}", e))?;
+
+    // Churn: customers from the previous equal-length period who did not buy again in this one
+    let previous_customers = count(
+        "SELECT COUNT(DISTINCT customer_id) FROM invoices
+         WHERE customer_id IS NOT NULL
+           AND datetime(created_at) >= ?1
+           AND datetime(created_at) < ?2",
+        &[&previous.start_utc, &previous.end_utc],
+    )?;
+    let churned_customers = count(
+        "SELECT COUNT(DISTINCT customer_id) FROM invoices p
+         WHERE customer_id IS NOT NULL
+           AND datetime(created_at) >= ?1
+           AND datetime(created_at) < ?2
+           AND NOT EXISTS (
+               SELECT 1 FROM invoices c
+               WHERE c.customer_id = p.customer_id
+                 AND datetime(c.created_at) >= ?3
+                 AND datetime(c.created_at) < ?4
+           )",
+        &[&previous.start_utc, &previous.end_utc, &range.start_utc, &range.end_utc],
+    )?;
+
+    let churn_rate = if previous_customers > 0 {
+        (churned_customers as f64 / previous_customers as f64) * 100.0
+    } else {
+        0.0
+    };
 
     Ok(CustomerAnalytics {
         total_customers,
         new_customers,
         repeat_customers,
         repeat_rate,
+        avg_period_value,
         avg_lifetime_value,
+        churned_customers,
+        churn_rate,
     })
 }
 
@@ -924,9 +1436,18 @@ pub fn get_top_customers(
     log::info!("get_top_customers called: {} to {}, limit {}", start_date, end_date, limit);
 
     let conn = db.get_conn()?;
+    get_top_customers_internal(&conn, &start_date, &end_date, limit)
+}
+
+fn get_top_customers_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    limit: i32,
+) -> Result<Vec<TopCustomer>, String> {
+    let range = ReportRange::load(conn, start_date, end_date)?;
 
-    let query = format!(
-        "SELECT
+    let query = "SELECT
             c.id,
             c.name,
             c.phone,
@@ -934,18 +1455,16 @@ pub fn get_top_customers(
             COUNT(i.id) as order_count
          FROM customers c
          JOIN invoices i ON c.id = i.customer_id
-         WHERE i.created_at >= datetime(?1)
-           AND i.created_at < datetime(?2, '+1 day')
+         WHERE datetime(i.created_at) >= ?1
+           AND datetime(i.created_at) < ?2
          GROUP BY c.id
          ORDER BY total_spent DESC
-         LIMIT {}",
-        limit
-    );
+         LIMIT ?3";
 
-    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
 
     let results = stmt
-        .query_map([&start_date, &end_date], |row| {
+        .query_map(rusqlite::params![&range.start_utc, &range.end_utc, clamp_limit(limit)], |row| {
             let total_spent: f64 = row.get(3)?;
             let order_count: i32 = row.get(4)?;
             Ok(TopCustomer {
@@ -975,43 +1494,42 @@ pub fn get_customer_trend(
     log::info!("get_customer_trend called: {} to {} ({})", start_date, end_date, granularity);
 
     let conn = db.get_conn()?;
-
-    let date_format = match granularity.as_str() {
-        "weekly" => "%Y-W%W",
-        "monthly" => "%Y-%m",
-        _ => "%Y-%m-%d",
-    };
+    let range = ReportRange::load(&conn, &start_date, &end_date)?;
+    let granularity = TrendGranularity::parse(&granularity);
 
     let mut stmt = conn
         .prepare(&format!(
             "WITH first_orders AS (
-                SELECT customer_id, MIN(created_at) as first_order_date
+                SELECT customer_id, MIN(datetime(created_at)) as first_order_date
                 FROM invoices
                 WHERE customer_id IS NOT NULL
                 GROUP BY customer_id
             )
             SELECT
-                strftime('{}', first_order_date) as period,
+                {} as period,
                 COUNT(*) as new_customers
             FROM first_orders
-            WHERE first_order_date >= datetime(?1)
-              AND first_order_date < datetime(?2, '+1 day')
-            GROUP BY period
-            ORDER BY period ASC",
-            date_format
+            WHERE first_order_date >= ?1
+              AND first_order_date < ?2
+            GROUP BY period",
+            granularity.bucket_sql("first_order_date", Some("?3"))
         ))
         .map_err(|e| e.to_string())?;
 
-    let mut cumulative = 0;
-    let results = stmt
-        .query_map([&start_date, &end_date], |row| {
+    let by_period: HashMap<String, i32> = stmt
+        .query_map([&range.start_utc, &range.end_utc, &range.local_modifier], |row| {
             Ok((row.get::<_, String>(0)?, row.get::<_, i32>(1)?))
         })
         .map_err(|e| e.to_string())?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut cumulative = 0;
+    let results = granularity
+        .periods(range.start_date, range.end_date)
         .into_iter()
-        .map(|(date, new_count)| {
+        .map(|date| {
+            let new_count = by_period.get(&date).copied().unwrap_or(0);
             cumulative += new_count;
             CustomerTrendPoint {
                 date,
@@ -1030,8 +1548,11 @@ pub fn get_inventory_health(db: State<Database>) -> Result<InventoryHealth, Stri
     log::info!("get_inventory_health called");
 
     let conn = db.get_conn()?;
+    get_inventory_health_internal(&conn)
+}
 
-    let (total, low, out, valuation, avg): (i32, i32, i32, f64, f64) = conn
+pub(crate) fn get_inventory_health_internal(conn: &Connection) -> Result<InventoryHealth, String> {
+    let (total, low, out, mut valuation, avg): (i32, i32, i32, f64, f64) = conn
         .query_row(
             "SELECT
                 COUNT(*),
@@ -1045,6 +1566,23 @@ pub fn get_inventory_health(db: State<Database>) -> Result<InventoryHealth, Stri
         )
         .map_err(|e| e.to_string())?;
 
+    let method = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            [INVENTORY_VALUATION_METHOD_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .ok();
+    if method.as_deref() == Some("fifo") {
+        valuation = conn
+            .query_row(
+                "SELECT COALESCE(SUM(quantity_remaining * unit_cost), 0.0) FROM inventory_batch_costs WHERE quantity_remaining > 0",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+    }
+
     Ok(InventoryHealth {
         total_products: total,
         low_stock_count: low,
@@ -1055,15 +1593,108 @@ pub fn get_inventory_health(db: State<Database>) -> Result<InventoryHealth, Stri
     })
 }
 
-/// Get low stock alerts with sales velocity
+/// Value stock on hand at cost (remaining FIFO batches) and at the current selling price.
+/// `category_id` includes its subcategories. Variant parents and bundles hold no stock and are
+/// left out; products whose batches don't add up to stock_quantity are flagged.
+#[tauri::command]
+pub fn get_inventory_valuation(
+    category_id: Option<i32>,
+    supplier_id: Option<i32>,
+    db: State<Database>,
+) -> Result<InventoryValuation, String> {
+    log::info!(
+        "get_inventory_valuation called: category_id: {:?}, supplier_id: {:?}",
+        category_id,
+        supplier_id
+    );
+
+    let conn = db.get_conn()?;
+    get_inventory_valuation_internal(&conn, category_id, supplier_id)
+}
+
+pub(crate) fn get_inventory_valuation_internal(
+    conn: &Connection,
+    category_id: Option<i32>,
+    supplier_id: Option<i32>,
+) -> Result<InventoryValuation, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "WITH {batch_costs}
+            SELECT p.id, {display_name}, p.sku, COALESCE(cat.name, p.category), s.name,
+                   p.stock_quantity, COALESCE(bc.qty, 0), COALESCE(bc.cost, 0.0), p.selling_price
+             FROM products p
+             LEFT JOIN batch_costs bc ON bc.product_id = p.id
+             LEFT JOIN categories cat ON cat.id = p.category_id
+             LEFT JOIN suppliers s ON s.id = p.supplier_id
+             WHERE COALESCE(p.is_bundle, 0) = 0
+               AND NOT EXISTS (SELECT 1 FROM products v WHERE v.parent_product_id = p.id)
+               AND (p.stock_quantity <> 0 OR COALESCE(bc.qty, 0) <> 0)
+               AND (?1 IS NULL OR p.category_id = ?1 OR p.category_id IN (SELECT id FROM categories WHERE parent_id = ?1))
+               AND (?2 IS NULL OR p.supplier_id = ?2)
+             ORDER BY COALESCE(bc.cost, 0.0) DESC, p.name",
+            batch_costs = BATCH_COSTS_CTE,
+            display_name = crate::commands::variants::DISPLAY_NAME_SQL,
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let products = stmt
+        .query_map(rusqlite::params![category_id, supplier_id], |row| {
+            let stock_quantity: f64 = row.get(5)?;
+            let batch_quantity = quantity::round_quantity(row.get(6)?);
+            let cost_value = money::round_money(row.get(7)?);
+            let selling_price: Option<f64> = row.get(8)?;
+            let retail_value = selling_price.map(|price| money::line_total(price, stock_quantity));
+            Ok(ProductValuation {
+                product_id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                category: row.get(3)?,
+                supplier_name: row.get(4)?,
+                stock_quantity,
+                batch_quantity,
+                cost_value,
+                selling_price,
+                retail_value,
+                potential_margin: retail_value.map(|retail| money::sub(retail, cost_value)),
+                quantity_mismatch: quantity::to_milli(batch_quantity) != quantity::to_milli(stock_quantity),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to value inventory: {}", e))?;
+
+    let priced = || products.iter().filter(|p| p.retail_value.is_some());
+    let total_retail_value = money::sum(priced().filter_map(|p| p.retail_value));
+    let total_potential_margin = money::sum(priced().filter_map(|p| p.potential_margin));
+
+    Ok(InventoryValuation {
+        total_cost_value: money::sum(products.iter().map(|p| p.cost_value)),
+        total_retail_value,
+        total_potential_margin,
+        margin_percent: if total_retail_value > 0.0 {
+            money::round_money(total_potential_margin / total_retail_value * 100.0)
+        } else {
+            0.0
+        },
+        mismatch_count: products.iter().filter(|p| p.quantity_mismatch).count() as i32,
+        unpriced_count: products.iter().filter(|p| p.retail_value.is_none() && p.stock_quantity > 0.0).count() as i32,
+        products,
+    })
+}
+
+/// Get low stock alerts with sales velocity. Stock on open purchase orders counts towards the
+/// threshold when low_stock_counts_on_order is set, so products already reordered drop out.
 #[tauri::command]
 pub fn get_low_stock_alerts(db: State<Database>) -> Result<Vec<LowStockAlert>, String> {
     log::info!("get_low_stock_alerts called");
 
     let conn = db.get_conn()?;
+    get_low_stock_alerts_internal(&conn)
+}
 
+pub(crate) fn get_low_stock_alerts_internal(conn: &Connection) -> Result<Vec<LowStockAlert>, String> {
     let mut stmt = conn
-        .prepare(
+        .prepare(&format!(
             "SELECT
                 p.id,
                 p.name,
@@ -1077,19 +1708,21 @@ pub fn get_low_stock_alerts(db: State<Database>) -> Result<Vec<LowStockAlert>, S
                      WHERE ii.product_id = p.id
                        AND i.created_at >= datetime('now', '-30 days')
                     ), 0.0
-                ) as avg_daily_sales
+                ) as avg_daily_sales,
+                {on_order} as on_order
              FROM products p
-             WHERE p.stock_quantity < 10
-             ORDER BY p.stock_quantity ASC"
-        )
+             WHERE p.stock_quantity + CASE WHEN ?1 THEN {on_order} ELSE 0 END < 10
+             ORDER BY p.stock_quantity ASC",
+            on_order = ON_ORDER_QUANTITY_SQL
+        ))
         .map_err(|e| e.to_string())?;
 
     let results = stmt
-        .query_map([], |row| {
-            let stock: i32 = row.get(3)?;
+        .query_map([counts_on_order(conn)], |row| {
+            let stock: f64 = row.get(3)?;
             let avg_sales: f64 = row.get(5)?;
             let days_until = if avg_sales > 0.0 {
-                Some((stock as f64 / avg_sales).floor() as i32)
+                Some((stock / avg_sales).floor() as i32)
             } else {
                 None
             };
@@ -1101,6 +1734,7 @@ pub fn get_low_stock_alerts(db: State<Database>) -> Result<Vec<LowStockAlert>, S
                 selling_price: row.get(4)?,
                 avg_daily_sales: avg_sales,
                 days_until_stockout: days_until,
+                on_order_quantity: quantity::round_quantity(row.get(6)?),
             })
         })
         .map_err(|e| e.to_string())?
@@ -1110,6 +1744,284 @@ pub fn get_low_stock_alerts(db: State<Database>) -> Result<Vec<LowStockAlert>, S
     Ok(results)
 }
 
+/// Get products that have not sold in `days` days (or never sold), for clearance planning.
+///
+/// Stock value uses the weighted cost of remaining FIFO batches, falling back to the product
+/// cost price. `sort_by` is "value" (highest stock value first) or "age" (default: never sold,
+/// then oldest last sale first). Out-of-stock products are only included when asked for.
+#[tauri::command]
+pub fn get_slow_moving_stock(
+    days: i32,
+    sort_by: Option<String>,
+    category: Option<String>,
+    include_out_of_stock: Option<bool>,
+    db: State<Database>,
+) -> Result<Vec<SlowMovingProduct>, String> {
+    log::info!(
+        "get_slow_moving_stock called: {} days, sort_by: {:?}, category: {:?}",
+        days,
+        sort_by,
+        category
+    );
+
+    if days < 0 {
+        return Err("Days threshold cannot be negative".to_string());
+    }
+
+    let conn = db.get_conn()?;
+
+    let order_by = match sort_by.as_deref() {
+        Some("value") => "stock_value DESC, p.name ASC",
+        _ => "ls.last_sale IS NOT NULL, ls.last_sale ASC, stock_value DESC",
+    };
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "WITH last_sales AS (
+                SELECT ii.product_id, MAX(datetime(i.created_at)) AS last_sale
+                FROM invoice_items ii
+                JOIN invoices i ON ii.invoice_id = i.id
+                GROUP BY ii.product_id
+            ),
+            {batch_costs},
+            last_purchases AS (
+                SELECT product_id, MAX(transaction_date) AS last_purchase
+                FROM inventory_transactions
+                WHERE transaction_type = 'purchase'
+                GROUP BY product_id
+            )
+            SELECT
+                p.id,
+                p.name,
+                p.sku,
+                p.category,
+                p.stock_quantity,
+                {unit_cost} AS unit_cost,
+                p.stock_quantity * {unit_cost} AS stock_value,
+                ls.last_sale,
+                CAST(julianday('now') - julianday(ls.last_sale) AS INTEGER),
+                lp.last_purchase
+             FROM products p
+             LEFT JOIN last_sales ls ON ls.product_id = p.id
+             LEFT JOIN batch_costs bc ON bc.product_id = p.id
+             LEFT JOIN last_purchases lp ON lp.product_id = p.id
+             WHERE (ls.last_sale IS NULL OR ls.last_sale < datetime('now', ?1))
+               AND (?2 = 1 OR p.stock_quantity > 0)
+               AND (?3 IS NULL OR p.category = ?3 COLLATE NOCASE)
+             ORDER BY {order_by}",
+            batch_costs = BATCH_COSTS_CTE,
+            unit_cost = UNIT_COST_SQL,
+            order_by = order_by
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let results = stmt
+        .query_map(
+            (
+                format!("-{} days", days),
+                include_out_of_stock.unwrap_or(false),
+                category.as_deref().filter(|c| !c.trim().is_empty()),
+            ),
+            |row| {
+                Ok(SlowMovingProduct {
+                    product_id: row.get(0)?,
+                    name: row.get(1)?,
+                    sku: row.get(2)?,
+                    category: row.get(3)?,
+                    stock_quantity: row.get(4)?,
+                    unit_cost: row.get(5)?,
+                    stock_value: row.get(6)?,
+                    last_sale_date: row.get(7)?,
+                    days_since_last_sale: row.get(8)?,
+                    last_purchase_date: row.get(9)?,
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(results)
+}
+
+// ============== Cash Events ==============
+
+/// A kind of money movement. Cashflow figures are built from a union of these: each kind reads
+/// its own table and yields positive amounts per local day, which reports add or subtract by
+/// `sign`.
+///
+/// Sales, purchases and purchase returns count value when the trade happens; customer and
+/// supplier payments count cash when it changes hands. A credit sale shows up both as a sale and
+/// as the payments that settle it, so a report can't combine a kind with one it `overlaps`
+/// (`parse_filter` refuses). A new kind (sales returns, refunds) goes into ALL, and the matches
+/// below make it declare its sign and overlaps; the tests check the declarations hang together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum CashEvent {
+    Sales,
+    Purchases,
+    PurchaseReturns,
+    CustomerPayments,
+    SupplierPayments,
+}
+
+impl CashEvent {
+    pub(crate) const ALL: [CashEvent; 5] = [
+        CashEvent::Sales,
+        CashEvent::Purchases,
+        CashEvent::PurchaseReturns,
+        CashEvent::CustomerPayments,
+        CashEvent::SupplierPayments,
+    ];
+
+    /// Shown when no filter is given: trade value on both sides
+    pub(crate) const DEFAULT: [CashEvent; 3] = [CashEvent::Sales, CashEvent::Purchases, CashEvent::PurchaseReturns];
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            CashEvent::Sales => "sales",
+            CashEvent::Purchases => "purchases",
+            CashEvent::PurchaseReturns => "purchase_returns",
+            CashEvent::CustomerPayments => "customer_payments",
+            CashEvent::SupplierPayments => "supplier_payments",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    /// 1 for money coming in, -1 for money going out
+    pub(crate) fn sign(self) -> f64 {
+        match self {
+            CashEvent::Sales | CashEvent::PurchaseReturns | CashEvent::CustomerPayments => 1.0,
+            CashEvent::Purchases | CashEvent::SupplierPayments => -1.0,
+        }
+    }
+
+    /// Kinds that count some of the same money
+    fn overlaps(self) -> &'static [CashEvent] {
+        match self {
+            CashEvent::Sales => &[CashEvent::CustomerPayments],
+            CashEvent::CustomerPayments => &[CashEvent::Sales],
+            CashEvent::Purchases | CashEvent::PurchaseReturns => &[CashEvent::SupplierPayments],
+            CashEvent::SupplierPayments => &[CashEvent::Purchases, CashEvent::PurchaseReturns],
+        }
+    }
+
+    /// `SELECT day, amount` for this kind, one row per record: day is the local "YYYY-MM-DD" and
+    /// amount is positive. ?1/?2 are the range's UTC bounds and ?3 its local modifier; tables
+    /// holding local dates compare against `date(?1, ?3)`/`date(?2, ?3)`, the local bounds.
+    fn sql(self) -> &'static str {
+        match self {
+            CashEvent::Sales => {
+                "SELECT date(created_at, ?3) AS day, total_amount AS amount FROM invoices
+                 WHERE datetime(created_at) >= ?1 AND datetime(created_at) < ?2"
+            }
+            // order_date and return_date are already local calendar dates
+            CashEvent::Purchases => {
+                "SELECT date(order_date) AS day, total_amount AS amount FROM purchase_orders
+                 WHERE status != 'cancelled' AND date(order_date) >= date(?1, ?3) AND date(order_date) < date(?2, ?3)"
+            }
+            CashEvent::PurchaseReturns => {
+                "SELECT date(return_date) AS day, total_amount AS amount FROM purchase_returns
+                 WHERE date(return_date) >= date(?1, ?3) AND date(return_date) < date(?2, ?3)"
+            }
+            CashEvent::CustomerPayments => {
+                "SELECT date(paid_at, ?3) AS day, amount FROM customer_payments
+                 WHERE datetime(paid_at) >= ?1 AND datetime(paid_at) < ?2"
+            }
+            CashEvent::SupplierPayments => {
+                "SELECT date(paid_at, ?3) AS day, amount FROM supplier_payments
+                 WHERE datetime(paid_at) >= ?1 AND datetime(paid_at) < ?2"
+            }
+        }
+    }
+
+    /// The kinds a report should include, by name; DEFAULT when none are given. Refuses names it
+    /// doesn't know and combinations that would count the same money twice.
+    pub(crate) fn parse_filter(names: Option<&[String]>) -> Result<Vec<CashEvent>, String> {
+        let Some(names) = names.filter(|names| !names.is_empty()) else {
+            return Ok(Self::DEFAULT.to_vec());
+        };
+
+        let mut kinds: Vec<CashEvent> = Vec::new();
+        for name in names {
+            let kind = Self::parse(name.trim()).ok_or_else(|| {
+                let known: Vec<&str> = Self::ALL.iter().map(|kind| kind.name()).collect();
+                format!("Unknown cash event '{}', expected one of: {}", name, known.join(", "))
+            })?;
+            if !kinds.contains(&kind) {
+                kinds.push(kind);
+            }
+        }
+        for kind in &kinds {
+            if let Some(other) = kind.overlaps().iter().find(|other| kinds.contains(other)) {
+                return Err(format!(
+                    "Cash events '{}' and '{}' count the same money and can't be shown together",
+                    kind.name(),
+                    other.name()
+                ));
+            }
+        }
+        Ok(kinds)
+    }
+}
+
+/// Amount per local day and event kind over a range, from one UNION ALL over the kinds' queries
+pub(crate) fn cash_events_by_day(
+    conn: &Connection,
+    range: &ReportRange,
+    kinds: &[CashEvent],
+) -> Result<BTreeMap<NaiveDate, HashMap<CashEvent, f64>>, String> {
+    let mut days: BTreeMap<NaiveDate, HashMap<CashEvent, f64>> = BTreeMap::new();
+    if kinds.is_empty() {
+        return Ok(days);
+    }
+
+    let events = kinds
+        .iter()
+        .enumerate()
+        .map(|(index, kind)| format!("SELECT {} AS kind, day, amount FROM ({})", index, kind.sql()))
+        .collect::<Vec<_>>()
+        .join("\n UNION ALL\n ");
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT kind, day, COALESCE(SUM(amount), 0.0) FROM ({}) GROUP BY kind, day",
+            events
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([&range.start_utc, &range.end_utc, &range.local_modifier], |row| {
+            Ok((row.get::<_, usize>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, f64>(2)?))
+        })
+        .map_err(|e| e.to_string())?;
+
+    for row in rows {
+        let (index, day, amount) = row.map_err(|e| format!("Failed to load cash events: {}", e))?;
+        // A record whose date doesn't parse can't be placed on a day
+        let Some(day) = day.and_then(|day| NaiveDate::parse_from_str(&day, "%Y-%m-%d").ok()) else {
+            continue;
+        };
+        *days.entry(day).or_default().entry(kinds[index]).or_insert(0.0) += amount;
+    }
+    Ok(days)
+}
+
+/// Total per event kind over a range
+pub(crate) fn cash_event_totals(
+    conn: &Connection,
+    range: &ReportRange,
+    kinds: &[CashEvent],
+) -> Result<HashMap<CashEvent, f64>, String> {
+    let mut totals: HashMap<CashEvent, f64> = HashMap::new();
+    for day in cash_events_by_day(conn, range, kinds)?.values() {
+        for (kind, amount) in day {
+            *totals.entry(*kind).or_insert(0.0) += amount;
+        }
+    }
+    Ok(totals)
+}
+
 /// Get purchase analytics
 /// Total Purchases = Sum of "Stock Amount" from inventory page = SUM(initial_stock * price) + SUM(received PO items cost)
 /// Amount Paid = Sum of all supplier payments
@@ -1123,7 +2035,14 @@ pub fn get_purchase_analytics(
     log::info!("get_purchase_analytics called: {} to {}", start_date, end_date);
 
     let conn = db.get_conn()?;
+    get_purchase_analytics_internal(&conn, &start_date, &end_date)
+}
 
+pub(crate) fn get_purchase_analytics_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<PurchaseAnalytics, String> {
     // Total Purchases = Sum of "Stock Amount" from inventory page
     // This matches the total_purchased_cost calculation in products.rs:
     // COALESCE(initial_stock * price, 0) + COALESCE(SUM(received PO items cost), 0)
@@ -1140,7 +2059,7 @@ pub fn get_purchase_analytics(
     // Part 2: Sum of all received PO items cost (Purchase Order Item * Unit Cost)
     let po_received_cost: f64 = conn
         .query_row(
-            "SELECT COALESCE(SUM(poi.quantity * poi.unit_cost), 0.0)
+            "SELECT COALESCE(SUM(poi.quantity * poi.unit_cost * po.exchange_rate), 0.0)
              FROM purchase_order_items poi
              JOIN purchase_orders po ON poi.po_id = po.id
              WHERE po.status = 'received'",
@@ -1152,27 +2071,27 @@ pub fn get_purchase_analytics(
     // Total Purchases = initial stock value + received PO items cost
     let total_purchases = initial_stock_total + po_received_cost;
 
-    // Amount Paid = Sum of all supplier payments
-    let total_paid: f64 = conn
-        .query_row(
-            "SELECT COALESCE(SUM(amount), 0.0) FROM supplier_payments",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+    // Amount Paid = all supplier payments; returns = all goods sent back to suppliers
+    let totals = cash_event_totals(
+        conn,
+        &ReportRange::all_time(business_offset_minutes(conn)),
+        &[CashEvent::SupplierPayments, CashEvent::PurchaseReturns],
+    )?;
+    let total_paid = money::round_money(totals.get(&CashEvent::SupplierPayments).copied().unwrap_or(0.0));
+    let total_returns = money::round_money(totals.get(&CashEvent::PurchaseReturns).copied().unwrap_or(0.0));
 
-    // Pending = Total Purchases - Amount Paid
-    let pending_payments = (total_purchases - total_paid).max(0.0);
+    // Pending = Total Purchases - Returns - Amount Paid
+    let pending_payments = money::sub(money::sub(total_purchases, total_returns), total_paid).max(0.0);
 
-    log::info!("Dashboard: TotalPurchases={} (initial={}, po={}), TotalPaid={}, Pending={}",
-               total_purchases, initial_stock_total, po_received_cost, total_paid, pending_payments);
+    log::info!("Dashboard: TotalPurchases={} (initial={}, po={}), TotalReturns={}, TotalPaid={}, Pending={}",
+               total_purchases, initial_stock_total, po_received_cost, total_returns, total_paid, pending_payments);
 
     // Active suppliers is still filtered by date range
     let active_suppliers: i32 = conn
         .query_row(
             "SELECT COUNT(DISTINCT supplier_id) FROM purchase_orders
              WHERE order_date >= ?1 AND order_date <= ?2",
-            [&start_date, &end_date],
+            [start_date, end_date],
             |row| row.get(0),
         )
         .unwrap_or(0);
@@ -1182,7 +2101,7 @@ pub fn get_purchase_analytics(
         .query_row(
             "SELECT COUNT(*) FROM purchase_orders
              WHERE order_date >= ?1 AND order_date <= ?2",
-            [&start_date, &end_date],
+            [start_date, end_date],
             |row| row.get(0),
         )
         .unwrap_or(0);
@@ -1190,77 +2109,373 @@ pub fn get_purchase_analytics(
     Ok(PurchaseAnalytics {
         total_purchases,
         total_paid,
+        total_returns,
         pending_payments,
         active_suppliers,
         purchase_order_count: po_count,
     })
 }
 
-/// Get cashflow trend (sales vs purchases)
+/// Get cashflow trend. `events` picks the series (see CashEvent), defaulting to sales,
+/// purchases and purchase returns; net adds money in and subtracts money out over those.
 #[tauri::command]
 pub fn get_cashflow_trend(
     start_date: String,
     end_date: String,
     granularity: String,
+    events: Option<Vec<String>>,
     db: State<Database>,
 ) -> Result<Vec<CashflowPoint>, String> {
-    log::info!("get_cashflow_trend called: {} to {} ({})", start_date, end_date, granularity);
+    log::info!("get_cashflow_trend called: {} to {} ({}), events: {:?}", start_date, end_date, granularity, events);
 
     let conn = db.get_conn()?;
+    get_cashflow_trend_internal(&conn, &start_date, &end_date, &granularity, events.as_deref())
+}
 
-    let date_format = match granularity.as_str() {
-        "weekly" => "%Y-W%W",
-        "monthly" => "%Y-%m",
-        _ => "%Y-%m-%d",
-    };
+pub(crate) fn get_cashflow_trend_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    granularity: &str,
+    events: Option<&[String]>,
+) -> Result<Vec<CashflowPoint>, String> {
+    let kinds = CashEvent::parse_filter(events)?;
+    let range = ReportRange::load(conn, start_date, end_date)?;
+    let granularity = TrendGranularity::parse(granularity);
+
+    let mut buckets: HashMap<String, HashMap<CashEvent, f64>> = HashMap::new();
+    for (day, amounts) in cash_events_by_day(conn, &range, &kinds)? {
+        let bucket = buckets.entry(granularity.label(granularity.bucket_start(day))).or_default();
+        for (kind, amount) in amounts {
+            *bucket.entry(kind).or_insert(0.0) += amount;
+        }
+    }
+
+    let results = granularity
+        .periods(range.start_date, range.end_date)
+        .into_iter()
+        .map(|date| {
+            let amounts = buckets.remove(&date).unwrap_or_default();
+            let amount = |kind: CashEvent| money::round_money(amounts.get(&kind).copied().unwrap_or(0.0));
+            CashflowPoint {
+                sales: amount(CashEvent::Sales),
+                purchases: amount(CashEvent::Purchases),
+                purchase_returns: amount(CashEvent::PurchaseReturns),
+                customer_payments: amount(CashEvent::CustomerPayments),
+                supplier_payments: amount(CashEvent::SupplierPayments),
+                net: money::sum(kinds.iter().map(|kind| kind.sign() * amount(*kind))),
+                date,
+            }
+        })
+        .collect();
+
+    Ok(results)
+}
+
+/// Profit & loss for a period: revenue, FIFO COGS, gross profit, operating expenses, net profit
+#[tauri::command]
+pub fn get_profit_and_loss(
+    start_date: String,
+    end_date: String,
+    db: State<Database>,
+) -> Result<ProfitAndLoss, String> {
+    log::info!("get_profit_and_loss called: {} to {}", start_date, end_date);
+
+    let conn = db.get_conn()?;
+    let range = ReportRange::load(&conn, &start_date, &end_date)?;
+
+    let totals = revenue_totals(&conn, &range)?;
+    let revenue = totals.gross_sales - totals.tax;
+
+    // Sale transactions store the average FIFO cost per unit for that invoice line
+    let cogs: f64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(-t.quantity_change * COALESCE(t.unit_cost, 0)), 0.0)
+             FROM inventory_transactions t
+             JOIN invoices i ON t.reference_id = i.id
+             WHERE t.transaction_type = 'sale'
+               AND t.reference_type = 'invoice'
+               AND datetime(i.created_at) >= ?1
+               AND datetime(i.created_at) < ?2",
+            [&range.start_utc, &range.end_utc],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to calculate COGS: {}", e))?;
+
+    // expense_date is a local calendar date, so compare against the local range
+    let mut stmt = conn
+        .prepare(
+            "SELECT category, SUM(amount)
+             FROM expenses
+             WHERE expense_date >= ?1 AND expense_date <= ?2
+             GROUP BY category
+             ORDER BY SUM(amount) DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let expenses_by_category = stmt
+        .query_map(
+            [
+                range.start_date.format("%Y-%m-%d").to_string(),
+                range.end_date.format("%Y-%m-%d").to_string(),
+            ],
+            |row| {
+                Ok(ExpenseCategoryTotal {
+                    category: row.get(0)?,
+                    amount: row.get(1)?,
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let total_expenses: f64 = expenses_by_category.iter().map(|e| e.amount).sum();
+    let gross_profit = revenue - cogs;
+    let net_profit = gross_profit - total_expenses;
+    let margin = |value: f64| if revenue > 0.0 { value / revenue * 100.0 } else { 0.0 };
+
+    Ok(ProfitAndLoss {
+        revenue,
+        discounts: totals.discount,
+        tax_collected: totals.tax,
+        cogs,
+        gross_profit,
+        gross_margin_percent: margin(gross_profit),
+        expenses_by_category,
+        total_expenses,
+        net_profit,
+        net_margin_percent: margin(net_profit),
+    })
+}
+
+/// Revenue per product aggregated by get_abc_analysis, before classification
+struct AbcInput {
+    product_id: i32,
+    name: String,
+    sku: String,
+    revenue: f64,
+    stock_quantity: f64,
+    stock_value: f64,
+}
+
+/// Assign A/B/C classes by cumulative revenue share.
+///
+/// Products are ranked by revenue; a product belongs to A while the cumulative share before it is
+/// under `a_cutoff`, to B while under `a_cutoff + b_cutoff`, and to C otherwise. Products without
+/// revenue are always C.
+fn classify_abc(mut rows: Vec<AbcInput>, a_cutoff: f64, b_cutoff: f64) -> AbcAnalysis {
+    rows.sort_by(|a, b| {
+        b.revenue
+            .partial_cmp(&a.revenue)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    let total_revenue: f64 = rows.iter().map(|r| r.revenue.max(0.0)).sum();
+    let mut classes: Vec<AbcClassSummary> = ["A", "B", "C"]
+        .iter()
+        .map(|class| AbcClassSummary {
+            class: class.to_string(),
+            product_count: 0,
+            revenue: 0.0,
+            revenue_share_percent: 0.0,
+            stock_value: 0.0,
+        })
+        .collect();
+
+    let mut cumulative = 0.0;
+    let mut products = Vec::with_capacity(rows.len());
+    for row in rows {
+        let revenue = row.revenue.max(0.0);
+        let share = if total_revenue > 0.0 { revenue / total_revenue * 100.0 } else { 0.0 };
+        let class_index = if revenue <= 0.0 {
+            2
+        } else if cumulative < a_cutoff {
+            0
+        } else if cumulative < a_cutoff + b_cutoff {
+            1
+        } else {
+            2
+        };
+        cumulative += share;
+
+        let summary = &mut classes[class_index];
+        summary.product_count += 1;
+        summary.revenue += revenue;
+        summary.stock_value += row.stock_value;
+
+        products.push(AbcProduct {
+            product_id: row.product_id,
+            name: row.name,
+            sku: row.sku,
+            revenue,
+            share_percent: share,
+            cumulative_percent: cumulative.min(100.0),
+            class: summary.class.clone(),
+            stock_quantity: row.stock_quantity,
+            stock_value: row.stock_value,
+        });
+    }
+
+    for summary in classes.iter_mut() {
+        if total_revenue > 0.0 {
+            summary.revenue_share_percent = summary.revenue / total_revenue * 100.0;
+        }
+    }
+
+    AbcAnalysis {
+        a_cutoff_percent: a_cutoff,
+        b_cutoff_percent: b_cutoff,
+        total_revenue,
+        products,
+        classes,
+    }
+}
+
+/// ABC analysis of products by revenue contribution over a period.
+///
+/// `a_cutoff`/`b_cutoff` are cumulative revenue percentages (default 70 and 20; C takes the rest).
+/// Every product is included; products that did not sell in the period fall into C.
+#[tauri::command]
+pub fn get_abc_analysis(
+    start_date: String,
+    end_date: String,
+    a_cutoff: Option<f64>,
+    b_cutoff: Option<f64>,
+    db: State<Database>,
+) -> Result<AbcAnalysis, String> {
+    log::info!(
+        "get_abc_analysis called: {} to {}, cutoffs {:?}/{:?}",
+        start_date,
+        end_date,
+        a_cutoff,
+        b_cutoff
+    );
+
+    let a_cutoff = a_cutoff.unwrap_or(70.0);
+    let b_cutoff = b_cutoff.unwrap_or(20.0);
+    if a_cutoff <= 0.0 || b_cutoff < 0.0 || a_cutoff + b_cutoff > 100.0 {
+        return Err("Cutoffs must be positive and add up to at most 100%".to_string());
+    }
+
+    let conn = db.get_conn()?;
+    let range = ReportRange::load(&conn, &start_date, &end_date)?;
 
     let mut stmt = conn
         .prepare(&format!(
-            "WITH sales_data AS (
-                SELECT strftime('{}', created_at) as period, SUM(total_amount) as amount
-                FROM invoices
-                WHERE created_at >= datetime(?1)
-                  AND created_at < datetime(?2, '+1 day')
-                GROUP BY period
-            ),
-            purchase_data AS (
-                SELECT strftime('{}', order_date) as period, SUM(total_amount) as amount
-                FROM purchase_orders
-                WHERE order_date >= ?1 AND order_date <= ?2
-                GROUP BY period
+            "WITH product_revenue AS (
+                SELECT ii.product_id,
+                       SUM(ii.quantity * ii.unit_price - COALESCE(ii.discount_amount, 0)) AS revenue
+                FROM invoice_items ii
+                JOIN invoices i ON ii.invoice_id = i.id
+                WHERE datetime(i.created_at) >= ?1
+                  AND datetime(i.created_at) < ?2
+                GROUP BY ii.product_id
             ),
-            all_periods AS (
-                SELECT period FROM sales_data
-                UNION
-                SELECT period FROM purchase_data
-            )
+            {batch_costs}
             SELECT
-                ap.period,
-                COALESCE(s.amount, 0.0) as sales,
-                COALESCE(p.amount, 0.0) as purchases
-            FROM all_periods ap
-            LEFT JOIN sales_data s ON ap.period = s.period
-            LEFT JOIN purchase_data p ON ap.period = p.period
-            ORDER BY ap.period ASC",
-            date_format, date_format
+                p.id,
+                p.name,
+                p.sku,
+                COALESCE(pr.revenue, 0.0),
+                p.stock_quantity,
+                p.stock_quantity * {unit_cost}
+             FROM products p
+             LEFT JOIN product_revenue pr ON pr.product_id = p.id
+             LEFT JOIN batch_costs bc ON bc.product_id = p.id",
+            batch_costs = BATCH_COSTS_CTE,
+            unit_cost = UNIT_COST_SQL
         ))
         .map_err(|e| e.to_string())?;
 
-    let results = stmt
-        .query_map([&start_date, &end_date], |row| {
-            let sales: f64 = row.get(1)?;
-            let purchases: f64 = row.get(2)?;
-            Ok(CashflowPoint {
-                date: row.get(0)?,
-                sales,
-                purchases,
-                net: sales - purchases,
+    let rows = stmt
+        .query_map([&range.start_utc, &range.end_utc], |row| {
+            Ok(AbcInput {
+                product_id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                revenue: row.get(3)?,
+                stock_quantity: row.get(4)?,
+                stock_value: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
+    Ok(classify_abc(rows, a_cutoff, b_cutoff))
+}
+
+/// SQL giving (weekday, hour) of `column` in local time: weekday 0 = Monday, hour 0-23
+fn weekday_hour_sql(column: &str, modifier: &str) -> (String, String) {
+    (
+        format!("(CAST(strftime('%w', {c}, {m}) AS INTEGER) + 6) % 7", c = column, m = modifier),
+        format!("CAST(strftime('%H', {c}, {m}) AS INTEGER)", c = column, m = modifier),
+    )
+}
+
+/// Order count and revenue by day of week and hour of day, for staffing.
+///
+/// Returns a flat list of every weekday x hour cell (7 x 24, empty cells as zero), or one cell
+/// per weekday with `hour: None` when `granularity` is "weekday".
+#[tauri::command]
+pub fn get_sales_heatmap(
+    start_date: String,
+    end_date: String,
+    granularity: Option<String>,
+    db: State<Database>,
+) -> Result<Vec<HeatmapCell>, String> {
+    log::info!(
+        "get_sales_heatmap called: {} to {} ({:?})",
+        start_date,
+        end_date,
+        granularity
+    );
+
+    let conn = db.get_conn()?;
+    let range = ReportRange::load(&conn, &start_date, &end_date)?;
+    let by_hour = granularity.as_deref() != Some("weekday");
+
+    let (weekday_sql, hour_sql) = weekday_hour_sql("created_at", "?3");
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT
+                {} AS weekday,
+                {} AS hour,
+                COUNT(*),
+                COALESCE(SUM(total_amount), 0.0)
+             FROM invoices
+             WHERE datetime(created_at) >= ?1
+               AND datetime(created_at) < ?2
+             GROUP BY weekday, hour",
+            weekday_sql,
+            if by_hour { hour_sql.as_str() } else { "NULL" }
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let cells: HashMap<(i32, Option<i32>), (i32, f64)> = stmt
+        .query_map([&range.start_utc, &range.end_utc, &range.local_modifier], |row| {
+            Ok(((row.get(0)?, row.get(1)?), (row.get(2)?, row.get(3)?)))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let hours: Vec<Option<i32>> = if by_hour { (0..24).map(Some).collect() } else { vec![None] };
+    let mut results = Vec::with_capacity(7 * hours.len());
+    for weekday in 0..7 {
+        for hour in &hours {
+            let (orders, revenue) = cells.get(&(weekday, *hour)).copied().unwrap_or((0, 0.0));
+            results.push(HeatmapCell {
+                weekday,
+                hour: *hour,
+                orders,
+                revenue,
+            });
+        }
+    }
+
     Ok(results)
 }
 
@@ -1276,8 +2491,7 @@ pub fn get_top_suppliers(
 
     let conn = db.get_conn()?;
 
-    let query = format!(
-        "SELECT
+    let query = "SELECT
             s.id,
             s.name,
             COALESCE(SUM(po.total_amount), 0.0) as total_spent,
@@ -1289,14 +2503,12 @@ pub fn get_top_suppliers(
          WHERE po.order_date >= ?1 AND po.order_date <= ?2
          GROUP BY s.id
          ORDER BY total_spent DESC
-         LIMIT {}",
-        limit
-    );
+         LIMIT ?3";
 
-    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(query).map_err(|e| e.to_string())?;
 
     let results = stmt
-        .query_map([&start_date, &end_date], |row| {
+        .query_map(rusqlite::params![&start_date, &end_date, clamp_limit(limit)], |row| {
             Ok(TopSupplier {
                 supplier_id: row.get(0)?,
                 supplier_name: row.get(1)?,
@@ -1312,6 +2524,11 @@ pub fn get_top_suppliers(
     Ok(results)
 }
 
+/// Tax of invoice `i`: the sum of its item-level taxes when present, else the invoice tax_amount
+const INVOICE_TAX_SQL: &str = "COALESCE(
+    (SELECT SUM(ii.tax_amount) FROM invoice_items ii WHERE ii.invoice_id = i.id AND ii.tax_amount IS NOT NULL),
+    i.tax_amount)";
+
 /// Get tax summary
 #[tauri::command]
 pub fn get_tax_summary(
@@ -1322,38 +2539,51 @@ pub fn get_tax_summary(
     log::info!("get_tax_summary called: {} to {}", start_date, end_date);
 
     let conn = db.get_conn()?;
+    get_tax_summary_internal(&conn, &start_date, &end_date)
+}
+
+fn get_tax_summary_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<TaxSummary, String> {
+    let range = ReportRange::load(conn, start_date, end_date)?;
 
     let (total_tax, cgst, sgst, igst): (f64, f64, f64, f64) = conn
         .query_row(
-            "SELECT
-                COALESCE(SUM(tax_amount), 0.0),
-                COALESCE(SUM(cgst_amount), 0.0),
-                COALESCE(SUM(sgst_amount), 0.0),
-                COALESCE(SUM(igst_amount), 0.0)
-             FROM invoices
-             WHERE created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')",
-            [&start_date, &end_date],
+            &format!(
+                "SELECT
+                    COALESCE(SUM({}), 0.0),
+                    COALESCE(SUM(cgst_amount), 0.0),
+                    COALESCE(SUM(sgst_amount), 0.0),
+                    COALESCE(SUM(igst_amount), 0.0)
+                 FROM invoices i
+                 WHERE datetime(created_at) >= ?1
+                   AND datetime(created_at) < ?2",
+                INVOICE_TAX_SQL
+            ),
+            [&range.start_utc, &range.end_utc],
             |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
         )
         .map_err(|e| e.to_string())?;
 
     let mut stmt = conn
-        .prepare(
+        .prepare(&format!(
             "SELECT
                 COALESCE(state, 'Unknown'),
-                COALESCE(SUM(tax_amount), 0.0),
+                COALESCE(SUM({tax}), 0.0),
                 COUNT(*)
-             FROM invoices
-             WHERE created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')
+             FROM invoices i
+             WHERE datetime(created_at) >= ?1
+               AND datetime(created_at) < ?2
              GROUP BY state
-             ORDER BY SUM(tax_amount) DESC"
-        )
+             ORDER BY SUM({tax}) DESC",
+            tax = INVOICE_TAX_SQL
+        ))
         .map_err(|e| e.to_string())?;
 
     let by_state = stmt
-        .query_map([&start_date, &end_date], |row| {
+        .query_map([&range.start_utc, &range.end_utc], |row| {
             Ok(StateTax {
                 state: row.get(0)?,
                 tax_amount: row.get(1)?,
@@ -1364,12 +2594,43 @@ pub fn get_tax_summary(
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
+    let mut stmt = conn
+        .prepare(
+            "SELECT
+                ii.tax_rate,
+                COALESCE(SUM(ii.unit_price * ii.quantity - COALESCE(ii.discount_amount, 0)), 0.0),
+                COALESCE(SUM(ii.tax_amount), 0.0),
+                COUNT(*)
+             FROM invoice_items ii
+             JOIN invoices i ON ii.invoice_id = i.id
+             WHERE ii.tax_rate IS NOT NULL
+               AND datetime(i.created_at) >= ?1
+               AND datetime(i.created_at) < ?2
+             GROUP BY ii.tax_rate
+             ORDER BY ii.tax_rate",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let by_rate = stmt
+        .query_map([&range.start_utc, &range.end_utc], |row| {
+            Ok(RateTax {
+                tax_rate: row.get(0)?,
+                taxable_value: row.get(1)?,
+                tax_amount: row.get(2)?,
+                item_count: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
     Ok(TaxSummary {
         total_tax,
         cgst_total: cgst,
         sgst_total: sgst,
         igst_total: igst,
         by_state,
+        by_rate,
     })
 }
 
@@ -1383,17 +2644,26 @@ pub fn get_discount_analysis(
     log::info!("get_discount_analysis called: {} to {}", start_date, end_date);
 
     let conn = db.get_conn()?;
+    get_discount_analysis_internal(&conn, &start_date, &end_date)
+}
+
+fn get_discount_analysis_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<DiscountAnalysis, String> {
+    let range = ReportRange::load(conn, start_date, end_date)?;
 
     let (total_discounts, total_revenue, orders_with_discount): (f64, f64, i32) = conn
         .query_row(
             "SELECT
                 COALESCE(SUM(discount_amount), 0.0),
                 COALESCE(SUM(total_amount), 0.0),
-                SUM(CASE WHEN discount_amount > 0 THEN 1 ELSE 0 END)
+                COALESCE(SUM(CASE WHEN discount_amount > 0 THEN 1 ELSE 0 END), 0)
              FROM invoices
-             WHERE created_at >= datetime(?1)
-               AND created_at < datetime(?2, '+1 day')",
-            [&start_date, &end_date],
+             WHERE datetime(created_at) >= ?1
+               AND datetime(created_at) < ?2",
+            [&range.start_utc, &range.end_utc],
             |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
         )
         .map_err(|e| e.to_string())?;
@@ -1417,3 +2687,509 @@ pub fn get_discount_analysis(
         avg_discount_per_order: avg_discount,
     })
 }
+
+// ============== Report Export ==============
+
+/// Number of products/customers included in the exported top lists
+const REPORT_TOP_LIMIT: i32 = 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalyticsReportMetadata {
+    pub start_date: String,
+    pub end_date: String,
+    /// Business timezone the period was evaluated in, e.g. "+05:30"
+    pub utc_offset: String,
+    pub generated_at: String,
+    pub app_version: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnalyticsReport {
+    pub metadata: AnalyticsReportMetadata,
+    pub sales: SalesAnalytics,
+    pub revenue_trend: Vec<RevenueTrendPoint>,
+    pub top_products: Vec<TopProduct>,
+    pub top_customers: Vec<TopCustomer>,
+    pub payment_methods: Vec<PaymentMethodBreakdown>,
+    pub tax_summary: TaxSummary,
+    pub discounts: DiscountAnalysis,
+    pub purchases: PurchaseAnalytics,
+}
+
+fn build_analytics_report(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+) -> Result<AnalyticsReport, String> {
+    let offset = business_offset_minutes(conn);
+    let sign = if offset < 0 { '-' } else { '+' };
+
+    Ok(AnalyticsReport {
+        metadata: AnalyticsReportMetadata {
+            start_date: start_date.to_string(),
+            end_date: end_date.to_string(),
+            utc_offset: format!("{}{:02}:{:02}", sign, offset.abs() / 60, offset.abs() % 60),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+        sales: get_sales_analytics_internal(conn, start_date, end_date)?,
+        revenue_trend: get_revenue_trend_internal(conn, start_date, end_date, "daily")?,
+        top_products: get_top_products_internal(conn, start_date, end_date, REPORT_TOP_LIMIT)?,
+        top_customers: get_top_customers_internal(conn, start_date, end_date, REPORT_TOP_LIMIT)?,
+        payment_methods: get_sales_by_payment_method_internal(conn, start_date, end_date)?,
+        tax_summary: get_tax_summary_internal(conn, start_date, end_date)?,
+        discounts: get_discount_analysis_internal(conn, start_date, end_date)?,
+        purchases: get_purchase_analytics_internal(conn, start_date, end_date)?,
+    })
+}
+
+/// Export everything the Reports screen shows for a period as one JSON document.
+/// Writes the report to `file_path` and returns the path.
+#[tauri::command]
+pub fn export_analytics_report(
+    start_date: String,
+    end_date: String,
+    file_path: String,
+    db: State<Database>,
+) -> Result<String, String> {
+    log::info!(
+        "export_analytics_report called: {} to {} -> {}",
+        start_date,
+        end_date,
+        file_path
+    );
+
+    let conn = db.get_conn()?;
+    let report = build_analytics_report(&conn, &start_date, &end_date)?;
+
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("Failed to serialize report: {}", e))?;
+    std::fs::write(&file_path, json).map_err(|e| format!("Failed to write report: {}", e))?;
+
+    log::info!("Analytics report written to {}", file_path);
+    Ok(file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IST: i32 = 330;
+
+    fn date(s: &str) -> NaiveDate {
+        parse_report_date(s).unwrap()
+    }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(parse_utc_offset("+05:30"), Some(330));
+        assert_eq!(parse_utc_offset("+0530"), Some(330));
+        assert_eq!(parse_utc_offset("-04:00"), Some(-240));
+        assert_eq!(parse_utc_offset("Z"), Some(0));
+        assert_eq!(parse_utc_offset("05:30"), None);
+        assert_eq!(parse_utc_offset("+25:00"), None);
+    }
+
+    #[test]
+    fn test_report_range_ist_boundaries() {
+        let range = ReportRange::new(date("2024-03-15"), date("2024-03-15"), IST);
+        assert_eq!(range.start_utc, "2024-03-14 18:30:00");
+        assert_eq!(range.end_utc, "2024-03-15 18:30:00");
+        assert_eq!(range.local_modifier, "+330 minutes");
+
+        let previous = ReportRange::new(date("2024-03-01"), date("2024-03-07"), IST).previous();
+        assert_eq!(previous.start_date, date("2024-02-23"));
+        assert_eq!(previous.end_date, date("2024-02-29"));
+    }
+
+    #[test]
+    fn test_weekly_periods_cross_year_boundary() {
+        // 2024-12-30 is the Monday of ISO week 2025-W01
+        let periods = TrendGranularity::Weekly.periods(date("2024-12-25"), date("2025-01-14"));
+        assert_eq!(periods, vec!["2024-12-23", "2024-12-30", "2025-01-06", "2025-01-13"]);
+
+        let months = TrendGranularity::Monthly.periods(date("2024-11-15"), date("2025-02-01"));
+        assert_eq!(months, vec!["2024-11", "2024-12", "2025-01", "2025-02"]);
+
+        let days = TrendGranularity::Daily.periods(date("2024-12-31"), date("2025-01-01"));
+        assert_eq!(days, vec!["2024-12-31", "2025-01-01"]);
+    }
+
+    #[test]
+    fn test_weekly_bucket_sql_matches_rust_buckets() {
+        let conn = Connection::open_in_memory().unwrap();
+        let sql = format!(
+            "SELECT {}",
+            TrendGranularity::Weekly.bucket_sql("?1", Some("?2"))
+        );
+        let cases = [
+            // Sunday evening UTC is already Monday in IST
+            ("2024-12-29T19:00:00+00:00", "2024-12-30"),
+            ("2024-12-29T10:00:00+00:00", "2024-12-23"),
+            ("2025-01-01T06:00:00+00:00", "2024-12-30"),
+            ("2025-01-05T12:00:00+00:00", "2024-12-30"),
+        ];
+        for (created_at, expected) in cases {
+            let bucket: String = conn
+                .query_row(&sql, [created_at, "+330 minutes"], |row| row.get(0))
+                .unwrap();
+            assert_eq!(bucket, expected, "bucket for {}", created_at);
+        }
+    }
+
+    fn abc_input(id: i32, revenue: f64) -> AbcInput {
+        AbcInput {
+            product_id: id,
+            name: format!("Product {}", id),
+            sku: format!("SKU-{}", id),
+            revenue,
+            stock_quantity: 1.0,
+            stock_value: 10.0,
+        }
+    }
+
+    #[test]
+    fn test_classify_abc_default_cutoffs() {
+        let rows = vec![
+            abc_input(1, 500.0),
+            abc_input(2, 250.0),
+            abc_input(3, 100.0),
+            abc_input(4, 100.0),
+            abc_input(5, 50.0),
+            abc_input(6, 0.0),
+        ];
+        let analysis = classify_abc(rows, 70.0, 20.0);
+        let classes: Vec<&str> = analysis.products.iter().map(|p| p.class.as_str()).collect();
+
+        // 0% -> A, 50% -> A, 75% -> B, 85% -> B, 95% -> C, no revenue -> C
+        assert_eq!(classes, vec!["A", "A", "B", "B", "C", "C"]);
+        assert_eq!(analysis.total_revenue, 1000.0);
+        assert_eq!(analysis.products[1].cumulative_percent, 75.0);
+
+        let a = &analysis.classes[0];
+        assert_eq!((a.product_count, a.revenue, a.revenue_share_percent), (2, 750.0, 75.0));
+        assert_eq!(analysis.classes[2].product_count, 2);
+        assert_eq!(analysis.classes[2].stock_value, 20.0);
+    }
+
+    #[test]
+    fn test_classify_abc_without_sales() {
+        let analysis = classify_abc(vec![abc_input(1, 0.0), abc_input(2, 0.0)], 70.0, 20.0);
+        assert!(analysis.products.iter().all(|p| p.class == "C"));
+        assert_eq!(analysis.classes[2].revenue_share_percent, 0.0);
+    }
+
+    #[test]
+    fn test_weekday_hour_uses_business_timezone() {
+        let conn = Connection::open_in_memory().unwrap();
+        let (weekday_sql, hour_sql) = weekday_hour_sql("?1", "?2");
+        let sql = format!("SELECT {}, {}", weekday_sql, hour_sql);
+        let cases = [
+            // Sunday 20:00 UTC is Monday 01:30 IST
+            ("2024-03-17T20:00:00+00:00", (0, 1)),
+            ("2024-03-17T10:00:00+00:00", (6, 15)),
+            ("2024-03-20T00:00:00+00:00", (2, 5)),
+        ];
+        for (created_at, expected) in cases {
+            let cell: (i32, i32) = conn
+                .query_row(&sql, [created_at, "+330 minutes"], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap();
+            assert_eq!(cell, expected, "cell for {}", created_at);
+        }
+    }
+
+    #[test]
+    fn test_customer_report_includes_location() {
+        let db = crate::test_support::TestDb::new();
+        let conn = db.conn();
+        conn.execute(
+            "INSERT INTO customers (name, phone, place, state, district, town, image_path)
+             VALUES ('Anitha', '9876543210', 'Market Road', 'Kerala', 'Ernakulam', 'Aluva', 'customers/1.jpg')",
+            [],
+        )
+        .unwrap();
+        let id = conn.last_insert_rowid() as i32;
+
+        let report = get_customer_report_internal(&conn, id).unwrap();
+        assert_eq!(report.customer.state.as_deref(), Some("Kerala"));
+        assert_eq!(report.customer.district.as_deref(), Some("Ernakulam"));
+        assert_eq!(report.customer.town.as_deref(), Some("Aluva"));
+        assert_eq!(report.customer.image_path.as_deref(), Some("customers/1.jpg"));
+
+        // Found by town as well as name, with the same full column set
+        let found = customer_search_internal(&conn, "Aluva", None).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].customer.district.as_deref(), Some("Ernakulam"));
+
+        crate::test_support::insert_customer(&conn, "Anil");
+        assert_eq!(customer_search_internal(&conn, "Ani", Some(1)).unwrap().len(), 1);
+        assert_eq!(customer_search_internal(&conn, "Ani", None).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_discount_analysis_internal() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE invoices (id INTEGER PRIMARY KEY, total_amount REAL, discount_amount REAL, created_at TEXT);
+             INSERT INTO invoices (total_amount, discount_amount, created_at) VALUES
+                (90.0, 10.0, '2024-03-15T05:00:00+00:00'),
+                (100.0, 0.0, '2024-03-15T06:00:00+00:00'),
+                (50.0, 50.0, '2024-03-16T06:00:00+00:00');",
+        )
+        .unwrap();
+
+        // No app_settings table: falls back to the default business offset
+        let analysis = get_discount_analysis_internal(&conn, "2024-03-15", "2024-03-15").unwrap();
+        assert_eq!(analysis.total_discounts, 10.0);
+        assert_eq!(analysis.orders_with_discount, 1);
+        assert_eq!(analysis.discount_percentage, 5.0);
+    }
+
+    #[test]
+    fn test_tax_summary_prefers_item_level_tax() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE invoices (id INTEGER PRIMARY KEY, tax_amount REAL, cgst_amount REAL, sgst_amount REAL,
+                                    igst_amount REAL, state TEXT, created_at TEXT);
+             CREATE TABLE invoice_items (id INTEGER PRIMARY KEY, invoice_id INTEGER, quantity INTEGER, unit_price REAL,
+                                         discount_amount REAL, tax_rate REAL, tax_amount REAL);
+             INSERT INTO invoices VALUES
+                (1, 23.0, 11.5, 11.5, 0.0, 'Kerala', '2024-03-15T05:00:00+00:00'),
+                (2, 12.0, NULL, NULL, NULL, 'Goa', '2024-03-15T06:00:00+00:00');
+             INSERT INTO invoice_items VALUES
+                (1, 1, 1, 100.0, 0.0, 18.0, 18.0),
+                (2, 1, 2, 50.0, 0.0, 5.0, 5.0),
+                (3, 2, 1, 100.0, 0.0, NULL, NULL);",
+        )
+        .unwrap();
+
+        let summary = get_tax_summary_internal(&conn, "2024-03-15", "2024-03-15").unwrap();
+        assert_eq!(summary.total_tax, 35.0);
+        assert_eq!(summary.cgst_total, 11.5);
+        assert_eq!(summary.by_rate.len(), 2);
+        assert_eq!(summary.by_rate[0].tax_rate, 5.0);
+        assert_eq!(summary.by_rate[0].taxable_value, 100.0);
+        assert_eq!(summary.by_rate[1].tax_amount, 18.0);
+    }
+
+    #[test]
+    fn test_sales_by_category_compares_previous_period() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE categories (id INTEGER PRIMARY KEY, name TEXT, parent_id INTEGER);
+             CREATE TABLE products (id INTEGER PRIMARY KEY, category_id INTEGER);
+             CREATE TABLE invoices (id INTEGER PRIMARY KEY, created_at TEXT);
+             CREATE TABLE invoice_items (id INTEGER PRIMARY KEY, invoice_id INTEGER, product_id INTEGER,
+                                         quantity INTEGER, unit_price REAL, discount_amount REAL);
+             CREATE TABLE batch_consumptions (invoice_item_id INTEGER, quantity INTEGER, unit_cost REAL);
+             INSERT INTO categories VALUES (1, 'Toys', NULL), (2, 'Books', NULL);
+             INSERT INTO products VALUES (1, 1), (2, NULL), (3, 2);
+             INSERT INTO invoices VALUES (1, '2024-03-15T05:00:00+00:00'), (2, '2024-03-15T06:00:00+00:00'),
+                                         (3, '2024-03-14T06:00:00+00:00');
+             INSERT INTO invoice_items VALUES
+                (1, 1, 1, 2, 150.0, 0.0),
+                (2, 2, 1, 1, 100.0, 10.0),
+                (3, 2, 2, 1, 100.0, 0.0),
+                (4, 3, 1, 1, 80.0, 0.0),
+                (5, 3, 3, 1, 40.0, 0.0);
+             INSERT INTO batch_consumptions VALUES (1, 2, 90.0), (2, 1, 60.0);",
+        )
+        .unwrap();
+
+        let sales = get_sales_by_category_internal(&conn, "2024-03-15", "2024-03-15", true).unwrap();
+        assert_eq!(sales.len(), 3);
+        assert_eq!(sales[0].category_name, "Toys");
+        assert_eq!(sales[0].revenue, 390.0);
+        assert_eq!(sales[0].order_count, 2);
+        assert_eq!(sales[0].quantity_sold, 3.0);
+        assert_eq!(sales[0].gross_profit, Some(150.0));
+        assert!((sales[0].revenue_share - 79.59).abs() < 0.01);
+        assert_eq!(sales[0].previous_revenue, Some(80.0));
+        assert_eq!(sales[1].category_name, "Uncategorized");
+        assert_eq!(sales[1].gross_profit, None);
+        assert_eq!(sales[1].previous_revenue, Some(0.0));
+        assert_eq!((sales[2].category_name.as_str(), sales[2].revenue), ("Books", 0.0));
+        assert_eq!(sales[2].previous_revenue, Some(40.0));
+    }
+
+    #[test]
+    fn test_midnight_boundary_counts_on_local_day() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE invoices (id INTEGER PRIMARY KEY, total_amount REAL, created_at TEXT);
+             INSERT INTO invoices (total_amount, created_at) VALUES
+                (100.0, '2024-03-14T18:29:59+00:00'),
+                (200.0, '2024-03-14T18:45:00+00:00'),
+                (300.0, '2024-03-15T18:29:00+00:00'),
+                (400.0, '2024-03-15T18:30:00+00:00');",
+        )
+        .unwrap();
+
+        // 00:15 IST on the 15th belongs to the 15th; 00:00 IST on the 16th does not
+        let range = ReportRange::new(date("2024-03-15"), date("2024-03-15"), IST);
+        let (total, day): (f64, String) = conn
+            .query_row(
+                "SELECT SUM(total_amount), MIN(strftime('%Y-%m-%d', created_at, ?3)) FROM invoices
+                 WHERE datetime(created_at) >= ?1 AND datetime(created_at) < ?2",
+                [&range.start_utc, &range.end_utc, &range.local_modifier],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(total, 500.0);
+        assert_eq!(day, "2024-03-15");
+    }
+
+    #[test]
+    fn test_inventory_valuation_uses_batch_costs() {
+        use crate::commands::products::{create_product_internal, CreateProductInput};
+        use crate::test_support::TestDb;
+
+        let db = TestDb::new();
+        let conn = db.conn();
+        let product = |name: &str, price: f64, selling_price: Option<f64>, stock: i32| {
+            create_product_internal(
+                &conn,
+                CreateProductInput {
+                    name: name.to_string(),
+                    sku: name.to_uppercase(),
+                    price,
+                    selling_price,
+                    stock_quantity: stock as f64,
+                    supplier_id: None,
+                    amount_paid: None,
+                    category: None,
+                    unit: None,
+                    allow_fractional: false,
+                },
+            )
+            .unwrap()
+            .id
+        };
+        let pen = product("Pen", 10.0, Some(15.0), 10);
+        let ink = product("Ink", 20.0, None, 5);
+
+        // A later batch at a higher cost, and the latest purchase price bumped on the product
+        conn.execute(
+            "INSERT INTO inventory_batches (product_id, quantity_remaining, unit_cost, purchase_date) VALUES (?1, 10, 12.0, datetime('now'))",
+            [pen],
+        )
+        .unwrap();
+        conn.execute("UPDATE products SET stock_quantity = 20, price = 12.0 WHERE id = ?1", [pen]).unwrap();
+        // Stock edited without a matching batch
+        conn.execute("UPDATE products SET stock_quantity = 7 WHERE id = ?1", [ink]).unwrap();
+
+        let valuation = get_inventory_valuation_internal(&conn, None, None).unwrap();
+        assert_eq!(valuation.products.len(), 2);
+        let pen_row = &valuation.products[0];
+        assert_eq!((pen_row.product_id, pen_row.cost_value), (pen, 220.0));
+        assert_eq!(pen_row.retail_value, Some(300.0));
+        assert_eq!(pen_row.potential_margin, Some(80.0));
+        assert!(!pen_row.quantity_mismatch);
+        assert!(valuation.products[1].quantity_mismatch);
+        assert_eq!(valuation.total_cost_value, 320.0);
+        assert_eq!(valuation.total_retail_value, 300.0);
+        assert_eq!(valuation.mismatch_count, 1);
+        assert_eq!(valuation.unpriced_count, 1);
+
+        // Health keeps price * stock until the setting switches it to batches
+        assert_eq!(get_inventory_health_internal(&conn).unwrap().total_valuation, 380.0);
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES ('inventory_valuation_method', 'fifo')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(get_inventory_health_internal(&conn).unwrap().total_valuation, 320.0);
+    }
+
+    #[test]
+    fn test_cash_event_declarations_are_consistent() {
+        // Exhaustive on purpose: a new kind fails to compile here until it is placed in ALL
+        for (position, kind) in CashEvent::ALL.iter().enumerate() {
+            let expected = match kind {
+                CashEvent::Sales => 0,
+                CashEvent::Purchases => 1,
+                CashEvent::PurchaseReturns => 2,
+                CashEvent::CustomerPayments => 3,
+                CashEvent::SupplierPayments => 4,
+            };
+            assert_eq!(position, expected);
+            assert_eq!(CashEvent::parse(kind.name()), Some(*kind));
+            assert!(kind.sign() == 1.0 || kind.sign() == -1.0);
+            assert!(!kind.overlaps().contains(kind));
+            for other in kind.overlaps() {
+                assert!(other.overlaps().contains(kind), "{} and {} overlap one way only", kind.name(), other.name());
+            }
+        }
+
+        let defaults = CashEvent::DEFAULT.map(|kind| kind.name().to_string());
+        assert_eq!(CashEvent::parse_filter(Some(&defaults)).unwrap(), CashEvent::DEFAULT.to_vec());
+    }
+
+    #[test]
+    fn test_cash_event_filter_refuses_double_counting() {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(CashEvent::parse_filter(None).unwrap(), CashEvent::DEFAULT.to_vec());
+        assert_eq!(CashEvent::parse_filter(Some(&[])).unwrap(), CashEvent::DEFAULT.to_vec());
+        assert_eq!(
+            CashEvent::parse_filter(Some(&names(&["customer_payments", "supplier_payments", "customer_payments"]))).unwrap(),
+            vec![CashEvent::CustomerPayments, CashEvent::SupplierPayments]
+        );
+        assert!(CashEvent::parse_filter(Some(&names(&["sales", "customer_payments"]))).is_err());
+        assert!(CashEvent::parse_filter(Some(&names(&["purchase_returns", "supplier_payments"]))).is_err());
+        assert!(CashEvent::parse_filter(Some(&names(&["refunds"]))).is_err());
+    }
+
+    #[test]
+    fn test_cash_events_count_each_record_once() {
+        use crate::test_support::TestDb;
+
+        let db = TestDb::new();
+        let conn = db.conn();
+        conn.execute_batch(
+            "INSERT INTO suppliers (id, name) VALUES (1, 'Acme');
+             INSERT INTO customers (id, name) VALUES (1, 'Ravi');
+             INSERT INTO invoices (id, invoice_number, customer_id, total_amount, created_at) VALUES
+                (1, 'INV-1', 1, 1000.0, '2024-03-14T20:00:00+00:00'),
+                (2, 'INV-2', 1, 500.0, '2024-03-20T06:00:00+00:00');
+             INSERT INTO purchase_orders (po_number, supplier_id, order_date, status, total_amount) VALUES
+                ('PO-1', 1, '2024-03-15', 'received', 600.0),
+                ('PO-2', 1, '2024-03-16', 'cancelled', 9999.0);
+             INSERT INTO purchase_returns (return_number, supplier_id, reason, total_amount, return_date) VALUES
+                ('PR-1', 1, 'Damaged', 50.0, '2024-03-16');
+             INSERT INTO customer_payments (customer_id, invoice_id, amount, paid_at) VALUES
+                (1, 1, 700.0, '2024-03-15 04:00:00');
+             INSERT INTO supplier_payments (supplier_id, amount, paid_at) VALUES
+                (1, 400.0, '2024-03-16 04:00:00');",
+        )
+        .unwrap();
+
+        let range = ReportRange::new(date("2024-03-15"), date("2024-03-21"), IST);
+        let totals = cash_event_totals(&conn, &range, &CashEvent::ALL).unwrap();
+        let total = |kind| totals.get(&kind).copied().unwrap_or(0.0);
+        // The invoice at 01:30 IST on the 15th falls in range; the cancelled order doesn't count
+        assert_eq!(total(CashEvent::Sales), 1500.0);
+        assert_eq!(total(CashEvent::Purchases), 600.0);
+        assert_eq!(total(CashEvent::PurchaseReturns), 50.0);
+        assert_eq!(total(CashEvent::CustomerPayments), 700.0);
+        assert_eq!(total(CashEvent::SupplierPayments), 400.0);
+
+        // Querying kinds together gives the same figures as querying each alone
+        for kind in CashEvent::ALL {
+            let alone = cash_event_totals(&conn, &range, &[kind]).unwrap();
+            assert_eq!(alone.get(&kind).copied().unwrap_or(0.0), total(kind), "{}", kind.name());
+        }
+
+        let trend = get_cashflow_trend_internal(&conn, "2024-03-15", "2024-03-21", "daily", None).unwrap();
+        assert_eq!(trend.len(), 7);
+        assert_eq!((trend[0].sales, trend[0].purchases, trend[0].net), (1000.0, 600.0, 400.0));
+        // Payments are left out of the default series
+        assert_eq!((trend[1].purchase_returns, trend[1].supplier_payments, trend[1].net), (50.0, 0.0, 50.0));
+        assert_eq!(trend.iter().map(|point| point.net).sum::<f64>(), 1500.0 - 600.0 + 50.0);
+
+        let payments = ["customer_payments".to_string(), "supplier_payments".to_string()];
+        let trend = get_cashflow_trend_internal(&conn, "2024-03-15", "2024-03-21", "weekly", Some(&payments)).unwrap();
+        assert_eq!(trend.iter().map(|point| point.sales).sum::<f64>(), 0.0);
+        assert_eq!(trend.iter().map(|point| point.net).sum::<f64>(), 300.0);
+    }
+}
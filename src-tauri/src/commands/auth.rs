@@ -1,7 +1,47 @@
 use crate::db::{Database, User};
 use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tauri::State;
 
+/// Error prefix returned by commands that need a logged-in user when nobody is logged in
+pub const LOGIN_REQUIRED: &str = "LOGIN_REQUIRED:";
+
+/// How long a login lasts before the user has to sign in again
+const SESSION_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+
+/// The user logged in to this app instance (by password or biometrics), and until when
+#[derive(Default)]
+pub struct AuthSession {
+    current: Mutex<Option<(User, Instant)>>,
+}
+
+impl AuthSession {
+    pub(crate) fn start(&self, user: &User) -> Result<(), String> {
+        let mut current = self.current.lock().map_err(|e| e.to_string())?;
+        *current = Some((user.clone(), Instant::now() + SESSION_TTL));
+        Ok(())
+    }
+
+    pub(crate) fn end(&self) -> Result<(), String> {
+        let mut current = self.current.lock().map_err(|e| e.to_string())?;
+        *current = None;
+        Ok(())
+    }
+
+    /// The logged-in user, or a LOGIN_REQUIRED error when nobody is logged in or the session expired
+    pub(crate) fn user(&self) -> Result<User, String> {
+        let mut current = self.current.lock().map_err(|e| e.to_string())?;
+        match current.as_ref() {
+            Some((user, expires)) if Instant::now() < *expires => Ok(user.clone()),
+            _ => {
+                *current = None;
+                Err(format!("{}Please log in first", LOGIN_REQUIRED))
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LoginInput {
     pub username: String,
@@ -27,7 +67,7 @@ pub struct UpdateUserInput {
 
 /// Login user
 #[tauri::command]
-pub fn login(input: LoginInput, db: State<Database>) -> Result<User, String> {
+pub fn login(input: LoginInput, session: State<AuthSession>, db: State<Database>) -> Result<User, String> {
     log::info!("login called for user: {}", input.username);
 
     let conn = db.get_conn()?;
@@ -48,9 +88,17 @@ pub fn login(input: LoginInput, db: State<Database>) -> Result<User, String> {
         )
         .map_err(|_| "Invalid username or password".to_string())?;
 
+    session.start(&user)?;
     Ok(user)
 }
 
+/// End the current session
+#[tauri::command]
+pub fn logout(session: State<AuthSession>) -> Result<(), String> {
+    log::info!("logout called");
+    session.end()
+}
+
 /// Get all users
 #[tauri::command]
 pub fn get_users(db: State<Database>) -> Result<Vec<User>, String> {
@@ -0,0 +1,360 @@
+/// Billing Snapshot
+/// One-call product data for the billing screen, so scanning an item doesn't cost an IPC round
+/// trip. The frontend keeps a local cache and refreshes it with `since` = the previous `as_of`.
+/// Also the last-sold price and price history lookups shown while billing an item.
+use rusqlite::{params, Connection, Params};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::purchase_orders::ON_ORDER_QUANTITY_SQL;
+use crate::commands::reservations::RESERVED_QUANTITY_SQL;
+use crate::commands::variants::DISPLAY_NAME_SQL;
+use crate::db::Database;
+
+/// Number of products in the quick-pick grid
+const QUICK_PICK_LIMIT: i32 = 20;
+/// Sales window the quick-pick grid is ranked over
+const QUICK_PICK_DAYS: i32 = 30;
+
+/// The fields billing needs for a product. The SKU doubles as the barcode (CSV import maps a
+/// barcode column onto sku); products have no tax rate of their own, billing applies the
+/// invoice GST rate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BillingProduct {
+    pub id: i32,
+    /// Display name ("Parent — L / Blue" for variants)
+    pub name: String,
+    pub sku: String,
+    pub selling_price: Option<f64>,
+    pub stock_quantity: f64,
+    /// Stock less active reservations
+    pub available_quantity: f64,
+    /// Units on open purchase orders still to arrive
+    pub on_order_quantity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BillingSnapshot {
+    /// Database time the snapshot was taken; pass back as `since` for the next refresh
+    pub as_of: String,
+    /// Sellable products, or only those changed since `since`
+    pub products: Vec<BillingProduct>,
+    /// Products deleted since `since` (always empty for a full snapshot)
+    pub removed_product_ids: Vec<i32>,
+    /// Most sold products over the last 30 days, best seller first
+    pub frequent_products: Vec<BillingProduct>,
+}
+
+/// A price an item was sold at on one invoice
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoldPrice {
+    pub unit_price: f64,
+    pub quantity: f64,
+    /// Line discount given on top of unit_price
+    pub discount_amount: f64,
+    pub invoice_id: i32,
+    pub invoice_number: String,
+    pub sold_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LastSoldPrice {
+    pub product_id: i32,
+    /// Most recent sale to anyone
+    pub last_sale: Option<SoldPrice>,
+    /// Most recent sale to the requested customer
+    pub last_sale_to_customer: Option<SoldPrice>,
+}
+
+/// One distinct unit price a product was sold at
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriceHistoryEntry {
+    pub unit_price: f64,
+    pub invoice_count: i32,
+    pub quantity_sold: f64,
+    pub first_sold_at: String,
+    pub last_sold_at: String,
+}
+
+fn billing_products<P: Params>(conn: &Connection, sql: &str, params: P) -> Result<Vec<BillingProduct>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params, |row| {
+            Ok(BillingProduct {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                selling_price: row.get(3)?,
+                stock_quantity: row.get(4)?,
+                available_quantity: row.get(5)?,
+                on_order_quantity: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load billing products: {}", e))
+}
+
+/// Get the billing product snapshot, optionally only products changed since a previous `as_of`
+#[tauri::command]
+pub fn get_billing_snapshot(since: Option<String>, db: State<Database>) -> Result<BillingSnapshot, String> {
+    log::info!("get_billing_snapshot called with since: {:?}", since);
+
+    let conn = db.get_conn()?;
+    get_billing_snapshot_internal(&conn, since.as_deref())
+}
+
+pub(crate) fn get_billing_snapshot_internal(conn: &Connection, since: Option<&str>) -> Result<BillingSnapshot, String> {
+    // Taken first, so anything changed while the snapshot is read shows up in the next refresh
+    let as_of: String = conn
+        .query_row("SELECT datetime('now')", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    // Parents with variants aren't sold directly; their variants are. A reservation expiring
+    // changes the available quantity without touching the product, and a PO changing status
+    // changes what is on order, so those count as changes too.
+    let products = billing_products(
+        conn,
+        &format!(
+            "SELECT p.id, {}, p.sku, p.selling_price, p.stock_quantity, p.stock_quantity - {}, {}
+             FROM products p
+             WHERE NOT EXISTS (SELECT 1 FROM products v WHERE v.parent_product_id = p.id)
+               AND (?1 IS NULL OR datetime(p.updated_at) >= datetime(?1)
+                    OR EXISTS (SELECT 1 FROM stock_reservations r
+                               WHERE r.product_id = p.id
+                                 AND r.expires_at >= datetime(?1) AND r.expires_at <= datetime('now'))
+                    OR EXISTS (SELECT 1 FROM purchase_order_items poi
+                               JOIN purchase_orders po ON po.id = poi.po_id
+                               WHERE poi.product_id = p.id AND datetime(po.updated_at) >= datetime(?1)))
+             ORDER BY p.name",
+            DISPLAY_NAME_SQL, RESERVED_QUANTITY_SQL, ON_ORDER_QUANTITY_SQL
+        ),
+        [since],
+    )?;
+
+    let removed_product_ids = match since {
+        Some(since) => {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT DISTINCT entity_id FROM deleted_items
+                     WHERE entity_type = 'product'
+                       AND datetime(deleted_at) >= datetime(?1)
+                       AND entity_id NOT IN (SELECT id FROM products)",
+                )
+                .map_err(|e| e.to_string())?;
+            let ids = stmt
+                .query_map([since], |row| row.get(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<i32>, _>>();
+            ids.map_err(|e| format!("Failed to load deleted products: {}", e))?
+        }
+        None => Vec::new(),
+    };
+
+    let frequent_products = billing_products(
+        conn,
+        &format!(
+            "SELECT p.id, {}, p.sku, p.selling_price, p.stock_quantity, p.stock_quantity - {}, {}
+             FROM invoice_items ii
+             JOIN invoices i ON ii.invoice_id = i.id
+             JOIN products p ON ii.product_id = p.id
+             WHERE datetime(i.created_at) >= datetime('now', '-{} days')
+             GROUP BY p.id
+             ORDER BY SUM(ii.quantity) DESC, p.name
+             LIMIT {}",
+            DISPLAY_NAME_SQL, RESERVED_QUANTITY_SQL, ON_ORDER_QUANTITY_SQL, QUICK_PICK_DAYS, QUICK_PICK_LIMIT
+        ),
+        params![],
+    )?;
+
+    Ok(BillingSnapshot { as_of, products, removed_product_ids, frequent_products })
+}
+
+/// Get the most recent price a product was sold at, overall and to one customer
+#[tauri::command]
+pub fn get_last_sold_price(
+    product_id: i32,
+    customer_id: Option<i32>,
+    db: State<Database>,
+) -> Result<LastSoldPrice, String> {
+    log::info!("get_last_sold_price called for product_id: {}, customer_id: {:?}", product_id, customer_id);
+
+    let conn = db.get_conn()?;
+    get_last_sold_price_internal(&conn, product_id, customer_id)
+}
+
+pub(crate) fn get_last_sold_price_internal(
+    conn: &Connection,
+    product_id: i32,
+    customer_id: Option<i32>,
+) -> Result<LastSoldPrice, String> {
+    // Both lookups in one statement; the customer one returns nothing without a customer
+    let mut stmt = conn
+        .prepare(
+            "SELECT * FROM (
+                 SELECT 0, ii.unit_price, ii.quantity, COALESCE(ii.discount_amount, 0), i.id, i.invoice_number, i.created_at
+                 FROM invoice_items ii
+                 JOIN invoices i ON ii.invoice_id = i.id
+                 WHERE ii.product_id = ?1
+                 ORDER BY i.created_at DESC, ii.id DESC
+                 LIMIT 1
+             )
+             UNION ALL
+             SELECT * FROM (
+                 SELECT 1, ii.unit_price, ii.quantity, COALESCE(ii.discount_amount, 0), i.id, i.invoice_number, i.created_at
+                 FROM invoice_items ii
+                 JOIN invoices i ON ii.invoice_id = i.id
+                 WHERE ii.product_id = ?1 AND i.customer_id = ?2
+                 ORDER BY i.created_at DESC, ii.id DESC
+                 LIMIT 1
+             )",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![product_id, customer_id], |row| {
+            let for_customer: bool = row.get(0)?;
+            let price = SoldPrice {
+                unit_price: row.get(1)?,
+                quantity: row.get(2)?,
+                discount_amount: row.get(3)?,
+                invoice_id: row.get(4)?,
+                invoice_number: row.get(5)?,
+                sold_at: row.get(6)?,
+            };
+            Ok((for_customer, price))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load last sold price: {}", e))?;
+
+    let mut result = LastSoldPrice {
+        product_id,
+        last_sale: None,
+        last_sale_to_customer: None,
+    };
+    for (for_customer, price) in rows {
+        if for_customer {
+            result.last_sale_to_customer = Some(price);
+        } else {
+            result.last_sale = Some(price);
+        }
+    }
+    Ok(result)
+}
+
+/// Get the distinct unit prices a product was sold at, most recently used first.
+/// With `customer_id`, only sales to that customer count.
+#[tauri::command]
+pub fn get_product_price_history(
+    product_id: i32,
+    customer_id: Option<i32>,
+    db: State<Database>,
+) -> Result<Vec<PriceHistoryEntry>, String> {
+    log::info!("get_product_price_history called for product_id: {}, customer_id: {:?}", product_id, customer_id);
+
+    let conn = db.get_conn()?;
+    get_product_price_history_internal(&conn, product_id, customer_id)
+}
+
+pub(crate) fn get_product_price_history_internal(
+    conn: &Connection,
+    product_id: i32,
+    customer_id: Option<i32>,
+) -> Result<Vec<PriceHistoryEntry>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT ii.unit_price, COUNT(DISTINCT ii.invoice_id), SUM(ii.quantity), MIN(i.created_at), MAX(i.created_at)
+             FROM invoice_items ii
+             JOIN invoices i ON ii.invoice_id = i.id
+             WHERE ii.product_id = ?1 AND (?2 IS NULL OR i.customer_id = ?2)
+             GROUP BY ii.unit_price
+             ORDER BY MAX(i.created_at) DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let history = stmt
+        .query_map(params![product_id, customer_id], |row| {
+            Ok(PriceHistoryEntry {
+                unit_price: row.get(0)?,
+                invoice_count: row.get(1)?,
+                quantity_sold: row.get(2)?,
+                first_sold_at: row.get(3)?,
+                last_sold_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>();
+    history.map_err(|e| format!("Failed to load price history: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::invoices::create_invoice_internal;
+    use crate::commands::products::{create_product_internal, CreateProductInput};
+    use crate::test_support::{insert_customer, invoice_input, product_input, TestDb};
+
+    fn product(conn: &Connection, name: &str, sku: &str) -> i32 {
+        let input = CreateProductInput { name: name.to_string(), ..product_input(sku, 10.0, 50, None) };
+        create_product_internal(conn, input).unwrap().id
+    }
+
+    #[test]
+    fn test_snapshot_is_incremental_and_ranks_quick_picks() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let pen = product(&conn, "Pen", "PEN");
+        let ink = product(&conn, "Ink", "INK");
+        let _pad = product(&conn, "Pad", "PAD");
+
+        let full = get_billing_snapshot_internal(&conn, None).unwrap();
+        assert_eq!(full.products.len(), 3);
+        assert!(full.frequent_products.is_empty());
+
+        // Backdate everything so only the sale below counts as a change
+        conn.execute("UPDATE products SET updated_at = '2020-01-01 00:00:00'", []).unwrap();
+        let since = "2024-01-01 00:00:00";
+        create_invoice_internal(&mut conn, invoice_input(None, vec![(ink, 3, 15.0), (pen, 1, 15.0)])).unwrap();
+
+        let changed = get_billing_snapshot_internal(&conn, Some(since)).unwrap();
+        let mut ids: Vec<i32> = changed.products.iter().map(|p| p.id).collect();
+        ids.sort();
+        assert_eq!(ids, vec![pen, ink]);
+        assert_eq!(changed.products.iter().find(|p| p.id == ink).unwrap().stock_quantity, 47.0);
+        let quick_picks: Vec<i32> = changed.frequent_products.iter().map(|p| p.id).collect();
+        assert_eq!(quick_picks, vec![ink, pen]);
+    }
+
+    #[test]
+    fn test_last_sold_price_and_price_history() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let pen = product(&conn, "Pen", "PEN");
+        let customer_id = insert_customer(&conn, "Asha");
+
+        let mut sell = |customer_id: Option<i32>, unit_price: f64| {
+            create_invoice_internal(&mut conn, invoice_input(customer_id, vec![(pen, 2, unit_price)])).unwrap().id
+        };
+        sell(Some(customer_id), 14.0);
+        let customer_invoice = sell(Some(customer_id), 13.0);
+        let walk_in_invoice = sell(None, 15.0);
+        sell(None, 15.0);
+
+        let last = get_last_sold_price_internal(&conn, pen, Some(customer_id)).unwrap();
+        let overall = last.last_sale.unwrap();
+        assert_eq!(overall.unit_price, 15.0);
+        assert!(overall.invoice_id > walk_in_invoice);
+        let for_customer = last.last_sale_to_customer.unwrap();
+        assert_eq!((for_customer.unit_price, for_customer.invoice_id), (13.0, customer_invoice));
+
+        let last = get_last_sold_price_internal(&conn, pen, None).unwrap();
+        assert!(last.last_sale.is_some());
+        assert!(last.last_sale_to_customer.is_none());
+
+        let history = get_product_price_history_internal(&conn, pen, None).unwrap();
+        let prices: Vec<(f64, i32, f64)> = history.iter().map(|h| (h.unit_price, h.invoice_count, h.quantity_sold)).collect();
+        assert_eq!(prices.len(), 3);
+        assert!(prices.contains(&(15.0, 2, 4.0)));
+        assert!(prices.contains(&(13.0, 1, 2.0)));
+        assert_eq!(get_product_price_history_internal(&conn, pen, Some(customer_id)).unwrap().len(), 2);
+    }
+}
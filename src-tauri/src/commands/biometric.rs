@@ -1,16 +1,118 @@
+use crate::commands::auth::AuthSession;
 use crate::db::{Database, User};
+use crate::services::device;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
 use sha2::{Sha256, Digest};
 use tauri::State;
 use uuid::Uuid;
 
+/// app_settings key holding a JSON list of actions that need biometric approval, e.g.
+/// `["delete_invoice", "clear_trash", "restore_from_backup", "price_change:20"]`.
+/// `price_change:N` protects product price changes of more than N percent (any change without N).
+/// Only set_protected_actions writes it.
+pub(crate) const REQUIRE_BIOMETRIC_KEY: &str = "require_biometric_for";
+
+/// Actions that can be listed in require_biometric_for
+const PROTECTED_ACTIONS: &[&str] = &["delete_invoice", "clear_trash", "restore_from_backup", "price_change", "price_override"];
+
+/// Approval action for removing or loosening a require_biometric_for entry; always required,
+/// so it can't be listed itself
+const CHANGE_PROTECTED_ACTIONS: &str = "change_protected_actions";
+
+/// Days an enrollment token stays valid before fingerprint login has to be enabled again.
+/// Migration 38 gives enrollments made before expiry existed the same lifetime.
+const BIOMETRIC_TOKEN_TTL_DAYS: i64 = 90;
+
+/// Seconds an action approval stays valid
+const APPROVAL_TTL_SECONDS: i64 = 60;
+
+/// Prefix of the error a protected command returns when it needs a (new) approval token;
+/// the rest of the message is the action name
+pub const BIOMETRIC_APPROVAL_REQUIRED: &str = "BIOMETRIC_APPROVAL_REQUIRED:";
+
+/// A short-lived, single-use approval for one protected action
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActionApproval {
+    pub token: String,
+    pub action: String,
+    pub approved_by: String,
+    pub expires_at: String,
+}
+
+fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// The user enrolled with this biometric token on this device. The token must belong to an
+/// enabled enrollment that has not expired and was made on `device_id`. Enrollments from before
+/// device binding carry no device yet and are bound to the first device that verifies with them.
+pub(crate) fn user_for_biometric_token(conn: &Connection, token: &str, device_id: &str) -> Result<User, String> {
+    let (user, bound_device, expired) = conn
+        .query_row(
+            "SELECT id, username, role, permissions, created_at, biometric_device_id,
+                    COALESCE(biometric_token_expires_at <= datetime('now'), 1)
+             FROM users
+             WHERE biometric_token_hash = ?1 AND biometric_enabled = 1",
+            [hash_token(token)],
+            |row| {
+                let user = User {
+                    id: row.get(0)?,
+                    username: row.get(1)?,
+                    role: row.get(2)?,
+                    permissions: row.get(3)?,
+                    created_at: row.get(4)?,
+                };
+                Ok((user, row.get::<_, Option<String>>(5)?, row.get::<_, bool>(6)?))
+            },
+        )
+        .map_err(|_| "Invalid biometric token".to_string())?;
+
+    if expired {
+        return Err("Biometric token has expired. Enable fingerprint login again in Settings.".to_string());
+    }
+
+    match bound_device {
+        Some(bound) if bound != device_id => {
+            log::warn!("Biometric token for {} presented from another device", user.username);
+            Err("Biometric token was enrolled on a different device".to_string())
+        }
+        Some(_) => Ok(user),
+        None => {
+            conn.execute(
+                "UPDATE users SET biometric_device_id = ?1 WHERE id = ?2 AND biometric_device_id IS NULL",
+                rusqlite::params![device_id, user.id],
+            )
+            .map_err(|e| format!("Failed to bind biometric enrollment: {}", e))?;
+            log::info!("Bound existing biometric enrollment of {} to this device", user.username);
+            Ok(user)
+        }
+    }
+}
+
 /// Generate a secure token for biometric enrollment
 /// Returns the raw token (to be stored in OS secure storage by frontend)
 #[tauri::command]
-pub fn generate_biometric_token(user_id: i32, db: State<Database>) -> Result<String, String> {
+pub fn generate_biometric_token(user_id: i32, session: State<AuthSession>, db: State<Database>) -> Result<String, String> {
     log::info!("generate_biometric_token called for user_id: {}", user_id);
 
+    // Only the logged-in user can enroll their own fingerprint
+    if session.user()?.id != user_id {
+        return Err("You can only enable fingerprint login for your own account".to_string());
+    }
+
     let conn = db.get_conn()?;
+    let token = generate_biometric_token_internal(&conn, user_id, &device::device_id()?)?;
 
+    log::info!("Biometric token generated and stored for user_id: {}", user_id);
+
+    // Return raw token - frontend will store in OS secure storage
+    Ok(token)
+}
+
+pub(crate) fn generate_biometric_token_internal(conn: &Connection, user_id: i32, device_id: &str) -> Result<String, String> {
     // Verify user exists
     let user_exists: i32 = conn
         .query_row(
@@ -24,85 +126,71 @@ pub fn generate_biometric_token(user_id: i32, db: State<Database>) -> Result<Str
         return Err("User not found".to_string());
     }
 
-    // Generate secure random token
+    // Generate secure random token; only its hash is stored
     let token = Uuid::new_v4().to_string();
 
-    // Hash the token for storage
-    let mut hasher = Sha256::new();
-    hasher.update(token.as_bytes());
-    let token_hash = hex::encode(hasher.finalize());
-
-    // Store hash in database and enable biometric
     conn.execute(
-        "UPDATE users SET biometric_token_hash = ?1, biometric_enabled = 1 WHERE id = ?2",
-        (&token_hash, user_id),
+        "UPDATE users
+         SET biometric_token_hash = ?1, biometric_enabled = 1, biometric_device_id = ?2,
+             biometric_token_expires_at = datetime('now', ?3)
+         WHERE id = ?4",
+        rusqlite::params![hash_token(&token), device_id, format!("+{} days", BIOMETRIC_TOKEN_TTL_DAYS), user_id],
     )
     .map_err(|e| format!("Failed to save biometric token: {}", e))?;
 
-    log::info!("Biometric token generated and stored for user_id: {}", user_id);
-
-    // Return raw token - frontend will store in OS secure storage
     Ok(token)
 }
 
-/// Verify biometric token and return user if valid
+/// Verify biometric token and return user if valid; a successful check logs the user in
 #[tauri::command]
-pub fn verify_biometric_token(token: String, db: State<Database>) -> Result<User, String> {
+pub fn verify_biometric_token(token: String, session: State<AuthSession>, db: State<Database>) -> Result<User, String> {
     log::info!("verify_biometric_token called");
 
     let conn = db.get_conn()?;
-
-    // Hash the provided token
-    let mut hasher = Sha256::new();
-    hasher.update(token.as_bytes());
-    let token_hash = hex::encode(hasher.finalize());
-
-    // Find user with matching token hash
-    let user = conn
-        .query_row(
-            "SELECT id, username, role, permissions, created_at FROM users
-             WHERE biometric_token_hash = ?1 AND biometric_enabled = 1",
-            [&token_hash],
-            |row| {
-                Ok(User {
-                    id: row.get(0)?,
-                    username: row.get(1)?,
-                    role: row.get(2)?,
-                    permissions: row.get(3)?,
-                    created_at: row.get(4)?,
-                })
-            },
-        )
-        .map_err(|_| "Invalid biometric token".to_string())?;
+    let user = user_for_biometric_token(&conn, &token, &device::device_id()?)?;
+    session.start(&user)?;
 
     log::info!("Biometric login successful for user: {}", user.username);
 
     Ok(user)
 }
 
-/// Disable biometric authentication for a user
+/// Disable biometric authentication for a user (the user themselves or an admin)
 #[tauri::command]
-pub fn disable_biometric(user_id: i32, db: State<Database>) -> Result<(), String> {
+pub fn disable_biometric(user_id: i32, session: State<AuthSession>, db: State<Database>) -> Result<(), String> {
     log::info!("disable_biometric called for user_id: {}", user_id);
 
+    let viewer = session.user()?;
+    if viewer.id != user_id && viewer.role != "admin" {
+        return Err("Only an admin can disable fingerprint login for another user".to_string());
+    }
+
     let conn = db.get_conn()?;
+    disable_biometric_internal(&conn, user_id)?;
+
+    log::info!("Biometric disabled for user_id: {}", user_id);
+
+    Ok(())
+}
 
+pub(crate) fn disable_biometric_internal(conn: &Connection, user_id: i32) -> Result<(), String> {
     conn.execute(
-        "UPDATE users SET biometric_enabled = 0, biometric_token_hash = NULL WHERE id = ?1",
+        "UPDATE users
+         SET biometric_enabled = 0, biometric_token_hash = NULL, biometric_device_id = NULL,
+             biometric_token_expires_at = NULL
+         WHERE id = ?1",
         [user_id],
     )
     .map_err(|e| format!("Failed to disable biometric: {}", e))?;
-
-    log::info!("Biometric disabled for user_id: {}", user_id);
-
     Ok(())
 }
 
-/// Check if biometric is enabled for a user
+/// Check if biometric is enabled for a user. Needs a logged-in session.
 #[tauri::command]
-pub fn get_biometric_status(user_id: i32, db: State<Database>) -> Result<bool, String> {
+pub fn get_biometric_status(user_id: i32, session: State<AuthSession>, db: State<Database>) -> Result<bool, String> {
     log::info!("get_biometric_status called for user_id: {}", user_id);
 
+    session.user()?;
     let conn = db.get_conn()?;
 
     let enabled: i32 = conn
@@ -116,12 +204,13 @@ pub fn get_biometric_status(user_id: i32, db: State<Database>) -> Result<bool, S
     Ok(enabled == 1)
 }
 
-/// Check if biometric is enabled for a specific username
-/// Used on login page to check if the entered user has biometric enabled
+/// Check if biometric is enabled for a specific username. Needs a logged-in session, so the
+/// login page can't be used to probe who has enrolled; it relies on the token held locally.
 #[tauri::command]
-pub fn get_biometric_status_by_username(username: String, db: State<Database>) -> Result<bool, String> {
+pub fn get_biometric_status_by_username(username: String, session: State<AuthSession>, db: State<Database>) -> Result<bool, String> {
     log::info!("get_biometric_status_by_username called for username: {}", username);
 
+    session.user()?;
     let conn = db.get_conn()?;
 
     let enabled: i32 = conn
@@ -135,21 +224,360 @@ pub fn get_biometric_status_by_username(username: String, db: State<Database>) -
     Ok(enabled == 1)
 }
 
-/// Check if any user has biometric enabled on this device
+/// Check if any user has a usable biometric enrollment on this device
 /// Used on login page to show/hide fingerprint button
 #[tauri::command]
 pub fn has_any_biometric_enrollment(db: State<Database>) -> Result<bool, String> {
     log::info!("has_any_biometric_enrollment called");
 
     let conn = db.get_conn()?;
+    Ok(has_device_enrollment(&conn, &device::device_id()?))
+}
 
+fn has_device_enrollment(conn: &Connection, device_id: &str) -> bool {
     let count: i32 = conn
         .query_row(
-            "SELECT COUNT(*) FROM users WHERE biometric_enabled = 1",
-            [],
+            "SELECT COUNT(*) FROM users
+             WHERE biometric_enabled = 1
+               AND (biometric_device_id = ?1 OR biometric_device_id IS NULL)
+               AND biometric_token_expires_at > datetime('now')",
+            [device_id],
             |row| row.get(0),
         )
         .unwrap_or(0);
 
-    Ok(count > 0)
+    count > 0
+}
+
+/// Exchange a biometric token (read from secure storage after a successful OS biometric prompt)
+/// for a one-time approval of a protected action. The approval expires after 60 seconds.
+#[tauri::command]
+pub fn verify_action_biometric(action: String, token: String, db: State<Database>) -> Result<ActionApproval, String> {
+    log::info!("verify_action_biometric called for action: {}", action);
+
+    let conn = db.get_conn()?;
+    let approval = issue_action_approval(&conn, &action, &token, &device::device_id()?)?;
+
+    log::info!("Biometric approval for {} issued to {}", action, approval.approved_by);
+    Ok(approval)
+}
+
+pub(crate) fn issue_action_approval(conn: &Connection, action: &str, token: &str, device_id: &str) -> Result<ActionApproval, String> {
+    if !PROTECTED_ACTIONS.contains(&action) && action != CHANGE_PROTECTED_ACTIONS {
+        return Err(format!("Unknown protected action: {}", action));
+    }
+
+    let user = user_for_biometric_token(conn, token, device_id)?;
+
+    // Used and expired approvals are of no further use
+    conn.execute(
+        "DELETE FROM action_approvals WHERE used_at IS NOT NULL OR expires_at <= datetime('now')",
+        [],
+    )
+    .map_err(|e| format!("Failed to clear old approvals: {}", e))?;
+
+    let approval_token = Uuid::new_v4().to_string();
+    let expires_at: String = conn
+        .query_row(
+            "INSERT INTO action_approvals (token_hash, action, user_id, expires_at)
+             VALUES (?1, ?2, ?3, datetime('now', ?4))
+             RETURNING expires_at",
+            rusqlite::params![hash_token(&approval_token), action, user.id, format!("+{} seconds", APPROVAL_TTL_SECONDS)],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to save approval: {}", e))?;
+
+    Ok(ActionApproval {
+        token: approval_token,
+        action: action.to_string(),
+        approved_by: user.username,
+        expires_at,
+    })
+}
+
+/// Entries of the require_biometric_for setting as (action, optional threshold)
+fn protected_actions(conn: &Connection) -> Vec<(String, Option<f64>)> {
+    let setting: Option<String> = conn
+        .query_row("SELECT value FROM app_settings WHERE key = ?1", [REQUIRE_BIOMETRIC_KEY], |row| row.get(0))
+        .optional()
+        .ok()
+        .flatten();
+
+    let entries: Vec<String> = setting
+        .and_then(|value| serde_json::from_str(&value).ok())
+        .unwrap_or_default();
+
+    entries.iter().map(|entry| parse_protected_entry(entry)).collect()
+}
+
+/// Split a require_biometric_for entry such as "price_change:20" into (action, threshold)
+fn parse_protected_entry(entry: &str) -> (String, Option<f64>) {
+    match entry.split_once(':') {
+        Some((action, threshold)) => (action.trim().to_string(), threshold.trim().parse().ok()),
+        None => (entry.trim().to_string(), None),
+    }
+}
+
+/// Whether the require_biometric_for setting lists `action`
+pub(crate) fn action_requires_biometric(conn: &Connection, action: &str) -> bool {
+    protected_actions(conn).iter().any(|(name, _)| name == action)
+}
+
+/// Whether changing a price from `old` to `new` needs a price_change approval
+pub(crate) fn price_change_requires_biometric(conn: &Connection, old: f64, new: f64) -> bool {
+    if (old - new).abs() < 0.005 {
+        return false;
+    }
+    protected_actions(conn).iter().any(|(name, threshold)| {
+        name == "price_change"
+            && match threshold {
+                Some(percent) if old.abs() >= 0.005 => ((new - old) / old).abs() * 100.0 > *percent,
+                _ => true,
+            }
+    })
+}
+
+/// Use up an approval token for `action`. Fails unless the token was issued for this action,
+/// has not expired and has not been used before. Returns the approving user's name.
+pub(crate) fn consume_action_approval(conn: &Connection, action: &str, approval_token: Option<&str>) -> Result<String, String> {
+    let approval_token = approval_token
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| format!("{}{}", BIOMETRIC_APPROVAL_REQUIRED, action))?;
+
+    let user_id: i32 = conn
+        .query_row(
+            "UPDATE action_approvals
+             SET used_at = datetime('now')
+             WHERE token_hash = ?1 AND action = ?2 AND used_at IS NULL AND expires_at > datetime('now')
+             RETURNING user_id",
+            rusqlite::params![hash_token(approval_token), action],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to check approval: {}", e))?
+        .ok_or_else(|| format!("{}{}", BIOMETRIC_APPROVAL_REQUIRED, action))?;
+
+    conn.query_row("SELECT username FROM users WHERE id = ?1", [user_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to get approving user: {}", e))
+}
+
+/// Require a valid approval token for `action` if require_biometric_for lists it
+pub(crate) fn require_biometric_approval(conn: &Connection, action: &str, approval_token: Option<&str>) -> Result<(), String> {
+    if action_requires_biometric(conn, action) {
+        let approved_by = consume_action_approval(conn, action, approval_token)?;
+        log::info!("{} approved by {} via biometric", action, approved_by);
+    }
+    Ok(())
+}
+
+/// Replace the require_biometric_for list. Needs a logged-in user; dropping an entry or raising
+/// a price_change threshold also needs a change_protected_actions approval, so protection can't
+/// be switched off from the counter. Returns the saved entries.
+#[tauri::command]
+pub fn set_protected_actions(
+    actions: Vec<String>,
+    approval_token: Option<String>,
+    session: State<AuthSession>,
+    db: State<Database>,
+) -> Result<Vec<String>, String> {
+    let user = session.user()?;
+    log::info!("set_protected_actions called by {}: {:?}", user.username, actions);
+
+    let mut conn = db.get_conn()?;
+    set_protected_actions_internal(&mut conn, &actions, approval_token.as_deref())
+}
+
+pub(crate) fn set_protected_actions_internal(
+    conn: &mut Connection,
+    actions: &[String],
+    approval_token: Option<&str>,
+) -> Result<Vec<String>, String> {
+    let mut entries: Vec<String> = Vec::new();
+    let mut requested: Vec<(String, Option<f64>)> = Vec::new();
+    for entry in actions {
+        let (action, threshold) = parse_protected_entry(entry);
+        if !PROTECTED_ACTIONS.contains(&action.as_str()) {
+            return Err(format!("Validation error: unknown protected action '{}'", action));
+        }
+        let has_threshold = entry.contains(':');
+        match threshold {
+            Some(percent) if action == "price_change" && percent.is_finite() && percent >= 0.0 => {
+                entries.push(format!("{}:{}", action, percent));
+            }
+            None if !has_threshold => entries.push(action.clone()),
+            _ => return Err(format!("Validation error: invalid protected action '{}'", entry.trim())),
+        }
+        requested.push((action, threshold));
+    }
+    entries.sort();
+    entries.dedup();
+
+    // A missing threshold protects every price change, so it is the strictest
+    let loosened = protected_actions(conn).iter().any(|(action, threshold)| {
+        !requested.iter().any(|(new_action, new_threshold)| {
+            new_action == action && new_threshold.unwrap_or(0.0) <= threshold.unwrap_or(0.0)
+        })
+    });
+
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    if loosened {
+        let approved_by = consume_action_approval(&tx, CHANGE_PROTECTED_ACTIONS, approval_token)?;
+        log::info!("Loosening of protected actions approved by {} via biometric", approved_by);
+    }
+    let value = serde_json::to_string(&entries).map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))
+         ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = datetime('now')",
+        [REQUIRE_BIOMETRIC_KEY, value.as_str()],
+    )
+    .map_err(|e| format!("Failed to save protected actions: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::settings::{delete_app_setting_internal, set_app_setting_internal};
+    use crate::test_support::TestDb;
+
+    const DEVICE: &str = "device-a";
+
+    fn enroll(conn: &Connection, username: &str) -> String {
+        conn.execute(
+            "INSERT INTO users (username, password, role, permissions) VALUES (?1, 'x', 'admin', '[\"*\"]')",
+            [username],
+        )
+        .unwrap();
+        generate_biometric_token_internal(conn, conn.last_insert_rowid() as i32, DEVICE).unwrap()
+    }
+
+    fn protect(conn: &Connection, actions: &str) {
+        conn.execute(
+            "INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)",
+            [REQUIRE_BIOMETRIC_KEY, actions],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_approval_is_single_use_and_action_bound() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let biometric = enroll(&conn, "manager");
+        protect(&conn, r#"["delete_invoice", "clear_trash"]"#);
+
+        let required = require_biometric_approval(&conn, "delete_invoice", None).unwrap_err();
+        assert_eq!(required, format!("{}delete_invoice", BIOMETRIC_APPROVAL_REQUIRED));
+        assert!(issue_action_approval(&conn, "delete_invoice", "not-enrolled", DEVICE).is_err());
+        assert!(issue_action_approval(&conn, "format_disk", &biometric, DEVICE).is_err());
+
+        let approval = issue_action_approval(&conn, "delete_invoice", &biometric, DEVICE).unwrap();
+        assert_eq!(approval.approved_by, "manager");
+        assert!(require_biometric_approval(&conn, "clear_trash", Some(&approval.token)).is_err());
+        require_biometric_approval(&conn, "delete_invoice", Some(&approval.token)).unwrap();
+        assert!(require_biometric_approval(&conn, "delete_invoice", Some(&approval.token)).is_err());
+
+        // Expired approvals are rejected
+        let approval = issue_action_approval(&conn, "delete_invoice", &biometric, DEVICE).unwrap();
+        conn.execute("UPDATE action_approvals SET expires_at = datetime('now', '-1 seconds')", []).unwrap();
+        assert!(require_biometric_approval(&conn, "delete_invoice", Some(&approval.token)).is_err());
+
+        // Unlisted actions pass without a token
+        require_biometric_approval(&conn, "restore_from_backup", None).unwrap();
+    }
+
+    #[test]
+    fn test_token_is_bound_to_device_and_expires() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let token = enroll(&conn, "cashier");
+        assert!(has_device_enrollment(&conn, DEVICE));
+        assert!(!has_device_enrollment(&conn, "device-b"));
+
+        assert_eq!(user_for_biometric_token(&conn, &token, DEVICE).unwrap().username, "cashier");
+        let err = user_for_biometric_token(&conn, &token, "device-b").unwrap_err();
+        assert!(err.contains("different device"));
+        assert!(issue_action_approval(&conn, "delete_invoice", &token, "device-b").is_err());
+
+        // Only the hash is stored
+        let stored: String = conn
+            .query_row("SELECT biometric_token_hash FROM users WHERE username = 'cashier'", [], |row| row.get(0))
+            .unwrap();
+        assert_ne!(stored, token);
+        assert!(user_for_biometric_token(&conn, &stored, DEVICE).is_err());
+
+        conn.execute("UPDATE users SET biometric_token_expires_at = datetime('now', '-1 seconds')", []).unwrap();
+        let err = user_for_biometric_token(&conn, &token, DEVICE).unwrap_err();
+        assert!(err.contains("expired"));
+        assert!(!has_device_enrollment(&conn, DEVICE));
+    }
+
+    #[test]
+    fn test_disabled_and_legacy_enrollments() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let token = enroll(&conn, "owner");
+        let user_id: i32 = conn.query_row("SELECT id FROM users WHERE username = 'owner'", [], |row| row.get(0)).unwrap();
+
+        disable_biometric_internal(&conn, user_id).unwrap();
+        assert!(user_for_biometric_token(&conn, &token, DEVICE).is_err());
+        assert!(!has_device_enrollment(&conn, DEVICE));
+
+        // An enrollment from before device binding is bound to the first device that uses it
+        let token = generate_biometric_token_internal(&conn, user_id, DEVICE).unwrap();
+        conn.execute("UPDATE users SET biometric_device_id = NULL", []).unwrap();
+        assert!(has_device_enrollment(&conn, "device-b"));
+        user_for_biometric_token(&conn, &token, "device-b").unwrap();
+        assert!(user_for_biometric_token(&conn, &token, DEVICE).is_err());
+        user_for_biometric_token(&conn, &token, "device-b").unwrap();
+    }
+
+    #[test]
+    fn test_loosening_protected_actions_needs_approval() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let biometric = enroll(&conn, "manager");
+        let list = |entries: &[&str]| entries.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+
+        // Adding and tightening entries goes through without an approval
+        set_protected_actions_internal(&mut conn, &list(&["delete_invoice", "price_change:20"]), None).unwrap();
+        set_protected_actions_internal(&mut conn, &list(&["delete_invoice", "price_change:10"]), None).unwrap();
+        assert!(set_protected_actions_internal(&mut conn, &list(&["format_disk"]), None).is_err());
+        assert!(set_protected_actions_internal(&mut conn, &list(&["delete_invoice:5"]), None).is_err());
+
+        // Raising a threshold or dropping an entry needs one
+        let err = set_protected_actions_internal(&mut conn, &list(&["delete_invoice", "price_change:30"]), None).unwrap_err();
+        assert_eq!(err, format!("{}{}", BIOMETRIC_APPROVAL_REQUIRED, CHANGE_PROTECTED_ACTIONS));
+        assert!(set_protected_actions_internal(&mut conn, &list(&["price_change:10"]), None).is_err());
+        assert!(action_requires_biometric(&conn, "delete_invoice"));
+
+        let approval = issue_action_approval(&conn, CHANGE_PROTECTED_ACTIONS, &biometric, DEVICE).unwrap();
+        let saved = set_protected_actions_internal(&mut conn, &list(&["price_change:10"]), Some(&approval.token)).unwrap();
+        assert_eq!(saved, list(&["price_change:10"]));
+        assert!(!action_requires_biometric(&conn, "delete_invoice"));
+
+        // The generic settings commands can't touch the list
+        assert!(delete_app_setting_internal(&conn, REQUIRE_BIOMETRIC_KEY).is_err());
+        assert!(set_app_setting_internal(&conn, REQUIRE_BIOMETRIC_KEY, "[]").is_err());
+        assert!(action_requires_biometric(&conn, "price_change"));
+    }
+
+    #[test]
+    fn test_price_change_threshold() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        assert!(!price_change_requires_biometric(&conn, 100.0, 500.0));
+
+        protect(&conn, r#"["price_change:20"]"#);
+        assert!(!price_change_requires_biometric(&conn, 100.0, 120.0));
+        assert!(price_change_requires_biometric(&conn, 100.0, 121.0));
+        assert!(price_change_requires_biometric(&conn, 100.0, 70.0));
+        assert!(price_change_requires_biometric(&conn, 0.0, 10.0));
+
+        protect(&conn, r#"["price_change"]"#);
+        assert!(price_change_requires_biometric(&conn, 100.0, 101.0));
+        assert!(!price_change_requires_biometric(&conn, 100.0, 100.0));
+    }
 }
@@ -0,0 +1,408 @@
+/// Bundle Commands
+/// Products sold as kits of other products. A bundle holds no stock of its own: selling it
+/// deducts its components through the FIFO path and records what was consumed per invoice line.
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::commands::analytics::ReportRange;
+use crate::commands::reservations::{self, StockReservation};
+use crate::db::Database;
+use crate::services::inventory_service;
+use crate::services::quantity::{self, from_milli, to_milli};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleComponentInput {
+    pub component_id: i32,
+    pub quantity: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleComponent {
+    pub component_id: i32,
+    pub name: String,
+    pub sku: String,
+    pub quantity: i32,
+    pub stock_quantity: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleDetails {
+    pub product_id: i32,
+    pub components: Vec<BundleComponent>,
+    /// Bundles that can be assembled from current component stock
+    pub max_assemblable: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BundleComponentUsage {
+    pub component_id: i32,
+    pub name: String,
+    pub sku: String,
+    pub quantity_consumed: f64,
+    pub bundles_sold: f64,
+    pub stock_quantity: f64,
+}
+
+/// Whether a product is flagged as a bundle
+pub(crate) fn is_bundle(conn: &Connection, product_id: i32) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT COALESCE(is_bundle, 0) FROM products WHERE id = ?1",
+        [product_id],
+        |row| row.get::<_, i32>(0),
+    )
+    .map(|flag| flag != 0)
+    .map_err(|_| format!("Product with id {} not found", product_id))
+}
+
+/// Bill of materials of a bundle as (component_id, quantity per bundle)
+pub(crate) fn bundle_components(conn: &Connection, bundle_id: i32) -> Result<Vec<(i32, i32)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT component_id, quantity FROM bundle_components WHERE bundle_id = ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let components = stmt
+        .query_map([bundle_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(components)
+}
+
+/// Whole bundles that can be assembled from current component stock
+pub(crate) fn max_assemblable(conn: &Connection, bundle_id: i32) -> Result<i32, String> {
+    conn.query_row(
+        "SELECT COALESCE(MIN(CAST(MAX(p.stock_quantity, 0) / bc.quantity AS INTEGER)), 0)
+         FROM bundle_components bc
+         JOIN products p ON bc.component_id = p.id
+         WHERE bc.bundle_id = ?1",
+        [bundle_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to calculate bundle stock: {}", e))
+}
+
+/// Stock actually drawn by selling `quantity` of a product, as (product_id, quantity).
+/// Bundles expand to their components; other products draw on themselves.
+pub(crate) fn stock_requirements(conn: &Connection, product_id: i32, quantity: f64) -> Result<Vec<(i32, f64)>, String> {
+    if !is_bundle(conn, product_id)? {
+        return Ok(vec![(product_id, quantity)]);
+    }
+    let components = bundle_components(conn, product_id)?;
+    if components.is_empty() {
+        return Err(format!("Bundle with id {} has no components", product_id));
+    }
+    Ok(components
+        .into_iter()
+        .map(|(component_id, per_bundle)| (component_id, quantity::round_quantity(per_bundle as f64 * quantity)))
+        .collect())
+}
+
+/// Check that all lines of a sale can be fulfilled, summing demand for products that
+/// appear on several lines or inside several bundles. Stock held by active reservations isn't
+/// available, except for the `consumed` reservations the sale itself fulfils.
+pub(crate) fn validate_sale_stock(
+    conn: &Connection,
+    items: &[(i32, f64)],
+    consumed: &[StockReservation],
+) -> Result<(), String> {
+    // Demand is summed in thousandths so fractional lines add up exactly
+    let mut demand: HashMap<i32, i64> = HashMap::new();
+    let mut order = Vec::new();
+    for &(product_id, quantity) in items {
+        for (stock_product_id, needed) in stock_requirements(conn, product_id, quantity)? {
+            if !demand.contains_key(&stock_product_id) {
+                order.push(stock_product_id);
+            }
+            *demand.entry(stock_product_id).or_insert(0) += to_milli(needed);
+        }
+    }
+
+    for product_id in order {
+        let (stock, name): (f64, String) = conn
+            .query_row(
+                "SELECT stock_quantity, name FROM products WHERE id = ?1",
+                [product_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|_| format!("Product with id {} not found", product_id))?;
+        let requested = demand[&product_id];
+        let reserved = to_milli(reservations::reserved_excluding(conn, product_id, consumed)?);
+        let available = to_milli(stock) - reserved;
+        if available < requested {
+            let held = if reserved > 0 {
+                format!(" ({} reserved)", quantity::format_quantity(from_milli(reserved)))
+            } else {
+                String::new()
+            };
+            return Err(format!(
+                "Insufficient stock for product '{}'. Available: {}{}, Requested: {}",
+                name,
+                quantity::format_quantity(from_milli(available.max(0))),
+                held,
+                quantity::format_quantity(from_milli(requested))
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Units of each sale line that stock can't cover, used in place of validate_sale_stock when
+/// allow_negative_stock is on. Lines draw on stock in order, so a product on several lines runs
+/// short on the later ones; a bundle is short by as many units as its scarcest component can't
+/// make. Reserved stock is held back as in validate_sale_stock.
+pub(crate) fn sale_shortfalls(
+    conn: &Connection,
+    items: &[(i32, f64)],
+    consumed: &[StockReservation],
+) -> Result<Vec<f64>, String> {
+    // Stock is tracked in thousandths so fractional lines run short exactly
+    let mut remaining: HashMap<i32, i64> = HashMap::new();
+    let mut shortfalls = Vec::with_capacity(items.len());
+    for &(product_id, quantity) in items {
+        let mut short = 0.0_f64;
+        for (stock_product_id, needed) in stock_requirements(conn, product_id, quantity)? {
+            let available = match remaining.get(&stock_product_id) {
+                Some(available) => *available,
+                None => {
+                    let stock: f64 = conn
+                        .query_row("SELECT stock_quantity FROM products WHERE id = ?1", [stock_product_id], |row| row.get(0))
+                        .map_err(|_| format!("Product with id {} not found", stock_product_id))?;
+                    let reserved = reservations::reserved_excluding(conn, stock_product_id, consumed)?;
+                    (to_milli(stock) - to_milli(reserved)).max(0)
+                }
+            };
+            let needed = to_milli(needed);
+            let covered = needed.min(available);
+            remaining.insert(stock_product_id, available - covered);
+
+            if covered < needed && quantity > 0.0 {
+                let missing = from_milli(needed - covered);
+                // A bundle is short by whole bundles: as many as its scarcest component can't make
+                let line_short = if stock_product_id == product_id {
+                    missing
+                } else {
+                    (missing / (from_milli(needed) / quantity)).ceil()
+                };
+                short = short.max(line_short);
+            }
+        }
+        shortfalls.push(short);
+    }
+    Ok(shortfalls)
+}
+
+/// Deduct stock for a sold invoice line and record the FIFO sale. Bundle lines deduct their
+/// components and record the consumption in invoice_item_components.
+pub(crate) fn record_item_sale(
+    conn: &Connection,
+    invoice_item_id: i32,
+    product_id: i32,
+    quantity: f64,
+    sale_date: &str,
+    invoice_id: i32,
+) -> Result<(), String> {
+    let bundle = is_bundle(conn, product_id)?;
+
+    for (stock_product_id, stock_quantity) in stock_requirements(conn, product_id, quantity)? {
+        conn.execute(
+            "UPDATE products SET stock_quantity = ROUND(stock_quantity - ?1, 3), updated_at = datetime('now') WHERE id = ?2",
+            (stock_quantity, stock_product_id),
+        )
+        .map_err(|e| format!("Failed to update product stock: {}", e))?;
+
+        inventory_service::record_sale_fifo(conn, stock_product_id, stock_quantity, sale_date, invoice_id, Some(invoice_item_id))
+            .map_err(|e| format!("Failed to record FIFO sale: {}", e))?;
+
+        if bundle {
+            conn.execute(
+                "INSERT INTO invoice_item_components (invoice_item_id, product_id, quantity) VALUES (?1, ?2, ?3)",
+                (invoice_item_id, stock_product_id, stock_quantity),
+            )
+            .map_err(|e| format!("Failed to record bundle components: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Stock consumed by an invoice line as (product_id, quantity): the recorded components
+/// for bundle lines, otherwise the line's own product
+pub(crate) fn consumed_stock(
+    conn: &Connection,
+    invoice_item_id: i32,
+    product_id: i32,
+    quantity: f64,
+) -> Result<Vec<(i32, f64)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT product_id, quantity FROM invoice_item_components WHERE invoice_item_id = ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let components = stmt
+        .query_map([invoice_item_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    if components.is_empty() {
+        Ok(vec![(product_id, quantity)])
+    } else {
+        Ok(components)
+    }
+}
+
+fn load_bundle_details(conn: &Connection, product_id: i32) -> Result<BundleDetails, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT bc.component_id, p.name, p.sku, bc.quantity, p.stock_quantity
+             FROM bundle_components bc
+             JOIN products p ON bc.component_id = p.id
+             WHERE bc.bundle_id = ?1
+             ORDER BY bc.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let components = stmt
+        .query_map([product_id], |row| {
+            Ok(BundleComponent {
+                component_id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                quantity: row.get(3)?,
+                stock_quantity: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(BundleDetails {
+        product_id,
+        components,
+        max_assemblable: max_assemblable(conn, product_id)?,
+    })
+}
+
+/// Get the components of a bundle and how many can be assembled
+#[tauri::command]
+pub fn get_bundle(product_id: i32, db: State<Database>) -> Result<BundleDetails, String> {
+    log::info!("get_bundle called for product_id: {}", product_id);
+
+    let conn = db.get_conn()?;
+    if !is_bundle(&conn, product_id)? {
+        return Err(format!("Product with id {} is not a bundle", product_id));
+    }
+    load_bundle_details(&conn, product_id)
+}
+
+/// Flag a product as a bundle with the given components, or clear the flag with an empty list.
+/// Past invoices keep the components recorded when they were sold.
+#[tauri::command]
+pub fn set_bundle_components(
+    product_id: i32,
+    components: Vec<BundleComponentInput>,
+    db: State<Database>,
+) -> Result<BundleDetails, String> {
+    log::info!(
+        "set_bundle_components called for product_id: {} ({} components)",
+        product_id,
+        components.len()
+    );
+
+    let mut conn = db.get_conn()?;
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let (was_bundle, stock): (bool, f64) = tx
+        .query_row(
+            "SELECT COALESCE(is_bundle, 0) != 0, stock_quantity FROM products WHERE id = ?1",
+            [product_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|_| format!("Product with id {} not found", product_id))?;
+
+    if !components.is_empty() && !was_bundle && stock > 0.0 {
+        return Err(format!(
+            "Product has {} units in stock. A bundle holds no stock of its own; adjust its stock to zero first.",
+            quantity::format_quantity(stock)
+        ));
+    }
+
+    let mut seen = Vec::new();
+    for component in &components {
+        if component.quantity <= 0 {
+            return Err("Component quantity must be greater than zero".to_string());
+        }
+        if component.component_id == product_id {
+            return Err("A bundle cannot contain itself".to_string());
+        }
+        if seen.contains(&component.component_id) {
+            return Err(format!("Component {} is listed more than once", component.component_id));
+        }
+        if is_bundle(&tx, component.component_id)? {
+            return Err("Bundles cannot contain other bundles".to_string());
+        }
+        seen.push(component.component_id);
+    }
+
+    tx.execute("DELETE FROM bundle_components WHERE bundle_id = ?1", [product_id])
+        .map_err(|e| format!("Failed to clear bundle components: {}", e))?;
+    for component in &components {
+        tx.execute(
+            "INSERT INTO bundle_components (bundle_id, component_id, quantity) VALUES (?1, ?2, ?3)",
+            params![product_id, component.component_id, component.quantity],
+        )
+        .map_err(|e| format!("Failed to save bundle component: {}", e))?;
+    }
+    tx.execute(
+        "UPDATE products SET is_bundle = ?1, updated_at = ?2 WHERE id = ?3",
+        params![!components.is_empty(), chrono::Utc::now().to_rfc3339(), product_id],
+    )
+    .map_err(|e| format!("Failed to update product: {}", e))?;
+
+    let details = load_bundle_details(&tx, product_id)?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    Ok(details)
+}
+
+/// Component stock consumed by bundle sales in a date range, so purchasing sees the real draw
+#[tauri::command]
+pub fn get_bundle_component_usage(
+    start_date: String,
+    end_date: String,
+    db: State<Database>,
+) -> Result<Vec<BundleComponentUsage>, String> {
+    log::info!("get_bundle_component_usage called: {} to {}", start_date, end_date);
+
+    let conn = db.get_conn()?;
+    let range = ReportRange::load(&conn, &start_date, &end_date)?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT iic.product_id, COALESCE(p.name, 'Deleted product'), COALESCE(p.sku, ''),
+                    SUM(iic.quantity), SUM(ii.quantity), COALESCE(p.stock_quantity, 0)
+             FROM invoice_item_components iic
+             JOIN invoice_items ii ON iic.invoice_item_id = ii.id
+             JOIN invoices i ON ii.invoice_id = i.id
+             LEFT JOIN products p ON iic.product_id = p.id
+             WHERE datetime(i.created_at) >= ?1 AND datetime(i.created_at) < ?2
+             GROUP BY iic.product_id
+             ORDER BY SUM(iic.quantity) DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let usage = stmt
+        .query_map(params![range.start_utc, range.end_utc], |row| {
+            Ok(BundleComponentUsage {
+                component_id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                quantity_consumed: row.get(3)?,
+                bundles_sold: row.get(4)?,
+                stock_quantity: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(usage)
+}
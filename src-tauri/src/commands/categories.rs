@@ -0,0 +1,418 @@
+/// Category Commands
+/// Product categories as records with one level of nesting (a category may have a parent,
+/// but a parent cannot itself be nested). products.category_id links a product to its
+/// category; products.category keeps the category name in step so text filters keep working.
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::Database;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Category {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+    pub sort_order: i32,
+    /// Products linked directly to this category (not counting subcategories)
+    pub product_count: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCategoryInput {
+    pub name: String,
+    pub parent_id: Option<i32>,
+    pub sort_order: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateCategoryInput {
+    pub id: i32,
+    pub name: String,
+    pub parent_id: Option<i32>,
+    pub sort_order: i32,
+}
+
+const CATEGORY_COLUMNS: &str = "c.id, c.name, c.parent_id, c.sort_order,
+    (SELECT COUNT(*) FROM products p WHERE p.category_id = c.id), c.created_at, c.updated_at";
+
+fn category_from_row(row: &rusqlite::Row) -> rusqlite::Result<Category> {
+    Ok(Category {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        parent_id: row.get(2)?,
+        sort_order: row.get(3)?,
+        product_count: row.get(4)?,
+        created_at: row.get(5)?,
+        updated_at: row.get(6)?,
+    })
+}
+
+fn get_category_internal(conn: &Connection, id: i32) -> Result<Category, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM categories c WHERE c.id = ?1", CATEGORY_COLUMNS),
+        [id],
+        category_from_row,
+    )
+    .map_err(|_| format!("Category with id {} not found", id))
+}
+
+/// Look up a category by name (case-insensitive), creating it if it does not exist.
+/// Returns the category id and its canonical name, or (None, None) for a blank name.
+pub(crate) fn resolve_category(conn: &Connection, name: Option<&str>) -> Result<(Option<i32>, Option<String>), String> {
+    let name = match name.map(str::trim).filter(|n| !n.is_empty()) {
+        Some(name) => name,
+        None => return Ok((None, None)),
+    };
+
+    let existing: Option<(i32, String)> = conn
+        .query_row(
+            "SELECT id, name FROM categories WHERE name = ?1 COLLATE NOCASE",
+            [name],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to look up category: {}", e))?;
+
+    if let Some((id, canonical)) = existing {
+        return Ok((Some(id), Some(canonical)));
+    }
+
+    conn.execute(
+        "INSERT INTO categories (name, sort_order)
+         VALUES (?1, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM categories WHERE parent_id IS NULL))",
+        [name],
+    )
+    .map_err(|e| format!("Failed to create category: {}", e))?;
+
+    Ok((Some(conn.last_insert_rowid() as i32), Some(name.to_string())))
+}
+
+/// Check a name is free and a parent keeps the hierarchy one level deep
+fn validate_category(conn: &Connection, id: Option<i32>, name: &str, parent_id: Option<i32>) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Category name cannot be empty".to_string());
+    }
+
+    let clash: Option<i32> = conn
+        .query_row(
+            "SELECT id FROM categories WHERE name = ?1 COLLATE NOCASE AND (?2 IS NULL OR id != ?2)",
+            params![name, id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if clash.is_some() {
+        return Err(format!("Category '{}' already exists", name));
+    }
+
+    if let Some(parent_id) = parent_id {
+        if Some(parent_id) == id {
+            return Err("A category cannot be its own parent".to_string());
+        }
+        let parent = get_category_internal(conn, parent_id)?;
+        if parent.parent_id.is_some() {
+            return Err(format!("'{}' is a subcategory; categories can only be nested one level deep", parent.name));
+        }
+        if let Some(id) = id {
+            let children: i64 = conn
+                .query_row("SELECT COUNT(*) FROM categories WHERE parent_id = ?1", [id], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            if children > 0 {
+                return Err(format!("'{}' has subcategories and cannot be nested", name));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Get all categories, ordered for display. Subcategories carry their parent_id.
+#[tauri::command]
+pub fn get_categories(db: State<Database>) -> Result<Vec<Category>, String> {
+    log::info!("get_categories called");
+
+    let conn = db.get_conn()?;
+    get_categories_internal(&conn)
+}
+
+pub(crate) fn get_categories_internal(conn: &Connection) -> Result<Vec<Category>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM categories c ORDER BY c.sort_order, c.name COLLATE NOCASE",
+            CATEGORY_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let categories = stmt
+        .query_map([], category_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(categories)
+}
+
+/// Create a category
+#[tauri::command]
+pub fn create_category(input: CreateCategoryInput, db: State<Database>) -> Result<Category, String> {
+    log::info!("create_category called with: {:?}", input);
+
+    let conn = db.get_conn()?;
+    create_category_internal(&conn, input)
+}
+
+pub(crate) fn create_category_internal(conn: &Connection, input: CreateCategoryInput) -> Result<Category, String> {
+    let name = input.name.trim();
+    validate_category(conn, None, name, input.parent_id)?;
+
+    conn.execute(
+        "INSERT INTO categories (name, parent_id, sort_order)
+         VALUES (?1, ?2, COALESCE(?3, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM categories
+                                       WHERE parent_id IS ?2)))",
+        params![name, input.parent_id, input.sort_order],
+    )
+    .map_err(|e| format!("Failed to create category: {}", e))?;
+
+    get_category_internal(conn, conn.last_insert_rowid() as i32)
+}
+
+/// Rename, move or reorder a category. A rename is applied to its products' category text.
+#[tauri::command]
+pub fn update_category(input: UpdateCategoryInput, db: State<Database>) -> Result<Category, String> {
+    log::info!("update_category called with: {:?}", input);
+
+    let conn = db.get_conn()?;
+    update_category_internal(&conn, input)
+}
+
+pub(crate) fn update_category_internal(conn: &Connection, input: UpdateCategoryInput) -> Result<Category, String> {
+    get_category_internal(conn, input.id)?;
+    let name = input.name.trim();
+    validate_category(conn, Some(input.id), name, input.parent_id)?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    tx.execute(
+        "UPDATE categories SET name = ?1, parent_id = ?2, sort_order = ?3, updated_at = datetime('now') WHERE id = ?4",
+        params![name, input.parent_id, input.sort_order, input.id],
+    )
+    .map_err(|e| format!("Failed to update category: {}", e))?;
+    tx.execute("UPDATE products SET category = ?1 WHERE category_id = ?2", params![name, input.id])
+        .map_err(|e| format!("Failed to update product categories: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    get_category_internal(conn, input.id)
+}
+
+/// Delete a category. Its products move to `reassign_to` (or become uncategorized) and its
+/// subcategories become top-level categories.
+#[tauri::command]
+pub fn delete_category(id: i32, reassign_to: Option<i32>, db: State<Database>) -> Result<(), String> {
+    log::info!("delete_category called with id: {}, reassign_to: {:?}", id, reassign_to);
+
+    let conn = db.get_conn()?;
+    delete_category_internal(&conn, id, reassign_to)
+}
+
+pub(crate) fn delete_category_internal(conn: &Connection, id: i32, reassign_to: Option<i32>) -> Result<(), String> {
+    get_category_internal(conn, id)?;
+    if reassign_to == Some(id) {
+        return Err("Cannot reassign products to the category being deleted".to_string());
+    }
+    let target_name = match reassign_to {
+        Some(target) => Some(get_category_internal(conn, target)?.name),
+        None => None,
+    };
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    tx.execute(
+        "UPDATE products SET category_id = ?1, category = ?2, updated_at = datetime('now') WHERE category_id = ?3",
+        params![reassign_to, target_name, id],
+    )
+    .map_err(|e| format!("Failed to reassign products: {}", e))?;
+    tx.execute("UPDATE categories SET parent_id = NULL WHERE parent_id = ?1", [id])
+        .map_err(|e| format!("Failed to move subcategories: {}", e))?;
+    tx.execute("DELETE FROM categories WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to delete category: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Deleted category {}", id);
+    Ok(())
+}
+
+/// Move products to a category (None leaves them uncategorized). Returns the number moved.
+#[tauri::command]
+pub fn reassign_products_category(
+    product_ids: Vec<i32>,
+    category_id: Option<i32>,
+    db: State<Database>,
+) -> Result<usize, String> {
+    log::info!("reassign_products_category called for {} product(s), category_id: {:?}", product_ids.len(), category_id);
+
+    let conn = db.get_conn()?;
+    reassign_products_category_internal(&conn, &product_ids, category_id)
+}
+
+pub(crate) fn reassign_products_category_internal(
+    conn: &Connection,
+    product_ids: &[i32],
+    category_id: Option<i32>,
+) -> Result<usize, String> {
+    let name = match category_id {
+        Some(id) => Some(get_category_internal(conn, id)?.name),
+        None => None,
+    };
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let mut moved = 0;
+    for product_id in product_ids {
+        moved += tx
+            .execute(
+                "UPDATE products SET category_id = ?1, category = ?2, updated_at = datetime('now') WHERE id = ?3",
+                params![category_id, name, product_id],
+            )
+            .map_err(|e| format!("Failed to reassign product {}: {}", product_id, e))?;
+    }
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(moved)
+}
+
+/// Merge a duplicate category (e.g. a misspelling) into another. The source's products move to
+/// the target and its subcategories move under the target, or alongside it when the target is
+/// itself a subcategory. The source is then deleted.
+#[tauri::command]
+pub fn merge_categories(source_id: i32, target_id: i32, db: State<Database>) -> Result<Category, String> {
+    log::info!("merge_categories called: source_id: {}, target_id: {}", source_id, target_id);
+
+    let conn = db.get_conn()?;
+    merge_categories_internal(&conn, source_id, target_id)
+}
+
+pub(crate) fn merge_categories_internal(conn: &Connection, source_id: i32, target_id: i32) -> Result<Category, String> {
+    if source_id == target_id {
+        return Err("Cannot merge a category into itself".to_string());
+    }
+    let source = get_category_internal(conn, source_id)?;
+    let target = get_category_internal(conn, target_id)?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // A target nested under the source takes the source's place in the hierarchy
+    let target_parent = if target.parent_id == Some(source_id) { source.parent_id } else { target.parent_id };
+    tx.execute("UPDATE categories SET parent_id = ?1 WHERE id = ?2", params![target_parent, target_id])
+        .map_err(|e| format!("Failed to update category: {}", e))?;
+    tx.execute(
+        "UPDATE categories SET parent_id = ?1 WHERE parent_id = ?2",
+        params![target_parent.unwrap_or(target_id), source_id],
+    )
+    .map_err(|e| format!("Failed to move subcategories: {}", e))?;
+
+    let moved = tx
+        .execute(
+            "UPDATE products SET category_id = ?1, category = ?2, updated_at = datetime('now') WHERE category_id = ?3",
+            params![target_id, target.name, source_id],
+        )
+        .map_err(|e| format!("Failed to repoint products: {}", e))?;
+    tx.execute("DELETE FROM categories WHERE id = ?1", [source_id])
+        .map_err(|e| format!("Failed to delete category: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Merged category '{}' into '{}' ({} product(s) moved)", source.name, target.name, moved);
+    get_category_internal(conn, target_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    fn create(conn: &Connection, name: &str, parent_id: Option<i32>) -> Category {
+        create_category_internal(conn, CreateCategoryInput { name: name.to_string(), parent_id, sort_order: None }).unwrap()
+    }
+
+    fn product_category(conn: &Connection, product_id: i32) -> (Option<i32>, Option<String>) {
+        conn.query_row("SELECT category_id, category FROM products WHERE id = ?1", [product_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_hierarchy_is_one_level_deep() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let electronics = create(&conn, "Electronics", None);
+        let phones = create(&conn, "Phones", Some(electronics.id));
+        assert_eq!(phones.sort_order, 0);
+
+        let nested = create_category_internal(
+            &conn,
+            CreateCategoryInput { name: "Cases".to_string(), parent_id: Some(phones.id), sort_order: None },
+        );
+        assert!(nested.unwrap_err().contains("one level deep"));
+        assert!(create_category_internal(
+            &conn,
+            CreateCategoryInput { name: "electronics".to_string(), parent_id: None, sort_order: None }
+        )
+        .unwrap_err()
+        .contains("already exists"));
+
+        let toys = create(&conn, "Toys", None);
+        let moved = update_category_internal(
+            &conn,
+            UpdateCategoryInput { id: electronics.id, name: "Electronics".to_string(), parent_id: Some(toys.id), sort_order: 0 },
+        );
+        assert!(moved.unwrap_err().contains("has subcategories"));
+    }
+
+    #[test]
+    fn test_merge_repoints_products_and_subcategories() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let (typo_id, _) = resolve_category(&conn, Some("Electroncis")).unwrap();
+        let (same_id, canonical) = resolve_category(&conn, Some(" electroncis ")).unwrap();
+        assert_eq!(same_id, typo_id);
+        assert_eq!(canonical.as_deref(), Some("Electroncis"));
+        let typo_id = typo_id.unwrap();
+        let cables = create(&conn, "Cables", Some(typo_id));
+        let target = create(&conn, "Electronics", None);
+
+        conn.execute(
+            "INSERT INTO products (name, sku, price, stock_quantity, category_id, category) VALUES ('TV', 'TV', 100, 1, ?1, 'Electroncis')",
+            [typo_id],
+        )
+        .unwrap();
+        let tv = conn.last_insert_rowid() as i32;
+
+        let merged = merge_categories_internal(&conn, typo_id, target.id).unwrap();
+        assert_eq!(merged.product_count, 1);
+        assert_eq!(product_category(&conn, tv), (Some(target.id), Some("Electronics".to_string())));
+        assert_eq!(get_category_internal(&conn, cables.id).unwrap().parent_id, Some(target.id));
+        assert!(get_category_internal(&conn, typo_id).is_err());
+
+        update_category_internal(
+            &conn,
+            UpdateCategoryInput { id: target.id, name: "Consumer Electronics".to_string(), parent_id: None, sort_order: 3 },
+        )
+        .unwrap();
+        assert_eq!(product_category(&conn, tv).1.as_deref(), Some("Consumer Electronics"));
+
+        delete_category_internal(&conn, target.id, Some(cables.id)).unwrap();
+        assert_eq!(product_category(&conn, tv), (Some(cables.id), Some("Cables".to_string())));
+        assert_eq!(get_category_internal(&conn, cables.id).unwrap().parent_id, None);
+
+        assert_eq!(reassign_products_category_internal(&conn, &[tv], None).unwrap(), 1);
+        assert_eq!(product_category(&conn, tv), (None, None));
+    }
+}
@@ -0,0 +1,687 @@
+/// Delivery Challans
+/// Goods sent to a customer's site before they are invoiced. An open challan holds its stock
+/// one of two ways, per the challan_stock_mode setting when it is created: "reserve" puts a
+/// stock reservation on each line (held until the challan is closed), "deduct" takes the units
+/// out of stock through the inventory service. Converting bills the challan through
+/// create_invoice, which draws on the challan's reservations, so the stock only leaves once;
+/// a deducted challan is first switched to reservations for that. Returning an open challan
+/// gives its stock back.
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::commands::bundles;
+use crate::commands::invoices::{
+    create_invoice_internal, queue_invoice_created, CreateInvoiceInput, CreateInvoiceItemInput, InvoiceTender,
+};
+use crate::commands::price_tiers::{customer_price_tier, list_price, tier_unit_price};
+use crate::commands::products;
+use crate::commands::reservations::{insert_reservation, release_reservation_internal};
+use crate::commands::stock_alerts;
+use crate::db::{Database, Invoice};
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use crate::services::{inventory_service, money, quantity};
+
+/// app_settings key choosing how new challans hold stock: "reserve" (default) or "deduct"
+pub const CHALLAN_STOCK_MODE_KEY: &str = "challan_stock_mode";
+pub const CHALLAN_STOCK_MODES: &[&str] = &["reserve", "deduct"];
+pub const CHALLAN_STATUSES: &[&str] = &["open", "invoiced", "returned"];
+
+/// Challan reservations are held until the challan is closed, not for a fixed time
+const CHALLAN_HOLD_EXPIRES_AT: &str = "9999-12-31 23:59:59";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChallanItemInput {
+    pub product_id: i32,
+    pub quantity: f64,
+    /// Price the goods will be billed at; defaults to the customer's tier price or the list price
+    #[serde(default)]
+    pub unit_price: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateChallanInput {
+    pub customer_id: i32,
+    pub items: Vec<ChallanItemInput>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+/// Billing details for the invoice a challan is converted to; the customer and items come from
+/// the challan and the rest falls back to the billing defaults as in create_invoice
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConvertChallanInput {
+    pub challan_id: i32,
+    #[serde(default)]
+    pub payment_method: Option<String>,
+    #[serde(default)]
+    pub initial_paid: Option<f64>,
+    #[serde(default)]
+    pub payments: Option<Vec<InvoiceTender>>,
+    #[serde(default)]
+    pub gst_rate: Option<f64>,
+    #[serde(default)]
+    pub allow_over_limit: bool,
+    #[serde(default)]
+    pub approved_by: Option<String>,
+    #[serde(default)]
+    pub price_override_reason: Option<String>,
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryChallanItem {
+    pub id: i32,
+    pub product_id: i32,
+    pub product_name: String,
+    pub sku: Option<String>,
+    pub quantity: f64,
+    pub unit_price: f64,
+    /// Reservation holding the line's stock while a "reserve" challan is open
+    pub reservation_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryChallan {
+    pub id: i32,
+    pub challan_number: String,
+    pub customer_id: Option<i32>,
+    pub customer_name: Option<String>,
+    pub customer_phone: Option<String>,
+    /// "open", "invoiced" or "returned"
+    pub status: String,
+    /// "reserve" or "deduct"
+    pub stock_mode: String,
+    pub notes: Option<String>,
+    pub invoice_id: Option<i32>,
+    pub invoice_number: Option<String>,
+    /// Quantity times unit price over the lines
+    pub total_value: f64,
+    pub created_by: Option<String>,
+    pub created_at: String,
+    /// When the challan was invoiced or returned
+    pub closed_at: Option<String>,
+    pub items: Vec<DeliveryChallanItem>,
+}
+
+/// How new challans hold stock, per the challan_stock_mode setting
+fn challan_stock_mode(conn: &Connection) -> Result<String, String> {
+    let mode: Option<String> = conn
+        .query_row("SELECT value FROM app_settings WHERE key = ?1", [CHALLAN_STOCK_MODE_KEY], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to load challan stock mode: {}", e))?;
+    Ok(mode
+        .map(|mode| mode.trim().to_string())
+        .filter(|mode| CHALLAN_STOCK_MODES.contains(&mode.as_str()))
+        .unwrap_or_else(|| "reserve".to_string()))
+}
+
+/// Next challan number (DC-YYYY-NNN)
+fn generate_challan_number(conn: &Connection) -> Result<String, String> {
+    let prefix = format!("DC-{}-", Utc::now().format("%Y"));
+    let max_seq: i32 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(CAST(substr(challan_number, ?2) AS INTEGER)), 0)
+             FROM delivery_challans WHERE challan_number LIKE ?1 || '%'",
+            params![prefix, prefix.len() as i32 + 1],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to generate challan number: {}", e))?;
+    Ok(format!("{}{:03}", prefix, max_seq + 1))
+}
+
+fn log_challan_action(conn: &Connection, challan: &DeliveryChallan, action: &str, field_changes: serde_json::Value, by: Option<&str>) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by)
+         VALUES ('challan', ?1, ?2, ?3, ?4, ?5)",
+        params![challan.id, challan.challan_number, action, field_changes.to_string(), by],
+    )
+    .map_err(|e| format!("Failed to log challan {}: {}", action, e))?;
+    Ok(())
+}
+
+/// Send goods to a customer on a delivery challan, holding their stock
+#[tauri::command]
+pub fn create_challan(input: CreateChallanInput, app: AppHandle, db: State<Database>) -> Result<DeliveryChallan, String> {
+    log::info!("create_challan called for customer {} with {} item(s)", input.customer_id, input.items.len());
+
+    let conn = db.get_conn()?;
+    let challan = create_challan_internal(&conn, &input)?;
+    for item in &challan.items {
+        emit_data_changed(&app, DataEntity::Product, DataOperation::Updated, item.product_id);
+    }
+    Ok(challan)
+}
+
+pub(crate) fn create_challan_internal(conn: &Connection, input: &CreateChallanInput) -> Result<DeliveryChallan, String> {
+    if input.items.is_empty() {
+        return Err("A challan needs at least one item".to_string());
+    }
+    let customer_exists: bool = conn
+        .query_row("SELECT COUNT(*) > 0 FROM customers WHERE id = ?1", [input.customer_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if !customer_exists {
+        return Err(format!("Customer with id {} not found", input.customer_id));
+    }
+    for item in &input.items {
+        if item.quantity <= 0.0 {
+            return Err("Item quantity must be greater than 0".to_string());
+        }
+        products::check_product_quantity(conn, item.product_id, item.quantity)?;
+        if bundles::is_bundle(conn, item.product_id)? {
+            return Err("Bundles hold no stock of their own; put their components on the challan instead".to_string());
+        }
+        if item.unit_price.is_some_and(|price| price < 0.0) {
+            return Err("Item price cannot be negative".to_string());
+        }
+    }
+    let requested: Vec<(i32, f64)> = input.items.iter().map(|item| (item.product_id, item.quantity)).collect();
+    bundles::validate_sale_stock(conn, &requested, &[])?;
+
+    let stock_mode = challan_stock_mode(conn)?;
+    let price_tier = customer_price_tier(conn, input.customer_id)?;
+    let notes = input.notes.as_deref().map(str::trim).filter(|notes| !notes.is_empty());
+    let today = Utc::now().format("%Y-%m-%d").to_string();
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let challan_number = generate_challan_number(&tx)?;
+    tx.execute(
+        "INSERT INTO delivery_challans (challan_number, customer_id, status, stock_mode, notes, created_by, created_at)
+         VALUES (?1, ?2, 'open', ?3, ?4, ?5, datetime('now'))",
+        params![challan_number, input.customer_id, stock_mode, notes, input.created_by],
+    )
+    .map_err(|e| format!("Failed to create challan: {}", e))?;
+    let challan_id = tx.last_insert_rowid() as i32;
+
+    for item in &input.items {
+        let product_name: String = tx
+            .query_row("SELECT name FROM products WHERE id = ?1", [item.product_id], |row| row.get(0))
+            .map_err(|_| format!("Product with id {} not found", item.product_id))?;
+        let unit_price = match item.unit_price {
+            Some(price) => money::round_money(price),
+            None => {
+                let list = list_price(&tx, item.product_id)?;
+                match &price_tier {
+                    Some((tier_id, _)) => tier_unit_price(&tx, *tier_id, item.product_id, list)?.0,
+                    None => list,
+                }
+            }
+        };
+        let reservation_id = match stock_mode.as_str() {
+            "reserve" => Some(insert_reservation(
+                &tx,
+                item.product_id,
+                item.quantity,
+                &format!("Challan {}", challan_number),
+                CHALLAN_HOLD_EXPIRES_AT,
+                input.created_by.as_deref(),
+            )?),
+            _ => None,
+        };
+
+        tx.execute(
+            "INSERT INTO delivery_challan_items (challan_id, product_id, product_name, quantity, unit_price, reservation_id)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![challan_id, item.product_id, product_name, item.quantity, unit_price, reservation_id],
+        )
+        .map_err(|e| format!("Failed to create challan item: {}", e))?;
+        let challan_item_id = tx.last_insert_rowid() as i32;
+
+        if reservation_id.is_none() {
+            inventory_service::record_challan_dispatch(&tx, item.product_id, item.quantity, challan_id, challan_item_id, &today)?;
+        }
+    }
+
+    let challan = get_challan_internal(&tx, challan_id)?;
+    log_challan_action(
+        &tx,
+        &challan,
+        "created",
+        serde_json::json!([{ "field": "stock_mode", "old": null, "new": challan.stock_mode }]),
+        input.created_by.as_deref(),
+    )?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Created challan {} ({}) for customer {}", challan.challan_number, challan.stock_mode, input.customer_id);
+    Ok(challan)
+}
+
+/// Get challans, newest first, optionally by status and customer
+#[tauri::command]
+pub fn get_challans(status: Option<String>, customer_id: Option<i32>, db: State<Database>) -> Result<Vec<DeliveryChallan>, String> {
+    log::info!("get_challans called with status: {:?}, customer_id: {:?}", status, customer_id);
+
+    let conn = db.get_conn()?;
+    get_challans_internal(&conn, status.as_deref(), customer_id)
+}
+
+pub(crate) fn get_challans_internal(
+    conn: &Connection,
+    status: Option<&str>,
+    customer_id: Option<i32>,
+) -> Result<Vec<DeliveryChallan>, String> {
+    let status = status.map(str::trim).filter(|status| !status.is_empty());
+    if let Some(status) = status {
+        if !CHALLAN_STATUSES.contains(&status) {
+            return Err(format!("Validation error: unknown challan status '{}'", status));
+        }
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id FROM delivery_challans
+             WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR customer_id = ?2)
+             ORDER BY created_at DESC, id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map(params![status, customer_id], |row| row.get::<_, i32>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load challans: {}", e))?;
+
+    ids.into_iter().map(|id| get_challan_internal(conn, id)).collect()
+}
+
+/// Get one challan with its items
+#[tauri::command]
+pub fn get_challan(id: i32, db: State<Database>) -> Result<DeliveryChallan, String> {
+    log::info!("get_challan called with id: {}", id);
+
+    let conn = db.get_conn()?;
+    get_challan_internal(&conn, id)
+}
+
+pub(crate) fn get_challan_internal(conn: &Connection, id: i32) -> Result<DeliveryChallan, String> {
+    let mut challan = conn
+        .query_row(
+            "SELECT dc.id, dc.challan_number, dc.customer_id, c.name, c.phone, dc.status, dc.stock_mode, dc.notes,
+                    dc.invoice_id, i.invoice_number, dc.created_by, dc.created_at, dc.closed_at
+             FROM delivery_challans dc
+             LEFT JOIN customers c ON c.id = dc.customer_id
+             LEFT JOIN invoices i ON i.id = dc.invoice_id
+             WHERE dc.id = ?1",
+            [id],
+            |row| {
+                Ok(DeliveryChallan {
+                    id: row.get(0)?,
+                    challan_number: row.get(1)?,
+                    customer_id: row.get(2)?,
+                    customer_name: row.get(3)?,
+                    customer_phone: row.get(4)?,
+                    status: row.get(5)?,
+                    stock_mode: row.get(6)?,
+                    notes: row.get(7)?,
+                    invoice_id: row.get(8)?,
+                    invoice_number: row.get(9)?,
+                    total_value: 0.0,
+                    created_by: row.get(10)?,
+                    created_at: row.get(11)?,
+                    closed_at: row.get(12)?,
+                    items: Vec::new(),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load challan: {}", e))?
+        .ok_or_else(|| format!("Challan with id {} not found", id))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT ci.id, ci.product_id, ci.product_name, p.sku, ci.quantity, ci.unit_price, ci.reservation_id
+             FROM delivery_challan_items ci
+             LEFT JOIN products p ON p.id = ci.product_id
+             WHERE ci.challan_id = ?1
+             ORDER BY ci.id",
+        )
+        .map_err(|e| e.to_string())?;
+    challan.items = stmt
+        .query_map([id], |row| {
+            Ok(DeliveryChallanItem {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                product_name: row.get(2)?,
+                sku: row.get(3)?,
+                quantity: row.get(4)?,
+                unit_price: row.get(5)?,
+                reservation_id: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load challan items: {}", e))?;
+    challan.total_value = money::sum(challan.items.iter().map(|item| money::line_total(item.unit_price, item.quantity)));
+
+    Ok(challan)
+}
+
+fn open_challan(conn: &Connection, id: i32) -> Result<DeliveryChallan, String> {
+    let challan = get_challan_internal(conn, id)?;
+    if challan.status != "open" {
+        return Err(format!("Challan {} is already {}", challan.challan_number, challan.status));
+    }
+    Ok(challan)
+}
+
+/// Release a reservation a challan line holds, if it still exists
+fn release_line_hold(conn: &Connection, item: &DeliveryChallanItem) -> Result<(), String> {
+    let Some(reservation_id) = item.reservation_id else {
+        return Ok(());
+    };
+    let held: bool = conn
+        .query_row("SELECT COUNT(*) > 0 FROM stock_reservations WHERE id = ?1", [reservation_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to load reservation: {}", e))?;
+    if held {
+        release_reservation_internal(conn, reservation_id)?;
+    }
+    Ok(())
+}
+
+/// Bill an open challan through create_invoice and mark it invoiced
+#[tauri::command]
+pub fn convert_challan_to_invoice(input: ConvertChallanInput, app: AppHandle, db: State<Database>) -> Result<Invoice, String> {
+    log::info!("convert_challan_to_invoice called for challan {}", input.challan_id);
+
+    let mut conn = db.get_conn()?;
+    let invoice = convert_challan_to_invoice_internal(&mut conn, &input)?;
+    emit_data_changed(&app, DataEntity::Invoice, DataOperation::Created, invoice.id);
+    queue_invoice_created(&app, &conn, invoice.id);
+    stock_alerts::notify_stock_alerts(&app, &conn, invoice.id);
+    Ok(invoice)
+}
+
+pub(crate) fn convert_challan_to_invoice_internal(conn: &mut Connection, input: &ConvertChallanInput) -> Result<Invoice, String> {
+    let mut challan = open_challan(conn, input.challan_id)?;
+    if challan.customer_id.is_none() {
+        return Err(format!("Challan {} has no customer to invoice", challan.challan_number));
+    }
+
+    // A deducted challan's units go back into stock under reservations, so the invoice takes
+    // them out once. If billing then fails the challan stays open, holding them that way.
+    if challan.stock_mode == "deduct" {
+        let tx = conn
+            .unchecked_transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        // Switch the mode first, so a return racing this finds the units under reservations
+        let switched = tx
+            .execute(
+                "UPDATE delivery_challans SET stock_mode = 'reserve' WHERE id = ?1 AND status = 'open' AND stock_mode = 'deduct'",
+                [challan.id],
+            )
+            .map_err(|e| format!("Failed to update challan: {}", e))?;
+        if switched == 0 {
+            return Err(format!("Challan {} is no longer open", challan.challan_number));
+        }
+        for item in &challan.items {
+            inventory_service::restore_stock_from_challan(&tx, item.product_id, item.quantity, challan.id, item.id)?;
+            let reservation_id = insert_reservation(
+                &tx,
+                item.product_id,
+                item.quantity,
+                &format!("Challan {}", challan.challan_number),
+                CHALLAN_HOLD_EXPIRES_AT,
+                input.created_by.as_deref(),
+            )?;
+            tx.execute(
+                "UPDATE delivery_challan_items SET reservation_id = ?1 WHERE id = ?2",
+                params![reservation_id, item.id],
+            )
+            .map_err(|e| format!("Failed to update challan item: {}", e))?;
+        }
+        tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        challan = get_challan_internal(conn, challan.id)?;
+    }
+
+    // Claim the challan before raising the invoice; a crash after this can't bill it twice
+    let claimed = conn
+        .execute(
+            "UPDATE delivery_challans SET status = 'invoiced', closed_at = datetime('now') WHERE id = ?1 AND status = 'open'",
+            [challan.id],
+        )
+        .map_err(|e| format!("Failed to update challan: {}", e))?;
+    if claimed == 0 {
+        return Err(format!("Challan {} is no longer open", challan.challan_number));
+    }
+
+    let invoice_input = CreateInvoiceInput {
+        customer_id: challan.customer_id,
+        items: challan
+            .items
+            .iter()
+            .map(|item| CreateInvoiceItemInput {
+                product_id: item.product_id,
+                quantity: item.quantity,
+                unit_price: item.unit_price,
+                discount_amount: None,
+                tax_rate: None,
+                reservation_id: item.reservation_id,
+            })
+            .collect(),
+        tax_amount: None,
+        discount_amount: None,
+        payment_method: input.payment_method.clone(),
+        state: None,
+        district: None,
+        town: None,
+        initial_paid: input.initial_paid,
+        price_tier_id: None,
+        gst_rate: input.gst_rate,
+        allow_over_limit: input.allow_over_limit,
+        approved_by: input.approved_by.clone(),
+        due_date: None,
+        payments: input.payments.clone(),
+        price_override_reason: input.price_override_reason.clone(),
+        price_override_approval: None,
+        created_by: input.created_by.clone(),
+    };
+    let invoice = match create_invoice_internal(conn, invoice_input) {
+        Ok(invoice) => invoice,
+        Err(e) => {
+            conn.execute(
+                "UPDATE delivery_challans SET status = 'open', closed_at = NULL WHERE id = ?1",
+                [challan.id],
+            )
+            .map_err(|e| format!("Failed to update challan: {}", e))?;
+            return Err(e);
+        }
+    };
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    tx.execute("UPDATE delivery_challans SET invoice_id = ?1 WHERE id = ?2", params![invoice.id, challan.id])
+        .map_err(|e| format!("Failed to update challan: {}", e))?;
+    log_challan_action(
+        &tx,
+        &challan,
+        "invoiced",
+        serde_json::json!([
+            { "field": "status", "old": "open", "new": "invoiced" },
+            { "field": "invoice_number", "old": null, "new": invoice.invoice_number },
+        ]),
+        input.created_by.as_deref(),
+    )?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Challan {} converted to invoice {}", challan.challan_number, invoice.invoice_number);
+    Ok(invoice)
+}
+
+/// Take back the goods of an open challan, returning its stock
+#[tauri::command]
+pub fn return_challan(id: i32, returned_by: Option<String>, app: AppHandle, db: State<Database>) -> Result<DeliveryChallan, String> {
+    log::info!("return_challan called for id: {}, returned_by: {:?}", id, returned_by);
+
+    let conn = db.get_conn()?;
+    let challan = return_challan_internal(&conn, id, returned_by.as_deref())?;
+    for item in &challan.items {
+        emit_data_changed(&app, DataEntity::Product, DataOperation::Updated, item.product_id);
+    }
+    Ok(challan)
+}
+
+pub(crate) fn return_challan_internal(conn: &Connection, id: i32, returned_by: Option<&str>) -> Result<DeliveryChallan, String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // Claim the challan first, so a second return or a conversion can't also act on its stock.
+    // Its stock mode and lines are read after the claim; a conversion may have just switched
+    // a deducted challan to reservations.
+    let claimed = tx
+        .execute(
+            "UPDATE delivery_challans SET status = 'returned', closed_at = datetime('now') WHERE id = ?1 AND status = 'open'",
+            [id],
+        )
+        .map_err(|e| format!("Failed to update challan: {}", e))?;
+    if claimed == 0 {
+        let challan = get_challan_internal(&tx, id)?;
+        return Err(format!("Challan {} is already {}", challan.challan_number, challan.status));
+    }
+    let challan = get_challan_internal(&tx, id)?;
+
+    for item in &challan.items {
+        match challan.stock_mode.as_str() {
+            "deduct" => inventory_service::restore_stock_from_challan(&tx, item.product_id, item.quantity, challan.id, item.id)?,
+            _ => release_line_hold(&tx, item)?,
+        }
+    }
+    let returned_units = quantity::sum(challan.items.iter().map(|item| item.quantity));
+    log_challan_action(
+        &tx,
+        &challan,
+        "returned",
+        serde_json::json!([
+            { "field": "status", "old": "open", "new": "returned" },
+            { "field": "quantity", "old": returned_units, "new": 0 },
+        ]),
+        returned_by,
+    )?;
+    let challan = get_challan_internal(&tx, challan.id)?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Challan {} returned", challan.challan_number);
+    Ok(challan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::products::{create_product_internal, get_product_internal};
+    use crate::commands::reservations::get_reservations_internal;
+    use crate::test_support::{batch_quantity, insert_customer, product_input, TestDb};
+
+    fn product(conn: &Connection, sku: &str, stock: i32) -> i32 {
+        create_product_internal(conn, product_input(sku, 40.0, stock, None)).unwrap().id
+    }
+
+    fn challan_input(customer_id: i32, items: &[(i32, i32)]) -> CreateChallanInput {
+        CreateChallanInput {
+            customer_id,
+            items: items
+                .iter()
+                .map(|&(product_id, quantity)| ChallanItemInput { product_id, quantity: quantity as f64, unit_price: None })
+                .collect(),
+            notes: Some("Site A".to_string()),
+            created_by: Some("ravi".to_string()),
+        }
+    }
+
+    fn stock(conn: &Connection, product_id: i32) -> (f64, Option<f64>) {
+        let product = get_product_internal(conn, product_id).unwrap();
+        (product.stock_quantity, product.available_quantity)
+    }
+
+    #[test]
+    fn test_reserved_challan_is_invoiced_once() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let customer_id = insert_customer(&conn, "Sharma Builders");
+        let pipe = product(&conn, "PIPE", 10);
+
+        let challan = create_challan_internal(&conn, &challan_input(customer_id, &[(pipe, 6)])).unwrap();
+        assert!(challan.challan_number.starts_with("DC-"));
+        assert_eq!((challan.status.as_str(), challan.stock_mode.as_str()), ("open", "reserve"));
+        assert_eq!(challan.total_value, 360.0);
+        assert_eq!(stock(&conn, pipe), (10.0, Some(4.0)));
+        assert!(create_challan_internal(&conn, &challan_input(customer_id, &[(pipe, 5)])).is_err());
+
+        let invoice = convert_challan_to_invoice_internal(&mut conn, &ConvertChallanInput { challan_id: challan.id, ..Default::default() })
+            .unwrap();
+        assert_eq!(invoice.total_amount, 360.0);
+        assert_eq!(stock(&conn, pipe), (4.0, Some(4.0)));
+        assert!(get_reservations_internal(&conn, Some(pipe)).unwrap().is_empty());
+
+        let converted = get_challan_internal(&conn, challan.id).unwrap();
+        assert_eq!((converted.status.as_str(), converted.invoice_number), ("invoiced", Some(invoice.invoice_number)));
+        assert!(convert_challan_to_invoice_internal(&mut conn, &ConvertChallanInput { challan_id: challan.id, ..Default::default() })
+            .is_err());
+        assert!(return_challan_internal(&conn, challan.id, None).is_err());
+
+        let open = get_challans_internal(&conn, Some("open"), None).unwrap();
+        assert!(open.is_empty());
+        assert_eq!(get_challans_internal(&conn, Some("invoiced"), Some(customer_id)).unwrap().len(), 1);
+        assert!(get_challans_internal(&conn, Some("lost"), None).is_err());
+    }
+
+    #[test]
+    fn test_deducted_challan_returns_and_converts_without_double_deduction() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('challan_stock_mode', 'deduct')", []).unwrap();
+        let customer_id = insert_customer(&conn, "Sharma Builders");
+        let pipe = product(&conn, "PIPE", 10);
+
+        // Returning puts the units back into batches
+        let returned = create_challan_internal(&conn, &challan_input(customer_id, &[(pipe, 4)])).unwrap();
+        assert_eq!(returned.stock_mode, "deduct");
+        assert_eq!(stock(&conn, pipe), (6.0, Some(6.0)));
+        assert_eq!(batch_quantity(&conn, pipe), 6.0);
+        let returned = return_challan_internal(&conn, returned.id, Some("ravi")).unwrap();
+        assert_eq!(returned.status, "returned");
+        assert_eq!(stock(&conn, pipe), (10.0, Some(10.0)));
+        assert_eq!(batch_quantity(&conn, pipe), 10.0);
+        assert!(inventory_service::validate_stock_consistency(&conn, pipe).unwrap());
+
+        // Converting takes the units out once
+        let challan = create_challan_internal(&conn, &challan_input(customer_id, &[(pipe, 3)])).unwrap();
+        assert_eq!(stock(&conn, pipe), (7.0, Some(7.0)));
+        convert_challan_to_invoice_internal(&mut conn, &ConvertChallanInput { challan_id: challan.id, ..Default::default() })
+            .unwrap();
+        assert_eq!(stock(&conn, pipe), (7.0, Some(7.0)));
+        assert_eq!(batch_quantity(&conn, pipe), 7.0);
+        assert!(get_reservations_internal(&conn, Some(pipe)).unwrap().is_empty());
+        assert_eq!(get_challan_internal(&conn, challan.id).unwrap().status, "invoiced");
+    }
+
+    #[test]
+    fn test_challan_is_returned_once() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let customer_id = insert_customer(&conn, "Sharma Builders");
+        let pipe = product(&conn, "PIPE", 10);
+
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('challan_stock_mode', 'deduct')", []).unwrap();
+        let deducted = create_challan_internal(&conn, &challan_input(customer_id, &[(pipe, 4)])).unwrap();
+        return_challan_internal(&conn, deducted.id, None).unwrap();
+        let err = return_challan_internal(&conn, deducted.id, None).unwrap_err();
+        assert!(err.contains("already returned"), "{}", err);
+        assert_eq!(stock(&conn, pipe), (10.0, Some(10.0)));
+        assert_eq!(batch_quantity(&conn, pipe), 10.0);
+
+        // A reserved challan's hold is released once; a later hold on the product stays
+        conn.execute("UPDATE app_settings SET value = 'reserve' WHERE key = 'challan_stock_mode'", []).unwrap();
+        let reserved = create_challan_internal(&conn, &challan_input(customer_id, &[(pipe, 4)])).unwrap();
+        return_challan_internal(&conn, reserved.id, None).unwrap();
+        let other = create_challan_internal(&conn, &challan_input(customer_id, &[(pipe, 3)])).unwrap();
+        assert!(return_challan_internal(&conn, reserved.id, None).is_err());
+        assert_eq!(stock(&conn, pipe), (10.0, Some(7.0)));
+        assert_eq!(get_challan_internal(&conn, other.id).unwrap().status, "open");
+    }
+}
@@ -0,0 +1,269 @@
+/// Costing Method
+/// Stock is costed FIFO by default; "weighted_average" costs sales at each product's running
+/// average_cost instead (see inventory_service). The method is kept in app_settings under
+/// costing_method but is only changed here: converting snapshots every product's quantity and
+/// value into costing_method_changes, re-seeds the running averages from the remaining batches
+/// when switching to weighted average, and logs the change in entity_modifications.
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::db::Database;
+use crate::services::events::{emit_bulk_data_changed, DataEntity, DataOperation};
+use crate::services::inventory_service::{costing_method, CostingMethod, COSTING_METHOD_KEY};
+use crate::services::{money, quantity};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CostingProductValue {
+    pub product_id: i32,
+    pub name: String,
+    /// Units left in batches
+    pub quantity: f64,
+    pub unit_cost_before: f64,
+    pub value_before: f64,
+    pub unit_cost_after: f64,
+    pub value_after: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CostingMethodChange {
+    pub id: i64,
+    pub from_method: String,
+    pub to_method: String,
+    /// Stock value under the old method
+    pub value_before: f64,
+    /// Stock value under the new method
+    pub value_after: f64,
+    pub products: Vec<CostingProductValue>,
+    pub changed_by: Option<String>,
+    pub changed_at: String,
+}
+
+/// Get the active costing method ("fifo" or "weighted_average")
+#[tauri::command]
+pub fn get_costing_method(db: State<Database>) -> Result<String, String> {
+    log::info!("get_costing_method called");
+
+    let conn = db.get_conn()?;
+    Ok(costing_method(&conn).as_str().to_string())
+}
+
+/// Switch the costing method, snapshotting the stock values it changes
+#[tauri::command]
+pub fn convert_costing_method(
+    method: String,
+    converted_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<CostingMethodChange, String> {
+    log::info!("convert_costing_method called: {}", method);
+
+    let conn = db.get_conn()?;
+    let change = convert_costing_method_internal(&conn, &method, converted_by.as_deref())?;
+
+    emit_bulk_data_changed(&app, DataEntity::Product, DataOperation::Updated, change.products.len());
+    Ok(change)
+}
+
+pub(crate) fn convert_costing_method_internal(
+    conn: &Connection,
+    method: &str,
+    converted_by: Option<&str>,
+) -> Result<CostingMethodChange, String> {
+    let to = CostingMethod::parse(method)
+        .ok_or_else(|| format!("Validation error: unknown costing method '{}'", method))?;
+    let from = costing_method(conn);
+    if from == to {
+        return Err(format!("Validation error: costing method is already {}", to.as_str()));
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // Every product with its remaining batch units, their FIFO value and the running average
+    let rows: Vec<(i32, String, f64, f64, Option<f64>, f64)> = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT p.id, p.name, COALESCE(SUM(ib.quantity_remaining), 0),
+                        COALESCE(SUM(ib.quantity_remaining * ib.unit_cost), 0.0), p.average_cost, COALESCE(p.price, 0.0)
+                 FROM products p
+                 LEFT JOIN inventory_batches ib ON ib.product_id = p.id AND ib.quantity_remaining > 0
+                 GROUP BY p.id
+                 ORDER BY p.id",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, quantity::round_quantity(row.get(2)?), row.get(3)?, row.get(4)?, row.get(5)?)))
+            .map_err(|e| format!("Failed to query stock values: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect stock values: {}", e))?
+    };
+
+    let mut products = Vec::new();
+    for (product_id, name, quantity, fifo_value, average_cost, price) in rows {
+        let fifo_unit_cost = if quantity > 0.0 { fifo_value / quantity } else { 0.0 };
+        let unit_cost_of = |method: CostingMethod| match method {
+            CostingMethod::Fifo => fifo_unit_cost,
+            CostingMethod::WeightedAverage => average_cost.unwrap_or(fifo_unit_cost),
+        };
+
+        if to == CostingMethod::WeightedAverage {
+            // The remaining batches' average carries today's FIFO value over unchanged; products
+            // without stock start from their cost price
+            let seeded = if quantity > 0.0 { fifo_unit_cost } else { price };
+            tx.execute(
+                "UPDATE products SET average_cost = ?1 WHERE id = ?2",
+                params![seeded, product_id],
+            )
+            .map_err(|e| format!("Failed to seed average cost: {}", e))?;
+        }
+
+        if quantity > 0.0 {
+            let (before, after) = (unit_cost_of(from), unit_cost_of(to));
+            products.push(CostingProductValue {
+                product_id,
+                name,
+                quantity,
+                unit_cost_before: before,
+                value_before: money::line_total(before, quantity),
+                unit_cost_after: after,
+                value_after: money::line_total(after, quantity),
+            });
+        }
+    }
+
+    let value_before = money::sum(products.iter().map(|p| p.value_before));
+    let value_after = money::sum(products.iter().map(|p| p.value_after));
+    let product_values = serde_json::to_string(&products).map_err(|e| format!("Failed to serialize stock values: {}", e))?;
+
+    tx.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))
+         ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = datetime('now')",
+        params![COSTING_METHOD_KEY, to.as_str()],
+    )
+    .map_err(|e| format!("Failed to save costing method: {}", e))?;
+
+    tx.execute(
+        "INSERT INTO costing_method_changes (from_method, to_method, value_before, value_after, product_values, changed_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![from.as_str(), to.as_str(), value_before, value_after, product_values, converted_by],
+    )
+    .map_err(|e| format!("Failed to record costing method change: {}", e))?;
+    let id = tx.last_insert_rowid();
+
+    let field_changes = serde_json::json!([
+        { "field": "costing_method", "old": from.as_str(), "new": to.as_str() },
+        { "field": "inventory_value", "old": value_before, "new": value_after },
+    ]);
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by)
+         VALUES ('costing_method', ?1, 'Costing method', 'converted', ?2, ?3)",
+        params![id, field_changes.to_string(), converted_by.unwrap_or("system")],
+    )
+    .map_err(|e| format!("Failed to log costing method change: {}", e))?;
+
+    let changed_at: String = tx
+        .query_row("SELECT changed_at FROM costing_method_changes WHERE id = ?1", [id], |row| row.get(0))
+        .map_err(|e| format!("Failed to read costing method change: {}", e))?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!(
+        "Costing method changed from {} to {}: stock value {:.2} -> {:.2}",
+        from.as_str(),
+        to.as_str(),
+        value_before,
+        value_after
+    );
+
+    Ok(CostingMethodChange {
+        id,
+        from_method: from.as_str().to_string(),
+        to_method: to.as_str().to_string(),
+        value_before,
+        value_after,
+        products,
+        changed_by: converted_by.map(str::to_string),
+        changed_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::inventory_service::{get_product_inventory_value, record_purchase, record_sale_fifo};
+    use crate::test_support::TestDb;
+
+    /// Buy 10 @ 10 and 10 @ 20, then sell 15; returns the product, the sale's COGS and the
+    /// value of the 5 units left
+    fn buy_twice_and_sell(conn: &Connection, sku: &str, invoice_id: i32) -> (i32, f64, f64) {
+        conn.execute(
+            "INSERT INTO products (name, sku, price, stock_quantity) VALUES (?1, ?1, 0, 20)",
+            [sku],
+        )
+        .unwrap();
+        let product_id = conn.last_insert_rowid() as i32;
+        record_purchase(conn, product_id, 10.0, 10.0, None, "2024-01-01").unwrap();
+        record_purchase(conn, product_id, 10.0, 20.0, None, "2024-02-01").unwrap();
+
+        conn.execute(
+            "INSERT INTO invoices (id, invoice_number, total_amount) VALUES (?1, ?2, 0)",
+            params![invoice_id, format!("INV-{}", invoice_id)],
+        )
+        .unwrap();
+        conn.execute("UPDATE products SET stock_quantity = 5 WHERE id = ?1", [product_id]).unwrap();
+        let cogs = record_sale_fifo(conn, product_id, 15.0, "2024-03-01", invoice_id, None).unwrap();
+
+        (product_id, cogs, get_product_inventory_value(conn, product_id).unwrap())
+    }
+
+    #[test]
+    fn test_fifo_and_weighted_average_cost_the_same_sales_differently() {
+        let db = TestDb::new();
+        let conn = db.conn();
+
+        // FIFO: the 10 @ 10 go first, then 5 of the 20s
+        let (fifo_product, cogs, value) = buy_twice_and_sell(&conn, "FIFO", 1);
+        assert_eq!(cogs, 200.0);
+        assert_eq!(value, 100.0);
+
+        assert!(convert_costing_method_internal(&conn, "lifo", None).unwrap_err().starts_with("Validation error"));
+        assert!(convert_costing_method_internal(&conn, "fifo", None).is_err());
+
+        // Converting keeps the FIFO product's value by seeding its average from the batch left
+        let change = convert_costing_method_internal(&conn, "weighted_average", Some("admin")).unwrap();
+        assert_eq!((change.from_method.as_str(), change.to_method.as_str()), ("fifo", "weighted_average"));
+        assert_eq!((change.value_before, change.value_after), (100.0, 100.0));
+        assert_eq!(get_product_inventory_value(&conn, fifo_product).unwrap(), 100.0);
+
+        // Weighted average: every unit sold costs the running average of 15
+        let (average_product, cogs, value) = buy_twice_and_sell(&conn, "AVG", 2);
+        assert_eq!(cogs, 225.0);
+        assert_eq!(value, 75.0);
+        let (sale_cost, consumed_cost): (f64, f64) = conn
+            .query_row(
+                "SELECT t.unit_cost, (SELECT SUM(quantity * unit_cost) FROM batch_consumptions WHERE invoice_id = 2)
+                 FROM inventory_transactions t WHERE t.reference_id = 2 AND t.transaction_type = 'sale'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((sale_cost, consumed_cost), (15.0, 225.0));
+
+        // Back to FIFO the batches price the stock again; the change and its snapshot are logged
+        let change = convert_costing_method_internal(&conn, "fifo", None).unwrap();
+        assert_eq!((change.value_before, change.value_after), (175.0, 200.0));
+        let snapshot = change.products.iter().find(|p| p.product_id == average_product).unwrap();
+        assert_eq!((snapshot.quantity, snapshot.unit_cost_before, snapshot.unit_cost_after), (5.0, 15.0, 20.0));
+        assert_eq!(get_product_inventory_value(&conn, average_product).unwrap(), 100.0);
+
+        let logged: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM entity_modifications WHERE entity_type = 'costing_method' AND action = 'converted'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(logged, 2);
+        assert_eq!(costing_method(&conn), CostingMethod::Fifo);
+    }
+}
@@ -0,0 +1,925 @@
+/// Guided CSV Import Commands
+/// Header detection, column mapping and upsert imports on top of the chunked import flow
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, State};
+
+use crate::commands::categories::resolve_category;
+use crate::commands::data_management::{DataOperationState, DataTransferSummary};
+use crate::commands::maintenance::{MaintenanceOperation, MaintenanceState};
+use crate::commands::products;
+use crate::commands::safety_snapshots::{take_safety_snapshot, LARGE_IMPORT_ROWS};
+use crate::db::Database;
+use crate::services::events::{emit_bulk_data_changed, DataEntity, DataOperation};
+use crate::services::{inventory_service, quantity};
+
+/// Rows returned by preview commands when no sample size is given
+const DEFAULT_PREVIEW_ROWS: usize = 10;
+/// Rows processed per import call when no chunk size is given
+const DEFAULT_IMPORT_CHUNK: usize = 500;
+
+/// Product fields that can be mapped to CSV columns, with header aliases used for suggestions.
+/// Aliases are compared after lowercasing and stripping everything but letters and digits.
+const PRODUCT_FIELDS: &[(&str, &[&str])] = &[
+    ("name", &["name", "product", "productname", "item", "itemname", "title"]),
+    ("sku", &["sku", "code", "productcode", "itemcode", "barcode"]),
+    ("price", &["price", "cost", "costprice", "purchaseprice", "buyprice", "unitcost"]),
+    ("selling_price", &["sellingprice", "saleprice", "sp", "mrp", "retailprice"]),
+    ("stock_quantity", &["stock", "stockquantity", "qty", "quantity", "onhand", "initialstock"]),
+    ("category", &["category", "group", "productcategory"]),
+    ("supplier_name", &["supplier", "suppliername", "vendor", "vendorname"]),
+];
+
+const CUSTOMER_FIELDS: &[(&str, &[&str])] = &[
+    ("name", &["name", "customer", "customername", "fullname"]),
+    ("phone", &["phone", "mobile", "phonenumber", "mobilenumber", "contact", "contactnumber"]),
+    ("email", &["email", "emailaddress", "mail"]),
+    ("address", &["address"]),
+    ("place", &["place", "city", "locality"]),
+    ("state", &["state"]),
+    ("district", &["district"]),
+    ("town", &["town", "village"]),
+];
+
+const SUPPLIER_FIELDS: &[(&str, &[&str])] = &[
+    ("name", &["name", "supplier", "suppliername", "vendor", "vendorname", "company"]),
+    ("contact_info", &["contactinfo", "phone", "mobile", "contact", "phonenumber", "contactnumber"]),
+    ("email", &["email", "emailaddress", "mail"]),
+    ("address", &["address"]),
+    ("comments", &["comments", "notes", "remarks"]),
+    ("place", &["place", "city", "locality"]),
+    ("state", &["state"]),
+    ("district", &["district"]),
+    ("town", &["town", "village"]),
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CsvPreview {
+    pub headers: Vec<String>,
+    pub sample_rows: Vec<Vec<String>>,
+    pub total_rows: usize,
+    /// Suggested mapping of field name -> CSV header
+    pub suggested_mapping: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductUpsertMode {
+    /// Insert new SKUs, skip rows whose SKU already exists
+    CreateOnly,
+    /// Update existing SKUs, skip rows whose SKU is unknown
+    UpdateBySku,
+    /// Insert new SKUs and update existing ones
+    Both,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductCsvImportInput {
+    pub csv_content: String,
+    /// Field name -> CSV header (see `preview_product_csv` for field names)
+    pub mapping: HashMap<String, String>,
+    pub mode: ProductUpsertMode,
+    /// Create suppliers referenced by name that don't exist yet; otherwise such rows fail
+    #[serde(default)]
+    pub create_missing_suppliers: bool,
+    /// Index of the first data row to process (0-based, header excluded)
+    #[serde(default)]
+    pub row_offset: usize,
+    pub chunk_size: Option<usize>,
+    pub imported_by: Option<String>,
+}
+
+/// What to do with a customer/supplier row that matches an existing record
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    Skip,
+    /// Overwrite the existing record's mapped, non-empty fields
+    Update,
+    /// Insert a second record; it will be reported by scan_duplicates
+    CreateAnyway,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContactCsvImportInput {
+    pub csv_content: String,
+    /// Field name -> CSV header (see the preview command for field names)
+    pub mapping: HashMap<String, String>,
+    pub duplicate_policy: DuplicatePolicy,
+    /// Index of the first data row to process (0-based, header excluded)
+    #[serde(default)]
+    pub row_offset: usize,
+    pub chunk_size: Option<usize>,
+    pub imported_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvRowError {
+    /// 1-based data row number (header excluded)
+    pub row: usize,
+    pub identifier: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CsvImportChunkResult {
+    pub processed: usize,
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub errors: Vec<CsvRowError>,
+    pub next_offset: usize,
+    pub total_rows: usize,
+    pub done: bool,
+}
+
+/// Parsed values of one product row; `None` means the column is unmapped or empty
+struct ProductCsvValues {
+    name: Option<String>,
+    price: Option<f64>,
+    selling_price: Option<f64>,
+    stock_quantity: Option<f64>,
+    category: Option<String>,
+    supplier_id: Option<i32>,
+}
+
+/// Table layout for customer/supplier imports
+struct ContactTable {
+    entity_type: &'static str,
+    table: &'static str,
+    fields: &'static [(&'static str, &'static [&'static str])],
+    /// Column holding the phone number used for duplicate detection
+    phone_field: &'static str,
+    /// Timestamp in the same format the create command writes
+    now: fn() -> String,
+}
+
+const CUSTOMER_TABLE: ContactTable = ContactTable {
+    entity_type: "customer",
+    table: "customers",
+    fields: CUSTOMER_FIELDS,
+    phone_field: "phone",
+    now: rfc3339_now,
+};
+
+const SUPPLIER_TABLE: ContactTable = ContactTable {
+    entity_type: "supplier",
+    table: "suppliers",
+    fields: SUPPLIER_FIELDS,
+    phone_field: "contact_info",
+    now: sqlite_now,
+};
+
+/// customers.created_at format (see create_customer)
+fn rfc3339_now() -> String {
+    Utc::now().to_rfc3339()
+}
+
+/// suppliers.created_at format, same as SQLite datetime('now') (see create_supplier)
+fn sqlite_now() -> String {
+    Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+enum RowOutcome {
+    Created,
+    Updated,
+    Skipped,
+}
+
+/// Normalize a header for alias matching ("Selling Price" -> "sellingprice")
+fn normalize_header(header: &str) -> String {
+    header
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Suggest a field -> header mapping using each field's aliases; each header is used at most once
+pub(crate) fn suggest_mapping(headers: &[String], fields: &[(&str, &[&str])]) -> HashMap<String, String> {
+    let mut mapping = HashMap::new();
+    let mut used = vec![false; headers.len()];
+
+    for (field, aliases) in fields {
+        let found = aliases.iter().find_map(|alias| {
+            headers
+                .iter()
+                .enumerate()
+                .find(|(i, h)| !used[*i] && normalize_header(h) == *alias)
+                .map(|(i, _)| i)
+        });
+        if let Some(i) = found {
+            used[i] = true;
+            mapping.insert(field.to_string(), headers[i].clone());
+        }
+    }
+
+    mapping
+}
+
+/// Parse CSV content into headers and rows
+pub(crate) fn parse_csv(content: &str) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.trim_start_matches('\u{feff}').as_bytes());
+
+    let headers = rdr
+        .headers()
+        .map_err(|e| format!("Failed to read CSV header: {}", e))?
+        .iter()
+        .map(|h| h.to_string())
+        .collect::<Vec<_>>();
+
+    if headers.iter().all(|h| h.is_empty()) {
+        return Err("CSV file has no header row".to_string());
+    }
+
+    let mut rows = Vec::new();
+    for (i, record) in rdr.records().enumerate() {
+        let record = record.map_err(|e| format!("Failed to parse CSV row {}: {}", i + 1, e))?;
+        rows.push(record.iter().map(|v| v.to_string()).collect());
+    }
+
+    Ok((headers, rows))
+}
+
+pub(crate) fn build_preview(
+    csv_content: &str,
+    sample_rows: Option<usize>,
+    fields: &[(&str, &[&str])],
+) -> Result<CsvPreview, String> {
+    let (headers, rows) = parse_csv(csv_content)?;
+    let suggested_mapping = suggest_mapping(&headers, fields);
+
+    Ok(CsvPreview {
+        total_rows: rows.len(),
+        sample_rows: rows
+            .into_iter()
+            .take(sample_rows.unwrap_or(DEFAULT_PREVIEW_ROWS))
+            .collect(),
+        headers,
+        suggested_mapping,
+    })
+}
+
+/// Resolve a field -> header mapping into field -> column index, rejecting unknown fields or headers
+pub(crate) fn resolve_mapping(
+    mapping: &HashMap<String, String>,
+    headers: &[String],
+    fields: &[(&str, &[&str])],
+) -> Result<HashMap<String, usize>, String> {
+    let mut columns = HashMap::new();
+    for (field, header) in mapping {
+        if header.is_empty() {
+            continue;
+        }
+        if !fields.iter().any(|(f, _)| f == field) {
+            return Err(format!("Unknown field '{}' in mapping", field));
+        }
+        let index = headers
+            .iter()
+            .position(|h| h == header)
+            .ok_or_else(|| format!("Column '{}' not found in CSV header", header))?;
+        columns.insert(field.clone(), index);
+    }
+    Ok(columns)
+}
+
+/// A single CSV row viewed through the column mapping
+pub(crate) struct MappedRow<'a> {
+    values: &'a [String],
+    columns: &'a HashMap<String, usize>,
+}
+
+impl<'a> MappedRow<'a> {
+    pub(crate) fn new(values: &'a [String], columns: &'a HashMap<String, usize>) -> Self {
+        Self { values, columns }
+    }
+
+    /// Trimmed, non-empty value of a mapped field
+    pub(crate) fn text(&self, field: &str) -> Option<String> {
+        self.columns
+            .get(field)
+            .and_then(|&i| self.values.get(i))
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    }
+
+    pub(crate) fn number(&self, field: &str) -> Result<Option<f64>, String> {
+        match self.text(field) {
+            None => Ok(None),
+            Some(v) => v
+                .replace([',', '₹'], "")
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite() && *n >= 0.0)
+                .map(Some)
+                .ok_or_else(|| format!("Invalid {} '{}': expected a non-negative number", field, v)),
+        }
+    }
+}
+
+/// Preview a product CSV: headers, the first rows and a suggested column mapping
+#[tauri::command]
+pub fn preview_product_csv(csv_content: String, sample_rows: Option<usize>) -> Result<CsvPreview, String> {
+    log::info!("preview_product_csv called");
+    build_preview(&csv_content, sample_rows, PRODUCT_FIELDS)
+}
+
+/// Describes one chunk of a mapped CSV import for `run_import_chunk`
+struct ImportChunk<'a> {
+    /// entity_type reported in data-transfer events ("inventory", "customer", "supplier")
+    entity_type: &'a str,
+    /// Entity announced in the data-changed event once the chunk is committed
+    data_entity: DataEntity,
+    skip_reason: &'a str,
+    /// Mapped field shown next to row errors (e.g. sku or phone)
+    identifier_field: &'a str,
+    rows: &'a [Vec<String>],
+    columns: &'a HashMap<String, usize>,
+    row_offset: usize,
+    chunk_size: Option<usize>,
+}
+
+/// Run `import_row` over one chunk of rows inside a transaction.
+/// Each row gets its own savepoint, so a failing row is rolled back and reported with its
+/// row number while the rest of the chunk is kept. Progress and the final summary are
+/// emitted through `DataOperationState` like `import_csv_chunk`, and the committed rows are
+/// announced with a single data-changed event per chunk. The import holds the maintenance lock
+/// from its first chunk until its last.
+fn run_import_chunk<F>(
+    chunk: ImportChunk,
+    app: &AppHandle,
+    ops: &DataOperationState,
+    db: &Database,
+    mut import_row: F,
+) -> Result<CsvImportChunkResult, String>
+where
+    F: FnMut(&Connection, &MappedRow) -> Result<RowOutcome, String>,
+{
+    let total_rows = chunk.rows.len();
+    let chunk_size = chunk.chunk_size.unwrap_or(DEFAULT_IMPORT_CHUNK).max(1);
+    let end = (chunk.row_offset + chunk_size).min(total_rows);
+
+    let maintenance = app.state::<MaintenanceState>();
+    let _lock = maintenance.acquire(MaintenanceOperation::CsvImport)?;
+
+    if chunk.row_offset == 0 {
+        if total_rows >= LARGE_IMPORT_ROWS {
+            let conn = db.get_conn()?;
+            take_safety_snapshot(&conn, &format!("import_csv_{}", chunk.entity_type))?;
+        }
+        ops.begin_import(chunk.entity_type, total_rows as i32, Some(chunk.skip_reason.to_string()))?;
+    }
+    if ops.is_cancelled() {
+        return Err("Import cancelled".to_string());
+    }
+
+    let mut result = CsvImportChunkResult {
+        total_rows,
+        next_offset: end,
+        done: end >= total_rows,
+        ..Default::default()
+    };
+
+    let mut conn = db.get_conn()?;
+    let mut tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    for (index, values) in chunk.rows.iter().enumerate().take(end).skip(chunk.row_offset) {
+        if ops.is_cancelled() {
+            log::info!("Import cancelled, rolled back chunk starting at row {}", chunk.row_offset + 1);
+            return Err("Import cancelled".to_string());
+        }
+
+        let row = MappedRow::new(values, chunk.columns);
+        result.processed += 1;
+
+        // Dropping the savepoint without committing rolls the row back
+        let outcome = {
+            let sp = tx
+                .savepoint()
+                .map_err(|e| format!("Failed to create savepoint: {}", e))?;
+            let outcome = import_row(&sp, &row);
+            if outcome.is_ok() {
+                sp.commit().map_err(|e| e.to_string())?;
+            }
+            outcome
+        };
+
+        match outcome {
+            Ok(RowOutcome::Created) => result.created += 1,
+            Ok(RowOutcome::Updated) => result.updated += 1,
+            Ok(RowOutcome::Skipped) => result.skipped += 1,
+            Err(message) => result.errors.push(CsvRowError {
+                row: index + 1,
+                identifier: row.text(chunk.identifier_field),
+                message,
+            }),
+        }
+    }
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit import: {}", e))?;
+    emit_bulk_data_changed(app, chunk.data_entity, DataOperation::Imported, result.created + result.updated);
+
+    ops.record_import_chunk(
+        app,
+        end as i32,
+        &DataTransferSummary {
+            inserted: result.created as i32,
+            updated: result.updated as i32,
+            skipped: result.skipped as i32,
+            failed: result.errors.len() as i32,
+            ..Default::default()
+        },
+    )?;
+
+    Ok(result)
+}
+
+/// Import one chunk of a product CSV using a column mapping.
+///
+/// Existing products are matched by SKU. Updates only touch mapped columns; new products
+/// get an initial FIFO batch for their starting stock, the same as `create_product`.
+/// Call again with `next_offset` until `done` is true.
+#[tauri::command]
+pub async fn import_products_csv(
+    input: ProductCsvImportInput,
+    app: AppHandle,
+    ops: State<'_, DataOperationState>,
+    db: State<'_, Database>,
+) -> Result<CsvImportChunkResult, String> {
+    log::info!(
+        "import_products_csv called with mode: {:?}, row_offset: {}",
+        input.mode,
+        input.row_offset
+    );
+
+    let (headers, rows) = parse_csv(&input.csv_content)?;
+    let columns = resolve_mapping(&input.mapping, &headers, PRODUCT_FIELDS)?;
+    if !columns.contains_key("sku") {
+        return Err("The sku column must be mapped".to_string());
+    }
+
+    let mut supplier_cache: HashMap<String, i32> = HashMap::new();
+
+    let chunk = ImportChunk {
+        entity_type: "inventory",
+        data_entity: DataEntity::Product,
+        skip_reason: "SKU excluded by upsert mode",
+        identifier_field: "sku",
+        rows: &rows,
+        columns: &columns,
+        row_offset: input.row_offset,
+        chunk_size: input.chunk_size,
+    };
+    run_import_chunk(chunk, &app, &ops, &db, |conn, row| {
+        let outcome = import_product_csv_row(conn, row, &input, &mut supplier_cache);
+        if outcome.is_err() {
+            // Suppliers created inside the rolled back savepoint no longer exist
+            supplier_cache.clear();
+        }
+        outcome
+    })
+}
+
+fn import_product_csv_row(
+    conn: &Connection,
+    row: &MappedRow,
+    input: &ProductCsvImportInput,
+    supplier_cache: &mut HashMap<String, i32>,
+) -> Result<RowOutcome, String> {
+    let sku = row.text("sku").ok_or("SKU is required")?;
+    let values = ProductCsvValues {
+        name: row.text("name"),
+        price: row.number("price")?,
+        selling_price: row.number("selling_price")?,
+        stock_quantity: row.number("stock_quantity")?,
+        category: resolve_category(conn, row.text("category").as_deref())?.1,
+        supplier_id: match row.text("supplier_name") {
+            Some(supplier_name) => Some(resolve_supplier(
+                conn,
+                &supplier_name,
+                input.create_missing_suppliers,
+                supplier_cache,
+            )?),
+            None => None,
+        },
+    };
+
+    let existing: Option<i32> = conn
+        .query_row("SELECT id FROM products WHERE sku = ?1", [&sku], |r| r.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    match existing {
+        Some(_) if input.mode == ProductUpsertMode::CreateOnly => Ok(RowOutcome::Skipped),
+        None if input.mode == ProductUpsertMode::UpdateBySku => Ok(RowOutcome::Skipped),
+        Some(id) => {
+            update_product_from_row(conn, id, &values, &input.imported_by)?;
+            Ok(RowOutcome::Updated)
+        }
+        None => {
+            let name = values.name.ok_or("Name is required for new products")?;
+            let price = values.price.ok_or("Price is required for new products")?;
+            // New products are counted in pieces
+            let initial_qty = values.stock_quantity.unwrap_or(0.0);
+            quantity::validate_quantity(initial_qty, quantity::DEFAULT_UNIT, false)?;
+
+            conn.execute(
+                "INSERT INTO products (name, sku, price, selling_price, initial_stock, stock_quantity, supplier_id, created_at, updated_at, category, category_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, datetime('now'), datetime('now'), ?7, (SELECT id FROM categories WHERE name = ?7))",
+                rusqlite::params![&name, &sku, price, values.selling_price, initial_qty, values.supplier_id, &values.category],
+            )
+            .map_err(|e| format!("Failed to create product: {}", e))?;
+            let id = conn.last_insert_rowid() as i32;
+
+            // Starting stock goes through a FIFO batch, same as create_product
+            if initial_qty > 0.0 {
+                let purchase_date = Utc::now().format("%Y-%m-%d").to_string();
+                inventory_service::record_purchase(conn, id, initial_qty, price, None, &purchase_date)?;
+                conn.execute(
+                    "UPDATE products SET stock_quantity = ?1, updated_at = datetime('now') WHERE id = ?2",
+                    (initial_qty, id),
+                )
+                .map_err(|e| format!("Failed to update product stock after batch creation: {}", e))?;
+            }
+
+            Ok(RowOutcome::Created)
+        }
+    }
+}
+
+/// Apply mapped, non-empty values to an existing product and log the changed fields
+fn update_product_from_row(
+    conn: &Connection,
+    id: i32,
+    values: &ProductCsvValues,
+    modified_by: &Option<String>,
+) -> Result<(), String> {
+    let old: (String, f64, Option<f64>, f64, Option<i32>, Option<String>) = conn
+        .query_row(
+            "SELECT name, price, selling_price, stock_quantity, supplier_id, category FROM products WHERE id = ?1",
+            [id],
+            |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?, r.get(3)?, r.get(4)?, r.get(5)?)),
+        )
+        .map_err(|e| format!("Product with id {} not found: {}", id, e))?;
+
+    let mut field_changes: Vec<serde_json::Value> = Vec::new();
+
+    if let Some(name) = values.name.as_ref().filter(|n| **n != old.0) {
+        field_changes.push(serde_json::json!({"field": "name", "old": old.0, "new": name}));
+    }
+    if let Some(price) = values.price.filter(|p| (p - old.1).abs() > 0.001) {
+        field_changes.push(serde_json::json!({"field": "price", "old": old.1, "new": price}));
+    }
+    if let Some(selling_price) = values.selling_price.filter(|p| Some(*p) != old.2) {
+        field_changes.push(serde_json::json!({"field": "selling_price", "old": old.2, "new": selling_price}));
+    }
+    if let Some(stock) = values.stock_quantity {
+        products::check_product_quantity(conn, id, stock)?;
+    }
+    if let Some(stock) = values.stock_quantity.filter(|s| quantity::to_milli(*s) != quantity::to_milli(old.3)) {
+        field_changes.push(serde_json::json!({"field": "stock_quantity", "old": old.3, "new": stock}));
+    }
+    if let Some(supplier_id) = values.supplier_id.filter(|s| Some(*s) != old.4) {
+        field_changes.push(serde_json::json!({"field": "supplier_id", "old": old.4, "new": supplier_id}));
+    }
+    if let Some(category) = values.category.as_ref().filter(|c| Some(*c) != old.5.as_ref()) {
+        field_changes.push(serde_json::json!({"field": "category", "old": old.5, "new": category}));
+    }
+
+    if field_changes.is_empty() {
+        return Ok(());
+    }
+
+    // Unmapped or empty columns are NULL here and keep their current value
+    conn.execute(
+        "UPDATE products SET
+            name = COALESCE(?1, name),
+            price = COALESCE(?2, price),
+            selling_price = COALESCE(?3, selling_price),
+            stock_quantity = COALESCE(?4, stock_quantity),
+            supplier_id = COALESCE(?5, supplier_id),
+            category = COALESCE(?6, category),
+            category_id = CASE WHEN ?6 IS NULL THEN category_id ELSE (SELECT id FROM categories WHERE name = ?6) END,
+            updated_at = datetime('now')
+         WHERE id = ?7",
+        rusqlite::params![
+            &values.name,
+            values.price,
+            values.selling_price,
+            values.stock_quantity,
+            values.supplier_id,
+            &values.category,
+            id
+        ],
+    )
+    .map_err(|e| format!("Failed to update product: {}", e))?;
+
+    let entity_name = values.name.clone().unwrap_or(old.0);
+    let changes_json = serde_json::to_string(&field_changes).unwrap_or_default();
+    conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        ("product", id, &entity_name, "updated", &changes_json, modified_by),
+    )
+    .map_err(|e| format!("Failed to log modification: {}", e))?;
+
+    Ok(())
+}
+
+/// Overwrite an existing product with a row's non-empty values (import_csv_chunk with
+/// update_existing); the legacy rows carry supplier_id rather than supplier_name
+pub(crate) fn update_product_from_mapped_row(conn: &Connection, id: i32, row: &MappedRow) -> Result<(), String> {
+    let values = ProductCsvValues {
+        name: row.text("name"),
+        price: row.number("price")?,
+        selling_price: row.number("selling_price")?,
+        stock_quantity: row.number("stock_quantity")?,
+        category: resolve_category(conn, row.text("category").as_deref())?.1,
+        supplier_id: row.text("supplier_id").and_then(|s| s.parse().ok()),
+    };
+    update_product_from_row(conn, id, &values, &None)
+}
+
+/// Overwrite an existing customer/supplier with a row's non-empty values (import_csv_chunk
+/// with update_existing)
+pub(crate) fn update_contact_from_mapped_row(
+    conn: &Connection,
+    entity_type: &str,
+    id: i32,
+    row: &MappedRow,
+) -> Result<(), String> {
+    let spec = if entity_type == "customer" { &CUSTOMER_TABLE } else { &SUPPLIER_TABLE };
+    let values: Vec<(&str, String)> = spec
+        .fields
+        .iter()
+        .filter_map(|(field, _)| Some((*field, row.text(field)?)))
+        .collect();
+    update_contact(conn, spec, id, &values, &(spec.now)(), &None)
+}
+
+/// Find a supplier by name (case-insensitive), optionally creating it
+fn resolve_supplier(
+    conn: &Connection,
+    name: &str,
+    create_missing: bool,
+    cache: &mut HashMap<String, i32>,
+) -> Result<i32, String> {
+    let cache_key = name.to_lowercase();
+    if let Some(id) = cache.get(&cache_key) {
+        return Ok(*id);
+    }
+
+    let existing: Option<i32> = conn
+        .query_row(
+            "SELECT id FROM suppliers WHERE name = ?1 COLLATE NOCASE ORDER BY id LIMIT 1",
+            [name],
+            |r| r.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let id = match existing {
+        Some(id) => id,
+        None if create_missing => {
+            conn.execute(
+                "INSERT INTO suppliers (name, contact_info, created_at, updated_at) VALUES (?1, '', datetime('now'), datetime('now'))",
+                [name],
+            )
+            .map_err(|e| format!("Failed to create supplier '{}': {}", name, e))?;
+            log::info!("Created supplier '{}' during product import", name);
+            conn.last_insert_rowid() as i32
+        }
+        None => return Err(format!("Supplier '{}' not found", name)),
+    };
+
+    cache.insert(cache_key, id);
+    Ok(id)
+}
+
+/// Preview a customer CSV: headers, the first rows and a suggested column mapping
+#[tauri::command]
+pub fn preview_customer_csv(csv_content: String, sample_rows: Option<usize>) -> Result<CsvPreview, String> {
+    log::info!("preview_customer_csv called");
+    build_preview(&csv_content, sample_rows, CUSTOMER_FIELDS)
+}
+
+/// Preview a supplier CSV: headers, the first rows and a suggested column mapping
+#[tauri::command]
+pub fn preview_supplier_csv(csv_content: String, sample_rows: Option<usize>) -> Result<CsvPreview, String> {
+    log::info!("preview_supplier_csv called");
+    build_preview(&csv_content, sample_rows, SUPPLIER_FIELDS)
+}
+
+/// Import one chunk of a customer CSV using a column mapping.
+/// Duplicates are detected by phone number and handled per `duplicate_policy`.
+#[tauri::command]
+pub async fn import_customers_csv(
+    input: ContactCsvImportInput,
+    app: AppHandle,
+    ops: State<'_, DataOperationState>,
+    db: State<'_, Database>,
+) -> Result<CsvImportChunkResult, String> {
+    log::info!(
+        "import_customers_csv called with policy: {:?}, row_offset: {}",
+        input.duplicate_policy,
+        input.row_offset
+    );
+    import_contacts_csv(&CUSTOMER_TABLE, input, &app, &ops, &db)
+}
+
+/// Import one chunk of a supplier CSV using a column mapping.
+/// Duplicates are detected by name (case-insensitive) plus contact number and handled per
+/// `duplicate_policy`.
+#[tauri::command]
+pub async fn import_suppliers_csv(
+    input: ContactCsvImportInput,
+    app: AppHandle,
+    ops: State<'_, DataOperationState>,
+    db: State<'_, Database>,
+) -> Result<CsvImportChunkResult, String> {
+    log::info!(
+        "import_suppliers_csv called with policy: {:?}, row_offset: {}",
+        input.duplicate_policy,
+        input.row_offset
+    );
+    import_contacts_csv(&SUPPLIER_TABLE, input, &app, &ops, &db)
+}
+
+fn import_contacts_csv(
+    spec: &ContactTable,
+    input: ContactCsvImportInput,
+    app: &AppHandle,
+    ops: &DataOperationState,
+    db: &Database,
+) -> Result<CsvImportChunkResult, String> {
+    let (headers, rows) = parse_csv(&input.csv_content)?;
+    let columns = resolve_mapping(&input.mapping, &headers, spec.fields)?;
+    if !columns.contains_key("name") {
+        return Err("The name column must be mapped".to_string());
+    }
+
+    let chunk = ImportChunk {
+        entity_type: spec.entity_type,
+        data_entity: match spec.entity_type {
+            "customer" => DataEntity::Customer,
+            _ => DataEntity::Supplier,
+        },
+        skip_reason: match spec.entity_type {
+            "customer" => "duplicate phone",
+            _ => "duplicate name and contact",
+        },
+        identifier_field: spec.phone_field,
+        rows: &rows,
+        columns: &columns,
+        row_offset: input.row_offset,
+        chunk_size: input.chunk_size,
+    };
+    run_import_chunk(chunk, app, ops, db, |conn, row| {
+        import_contact_row(conn, row, spec, input.duplicate_policy, &input.imported_by)
+    })
+}
+
+/// Customer phones follow the same 10-digit rule as create_customer; a leading 91 country
+/// code and separators are stripped
+fn normalize_customer_phone(phone: &str) -> Result<String, String> {
+    let digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+    let digits = match digits.strip_prefix("91") {
+        Some(local) if digits.len() == 12 => local.to_string(),
+        _ => digits,
+    };
+    if digits.len() != 10 {
+        return Err(format!("Invalid phone '{}': must be exactly 10 digits", phone));
+    }
+    Ok(digits)
+}
+
+fn import_contact_row(
+    conn: &Connection,
+    row: &MappedRow,
+    spec: &ContactTable,
+    policy: DuplicatePolicy,
+    modified_by: &Option<String>,
+) -> Result<RowOutcome, String> {
+    let name = row.text("name").ok_or("Name is required")?;
+
+    let mut values: Vec<(&str, String)> = Vec::new();
+    for (field, _) in spec.fields {
+        let value = match row.text(field) {
+            Some(v) if *field == "phone" => normalize_customer_phone(&v)?,
+            Some(v) => v,
+            None => continue,
+        };
+        values.push((*field, value));
+    }
+    let phone = values.iter().find(|(f, _)| *f == spec.phone_field).map(|(_, v)| v.clone());
+
+    let existing: Option<i32> = match spec.entity_type {
+        "customer" => match &phone {
+            Some(phone) => conn
+                .query_row(
+                    "SELECT id FROM customers WHERE phone = ?1 ORDER BY id LIMIT 1",
+                    [phone],
+                    |r| r.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?,
+            None => None,
+        },
+        _ => conn
+            .query_row(
+                "SELECT id FROM suppliers WHERE name = ?1 COLLATE NOCASE AND COALESCE(contact_info, '') = ?2 ORDER BY id LIMIT 1",
+                (&name, phone.as_deref().unwrap_or("")),
+                |r| r.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?,
+    };
+
+    let now = (spec.now)();
+
+    match (existing, policy) {
+        (Some(_), DuplicatePolicy::Skip) => Ok(RowOutcome::Skipped),
+        (Some(id), DuplicatePolicy::Update) => {
+            update_contact(conn, spec, id, &values, &now, modified_by)?;
+            Ok(RowOutcome::Updated)
+        }
+        _ => {
+            // Column names come from the static field list, never from the CSV
+            let column_list = values.iter().map(|(f, _)| *f).collect::<Vec<_>>().join(", ");
+            let placeholders = (1..=values.len() + 2)
+                .map(|i| format!("?{}", i))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let mut params: Vec<&dyn rusqlite::ToSql> = values.iter().map(|(_, v)| v as &dyn rusqlite::ToSql).collect();
+            params.push(&now);
+            params.push(&now);
+
+            conn.execute(
+                &format!(
+                    "INSERT INTO {} ({}, created_at, updated_at) VALUES ({})",
+                    spec.table, column_list, placeholders
+                ),
+                params.as_slice(),
+            )
+            .map_err(|e| format!("Failed to insert {}: {}", spec.entity_type, e))?;
+
+            Ok(RowOutcome::Created)
+        }
+    }
+}
+
+/// Overwrite the mapped, non-empty fields of an existing customer/supplier and log the changes
+fn update_contact(
+    conn: &Connection,
+    spec: &ContactTable,
+    id: i32,
+    values: &[(&str, String)],
+    now: &str,
+    modified_by: &Option<String>,
+) -> Result<(), String> {
+    let mut field_changes: Vec<serde_json::Value> = Vec::new();
+    let mut entity_name = String::new();
+
+    for (field, new_value) in values {
+        let old_value: Option<String> = conn
+            .query_row(
+                &format!("SELECT {} FROM {} WHERE id = ?1", field, spec.table),
+                [id],
+                |r| r.get(0),
+            )
+            .map_err(|e| format!("Failed to read {} {}: {}", spec.entity_type, id, e))?;
+
+        if *field == "name" {
+            entity_name = new_value.clone();
+        }
+        if old_value.as_deref() == Some(new_value.as_str()) {
+            continue;
+        }
+
+        conn.execute(
+            &format!("UPDATE {} SET {} = ?1, updated_at = ?2 WHERE id = ?3", spec.table, field),
+            (new_value, now, id),
+        )
+        .map_err(|e| format!("Failed to update {}: {}", spec.entity_type, e))?;
+        field_changes.push(serde_json::json!({"field": field, "old": old_value, "new": new_value}));
+    }
+
+    if !field_changes.is_empty() {
+        let changes_json = serde_json::to_string(&field_changes).unwrap_or_default();
+        conn.execute(
+            "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (spec.entity_type, id, &entity_name, "updated", &changes_json, modified_by),
+        )
+        .map_err(|e| format!("Failed to log modification: {}", e))?;
+    }
+
+    Ok(())
+}
@@ -1,8 +1,15 @@
-use crate::db::models::{CustomerCreditSummary, CustomerInvoiceCreditSummary, CustomerPayment};
+use crate::commands::analytics::{business_offset_minutes, business_today, parse_report_date, ReportRange};
+use crate::commands::invoices::{INITIAL_PAYMENT_NOTE, INVOICE_PAID_SQL};
+use crate::commands::webhooks;
+use crate::commands::{validate_pagination, PaginatedResult};
+use crate::db::models::{CreditInvoice, CustomerCreditSummary, CustomerInvoiceCreditSummary, CustomerPayment, OverdueInvoice};
 use crate::db::Database;
+use rusqlite::{params, Connection};
+use crate::services::money;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use tauri::{AppHandle, State};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateCustomerPaymentInput {
@@ -18,6 +25,7 @@ pub struct CreateCustomerPaymentInput {
 #[tauri::command]
 pub fn create_customer_payment(
     input: CreateCustomerPaymentInput,
+    app: AppHandle,
     db: State<Database>,
 ) -> Result<CustomerPayment, String> {
     log::info!(
@@ -27,12 +35,22 @@ pub fn create_customer_payment(
         input.amount
     );
 
+    let conn = db.get_conn()?;
+    let payment = create_customer_payment_internal(&conn, input)?;
+    webhooks::queue_webhook_event(&app, &conn, webhooks::PAYMENT_CREATED, Some(payment.id), || {
+        serde_json::to_value(&payment).map_err(|e| e.to_string())
+    });
+    Ok(payment)
+}
+
+pub(crate) fn create_customer_payment_internal(
+    conn: &Connection,
+    input: CreateCustomerPaymentInput,
+) -> Result<CustomerPayment, String> {
     if input.amount <= 0.0 {
         return Err("Amount must be greater than zero".into());
     }
 
-    let conn = db.get_conn()?;
-
     // Verify the invoice exists and belongs to this customer
     let invoice_check: Result<(i32, Option<i32>), _> = conn.query_row(
         "SELECT id, customer_id FROM invoices WHERE id = ?1",
@@ -95,48 +113,119 @@ pub fn create_customer_payment(
     Ok(payment)
 }
 
-/// Get all payments for a customer
+/// Map a `cp.id, cp.customer_id, cp.invoice_id, i.invoice_number, cp.amount, cp.payment_method,
+/// cp.note, cp.paid_at, cp.created_at` row
+fn row_to_payment(row: &rusqlite::Row) -> rusqlite::Result<CustomerPayment> {
+    Ok(CustomerPayment {
+        id: row.get(0)?,
+        customer_id: row.get(1)?,
+        invoice_id: row.get(2)?,
+        invoice_number: row.get(3)?,
+        amount: row.get(4)?,
+        payment_method: row.get(5)?,
+        note: row.get(6)?,
+        paid_at: row.get(7)?,
+        created_at: row.get(8)?,
+    })
+}
+
+/// UTC bounds `(from, until)` for an optional business-local date range, for
+/// `datetime(col) >= from AND datetime(col) < until`
+fn local_day_bounds(
+    conn: &Connection,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Result<(Option<String>, Option<String>), String> {
+    let offset_minutes = business_offset_minutes(conn);
+    let from = start_date
+        .map(|start| parse_report_date(start).map(|day| ReportRange::new(day, day, offset_minutes).start_utc))
+        .transpose()?;
+    let until = end_date
+        .map(|end| parse_report_date(end).map(|day| ReportRange::new(day, day, offset_minutes).end_utc))
+        .transpose()?;
+    Ok((from, until))
+}
+
+/// Customer ?1's payments, optionally from ?2 / until ?3 (UTC) and by method ?4
+const CUSTOMER_PAYMENTS_FILTER: &str = "WHERE cp.customer_id = ?1
+               AND (?2 IS NULL OR datetime(cp.paid_at) >= ?2)
+               AND (?3 IS NULL OR datetime(cp.paid_at) < ?3)
+               AND (?4 IS NULL OR cp.payment_method = ?4 COLLATE NOCASE)";
+
+/// Get a page of a customer's payments, newest first, optionally limited to a date range
+/// (inclusive business days) and payment method
 #[tauri::command]
 pub fn get_customer_payments(
     customer_id: i32,
+    page: i32,
+    page_size: i32,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    payment_method: Option<String>,
     db: State<Database>,
-) -> Result<Vec<CustomerPayment>, String> {
-    log::info!("get_customer_payments called for customer_id: {}", customer_id);
+) -> Result<PaginatedResult<CustomerPayment>, String> {
+    log::info!(
+        "get_customer_payments called for customer_id: {} - page: {}, size: {}, {:?} to {:?}, method: {:?}",
+        customer_id,
+        page,
+        page_size,
+        start_date,
+        end_date,
+        payment_method
+    );
 
     let conn = db.get_conn()?;
+    get_customer_payments_internal(
+        &conn,
+        customer_id,
+        page,
+        page_size,
+        start_date.as_deref(),
+        end_date.as_deref(),
+        payment_method.as_deref(),
+    )
+}
+
+pub(crate) fn get_customer_payments_internal(
+    conn: &Connection,
+    customer_id: i32,
+    page: i32,
+    page_size: i32,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+    payment_method: Option<&str>,
+) -> Result<PaginatedResult<CustomerPayment>, String> {
+    let (limit, offset) = validate_pagination(page, page_size)?;
+    let (from, until) = local_day_bounds(conn, start_date, end_date)?;
+    let method = payment_method.filter(|m| !m.is_empty());
+
+    let total_count: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM customer_payments cp {}", CUSTOMER_PAYMENTS_FILTER),
+            params![customer_id, from, until, method],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
 
     let mut stmt = conn
-        .prepare(
+        .prepare(&format!(
             "SELECT cp.id, cp.customer_id, cp.invoice_id, i.invoice_number, cp.amount, cp.payment_method, cp.note, cp.paid_at, cp.created_at
              FROM customer_payments cp
              JOIN invoices i ON cp.invoice_id = i.id
-             WHERE cp.customer_id = ?1
-             ORDER BY cp.paid_at DESC, cp.id DESC",
-        )
+             {}
+             ORDER BY cp.paid_at DESC, cp.id DESC
+             LIMIT ?5 OFFSET ?6",
+            CUSTOMER_PAYMENTS_FILTER
+        ))
         .map_err(|e| e.to_string())?;
 
-    let payment_iter = stmt
-        .query_map([customer_id], |row| {
-            Ok(CustomerPayment {
-                id: row.get(0)?,
-                customer_id: row.get(1)?,
-                invoice_id: row.get(2)?,
-                invoice_number: row.get(3)?,
-                amount: row.get(4)?,
-                payment_method: row.get(5)?,
-                note: row.get(6)?,
-                paid_at: row.get(7)?,
-                created_at: row.get(8)?,
-            })
-        })
+    let items = stmt
+        .query_map(params![customer_id, from, until, method, limit, offset], row_to_payment)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    let mut payments = Vec::new();
-    for payment in payment_iter {
-        payments.push(payment.map_err(|e| e.to_string())?);
-    }
-
-    Ok(payments)
+    Ok(PaginatedResult { items, total_count })
 }
 
 /// Get payments for a specific invoice
@@ -148,7 +237,14 @@ pub fn get_invoice_payments(
     log::info!("get_invoice_payments called for invoice_id: {}", invoice_id);
 
     let conn = db.get_conn()?;
+    get_invoice_payments_internal(&conn, invoice_id)
+}
 
+/// Payments recorded against one invoice, newest first
+pub(crate) fn get_invoice_payments_internal(
+    conn: &Connection,
+    invoice_id: i32,
+) -> Result<Vec<CustomerPayment>, String> {
     let mut stmt = conn
         .prepare(
             "SELECT cp.id, cp.customer_id, cp.invoice_id, i.invoice_number, cp.amount, cp.payment_method, cp.note, cp.paid_at, cp.created_at
@@ -183,23 +279,15 @@ pub fn get_invoice_payments(
     Ok(payments)
 }
 
-/// Get credit history for a customer (all invoices with credit/partial payments)
-#[tauri::command]
-pub fn get_customer_credit_history(
-    customer_id: i32,
-    db: State<Database>,
-) -> Result<Vec<CustomerInvoiceCreditSummary>, String> {
-    log::info!(
-        "get_customer_credit_history called for customer_id: {}",
-        customer_id
-    );
-
-    let conn = db.get_conn()?;
+/// Customer ?1's credit invoices, optionally created from ?2 / until ?3 (UTC)
+const CUSTOMER_CREDIT_INVOICES_FILTER: &str = "WHERE i.customer_id = ?1
+               AND (i.credit_amount > 0 OR i.payment_method = 'Credit')
+               AND (?2 IS NULL OR datetime(i.created_at) >= ?2)
+               AND (?3 IS NULL OR datetime(i.created_at) < ?3)";
 
-    // Get all credit invoices (where credit_amount > 0 or payment_method = 'Credit')
-    let mut stmt = conn
-        .prepare(
-            "SELECT
+/// A page (?4 rows from ?5) of customer ?1's credit invoices with payments so far, newest first,
+/// filtered like CUSTOMER_CREDIT_INVOICES_FILTER; served by idx_invoices_customer_created
+pub(crate) const CUSTOMER_CREDIT_INVOICES_SQL: &str = "SELECT
                 i.id,
                 i.invoice_number,
                 i.created_at,
@@ -210,55 +298,83 @@ pub fn get_customer_credit_history(
              FROM invoices i
              WHERE i.customer_id = ?1
                AND (i.credit_amount > 0 OR i.payment_method = 'Credit')
-             ORDER BY i.created_at DESC",
+               AND (?2 IS NULL OR datetime(i.created_at) >= ?2)
+               AND (?3 IS NULL OR datetime(i.created_at) < ?3)
+             ORDER BY i.created_at DESC
+             LIMIT ?4 OFFSET ?5";
+
+/// Get a page of a customer's credit history (invoices with credit/partial payments), newest
+/// first, optionally limited to invoices from a date range (inclusive business days)
+#[tauri::command]
+pub fn get_customer_credit_history(
+    customer_id: i32,
+    page: i32,
+    page_size: i32,
+    start_date: Option<String>,
+    end_date: Option<String>,
+    db: State<Database>,
+) -> Result<PaginatedResult<CustomerInvoiceCreditSummary>, String> {
+    log::info!(
+        "get_customer_credit_history called for customer_id: {} - page: {}, size: {}, {:?} to {:?}",
+        customer_id,
+        page,
+        page_size,
+        start_date,
+        end_date
+    );
+
+    let conn = db.get_conn()?;
+    get_customer_credit_history_internal(&conn, customer_id, page, page_size, start_date.as_deref(), end_date.as_deref())
+}
+
+pub(crate) fn get_customer_credit_history_internal(
+    conn: &Connection,
+    customer_id: i32,
+    page: i32,
+    page_size: i32,
+    start_date: Option<&str>,
+    end_date: Option<&str>,
+) -> Result<PaginatedResult<CustomerInvoiceCreditSummary>, String> {
+    let (limit, offset) = validate_pagination(page, page_size)?;
+    let (from, until) = local_day_bounds(conn, start_date, end_date)?;
+
+    let total_count: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM invoices i {}", CUSTOMER_CREDIT_INVOICES_FILTER),
+            params![customer_id, from, until],
+            |row| row.get(0),
         )
         .map_err(|e| e.to_string())?;
 
-    let history_iter = stmt
-        .query_map([customer_id], |row| {
-            let invoice_id: i32 = row.get(0)?;
-            let invoice_number: String = row.get(1)?;
-            let invoice_date: String = row.get(2)?;
+    let mut stmt = conn.prepare(CUSTOMER_CREDIT_INVOICES_SQL).map_err(|e| e.to_string())?;
+
+    let items = stmt
+        .query_map(params![customer_id, from, until, limit, offset], |row| {
             let bill_amount: f64 = row.get(3)?;
-            let initial_paid: f64 = row.get(4)?;
-            let credit_amount: f64 = row.get(5)?;
             let payments_sum: f64 = row.get(6)?;
 
-            // Total paid is simply the sum of all payments (since initial_paid is inserted into customer_payments)
-            let total_paid = payments_sum;
-
-            // Balance remaining = Bill Amount - Total Paid
-            // Or alternatively: credit_amount - (payments_sum - initial_paid)
-            // Using bill_amount - payments_sum is cleaner assuming payments_sum includes everything
-            let balance_remaining = (bill_amount - payments_sum).max(0.0);
-
-            // Status
-            let status = if balance_remaining <= 0.0 {
-                "Clear".to_string()
-            } else {
-                "Pending".to_string()
-            };
+            // Payments include the amount paid at billing (create_invoice records it), so the
+            // balance is simply what the bill still lacks
+            let balance_remaining = money::sub(bill_amount, payments_sum).max(0.0);
+            let status = if balance_remaining <= 0.0 { "Clear" } else { "Pending" };
 
             Ok(CustomerInvoiceCreditSummary {
-                invoice_id,
-                invoice_number,
-                invoice_date,
+                invoice_id: row.get(0)?,
+                invoice_number: row.get(1)?,
+                invoice_date: row.get(2)?,
                 bill_amount,
-                initial_paid,
-                credit_amount,
-                total_paid,
+                initial_paid: row.get(4)?,
+                credit_amount: row.get(5)?,
+                total_paid: payments_sum,
                 balance_remaining,
-                status,
+                status: status.to_string(),
             })
         })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    let mut history = Vec::new();
-    for entry in history_iter {
-        history.push(entry.map_err(|e| e.to_string())?);
-    }
-
-    Ok(history)
+    Ok(PaginatedResult { items, total_count })
 }
 
 /// Get overall credit summary for a customer
@@ -273,64 +389,273 @@ pub fn get_customer_credit_summary(
     );
 
     let conn = db.get_conn()?;
+    get_customer_credit_summary_internal(&conn, customer_id)
+}
 
-    // Total credit amount (sum of all credit_amount from credit invoices)
-    let total_credit_amount: f64 = conn
-        .query_row(
-            "SELECT COALESCE(SUM(credit_amount), 0)
-             FROM invoices
-             WHERE customer_id = ?1 AND (credit_amount > 0 OR payment_method = 'Credit')",
-            [customer_id],
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+/// One aggregate over the customer's credit invoices, independent of any history page.
+/// Payments include the amount paid at billing (create_invoice records it).
+pub(crate) fn get_customer_credit_summary_internal(
+    conn: &Connection,
+    customer_id: i32,
+) -> Result<CustomerCreditSummary, String> {
+    let today = business_today(conn).format("%Y-%m-%d").to_string();
 
-    // Total initial paid (sum of initial_paid from credit invoices)
-    let total_initial_paid: f64 = conn
+    let (total_credit_amount, total_initial_paid, total_payments, overdue_balance): (f64, f64, f64, f64) = conn
         .query_row(
-            "SELECT COALESCE(SUM(initial_paid), 0)
-             FROM invoices
-             WHERE customer_id = ?1 AND (credit_amount > 0 OR payment_method = 'Credit')",
-            [customer_id],
-            |row| row.get(0),
+            "SELECT
+                COALESCE(SUM(credit_amount), 0),
+                COALESCE(SUM(initial_paid), 0),
+                COALESCE(SUM(paid), 0),
+                COALESCE(SUM(CASE WHEN due_date IS NOT NULL AND due_date < ?2
+                                  THEN MAX(ROUND(total_amount - paid, 2), 0) ELSE 0 END), 0)
+             FROM (
+                SELECT i.credit_amount, i.initial_paid, i.total_amount, i.due_date,
+                       COALESCE((SELECT SUM(cp.amount) FROM customer_payments cp WHERE cp.invoice_id = i.id), 0) as paid
+                FROM invoices i
+                WHERE i.customer_id = ?1 AND (i.credit_amount > 0 OR i.payment_method = 'Credit')
+             )",
+            params![customer_id, today],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
         )
-        .unwrap_or(0.0);
+        .map_err(|e| format!("Failed to load credit summary: {}", e))?;
 
-    // Total payments made (from customer_payments table)
-    // This INCLUDES the initial_paid records because create_invoice inserts them
-    let total_payments: f64 = conn
-        .query_row(
-            "SELECT COALESCE(SUM(cp.amount), 0)
-             FROM customer_payments cp
-             JOIN invoices i ON cp.invoice_id = i.id
-             WHERE cp.customer_id = ?1 AND (i.credit_amount > 0 OR i.payment_method = 'Credit')",
-            [customer_id],
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
-
-    // Total paid is just total_payments (since it includes initial)
-    let total_paid = total_payments;
-    
-    // Pending amount calculation:
-    // We want: Sum(Remaining Debt)
-    // Remaining Debt per invoice = Credit Amount - (Payments - Initial)
-    // Sum(Remaining) = Sum(Credit) - (Sum(Payments) - Sum(Initial))
-    //                = Sum(Credit) - Sum(Payments) + Sum(Initial)
-    let pending_amount = (total_credit_amount - (total_payments - total_initial_paid)).max(0.0);
+    // Remaining debt per invoice = credit - (payments - initial paid), so across invoices
+    // pending = sum(credit) - (sum(payments) - sum(initial paid))
+    let pending_amount = money::sub(total_credit_amount, money::sub(total_payments, total_initial_paid)).max(0.0);
+
+    // Split what is pending into overdue and not yet due
+    let overdue_amount = money::round_money(overdue_balance).min(pending_amount);
+    let current_amount = money::sub(pending_amount, overdue_amount);
 
     Ok(CustomerCreditSummary {
         total_credit_amount,
-        total_paid,
+        total_paid: total_payments,
         pending_amount,
+        current_amount,
+        overdue_amount,
+    })
+}
+
+/// Get credit invoices with an unpaid balance past their due date, most overdue first
+#[tauri::command]
+pub fn get_overdue_invoices(
+    customer_id: Option<i32>,
+    db: State<Database>,
+) -> Result<Vec<OverdueInvoice>, String> {
+    log::info!("get_overdue_invoices called for customer_id: {:?}", customer_id);
+
+    let conn = db.get_conn()?;
+    get_overdue_invoices_internal(&conn, customer_id)
+}
+
+pub(crate) fn get_overdue_invoices_internal(
+    conn: &Connection,
+    customer_id: Option<i32>,
+) -> Result<Vec<OverdueInvoice>, String> {
+    let today = business_today(conn).format("%Y-%m-%d").to_string();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT
+                i.id,
+                i.invoice_number,
+                i.created_at,
+                i.due_date,
+                CAST(julianday(?1) - julianday(i.due_date) AS INTEGER) as days_overdue,
+                i.total_amount,
+                COALESCE((SELECT SUM(cp.amount) FROM customer_payments cp WHERE cp.invoice_id = i.id), 0) as payments_sum,
+                c.id, c.name, c.phone, c.email, c.address
+             FROM invoices i
+             JOIN customers c ON i.customer_id = c.id
+             WHERE i.due_date IS NOT NULL
+               AND i.due_date < ?1
+               AND (i.credit_amount > 0 OR i.payment_method = 'Credit')
+               AND (?2 IS NULL OR i.customer_id = ?2)
+             ORDER BY i.due_date ASC, i.id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(rusqlite::params![today, customer_id], |row| {
+            let bill_amount: f64 = row.get(5)?;
+            let total_paid: f64 = row.get(6)?;
+            Ok(OverdueInvoice {
+                invoice_id: row.get(0)?,
+                invoice_number: row.get(1)?,
+                invoice_date: row.get(2)?,
+                due_date: row.get(3)?,
+                days_overdue: row.get(4)?,
+                bill_amount,
+                total_paid,
+                balance_remaining: money::sub(bill_amount, total_paid).max(0.0),
+                customer_id: row.get(7)?,
+                customer_name: row.get(8)?,
+                customer_phone: row.get(9)?,
+                customer_email: row.get(10)?,
+                customer_address: row.get(11)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut overdue = Vec::new();
+    for row in rows {
+        let invoice = row.map_err(|e| e.to_string())?;
+        if invoice.balance_remaining > 0.0 {
+            overdue.push(invoice);
+        }
+    }
+
+    Ok(overdue)
+}
+
+/// Credit invoice list filters; every field is optional
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CreditInvoiceFilters {
+    pub customer_id: Option<i32>,
+    /// "paid", "partial" or "unpaid"
+    pub status: Option<String>,
+    /// "age" (default) or "balance"
+    pub sort: Option<String>,
+    /// Defaults to oldest/largest first
+    pub descending: Option<bool>,
+}
+
+/// A page of credit invoices with the balance outstanding across every page
+#[derive(Debug, Serialize)]
+pub struct CreditInvoicePage {
+    pub items: Vec<CreditInvoice>,
+    pub total_count: i64,
+    /// Sum of balance_remaining over all invoices matching the filters
+    pub total_balance: f64,
+}
+
+/// Credit invoices of customer ?1 (any when NULL) with status ?4 (any when NULL), with paid,
+/// balance and days outstanding as of business date ?2 (?3 shifts UTC into business time).
+/// Paid uses INVOICE_PAID_SQL, so it is computed per row in SQL and older bills whose initial
+/// payment was never recorded still count initial_paid.
+const CREDIT_INVOICES_CTE: &str = "WITH credit AS (
+                SELECT i.id, i.invoice_number, i.created_at, i.due_date, i.customer_id,
+                       c.name AS customer_name, c.phone AS customer_phone, i.total_amount,
+                       COALESCE(i.initial_paid, 0) AS initial_paid,
+                       {paid} AS paid,
+                       CAST(julianday(?2) - julianday(date(i.created_at, ?3)) AS INTEGER) AS days_outstanding
+                FROM invoices i
+                LEFT JOIN customers c ON i.customer_id = c.id
+                WHERE (i.credit_amount > 0 OR i.payment_method = 'Credit')
+                  AND (?1 IS NULL OR i.customer_id = ?1)
+             ), balances AS (
+                SELECT *,
+                       MAX(ROUND(total_amount - paid, 2), 0) AS balance,
+                       CASE WHEN ROUND(total_amount - paid, 2) <= 0 THEN 'paid'
+                            WHEN paid > 0.005 THEN 'partial'
+                            ELSE 'unpaid' END AS status
+                FROM credit
+             )";
+
+/// Get a page of credit invoices with what was paid and what is still owed, plus the total
+/// balance for the filters, so the credit screen needs no per-invoice payment lookups
+#[tauri::command]
+pub fn get_credit_invoices(
+    page: i32,
+    page_size: i32,
+    filters: Option<CreditInvoiceFilters>,
+    db: State<Database>,
+) -> Result<CreditInvoicePage, String> {
+    log::info!("get_credit_invoices called - page: {}, size: {}, filters: {:?}", page, page_size, filters);
+
+    let conn = db.get_conn()?;
+    get_credit_invoices_internal(&conn, page, page_size, &filters.unwrap_or_default())
+}
+
+pub(crate) fn get_credit_invoices_internal(
+    conn: &Connection,
+    page: i32,
+    page_size: i32,
+    filters: &CreditInvoiceFilters,
+) -> Result<CreditInvoicePage, String> {
+    let (limit, offset) = validate_pagination(page, page_size)?;
+
+    if let Some(status) = filters.status.as_deref() {
+        if !["paid", "partial", "unpaid"].contains(&status) {
+            return Err(format!("Invalid credit status '{}', expected paid, partial or unpaid", status));
+        }
+    }
+
+    // Whitelisted so the column name never comes from the caller; the oldest invoice has the
+    // earliest created_at, so age sorts that column the other way round
+    let descending = filters.descending.unwrap_or(true);
+    let (sort_column, column_descending) = match filters.sort.as_deref() {
+        None | Some("age") => ("datetime(created_at)", !descending),
+        Some("balance") => ("balance", descending),
+        Some(other) => return Err(format!("Invalid sort '{}', expected age or balance", other)),
+    };
+    let direction = if column_descending { "DESC" } else { "ASC" };
+
+    let cte = CREDIT_INVOICES_CTE.replace("{paid}", INVOICE_PAID_SQL);
+    let today = business_today(conn).format("%Y-%m-%d").to_string();
+    let modifier = format!("{:+} minutes", business_offset_minutes(conn));
+    let status = filters.status.as_deref();
+
+    let (total_count, total_balance): (i64, f64) = conn
+        .query_row(
+            &format!("{} SELECT COUNT(*), COALESCE(SUM(balance), 0) FROM balances WHERE (?4 IS NULL OR status = ?4)", cte),
+            params![filters.customer_id, today, modifier, status],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to count credit invoices: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} SELECT id, invoice_number, created_at, due_date, days_outstanding, customer_id, customer_name,
+                    customer_phone, total_amount, initial_paid, paid, balance, status
+             FROM balances
+             WHERE (?4 IS NULL OR status = ?4)
+             ORDER BY {} {}, id {}
+             LIMIT ?5 OFFSET ?6",
+            cte, sort_column, direction, direction
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let items = stmt
+        .query_map(params![filters.customer_id, today, modifier, status, limit, offset], |row| {
+            Ok(CreditInvoice {
+                invoice_id: row.get(0)?,
+                invoice_number: row.get(1)?,
+                invoice_date: row.get(2)?,
+                due_date: row.get(3)?,
+                days_outstanding: row.get(4)?,
+                customer_id: row.get(5)?,
+                customer_name: row.get(6)?,
+                customer_phone: row.get(7)?,
+                bill_amount: row.get(8)?,
+                initial_paid: row.get(9)?,
+                total_paid: money::round_money(row.get(10)?),
+                balance_remaining: row.get(11)?,
+                payment_status: row.get(12)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load credit invoices: {}", e))?;
+
+    Ok(CreditInvoicePage {
+        items,
+        total_count,
+        total_balance: money::round_money(total_balance),
     })
 }
 
-/// Delete a customer payment
+/// Delete a customer payment.
+///
+/// Invoice balances are derived from the remaining payments, but a credit invoice also stores
+/// what was paid at billing (initial_paid / credit_amount); deleting that initial payment moves
+/// it back onto the credit in the same transaction, so history and summaries agree right away.
 #[tauri::command]
 pub fn delete_customer_payment(
     id: i32,
     deleted_by: Option<String>,
+    app: AppHandle,
     db: State<Database>,
 ) -> Result<(), String> {
     log::info!(
@@ -339,8 +664,19 @@ pub fn delete_customer_payment(
         deleted_by
     );
 
-    let mut conn = db.get_conn()?;
+    let conn = db.get_conn()?;
+    let payment = delete_customer_payment_internal(&conn, id, deleted_by)?;
 
+    emit_data_changed(&app, DataEntity::Invoice, DataOperation::Updated, payment.invoice_id);
+    emit_data_changed(&app, DataEntity::Customer, DataOperation::Updated, payment.customer_id);
+    Ok(())
+}
+
+pub(crate) fn delete_customer_payment_internal(
+    conn: &Connection,
+    id: i32,
+    deleted_by: Option<String>,
+) -> Result<CustomerPayment, String> {
     // Fetch payment details for audit
     let payment = conn
         .query_row(
@@ -349,28 +685,16 @@ pub fn delete_customer_payment(
              JOIN invoices i ON cp.invoice_id = i.id
              WHERE cp.id = ?1",
             [id],
-            |row| {
-                Ok(CustomerPayment {
-                    id: row.get(0)?,
-                    customer_id: row.get(1)?,
-                    invoice_id: row.get(2)?,
-                    invoice_number: row.get(3)?,
-                    amount: row.get(4)?,
-                    payment_method: row.get(5)?,
-                    note: row.get(6)?,
-                    paid_at: row.get(7)?,
-                    created_at: row.get(8)?,
-                })
-            },
+            row_to_payment,
         )
         .map_err(|e| format!("Payment not found: {}", e))?;
 
     let tx = conn
-        .transaction()
+        .unchecked_transaction()
         .map_err(|e| format!("Transaction failed: {}", e))?;
 
     // Archive
-    crate::db::archive::archive_entity(&tx, "customer_payment", id, &payment, None, deleted_by)?;
+    crate::db::archive::archive_entity(&tx, "customer_payment", id, &payment, None, deleted_by.clone())?;
 
     // Delete
     let rows_affected = tx
@@ -381,6 +705,41 @@ pub fn delete_customer_payment(
         return Err(format!("Customer payment with id {} not found", id));
     }
 
+    // The payment taken at billing is also recorded on the invoice itself
+    if payment.note.as_deref() == Some(INITIAL_PAYMENT_NOTE) {
+        tx.execute(
+            "UPDATE invoices
+             SET initial_paid = MAX(ROUND(COALESCE(initial_paid, 0) - ?2, 2), 0),
+                 credit_amount = MAX(ROUND(total_amount - MAX(ROUND(COALESCE(initial_paid, 0) - ?2, 2), 0), 2), 0)
+             WHERE id = ?1 AND payment_method = 'Credit'",
+            params![payment.invoice_id, payment.amount],
+        )
+        .map_err(|e| format!("Failed to update invoice credit: {}", e))?;
+    }
+
+    let (bill_amount, paid_after): (f64, f64) = tx
+        .query_row(
+            "SELECT total_amount, COALESCE((SELECT SUM(amount) FROM customer_payments WHERE invoice_id = ?1), 0)
+             FROM invoices WHERE id = ?1",
+            [payment.invoice_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to recompute invoice balance: {}", e))?;
+    let balance_after = money::sub(bill_amount, paid_after).max(0.0);
+    let balance_before = money::sub(bill_amount, money::sum([paid_after, payment.amount])).max(0.0);
+
+    let invoice_number = payment.invoice_number.clone().unwrap_or_default();
+    let field_changes = serde_json::json!([
+        {"field": "amount", "old": payment.amount, "new": null},
+        {"field": "balance_remaining", "old": balance_before, "new": balance_after},
+    ]);
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params!["customer_payment", id, format!("Payment on {}", invoice_number), "deleted", field_changes.to_string(), deleted_by],
+    )
+    .map_err(|e| format!("Failed to log entity modification: {}", e))?;
+
     tx.commit().map_err(|e| format!("Commit failed: {}", e))?;
-    Ok(())
+    log::info!("Deleted payment {} on {}; balance now {}", id, invoice_number, balance_after);
+    Ok(payment)
 }
@@ -1,7 +1,11 @@
 use crate::db::{Database, Customer};
-use crate::commands::PaginatedResult;
+use crate::commands::{validate_pagination, PaginatedResult};
+use crate::commands::images::remove_image_files;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use crate::services::money;
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, State};
 use chrono::Utc;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -14,6 +18,9 @@ pub struct CreateCustomerInput {
     pub state: Option<String>,
     pub district: Option<String>,
     pub town: Option<String>,
+    /// Maximum outstanding credit; None means unlimited
+    #[serde(default)]
+    pub credit_limit: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -27,122 +34,203 @@ pub struct UpdateCustomerInput {
     pub state: Option<String>,
     pub district: Option<String>,
     pub town: Option<String>,
+    /// Maximum outstanding credit; None means unlimited
+    #[serde(default)]
+    pub credit_limit: Option<f64>,
+}
+
+/// Prefix of the error returned when a new customer's phone belongs to an archived customer;
+/// the rest of the message is a JSON `ArchivedCustomerConflict`
+pub const ARCHIVED_CUSTOMER_EXISTS: &str = "ARCHIVED_CUSTOMER_EXISTS:";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ArchivedCustomerConflict {
+    /// Pass to restore_customer to bring the customer back
+    pub customer_id: i32,
+    pub name: String,
+    pub phone: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CustomerWithStats {
     #[serde(flatten)]
     pub customer: Customer,
-    pub invoice_count: i32,
+    /// Purchase stats, filled in only when get_customers is asked for them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice_count: Option<i32>,
+    /// Date of the latest invoice
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub last_billed: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_spent: Option<f64>,
+    /// Credit still owed, as get_customer_credit_summary's pending_amount
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outstanding_balance: Option<f64>,
+}
+
+/// Order of the get_customers list
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CustomerSort {
+    /// By name; the default, read straight off idx_customers_name_id
+    Name,
+    /// Latest invoice first, customers who never bought last
+    RecentlyActive,
+    /// Most credit owed first
+    HighestOutstanding,
+}
+
+impl CustomerSort {
+    fn parse(sort_by: Option<&str>) -> Result<Self, String> {
+        match sort_by {
+            None | Some("name") => Ok(CustomerSort::Name),
+            Some("recent") => Ok(CustomerSort::RecentlyActive),
+            Some("outstanding") => Ok(CustomerSort::HighestOutstanding),
+            Some(other) => Err(format!(
+                "Validation error: unknown customer sort '{}', expected name, recent or outstanding",
+                other
+            )),
+        }
+    }
+}
+
+/// A customer's latest invoice date, correlated on `c`; served by idx_invoices_customer_created
+const LAST_BILLED_SQL: &str = "(SELECT MAX(i.created_at) FROM invoices i WHERE i.customer_id = c.id)";
+
+/// A customer's unpaid credit, correlated on `c`. Same sum as get_customer_credit_summary:
+/// credit given, less payments beyond what was paid up front, on credit invoices.
+const OUTSTANDING_BALANCE_SQL: &str = "(SELECT MAX(
+            COALESCE(SUM(i.credit_amount), 0) + COALESCE(SUM(i.initial_paid), 0)
+                - COALESCE(SUM((SELECT SUM(cp.amount) FROM customer_payments cp WHERE cp.invoice_id = i.id)), 0),
+            0)
+         FROM invoices i
+         WHERE i.customer_id = c.id AND (i.credit_amount > 0 OR i.payment_method = 'Credit'))";
+
+/// Filter shared by the count and page queries: ?1 search pattern (NULL for none), ?2 whether
+/// archived customers are included
+const CUSTOMER_FILTER_SQL: &str = "WHERE (?2 = 1 OR c.is_archived = 0)
+           AND (?1 IS NULL OR c.name LIKE ?1 OR c.phone LIKE ?1 OR c.place LIKE ?1 OR c.email LIKE ?1)";
+
+/// Page of customers, ?3 limit and ?4 offset. Without stats this only reads customers; the
+/// stats and the activity/credit sorts are per-customer subqueries over that customer's
+/// invoices, never a join across all of them.
+pub(crate) fn customers_page_sql(sort: CustomerSort, include_stats: bool) -> String {
+    let stats = if include_stats {
+        format!(
+            "(SELECT COUNT(*) FROM invoices i WHERE i.customer_id = c.id),
+             {},
+             (SELECT COALESCE(SUM(i.total_amount), 0) FROM invoices i WHERE i.customer_id = c.id),
+             {}",
+            LAST_BILLED_SQL, OUTSTANDING_BALANCE_SQL
+        )
+    } else {
+        "NULL, NULL, NULL, NULL".to_string()
+    };
+    let order_by = match sort {
+        CustomerSort::Name => "c.name, c.id".to_string(),
+        CustomerSort::RecentlyActive => format!("{} DESC NULLS LAST, c.name, c.id", LAST_BILLED_SQL),
+        CustomerSort::HighestOutstanding => format!("{} DESC, c.name, c.id", OUTSTANDING_BALANCE_SQL),
+    };
+
+    format!(
+        "SELECT c.id, c.name, c.email, c.phone, c.address, c.place, c.state, c.district, c.town, c.created_at, c.updated_at,
+                c.image_path, c.credit_limit, c.is_archived,
+                {}
+         FROM customers c
+         {}
+         ORDER BY {}
+         LIMIT ?3 OFFSET ?4",
+        stats, CUSTOMER_FILTER_SQL, order_by
+    )
 }
 
-/// Get all customers, optionally filtered by search query, with pagination
+/// Get customers, optionally filtered by a search over name, phone, place and email, with
+/// pagination. Archived customers are left out unless include_archived is set.
+///
+/// `sort_by` is "name" (default), "recent" (latest invoice first) or "outstanding" (most credit
+/// owed first). Invoice count, last billed date, total spent and outstanding balance are only
+/// computed when include_stats is set, as they read each customer's invoices.
 #[tauri::command]
 pub fn get_customers(
     search: Option<String>,
     page: i32,
     page_size: i32,
+    include_archived: Option<bool>,
+    sort_by: Option<String>,
+    include_stats: Option<bool>,
     db: State<Database>
 ) -> Result<PaginatedResult<CustomerWithStats>, String> {
-    log::info!("get_customers called with search: {:?}, page: {}, page_size: {}", search, page, page_size);
+    log::info!(
+        "get_customers called with search: {:?}, page: {}, page_size: {}, include_archived: {:?}, sort_by: {:?}, include_stats: {:?}",
+        search, page, page_size, include_archived, sort_by, include_stats
+    );
 
     let conn = db.get_conn()?;
+    get_customers_internal(
+        &conn,
+        search.as_deref(),
+        page,
+        page_size,
+        include_archived.unwrap_or(false),
+        sort_by.as_deref(),
+        include_stats.unwrap_or(false),
+    )
+}
 
-    let offset = (page - 1) * page_size;
-    let limit = page_size;
-
-    let mut customers = Vec::new();
-    let total_count: i64;
-
-    let base_query = "
-        SELECT c.id, c.name, c.email, c.phone, c.address, c.place, c.state, c.district, c.town, c.created_at, c.updated_at,
-               COUNT(i.id) as invoice_count,
-               MAX(i.created_at) as last_billed
-        FROM customers c
-        LEFT JOIN invoices i ON c.id = i.customer_id
-    ";
-
-    let count_query = "SELECT COUNT(*) FROM customers c";
-
-    let group_by = "GROUP BY c.id ORDER BY last_billed DESC NULLS LAST, c.name ASC";
-
-    if let Some(search_term) = search {
-        let search_pattern = format!("%{}%", search_term);
-        let where_clause = "WHERE c.name LIKE ?1 OR c.email LIKE ?1 OR c.phone LIKE ?1 OR c.place LIKE ?1";
-        
-        // Get total count
-        let count_sql = format!("{} {}", count_query, where_clause);
-        total_count = conn
-            .query_row(&count_sql, [&search_pattern], |row| row.get(0))
-            .map_err(|e| e.to_string())?;
-
-        // Get paginated items
-        let query = format!("{} {} {} LIMIT ?2 OFFSET ?3", base_query, where_clause, group_by);
-        
-        let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
-
-        let customer_iter = stmt
-            .query_map(rusqlite::params![&search_pattern, limit, offset], |row| {
-                Ok(CustomerWithStats {
-                    customer: Customer {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        email: row.get(2)?,
-                        phone: row.get(3)?,
-                        address: row.get(4)?,
-                        place: row.get(5)?,
-                        state: row.get(6)?,
-                        district: row.get(7)?,
-                        town: row.get(8)?,
-                        created_at: row.get(9)?,
-                        updated_at: row.get(10)?,
-                    },
-                    invoice_count: row.get(11)?,
-                    last_billed: row.get(12)?,
-                })
-            })
-            .map_err(|e| e.to_string())?;
+pub(crate) fn get_customers_internal(
+    conn: &rusqlite::Connection,
+    search: Option<&str>,
+    page: i32,
+    page_size: i32,
+    include_archived: bool,
+    sort_by: Option<&str>,
+    include_stats: bool,
+) -> Result<PaginatedResult<CustomerWithStats>, String> {
+    let (limit, offset) = validate_pagination(page, page_size)?;
+    let sort = CustomerSort::parse(sort_by)?;
+    let search_pattern = search
+        .map(str::trim)
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("%{}%", term));
+
+    let total_count: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM customers c {}", CUSTOMER_FILTER_SQL),
+            rusqlite::params![search_pattern, include_archived],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
 
-        for customer in customer_iter {
-            customers.push(customer.map_err(|e| e.to_string())?);
-        }
-    } else {
-        // Get total count
-        total_count = conn
-            .query_row(count_query, [], |row| row.get(0))
-            .map_err(|e| e.to_string())?;
-
-        // Get paginated items
-        let query = format!("{} {} LIMIT ?1 OFFSET ?2", base_query, group_by);
-        let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
-
-        let customer_iter = stmt
-            .query_map([limit, offset], |row| {
-                Ok(CustomerWithStats {
-                    customer: Customer {
-                        id: row.get(0)?,
-                        name: row.get(1)?,
-                        email: row.get(2)?,
-                        phone: row.get(3)?,
-                        address: row.get(4)?,
-                        place: row.get(5)?,
-                        state: row.get(6)?,
-                        district: row.get(7)?,
-                        town: row.get(8)?,
-                        created_at: row.get(9)?,
-                        updated_at: row.get(10)?,
-                    },
-                    invoice_count: row.get(11)?,
-                    last_billed: row.get(12)?,
-                })
+    let mut stmt = conn
+        .prepare(&customers_page_sql(sort, include_stats))
+        .map_err(|e| e.to_string())?;
+    let customers = stmt
+        .query_map(rusqlite::params![search_pattern, include_archived, limit, offset], |row| {
+            Ok(CustomerWithStats {
+                customer: Customer {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    email: row.get(2)?,
+                    phone: row.get(3)?,
+                    address: row.get(4)?,
+                    place: row.get(5)?,
+                    state: row.get(6)?,
+                    district: row.get(7)?,
+                    town: row.get(8)?,
+                    created_at: row.get(9)?,
+                    updated_at: row.get(10)?,
+                    image_path: row.get(11)?,
+                    credit_limit: row.get(12)?,
+                    is_archived: row.get(13)?,
+                },
+                invoice_count: row.get(14)?,
+                last_billed: row.get(15)?,
+                total_spent: row.get::<_, Option<f64>>(16)?.map(money::round_money),
+                outstanding_balance: row.get::<_, Option<f64>>(17)?.map(money::round_money),
             })
-            .map_err(|e| e.to_string())?;
-
-        for customer in customer_iter {
-            customers.push(customer.map_err(|e| e.to_string())?);
-        }
-    }
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
 
     log::info!("Returning {} customers (page {}, size {}, total {})", customers.len(), page, page_size, total_count);
     Ok(PaginatedResult {
@@ -160,7 +248,7 @@ pub fn get_customer(id: i32, db: State<Database>) -> Result<Customer, String> {
 
     let customer = conn
         .query_row(
-            "SELECT id, name, email, phone, address, place, state, district, town, created_at, updated_at FROM customers WHERE id = ?1",
+            "SELECT id, name, email, phone, address, place, state, district, town, created_at, updated_at, image_path, credit_limit, is_archived FROM customers WHERE id = ?1",
             [id],
             |row| {
                 Ok(Customer {
@@ -175,6 +263,9 @@ pub fn get_customer(id: i32, db: State<Database>) -> Result<Customer, String> {
                     town: row.get(8)?,
                     created_at: row.get(9)?,
                     updated_at: row.get(10)?,
+                    image_path: row.get(11)?,
+                    credit_limit: row.get(12)?,
+                    is_archived: row.get(13)?,
                 })
             },
         )
@@ -183,6 +274,16 @@ pub fn get_customer(id: i32, db: State<Database>) -> Result<Customer, String> {
     Ok(customer)
 }
 
+/// Helper to validate a credit limit (None = unlimited, otherwise not negative)
+fn validate_credit_limit(credit_limit: Option<f64>) -> Result<(), String> {
+    match credit_limit {
+        Some(limit) if !limit.is_finite() || limit < 0.0 => {
+            Err(format!("Validation error: credit limit must be zero or more (got {})", limit))
+        }
+        _ => Ok(()),
+    }
+}
+
 /// Helper to validate phone number (must be 10 digits)
 fn validate_phone(phone: &Option<String>) -> Result<(), String> {
     if let Some(p) = phone {
@@ -202,18 +303,40 @@ fn validate_phone(phone: &Option<String>) -> Result<(), String> {
 
 /// Create a new customer
 #[tauri::command]
-pub fn create_customer(input: CreateCustomerInput, db: State<Database>) -> Result<Customer, String> {
+pub fn create_customer(input: CreateCustomerInput, app: AppHandle, db: State<Database>) -> Result<Customer, String> {
     log::info!("create_customer called with: {:?}", input);
 
-    validate_phone(&input.phone)?;
-
     let conn = db.get_conn()?;
+    let customer = create_customer_internal(&conn, input)?;
+    emit_data_changed(&app, DataEntity::Customer, DataOperation::Created, customer.id);
+    Ok(customer)
+}
+
+pub(crate) fn create_customer_internal(conn: &rusqlite::Connection, input: CreateCustomerInput) -> Result<Customer, String> {
+    validate_phone(&input.phone)?;
+    validate_credit_limit(input.credit_limit)?;
+
+    // Offer the archived customer back rather than creating a second one with the same phone
+    if let Some(phone) = &input.phone {
+        let archived = conn
+            .query_row(
+                "SELECT id, name FROM customers WHERE phone = ?1 AND is_archived = 1 ORDER BY id LIMIT 1",
+                [phone],
+                |row| Ok(ArchivedCustomerConflict { customer_id: row.get(0)?, name: row.get(1)?, phone: phone.clone() }),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to check archived customers: {}", e))?;
+        if let Some(conflict) = archived {
+            let details = serde_json::to_string(&conflict).map_err(|e| e.to_string())?;
+            return Err(format!("{}{}", ARCHIVED_CUSTOMER_EXISTS, details));
+        }
+    }
 
     let now = Utc::now().to_rfc3339();
 
     conn.execute(
-        "INSERT INTO customers (name, email, phone, address, place, state, district, town, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        (&input.name, &input.email, &input.phone, &input.address, &input.place, &input.state, &input.district, &input.town, &now, &now),
+        "INSERT INTO customers (name, email, phone, address, place, state, district, town, credit_limit, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        (&input.name, &input.email, &input.phone, &input.address, &input.place, &input.state, &input.district, &input.town, input.credit_limit, &now, &now),
     )
     .map_err(|e| format!("Failed to create customer: {}", e))?;
 
@@ -231,6 +354,9 @@ pub fn create_customer(input: CreateCustomerInput, db: State<Database>) -> Resul
         town: input.town,
         created_at: now.clone(),
         updated_at: now,
+        image_path: None,
+        credit_limit: input.credit_limit,
+        is_archived: false,
     };
 
     log::info!("Created customer with id: {}", id);
@@ -239,17 +365,23 @@ pub fn create_customer(input: CreateCustomerInput, db: State<Database>) -> Resul
 
 /// Update an existing customer
 #[tauri::command]
-pub fn update_customer(input: UpdateCustomerInput, modified_by: Option<String>, db: State<Database>) -> Result<Customer, String> {
+pub fn update_customer(
+    input: UpdateCustomerInput,
+    modified_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<Customer, String> {
     log::info!("update_customer called with: {:?}", input);
 
     validate_phone(&input.phone)?;
+    validate_credit_limit(input.credit_limit)?;
 
     let conn = db.get_conn()?;
 
     // Get old values for modification logging
     let old_customer: Customer = conn
         .query_row(
-            "SELECT id, name, email, phone, address, place, state, district, town, created_at, updated_at FROM customers WHERE id = ?1",
+            "SELECT id, name, email, phone, address, place, state, district, town, created_at, updated_at, image_path, credit_limit, is_archived FROM customers WHERE id = ?1",
             [input.id],
             |row| {
                 Ok(Customer {
@@ -264,6 +396,9 @@ pub fn update_customer(input: UpdateCustomerInput, modified_by: Option<String>,
                     town: row.get(8)?,
                     created_at: row.get(9)?,
                     updated_at: row.get(10)?,
+                    image_path: row.get(11)?,
+                    credit_limit: row.get(12)?,
+                    is_archived: row.get(13)?,
                 })
             },
         )
@@ -298,11 +433,14 @@ pub fn update_customer(input: UpdateCustomerInput, modified_by: Option<String>,
     if old_customer.town != input.town {
         field_changes.push(serde_json::json!({"field": "town", "old": old_customer.town, "new": input.town}));
     }
+    if old_customer.credit_limit != input.credit_limit {
+        field_changes.push(serde_json::json!({"field": "credit_limit", "old": old_customer.credit_limit, "new": input.credit_limit}));
+    }
 
     let rows_affected = conn
         .execute(
-            "UPDATE customers SET name = ?1, email = ?2, phone = ?3, address = ?4, place = ?5, state = ?6, district = ?7, town = ?8, updated_at = ?9 WHERE id = ?10",
-            (&input.name, &input.email, &input.phone, &input.address, &input.place, &input.state, &input.district, &input.town, &now, input.id),
+            "UPDATE customers SET name = ?1, email = ?2, phone = ?3, address = ?4, place = ?5, state = ?6, district = ?7, town = ?8, credit_limit = ?9, updated_at = ?10 WHERE id = ?11",
+            (&input.name, &input.email, &input.phone, &input.address, &input.place, &input.state, &input.district, &input.town, input.credit_limit, &now, input.id),
         )
         .map_err(|e| format!("Failed to update customer: {}", e))?;
 
@@ -332,22 +470,133 @@ pub fn update_customer(input: UpdateCustomerInput, modified_by: Option<String>,
         town: input.town,
         created_at: old_customer.created_at,
         updated_at: now,
+        image_path: old_customer.image_path,
+        credit_limit: input.credit_limit,
+        is_archived: old_customer.is_archived,
     };
 
     log::info!("Updated customer with id: {}", input.id);
+    emit_data_changed(&app, DataEntity::Customer, DataOperation::Updated, customer.id);
     Ok(customer)
 }
 
-/// Delete a customer by ID
+/// Delete a customer by ID. Customers with invoices or payments are archived instead, so their
+/// bills keep the customer; only customers with no history are moved to trash.
 #[tauri::command]
-pub fn delete_customer(id: i32, deleted_by: Option<String>, db: State<Database>) -> Result<(), String> {
+pub fn delete_customer(
+    id: i32,
+    deleted_by: Option<String>,
+    app_handle: AppHandle,
+    db: State<Database>,
+) -> Result<(), String> {
     log::info!("delete_customer called with id: {}", id);
 
     let mut conn = db.get_conn()?;
+    let image_path = delete_customer_internal(&mut conn, id, deleted_by)?;
+    remove_image_files(&app_handle, &conn, image_path.as_deref());
+    emit_data_changed(&app_handle, DataEntity::Customer, DataOperation::Deleted, id);
+    Ok(())
+}
+
+/// Returns the image path of a hard-deleted customer, which the caller removes from disk
+pub(crate) fn delete_customer_internal(
+    conn: &mut rusqlite::Connection,
+    id: i32,
+    deleted_by: Option<String>,
+) -> Result<Option<String>, String> {
+    let customer = load_customer(conn, id)?;
+
+    let has_history: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM invoices WHERE customer_id = ?1)
+                 OR EXISTS (SELECT 1 FROM customer_payments WHERE customer_id = ?1)",
+            [id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to check customer history: {}", e))?;
+
+    if has_history {
+        set_customer_archived(conn, &customer, true, deleted_by)?;
+        log::info!("Archived customer with id: {} (has invoice or payment history)", id);
+        return Ok(None);
+    }
+
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    crate::db::archive::archive_entity(&tx, "customer", id, &customer, None, deleted_by)?;
+
+    let rows_affected = tx.execute("DELETE FROM customers WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to delete customer: {}", e))?;
+
+    if rows_affected == 0 {
+        return Err(format!("Customer with id {} not found", id));
+    }
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Deleted customer with id: {} and saved to trash", id);
+    Ok(customer.image_path)
+}
+
+/// Bring an archived customer back into the customer list
+pub(crate) fn unarchive_customer_internal(
+    conn: &rusqlite::Connection,
+    id: i32,
+    restored_by: Option<String>,
+) -> Result<Customer, String> {
+    let mut customer = load_customer(conn, id)?;
+    if !customer.is_archived {
+        return Err(format!("Customer with id {} is not archived", id));
+    }
+
+    customer.updated_at = set_customer_archived(conn, &customer, false, restored_by)?;
+    customer.is_archived = false;
+
+    log::info!("Unarchived customer with id: {}", id);
+    Ok(customer)
+}
+
+/// Set or clear is_archived and log it as a modification (restoring the modification reverses it).
+/// Returns the new updated_at.
+fn set_customer_archived(
+    conn: &rusqlite::Connection,
+    customer: &Customer,
+    archived: bool,
+    modified_by: Option<String>,
+) -> Result<String, String> {
+    let now = Utc::now().to_rfc3339();
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
 
-    // Get customer data before deletion for audit trail
-    let customer = conn.query_row(
-        "SELECT id, name, email, phone, address, place, state, district, town, created_at, updated_at FROM customers WHERE id = ?1",
+    tx.execute(
+        "UPDATE customers SET is_archived = ?1, updated_at = ?2 WHERE id = ?3",
+        rusqlite::params![archived, &now, customer.id],
+    )
+    .map_err(|e| format!("Failed to update customer: {}", e))?;
+
+    let changes = serde_json::json!([{"field": "is_archived", "old": customer.is_archived, "new": archived}]);
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        (
+            "customer",
+            customer.id,
+            &customer.name,
+            if archived { "archived" } else { "unarchived" },
+            changes.to_string(),
+            &modified_by,
+        ),
+    )
+    .map_err(|e| format!("Failed to log modification: {}", e))?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    Ok(now)
+}
+
+/// Load a customer row by ID using an existing connection
+fn load_customer(conn: &rusqlite::Connection, id: i32) -> Result<Customer, String> {
+    conn.query_row(
+        "SELECT id, name, email, phone, address, place, state, district, town, created_at, updated_at, image_path, credit_limit, is_archived FROM customers WHERE id = ?1",
         [id],
         |row| {
             Ok(Customer {
@@ -362,81 +611,121 @@ pub fn delete_customer(id: i32, deleted_by: Option<String>, db: State<Database>)
                 town: row.get(8)?,
                 created_at: row.get(9)?,
                 updated_at: row.get(10)?,
+                image_path: row.get(11)?,
+                credit_limit: row.get(12)?,
+                is_archived: row.get(13)?,
             })
         },
     )
-    .map_err(|e| format!("Customer with id {} not found: {}", id, e))?;
+    .map_err(|e| format!("Customer with id {} not found: {}", id, e))
+}
 
-    // Get related invoices (scoped to release borrow before transaction)
-    let invoices = {
-        let mut stmt = conn.prepare("SELECT id, invoice_number, customer_id, total_amount, tax_amount, discount_amount, payment_method, created_at, cgst_amount, fy_year, gst_rate, igst_amount, sgst_amount, state, district, town FROM invoices WHERE customer_id = ?1").map_err(|e| e.to_string())?;
-        let invoices_iter = stmt.query_map([id], |row| {
-            Ok(crate::db::Invoice {
-                id: row.get(0)?,
-                invoice_number: row.get(1)?,
-                customer_id: row.get(2)?,
-                total_amount: row.get(3)?,
-                tax_amount: row.get(4)?,
-                discount_amount: row.get(5)?,
-                payment_method: row.get(6)?,
-                created_at: row.get(7)?,
-                cgst_amount: row.get(8)?,
-                fy_year: row.get(9)?,
-                gst_rate: row.get(10)?,
-                igst_amount: row.get(11)?,
-                sgst_amount: row.get(12)?,
-                state: row.get(13)?,
-                district: row.get(14)?,
-                town: row.get(15)?,
-                customer_name: None,
-                customer_phone: None,
-                item_count: None,
-                quantity: None,
-                product_amount: None,
-            })
-        }).map_err(|e| e.to_string())?;
+/// Merge a duplicate customer (source) into another customer (target).
+/// Invoices and payments are repointed to the target, empty target fields are filled
+/// from the source, and the source is archived to trash. The target keeps its own
+/// phone/name when both are set; the source's values are kept in the archive JSON.
+#[tauri::command]
+pub fn merge_customers(
+    source_id: i32,
+    target_id: i32,
+    merged_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<Customer, String> {
+    log::info!("merge_customers called: source_id: {}, target_id: {}", source_id, target_id);
+
+    if source_id == target_id {
+        return Err("Cannot merge a customer into itself".to_string());
+    }
 
-        let mut invoices = Vec::new();
-        for invoice in invoices_iter {
-            invoices.push(invoice.map_err(|e| e.to_string())?);
-        }
-        invoices
-    };
+    let mut conn = db.get_conn()?;
+
+    let source = load_customer(&conn, source_id)?;
+    let target = load_customer(&conn, target_id)?;
 
     let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
 
-    // Save to deleted_items
-    let invoices_json = if invoices.is_empty() {
-        None
-    } else {
-        Some(serde_json::to_string(&invoices).map_err(|e| format!("Failed to serialize invoices: {}", e))?)
-    };
+    let invoices_moved = tx
+        .execute("UPDATE invoices SET customer_id = ?1 WHERE customer_id = ?2", [target_id, source_id])
+        .map_err(|e| format!("Failed to repoint invoices: {}", e))?;
+    let payments_moved = tx
+        .execute("UPDATE customer_payments SET customer_id = ?1 WHERE customer_id = ?2", [target_id, source_id])
+        .map_err(|e| format!("Failed to repoint customer payments: {}", e))?;
+
+    // Fill gaps on the target from the source; conflicting values stay on the target
+    let mut merged = target.clone();
+    let mut field_changes: Vec<serde_json::Value> = Vec::new();
+    let mut conflicts = serde_json::Map::new();
+    {
+        let mut fill = |field: &str, target_val: &mut Option<String>, source_val: &Option<String>| {
+            match (target_val.as_ref(), source_val) {
+                (None, Some(v)) => {
+                    field_changes.push(serde_json::json!({"field": field, "old": serde_json::Value::Null, "new": v}));
+                    *target_val = Some(v.clone());
+                }
+                (Some(t), Some(v)) if t != v => {
+                    conflicts.insert(field.to_string(), serde_json::json!(v));
+                }
+                _ => {}
+            }
+        };
+        fill("email", &mut merged.email, &source.email);
+        fill("phone", &mut merged.phone, &source.phone);
+        fill("address", &mut merged.address, &source.address);
+        fill("place", &mut merged.place, &source.place);
+        fill("state", &mut merged.state, &source.state);
+        fill("district", &mut merged.district, &source.district);
+        fill("town", &mut merged.town, &source.town);
+    }
+    if source.name != target.name {
+        conflicts.insert("name".to_string(), serde_json::json!(source.name));
+    }
+
+    let now = Utc::now().to_rfc3339();
+    tx.execute(
+        "UPDATE customers SET email = ?1, phone = ?2, address = ?3, place = ?4, state = ?5, district = ?6, town = ?7, updated_at = ?8 WHERE id = ?9",
+        (&merged.email, &merged.phone, &merged.address, &merged.place, &merged.state, &merged.district, &merged.town, &now, target_id),
+    )
+    .map_err(|e| format!("Failed to update target customer: {}", e))?;
+    merged.updated_at = now;
+
+    let merge_info = serde_json::json!({
+        "merged_into": target_id,
+        "invoices_moved": invoices_moved,
+        "payments_moved": payments_moved,
+        "conflicting_fields": conflicts,
+    });
 
     crate::db::archive::archive_entity(
         &tx,
         "customer",
-        id,
-        &customer,
-        invoices_json,
-        deleted_by,
+        source_id,
+        &source,
+        Some(merge_info.to_string()),
+        merged_by.clone(),
     )?;
 
-    // Delete linked invoices first (invoice_items will cascade delete due to FK)
-    tx.execute("DELETE FROM invoices WHERE customer_id = ?1", [id])
-        .map_err(|e| format!("Failed to delete customer invoices: {}", e))?;
-
-    // Delete the customer
-    let rows_affected = tx.execute("DELETE FROM customers WHERE id = ?1", [id])
-        .map_err(|e| format!("Failed to delete customer: {}", e))?;
+    tx.execute("DELETE FROM customers WHERE id = ?1", [source_id])
+        .map_err(|e| format!("Failed to delete merged customer: {}", e))?;
 
-    if rows_affected == 0 {
-        return Err(format!("Customer with id {} not found", id));
-    }
+    let changes_json = serde_json::to_string(&field_changes).unwrap_or_default();
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        ("customer", target_id, format!("{} (merged from {} #{})", target.name, source.name, source_id), "merged", &changes_json, &merged_by),
+    ).map_err(|e| format!("Failed to log modification: {}", e))?;
 
     tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
-    log::info!("Deleted customer with id: {} and saved to trash", id);
-    Ok(())
+    log::info!(
+        "Merged customer {} into {} ({} invoices, {} payments moved)",
+        source_id,
+        target_id,
+        invoices_moved,
+        payments_moved
+    );
+    emit_data_changed(&app, DataEntity::Customer, DataOperation::Deleted, source_id);
+    emit_data_changed(&app, DataEntity::Customer, DataOperation::Updated, target_id);
+    Ok(merged)
 }
 
 /// Add mock customer data for testing
@@ -477,3 +766,113 @@ pub fn add_mock_customers(db: State<Database>) -> Result<String, String> {
     log::info!("Added {} mock customers", inserted);
     Ok(format!("Successfully added {} mock customers", inserted))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    fn input(name: &str, phone: &str) -> CreateCustomerInput {
+        CreateCustomerInput {
+            name: name.to_string(),
+            email: None,
+            phone: Some(phone.to_string()),
+            address: None,
+            place: None,
+            state: None,
+            district: None,
+            town: None,
+            credit_limit: None,
+        }
+    }
+
+    #[test]
+    fn test_delete_archives_customers_with_history() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+
+        let regular = create_customer_internal(&conn, input("Asha", "9876543210")).unwrap();
+        let walk_in = create_customer_internal(&conn, input("Ravi", "9123456780")).unwrap();
+        conn.execute(
+            "INSERT INTO invoices (invoice_number, customer_id, total_amount) VALUES ('INV-1', ?1, 100)",
+            [regular.id],
+        )
+        .unwrap();
+
+        delete_customer_internal(&mut conn, regular.id, Some("admin".to_string())).unwrap();
+        delete_customer_internal(&mut conn, walk_in.id, None).unwrap();
+
+        // The invoice keeps its customer; the customer without history went to trash
+        assert!(load_customer(&conn, regular.id).unwrap().is_archived);
+        let invoice_customer: Option<i32> = conn
+            .query_row("SELECT customer_id FROM invoices WHERE invoice_number = 'INV-1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(invoice_customer, Some(regular.id));
+        assert!(load_customer(&conn, walk_in.id).is_err());
+        let trashed: Vec<i32> = conn
+            .prepare("SELECT entity_id FROM deleted_items WHERE entity_type = 'customer'")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(trashed, vec![walk_in.id]);
+
+        let err = create_customer_internal(&conn, input("Asha K", "9876543210")).unwrap_err();
+        let details = err.strip_prefix(ARCHIVED_CUSTOMER_EXISTS).expect("structured archived customer error");
+        let conflict: ArchivedCustomerConflict = serde_json::from_str(details).unwrap();
+        assert_eq!(conflict.customer_id, regular.id);
+
+        let restored = unarchive_customer_internal(&conn, regular.id, None).unwrap();
+        assert!(!restored.is_archived);
+        assert!(unarchive_customer_internal(&conn, regular.id, None).is_err());
+        assert!(create_customer_internal(&conn, input("Ravi", "9123456780")).is_ok());
+    }
+
+    #[test]
+    fn test_get_customers_sorts_and_stats() {
+        use crate::commands::customer_payments::get_customer_credit_summary_internal;
+
+        let db = TestDb::new();
+        let conn = db.conn();
+        let asha = create_customer_internal(&conn, input("Asha", "9876543210")).unwrap();
+        let ravi = create_customer_internal(&conn, input("Ravi", "9123456780")).unwrap();
+        let meena = create_customer_internal(&conn, input("Meena", "9988776655")).unwrap();
+        conn.execute_batch(&format!(
+            "INSERT INTO invoices (id, invoice_number, customer_id, total_amount, credit_amount, initial_paid, payment_method, created_at) VALUES
+                (1, 'INV-1', {ravi}, 500, 300, 200, 'Credit', '2024-01-10T10:00:00+00:00'),
+                (2, 'INV-2', {asha}, 100, 0, 0, 'Cash', '2024-03-01T10:00:00+00:00'),
+                (3, 'INV-3', {asha}, 50, 0, 0, 'Cash', '2024-02-01T10:00:00+00:00');
+             INSERT INTO customer_payments (customer_id, invoice_id, amount) VALUES ({ravi}, 1, 200), ({ravi}, 1, 50);",
+            ravi = ravi.id,
+            asha = asha.id
+        ))
+        .unwrap();
+
+        let ids = |sort_by: Option<&str>| -> Vec<i32> {
+            get_customers_internal(&conn, None, 1, 10, false, sort_by, false)
+                .unwrap()
+                .items
+                .iter()
+                .map(|row| row.customer.id)
+                .collect()
+        };
+        assert_eq!(ids(None), vec![asha.id, meena.id, ravi.id]);
+        assert_eq!(ids(Some("recent")), vec![asha.id, ravi.id, meena.id]);
+        assert_eq!(ids(Some("outstanding")), vec![ravi.id, asha.id, meena.id]);
+        assert!(get_customers_internal(&conn, None, 1, 10, false, Some("spend"), false).is_err());
+
+        let plain = get_customers_internal(&conn, Some("ravi"), 1, 10, false, None, false).unwrap();
+        assert_eq!(plain.total_count, 1);
+        assert_eq!((plain.items[0].invoice_count, plain.items[0].outstanding_balance), (None, None));
+
+        let with_stats = get_customers_internal(&conn, Some("9123"), 1, 10, false, None, true).unwrap();
+        let row = &with_stats.items[0];
+        assert_eq!(row.invoice_count, Some(1));
+        assert_eq!(row.last_billed.as_deref(), Some("2024-01-10T10:00:00+00:00"));
+        assert_eq!(row.total_spent, Some(500.0));
+        let pending = get_customer_credit_summary_internal(&conn, ravi.id).unwrap().pending_amount;
+        assert_eq!(row.outstanding_balance, Some(pending));
+        assert_eq!(pending, 250.0);
+    }
+}
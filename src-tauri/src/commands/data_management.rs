@@ -1,8 +1,23 @@
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 use crate::db::Database;
-use crate::commands::{get_products, get_customers, get_suppliers};
+use crate::commands::maintenance::{MaintenanceOperation, MaintenanceState};
+use crate::commands::{get_products, get_customers, get_suppliers, PaginatedResult, MAX_PAGE_SIZE};
+use crate::commands::categories::resolve_category;
+use crate::commands::csv_import::{update_contact_from_mapped_row, update_product_from_mapped_row, MappedRow};
+use crate::commands::safety_snapshots::{take_safety_snapshot, LARGE_IMPORT_ROWS};
+use crate::services::events::{emit_bulk_data_changed, DataEntity, DataOperation};
+use rusqlite::OptionalExtension;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Emit export progress every N rows
+pub(crate) const EXPORT_PROGRESS_INTERVAL: usize = 500;
+/// An import whose last chunk is older than this is treated as abandoned and stops holding
+/// the maintenance lock
+const IMPORT_STALE_AFTER: Duration = Duration::from_secs(120);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InsertedItem {
@@ -30,11 +45,134 @@ pub struct ScanResult {
 pub struct ImportResult {
     pub processed: i32,
     pub success: i32,
+    pub updated: i32,
+    pub skipped: i32,
     pub errors: Vec<String>,
     pub duplicate_found: bool,
     pub added_items: Vec<InsertedItem>,
 }
 
+/// Progress info emitted on "data-transfer-progress" during CSV import/export
+#[derive(Debug, Clone, Serialize)]
+pub struct DataTransferProgress {
+    pub operation: String, // "import" or "export"
+    pub entity_type: String,
+    pub processed: i32,
+    pub total: i32,
+    pub percentage: f32,
+}
+
+/// Summary emitted on "data-transfer-complete" when an import or export finishes
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DataTransferSummary {
+    pub operation: String,
+    pub entity_type: String,
+    pub total: i32,
+    pub inserted: i32,
+    pub updated: i32,
+    pub skipped: i32,
+    pub failed: i32,
+    pub skip_reason: Option<String>, // e.g. "duplicate SKU"
+}
+
+/// State for cancelling long-running CSV operations between chunks
+#[derive(Default)]
+pub struct DataOperationState {
+    cancelled: AtomicBool,
+    import_summary: Mutex<DataTransferSummary>,
+}
+
+impl DataOperationState {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Clear a cancellation left over from an earlier operation before starting an export
+    pub(crate) fn reset_cancelled(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
+    /// Reset cancellation and the running summary when the first chunk of an import arrives
+    pub(crate) fn begin_import(&self, entity_type: &str, total_rows: i32, skip_reason: Option<String>) -> Result<(), String> {
+        self.cancelled.store(false, Ordering::SeqCst);
+        let mut summary = self.import_summary.lock().map_err(|e| e.to_string())?;
+        *summary = DataTransferSummary {
+            operation: "import".to_string(),
+            entity_type: entity_type.to_string(),
+            total: total_rows,
+            skip_reason,
+            ..Default::default()
+        };
+        Ok(())
+    }
+
+    /// Add one chunk's counts to the running summary, emit progress, and emit
+    /// "data-transfer-complete" once `rows_done` reaches the summary total.
+    /// The maintenance lock stays held between chunks until the import finishes.
+    pub(crate) fn record_import_chunk(&self, app: &AppHandle, rows_done: i32, chunk: &DataTransferSummary) -> Result<(), String> {
+        let mut summary = self.import_summary.lock().map_err(|e| e.to_string())?;
+        summary.inserted += chunk.inserted;
+        summary.updated += chunk.updated;
+        summary.skipped += chunk.skipped;
+        summary.failed += chunk.failed;
+
+        emit_progress(app, "import", &summary.entity_type, rows_done, summary.total);
+
+        let finished = rows_done >= summary.total;
+        let maintenance = app.state::<MaintenanceState>();
+        if finished {
+            maintenance.end_session(MaintenanceOperation::CsvImport);
+        } else {
+            maintenance.hold_session(MaintenanceOperation::CsvImport, IMPORT_STALE_AFTER);
+        }
+
+        if finished {
+            log::info!(
+                "CSV import finished: {} inserted, {} updated, {} skipped, {} failed",
+                summary.inserted,
+                summary.updated,
+                summary.skipped,
+                summary.failed
+            );
+            let _ = app.emit("data-transfer-complete", summary.clone());
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn emit_progress(app: &AppHandle, operation: &str, entity_type: &str, processed: i32, total: i32) {
+    let progress = DataTransferProgress {
+        operation: operation.to_string(),
+        entity_type: entity_type.to_string(),
+        processed,
+        total,
+        percentage: if total > 0 { (processed as f32 / total as f32) * 100.0 } else { 100.0 },
+    };
+    let _ = app.emit("data-transfer-progress", progress);
+}
+
+fn duplicate_reason(entity_type: &str) -> Option<String> {
+    match entity_type {
+        "customer" => Some("duplicate phone or name".to_string()),
+        "inventory" => Some("duplicate SKU".to_string()),
+        "supplier" => Some("duplicate name".to_string()),
+        _ => None,
+    }
+}
+
+/// Request cancellation of the running CSV import/export.
+/// Import rolls back the chunk in progress; export stops without returning data.
+#[tauri::command]
+pub fn cancel_data_operation(
+    ops: State<DataOperationState>,
+    maintenance: State<MaintenanceState>,
+) -> Result<(), String> {
+    log::info!("cancel_data_operation called");
+    ops.cancelled.store(true, Ordering::SeqCst);
+    maintenance.end_session(MaintenanceOperation::CsvImport);
+    Ok(())
+}
+
 /// Pre-scan ALL rows to identify duplicates before import
 #[tauri::command]
 pub fn scan_duplicates(
@@ -49,22 +187,7 @@ pub fn scan_duplicates(
     let mut duplicates: Vec<DuplicateItem> = Vec::new();
     
     for (index, row) in data.iter().enumerate() {
-        let is_dup = match entity_type.as_str() {
-            "customer" => check_customer_duplicate(
-                row.get("phone").map(|s| s.as_str()), 
-                row.get("name").map(|s| s.as_str()), 
-                &conn
-            )?,
-            "inventory" => check_product_duplicate(
-                row.get("sku").map(|s| s.as_str()), 
-                &conn
-            )?,
-            "supplier" => check_supplier_duplicate(
-                row.get("name").map(|s| s.as_str()), 
-                &conn
-            )?,
-            _ => false,
-        };
+        let is_dup = find_duplicate(&entity_type, row, &conn)?.is_some();
         
         if is_dup {
             duplicate_count += 1;
@@ -94,42 +217,95 @@ pub fn scan_duplicates(
 
 
 #[tauri::command]
-pub fn export_csv(entity_type: String, db: State<Database>) -> Result<String, String> {
+pub async fn export_csv(
+    entity_type: String,
+    app: AppHandle,
+    ops: State<'_, DataOperationState>,
+    db: State<'_, Database>,
+) -> Result<String, String> {
+    log::info!("export_csv called for entity_type: {}", entity_type);
+
+    ops.reset_cancelled();
     let mut wtr = csv::Writer::from_writer(vec![]);
 
-    match entity_type.as_str() {
+    let total = match entity_type.as_str() {
         "customer" => {
-            let result = get_customers(None, 1, 1000000, db.clone())?;
-            for item in result.items {
-                let export_item = ExportCustomer::from(item.customer);
-                wtr.serialize(export_item).map_err(|e| e.to_string())?;
-            }
+            let items = fetch_all_pages(|page, page_size| get_customers(None, page, page_size, Some(true), None, None, db.clone()))?;
+            let rows = items.into_iter().map(|item| ExportCustomer::from(item.customer)).collect();
+            write_export_rows(&mut wtr, rows, &entity_type, &app, &ops)?
         },
         "inventory" => {
-            let result = get_products(None, 1, 1000000, db.clone())?;
-            for item in result.items {
-                 let export_item = ExportProduct::from(item);
-                wtr.serialize(export_item).map_err(|e| e.to_string())?;
-            }
+            let items = fetch_all_pages(|page, page_size| get_products(None, page, page_size, None, None, None, db.clone()))?;
+            let rows = items.into_iter().map(ExportProduct::from).collect();
+            write_export_rows(&mut wtr, rows, &entity_type, &app, &ops)?
         },
         "supplier" => {
-            let result = get_suppliers(None, 1, 1000000, db.clone())?;
-            for item in result.items {
-                let export_item = ExportSupplier::from(item);
-                wtr.serialize(export_item).map_err(|e| e.to_string())?;
-            }
+            let items = fetch_all_pages(|page, page_size| get_suppliers(None, page, page_size, db.clone()))?;
+            let rows = items.into_iter().map(ExportSupplier::from).collect();
+            write_export_rows(&mut wtr, rows, &entity_type, &app, &ops)?
         },
         _ => return Err(format!("Unknown entity type: {}", entity_type)),
-    }
+    };
 
     let data = String::from_utf8(wtr.into_inner().map_err(|e| e.to_string())?)
         .map_err(|e| e.to_string())?;
-        
+
+    let _ = app.emit("data-transfer-complete", DataTransferSummary {
+        operation: "export".to_string(),
+        entity_type: entity_type.clone(),
+        total,
+        inserted: total,
+        ..Default::default()
+    });
+
     Ok(data)
 }
 
+/// Collect every row of a paginated command, one max-size page at a time
+fn fetch_all_pages<T>(
+    mut fetch: impl FnMut(i32, i32) -> Result<PaginatedResult<T>, String>,
+) -> Result<Vec<T>, String> {
+    let mut items = Vec::new();
+    for page in 1.. {
+        let result = fetch(page, MAX_PAGE_SIZE)?;
+        let fetched = result.items.len();
+        items.extend(result.items);
+        if fetched == 0 || items.len() as i64 >= result.total_count {
+            break;
+        }
+    }
+    Ok(items)
+}
+
+/// Serialize export rows, emitting progress and honouring cancellation
+fn write_export_rows<T: Serialize>(
+    wtr: &mut csv::Writer<Vec<u8>>,
+    rows: Vec<T>,
+    entity_type: &str,
+    app: &AppHandle,
+    ops: &DataOperationState,
+) -> Result<i32, String> {
+    let total = rows.len() as i32;
+    emit_progress(app, "export", entity_type, 0, total);
+
+    for (index, row) in rows.into_iter().enumerate() {
+        if ops.is_cancelled() {
+            return Err("Export cancelled".to_string());
+        }
+        wtr.serialize(row).map_err(|e| e.to_string())?;
+
+        let written = index + 1;
+        if written % EXPORT_PROGRESS_INTERVAL == 0 {
+            emit_progress(app, "export", entity_type, written as i32, total);
+        }
+    }
+
+    emit_progress(app, "export", entity_type, total, total);
+    Ok(total)
+}
+
 // Helper to convert UTC string to IST string
-fn to_ist(date_str: &str) -> String {
+pub(crate) fn to_ist(date_str: &str) -> String {
     use chrono::{DateTime, FixedOffset, NaiveDateTime, Utc};
     
     // Try parsing as RFC3339 (e.g., 2023-10-27T10:00:00Z)
@@ -163,6 +339,8 @@ struct ExportCustomer {
     town: Option<String>,
     created_at: String, // IST
     updated_at: String, // IST
+    image_path: Option<String>,
+    credit_limit: Option<f64>,
 }
 
 impl From<crate::db::Customer> for ExportCustomer {
@@ -179,6 +357,8 @@ impl From<crate::db::Customer> for ExportCustomer {
             town: c.town,
             created_at: to_ist(&c.created_at),
             updated_at: to_ist(&c.updated_at),
+            image_path: c.image_path,
+            credit_limit: c.credit_limit,
         }
     }
 }
@@ -190,8 +370,8 @@ struct ExportProduct {
     sku: String,
     price: f64,
     selling_price: Option<f64>,
-    initial_stock: Option<i32>,
-    stock_quantity: i32,
+    initial_stock: Option<f64>,
+    stock_quantity: f64,
     supplier_id: Option<i32>,
     category: Option<String>,
     created_at: String, // IST
@@ -250,14 +430,52 @@ impl From<crate::db::Supplier> for ExportSupplier {
 }
 
 
+/// Import one chunk of CSV rows in its own transaction.
+/// `row_offset` / `total_rows` describe where this chunk sits in the overall import so
+/// progress and the final summary cover the whole file, not just this chunk.
+/// Rows matching an existing record are skipped, or overwrite it when `update_existing` is set.
 #[tauri::command]
-pub fn import_csv_chunk(
+pub async fn import_csv_chunk(
     entity_type: String,
     data: Vec<HashMap<String, String>>,
-    db: State<Database>
+    row_offset: Option<i32>,
+    total_rows: Option<i32>,
+    update_existing: Option<bool>,
+    app: AppHandle,
+    db: State<'_, Database>,
 ) -> Result<ImportResult, String> {
+    let row_offset = row_offset.unwrap_or(0);
+    let total_rows = total_rows.unwrap_or(row_offset + data.len() as i32);
+    let update_existing = update_existing.unwrap_or(false);
+    log::info!(
+        "import_csv_chunk called for entity_type: {}, rows {}-{} of {}",
+        entity_type,
+        row_offset + 1,
+        row_offset + data.len() as i32,
+        total_rows
+    );
+
+    let ops = app.state::<DataOperationState>();
+    let maintenance = app.state::<MaintenanceState>();
+    let _lock = maintenance.acquire(MaintenanceOperation::CsvImport)?;
+
+    // First chunk starts a fresh import
+    if row_offset == 0 {
+        if total_rows >= LARGE_IMPORT_ROWS as i32 {
+            let conn = db.get_conn()?;
+            take_safety_snapshot(&conn, &format!("import_csv_{}", entity_type))?;
+        }
+        ops.begin_import(&entity_type, total_rows, duplicate_reason(&entity_type))?;
+    }
+
+    if ops.is_cancelled() {
+        return Err("Import cancelled".to_string());
+    }
+
     let mut processed = 0;
     let mut success = 0;
+    let mut updated = 0;
+    let mut skipped = 0;
     let mut errors = Vec::new();
     let mut added_items: Vec<InsertedItem> = Vec::new();
 
@@ -268,18 +486,24 @@ pub fn import_csv_chunk(
         .map_err(|e| e.to_string())?;
 
     for row in data {
+        if ops.is_cancelled() {
+            conn.execute("ROLLBACK", []).ok();
+            log::info!("Import cancelled, rolled back chunk starting at row {}", row_offset + 1);
+            return Err("Import cancelled".to_string());
+        }
+
         processed += 1;
         
-        // Always check and skip duplicates
-        let is_dup = match entity_type.as_str() {
-            "customer" => check_customer_duplicate(row.get("phone").map(|s| s.as_str()), row.get("name").map(|s| s.as_str()), &conn)?,
-            "inventory" => check_product_duplicate(row.get("sku").map(|s| s.as_str()), &conn)?,
-            "supplier" => check_supplier_duplicate(row.get("name").map(|s| s.as_str()), &conn)?,
-            _ => false,
-        };
-
-        // Skip duplicates - never add them
-        if is_dup {
+        // Duplicates are never added again; they are skipped, or updated with update_existing
+        if let Some(existing_id) = find_duplicate(&entity_type, &row, &conn)? {
+            if !update_existing {
+                skipped += 1;
+                continue;
+            }
+            match update_duplicate_row(&entity_type, existing_id, &row, &conn) {
+                Ok(()) => updated += 1,
+                Err(e) => errors.push(format!("Row {}: {}", row_offset + processed, e)),
+            }
             continue;
         }
 
@@ -304,69 +528,111 @@ pub fn import_csv_chunk(
                 added_items.push(InsertedItem { id: last_id, name, identifier });
             },
             Err(e) => {
-                errors.push(format!("Row {}: {}", processed, e));
+                errors.push(format!("Row {}: {}", row_offset + processed, e));
             },
         }
     }
 
     conn.execute("COMMIT", []).map_err(|e| e.to_string())?;
 
+    let data_entity = match entity_type.as_str() {
+        "customer" => Some(DataEntity::Customer),
+        "inventory" => Some(DataEntity::Product),
+        "supplier" => Some(DataEntity::Supplier),
+        _ => None,
+    };
+    if let Some(entity) = data_entity {
+        emit_bulk_data_changed(&app, entity, DataOperation::Imported, (success + updated) as usize);
+    }
+
+    ops.record_import_chunk(
+        &app,
+        row_offset + processed,
+        &DataTransferSummary {
+            inserted: success,
+            updated,
+            skipped,
+            failed: errors.len() as i32,
+            ..Default::default()
+        },
+    )?;
 
     Ok(ImportResult {
         processed,
         success,
+        updated,
+        skipped,
         errors,
-        duplicate_found: false,
+        duplicate_found: skipped > 0,
         added_items,
     })
 }
 
 // Check Helpers
-fn check_customer_duplicate(phone: Option<&str>, name: Option<&str>, conn: &rusqlite::Connection) -> Result<bool, String> {
-    if let Some(p) = phone {
-        if !p.is_empty() {
-             let count: i32 = conn.query_row(
-                "SELECT COUNT(*) FROM customers WHERE phone = ?",
-                [p],
-                |row| row.get(0)
-            ).unwrap_or(0);
-            if count > 0 { return Ok(true); }
+/// Id of the customer a row duplicates: same phone, or same name (case-insensitive)
+fn find_customer_duplicate(phone: Option<&str>, name: Option<&str>, conn: &rusqlite::Connection) -> Result<Option<i32>, String> {
+    if let Some(p) = phone.filter(|p| !p.is_empty()) {
+        let id = conn
+            .query_row("SELECT id FROM customers WHERE phone = ? ORDER BY id LIMIT 1", [p], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if id.is_some() {
+            return Ok(id);
         }
     }
-    // Optional: Check name if phone empty?
-    if let Some(n) = name {
-         let count: i32 = conn.query_row(
-            "SELECT COUNT(*) FROM customers WHERE name = ? COLLATE NOCASE",
-            [n],
-            |row| row.get(0)
-        ).unwrap_or(0);
-        if count > 0 { return Ok(true); }
+    match name {
+        Some(n) => conn
+            .query_row("SELECT id FROM customers WHERE name = ? COLLATE NOCASE ORDER BY id LIMIT 1", [n], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Id of the product with the row's SKU
+fn find_product_duplicate(sku: Option<&str>, conn: &rusqlite::Connection) -> Result<Option<i32>, String> {
+    match sku {
+        Some(s) => conn
+            .query_row("SELECT id FROM products WHERE sku = ? ORDER BY id LIMIT 1", [s], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Id of the supplier with the row's name (case-insensitive)
+fn find_supplier_duplicate(name: Option<&str>, conn: &rusqlite::Connection) -> Result<Option<i32>, String> {
+    match name {
+        Some(n) => conn
+            .query_row("SELECT id FROM suppliers WHERE name = ? COLLATE NOCASE ORDER BY id LIMIT 1", [n], |row| row.get(0))
+            .optional()
+            .map_err(|e| e.to_string()),
+        None => Ok(None),
     }
-    Ok(false)
 }
 
-fn check_product_duplicate(sku: Option<&str>, conn: &rusqlite::Connection) -> Result<bool, String> {
-    if let Some(s) = sku {
-         let count: i32 = conn.query_row(
-            "SELECT COUNT(*) FROM products WHERE sku = ?",
-            [s],
-            |row| row.get(0)
-        ).unwrap_or(0);
-        if count > 0 { return Ok(true); }
+/// Id of the existing record an import row duplicates, if any
+fn find_duplicate(entity_type: &str, row: &HashMap<String, String>, conn: &rusqlite::Connection) -> Result<Option<i32>, String> {
+    let field = |name: &str| row.get(name).map(|s| s.as_str());
+    match entity_type {
+        "customer" => find_customer_duplicate(field("phone"), field("name"), conn),
+        "inventory" => find_product_duplicate(field("sku"), conn),
+        "supplier" => find_supplier_duplicate(field("name"), conn),
+        _ => Ok(None),
     }
-    Ok(false)
 }
 
-fn check_supplier_duplicate(name: Option<&str>, conn: &rusqlite::Connection) -> Result<bool, String> {
-    if let Some(n) = name {
-         let count: i32 = conn.query_row(
-            "SELECT COUNT(*) FROM suppliers WHERE name = ? COLLATE NOCASE",
-            [n],
-            |row| row.get(0)
-        ).unwrap_or(0);
-        if count > 0 { return Ok(true); }
+/// Overwrite a duplicated record with the row's non-empty values, the same way the guided
+/// import's Update policy does (changes are logged to entity_modifications)
+fn update_duplicate_row(entity_type: &str, id: i32, row: &HashMap<String, String>, conn: &rusqlite::Connection) -> Result<(), String> {
+    let (fields, values): (Vec<String>, Vec<String>) = row.iter().map(|(k, v)| (k.clone(), v.clone())).unzip();
+    let columns: HashMap<String, usize> = fields.into_iter().enumerate().map(|(i, field)| (field, i)).collect();
+    let mapped = MappedRow::new(&values, &columns);
+    match entity_type {
+        "inventory" => update_product_from_mapped_row(conn, id, &mapped),
+        "customer" | "supplier" => update_contact_from_mapped_row(conn, entity_type, id, &mapped),
+        _ => Err("Unknown entity type".to_string()),
     }
-    Ok(false)
 }
 
 
@@ -410,11 +676,11 @@ fn import_product_row(row: &HashMap<String, String>, conn: &rusqlite::Connection
         .and_then(|s| s.parse().ok())
         .ok_or("Invalid or missing selling_price")?;
         
-    let initial_stock: i32 = row.get("initial_stock")
+    let initial_stock: f64 = row.get("initial_stock")
         .and_then(|s| s.parse().ok())
         .ok_or("Invalid or missing initial_stock")?;
         
-    let stock_quantity: i32 = row.get("stock_quantity")
+    let stock_quantity: f64 = row.get("stock_quantity")
         .and_then(|s| s.parse().ok())
         .ok_or("Invalid or missing stock_quantity")?;
 
@@ -422,13 +688,13 @@ fn import_product_row(row: &HashMap<String, String>, conn: &rusqlite::Connection
     let supplier_id: Option<i32> = row.get("supplier_id")
         .filter(|s| !s.is_empty())
         .and_then(|s| s.parse().ok());
-    let category = row.get("category").filter(|s| !s.is_empty()).cloned();
+    let (category_id, category) = resolve_category(conn, row.get("category").map(String::as_str))?;
 
     let now = chrono::Utc::now().to_rfc3339();
 
     conn.execute(
-        "INSERT INTO products (name, sku, price, selling_price, stock_quantity, initial_stock, supplier_id, category, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        rusqlite::params![&name, &sku, price, selling_price, stock_quantity, initial_stock, &supplier_id, &category, &now, &now],
+        "INSERT INTO products (name, sku, price, selling_price, stock_quantity, initial_stock, supplier_id, category, category_id, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        rusqlite::params![&name, &sku, price, selling_price, stock_quantity, initial_stock, &supplier_id, &category, category_id, &now, &now],
     ).map_err(|e| format!("Failed to insert product: {}", e))?;
 
     Ok(())
@@ -0,0 +1,383 @@
+/// Day Closures
+/// Closing a business day takes a Z-report of it: the day's invoice count and revenue by payment
+/// method, cash collected from credit customers and cash expenses, which give the cash the drawer
+/// should hold. The counted cash and the difference are stored with that snapshot in day_closures.
+///
+/// Invoices dated in a closed day can't be edited or deleted unless a manager approves the change
+/// (`closed_day_override`); each approved change is logged against the invoice in
+/// entity_modifications as a closed_day_override.
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::analytics::{
+    business_offset_minutes, business_today, parse_report_date, payment_method_breakdown, PaymentMethodBreakdown,
+    ReportRange,
+};
+use crate::commands::{validate_pagination, PaginatedResult};
+use crate::db::Database;
+use crate::services::money;
+
+/// Prefix of the error returned when a change touches an invoice in a closed day; the rest of
+/// the message is the business date ("YYYY-MM-DD")
+pub const DAY_CLOSED: &str = "DAY_CLOSED:";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DayClosure {
+    pub id: i64,
+    /// Local business day, "YYYY-MM-DD"
+    pub business_date: String,
+    pub invoice_count: i64,
+    pub revenue: f64,
+    pub payment_methods: Vec<PaymentMethodBreakdown>,
+    /// Invoices paid in cash
+    pub cash_sales: f64,
+    /// Cash received from credit customers, including amounts paid up front on credit invoices
+    pub credit_collections: f64,
+    /// Expenses paid in cash
+    pub cash_expenses: f64,
+    pub expected_cash: f64,
+    pub counted_cash: f64,
+    /// Counted minus expected; negative when the drawer is short
+    pub difference: f64,
+    pub notes: Option<String>,
+    pub closed_by: Option<String>,
+    pub closed_at: String,
+}
+
+const CLOSURE_SELECT: &str = "SELECT id, business_date, invoice_count, revenue, payment_methods, cash_sales, credit_collections,
+            cash_expenses, expected_cash, counted_cash, difference, notes, closed_by, closed_at
+     FROM day_closures";
+
+fn closure_from_row(row: &rusqlite::Row) -> rusqlite::Result<DayClosure> {
+    let payment_methods: String = row.get(4)?;
+    Ok(DayClosure {
+        id: row.get(0)?,
+        business_date: row.get(1)?,
+        invoice_count: row.get(2)?,
+        revenue: row.get(3)?,
+        payment_methods: serde_json::from_str(&payment_methods).unwrap_or_default(),
+        cash_sales: row.get(5)?,
+        credit_collections: row.get(6)?,
+        cash_expenses: row.get(7)?,
+        expected_cash: row.get(8)?,
+        counted_cash: row.get(9)?,
+        difference: row.get(10)?,
+        notes: row.get(11)?,
+        closed_by: row.get(12)?,
+        closed_at: row.get(13)?,
+    })
+}
+
+/// Cash the drawer should hold at the end of `day`, as (cash sales, credit collections, cash expenses).
+/// Cash sales are the cash part of split payments, or whole invoices paid in cash; what credit
+/// invoices took at the counter is recorded as a customer payment and counts as a collection.
+fn cash_totals(conn: &Connection, day: NaiveDate, range: &ReportRange) -> Result<(f64, f64, f64), String> {
+    let cash_sales: f64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(
+                 CASE WHEN EXISTS (SELECT 1 FROM invoice_tenders t WHERE t.invoice_id = i.id)
+                      THEN (SELECT COALESCE(SUM(t.amount), 0) FROM invoice_tenders t
+                            WHERE t.invoice_id = i.id AND t.method = 'Cash' COLLATE NOCASE)
+                      WHEN i.payment_method = 'Cash' COLLATE NOCASE THEN i.total_amount
+                      ELSE 0 END), 0.0)
+             FROM invoices i
+             WHERE datetime(i.created_at) >= ?1 AND datetime(i.created_at) < ?2
+               AND COALESCE(i.credit_amount, 0) = 0",
+            [&range.start_utc, &range.end_utc],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to calculate cash sales: {}", e))?;
+
+    let credit_collections: f64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(amount), 0.0) FROM customer_payments
+             WHERE datetime(paid_at) >= ?1 AND datetime(paid_at) < ?2
+               AND payment_method = 'Cash' COLLATE NOCASE",
+            [&range.start_utc, &range.end_utc],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to calculate credit collections: {}", e))?;
+
+    let cash_expenses: f64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(amount), 0.0) FROM expenses
+             WHERE expense_date = ?1 AND payment_method = 'Cash' COLLATE NOCASE",
+            [day.format("%Y-%m-%d").to_string()],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to calculate cash expenses: {}", e))?;
+
+    Ok((
+        money::round_money(cash_sales),
+        money::round_money(credit_collections),
+        money::round_money(cash_expenses),
+    ))
+}
+
+/// Close a business day: take its Z-report, record the counted cash and lock its invoices
+#[tauri::command]
+pub fn close_business_day(
+    date: String,
+    counted_cash: f64,
+    notes: Option<String>,
+    closed_by: Option<String>,
+    db: State<Database>,
+) -> Result<DayClosure, String> {
+    log::info!("close_business_day called for {} with counted cash {}", date, counted_cash);
+
+    let conn = db.get_conn()?;
+    close_business_day_internal(&conn, &date, counted_cash, notes.as_deref(), closed_by.as_deref())
+}
+
+pub(crate) fn close_business_day_internal(
+    conn: &Connection,
+    date: &str,
+    counted_cash: f64,
+    notes: Option<&str>,
+    closed_by: Option<&str>,
+) -> Result<DayClosure, String> {
+    let day = parse_report_date(date)?;
+    if day > business_today(conn) {
+        return Err(format!("Validation error: {} hasn't started yet", day));
+    }
+    if !counted_cash.is_finite() || counted_cash < 0.0 {
+        return Err("Validation error: counted cash can't be negative".to_string());
+    }
+    let business_date = day.format("%Y-%m-%d").to_string();
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let already_closed: bool = tx
+        .query_row("SELECT EXISTS(SELECT 1 FROM day_closures WHERE business_date = ?1)", [&business_date], |row| row.get(0))
+        .map_err(|e| format!("Failed to check day closures: {}", e))?;
+    if already_closed {
+        return Err(format!("Business day {} is already closed", business_date));
+    }
+
+    let range = ReportRange::new(day, day, business_offset_minutes(&tx));
+    let payment_methods = payment_method_breakdown(&tx, &range)?;
+    // A split invoice counts under each of its methods, so count invoices on their own
+    let invoice_count: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM invoices WHERE datetime(created_at) >= ?1 AND datetime(created_at) < ?2",
+            [&range.start_utc, &range.end_utc],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count invoices: {}", e))?;
+    let revenue = money::sum(payment_methods.iter().map(|method| method.total_amount));
+    let (cash_sales, credit_collections, cash_expenses) = cash_totals(&tx, day, &range)?;
+    let expected_cash = money::sub(money::sum([cash_sales, credit_collections]), cash_expenses);
+    let counted_cash = money::round_money(counted_cash);
+    let difference = money::sub(counted_cash, expected_cash);
+
+    let methods_json = serde_json::to_string(&payment_methods).map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO day_closures (business_date, invoice_count, revenue, payment_methods, cash_sales, credit_collections,
+                                   cash_expenses, expected_cash, counted_cash, difference, notes, closed_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            business_date,
+            invoice_count,
+            revenue,
+            methods_json,
+            cash_sales,
+            credit_collections,
+            cash_expenses,
+            expected_cash,
+            counted_cash,
+            difference,
+            notes.map(str::trim).filter(|n| !n.is_empty()),
+            closed_by,
+        ],
+    )
+    .map_err(|e| format!("Failed to close business day: {}", e))?;
+
+    let closure = tx
+        .query_row(&format!("{} WHERE id = ?1", CLOSURE_SELECT), [tx.last_insert_rowid()], closure_from_row)
+        .map_err(|e| format!("Failed to load day closure: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!(
+        "Closed business day {}: expected cash {:.2}, counted {:.2}, difference {:.2}",
+        business_date,
+        expected_cash,
+        counted_cash,
+        difference
+    );
+    Ok(closure)
+}
+
+/// The closure of a business day, if it has been closed
+#[tauri::command]
+pub fn get_day_closure(date: String, db: State<Database>) -> Result<Option<DayClosure>, String> {
+    log::info!("get_day_closure called for {}", date);
+
+    let conn = db.get_conn()?;
+    let business_date = parse_report_date(&date)?.format("%Y-%m-%d").to_string();
+    conn.query_row(&format!("{} WHERE business_date = ?1", CLOSURE_SELECT), [business_date], closure_from_row)
+        .optional()
+        .map_err(|e| format!("Failed to load day closure: {}", e))
+}
+
+/// Closed business days, most recent first
+#[tauri::command]
+pub fn get_closure_history(page: i32, page_size: i32, db: State<Database>) -> Result<PaginatedResult<DayClosure>, String> {
+    log::info!("get_closure_history called: page {}, page_size {}", page, page_size);
+
+    let conn = db.get_conn()?;
+    get_closure_history_internal(&conn, page, page_size)
+}
+
+pub(crate) fn get_closure_history_internal(
+    conn: &Connection,
+    page: i32,
+    page_size: i32,
+) -> Result<PaginatedResult<DayClosure>, String> {
+    let (limit, offset) = validate_pagination(page, page_size)?;
+
+    let total_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM day_closures", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to count day closures: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(&format!("{} ORDER BY business_date DESC LIMIT ?1 OFFSET ?2", CLOSURE_SELECT))
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    let items = stmt
+        .query_map([limit, offset], closure_from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load day closures: {}", e))?;
+
+    Ok(PaginatedResult { items, total_count })
+}
+
+/// Fail with DAY_CLOSED when `timestamp` (an invoice's created_at) falls in a closed business
+/// day, unless `override_by` names the manager approving the change; the approval is logged
+/// against the invoice. `change` describes what was done, e.g. "updated".
+pub(crate) fn ensure_day_open(
+    conn: &Connection,
+    invoice_id: i32,
+    timestamp: &str,
+    override_by: Option<&str>,
+    change: &str,
+) -> Result<(), String> {
+    let modifier = format!("{:+} minutes", business_offset_minutes(conn));
+    let closed_day: Option<String> = conn
+        .query_row(
+            "SELECT business_date FROM day_closures WHERE business_date = date(?1, ?2)",
+            params![timestamp, modifier],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to check day closures: {}", e))?;
+    let Some(business_date) = closed_day else {
+        return Ok(());
+    };
+
+    let approver = override_by.map(str::trim).filter(|name| !name.is_empty());
+    let Some(approver) = approver else {
+        return Err(format!("{}{}", DAY_CLOSED, business_date));
+    };
+
+    let details = serde_json::to_string(&[serde_json::json!({
+        "field": "closed_day",
+        "business_date": business_date,
+        "change": change,
+    })])
+    .unwrap_or_default();
+    conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by)
+         VALUES ('invoice', ?1, (SELECT invoice_number FROM invoices WHERE id = ?1), 'closed_day_override', ?2, ?3)",
+        params![invoice_id, details, approver],
+    )
+    .map_err(|e| format!("Failed to log closed day override: {}", e))?;
+
+    log::info!("Invoice {} in closed day {} {} with approval from {}", invoice_id, business_date, change, approver);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::invoices::{
+        create_invoice_internal, delete_invoice_internal, update_invoice_internal, CreateInvoiceInput, InvoiceTender,
+        UpdateInvoiceInput,
+    };
+    use crate::commands::products::create_product_internal;
+    use crate::test_support::{insert_customer, invoice_input, product_input, TestDb};
+
+    fn sale(product_id: i32, customer_id: Option<i32>, method: &str, initial_paid: Option<f64>) -> CreateInvoiceInput {
+        CreateInvoiceInput {
+            payment_method: Some(method.to_string()),
+            initial_paid,
+            ..invoice_input(customer_id, vec![(product_id, 1, 100.0)])
+        }
+    }
+
+    #[test]
+    fn test_closing_a_day_reconciles_cash_and_locks_its_invoices() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product_id = create_product_internal(&conn, product_input("KET-1", 60.0, 10, None)).unwrap().id;
+        let customer_id = insert_customer(&conn, "Asha");
+
+        let cash = create_invoice_internal(&mut conn, sale(product_id, None, "Cash", None)).unwrap();
+        let card = create_invoice_internal(&mut conn, sale(product_id, None, "Card", None)).unwrap();
+        create_invoice_internal(&mut conn, sale(product_id, Some(customer_id), "Credit", Some(30.0))).unwrap();
+        // Only the cash part of a split payment is expected in the drawer
+        let mut split = sale(product_id, None, "Cash", None);
+        split.payments = Some(vec![
+            InvoiceTender { method: "Cash".to_string(), amount: 40.0 },
+            InvoiceTender { method: "UPI".to_string(), amount: 60.0 },
+        ]);
+        create_invoice_internal(&mut conn, split).unwrap();
+        let today = business_today(&conn).format("%Y-%m-%d").to_string();
+        conn.execute(
+            "INSERT INTO expenses (expense_date, category, amount, payment_method) VALUES (?1, 'Tea', 12.0, 'cash')",
+            [&today],
+        )
+        .unwrap();
+
+        let expected = money::sub(money::sum([cash.total_amount, 40.0, 30.0]), 12.0);
+        let closure = close_business_day_internal(&conn, &today, expected - 5.0, Some("Short"), Some("owner")).unwrap();
+        assert_eq!(closure.invoice_count, 4);
+        assert_eq!(closure.payment_methods.len(), 4);
+        assert_eq!(closure.revenue, 400.0);
+        assert_eq!(closure.cash_sales, money::sum([cash.total_amount, 40.0]));
+        assert_eq!(closure.credit_collections, 30.0);
+        assert_eq!(closure.cash_expenses, 12.0);
+        assert_eq!(closure.expected_cash, expected);
+        assert_eq!(closure.difference, -5.0);
+        assert!(close_business_day_internal(&conn, &today, 0.0, None, None).is_err());
+        assert_eq!(get_closure_history_internal(&conn, 1, 20).unwrap().total_count, 1);
+
+        // Edits to the closed day need a manager's approval, which is logged
+        let mut update = UpdateInvoiceInput {
+            id: card.id,
+            customer_id: None,
+            payment_method: Some("UPI".to_string()),
+            created_at: None,
+            status: None,
+            modified_by: Some("cashier".to_string()),
+            closed_day_override: None,
+        };
+        let err = update_invoice_internal(&mut conn, &update).unwrap_err();
+        assert_eq!(err, format!("{}{}", DAY_CLOSED, today));
+        assert!(delete_invoice_internal(&mut conn, cash.id, None, None).unwrap_err().starts_with(DAY_CLOSED));
+
+        update.closed_day_override = Some("manager".to_string());
+        update_invoice_internal(&mut conn, &update).unwrap();
+        let approver: String = conn
+            .query_row(
+                "SELECT modified_by FROM entity_modifications WHERE entity_id = ?1 AND action = 'closed_day_override'",
+                [card.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(approver, "manager");
+    }
+}
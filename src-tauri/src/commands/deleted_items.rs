@@ -1,7 +1,22 @@
-use crate::db::{Database, Customer, Product, Supplier, Invoice};
+use crate::commands::invoices::InvoiceItemWithProduct;
+use crate::commands::{validate_pagination, PaginatedResult};
+use crate::commands::analytics::{business_offset_minutes, parse_report_date, ReportRange};
+use crate::db::{archive, Database, Customer, Product, Supplier, Invoice};
+use crate::commands::biometric::require_biometric_approval;
+use crate::commands::bundles;
+use crate::commands::safety_snapshots::{create_safety_snapshot, log_safety_snapshot, take_safety_snapshot};
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use crate::services::quantity;
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
 use serde_json;
-use tauri::State;
+use tauri::{AppHandle, State};
+
+/// app_settings key for how many days deleted items stay in trash (unset or "0" keeps them forever)
+const TRASH_RETENTION_KEY: &str = "trash_retention_days";
+/// app_settings key for how many days modification history is kept (unset or "0" keeps it forever)
+const MODIFICATIONS_RETENTION_KEY: &str = "modifications_retention_days";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DeletedItemDisplay {
@@ -16,19 +31,186 @@ pub struct DeletedItemDisplay {
     pub restore_notes: Option<String>,
 }
 
-/// Get all deleted items
+/// Build a WHERE clause selecting trash rows by entity type and/or minimum age
+fn trash_filter(entity_type: &Option<String>, older_than_days: Option<i64>) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+    let mut where_clauses = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    if let Some(entity_type) = entity_type {
+        where_clauses.push("entity_type = ?");
+        params.push(Box::new(entity_type.clone()));
+    }
+
+    if let Some(days) = older_than_days {
+        // deleted_at is RFC 3339 (archive_entity) or SQLite datetime for older rows
+        where_clauses.push("COALESCE(datetime(deleted_at), deleted_at) < datetime('now', ?)");
+        params.push(Box::new(format!("-{} days", days)));
+    }
+
+    let where_sql = if where_clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", where_clauses.join(" AND "))
+    };
+
+    (where_sql, params)
+}
+
+/// Delete trash rows matching the filter and record what was purged in the activity log.
+/// Returns the number of rows deleted.
+fn purge_trash(
+    conn: &Connection,
+    entity_type: &Option<String>,
+    older_than_days: Option<i64>,
+    reason: &str,
+    purged_by: &Option<String>,
+) -> Result<usize, String> {
+    let (where_sql, params) = trash_filter(entity_type, older_than_days);
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT entity_type, COUNT(*) FROM deleted_items {} GROUP BY entity_type ORDER BY entity_type",
+            where_sql
+        ))
+        .map_err(|e| e.to_string())?;
+    let counts = stmt
+        .query_map(rusqlite::params_from_iter(param_refs.iter()), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let rows_affected = conn
+        .execute(
+            &format!("DELETE FROM deleted_items {}", where_sql),
+            rusqlite::params_from_iter(param_refs.iter()),
+        )
+        .map_err(|e| format!("Failed to clear trash: {}", e))?;
+
+    if rows_affected > 0 {
+        log_trash_purge(conn, &counts, rows_affected, reason, purged_by)?;
+    }
+
+    Ok(rows_affected)
+}
+
+/// Write an activity log entry (entity_modifications, entity_type "trash") for a purge
+fn log_trash_purge(
+    conn: &Connection,
+    counts: &[(String, i64)],
+    total: usize,
+    reason: &str,
+    purged_by: &Option<String>,
+) -> Result<(), String> {
+    let breakdown = counts
+        .iter()
+        .map(|(entity_type, count)| format!("{}: {}", entity_type, count))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let summary = format!("{} - purged {} item(s) ({})", reason, total, breakdown);
+
+    conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES ('trash', 0, ?1, 'purged', NULL, ?2)",
+        (&summary, purged_by),
+    )
+    .map_err(|e| format!("Failed to log trash purge: {}", e))?;
+
+    log::info!("{}", summary);
+    Ok(())
+}
+
+/// Purge trash items older than the trash_retention_days setting; nothing is purged until a
+/// retention is chosen (unset or 0 keeps trash forever).
+/// Runs on app startup and via the purge_expired_trash command.
+pub fn purge_expired_trash_items(conn: &Connection) -> Result<usize, String> {
+    let retention_days = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            [TRASH_RETENTION_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(0);
+
+    if retention_days <= 0 {
+        return Ok(0);
+    }
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let purged = purge_trash(
+        &tx,
+        &None,
+        Some(retention_days),
+        &format!("Trash retention ({} days)", retention_days),
+        &Some("system".to_string()),
+    )?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(purged)
+}
+
+/// Purge trash items older than the retention window
 #[tauri::command]
-pub fn get_deleted_items(db: State<Database>) -> Result<Vec<DeletedItemDisplay>, String> {
-    log::info!("get_deleted_items called");
+pub fn purge_expired_trash(db: State<Database>) -> Result<usize, String> {
+    log::info!("purge_expired_trash called");
 
     let conn = db.get_conn()?;
+    purge_expired_trash_items(&conn)
+}
+
+/// Get deleted items, newest first, optionally filtered by entity type.
+/// Without page/page_size all matching items are returned.
+#[tauri::command]
+pub fn get_deleted_items(
+    page: Option<i32>,
+    page_size: Option<i32>,
+    entity_type: Option<String>,
+    db: State<Database>,
+) -> Result<PaginatedResult<DeletedItemDisplay>, String> {
+    log::info!(
+        "get_deleted_items called - page: {:?}, size: {:?}, entity_type: {:?}",
+        page,
+        page_size,
+        entity_type
+    );
+
+    let conn = db.get_conn()?;
+
+    let (where_sql, mut params) = trash_filter(&entity_type, None);
+
+    let total_count: i64 = {
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM deleted_items {}", where_sql),
+            rusqlite::params_from_iter(param_refs.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?
+    };
+
+    // LIMIT -1 means no limit in SQLite
+    let (limit, offset) = match (page, page_size) {
+        (Some(page), Some(page_size)) if page_size > 0 => (page_size, (page.max(1) - 1) * page_size),
+        _ => (-1, 0),
+    };
+    params.push(Box::new(limit));
+    params.push(Box::new(offset));
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
     let mut stmt = conn
-        .prepare("SELECT id, entity_type, entity_id, entity_data, deleted_at, deleted_by FROM deleted_items ORDER BY deleted_at DESC")
+        .prepare(&format!(
+            "SELECT id, entity_type, entity_id, entity_data, deleted_at, deleted_by, related_data, schema_version FROM deleted_items {} ORDER BY deleted_at DESC LIMIT ? OFFSET ?",
+            where_sql
+        ))
         .map_err(|e| e.to_string())?;
 
     let items_iter = stmt
-        .query_map([], |row| {
+        .query_map(rusqlite::params_from_iter(param_refs.iter()), |row| {
             Ok((
                 row.get::<_, i32>(0)?,
                 row.get::<_, String>(1)?,
@@ -36,33 +218,36 @@ pub fn get_deleted_items(db: State<Database>) -> Result<Vec<DeletedItemDisplay>,
                 row.get::<_, String>(3)?, // entity_data
                 row.get::<_, String>(4)?,
                 row.get::<_, Option<String>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+                row.get::<_, i32>(7)?,
             ))
         })
         .map_err(|e| e.to_string())?;
 
     let mut items = Vec::new();
     for item in items_iter {
-        let (id, entity_type, entity_id, entity_data, deleted_at, deleted_by) = item.map_err(|e| e.to_string())?;
+        let (id, entity_type, entity_id, entity_data, deleted_at, deleted_by, related_data, schema_version) =
+            item.map_err(|e| e.to_string())?;
 
         // Extract name from entity_data JSON
         let entity_name = match entity_type.as_str() {
             "customer" => {
-                serde_json::from_str::<Customer>(&entity_data)
+                archive::parse_archived::<Customer>("customer", schema_version, &entity_data)
                     .map(|c| c.name)
                     .unwrap_or_else(|_| format!("Customer #{}", entity_id))
             },
             "product" => {
-                serde_json::from_str::<Product>(&entity_data)
+                archive::parse_archived::<Product>("product", schema_version, &entity_data)
                     .map(|p| p.name)
                     .unwrap_or_else(|_| format!("Product #{}", entity_id))
             },
             "supplier" => {
-                serde_json::from_str::<Supplier>(&entity_data)
+                archive::parse_archived::<Supplier>("supplier", schema_version, &entity_data)
                     .map(|s| s.name)
                     .unwrap_or_else(|_| format!("Supplier #{}", entity_id))
             },
             "invoice" => {
-                serde_json::from_str::<Invoice>(&entity_data)
+                archive::parse_archived::<Invoice>("invoice", schema_version, &entity_data)
                     .map(|i| i.invoice_number)
                     .unwrap_or_else(|_| format!("Invoice #{}", entity_id))
             },
@@ -76,8 +261,9 @@ pub fn get_deleted_items(db: State<Database>) -> Result<Vec<DeletedItemDisplay>,
             _ => format!("{} #{}", entity_type, entity_id),
         };
 
-        let can_restore = true; // Simplified for now, or check dependencies logic if needed
-        let restore_notes = None;
+        // Rows whose archived data no longer parses can't be restored; say why
+        let restore_notes = check_restorable(&entity_type, schema_version, &entity_data, related_data.as_deref()).err();
+        let can_restore = restore_notes.is_none();
 
         items.push(DeletedItemDisplay {
             id,
@@ -92,108 +278,317 @@ pub fn get_deleted_items(db: State<Database>) -> Result<Vec<DeletedItemDisplay>,
         });
     }
 
-    log::info!("Returning {} deleted items", items.len());
-    Ok(items)
+    log::info!("Returning {} of {} deleted items", items.len(), total_count);
+    Ok(PaginatedResult { items, total_count })
+}
+
+/// Parse a trash row the way its restore command would. Types without a restore command are
+/// not checked.
+fn check_restorable(
+    entity_type: &str,
+    schema_version: i32,
+    entity_data: &str,
+    related_data: Option<&str>,
+) -> Result<(), String> {
+    match entity_type {
+        "customer" => {
+            archive::parse_archived::<Customer>("customer", schema_version, entity_data)?;
+            archived_customer_invoices(schema_version, related_data)?;
+        }
+        "product" => {
+            archive::parse_archived::<Product>("product", schema_version, entity_data)?;
+        }
+        "supplier" => {
+            archive::parse_archived::<Supplier>("supplier", schema_version, entity_data)?;
+        }
+        "invoice" => {
+            archive::parse_archived::<Invoice>("invoice", schema_version, entity_data)?;
+            if let Some(items_json) = related_data {
+                archive::parse_archived_list::<InvoiceItemWithProduct>("invoice_item", schema_version, items_json)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnrestorableItem {
+    /// deleted_items id
+    pub id: i32,
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub schema_version: i32,
+    pub error: String,
 }
 
-/// Restore a deleted customer
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashValidation {
+    /// Rows of a restorable type that were checked
+    pub checked: usize,
+    pub unrestorable: Vec<UnrestorableItem>,
+}
+
+/// Dry-run the parsing every restore would do and report the trash rows that would fail
 #[tauri::command]
-pub fn restore_customer(deleted_item_id: i32, db: State<Database>) -> Result<(), String> {
-    log::info!("restore_customer called with deleted_item_id: {}", deleted_item_id);
+pub fn validate_trash(db: State<Database>) -> Result<TrashValidation, String> {
+    log::info!("validate_trash called");
 
-    let mut conn = db.get_conn()?;
+    let conn = db.get_conn()?;
+    validate_trash_internal(&conn)
+}
 
-    // Get deleted item
-    let (entity_data, related_data): (String, Option<String>) = conn
-        .query_row(
-            "SELECT entity_data, related_data FROM deleted_items WHERE id = ?1 AND entity_type = 'customer'",
-            [deleted_item_id],
-            |row| Ok((row.get(0)?, row.get(1)?)),
+pub(crate) fn validate_trash_internal(conn: &Connection) -> Result<TrashValidation, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, entity_type, entity_id, entity_data, related_data, schema_version
+             FROM deleted_items
+             WHERE entity_type IN ('customer', 'product', 'supplier', 'invoice')
+             ORDER BY id",
         )
-        .map_err(|e| format!("Deleted customer not found: {}", e))?;
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i32>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, i32>(5)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
 
-    let customer: Customer = serde_json::from_str(&entity_data)
-        .map_err(|e| format!("Failed to parse customer data: {}", e))?;
+    let mut checked = 0;
+    let mut unrestorable = Vec::new();
+    for row in rows {
+        let (id, entity_type, entity_id, entity_data, related_data, schema_version) = row.map_err(|e| e.to_string())?;
+        checked += 1;
+        if let Err(error) = check_restorable(&entity_type, schema_version, &entity_data, related_data.as_deref()) {
+            unrestorable.push(UnrestorableItem {
+                id,
+                entity_type,
+                entity_id,
+                schema_version,
+                error,
+            });
+        }
+    }
+
+    log::info!("Checked {} trash items, {} can't be restored", checked, unrestorable.len());
+    Ok(TrashValidation { checked, unrestorable })
+}
 
+/// Prefix of the error a restore returns when its trash row is gone
+pub const ALREADY_RESTORED: &str = "ALREADY_RESTORED:";
+
+/// A trash row as the restore commands read it
+struct TrashRow {
+    entity_data: String,
+    related_data: Option<String>,
+    schema_version: i32,
+}
+
+/// Take a trash row out of deleted_items, inside the restore's transaction. Taking the row
+/// before anything is inserted makes restores idempotent: a second restore of the same row
+/// (a double click) finds nothing and fails with ALREADY_RESTORED.
+fn take_trash_row(tx: &Connection, deleted_item_id: i32, entity_type: &str) -> Result<TrashRow, String> {
+    tx.query_row(
+        "DELETE FROM deleted_items WHERE id = ?1 AND entity_type = ?2
+         RETURNING entity_data, related_data, schema_version",
+        rusqlite::params![deleted_item_id, entity_type],
+        |row| {
+            Ok(TrashRow {
+                entity_data: row.get(0)?,
+                related_data: row.get(1)?,
+                schema_version: row.get(2)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to remove from trash: {}", e))?
+    .ok_or_else(|| {
+        format!(
+            "{}deleted {} #{} is no longer in trash; it was already restored or permanently deleted",
+            ALREADY_RESTORED, entity_type, deleted_item_id
+        )
+    })
+}
+
+/// `id` if no row of `table` uses it, else None so the insert assigns a new one
+fn free_id(tx: &Connection, table: &str, id: i32) -> Result<Option<i32>, String> {
+    let taken: bool = tx
+        .query_row(&format!("SELECT EXISTS (SELECT 1 FROM {} WHERE id = ?1)", table), [id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    Ok(if taken { None } else { Some(id) })
+}
+
+/// Log a restore in the activity log. An entity that couldn't get its old id back says so in
+/// the entry's name, along with any other notes.
+fn log_restore(
+    tx: &Connection,
+    entity_type: &str,
+    old_id: i32,
+    new_id: i32,
+    name: &str,
+    mut notes: Vec<String>,
+    restored_by: &Option<String>,
+) -> Result<(), String> {
+    if new_id != old_id {
+        notes.insert(0, format!("id #{} was taken, restored as #{}", old_id, new_id));
+    }
+    let entity_name = if notes.is_empty() {
+        name.to_string()
+    } else {
+        format!("{} ({})", name, notes.join("; "))
+    };
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, 'restored', NULL, ?4)",
+        (entity_type, new_id, &entity_name, restored_by),
+    )
+    .map_err(|e| format!("Failed to log modification: {}", e))?;
+    Ok(())
+}
+
+/// Invoices archived with a customer. Merges store a summary object in related_data instead,
+/// which has nothing to restore.
+fn archived_customer_invoices(schema_version: i32, related_data: Option<&str>) -> Result<Vec<Invoice>, String> {
+    match related_data.filter(|json| json.trim_start().starts_with('[')) {
+        Some(json) => archive::parse_archived_list("invoice", schema_version, json),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Restore a customer: from trash by deleted_item_id, or an archived customer by customer_id
+#[tauri::command]
+pub fn restore_customer(
+    deleted_item_id: Option<i32>,
+    customer_id: Option<i32>,
+    restored_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<(), String> {
+    log::info!(
+        "restore_customer called with deleted_item_id: {:?}, customer_id: {:?}",
+        deleted_item_id,
+        customer_id
+    );
+
+    let mut conn = db.get_conn()?;
+
+    let deleted_item_id = match (deleted_item_id, customer_id) {
+        (Some(deleted_item_id), None) => deleted_item_id,
+        (None, Some(customer_id)) => {
+            crate::commands::customers::unarchive_customer_internal(&conn, customer_id, restored_by)?;
+            emit_data_changed(&app, DataEntity::Customer, DataOperation::Restored, customer_id);
+            return Ok(());
+        }
+        _ => return Err("Pass either deleted_item_id or customer_id".to_string()),
+    };
+
+    let customer_id = restore_customer_from_trash(&mut conn, deleted_item_id, &restored_by)?;
+    emit_data_changed(&app, DataEntity::Customer, DataOperation::Restored, customer_id);
+    Ok(())
+}
+
+pub(crate) fn restore_customer_from_trash(
+    conn: &mut Connection,
+    deleted_item_id: i32,
+    restored_by: &Option<String>,
+) -> Result<i32, String> {
     let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
 
-    // Restore customer
+    let trash = take_trash_row(&tx, deleted_item_id, "customer")?;
+    let customer: Customer = archive::parse_archived("customer", trash.schema_version, &trash.entity_data)?;
+    let invoices = archived_customer_invoices(trash.schema_version, trash.related_data.as_deref())?;
+
+    // Restore customer, under its old id when that is still free
     tx.execute(
-        "INSERT INTO customers (id, name, email, phone, address, place, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        (
-            customer.id,
+        "INSERT INTO customers (id, name, email, phone, address, place, state, district, town, image_path, credit_limit, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        rusqlite::params![
+            free_id(&tx, "customers", customer.id)?,
             &customer.name,
             &customer.email,
             &customer.phone,
             &customer.address,
             &customer.place,
+            &customer.state,
+            &customer.district,
+            &customer.town,
+            &customer.image_path,
+            customer.credit_limit,
             &customer.created_at,
             &customer.updated_at,
-        ),
+        ],
     )
     .map_err(|e| format!("Failed to restore customer: {}", e))?;
+    let customer_id = tx.last_insert_rowid() as i32;
 
     // Restore related invoices if any
-    if let Some(invoices_json) = related_data {
-        if let Ok(invoices) = serde_json::from_str::<Vec<Invoice>>(&invoices_json) {
-            for invoice in invoices {
-                tx.execute(
-                    "INSERT INTO invoices (id, invoice_number, customer_id, total_amount, tax_amount, discount_amount, payment_method, created_at, cgst_amount, fy_year, gst_rate, igst_amount, sgst_amount, state, district, town) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
-                    (
-                        invoice.id,
-                        &invoice.invoice_number,
-                        invoice.customer_id,
-                        invoice.total_amount,
-                        invoice.tax_amount,
-                        invoice.discount_amount,
-                        &invoice.payment_method,
-                        &invoice.created_at,
-                        invoice.cgst_amount,
-                        &invoice.fy_year,
-                        invoice.gst_rate,
-                        invoice.igst_amount,
-                        invoice.sgst_amount,
-                        &invoice.state,
-                        &invoice.district,
-                        &invoice.town,
-                    ),
-                )
-                .map_err(|e| format!("Failed to restore invoice: {}", e))?;
-            }
-        }
+    for invoice in invoices {
+        tx.execute(
+            "INSERT INTO invoices (id, invoice_number, customer_id, total_amount, tax_amount, discount_amount, payment_method, created_at, cgst_amount, fy_year, gst_rate, igst_amount, sgst_amount, state, district, town, round_off) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            rusqlite::params![
+                free_id(&tx, "invoices", invoice.id)?,
+                &invoice.invoice_number,
+                customer_id,
+                invoice.total_amount,
+                invoice.tax_amount,
+                invoice.discount_amount,
+                &invoice.payment_method,
+                &invoice.created_at,
+                invoice.cgst_amount,
+                &invoice.fy_year,
+                invoice.gst_rate,
+                invoice.igst_amount,
+                invoice.sgst_amount,
+                &invoice.state,
+                &invoice.district,
+                &invoice.town,
+                invoice.round_off,
+            ],
+        )
+        .map_err(|e| format!("Failed to restore invoice: {}", e))?;
     }
 
-    // Remove from deleted_items
-    tx.execute("DELETE FROM deleted_items WHERE id = ?1", [deleted_item_id])
-        .map_err(|e| format!("Failed to remove from trash: {}", e))?;
+    log_restore(&tx, "customer", customer.id, customer_id, &customer.name, Vec::new(), restored_by)?;
 
     tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
     log::info!("Restored customer successfully");
-    Ok(())
+    Ok(customer_id)
 }
 
 /// Restore a deleted product
 #[tauri::command]
-pub fn restore_product(deleted_item_id: i32, db: State<Database>) -> Result<(), String> {
+pub fn restore_product(
+    deleted_item_id: i32,
+    restored_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<(), String> {
     log::info!("restore_product called with deleted_item_id: {}", deleted_item_id);
 
     let mut conn = db.get_conn()?;
+    let product_id = restore_product_internal(&mut conn, deleted_item_id, &restored_by)?;
+    emit_data_changed(&app, DataEntity::Product, DataOperation::Restored, product_id);
+    Ok(())
+}
 
-    // Get deleted item
-    let entity_data: String = conn
-        .query_row(
-            "SELECT entity_data FROM deleted_items WHERE id = ?1 AND entity_type = 'product'",
-            [deleted_item_id],
-            |row| row.get(0),
-        )
-        .map_err(|e| format!("Deleted product not found: {}", e))?;
+pub(crate) fn restore_product_internal(
+    conn: &mut Connection,
+    deleted_item_id: i32,
+    restored_by: &Option<String>,
+) -> Result<i32, String> {
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
 
-    let product: Product = serde_json::from_str(&entity_data)
-        .map_err(|e| format!("Failed to parse product data: {}", e))?;
+    let trash = take_trash_row(&tx, deleted_item_id, "product")?;
+    let product: Product = archive::parse_archived("product", trash.schema_version, &trash.entity_data)?;
 
     // Check for SKU conflict
-    let sku_exists: bool = conn
+    let sku_exists: bool = tx
         .query_row(
             "SELECT COUNT(*) FROM products WHERE sku = ?1",
             [&product.sku],
@@ -206,112 +601,353 @@ pub fn restore_product(deleted_item_id: i32, db: State<Database>) -> Result<(),
         return Err(format!("Cannot restore: Product with SKU '{}' already exists", product.sku));
     }
 
-    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
-
-    // Restore product
+    // Restore product; links to a supplier, category or parent deleted since are dropped
     tx.execute(
-        "INSERT INTO products (id, name, sku, price, stock_quantity, supplier_id) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        (
-            product.id,
+        "INSERT INTO products (id, name, sku, price, selling_price, initial_stock, stock_quantity, supplier_id, created_at, updated_at,
+                               image_path, category, category_id, parent_product_id, variant_attributes, unit, allow_fractional)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, (SELECT id FROM suppliers WHERE id = ?8), ?9, ?10, ?11, ?12,
+                 (SELECT id FROM categories WHERE id = ?13), (SELECT id FROM products WHERE id = ?14), ?15, ?16, ?17)",
+        rusqlite::params![
+            free_id(&tx, "products", product.id)?,
             &product.name,
             &product.sku,
             product.price,
+            product.selling_price,
+            product.initial_stock,
             product.stock_quantity,
             product.supplier_id,
-        ),
+            &product.created_at,
+            &product.updated_at,
+            &product.image_path,
+            &product.category,
+            product.category_id,
+            product.parent_product_id,
+            &product.variant_attributes,
+            &product.unit,
+            product.allow_fractional,
+        ],
     )
     .map_err(|e| format!("Failed to restore product: {}", e))?;
+    let product_id = tx.last_insert_rowid() as i32;
 
-    // Remove from deleted_items
-    tx.execute("DELETE FROM deleted_items WHERE id = ?1", [deleted_item_id])
-        .map_err(|e| format!("Failed to remove from trash: {}", e))?;
+    log_restore(&tx, "product", product.id, product_id, &product.name, Vec::new(), restored_by)?;
 
     tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
     log::info!("Restored product successfully");
-    Ok(())
+    Ok(product_id)
 }
 
 /// Restore a deleted supplier
 #[tauri::command]
-pub fn restore_supplier(deleted_item_id: i32, db: State<Database>) -> Result<(), String> {
+pub fn restore_supplier(
+    deleted_item_id: i32,
+    restored_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<(), String> {
     log::info!("restore_supplier called with deleted_item_id: {}", deleted_item_id);
 
     let mut conn = db.get_conn()?;
+    let supplier_id = restore_supplier_internal(&mut conn, deleted_item_id, &restored_by)?;
+    emit_data_changed(&app, DataEntity::Supplier, DataOperation::Restored, supplier_id);
+    Ok(())
+}
 
-    // Get deleted item
-    let (entity_data, related_data): (String, Option<String>) = conn
-        .query_row(
-            "SELECT entity_data, related_data FROM deleted_items WHERE id = ?1 AND entity_type = 'supplier'",
-            [deleted_item_id],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        )
-        .map_err(|e| format!("Deleted supplier not found: {}", e))?;
-
-    let supplier: Supplier = serde_json::from_str(&entity_data)
-        .map_err(|e| format!("Failed to parse supplier data: {}", e))?;
-
+pub(crate) fn restore_supplier_internal(
+    conn: &mut Connection,
+    deleted_item_id: i32,
+    restored_by: &Option<String>,
+) -> Result<i32, String> {
     let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
 
+    let trash = take_trash_row(&tx, deleted_item_id, "supplier")?;
+    let supplier: Supplier = archive::parse_archived("supplier", trash.schema_version, &trash.entity_data)?;
+
     // Restore supplier
     tx.execute(
-        "INSERT INTO suppliers (id, name, contact_info) VALUES (?1, ?2, ?3)",
-        (supplier.id, &supplier.name, &supplier.contact_info),
+        "INSERT INTO suppliers (id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at, opening_balance)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        rusqlite::params![
+            free_id(&tx, "suppliers", supplier.id)?,
+            &supplier.name,
+            &supplier.contact_info,
+            &supplier.address,
+            &supplier.email,
+            &supplier.comments,
+            &supplier.state,
+            &supplier.district,
+            &supplier.town,
+            &supplier.image_path,
+            &supplier.created_at,
+            &supplier.updated_at,
+            supplier.opening_balance,
+        ],
     )
     .map_err(|e| format!("Failed to restore supplier: {}", e))?;
+    let supplier_id = tx.last_insert_rowid() as i32;
 
     // Re-link products if any
-    if let Some(product_ids_json) = related_data {
+    if let Some(product_ids_json) = trash.related_data {
         if let Ok(product_ids) = serde_json::from_str::<Vec<i32>>(&product_ids_json) {
             for product_id in product_ids {
                 tx.execute(
                     "UPDATE products SET supplier_id = ?1 WHERE id = ?2",
-                    (supplier.id, product_id),
+                    (supplier_id, product_id),
                 )
                 .map_err(|e| format!("Failed to re-link product: {}", e))?;
             }
         }
     }
 
-    // Remove from deleted_items
-    tx.execute("DELETE FROM deleted_items WHERE id = ?1", [deleted_item_id])
-        .map_err(|e| format!("Failed to remove from trash: {}", e))?;
+    log_restore(&tx, "supplier", supplier.id, supplier_id, &supplier.name, Vec::new(), restored_by)?;
 
     tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
     log::info!("Restored supplier successfully");
-    Ok(())
+    Ok(supplier_id)
+}
+
+/// Restore a deleted invoice with its items, deducting stock again through FIFO.
+///
+/// The original id and invoice number are reused when still free; otherwise a new id is
+/// assigned and the number gets an "-R<n>" suffix. Fails without changing anything when a
+/// product no longer exists or current stock can't cover the restored quantities.
+/// Credit payments are not part of the archive, so a restored credit invoice is fully outstanding.
+#[tauri::command]
+pub fn restore_invoice(
+    deleted_item_id: i32,
+    restored_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<Invoice, String> {
+    log::info!("restore_invoice called with deleted_item_id: {}", deleted_item_id);
+
+    let mut conn = db.get_conn()?;
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let trash = take_trash_row(&tx, deleted_item_id, "invoice")?;
+    let mut invoice: Invoice = archive::parse_archived("invoice", trash.schema_version, &trash.entity_data)?;
+    let items: Vec<InvoiceItemWithProduct> = match &trash.related_data {
+        Some(items_json) => archive::parse_archived_list("invoice_item", trash.schema_version, items_json)?,
+        None => Vec::new(),
+    };
+
+    // Check stock for every product up front so nothing is partially restored.
+    // Bundles are checked against their current components.
+    let mut shortages = Vec::new();
+    let mut required: Vec<(i32, f64)> = Vec::new();
+    for item in &items {
+        let exists: bool = tx
+            .query_row("SELECT COUNT(*) FROM products WHERE id = ?1", [item.product_id], |row| row.get::<_, i32>(0))
+            .map(|count| count > 0)
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            shortages.push(format!("'{}' no longer exists", item.product_name));
+            continue;
+        }
+        for (product_id, quantity) in bundles::stock_requirements(&tx, item.product_id, item.quantity)? {
+            match required.iter_mut().find(|(id, _)| *id == product_id) {
+                Some(entry) => entry.1 = quantity::sum([entry.1, quantity]),
+                None => required.push((product_id, quantity)),
+            }
+        }
+    }
+
+    for (product_id, quantity) in &required {
+        let (stock, product_name): (f64, String) = tx
+            .query_row("SELECT stock_quantity, name FROM products WHERE id = ?1", [product_id], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .map_err(|e| e.to_string())?;
+        if quantity::to_milli(stock) < quantity::to_milli(*quantity) {
+            shortages.push(format!(
+                "'{}' needs {}, only {} in stock (short by {})",
+                product_name,
+                quantity::format_quantity(*quantity),
+                quantity::format_quantity(stock),
+                quantity::format_quantity(quantity::sub(*quantity, stock))
+            ));
+        }
+    }
+
+    if !shortages.is_empty() {
+        return Err(format!(
+            "Cannot restore invoice {}: {}",
+            invoice.invoice_number,
+            shortages.join("; ")
+        ));
+    }
+
+    let original_number = invoice.invoice_number.clone();
+    let number_taken = |number: &str| -> Result<bool, String> {
+        tx.query_row("SELECT COUNT(*) FROM invoices WHERE invoice_number = ?1", [number], |row| row.get::<_, i32>(0))
+            .map(|count| count > 0)
+            .map_err(|e| e.to_string())
+    };
+    let mut suffix = 0;
+    while number_taken(&invoice.invoice_number)? {
+        suffix += 1;
+        invoice.invoice_number = format!("{}-R{}", original_number, suffix);
+    }
+
+    let original_id = invoice.id;
+    let restore_id = free_id(&tx, "invoices", invoice.id)?;
+
+    // A deleted customer can't be referenced any more; restore as a walk-in sale
+    let mut notes = Vec::new();
+    if let Some(customer_id) = invoice.customer_id {
+        let customer_exists: bool = tx
+            .query_row("SELECT COUNT(*) FROM customers WHERE id = ?1", [customer_id], |row| row.get::<_, i32>(0))
+            .map(|count| count > 0)
+            .map_err(|e| e.to_string())?;
+        if !customer_exists {
+            invoice.customer_id = None;
+            notes.push(format!("customer #{} no longer exists, restored without customer", customer_id));
+        }
+    }
+
+    let is_credit = invoice.payment_method.as_deref() == Some("Credit");
+    let (initial_paid, credit_amount) = if is_credit {
+        (0.0, invoice.total_amount)
+    } else {
+        (invoice.total_amount, 0.0)
+    };
+
+    tx.execute(
+        "INSERT INTO invoices (id, invoice_number, customer_id, total_amount, tax_amount, discount_amount, payment_method, created_at, cgst_amount, fy_year, gst_rate, igst_amount, sgst_amount, state, district, town, initial_paid, credit_amount, round_off) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+        rusqlite::params![
+            restore_id,
+            &invoice.invoice_number,
+            invoice.customer_id,
+            invoice.total_amount,
+            invoice.tax_amount,
+            invoice.discount_amount,
+            &invoice.payment_method,
+            &invoice.created_at,
+            invoice.cgst_amount,
+            &invoice.fy_year,
+            invoice.gst_rate,
+            invoice.igst_amount,
+            invoice.sgst_amount,
+            &invoice.state,
+            &invoice.district,
+            &invoice.town,
+            initial_paid,
+            credit_amount,
+            invoice.round_off,
+        ],
+    )
+    .map_err(|e| format!("Failed to restore invoice: {}", e))?;
+    invoice.id = tx.last_insert_rowid() as i32;
+
+    let sale_date = Utc::now().format("%Y-%m-%d").to_string();
+    for item in &items {
+        tx.execute(
+            "INSERT INTO invoice_items (id, invoice_id, product_id, quantity, unit_price, product_name, discount_amount, tax_rate, tax_amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                free_id(&tx, "invoice_items", item.id)?,
+                invoice.id,
+                item.product_id,
+                item.quantity,
+                item.unit_price,
+                &item.product_name,
+                item.discount_amount,
+                item.tax_rate,
+                item.tax_amount,
+            ],
+        )
+        .map_err(|e| format!("Failed to restore invoice item: {}", e))?;
+        let invoice_item_id = tx.last_insert_rowid() as i32;
+
+        bundles::record_item_sale(&tx, invoice_item_id, item.product_id, item.quantity, &sale_date, invoice.id)?;
+    }
+
+    if invoice.invoice_number != original_number {
+        notes.push(format!("invoice number {} was taken, restored as {}", original_number, invoice.invoice_number));
+    }
+    if is_credit {
+        notes.push("credit payments were not restored".to_string());
+    }
+    log_restore(&tx, "invoice", original_id, invoice.id, &invoice.invoice_number, notes, &restored_by)?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    invoice.item_count = Some(items.len() as i32);
+    log::info!("Restored invoice {} with {} items", invoice.invoice_number, items.len());
+    emit_data_changed(&app, DataEntity::Invoice, DataOperation::Restored, invoice.id);
+    Ok(invoice)
 }
 
 /// Permanently delete an item from trash
 #[tauri::command]
-pub fn permanently_delete_item(deleted_item_id: i32, db: State<Database>) -> Result<(), String> {
+pub fn permanently_delete_item(deleted_item_id: i32, deleted_by: Option<String>, db: State<Database>) -> Result<(), String> {
     log::info!("permanently_delete_item called with id: {}", deleted_item_id);
 
-    let conn = db.get_conn()?;
+    let mut conn = db.get_conn()?;
+    permanently_delete_item_internal(&mut conn, deleted_item_id, &deleted_by)
+}
 
-    let rows_affected = conn
-        .execute("DELETE FROM deleted_items WHERE id = ?1", [deleted_item_id])
+pub(crate) fn permanently_delete_item_internal(
+    conn: &mut Connection,
+    deleted_item_id: i32,
+    deleted_by: &Option<String>,
+) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let entity_type: String = tx
+        .query_row(
+            "SELECT entity_type FROM deleted_items WHERE id = ?1",
+            [deleted_item_id],
+            |row| row.get(0),
+        )
+        .map_err(|_| format!("Deleted item with id {} not found", deleted_item_id))?;
+
+    tx.execute("DELETE FROM deleted_items WHERE id = ?1", [deleted_item_id])
         .map_err(|e| format!("Failed to delete item: {}", e))?;
 
-    if rows_affected == 0 {
-        return Err(format!("Deleted item with id {} not found", deleted_item_id));
-    }
+    log_trash_purge(
+        &tx,
+        &[(entity_type, 1)],
+        1,
+        &format!("Permanently deleted trash item #{}", deleted_item_id),
+        deleted_by,
+    )?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
     log::info!("Permanently deleted item with id: {}", deleted_item_id);
     Ok(())
 }
 
-/// Clear all items from trash
+/// Clear items from trash. Without filters everything is removed; `entity_type` and
+/// `older_than_days` limit the purge to matching items.
 #[tauri::command]
-pub fn clear_trash(db: State<Database>) -> Result<usize, String> {
-    log::info!("clear_trash called");
+pub fn clear_trash(
+    entity_type: Option<String>,
+    older_than_days: Option<i64>,
+    deleted_by: Option<String>,
+    approval_token: Option<String>,
+    db: State<Database>,
+) -> Result<usize, String> {
+    log::info!(
+        "clear_trash called - entity_type: {:?}, older_than_days: {:?}",
+        entity_type,
+        older_than_days
+    );
+
+    if older_than_days.is_some_and(|days| days < 0) {
+        return Err("older_than_days cannot be negative".to_string());
+    }
 
-    let conn = db.get_conn()?;
+    let mut conn = db.get_conn()?;
+    require_biometric_approval(&conn, "clear_trash", approval_token.as_deref())?;
+    take_safety_snapshot(&conn, "clear_trash")?;
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
 
-    let rows_affected = conn
-        .execute("DELETE FROM deleted_items", [])
-        .map_err(|e| format!("Failed to clear trash: {}", e))?;
+    let rows_affected = purge_trash(&tx, &entity_type, older_than_days, "Cleared trash", &deleted_by)?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
     log::info!("Cleared {} items from trash", rows_affected);
     Ok(rows_affected)
@@ -333,19 +969,213 @@ pub struct EntityModificationDisplay {
     pub modified_at: String,
 }
 
-/// Get all entity modifications
+/// Filters for the modifications history; every field is optional
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModificationFilters {
+    pub entity_type: Option<String>,
+    pub entity_id: Option<i32>,
+    pub modified_by: Option<String>,
+    /// Business-local "YYYY-MM-DD", inclusive
+    pub start_date: Option<String>,
+    /// Business-local "YYYY-MM-DD", inclusive
+    pub end_date: Option<String>,
+}
+
+/// One changed field of a modification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: serde_json::Value,
+    pub new: serde_json::Value,
+}
+
+/// An event in an entity's history, from whichever table recorded it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    /// "modification" (entity_modifications), "trash" (deleted_items) or
+    /// "invoice_modification" (invoice_modifications)
+    pub source: String,
+    /// Row id in the source table
+    pub source_id: i32,
+    pub action: String,
+    pub summary: Option<String>,
+    pub changes: Vec<FieldChange>,
+    pub modified_by: Option<String>,
+    /// UTC "YYYY-MM-DD HH:MM:SS"
+    pub modified_at: String,
+}
+
+/// Build a WHERE clause selecting entity_modifications rows by the filters
+fn modification_filter(conn: &Connection, filters: &ModificationFilters) -> Result<(String, Vec<Box<dyn rusqlite::ToSql>>), String> {
+    let mut where_clauses = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+    if let Some(entity_type) = filters.entity_type.as_deref().filter(|value| !value.is_empty()) {
+        where_clauses.push("entity_type = ?");
+        params.push(Box::new(entity_type.to_string()));
+    }
+    if let Some(entity_id) = filters.entity_id {
+        where_clauses.push("entity_id = ?");
+        params.push(Box::new(entity_id));
+    }
+    if let Some(modified_by) = filters.modified_by.as_deref().filter(|value| !value.is_empty()) {
+        where_clauses.push("modified_by = ? COLLATE NOCASE");
+        params.push(Box::new(modified_by.to_string()));
+    }
+
+    // Dates are business-local days; modified_at is stored in UTC
+    let offset_minutes = business_offset_minutes(conn);
+    if let Some(start) = &filters.start_date {
+        let day = parse_report_date(start)?;
+        where_clauses.push("datetime(modified_at) >= ?");
+        params.push(Box::new(ReportRange::new(day, day, offset_minutes).start_utc));
+    }
+    if let Some(end) = &filters.end_date {
+        let day = parse_report_date(end)?;
+        where_clauses.push("datetime(modified_at) < ?");
+        params.push(Box::new(ReportRange::new(day, day, offset_minutes).end_utc));
+    }
+
+    let where_sql = if where_clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", where_clauses.join(" AND "))
+    };
+
+    Ok((where_sql, params))
+}
+
+/// Bring a field_changes payload into the `[{field, old, new}]` shape. Returns None when it
+/// already has it. Older rows used old_value/new_value or from/to keys, a map of field to
+/// `{old, new}`, `[old, new]` or the new value, or plain text; entries that carried their
+/// details as extra keys become one change per key.
+pub(crate) fn normalize_field_changes(raw: &str) -> Option<String> {
+    let parsed: serde_json::Value = match serde_json::from_str(raw) {
+        Ok(value) => value,
+        Err(_) => {
+            let change = FieldChange { field: "details".to_string(), old: serde_json::Value::Null, new: raw.into() };
+            return serde_json::to_string(&[change]).ok();
+        }
+    };
+    let is_canonical = |entry: &serde_json::Value| {
+        entry.as_object().is_some_and(|object| {
+            object.get("field").is_some_and(|field| field.is_string())
+                && object.contains_key("old")
+                && object.contains_key("new")
+                && object.len() == 3
+        })
+    };
+    if parsed.as_array().is_some_and(|entries| entries.iter().all(is_canonical)) {
+        return None;
+    }
+
+    let pick = |object: &serde_json::Map<String, serde_json::Value>, keys: &[&str]| {
+        keys.iter().find_map(|key| object.get(*key).cloned())
+    };
+    let from_pair = |field: String, value: &serde_json::Value| -> FieldChange {
+        match value {
+            serde_json::Value::Object(object) => FieldChange {
+                field,
+                old: pick(object, &["old", "old_value", "from", "before"]).unwrap_or_default(),
+                new: pick(object, &["new", "new_value", "to", "after"]).unwrap_or_default(),
+            },
+            serde_json::Value::Array(pair) if pair.len() == 2 => FieldChange { field, old: pair[0].clone(), new: pair[1].clone() },
+            other => FieldChange { field, old: serde_json::Value::Null, new: other.clone() },
+        }
+    };
+
+    let mut changes = Vec::new();
+    match parsed {
+        serde_json::Value::Array(entries) => {
+            for entry in entries {
+                let Some(object) = entry.as_object() else {
+                    changes.push(FieldChange { field: "details".to_string(), old: serde_json::Value::Null, new: entry });
+                    continue;
+                };
+                let field = object.get("field").and_then(|field| field.as_str()).map(str::to_string);
+                const VALUE_KEYS: &[&str] = &["old", "old_value", "from", "before", "new", "new_value", "to", "after"];
+                match field {
+                    Some(field) if VALUE_KEYS.iter().any(|key| object.contains_key(*key)) => {
+                        changes.push(from_pair(field, &entry));
+                    }
+                    // Details stored as extra keys next to the field name
+                    _ => {
+                        for (key, value) in object.iter().filter(|(key, _)| key.as_str() != "field") {
+                            changes.push(FieldChange { field: key.clone(), old: serde_json::Value::Null, new: value.clone() });
+                        }
+                    }
+                }
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (field, value) in map {
+                changes.push(from_pair(field, &value));
+            }
+        }
+        other => changes.push(FieldChange { field: "details".to_string(), old: serde_json::Value::Null, new: other }),
+    }
+    serde_json::to_string(&changes).ok()
+}
+
+/// Get entity modifications, newest first, a page at a time
 #[tauri::command]
-pub fn get_all_modifications(db: State<Database>) -> Result<Vec<EntityModificationDisplay>, String> {
-    log::info!("get_all_modifications called");
+pub fn get_all_modifications(
+    filters: Option<ModificationFilters>,
+    page: Option<i32>,
+    page_size: Option<i32>,
+    db: State<Database>,
+) -> Result<PaginatedResult<EntityModificationDisplay>, String> {
+    log::info!("get_all_modifications called - page: {:?}, size: {:?}, filters: {:?}", page, page_size, filters);
 
     let conn = db.get_conn()?;
+    get_all_modifications_internal(&conn, &filters.unwrap_or_default(), page.unwrap_or(1), page_size.unwrap_or(100))
+}
+
+pub(crate) fn get_all_modifications_internal(
+    conn: &Connection,
+    filters: &ModificationFilters,
+    page: i32,
+    page_size: i32,
+) -> Result<PaginatedResult<EntityModificationDisplay>, String> {
+    let (limit, offset) = validate_pagination(page, page_size)?;
+    let (where_sql, mut params) = modification_filter(conn, filters)?;
+
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let total_count: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM entity_modifications {}", where_sql),
+            rusqlite::params_from_iter(param_refs.iter()),
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    params.push(Box::new(limit));
+    params.push(Box::new(offset));
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let items = load_modifications(
+        conn,
+        &format!("{} ORDER BY datetime(modified_at) DESC, id DESC LIMIT ? OFFSET ?", where_sql),
+        &param_refs,
+    )?;
+
+    log::info!("Returning {} of {} modifications", items.len(), total_count);
+    Ok(PaginatedResult { items, total_count })
+}
 
+/// entity_modifications rows selected by `tail` (WHERE/ORDER BY/LIMIT). Rows in an older
+/// field_changes shape are rewritten as they are read.
+fn load_modifications(conn: &Connection, tail: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<EntityModificationDisplay>, String> {
     let mut stmt = conn
-        .prepare("SELECT id, entity_type, entity_id, entity_name, action, field_changes, modified_by, modified_at FROM entity_modifications ORDER BY modified_at DESC LIMIT 200")
+        .prepare(&format!(
+            "SELECT id, entity_type, entity_id, entity_name, action, field_changes, modified_by, modified_at
+             FROM entity_modifications {}",
+            tail
+        ))
         .map_err(|e| e.to_string())?;
 
-    let items_iter = stmt
-        .query_map([], |row| {
+    let mut items: Vec<EntityModificationDisplay> = stmt
+        .query_map(params, |row| {
             Ok(EntityModificationDisplay {
                 id: row.get(0)?,
                 entity_type: row.get(1)?,
@@ -357,14 +1187,135 @@ pub fn get_all_modifications(db: State<Database>) -> Result<Vec<EntityModificati
                 modified_at: row.get(7)?,
             })
         })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
 
-    let items: Vec<EntityModificationDisplay> = items_iter
+    for item in &mut items {
+        if let Some(normalized) = item.field_changes.as_deref().and_then(normalize_field_changes) {
+            conn.execute("UPDATE entity_modifications SET field_changes = ?1 WHERE id = ?2", (&normalized, item.id))
+                .map_err(|e| format!("Failed to upgrade modification {}: {}", item.id, e))?;
+            item.field_changes = Some(normalized);
+        }
+    }
+    Ok(items)
+}
+
+/// Per-key changes between two JSON objects, or the whole values as one "data" change
+fn json_diff(original: Option<&str>, new: Option<&str>) -> Vec<FieldChange> {
+    let parse = |raw: Option<&str>| raw.and_then(|raw| serde_json::from_str::<serde_json::Value>(raw).ok()).unwrap_or_default();
+    let (original, new) = (parse(original), parse(new));
+    match (original.as_object(), new.as_object()) {
+        (Some(before), Some(after)) => {
+            let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+            fields.sort();
+            fields.dedup();
+            fields
+                .into_iter()
+                .map(|field| FieldChange {
+                    field: field.clone(),
+                    old: before.get(field).cloned().unwrap_or_default(),
+                    new: after.get(field).cloned().unwrap_or_default(),
+                })
+                .filter(|change| change.old != change.new)
+                .collect()
+        }
+        _ if original == new => Vec::new(),
+        _ => vec![FieldChange { field: "data".to_string(), old: original, new }],
+    }
+}
+
+/// One entity's history, oldest first: its modifications, its trips to the trash and, for
+/// invoices, the invoice_modifications rows that have no entity_modifications twin
+#[tauri::command]
+pub fn get_entity_timeline(entity_type: String, entity_id: i32, db: State<Database>) -> Result<Vec<TimelineEntry>, String> {
+    log::info!("get_entity_timeline called for {} #{}", entity_type, entity_id);
+
+    let conn = db.get_conn()?;
+    get_entity_timeline_internal(&conn, &entity_type, entity_id)
+}
+
+pub(crate) fn get_entity_timeline_internal(conn: &Connection, entity_type: &str, entity_id: i32) -> Result<Vec<TimelineEntry>, String> {
+    let modifications = load_modifications(conn, "WHERE entity_type = ?1 AND entity_id = ?2", &[&entity_type, &entity_id])?;
+    let mut timeline: Vec<TimelineEntry> = modifications
+        .into_iter()
+        .map(|modification| TimelineEntry {
+            source: "modification".to_string(),
+            source_id: modification.id,
+            action: modification.action,
+            summary: modification.entity_name,
+            changes: modification
+                .field_changes
+                .as_deref()
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or_default(),
+            modified_by: modification.modified_by,
+            modified_at: modification.modified_at,
+        })
+        .collect();
+
+    // deleted_at is RFC 3339 (archive_entity) or SQLite datetime for older rows
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, COALESCE(json_extract(entity_data, '$.name'), json_extract(entity_data, '$.invoice_number')),
+                    COALESCE(datetime(deleted_at), deleted_at), deleted_by
+             FROM deleted_items WHERE entity_type = ?1 AND entity_id = ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let trashed = stmt
+        .query_map(rusqlite::params![entity_type, entity_id], |row| {
+            Ok(TimelineEntry {
+                source: "trash".to_string(),
+                source_id: row.get(0)?,
+                action: "deleted".to_string(),
+                summary: row.get(1)?,
+                changes: Vec::new(),
+                modified_by: row.get(3)?,
+                modified_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| e.to_string())?;
+    timeline.extend(trashed);
+
+    // Invoice edits are written to both tables in one transaction; only rows without a twin
+    // (from before entity_modifications existed) add anything
+    if entity_type == "invoice" {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, action, modified_by, datetime(modified_at), original_data, new_data
+                 FROM invoice_modifications m
+                 WHERE invoice_id = ?1
+                   AND NOT EXISTS (
+                       SELECT 1 FROM entity_modifications e
+                       WHERE e.entity_type = 'invoice' AND e.entity_id = m.invoice_id
+                         AND e.action = m.action AND datetime(e.modified_at) = datetime(m.modified_at)
+                   )",
+            )
+            .map_err(|e| e.to_string())?;
+        let edits = stmt
+            .query_map([entity_id], |row| {
+                let original: Option<String> = row.get(4)?;
+                let new: Option<String> = row.get(5)?;
+                Ok(TimelineEntry {
+                    source: "invoice_modification".to_string(),
+                    source_id: row.get(0)?,
+                    action: row.get(1)?,
+                    summary: None,
+                    changes: json_diff(original.as_deref(), new.as_deref()),
+                    modified_by: row.get(2)?,
+                    modified_at: row.get(3)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        timeline.extend(edits);
+    }
 
-    log::info!("Returning {} modifications", items.len());
-    Ok(items)
+    timeline.sort_by(|a, b| a.modified_at.cmp(&b.modified_at).then(a.source_id.cmp(&b.source_id)));
+    Ok(timeline)
 }
 
 /// Restore an entity to its previous state from a modification
@@ -454,17 +1405,435 @@ pub fn permanently_delete_modification(modification_id: i32, db: State<Database>
     Ok(())
 }
 
-/// Clear all modification history (Master Admin only - enforced in frontend)
-#[tauri::command]
-pub fn clear_modifications_history(db: State<Database>) -> Result<usize, String> {
-    log::info!("clear_modifications_history called");
+/// Build a WHERE clause selecting modification history rows by entity type and/or minimum age
+fn modification_purge_filter(entity_type: &Option<String>, older_than_days: Option<i64>) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+    let mut where_clauses = Vec::new();
+    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
-    let conn = db.get_conn()?;
+    if let Some(entity_type) = entity_type {
+        where_clauses.push("entity_type = ?");
+        params.push(Box::new(entity_type.clone()));
+    }
+
+    if let Some(days) = older_than_days {
+        where_clauses.push("datetime(modified_at) < datetime('now', ?)");
+        params.push(Box::new(format!("-{} days", days)));
+    }
+
+    let where_sql = if where_clauses.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", where_clauses.join(" AND "))
+    };
+
+    (where_sql, params)
+}
+
+/// Count the modification history rows a purge with this filter would delete
+fn count_purgeable_modifications(
+    conn: &Connection,
+    entity_type: &Option<String>,
+    older_than_days: Option<i64>,
+) -> Result<usize, String> {
+    let (where_sql, params) = modification_purge_filter(entity_type, older_than_days);
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    conn.query_row(
+        &format!("SELECT COUNT(*) FROM entity_modifications {}", where_sql),
+        rusqlite::params_from_iter(param_refs.iter()),
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count as usize)
+    .map_err(|e| format!("Failed to count modifications: {}", e))
+}
+
+/// Delete modification history rows matching the filter and record the purge itself in the
+/// activity log (entity_type "modifications"). Returns the number of rows deleted.
+fn purge_modifications(
+    conn: &Connection,
+    entity_type: &Option<String>,
+    older_than_days: Option<i64>,
+    reason: &str,
+    purged_by: &Option<String>,
+) -> Result<usize, String> {
+    let (where_sql, params) = modification_purge_filter(entity_type, older_than_days);
+    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
 
     let rows_affected = conn
-        .execute("DELETE FROM entity_modifications", [])
+        .execute(
+            &format!("DELETE FROM entity_modifications {}", where_sql),
+            rusqlite::params_from_iter(param_refs.iter()),
+        )
         .map_err(|e| format!("Failed to clear modifications: {}", e))?;
 
+    if rows_affected > 0 {
+        let mut filter = Vec::new();
+        if let Some(entity_type) = entity_type {
+            filter.push(format!("entity type {}", entity_type));
+        }
+        if let Some(days) = older_than_days {
+            filter.push(format!("older than {} days", days));
+        }
+        let filter = if filter.is_empty() { "all records".to_string() } else { filter.join(", ") };
+        let summary = format!("{} - purged {} record(s) ({})", reason, rows_affected, filter);
+
+        conn.execute(
+            "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES ('modifications', 0, ?1, 'purged', NULL, ?2)",
+            (&summary, purged_by),
+        )
+        .map_err(|e| format!("Failed to log modifications purge: {}", e))?;
+        log::info!("{}", summary);
+    }
+
+    Ok(rows_affected)
+}
+
+/// Purge modification history older than the modifications_retention_days setting (unset or
+/// 0 keeps it forever). Runs on app startup.
+pub fn purge_expired_modifications(conn: &Connection) -> Result<usize, String> {
+    let retention_days = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            [MODIFICATIONS_RETENTION_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(0);
+
+    if retention_days <= 0 {
+        return Ok(0);
+    }
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let purged = purge_modifications(
+        &tx,
+        &None,
+        Some(retention_days),
+        &format!("Modification history retention ({} days)", retention_days),
+        &Some("system".to_string()),
+    )?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(purged)
+}
+
+/// Clear modification history (Master Admin only - enforced in frontend), optionally only
+/// rows older than `older_than_days` and/or of one entity type. With `dry_run` nothing is
+/// deleted and the number of rows that would be is returned.
+#[tauri::command]
+pub fn clear_modifications_history(
+    older_than_days: Option<i64>,
+    entity_type: Option<String>,
+    dry_run: Option<bool>,
+    cleared_by: Option<String>,
+    db: State<Database>,
+) -> Result<usize, String> {
+    log::info!(
+        "clear_modifications_history called - older_than_days: {:?}, entity_type: {:?}, dry_run: {:?}",
+        older_than_days,
+        entity_type,
+        dry_run
+    );
+
+    let mut conn = db.get_conn()?;
+    clear_modifications_history_internal(&mut conn, older_than_days, entity_type, dry_run.unwrap_or(false), &cleared_by)
+}
+
+pub(crate) fn clear_modifications_history_internal(
+    conn: &mut Connection,
+    older_than_days: Option<i64>,
+    entity_type: Option<String>,
+    dry_run: bool,
+    cleared_by: &Option<String>,
+) -> Result<usize, String> {
+    if older_than_days.is_some_and(|days| days < 0) {
+        return Err("older_than_days cannot be negative".to_string());
+    }
+    let entity_type = entity_type.map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+
+    if dry_run {
+        return count_purgeable_modifications(conn, &entity_type, older_than_days);
+    }
+
+    let safety = create_safety_snapshot(conn, "clear_modifications_history")?;
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let rows_affected = purge_modifications(&tx, &entity_type, older_than_days, "Cleared modification history", cleared_by)?;
+    // Logged after the delete so a full clear leaves a history pointing at the snapshot
+    log_safety_snapshot(&tx, &safety)?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
     log::info!("Cleared {} modification records", rows_affected);
     Ok(rows_affected)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::archive::ARCHIVE_SCHEMA_VERSION;
+    use crate::test_support::{insert_supplier, TestDb};
+
+    fn trash(conn: &Connection, entity_type: &str, entity_id: i32, entity_data: &str, schema_version: i32) -> i32 {
+        conn.execute(
+            "INSERT INTO deleted_items (entity_type, entity_id, entity_data, schema_version) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![entity_type, entity_id, entity_data, schema_version],
+        )
+        .unwrap();
+        conn.last_insert_rowid() as i32
+    }
+
+    #[test]
+    fn test_old_trash_payloads_restore_on_the_current_schema() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme");
+
+        // Archived before category, image_path and timestamps were part of the product
+        let old_product = format!(
+            r#"{{"id": 40, "name": "Kettle", "sku": "KET-1", "price": 450.0, "stock_quantity": 3, "supplier_id": {}}}"#,
+            supplier_id
+        );
+        let product_item = trash(&conn, "product", 40, &old_product, 1);
+        // Archived when suppliers still had place instead of district
+        let supplier_item = trash(&conn, "supplier", 41, r#"{"id": 41, "name": "Bolt Co", "place": "North"}"#, 1);
+        // Missing its SKU altogether
+        let broken_item = trash(&conn, "product", 42, r#"{"id": 42, "name": "Mystery", "price": 1.0}"#, 1);
+        // Written by a newer app
+        let newer_item = trash(&conn, "customer", 43, r#"{"id": 43, "name": "Ravi"}"#, ARCHIVE_SCHEMA_VERSION + 1);
+
+        let report = validate_trash_internal(&conn).unwrap();
+        assert_eq!(report.checked, 4);
+        let unrestorable: Vec<i32> = report.unrestorable.iter().map(|item| item.id).collect();
+        assert_eq!(unrestorable, vec![broken_item, newer_item]);
+
+        assert_eq!(restore_product_internal(&mut conn, product_item, &None).unwrap(), 40);
+        let (sku, stock, supplier, category, created_at): (String, f64, Option<i32>, Option<String>, String) = conn
+            .query_row(
+                "SELECT sku, stock_quantity, supplier_id, category, created_at FROM products WHERE id = 40",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+            )
+            .unwrap();
+        assert_eq!((sku.as_str(), stock, supplier, category), ("KET-1", 3.0, Some(supplier_id), None));
+        assert!(!created_at.is_empty());
+
+        restore_supplier_internal(&mut conn, supplier_item, &None).unwrap();
+        let district: Option<String> = conn
+            .query_row("SELECT district FROM suppliers WHERE id = 41", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(district.as_deref(), Some("North"));
+
+        assert!(restore_product_internal(&mut conn, broken_item, &None).unwrap_err().contains("sku"));
+        // A failed restore leaves the row in trash
+        assert_eq!(validate_trash_internal(&conn).unwrap().unrestorable.len(), 2);
+    }
+
+    #[test]
+    fn test_restoring_twice_or_after_purge_is_refused() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let restored_by = Some("admin".to_string());
+
+        let item = trash(&conn, "supplier", 7, r#"{"id": 7, "name": "Bolt Co"}"#, 1);
+        assert_eq!(restore_supplier_internal(&mut conn, item, &restored_by).unwrap(), 7);
+        let err = restore_supplier_internal(&mut conn, item, &restored_by).unwrap_err();
+        assert!(err.starts_with(ALREADY_RESTORED), "{}", err);
+        let suppliers: i32 = conn.query_row("SELECT COUNT(*) FROM suppliers", [], |row| row.get(0)).unwrap();
+        assert_eq!(suppliers, 1);
+
+        let purged = trash(&conn, "product", 8, r#"{"id": 8, "name": "Kettle", "sku": "KET-1", "price": 1.0}"#, 1);
+        permanently_delete_item_internal(&mut conn, purged, &restored_by).unwrap();
+        let err = restore_product_internal(&mut conn, purged, &restored_by).unwrap_err();
+        assert!(err.starts_with(ALREADY_RESTORED), "{}", err);
+        let products: i32 = conn.query_row("SELECT COUNT(*) FROM products", [], |row| row.get(0)).unwrap();
+        assert_eq!(products, 0);
+    }
+
+    #[test]
+    fn test_trash_is_kept_until_a_retention_is_chosen() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let old = trash(&conn, "supplier", 9, r#"{"id": 9, "name": "Bolt Co"}"#, 1);
+        conn.execute("UPDATE deleted_items SET deleted_at = datetime('now', '-400 days') WHERE id = ?1", [old]).unwrap();
+        trash(&conn, "supplier", 10, r#"{"id": 10, "name": "Nut Co"}"#, 1);
+
+        assert_eq!(purge_expired_trash_items(&conn).unwrap(), 0);
+        conn.execute("INSERT INTO app_settings (key, value) VALUES (?1, '90')", [TRASH_RETENTION_KEY]).unwrap();
+        assert_eq!(purge_expired_trash_items(&conn).unwrap(), 1);
+        let left: i32 = conn.query_row("SELECT COUNT(*) FROM deleted_items", [], |row| row.get(0)).unwrap();
+        assert_eq!(left, 1);
+    }
+
+    #[test]
+    fn test_restore_takes_a_new_id_when_the_old_one_is_used() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let taken_id = insert_supplier(&conn, "Acme");
+
+        let item = trash(&conn, "supplier", taken_id, &format!(r#"{{"id": {}, "name": "Bolt Co"}}"#, taken_id), 1);
+        let restored_id = restore_supplier_internal(&mut conn, item, &Some("admin".to_string())).unwrap();
+        assert_ne!(restored_id, taken_id);
+
+        let (entity_id, entity_name): (i32, String) = conn
+            .query_row(
+                "SELECT entity_id, entity_name FROM entity_modifications WHERE entity_type = 'supplier' AND action = 'restored'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(entity_id, restored_id);
+        assert_eq!(entity_name, format!("Bolt Co (id #{} was taken, restored as #{})", taken_id, restored_id));
+    }
+
+    #[test]
+    fn test_older_field_change_shapes_are_normalized() {
+        let canonical = r#"[{"field":"name","old":"A","new":"B"}]"#;
+        assert_eq!(normalize_field_changes(canonical), None);
+
+        let parse = |raw: &str| -> Vec<FieldChange> { serde_json::from_str(&normalize_field_changes(raw).unwrap()).unwrap() };
+        let change = |field: &str, old: serde_json::Value, new: serde_json::Value| FieldChange { field: field.to_string(), old, new };
+        assert_eq!(
+            parse(r#"[{"field":"price","old_value":1.5,"new_value":2}]"#),
+            vec![change("price", 1.5.into(), 2.into())]
+        );
+        assert_eq!(
+            parse(r#"{"name":{"from":"A","to":"B"},"phone":["1","2"]}"#),
+            vec![change("name", "A".into(), "B".into()), change("phone", "1".into(), "2".into())]
+        );
+        assert_eq!(
+            parse(r#"[{"field":"credit_limit","credit_limit":500.0,"overflow":20.0}]"#),
+            vec![
+                change("credit_limit", serde_json::Value::Null, 500.0.into()),
+                change("overflow", serde_json::Value::Null, 20.0.into()),
+            ]
+        );
+        assert_eq!(parse("stock recount"), vec![change("details", serde_json::Value::Null, "stock recount".into())]);
+    }
+
+    #[test]
+    fn test_modifications_page_filter_and_build_a_timeline() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let log = |entity_type: &str, entity_id: i32, action: &str, changes: &str, by: &str, at: &str| {
+            conn.execute(
+                "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by, modified_at)
+                 VALUES (?1, ?2, 'INV-000001', ?3, ?4, ?5, ?6)",
+                rusqlite::params![entity_type, entity_id, action, changes, by, at],
+            )
+            .unwrap();
+        };
+        log("invoice", 1, "metadata_modified", r#"[{"field":"payment_method","old":"Cash","new":"UPI"}]"#, "asha", "2024-03-01 10:00:00");
+        log("invoice", 1, "items_modified", r#"{"Total Amount":["Rs.10.00","Rs.12.00"]}"#, "ravi", "2024-03-02 10:00:00");
+        log("product", 1, "updated", r#"[{"field":"name","old":"A","new":"B"}]"#, "asha", "2024-03-03 10:00:00");
+        conn.execute("INSERT INTO invoices (id, invoice_number, total_amount) VALUES (1, 'INV-000001', 12.0)", [])
+            .unwrap();
+        conn.execute(
+            r#"INSERT INTO invoice_modifications (invoice_id, action, modified_by, modified_at, original_data, new_data)
+               VALUES (1, 'metadata_modified', 'asha', '2024-03-01 10:00:00', '{}', '{}'),
+                      (1, 'metadata_modified', 'old-app', '2023-12-31 09:00:00', '{"due_date":null}', '{"due_date":"2024-01-30"}')"#,
+            [],
+        )
+        .unwrap();
+        trash(&conn, "invoice", 1, r#"{"id": 1, "invoice_number": "INV-000001"}"#, 1);
+
+        let first = get_all_modifications_internal(&conn, &ModificationFilters::default(), 1, 2).unwrap();
+        assert_eq!(first.total_count, 3);
+        let actions: Vec<&str> = first.items.iter().map(|m| m.action.as_str()).collect();
+        assert_eq!(actions, vec!["updated", "items_modified"]);
+        // The map-shaped row was upgraded in place
+        let stored: String = conn
+            .query_row("SELECT field_changes FROM entity_modifications WHERE action = 'items_modified'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stored, r#"[{"field":"Total Amount","old":"Rs.10.00","new":"Rs.12.00"}]"#);
+
+        let filters = ModificationFilters {
+            entity_type: Some("invoice".to_string()),
+            modified_by: Some("ASHA".to_string()),
+            start_date: Some("2024-02-28".to_string()),
+            end_date: Some("2024-03-01".to_string()),
+            ..Default::default()
+        };
+        let filtered = get_all_modifications_internal(&conn, &filters, 1, 20).unwrap();
+        assert_eq!(filtered.total_count, 1);
+        assert!(get_all_modifications_internal(&conn, &filters, 0, 20).is_err());
+
+        let timeline = get_entity_timeline_internal(&conn, "invoice", 1).unwrap();
+        let feed: Vec<(&str, &str)> = timeline.iter().map(|entry| (entry.source.as_str(), entry.action.as_str())).collect();
+        // The invoice edit logged in both tables shows once
+        assert_eq!(
+            feed,
+            vec![
+                ("invoice_modification", "metadata_modified"),
+                ("modification", "metadata_modified"),
+                ("modification", "items_modified"),
+                ("trash", "deleted"),
+            ]
+        );
+        assert_eq!(
+            timeline[0].changes,
+            vec![FieldChange { field: "due_date".to_string(), old: serde_json::Value::Null, new: "2024-01-30".into() }]
+        );
+    }
+
+    #[test]
+    fn test_modifications_purge_by_age_and_type_with_retention() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        for (entity_type, at) in [
+            ("product", "datetime('now', '-400 days')"),
+            ("customer", "datetime('now', '-400 days')"),
+            ("product", "datetime('now', '-10 days')"),
+        ] {
+            conn.execute(
+                &format!(
+                    "INSERT INTO entity_modifications (entity_type, entity_id, action, modified_by, modified_at) VALUES (?1, 1, 'updated', 'asha', {})",
+                    at
+                ),
+                [entity_type],
+            )
+            .unwrap();
+        }
+        let remaining = |conn: &Connection| -> Vec<(String, String)> {
+            let mut stmt = conn.prepare("SELECT entity_type, action FROM entity_modifications ORDER BY id").unwrap();
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap()
+        };
+
+        assert!(clear_modifications_history_internal(&mut conn, Some(-1), None, true, &None).is_err());
+        assert_eq!(clear_modifications_history_internal(&mut conn, Some(365), None, true, &None).unwrap(), 2);
+        assert_eq!(remaining(&conn).len(), 3);
+
+        let purged =
+            clear_modifications_history_internal(&mut conn, Some(365), Some("product".to_string()), false, &Some("owner".to_string()))
+                .unwrap();
+        assert_eq!(purged, 1);
+        let summary: (String, Option<String>) = conn
+            .query_row(
+                "SELECT entity_name, modified_by FROM entity_modifications WHERE entity_type = 'modifications' AND action = 'purged'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            summary,
+            (
+                "Cleared modification history - purged 1 record(s) (entity type product, older than 365 days)".to_string(),
+                Some("owner".to_string())
+            )
+        );
+        let kept: Vec<String> = remaining(&conn).into_iter().map(|(entity_type, _)| entity_type).collect();
+        assert_eq!(kept, vec!["customer", "product", "modifications", "database"]);
+
+        // Retention is off until the setting is given a number of days
+        assert_eq!(purge_expired_modifications(&conn).unwrap(), 0);
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('modifications_retention_days', '365')", [])
+            .unwrap();
+        assert_eq!(purge_expired_modifications(&conn).unwrap(), 1);
+        assert!(remaining(&conn).iter().all(|(entity_type, _)| entity_type != "customer"));
+    }
+}
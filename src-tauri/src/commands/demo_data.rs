@@ -0,0 +1,763 @@
+/// Demo Data
+/// A linked demo dataset (suppliers, customers, products, received purchase orders and
+/// invoices with credit sales and part payments) created through the regular create_* code
+/// paths, so FIFO batches, payments and analytics agree with each other. Generated rows are
+/// backdated over the last few months and recorded in demo_records; clear_demo_data removes
+/// only those, leaving alone anything real data has since come to depend on.
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::analytics::{business_offset_minutes, business_today};
+use crate::commands::bundles;
+use crate::commands::customer_payments::{create_customer_payment_internal, CreateCustomerPaymentInput};
+use crate::commands::customers::{create_customer_internal, CreateCustomerInput};
+use crate::commands::invoices::{create_invoice_internal, CreateInvoiceInput, CreateInvoiceItemInput};
+use crate::commands::products::{create_product_internal, CreateProductInput};
+use crate::commands::purchase_orders::create_purchase_order_internal;
+use crate::commands::suppliers::{create_supplier_internal, CreateSupplierInput};
+use crate::db::models::{CreatePurchaseOrderInput, PurchaseOrderItemInput};
+use crate::db::Database;
+use crate::services::{inventory_service, money};
+
+/// (name, category, unit cost)
+const CATALOG: &[(&str, &str, f64)] = &[
+    ("Basmati Rice 5kg", "Groceries", 420.0),
+    ("Toor Dal 1kg", "Groceries", 130.0),
+    ("Sunflower Oil 1L", "Groceries", 145.0),
+    ("Atta 10kg", "Groceries", 380.0),
+    ("Sugar 1kg", "Groceries", 42.0),
+    ("Assam Tea 500g", "Beverages", 210.0),
+    ("Filter Coffee 250g", "Beverages", 160.0),
+    ("Mango Drink 1L", "Beverages", 60.0),
+    ("Glucose Biscuits", "Snacks", 20.0),
+    ("Salted Peanuts 200g", "Snacks", 45.0),
+    ("Potato Chips 90g", "Snacks", 25.0),
+    ("Detergent Powder 1kg", "Household", 95.0),
+    ("Dishwash Bar", "Household", 18.0),
+    ("Floor Cleaner 1L", "Household", 110.0),
+    ("Toilet Cleaner 500ml", "Household", 78.0),
+    ("Bath Soap", "Personal Care", 32.0),
+    ("Shampoo 340ml", "Personal Care", 190.0),
+    ("Toothpaste 150g", "Personal Care", 85.0),
+    ("Coconut Oil 500ml", "Personal Care", 120.0),
+    ("Notebook 200 pages", "Stationery", 48.0),
+    ("Ball Pens (10)", "Stationery", 55.0),
+    ("LED Bulb 9W", "Electricals", 70.0),
+    ("Extension Board", "Electricals", 260.0),
+    ("AA Batteries (4)", "Electricals", 88.0),
+    ("Steel Tumbler Set", "Kitchenware", 240.0),
+];
+
+/// Name suffix and cost multiplier for products beyond the base catalog
+const PACKS: &[(&str, f64)] = &[("", 1.0), (" Value Pack", 2.6), (" Family Pack", 4.0), (" Mini", 0.5)];
+
+const SUPPLIER_PREFIXES: &[&str] = &[
+    "Sri Balaji", "Annapurna", "Shree Ganesh", "Kaveri", "Himalaya", "Lakshmi", "Navratna", "Royal", "Sagar", "Metro",
+];
+const SUPPLIER_SUFFIXES: &[&str] = &["Traders", "Distributors", "Wholesale", "Agencies"];
+
+const FIRST_NAMES: &[&str] = &[
+    "Aarav", "Priya", "Rahul", "Ananya", "Vikram", "Sneha", "Arjun", "Kavya", "Rohan", "Meera", "Karan", "Divya",
+    "Suresh", "Lakshmi", "Imran", "Fatima", "Joseph", "Neha", "Manoj", "Pooja",
+];
+const LAST_NAMES: &[&str] = &[
+    "Sharma", "Iyer", "Patel", "Reddy", "Khan", "Nair", "Gupta", "Menon", "Singh", "Das", "Joshi", "Pillai",
+];
+
+/// (state, district, town)
+const PLACES: &[(&str, &str, &str)] = &[
+    ("Karnataka", "Bengaluru Urban", "Jayanagar"),
+    ("Karnataka", "Mysuru", "Mysuru"),
+    ("Tamil Nadu", "Chennai", "T. Nagar"),
+    ("Tamil Nadu", "Coimbatore", "Gandhipuram"),
+    ("Kerala", "Ernakulam", "Kochi"),
+    ("Maharashtra", "Pune", "Kothrud"),
+    ("Maharashtra", "Mumbai Suburban", "Andheri"),
+    ("Telangana", "Hyderabad", "Ameerpet"),
+    ("Gujarat", "Ahmedabad", "Navrangpura"),
+    ("Delhi", "South Delhi", "Saket"),
+];
+
+const PAYMENT_METHODS: &[&str] = &["Cash", "Cash", "UPI", "UPI", "Card"];
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DemoDataOptions {
+    pub products: Option<usize>,
+    pub suppliers: Option<usize>,
+    pub customers: Option<usize>,
+    pub purchase_orders: Option<usize>,
+    pub invoices: Option<usize>,
+    /// How far back the generated history goes
+    pub months: Option<u32>,
+    /// Fixed seed for a reproducible dataset
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DemoDataCounts {
+    pub suppliers: usize,
+    pub customers: usize,
+    pub products: usize,
+    pub categories: usize,
+    pub purchase_orders: usize,
+    pub invoices: usize,
+    pub customer_payments: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClearDemoDataResult {
+    pub removed: DemoDataCounts,
+    /// Demo rows kept because real data now refers to them
+    pub kept: usize,
+}
+
+/// xorshift64*; plenty for demo data and keeps the dependency list short
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in lo..=hi
+    fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        lo + (self.next() % (hi - lo + 1) as u64) as i64
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        (self.next() % 10_000) as f64 / 10_000.0 < probability
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[(self.next() % items.len() as u64) as usize]
+    }
+}
+
+struct DemoProduct {
+    id: i32,
+    supplier_id: i32,
+    cost: f64,
+    selling_price: f64,
+    stock: f64,
+}
+
+fn tag(conn: &Connection, entity_type: &str, id: i32) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR IGNORE INTO demo_records (entity_type, entity_id) VALUES (?1, ?2)",
+        params![entity_type, id],
+    )
+    .map_err(|e| format!("Failed to tag demo record: {}", e))?;
+    Ok(())
+}
+
+/// RFC 3339 UTC timestamp for a local business date and time
+fn utc_timestamp(local: NaiveDateTime, offset_minutes: i32) -> String {
+    (local - Duration::minutes(offset_minutes as i64))
+        .format("%Y-%m-%dT%H:%M:%S+00:00")
+        .to_string()
+}
+
+/// Invoices per day: busier weekends, a yearly season and slow growth over the period
+fn invoices_per_day(start: NaiveDate, end: NaiveDate, total: usize) -> Vec<(NaiveDate, usize)> {
+    let days: Vec<NaiveDate> = start.iter_days().take_while(|day| *day <= end).collect();
+    let span = days.len().max(1) as f64;
+    let weights: Vec<f64> = days
+        .iter()
+        .enumerate()
+        .map(|(index, day)| {
+            let season = 1.0 + 0.35 * (std::f64::consts::TAU * day.ordinal() as f64 / 365.25).sin();
+            let weekend = if day.weekday().number_from_monday() >= 6 { 1.4 } else { 1.0 };
+            season * weekend * (0.8 + 0.4 * index as f64 / span)
+        })
+        .collect();
+    let total_weight: f64 = weights.iter().sum();
+
+    // Rounding the running total keeps the grand total exact
+    let mut cumulative = 0.0;
+    let mut assigned = 0;
+    days.into_iter()
+        .zip(weights)
+        .map(|(day, weight)| {
+            cumulative += weight;
+            let target = (total as f64 * cumulative / total_weight).round() as usize;
+            let count = target - assigned;
+            assigned = target;
+            (day, count)
+        })
+        .collect()
+}
+
+/// Generate a linked demo dataset
+#[tauri::command]
+pub fn generate_demo_data(options: Option<DemoDataOptions>, db: State<Database>) -> Result<DemoDataCounts, String> {
+    log::info!("generate_demo_data called with: {:?}", options);
+
+    let mut conn = db.get_conn()?;
+    generate_demo_data_internal(&mut conn, options.unwrap_or_default())
+}
+
+pub(crate) fn generate_demo_data_internal(conn: &mut Connection, options: DemoDataOptions) -> Result<DemoDataCounts, String> {
+    let existing: i64 = conn
+        .query_row("SELECT COUNT(*) FROM demo_records", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if existing > 0 {
+        return Err("Demo data already exists; clear it before generating again".to_string());
+    }
+
+    let product_count = options.products.unwrap_or(50);
+    let supplier_count = options.suppliers.unwrap_or(20).max(1);
+    let customer_count = options.customers.unwrap_or(200);
+    let po_count = options.purchase_orders.unwrap_or(30);
+    let invoice_count = options.invoices.unwrap_or(1000);
+    let months = options.months.unwrap_or(6).max(1);
+    let mut rng = Rng::new(options.seed.unwrap_or_else(|| Utc::now().timestamp_millis() as u64));
+
+    let offset = business_offset_minutes(conn);
+    let today = business_today(conn);
+    let start = today.checked_sub_months(Months::new(months)).unwrap_or(today);
+    let now_local = Utc::now().naive_utc() + Duration::minutes(offset as i64);
+    let mut counts = DemoDataCounts::default();
+
+    // Suppliers
+    let mut supplier_ids = Vec::new();
+    for i in 0..supplier_count {
+        let mut name = format!(
+            "{} {}",
+            SUPPLIER_PREFIXES[i % SUPPLIER_PREFIXES.len()],
+            SUPPLIER_SUFFIXES[(i / SUPPLIER_PREFIXES.len()) % SUPPLIER_SUFFIXES.len()]
+        );
+        if i >= SUPPLIER_PREFIXES.len() * SUPPLIER_SUFFIXES.len() {
+            name = format!("{} {}", name, i + 1);
+        }
+        let (state, district, town) = *rng.pick(PLACES);
+        let supplier = create_supplier_internal(
+            conn,
+            CreateSupplierInput {
+                name,
+                contact_info: Some(format!("9{:09}", rng.range(0, 999_999_999))),
+                address: None,
+                email: None,
+                comments: Some("Demo data".to_string()),
+                state: Some(state.to_string()),
+                district: Some(district.to_string()),
+                town: Some(town.to_string()),
+            },
+        )?;
+        tag(conn, "supplier", supplier.id)?;
+        supplier_ids.push(supplier.id);
+    }
+    counts.suppliers = supplier_ids.len();
+
+    // Customers
+    let mut customers = Vec::new();
+    for i in 0..customer_count {
+        let (state, district, town) = *rng.pick(PLACES);
+        let first = FIRST_NAMES[i % FIRST_NAMES.len()];
+        let last = LAST_NAMES[(i / FIRST_NAMES.len() + i) % LAST_NAMES.len()];
+        let customer = create_customer_internal(
+            conn,
+            CreateCustomerInput {
+                name: format!("{} {}", first, last),
+                email: None,
+                phone: Some(format!("9{:09}", rng.range(0, 999_999_999))),
+                address: None,
+                place: Some(town.to_string()),
+                state: Some(state.to_string()),
+                district: Some(district.to_string()),
+                town: Some(town.to_string()),
+                credit_limit: None,
+            },
+        )?;
+        tag(conn, "customer", customer.id)?;
+        customers.push(customer);
+    }
+    counts.customers = customers.len();
+
+    // Products, supplied by the suppliers that get purchase orders (stock comes from those)
+    let stocking_suppliers = supplier_count.min((po_count / 2).max(1));
+    let existing_categories: Vec<i32> = {
+        let mut stmt = conn.prepare("SELECT id FROM categories").map_err(|e| e.to_string())?;
+        let ids = stmt.query_map([], |row| row.get(0)).map_err(|e| e.to_string())?.collect::<Result<Vec<_>, _>>();
+        ids.map_err(|e| e.to_string())?
+    };
+    let mut products = Vec::new();
+    for i in 0..product_count {
+        let (base, category, base_cost) = CATALOG[i % CATALOG.len()];
+        let (pack, multiplier) = PACKS[(i / CATALOG.len()) % PACKS.len()];
+        let mut name = format!("{}{}", base, pack);
+        if i >= CATALOG.len() * PACKS.len() {
+            name = format!("{} #{}", name, i + 1);
+        }
+        let cost = money::round_money(base_cost * multiplier);
+        let selling_price = (cost * (1.15 + rng.range(0, 30) as f64 / 100.0)).round();
+        let supplier_id = supplier_ids[i % stocking_suppliers];
+        let product = create_product_internal(
+            conn,
+            CreateProductInput {
+                name,
+                sku: format!("DEMO-{:04}", i + 1),
+                price: cost,
+                selling_price: Some(selling_price),
+                stock_quantity: 0.0,
+                supplier_id: Some(supplier_id),
+                amount_paid: None,
+                category: Some(category.to_string()),
+                unit: None,
+                allow_fractional: false,
+            },
+        )?;
+        tag(conn, "product", product.id)?;
+        if let Some(category_id) = product.category_id.filter(|id| !existing_categories.contains(id)) {
+            tag(conn, "category", category_id)?;
+        }
+        products.push(DemoProduct { id: product.id, supplier_id, cost, selling_price, stock: 0.0 });
+    }
+    counts.products = products.len();
+
+    // Purchase orders: an opening order per stocking supplier, the rest spread out as restocks
+    let total_days = (today - start).num_days().max(1);
+    let expected_demand = (invoice_count * 5 / products.len().max(1)) as i32;
+    let level = (expected_demand * 3 / 5).max(10) as f64;
+    let mut po_schedule: Vec<(NaiveDate, usize)> = (0..stocking_suppliers.min(po_count)).map(|s| (start, s)).collect();
+    let restocks = po_count - po_schedule.len();
+    for k in 0..restocks {
+        let day = start + Duration::days(total_days * (k as i64 + 1) / (restocks as i64 + 1));
+        po_schedule.push((day, k % stocking_suppliers));
+    }
+    po_schedule.sort();
+
+    let mut next_po = 0;
+    for (day, invoices_today) in invoices_per_day(start, today, invoice_count) {
+        while next_po < po_schedule.len() && po_schedule[next_po].0 <= day {
+            let supplier_id = supplier_ids[po_schedule[next_po].1];
+            next_po += 1;
+
+            // Cost creeps up a little over the period so FIFO layers differ
+            let drift = 1.0 + 0.06 * (day - start).num_days() as f64 / total_days as f64;
+            let mut items = Vec::new();
+            for product in products.iter().filter(|p| p.supplier_id == supplier_id && p.stock < level) {
+                items.push(PurchaseOrderItemInput {
+                    product_id: product.id,
+                    quantity: level - product.stock + rng.range(0, 10) as f64,
+                    unit_cost: money::round_money(product.cost * drift),
+                });
+            }
+            if items.is_empty() {
+                continue;
+            }
+            let order_total = money::sum(items.iter().map(|item| item.quantity * item.unit_cost));
+            let initial_payment = match rng.range(0, 9) {
+                0..=4 => Some(order_total),
+                5..=7 => Some(money::round_money(order_total / 2.0)),
+                _ => None,
+            };
+
+            let order_date = day.format("%Y-%m-%d").to_string();
+            let tx = conn.unchecked_transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+            let po = create_purchase_order_internal(
+                &tx,
+                CreatePurchaseOrderInput {
+                    supplier_id,
+                    items,
+                    order_date: Some(order_date.clone()),
+                    expected_delivery_date: None,
+                    notes: Some("Demo data".to_string()),
+                    initial_payment,
+                    currency: None,
+                    exchange_rate: None,
+                },
+            )?;
+            let created_at = format!("{} 10:00:00", order_date);
+            tx.execute(
+                "UPDATE purchase_orders SET received_date = order_date, created_at = ?1, updated_at = ?1 WHERE id = ?2",
+                params![created_at, po.id],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.execute("UPDATE supplier_payments SET created_at = ?1 WHERE po_id = ?2", params![created_at, po.id])
+                .map_err(|e| e.to_string())?;
+            tag(&tx, "purchase_order", po.id)?;
+            tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+            let mut stmt = conn
+                .prepare("SELECT product_id, quantity FROM purchase_order_items WHERE po_id = ?1")
+                .map_err(|e| e.to_string())?;
+            let received = stmt
+                .query_map([po.id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, f64>(1)?)))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>();
+            for (product_id, quantity) in received.map_err(|e| e.to_string())? {
+                if let Some(product) = products.iter_mut().find(|p| p.id == product_id) {
+                    product.stock += quantity;
+                }
+            }
+            counts.purchase_orders += 1;
+        }
+
+        for _ in 0..invoices_today {
+            let in_stock: Vec<usize> = (0..products.len()).filter(|&i| products[i].stock > 0.0).collect();
+            if in_stock.is_empty() {
+                break;
+            }
+
+            let mut items: Vec<CreateInvoiceItemInput> = Vec::new();
+            for _ in 0..rng.range(1, 4) {
+                let product = &products[*rng.pick(&in_stock)];
+                if items.iter().any(|item| item.product_id == product.id) {
+                    continue;
+                }
+                items.push(CreateInvoiceItemInput {
+                    product_id: product.id,
+                    quantity: (rng.range(1, 3) as f64).min(product.stock),
+                    unit_price: product.selling_price,
+                    discount_amount: None,
+                    tax_rate: None,
+                    reservation_id: None,
+                });
+            }
+
+            let customer = if customers.is_empty() || rng.chance(0.35) { None } else { Some(rng.pick(&customers)) };
+            let is_credit = customer.is_some() && rng.chance(0.15);
+            let estimated_total = money::sum(items.iter().map(|item| money::line_total(item.unit_price, item.quantity)));
+            let sold: Vec<(i32, f64)> = items.iter().map(|item| (item.product_id, item.quantity)).collect();
+            let initial_paid = (is_credit && rng.chance(0.5)).then(|| (estimated_total * 0.3).round());
+
+            // Business hours, never later than now
+            let local = day.and_hms_opt(rng.range(9, 20) as u32, rng.range(0, 59) as u32, rng.range(0, 59) as u32).unwrap_or_default();
+            let local = local.min(now_local - Duration::minutes(1));
+
+            let invoice = create_invoice_internal(
+                conn,
+                CreateInvoiceInput {
+                    customer_id: customer.map(|c| c.id),
+                    items,
+                    tax_amount: None,
+                    discount_amount: None,
+                    payment_method: Some(if is_credit { "Credit" } else { *rng.pick(PAYMENT_METHODS) }.to_string()),
+                    state: customer.and_then(|c| c.state.clone()),
+                    district: customer.and_then(|c| c.district.clone()),
+                    town: customer.and_then(|c| c.town.clone()),
+                    initial_paid,
+                    price_tier_id: None,
+                    gst_rate: None,
+                    allow_over_limit: false,
+                    approved_by: None,
+                    due_date: is_credit.then(|| (local.date() + Duration::days(30)).format("%Y-%m-%d").to_string()),
+                    payments: None,
+                    price_override_reason: None,
+                    price_override_approval: None,
+                    created_by: None,
+                },
+            )?;
+
+            let created_at = utc_timestamp(local, offset);
+            let tx = conn.unchecked_transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+            tx.execute("UPDATE invoices SET created_at = ?1 WHERE id = ?2", params![created_at, invoice.id])
+                .map_err(|e| e.to_string())?;
+            tx.execute(
+                "UPDATE inventory_transactions SET transaction_date = ?1, created_at = ?2
+                 WHERE reference_type = 'invoice' AND reference_id = ?3",
+                params![local.date().format("%Y-%m-%d").to_string(), created_at, invoice.id],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.execute(
+                "UPDATE customer_payments SET paid_at = ?1, created_at = ?1 WHERE invoice_id = ?2",
+                params![created_at, invoice.id],
+            )
+            .map_err(|e| e.to_string())?;
+            tag(&tx, "invoice", invoice.id)?;
+            tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+            counts.invoices += 1;
+
+            for (product_id, quantity) in sold {
+                if let Some(product) = products.iter_mut().find(|p| p.id == product_id) {
+                    product.stock -= quantity;
+                }
+            }
+
+            // Most credit customers pay something back later, some in full
+            if let (true, Some(customer)) = (is_credit, customer) {
+                let paid_on = local + Duration::days(rng.range(5, 45));
+                let outstanding = money::sub(invoice.total_amount, initial_paid.unwrap_or(0.0));
+                if paid_on < now_local && outstanding > 0.0 && rng.chance(0.7) {
+                    let amount = if rng.chance(0.5) { outstanding } else { money::round_money(outstanding / 2.0) };
+                    let payment = create_customer_payment_internal(
+                        conn,
+                        CreateCustomerPaymentInput {
+                            customer_id: customer.id,
+                            invoice_id: invoice.id,
+                            amount,
+                            payment_method: Some(rng.pick(&["Cash", "UPI"]).to_string()),
+                            note: None,
+                            paid_at: Some(utc_timestamp(paid_on, offset)),
+                        },
+                    )?;
+                    tag(conn, "customer_payment", payment.id)?;
+                    counts.customer_payments += 1;
+                }
+            }
+        }
+    }
+
+    // The rows themselves should look as old as the history they carry
+    let created_at = utc_timestamp(start.and_hms_opt(9, 0, 0).unwrap_or_default(), offset);
+    for table in ["suppliers", "customers", "products"] {
+        conn.execute(
+            &format!(
+                "UPDATE {} SET created_at = ?1
+                 WHERE id IN (SELECT entity_id FROM demo_records WHERE entity_type = ?2)",
+                table
+            ),
+            params![created_at, table.trim_end_matches('s')],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    counts.categories = conn
+        .query_row("SELECT COUNT(*) FROM demo_records WHERE entity_type = 'category'", [], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())? as usize;
+
+    log::info!("Generated demo data: {:?}", counts);
+    Ok(counts)
+}
+
+/// Remove the generated demo rows
+#[tauri::command]
+pub fn clear_demo_data(db: State<Database>) -> Result<ClearDemoDataResult, String> {
+    log::info!("clear_demo_data called");
+
+    let conn = db.get_conn()?;
+    clear_demo_data_internal(&conn)
+}
+
+fn demo_count(conn: &Connection, entity_type: &str) -> Result<usize, String> {
+    conn.query_row("SELECT COUNT(*) FROM demo_records WHERE entity_type = ?1", [entity_type], |row| row.get::<_, i64>(0))
+        .map(|count| count as usize)
+        .map_err(|e| e.to_string())
+}
+
+pub(crate) fn clear_demo_data_internal(conn: &Connection) -> Result<ClearDemoDataResult, String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let before = DemoDataCounts {
+        suppliers: demo_count(&tx, "supplier")?,
+        customers: demo_count(&tx, "customer")?,
+        products: demo_count(&tx, "product")?,
+        categories: demo_count(&tx, "category")?,
+        purchase_orders: demo_count(&tx, "purchase_order")?,
+        invoices: demo_count(&tx, "invoice")?,
+        customer_payments: demo_count(&tx, "customer_payment")?,
+    };
+
+    // Invoices go the way delete_invoice does (stock back into FIFO batches), minus the trash
+    let items: Vec<(i32, i32, i32, f64)> = {
+        let mut stmt = tx
+            .prepare(
+                "SELECT ii.invoice_id, ii.id, ii.product_id, ii.quantity FROM invoice_items ii
+                 WHERE ii.invoice_id IN (SELECT entity_id FROM demo_records WHERE entity_type = 'invoice')",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>();
+        rows.map_err(|e| e.to_string())?
+    };
+    for (invoice_id, item_id, product_id, quantity) in items {
+        for (stock_product_id, stock_quantity) in bundles::consumed_stock(&tx, item_id, product_id, quantity)? {
+            inventory_service::restore_stock_from_invoice(&tx, stock_product_id, stock_quantity, invoice_id, false)?;
+        }
+    }
+    tx.execute_batch(
+        "DELETE FROM invoice_items WHERE invoice_id IN (SELECT entity_id FROM demo_records WHERE entity_type = 'invoice');
+         DELETE FROM invoices WHERE id IN (SELECT entity_id FROM demo_records WHERE entity_type = 'invoice');
+
+         -- Products real data has come to use stay, and so do the purchase orders that stocked
+         -- them, along with everything else on those orders
+         CREATE TEMP TABLE demo_kept_products AS
+         WITH RECURSIVE kept(id) AS (
+             SELECT p.id FROM products p
+             WHERE p.id IN (SELECT entity_id FROM demo_records WHERE entity_type = 'product')
+               AND (EXISTS (SELECT 1 FROM invoice_items ii WHERE ii.product_id = p.id)
+                 OR EXISTS (SELECT 1 FROM invoice_item_components c WHERE c.product_id = p.id)
+                 OR EXISTS (SELECT 1 FROM purchase_order_items poi WHERE poi.product_id = p.id
+                            AND poi.po_id NOT IN (SELECT entity_id FROM demo_records WHERE entity_type = 'purchase_order'))
+                 OR EXISTS (SELECT 1 FROM bundle_components bc WHERE bc.component_id = p.id
+                            AND bc.bundle_id NOT IN (SELECT entity_id FROM demo_records WHERE entity_type = 'product'))
+                 OR EXISTS (SELECT 1 FROM products v WHERE v.parent_product_id = p.id
+                            AND v.id NOT IN (SELECT entity_id FROM demo_records WHERE entity_type = 'product')))
+             UNION
+             SELECT other.product_id FROM kept
+             JOIN purchase_order_items poi ON poi.product_id = kept.id
+             JOIN purchase_order_items other ON other.po_id = poi.po_id
+         )
+         SELECT id FROM kept;
+
+         CREATE TEMP TABLE demo_removed_orders AS
+         SELECT po.id FROM purchase_orders po
+         WHERE po.id IN (SELECT entity_id FROM demo_records WHERE entity_type = 'purchase_order')
+           AND NOT EXISTS (SELECT 1 FROM purchase_order_items poi
+                           WHERE poi.po_id = po.id AND poi.product_id IN (SELECT id FROM demo_kept_products));
+         DELETE FROM supplier_payments WHERE po_id IN (SELECT id FROM demo_removed_orders);
+         DELETE FROM purchase_orders WHERE id IN (SELECT id FROM demo_removed_orders);
+
+         CREATE TEMP TABLE demo_removed_products AS
+         SELECT entity_id AS id FROM demo_records
+         WHERE entity_type = 'product' AND entity_id NOT IN (SELECT id FROM demo_kept_products);
+         DELETE FROM inventory_transactions WHERE product_id IN (SELECT id FROM demo_removed_products);
+         DELETE FROM inventory_batches WHERE product_id IN (SELECT id FROM demo_removed_products);
+         DELETE FROM products WHERE id IN (SELECT id FROM demo_removed_products);
+
+         DELETE FROM customers
+         WHERE id IN (SELECT entity_id FROM demo_records WHERE entity_type = 'customer')
+           AND NOT EXISTS (SELECT 1 FROM invoices i WHERE i.customer_id = customers.id)
+           AND NOT EXISTS (SELECT 1 FROM customer_payments cp WHERE cp.customer_id = customers.id);
+
+         DELETE FROM suppliers
+         WHERE id IN (SELECT entity_id FROM demo_records WHERE entity_type = 'supplier')
+           AND NOT EXISTS (SELECT 1 FROM purchase_orders po WHERE po.supplier_id = suppliers.id)
+           AND NOT EXISTS (SELECT 1 FROM products p WHERE p.supplier_id = suppliers.id)
+           AND NOT EXISTS (SELECT 1 FROM supplier_payments sp WHERE sp.supplier_id = suppliers.id);
+
+         DELETE FROM categories
+         WHERE id IN (SELECT entity_id FROM demo_records WHERE entity_type = 'category')
+           AND NOT EXISTS (SELECT 1 FROM products p WHERE p.category_id = categories.id)
+           AND NOT EXISTS (SELECT 1 FROM categories child WHERE child.parent_id = categories.id);
+
+         DROP TABLE demo_kept_products;
+         DROP TABLE demo_removed_orders;
+         DROP TABLE demo_removed_products;
+
+         DELETE FROM demo_records WHERE entity_type = 'invoice' AND entity_id NOT IN (SELECT id FROM invoices);
+         DELETE FROM demo_records WHERE entity_type = 'customer_payment' AND entity_id NOT IN (SELECT id FROM customer_payments);
+         DELETE FROM demo_records WHERE entity_type = 'purchase_order' AND entity_id NOT IN (SELECT id FROM purchase_orders);
+         DELETE FROM demo_records WHERE entity_type = 'product' AND entity_id NOT IN (SELECT id FROM products);
+         DELETE FROM demo_records WHERE entity_type = 'customer' AND entity_id NOT IN (SELECT id FROM customers);
+         DELETE FROM demo_records WHERE entity_type = 'supplier' AND entity_id NOT IN (SELECT id FROM suppliers);
+         DELETE FROM demo_records WHERE entity_type = 'category' AND entity_id NOT IN (SELECT id FROM categories);",
+    )
+    .map_err(|e| format!("Failed to clear demo data: {}", e))?;
+
+    let removed = DemoDataCounts {
+        suppliers: before.suppliers - demo_count(&tx, "supplier")?,
+        customers: before.customers - demo_count(&tx, "customer")?,
+        products: before.products - demo_count(&tx, "product")?,
+        categories: before.categories - demo_count(&tx, "category")?,
+        purchase_orders: before.purchase_orders - demo_count(&tx, "purchase_order")?,
+        invoices: before.invoices - demo_count(&tx, "invoice")?,
+        customer_payments: before.customer_payments - demo_count(&tx, "customer_payment")?,
+    };
+    let kept: i64 = tx
+        .query_row("SELECT COUNT(*) FROM demo_records", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Cleared demo data: {:?}, kept {} row(s) in use", removed, kept);
+    Ok(ClearDemoDataResult { removed, kept: kept as usize })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analytics::ReportRange;
+    use crate::commands::sales_summary::live_daily_totals;
+    use crate::test_support::{batch_quantity, TestDb};
+
+    fn small() -> DemoDataOptions {
+        DemoDataOptions {
+            products: Some(12),
+            suppliers: Some(4),
+            customers: Some(15),
+            purchase_orders: Some(8),
+            invoices: Some(120),
+            months: Some(3),
+            seed: Some(42),
+        }
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn test_demo_data_is_consistent_and_clears_cleanly() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+
+        let counts = generate_demo_data_internal(&mut conn, small()).unwrap();
+        assert_eq!(counts.products, 12);
+        assert_eq!(counts.customers, 15);
+        assert!(counts.invoices > 100, "only {} invoices", counts.invoices);
+        assert!(counts.purchase_orders > 0);
+        assert!(generate_demo_data_internal(&mut conn, small()).is_err());
+
+        // Stock on hand matches the FIFO batches for every product
+        let mut stmt = conn.prepare("SELECT id, stock_quantity FROM products").unwrap();
+        let stock: Vec<(i32, f64)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap().map(Result::unwrap).collect();
+        drop(stmt);
+        for (product_id, quantity) in stock {
+            assert!(quantity >= 0.0);
+            assert_eq!(batch_quantity(&conn, product_id), quantity);
+        }
+
+        // Sales are spread over the whole period
+        let today = business_today(&conn);
+        let range = ReportRange::new(today - Duration::days(100), today, business_offset_minutes(&conn));
+        assert!(live_daily_totals(&conn, &range).unwrap().len() > 30);
+
+        // A real customer buying a demo product keeps that product (and its supplier) around
+        let real_customer = crate::test_support::insert_customer(&conn, "Real Buyer");
+        let product_id: i32 = conn
+            .query_row("SELECT id FROM products WHERE stock_quantity > 0 ORDER BY id LIMIT 1", [], |row| row.get(0))
+            .unwrap();
+        create_invoice_internal(
+            &mut conn,
+            CreateInvoiceInput {
+                customer_id: Some(real_customer),
+                items: vec![CreateInvoiceItemInput { product_id, quantity: 1.0, unit_price: 10.0, discount_amount: None, tax_rate: None, reservation_id: None }],
+                tax_amount: None,
+                discount_amount: None,
+                payment_method: Some("Cash".to_string()),
+                state: None,
+                district: None,
+                town: None,
+                initial_paid: None,
+                price_tier_id: None,
+                gst_rate: None,
+                allow_over_limit: false,
+                approved_by: None,
+                due_date: None,
+                payments: None,
+                price_override_reason: None,
+                price_override_approval: None,
+                created_by: None,
+            },
+        )
+        .unwrap();
+
+        let result = clear_demo_data_internal(&conn).unwrap();
+        assert_eq!(result.removed.invoices, counts.invoices);
+        assert_eq!(result.removed.customers, 15);
+        assert!(result.removed.products > 0 && result.removed.products < 12);
+        assert!(result.kept >= 3);
+        assert_eq!(count(&conn, "invoices"), 1);
+        assert_eq!(count(&conn, "products"), (12 - result.removed.products) as i64);
+        assert_eq!(count(&conn, "customers"), 1);
+        assert_eq!(count(&conn, "customer_payments"), 0);
+        let dangling: i64 = conn.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| row.get(0)).unwrap();
+        assert_eq!(dangling, 0);
+        let stock: f64 = conn.query_row("SELECT stock_quantity FROM products WHERE id = ?1", [product_id], |row| row.get(0)).unwrap();
+        assert_eq!(batch_quantity(&conn, product_id), stock);
+    }
+}
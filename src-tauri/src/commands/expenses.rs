@@ -0,0 +1,133 @@
+use crate::db::models::Expense;
+use crate::db::Database;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateExpenseInput {
+    pub expense_date: String,
+    pub category: String,
+    pub amount: f64,
+    pub note: Option<String>,
+    pub payment_method: Option<String>,
+}
+
+fn row_to_expense(row: &rusqlite::Row) -> rusqlite::Result<Expense> {
+    Ok(Expense {
+        id: row.get(0)?,
+        expense_date: row.get(1)?,
+        category: row.get(2)?,
+        amount: row.get(3)?,
+        note: row.get(4)?,
+        payment_method: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+/// Record an operating expense
+#[tauri::command]
+pub fn create_expense(input: CreateExpenseInput, db: State<Database>) -> Result<Expense, String> {
+    log::info!(
+        "create_expense called: {} {} on {}",
+        input.category,
+        input.amount,
+        input.expense_date
+    );
+
+    if input.amount <= 0.0 {
+        return Err("Amount must be greater than zero".into());
+    }
+    let category = input.category.trim();
+    if category.is_empty() {
+        return Err("Category is required".into());
+    }
+    let expense_date = NaiveDate::parse_from_str(input.expense_date.get(..10).unwrap_or(""), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid expense date '{}', expected YYYY-MM-DD", input.expense_date))?
+        .format("%Y-%m-%d")
+        .to_string();
+
+    let conn = db.get_conn()?;
+
+    conn.execute(
+        "INSERT INTO expenses (expense_date, category, amount, note, payment_method, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))",
+        (
+            &expense_date,
+            category,
+            input.amount,
+            input.note.as_deref().filter(|n| !n.trim().is_empty()),
+            input.payment_method.as_deref(),
+        ),
+    )
+    .map_err(|e| format!("Failed to create expense: {}", e))?;
+
+    let id = conn.last_insert_rowid();
+
+    conn.query_row(
+        "SELECT id, expense_date, category, amount, note, payment_method, created_at
+         FROM expenses WHERE id = ?1",
+        [id],
+        row_to_expense,
+    )
+    .map_err(|e| format!("Failed to fetch created expense: {}", e))
+}
+
+/// Get expenses, newest first, optionally limited to a date range (inclusive) and category
+#[tauri::command]
+pub fn get_expenses(
+    start_date: Option<String>,
+    end_date: Option<String>,
+    category: Option<String>,
+    db: State<Database>,
+) -> Result<Vec<Expense>, String> {
+    log::info!(
+        "get_expenses called: {:?} to {:?}, category: {:?}",
+        start_date,
+        end_date,
+        category
+    );
+
+    let conn = db.get_conn()?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, expense_date, category, amount, note, payment_method, created_at
+             FROM expenses
+             WHERE (?1 IS NULL OR expense_date >= ?1)
+               AND (?2 IS NULL OR expense_date <= ?2)
+               AND (?3 IS NULL OR category = ?3 COLLATE NOCASE)
+             ORDER BY expense_date DESC, id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let day = |d: &Option<String>| d.as_deref().map(|d| d.get(..10).unwrap_or(d).to_string());
+    let expenses = stmt
+        .query_map(
+            (day(&start_date), day(&end_date), category.as_deref().filter(|c| !c.is_empty())),
+            row_to_expense,
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(expenses)
+}
+
+/// Delete an expense
+#[tauri::command]
+pub fn delete_expense(id: i32, db: State<Database>) -> Result<(), String> {
+    log::info!("delete_expense called for id: {}", id);
+
+    let conn = db.get_conn()?;
+
+    let affected = conn
+        .execute("DELETE FROM expenses WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to delete expense: {}", e))?;
+
+    if affected == 0 {
+        return Err("Expense not found".into());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+/// Held Sales
+/// Bills parked mid-entry at the counter and resumed later. The draft is stored as the
+/// CreateInvoiceInput it will be created from; nothing is reserved, so stock is only checked
+/// once the resumed bill is saved. Holds older than held_sale_retention_hours are purged on startup.
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::invoices::CreateInvoiceInput;
+use crate::db::Database;
+use crate::services::money;
+
+/// app_settings key for how long a held sale is kept, in hours (0 keeps them forever)
+const HELD_SALE_RETENTION_KEY: &str = "held_sale_retention_hours";
+const DEFAULT_HELD_SALE_RETENTION_HOURS: i64 = 24;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HeldSale {
+    pub id: i32,
+    pub label: String,
+    pub item_count: i32,
+    /// Line totals less item discounts, before invoice discount and tax
+    pub subtotal: f64,
+    pub held_by: Option<String>,
+    pub created_at: String,
+    /// Minutes since the sale was held
+    pub age_minutes: i64,
+}
+
+/// Park the current bill under a label
+#[tauri::command]
+pub fn hold_sale(
+    draft: CreateInvoiceInput,
+    label: String,
+    held_by: Option<String>,
+    db: State<Database>,
+) -> Result<HeldSale, String> {
+    log::info!("hold_sale called with label: {}", label);
+
+    let conn = db.get_conn()?;
+    hold_sale_internal(&conn, &draft, &label, held_by.as_deref())
+}
+
+pub(crate) fn hold_sale_internal(
+    conn: &Connection,
+    draft: &CreateInvoiceInput,
+    label: &str,
+    held_by: Option<&str>,
+) -> Result<HeldSale, String> {
+    if draft.items.is_empty() {
+        return Err("Cannot hold a sale with no items".to_string());
+    }
+
+    let label = match label.trim() {
+        "" => format!("{} item(s)", draft.items.len()),
+        label => label.to_string(),
+    };
+    let subtotal = money::sum(draft.items.iter().map(|item| {
+        money::sub(money::line_total(item.unit_price, item.quantity), item.discount_amount.unwrap_or(0.0))
+    }));
+    let json = serde_json::to_string(draft).map_err(|e| format!("Failed to serialize sale: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO held_sales (label, draft, item_count, subtotal, held_by, created_at) VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))",
+        params![label, json, draft.items.len() as i32, subtotal, held_by],
+    )
+    .map_err(|e| format!("Failed to hold sale: {}", e))?;
+
+    let id = conn.last_insert_rowid() as i32;
+    log::info!("Held sale {} ({})", id, label);
+    get_held_sales_internal(conn)?
+        .into_iter()
+        .find(|sale| sale.id == id)
+        .ok_or_else(|| "Held sale not found".to_string())
+}
+
+/// Get held sales, oldest first
+#[tauri::command]
+pub fn get_held_sales(db: State<Database>) -> Result<Vec<HeldSale>, String> {
+    log::info!("get_held_sales called");
+
+    let conn = db.get_conn()?;
+    get_held_sales_internal(&conn)
+}
+
+pub(crate) fn get_held_sales_internal(conn: &Connection) -> Result<Vec<HeldSale>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, label, item_count, subtotal, held_by, created_at,
+                    CAST((julianday('now') - julianday(created_at)) * 1440 AS INTEGER)
+             FROM held_sales
+             ORDER BY created_at, id",
+        )
+        .map_err(|e| e.to_string())?;
+    let sales = stmt
+        .query_map([], |row| {
+            Ok(HeldSale {
+                id: row.get(0)?,
+                label: row.get(1)?,
+                item_count: row.get(2)?,
+                subtotal: row.get(3)?,
+                held_by: row.get(4)?,
+                created_at: row.get(5)?,
+                age_minutes: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>();
+    sales.map_err(|e| format!("Failed to load held sales: {}", e))
+}
+
+/// Take a held sale back into billing: returns its draft and removes the hold
+#[tauri::command]
+pub fn resume_held_sale(id: i32, db: State<Database>) -> Result<CreateInvoiceInput, String> {
+    log::info!("resume_held_sale called for id: {}", id);
+
+    let conn = db.get_conn()?;
+    resume_held_sale_internal(&conn, id)
+}
+
+pub(crate) fn resume_held_sale_internal(conn: &Connection, id: i32) -> Result<CreateInvoiceInput, String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let json: String = tx
+        .query_row("SELECT draft FROM held_sales WHERE id = ?1", [id], |row| row.get(0))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Held sale {} not found", id))?;
+    let draft: CreateInvoiceInput =
+        serde_json::from_str(&json).map_err(|e| format!("Failed to read held sale: {}", e))?;
+
+    tx.execute("DELETE FROM held_sales WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to remove held sale: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(draft)
+}
+
+/// Throw away a held sale
+#[tauri::command]
+pub fn discard_held_sale(id: i32, db: State<Database>) -> Result<(), String> {
+    log::info!("discard_held_sale called for id: {}", id);
+
+    let conn = db.get_conn()?;
+    let removed = conn
+        .execute("DELETE FROM held_sales WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to discard held sale: {}", e))?;
+    if removed == 0 {
+        return Err(format!("Held sale {} not found", id));
+    }
+    Ok(())
+}
+
+/// Delete held sales older than the held_sale_retention_hours setting (default 24, 0 disables).
+/// Runs on app startup.
+pub fn purge_stale_held_sales(conn: &Connection) -> Result<usize, String> {
+    let retention_hours = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            [HELD_SALE_RETENTION_KEY],
+            |row| row.get::<_, String>(0),
+        )
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(DEFAULT_HELD_SALE_RETENTION_HOURS);
+
+    if retention_hours <= 0 {
+        return Ok(0);
+    }
+
+    conn.execute(
+        "DELETE FROM held_sales WHERE datetime(created_at) < datetime('now', ?1)",
+        [format!("-{} hours", retention_hours)],
+    )
+    .map_err(|e| format!("Failed to purge held sales: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{invoice_input, TestDb};
+
+    fn draft(quantity: i32) -> CreateInvoiceInput {
+        let mut input = CreateInvoiceInput { gst_rate: Some(5.0), ..invoice_input(None, vec![(1, quantity, 12.5)]) };
+        input.items[0].discount_amount = Some(1.0);
+        input
+    }
+
+    #[test]
+    fn test_hold_resume_and_purge() {
+        let db = TestDb::new();
+        let conn = db.conn();
+
+        let held = hold_sale_internal(&conn, &draft(4), "  ", Some("cashier")).unwrap();
+        assert_eq!(held.label, "1 item(s)");
+        assert_eq!(held.subtotal, 49.0);
+        let stale = hold_sale_internal(&conn, &draft(2), "Blue shirt guy", None).unwrap();
+        assert_eq!(get_held_sales_internal(&conn).unwrap().len(), 2);
+
+        let resumed = resume_held_sale_internal(&conn, held.id).unwrap();
+        assert_eq!(resumed.items[0].quantity, 4.0);
+        assert_eq!(resumed.gst_rate, Some(5.0));
+        assert!(resume_held_sale_internal(&conn, held.id).is_err());
+
+        conn.execute("UPDATE held_sales SET created_at = datetime('now', '-2 days') WHERE id = ?1", [stale.id]).unwrap();
+        assert_eq!(purge_stale_held_sales(&conn).unwrap(), 1);
+        assert!(get_held_sales_internal(&conn).unwrap().is_empty());
+    }
+}
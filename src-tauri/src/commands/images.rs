@@ -4,14 +4,25 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Manager, State};
 
 use crate::db::Database;
+use crate::services::image_search::{self, ImageSearchProvider};
 
 // Constants
 const PICTURES_FOLDER: &str = "pictures-Inventry"; 
 const THUMBNAIL_SIZE: u32 = 80;
 
+/// app_settings key for the longest edge, in pixels, a stored image may have
+const IMAGE_MAX_DIMENSION_KEY: &str = "image_max_dimension";
+const DEFAULT_IMAGE_MAX_DIMENSION: u32 = 1600;
+
+/// app_settings key for the JPEG quality (1-100) stored images are re-encoded at
+const IMAGE_QUALITY_KEY: &str = "image_quality";
+const DEFAULT_IMAGE_QUALITY: u8 = 85;
+
 /// Google Image Search result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GoogleImageResult {
@@ -19,6 +30,9 @@ pub struct GoogleImageResult {
     pub link: String,           // Full-size image URL
     pub thumbnail_link: String, // Small preview from Google
     pub display_link: String,   // Source website
+    /// Provider that found the image: "google", "bing" or "searxng"
+    #[serde(default)]
+    pub provider: String,
 }
 
 /// Get the base pictures directory path: AppData/pictures-Inventry
@@ -86,9 +100,132 @@ fn generate_thumbnail(source_path: &PathBuf, thumb_path: &PathBuf) -> Result<(),
     Ok(())
 }
 
-/// Get the filename for an entity image
-fn get_entity_filename(entity_id: i32, extension: &str, entity_prefix: &str) -> String {
-    format!("{}_{}.{}", entity_prefix, entity_id, extension.to_lowercase())
+/// Size and quality limits for stored images, from settings
+struct ImageLimits {
+    max_dimension: u32,
+    quality: u8,
+}
+
+fn image_limits(conn: &rusqlite::Connection) -> ImageLimits {
+    let setting = |key: &str| -> Option<u32> {
+        conn.query_row("SELECT value FROM app_settings WHERE key = ?1", [key], |row| row.get::<_, String>(0))
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+    };
+
+    ImageLimits {
+        max_dimension: setting(IMAGE_MAX_DIMENSION_KEY).filter(|d| *d > 0).unwrap_or(DEFAULT_IMAGE_MAX_DIMENSION),
+        quality: setting(IMAGE_QUALITY_KEY).map(|q| q.clamp(1, 100) as u8).unwrap_or(DEFAULT_IMAGE_QUALITY),
+    }
+}
+
+/// Image bytes ready to be written to the pictures directory
+struct NormalizedImage {
+    data: Vec<u8>,
+    extension: &'static str,
+    /// Hex SHA-256 of `data`
+    hash: String,
+}
+
+/// Decode an image, downscale it so its long edge fits `max_dimension` and re-encode it:
+/// photos as JPEG at the configured quality, images with transparency as PNG.
+/// With `keep_format` JPEGs and PNGs stay in their own format and other formats are left as is.
+/// The original bytes are kept when re-encoding wouldn't make them smaller.
+fn normalize_image(data: &[u8], limits: &ImageLimits, keep_format: bool) -> Result<NormalizedImage, String> {
+    use image::{ImageFormat, ImageOutputFormat};
+
+    let format = image::guess_format(data).map_err(|e| format!("Unrecognised image data: {}", e))?;
+    let original_extension = match format {
+        ImageFormat::Jpeg => "jpg",
+        ImageFormat::Png => "png",
+        ImageFormat::Gif => "gif",
+        ImageFormat::WebP => "webp",
+        _ => return Err("Invalid image format. Supported: jpg, jpeg, png, gif, webp".to_string()),
+    };
+    let original = || NormalizedImage {
+        data: data.to_vec(),
+        extension: original_extension,
+        hash: content_hash(data),
+    };
+
+    if keep_format && !matches!(format, ImageFormat::Jpeg | ImageFormat::Png) {
+        return Ok(original());
+    }
+
+    let img = image::load_from_memory(data).map_err(|e| format!("Failed to decode image: {}", e))?;
+    let resized = img.width().max(img.height()) > limits.max_dimension;
+    let img = if resized {
+        img.resize(limits.max_dimension, limits.max_dimension, FilterType::Lanczos3)
+    } else {
+        img
+    };
+
+    let as_png = if keep_format { format == ImageFormat::Png } else { img.color().has_alpha() };
+    let mut encoded = std::io::Cursor::new(Vec::new());
+    let extension = if as_png {
+        img.write_to(&mut encoded, ImageOutputFormat::Png)
+            .map_err(|e| format!("Failed to encode image: {}", e))?;
+        "png"
+    } else {
+        image::DynamicImage::ImageRgb8(img.to_rgb8())
+            .write_to(&mut encoded, ImageOutputFormat::Jpeg(limits.quality))
+            .map_err(|e| format!("Failed to encode image: {}", e))?;
+        "jpg"
+    };
+    let encoded = encoded.into_inner();
+
+    if !resized && extension == original_extension && encoded.len() >= data.len() {
+        return Ok(original());
+    }
+
+    Ok(NormalizedImage {
+        hash: content_hash(&encoded),
+        data: encoded,
+        extension,
+    })
+}
+
+fn content_hash(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    hex::encode(Sha256::digest(data))
+}
+
+/// Filename for stored image content; identical content gets the same name, so a photo
+/// used by several entities of the same kind is stored once
+fn get_content_filename(entity_prefix: &str, image: &NormalizedImage) -> String {
+    format!("{}_{}.{}", entity_prefix, &image.hash[..16], image.extension)
+}
+
+/// How many products, suppliers and customers point at an image path
+fn image_reference_count(conn: &rusqlite::Connection, rel_path: &str) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM products WHERE image_path = ?1)
+              + (SELECT COUNT(*) FROM suppliers WHERE image_path = ?1)
+              + (SELECT COUNT(*) FROM customers WHERE image_path = ?1)",
+        [rel_path],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to count image references: {}", e))
+}
+
+/// Remove an image and its thumbnail unless something other than the releasing entity
+/// (`own_references`, 1 while its row still points at the file) still uses it
+fn release_image(
+    conn: &rusqlite::Connection,
+    base_dir: &Path,
+    rel_path: &str,
+    own_references: i64,
+) -> Result<(), String> {
+    let shared_with = image_reference_count(conn, rel_path)? - own_references;
+    if shared_with > 0 {
+        log::info!("Keeping image {} still used by {} other record(s)", rel_path, shared_with);
+        return Ok(());
+    }
+
+    for file in image_files_for(rel_path) {
+        let _ = fs::remove_file(base_dir.join(file));
+    }
+    Ok(())
 }
 
 // --- Generic Helper Functions ---
@@ -111,31 +248,39 @@ fn save_product_image_internal(
         return Err("Invalid image format. Supported: jpg, jpeg, png, gif, webp".to_string());
     }
 
+    let conn = db.get_conn()?;
+    let image = normalize_image(&file_data, &image_limits(&conn), false)?;
+
     // Delete existing images for this entity first
     let _ = delete_product_image_internal(product_id, app_handle, db);
 
     // Generate filenames
-    let image_filename = get_entity_filename(product_id, &ext, "product");
+    let image_filename = get_content_filename("product", &image);
     
     let image_path = normal_dir.join(&image_filename);
     let thumb_path = thumb_dir.join(&image_filename); // Same filename, different folder
 
-    // Save full-size
-    let mut file = fs::File::create(&image_path).map_err(|e| format!("Failed to create image file: {}", e))?;
-    file.write_all(&file_data).map_err(|e| format!("Failed to write image data: {}", e))?;
+    // Save full-size, unless another product already stored the same photo
+    if image_path.exists() {
+        log::info!("Product {} image duplicates an existing file, reusing {}", product_id, image_filename);
+    } else {
+        let mut file = fs::File::create(&image_path).map_err(|e| format!("Failed to create image file: {}", e))?;
+        file.write_all(&image.data).map_err(|e| format!("Failed to write image data: {}", e))?;
+    }
 
     // Generate thumbnail
-    generate_thumbnail(&image_path, &thumb_path)?;
+    if !thumb_path.exists() {
+        generate_thumbnail(&image_path, &thumb_path)?;
+    }
 
     // Store RELATIVE path in DB: Inventory/normal/[filename]
     // The simplified structure is "Inventory/normal/filename.jpg"
     let relative_path = format!("Inventory/normal/{}", image_filename);
 
     // Update DB
-    let conn = db.get_conn()?;
     conn.execute(
-        "UPDATE products SET image_path = ?1, updated_at = datetime('now') WHERE id = ?2",
-        rusqlite::params![&relative_path, product_id]
+        "UPDATE products SET image_path = ?1, image_hash = ?2, updated_at = datetime('now') WHERE id = ?3",
+        rusqlite::params![&relative_path, &image.hash, product_id]
     ).map_err(|e| format!("Failed to update product image path: {}", e))?;
 
     log::info!("Saved product image: {}", relative_path);
@@ -160,24 +305,43 @@ fn save_entity_image_internal(
     }
 
     let ext = file_extension.trim_start_matches('.').to_lowercase();
-    let image_filename = get_entity_filename(entity_id, &ext, entity_prefix);
+    if !["jpg", "jpeg", "png", "gif", "webp"].contains(&ext.as_str()) {
+        return Err("Invalid image format. Supported: jpg, jpeg, png, gif, webp".to_string());
+    }
+
+    let conn = db.get_conn()?;
+    let image = normalize_image(&file_data, &image_limits(&conn), false)?;
+
+    // Release the entity's previous image
+    let query = format!("SELECT image_path FROM {} WHERE id = ?1", table_name);
+    let previous: Option<String> = conn.query_row(&query, [entity_id], |row| row.get(0)).ok().flatten();
+    if let Some(previous) = previous.filter(|p| !p.is_empty()) {
+        release_image(&conn, &base_dir, &previous, 1)?;
+    }
+
+    let image_filename = get_content_filename(entity_prefix, &image);
     let image_path = folder_path.join(&image_filename);
 
-    // Save full-size
-    let mut file = fs::File::create(&image_path).map_err(|e| format!("Failed to create image file: {}", e))?;
-    file.write_all(&file_data).map_err(|e| format!("Failed to write image data: {}", e))?;
+    // Save full-size, unless another record already stored the same photo
+    if image_path.exists() {
+        log::info!("{} {} image duplicates an existing file, reusing {}", entity_prefix, entity_id, image_filename);
+    } else {
+        let mut file = fs::File::create(&image_path).map_err(|e| format!("Failed to create image file: {}", e))?;
+        file.write_all(&image.data).map_err(|e| format!("Failed to write image data: {}", e))?;
+    }
 
     // Generate _thumb file
-    let thumb_filename = format!("{}_{}_thumb.{}", entity_prefix, entity_id, ext);
+    let thumb_filename = format!("{}_{}_thumb.{}", entity_prefix, &image.hash[..16], image.extension);
     let thumb_path = folder_path.join(&thumb_filename);
-    generate_thumbnail(&image_path, &thumb_path)?;
+    if !thumb_path.exists() {
+        generate_thumbnail(&image_path, &thumb_path)?;
+    }
 
     // Relative path: "Folder/filename.jpg"
     let relative_path = format!("{}/{}", target_folder, image_filename);
 
-    let conn = db.get_conn()?;
-    let query = format!("UPDATE {} SET image_path = ?1, updated_at = datetime('now') WHERE id = ?2", table_name);
-    conn.execute(&query, rusqlite::params![&relative_path, entity_id])
+    let query = format!("UPDATE {} SET image_path = ?1, image_hash = ?2, updated_at = datetime('now') WHERE id = ?3", table_name);
+    conn.execute(&query, rusqlite::params![&relative_path, &image.hash, entity_id])
         .map_err(|e| format!("Failed to update {} image path: {}", table_name, e))?;
 
     Ok(relative_path)
@@ -199,21 +363,10 @@ fn delete_product_image_internal(
         if rel_path.is_empty() { return Ok(()); }
         
         let base_dir = get_base_pictures_dir(app_handle)?;
-        
-        // Handle migration case: Old path might be just filename "product_1.jpg"
-        if !rel_path.contains('/') && !rel_path.contains('\\') {
-             let p = base_dir.join(&rel_path);
-             let _ = fs::remove_file(p);
-             return Ok(());
-        }
-
-        let normal_path = base_dir.join(&rel_path);
-        let _ = fs::remove_file(&normal_path);
 
-        // Thumbnail path: replace /normal/ with /thumbnail/
-        let thumb_rel = rel_path.replace("/normal/", "/thumbnail/");
-        let thumb_path = base_dir.join(&thumb_rel);
-        let _ = fs::remove_file(&thumb_path);
+        // Removes the file and its /thumbnail/ copy (or just the file for old
+        // filename-only paths like "product_1.jpg") once no other record uses it
+        release_image(&conn, &base_dir, &rel_path, 1)?;
     }
     Ok(())
 }
@@ -379,16 +532,9 @@ pub fn delete_supplier_image(
     let conn = db.get_conn()?;
     let path: Option<String> = conn.query_row("SELECT image_path FROM suppliers WHERE id=?1", [supplier_id], |row| row.get(0)).ok().flatten();
     
-    if let Some(p) = path {
+    if let Some(p) = path.filter(|p| !p.is_empty()) {
         let base_dir = get_base_pictures_dir(&app_handle)?;
-        let full_path = base_dir.join(&p);
-        let _ = fs::remove_file(full_path);
-        
-        let parts: Vec<&str> = p.rsplitn(2, '.').collect();
-         if parts.len() == 2 {
-             let thumb_rel = format!("{}_thumb.{}", parts[1], parts[0]);
-             let _ = fs::remove_file(base_dir.join(thumb_rel));
-         }
+        release_image(&conn, &base_dir, &p, 1)?;
     }
 
     conn.execute(
@@ -461,16 +607,9 @@ pub fn delete_customer_image(
     let conn = db.get_conn()?;
     let path: Option<String> = conn.query_row("SELECT image_path FROM customers WHERE id=?1", [customer_id], |row| row.get(0)).ok().flatten();
     
-    if let Some(p) = path {
+    if let Some(p) = path.filter(|p| !p.is_empty()) {
         let base_dir = get_base_pictures_dir(&app_handle)?;
-        let full_path = base_dir.join(&p);
-        let _ = fs::remove_file(full_path);
-        
-        let parts: Vec<&str> = p.rsplitn(2, '.').collect();
-         if parts.len() == 2 {
-             let thumb_rel = format!("{}_thumb.{}", parts[1], parts[0]);
-             let _ = fs::remove_file(base_dir.join(thumb_rel));
-         }
+        release_image(&conn, &base_dir, &p, 1)?;
     }
 
     conn.execute(
@@ -481,6 +620,339 @@ pub fn delete_customer_image(
     Ok(())
 }
 
+// 4. CLEANUP
+
+/// Subfolder of the pictures directory that orphan cleanup moves files into
+const IMAGE_TRASH_FOLDER: &str = ".trash";
+
+/// Image extensions the orphan scan looks at; anything else in the folder is left alone
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
+
+/// An image file no product, supplier, customer or the invoice logo points at
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanImageFile {
+    /// Path relative to the pictures directory, with '/' separators
+    pub path: String,
+    pub bytes: u64,
+}
+
+/// A DB image_path whose file no longer exists
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DanglingImagePath {
+    pub entity_type: String,
+    pub entity_id: i32,
+    pub entity_name: String,
+    pub image_path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageOrphanReport {
+    pub unreferenced_files: Vec<OrphanImageFile>,
+    pub dangling_paths: Vec<DanglingImagePath>,
+    pub reclaimable_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageCleanupResult {
+    pub files_moved: usize,
+    pub bytes_moved: u64,
+    pub paths_cleared: usize,
+    /// Folder (relative to the pictures directory) the files were moved into
+    pub trash_folder: Option<String>,
+}
+
+/// Relative paths of an image_path's file and its thumbnail
+fn image_files_for(rel_path: &str) -> Vec<String> {
+    let rel_path = rel_path.replace('\\', "/");
+    let mut files = vec![rel_path.clone()];
+
+    if rel_path.contains("/normal/") {
+        // Products: Inventory/normal/x.jpg -> Inventory/thumbnail/x.jpg
+        files.push(rel_path.replace("/normal/", "/thumbnail/"));
+    } else if rel_path.contains('/') {
+        // Suppliers/customers: Folder/x.jpg -> Folder/x_thumb.jpg
+        if let Some((stem, ext)) = rel_path.rsplit_once('.') {
+            files.push(format!("{}_thumb.{}", stem, ext));
+        }
+    }
+    files
+}
+
+/// Remove a deleted entity's image and thumbnail unless another record shares them;
+/// missing files are ignored
+pub(crate) fn remove_image_files(app_handle: &AppHandle, conn: &rusqlite::Connection, rel_path: Option<&str>) {
+    let rel_path = match rel_path.filter(|p| !p.is_empty()) {
+        Some(rel_path) => rel_path,
+        None => return,
+    };
+    let base_dir = match get_base_pictures_dir(app_handle) {
+        Ok(base_dir) => base_dir,
+        Err(e) => {
+            log::warn!("Failed to remove image {}: {}", rel_path, e);
+            return;
+        }
+    };
+
+    if let Err(e) = release_image(conn, &base_dir, rel_path, 0) {
+        log::warn!("Failed to remove image {}: {}", rel_path, e);
+    }
+}
+
+/// Scan the pictures directory for (a) image files nothing references and (b) DB image paths
+/// whose files are missing
+#[tauri::command]
+pub fn scan_image_orphans(app_handle: AppHandle, db: State<Database>) -> Result<ImageOrphanReport, String> {
+    log::info!("scan_image_orphans called");
+
+    let base_dir = get_base_pictures_dir(&app_handle)?;
+    let conn = db.get_conn()?;
+    scan_image_orphans_internal(&conn, &base_dir)
+}
+
+/// Move unreferenced image files into the pictures .trash folder and clear dangling DB paths
+#[tauri::command]
+pub fn cleanup_image_orphans(app_handle: AppHandle, db: State<Database>) -> Result<ImageCleanupResult, String> {
+    log::info!("cleanup_image_orphans called");
+
+    let base_dir = get_base_pictures_dir(&app_handle)?;
+    let conn = db.get_conn()?;
+    cleanup_image_orphans_internal(&conn, &base_dir)
+}
+
+/// A product, supplier or customer row that points at an image
+struct ReferencedImage {
+    entity_type: &'static str,
+    table: &'static str,
+    id: i32,
+    name: String,
+    image_path: String,
+}
+
+/// Every row with an image path set, across products, suppliers and customers
+fn referenced_images(conn: &rusqlite::Connection) -> Result<Vec<ReferencedImage>, String> {
+    let mut images = Vec::new();
+    for (entity_type, table) in [("product", "products"), ("supplier", "suppliers"), ("customer", "customers")] {
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT id, name, image_path FROM {} WHERE image_path IS NOT NULL AND image_path != ''",
+                table
+            ))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (id, name, image_path) = row.map_err(|e| e.to_string())?;
+            images.push(ReferencedImage { entity_type, table, id, name, image_path });
+        }
+    }
+    Ok(images)
+}
+
+/// Image files under `dir` (relative to `base_dir`), skipping the cleanup trash folder
+fn collect_image_files(base_dir: &Path, dir: &Path, files: &mut Vec<OrphanImageFile>) -> Result<(), String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let path = entry.path();
+        let metadata = entry.metadata().map_err(|e| e.to_string())?;
+
+        if metadata.is_dir() {
+            if dir == base_dir && entry.file_name() == IMAGE_TRASH_FOLDER {
+                continue;
+            }
+            collect_image_files(base_dir, &path, files)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        {
+            let rel = path.strip_prefix(base_dir).map_err(|e| e.to_string())?;
+            files.push(OrphanImageFile {
+                path: rel.to_string_lossy().replace('\\', "/"),
+                bytes: metadata.len(),
+            });
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn scan_image_orphans_internal(
+    conn: &rusqlite::Connection,
+    base_dir: &Path,
+) -> Result<ImageOrphanReport, String> {
+    let images = referenced_images(conn)?;
+
+    let mut referenced: std::collections::HashSet<String> = images
+        .iter()
+        .flat_map(|image| image_files_for(&image.image_path))
+        .collect();
+    let logo: Option<String> = conn
+        .query_row("SELECT value FROM app_settings WHERE key = 'invoice_logo_path'", [], |row| row.get(0))
+        .ok();
+    if let Some(logo) = logo.filter(|l| !l.trim().is_empty()) {
+        referenced.insert(logo.trim().replace('\\', "/"));
+    }
+
+    let mut files = Vec::new();
+    collect_image_files(base_dir, base_dir, &mut files)?;
+    let mut unreferenced_files: Vec<OrphanImageFile> =
+        files.into_iter().filter(|f| !referenced.contains(&f.path)).collect();
+    unreferenced_files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let dangling_paths = images
+        .into_iter()
+        .filter(|image| !base_dir.join(&image.image_path).is_file())
+        .map(|image| DanglingImagePath {
+            entity_type: image.entity_type.to_string(),
+            entity_id: image.id,
+            entity_name: image.name,
+            image_path: image.image_path,
+        })
+        .collect();
+
+    Ok(ImageOrphanReport {
+        reclaimable_bytes: unreferenced_files.iter().map(|f| f.bytes).sum(),
+        unreferenced_files,
+        dangling_paths,
+    })
+}
+
+pub(crate) fn cleanup_image_orphans_internal(
+    conn: &rusqlite::Connection,
+    base_dir: &Path,
+) -> Result<ImageCleanupResult, String> {
+    let report = scan_image_orphans_internal(conn, base_dir)?;
+
+    let mut result = ImageCleanupResult {
+        files_moved: 0,
+        bytes_moved: 0,
+        paths_cleared: 0,
+        trash_folder: None,
+    };
+
+    if !report.unreferenced_files.is_empty() {
+        let batch = format!("{}/{}", IMAGE_TRASH_FOLDER, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        let trash_dir = base_dir.join(&batch);
+
+        for file in &report.unreferenced_files {
+            let target = trash_dir.join(&file.path);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to create trash folder: {}", e))?;
+            }
+            match fs::rename(base_dir.join(&file.path), &target) {
+                Ok(()) => {
+                    result.files_moved += 1;
+                    result.bytes_moved += file.bytes;
+                }
+                Err(e) => log::warn!("Failed to move orphan image {}: {}", file.path, e),
+            }
+        }
+        result.trash_folder = Some(batch);
+    }
+
+    let dangling = referenced_images(conn)?
+        .into_iter()
+        .filter(|image| !base_dir.join(&image.image_path).is_file());
+    for image in dangling {
+        result.paths_cleared += conn
+            .execute(
+                &format!("UPDATE {} SET image_path = NULL WHERE id = ?1 AND image_path = ?2", image.table),
+                rusqlite::params![image.id, image.image_path],
+            )
+            .map_err(|e| format!("Failed to clear image path: {}", e))?;
+    }
+
+    log::info!(
+        "Image cleanup moved {} file(s) ({} bytes) to trash and cleared {} dangling path(s)",
+        result.files_moved, result.bytes_moved, result.paths_cleared
+    );
+    Ok(result)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImageCompressionReport {
+    pub files_checked: usize,
+    pub files_compressed: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub bytes_saved: u64,
+    pub errors: Vec<String>,
+}
+
+/// Shrink the images already in the pictures directory to the current size and quality
+/// settings, in place and in their own format. Thumbnails and the cleanup trash are skipped.
+#[tauri::command]
+pub fn migrate_compress_existing_images(
+    app_handle: AppHandle,
+    db: State<Database>,
+) -> Result<ImageCompressionReport, String> {
+    log::info!("migrate_compress_existing_images called");
+
+    let base_dir = get_base_pictures_dir(&app_handle)?;
+    let conn = db.get_conn()?;
+    compress_existing_images_internal(&conn, &base_dir)
+}
+
+pub(crate) fn compress_existing_images_internal(
+    conn: &rusqlite::Connection,
+    base_dir: &Path,
+) -> Result<ImageCompressionReport, String> {
+    let limits = image_limits(conn);
+    let mut files = Vec::new();
+    collect_image_files(base_dir, base_dir, &mut files)?;
+
+    let mut report = ImageCompressionReport::default();
+    for file in files {
+        if file.path.contains("/thumbnail/") || file.path.contains("_thumb.") {
+            continue;
+        }
+        report.files_checked += 1;
+        report.bytes_before += file.bytes;
+
+        let path = base_dir.join(&file.path);
+        let image = match fs::read(&path).map_err(|e| e.to_string()).and_then(|data| normalize_image(&data, &limits, true)) {
+            Ok(image) => image,
+            Err(e) => {
+                report.errors.push(format!("{}: {}", file.path, e));
+                report.bytes_after += file.bytes;
+                continue;
+            }
+        };
+
+        if (image.data.len() as u64) >= file.bytes {
+            report.bytes_after += file.bytes;
+            continue;
+        }
+        if let Err(e) = fs::write(&path, &image.data) {
+            report.errors.push(format!("{}: {}", file.path, e));
+            report.bytes_after += file.bytes;
+            continue;
+        }
+
+        report.files_compressed += 1;
+        report.bytes_after += image.data.len() as u64;
+        for table in ["products", "suppliers", "customers"] {
+            conn.execute(
+                &format!("UPDATE {} SET image_hash = ?1 WHERE image_path = ?2", table),
+                rusqlite::params![image.hash, file.path],
+            )
+            .map_err(|e| format!("Failed to update image hash: {}", e))?;
+        }
+    }
+
+    report.bytes_saved = report.bytes_before - report.bytes_after;
+    log::info!(
+        "Compressed {} of {} image(s), saved {} bytes",
+        report.files_compressed, report.files_checked, report.bytes_saved
+    );
+    Ok(report)
+}
+
 // --- MIGRATION COMMAND ---
 
 #[tauri::command]
@@ -609,51 +1081,126 @@ pub fn migrate_images(app_handle: AppHandle, db: State<Database>) -> Result<Stri
 
 // --- Other Existing Commands (search, get_directory, crop) ---
 
+/// How long search results are reused for the same query
+const IMAGE_SEARCH_CACHE_TTL: Duration = Duration::from_secs(120);
+
+/// The last image search, so paging through thumbnails doesn't spend quota twice
+#[derive(Default)]
+pub struct ImageSearchCache {
+    last: Mutex<Option<(String, Instant, Vec<GoogleImageResult>)>>,
+}
+
+impl ImageSearchCache {
+    fn key(query: &str, limit: i32) -> String {
+        format!("{}|{}", query.trim().to_lowercase(), limit)
+    }
+
+    fn get(&self, query: &str, limit: i32) -> Option<Vec<GoogleImageResult>> {
+        let last = self.last.lock().ok()?;
+        match last.as_ref() {
+            Some((key, at, results)) if *key == Self::key(query, limit) && at.elapsed() < IMAGE_SEARCH_CACHE_TTL => {
+                Some(results.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn put(&self, query: &str, limit: i32, results: &[GoogleImageResult]) {
+        if let Ok(mut last) = self.last.lock() {
+            *last = Some((Self::key(query, limit), Instant::now(), results.to_vec()));
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut last) = self.last.lock() {
+            *last = None;
+        }
+    }
+}
+
+/// Search for product images with the providers in the image_search_providers setting,
+/// falling back to the next one when a provider fails or finds nothing. Errors start with
+/// IMAGE_SEARCH_QUOTA_EXCEEDED, IMAGE_SEARCH_AUTH_FAILED, IMAGE_SEARCH_NETWORK_ERROR,
+/// IMAGE_SEARCH_NOT_CONFIGURED or IMAGE_SEARCH_PROVIDER_ERROR.
 #[tauri::command]
 pub async fn search_google_images(
     query: String,
     limit: i32,
+    cache: State<'_, ImageSearchCache>,
     db: State<'_, Database>,
 ) -> Result<Vec<GoogleImageResult>, String> {
-    // (Implementation similar to original, omitted for brevity but I need to include it!)
-    // RE-IMPLEMENTING FULL CODE to ensure it works.
-    let conn = db.get_conn()?;
-    let api_key: Option<String> = conn.query_row("SELECT value FROM app_settings WHERE key = 'google_api_key'", [], |row| row.get(0)).ok();
-    let cx_id: Option<String> = conn.query_row("SELECT value FROM app_settings WHERE key = 'google_cx_id'", [], |row| row.get(0)).ok();
+    log::info!("search_google_images called: {}", query);
 
-    let api_key = api_key.map(|k| k.trim().to_string()).filter(|k| !k.is_empty()).ok_or("Google API Key not configured.")?;
-    let cx_id = cx_id.map(|c| c.trim().to_string()).filter(|c| !c.is_empty()).ok_or("Google CX ID not configured.")?;
+    let num = limit.clamp(1, 10);
+    if let Some(results) = cache.get(&query, num) {
+        log::info!("Returning {} cached image results", results.len());
+        return Ok(results);
+    }
 
-    let num = limit.min(10).max(1);
-    let url = format!(
-        "https://www.googleapis.com/customsearch/v1?key={}&cx={}&q={}&searchType=image&num={}",
-        api_key, cx_id, urlencoding::encode(&query), num
-    );
+    let providers: Vec<_> = {
+        let conn = db.get_conn()?;
+        image_search::provider_order(&conn)
+            .iter()
+            .map(|name| ImageSearchProvider::load(&conn, name))
+            .collect()
+    };
 
-    let client = reqwest::Client::new();
-    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let results = image_search::search_with_fallback(&providers, &query, num)
+        .await
+        .map_err(|e| e.to_string())?;
 
-    if !response.status().is_success() {
-        return Err(format!("Google API error: {}", response.status()));
-    }
-
-    let json: serde_json::Value = response.json().await.map_err(|e| e.to_string())?;
-    let items = json["items"].as_array();
-
-    let results: Vec<GoogleImageResult> = match items {
-        Some(items) => items.iter().filter_map(|item| {
-            Some(GoogleImageResult {
-                title: item["title"].as_str()?.to_string(),
-                link: item["link"].as_str()?.to_string(),
-                thumbnail_link: item["image"]["thumbnailLink"].as_str().unwrap_or("").to_string(),
-                display_link: item["displayLink"].as_str().unwrap_or("").to_string(),
-            })
-        }).collect(),
-        None => Vec::new(),
-    };
+    if !results.is_empty() {
+        cache.put(&query, num, &results);
+    }
     Ok(results)
 }
 
+/// Image search provider setup, for the settings screen
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImageSearchProviderStatus {
+    pub provider: String,
+    /// Listed in the image_search_providers setting
+    pub enabled: bool,
+    /// Has everything it needs (key, CX ID or URL)
+    pub configured: bool,
+    pub key_in_keyring: bool,
+}
+
+#[tauri::command]
+pub fn get_image_search_status(db: State<Database>) -> Result<Vec<ImageSearchProviderStatus>, String> {
+    log::info!("get_image_search_status called");
+
+    let conn = db.get_conn()?;
+    let order = image_search::provider_order(&conn);
+    Ok(image_search::PROVIDERS
+        .iter()
+        .map(|name| ImageSearchProviderStatus {
+            provider: name.to_string(),
+            enabled: order.iter().any(|p| p == name),
+            configured: ImageSearchProvider::load(&conn, name).is_ok(),
+            key_in_keyring: image_search::key_in_keyring(name),
+        })
+        .collect())
+}
+
+/// Save an image search provider's API key in the OS keyring; an empty key removes it
+#[tauri::command]
+pub fn set_image_search_key(
+    provider: String,
+    api_key: String,
+    cache: State<ImageSearchCache>,
+    db: State<Database>,
+) -> Result<(), String> {
+    log::info!("set_image_search_key called for provider: {}", provider);
+
+    let conn = db.get_conn()?;
+    image_search::store_api_key(&conn, &provider, &api_key)?;
+
+    // Results from the old key shouldn't hide a key problem from the next search
+    cache.clear();
+    Ok(())
+}
+
 #[tauri::command]
 pub fn get_pictures_directory(app_handle: AppHandle) -> Result<String, String> {
     let pictures_dir = get_base_pictures_dir(&app_handle)?;
@@ -683,3 +1230,123 @@ pub fn save_cropped_image(
 
     save_product_image_internal(product_id, file_data, file_extension, category, &app_handle, &db)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_customer, TestDb};
+
+    #[test]
+    fn test_image_orphans_are_moved_and_dangling_paths_cleared() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let base_dir = std::env::temp_dir().join(format!("pictures_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base_dir);
+        fs::create_dir_all(base_dir.join("Company")).unwrap();
+        fs::create_dir_all(base_dir.join("Inventory/normal")).unwrap();
+
+        let kept = insert_customer(&conn, "Kept");
+        let gone = insert_customer(&conn, "Gone");
+        conn.execute("UPDATE customers SET image_path = 'Company/customer_1.jpg' WHERE id = ?1", [kept]).unwrap();
+        conn.execute("UPDATE customers SET image_path = 'Company/customer_2.jpg' WHERE id = ?1", [gone]).unwrap();
+        fs::write(base_dir.join("Company/customer_1.jpg"), b"img").unwrap();
+        fs::write(base_dir.join("Company/customer_1_thumb.jpg"), b"t").unwrap();
+        fs::write(base_dir.join("Inventory/normal/product_9.jpg"), b"orphan").unwrap();
+        fs::write(base_dir.join("notes.txt"), b"not an image").unwrap();
+
+        let report = scan_image_orphans_internal(&conn, &base_dir).unwrap();
+        assert_eq!(report.unreferenced_files.len(), 1);
+        assert_eq!(report.unreferenced_files[0].path, "Inventory/normal/product_9.jpg");
+        assert_eq!(report.reclaimable_bytes, 6);
+        assert_eq!(report.dangling_paths.len(), 1);
+        assert_eq!(report.dangling_paths[0].entity_id, gone);
+
+        let result = cleanup_image_orphans_internal(&conn, &base_dir).unwrap();
+        assert_eq!(result.files_moved, 1);
+        assert_eq!(result.paths_cleared, 1);
+        assert!(!base_dir.join("Inventory/normal/product_9.jpg").exists());
+        assert!(base_dir.join(result.trash_folder.unwrap()).join("Inventory/normal/product_9.jpg").exists());
+
+        let report = scan_image_orphans_internal(&conn, &base_dir).unwrap();
+        assert!(report.unreferenced_files.is_empty());
+        assert!(report.dangling_paths.is_empty());
+
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_image_search_cache_is_per_query() {
+        let cache = ImageSearchCache::default();
+        let hit = GoogleImageResult {
+            title: "Rice".to_string(),
+            link: "https://x/rice.jpg".to_string(),
+            thumbnail_link: String::new(),
+            display_link: String::new(),
+            provider: "google".to_string(),
+        };
+        cache.put("Rice ", 10, &[hit]);
+
+        assert_eq!(cache.get("rice", 10).unwrap().len(), 1);
+        assert!(cache.get("rice", 5).is_none());
+        assert!(cache.get("wheat", 10).is_none());
+    }
+
+    fn encode(img: image::DynamicImage, format: image::ImageOutputFormat) -> Vec<u8> {
+        let mut data = std::io::Cursor::new(Vec::new());
+        img.write_to(&mut data, format).unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn test_normalize_image_caps_dimensions() {
+        let limits = ImageLimits { max_dimension: 400, quality: 80 };
+
+        let photo = encode(
+            image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(1000, 500, image::Rgb([200, 40, 40]))),
+            image::ImageOutputFormat::Png,
+        );
+        let normalized = normalize_image(&photo, &limits, false).unwrap();
+        assert_eq!(normalized.extension, "jpg");
+        let decoded = image::load_from_memory(&normalized.data).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (400, 200));
+
+        // Same upload, same stored content and filename
+        let again = normalize_image(&photo, &limits, false).unwrap();
+        assert_eq!(again.hash, normalized.hash);
+        assert_eq!(get_content_filename("product", &again), get_content_filename("product", &normalized));
+
+        let logo = encode(
+            image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(50, 50, image::Rgba([0, 0, 0, 0]))),
+            image::ImageOutputFormat::Png,
+        );
+        assert_eq!(normalize_image(&logo, &limits, false).unwrap().extension, "png");
+
+        assert!(normalize_image(b"not an image", &limits, false).is_err());
+    }
+
+    #[test]
+    fn test_compress_existing_images_in_place() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('image_max_dimension', '100')", []).unwrap();
+
+        let base_dir = std::env::temp_dir().join(format!("pictures_compress_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base_dir);
+        fs::create_dir_all(base_dir.join("Inventory/normal")).unwrap();
+        let big = encode(
+            image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(600, 300, |x, y| image::Rgb([(x % 256) as u8, (y % 256) as u8, 90]))),
+            image::ImageOutputFormat::Png,
+        );
+        fs::write(base_dir.join("Inventory/normal/product_1.png"), &big).unwrap();
+
+        let report = compress_existing_images_internal(&conn, &base_dir).unwrap();
+        assert_eq!(report.files_checked, 1);
+        assert_eq!(report.files_compressed, 1);
+        assert!(report.bytes_saved > 0);
+
+        let stored = image::open(base_dir.join("Inventory/normal/product_1.png")).unwrap();
+        assert_eq!((stored.width(), stored.height()), (100, 50));
+
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+}
@@ -0,0 +1,670 @@
+/// Integration Bridge
+/// Optional JSON-RPC 2.0 listener on 127.0.0.1 for local integrations, such as a relay pushing
+/// orders from the website's order form. It is off unless integration_enabled is "true" and an
+/// integration_token is set, and every request must carry "Authorization: Bearer <token>".
+///
+/// Requests are `POST /rpc` with `{"jsonrpc": "2.0", "method", "params", "id"}`. The methods call
+/// the same internals as the Tauri commands of the same name: create_invoice,
+/// get_product_by_sku, get_product_by_barcode (the SKU doubles as the barcode), get_customers
+/// and get_stock. Writes are refused while the maintenance lock is held, and every request is
+/// logged in entity_modifications as an integration_request. No CORS headers are sent, so web
+/// pages open in a browser can't call it.
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+use crate::commands::customers::get_customers_internal;
+use crate::commands::invoices::{create_invoice_internal, queue_invoice_created, CreateInvoiceInput};
+use crate::commands::stock_alerts::notify_stock_alerts;
+use crate::commands::maintenance::MaintenanceState;
+use crate::commands::products::get_product_internal;
+use crate::commands::reservations::available_quantity;
+use crate::db::Database;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+
+const ENABLED_KEY: &str = "integration_enabled";
+const PORT_KEY: &str = "integration_port";
+const TOKEN_KEY: &str = "integration_token";
+const DEFAULT_PORT: u16 = 8765;
+/// Shortest bearer token the listener can be enabled with
+const MIN_TOKEN_LENGTH: usize = 16;
+
+/// Request line and headers
+const MAX_HEAD_BYTES: usize = 8 * 1024;
+const MAX_BODY_BYTES: usize = 256 * 1024;
+/// Time a client gets to send its whole request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i32 = -32700;
+const INVALID_REQUEST: i32 = -32600;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+/// The delegated command returned an error; the message is the command's error
+const COMMAND_ERROR: i32 = -32000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntegrationStatus {
+    /// integration_enabled is set
+    pub enabled: bool,
+    pub running: bool,
+    pub port: u16,
+    /// "127.0.0.1:<port>" while running
+    pub address: Option<String>,
+    pub token_configured: bool,
+    /// Why the listener last failed to start
+    pub last_error: Option<String>,
+}
+
+struct RunningServer {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+}
+
+/// The running listener, if any
+#[derive(Default)]
+pub struct IntegrationState {
+    server: Mutex<Option<RunningServer>>,
+    last_error: Mutex<Option<String>>,
+}
+
+impl IntegrationState {
+    fn running_port(&self) -> Option<u16> {
+        self.server.lock().unwrap_or_else(|p| p.into_inner()).as_ref().map(|s| s.port)
+    }
+
+    fn set_last_error(&self, error: Option<String>) {
+        *self.last_error.lock().unwrap_or_else(|p| p.into_inner()) = error;
+    }
+
+    fn stop(&self) {
+        if let Some(server) = self.server.lock().unwrap_or_else(|p| p.into_inner()).take() {
+            let _ = server.shutdown.send(());
+            log::info!("Integration listener on port {} stopped", server.port);
+        }
+    }
+}
+
+fn setting(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row("SELECT value FROM app_settings WHERE key = ?1", [key], |row| row.get::<_, String>(0))
+        .optional()
+        .ok()
+        .flatten()
+}
+
+fn configured_port(conn: &Connection) -> u16 {
+    setting(conn, PORT_KEY)
+        .and_then(|v| v.trim().parse::<u16>().ok())
+        .filter(|port| *port > 0)
+        .unwrap_or(DEFAULT_PORT)
+}
+
+fn configured_token(conn: &Connection) -> Option<String> {
+    setting(conn, TOKEN_KEY).filter(|t| t.len() >= MIN_TOKEN_LENGTH)
+}
+
+fn integration_status(conn: &Connection, state: &IntegrationState) -> IntegrationStatus {
+    let running_port = state.running_port();
+    IntegrationStatus {
+        enabled: setting(conn, ENABLED_KEY).as_deref() == Some("true"),
+        running: running_port.is_some(),
+        port: running_port.unwrap_or_else(|| configured_port(conn)),
+        address: running_port.map(|port| format!("127.0.0.1:{}", port)),
+        token_configured: configured_token(conn).is_some(),
+        last_error: state.last_error.lock().unwrap_or_else(|p| p.into_inner()).clone(),
+    }
+}
+
+/// Start the listener when the settings enable it, replacing one already running. Called at
+/// startup and whenever the integration settings change.
+pub fn start_integration_server(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<IntegrationState>();
+    state.stop();
+
+    let conn = app.state::<Database>().get_conn()?;
+    if setting(&conn, ENABLED_KEY).as_deref() != Some("true") {
+        state.set_last_error(None);
+        return Ok(());
+    }
+    if configured_token(&conn).is_none() {
+        let error = format!("{} must be at least {} characters", TOKEN_KEY, MIN_TOKEN_LENGTH);
+        state.set_last_error(Some(error.clone()));
+        return Err(error);
+    }
+
+    let port = configured_port(&conn);
+    // Bound here rather than in the task so a port in use is reported to the caller
+    let listener = std::net::TcpListener::bind(("127.0.0.1", port))
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+        .map_err(|e| {
+            let error = format!("Failed to listen on 127.0.0.1:{}: {}", port, e);
+            state.set_last_error(Some(error.clone()));
+            error
+        })?;
+
+    let (shutdown, mut stopped) = oneshot::channel();
+    let task_app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!("Integration listener failed: {}", e);
+                return;
+            }
+        };
+        loop {
+            tokio::select! {
+                _ = &mut stopped => break,
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        tauri::async_runtime::spawn(serve_connection(task_app.clone(), stream));
+                    }
+                    Err(e) => log::warn!("Integration listener failed to accept a connection: {}", e),
+                },
+            }
+        }
+    });
+
+    *state.server.lock().unwrap_or_else(|p| p.into_inner()) = Some(RunningServer { port, shutdown });
+    state.set_last_error(None);
+    log::info!("Integration listener running on 127.0.0.1:{}", port);
+    Ok(())
+}
+
+/// Whether the integration listener is running, and on which port
+#[tauri::command]
+pub fn get_integration_status(state: State<IntegrationState>, db: State<Database>) -> Result<IntegrationStatus, String> {
+    log::info!("get_integration_status called");
+
+    let conn = db.get_conn()?;
+    Ok(integration_status(&conn, &state))
+}
+
+/// Save the integration settings and start, restart or stop the listener to match.
+/// `token` replaces the stored bearer token when given.
+#[tauri::command]
+pub fn set_integration_settings(
+    enabled: bool,
+    port: Option<u16>,
+    token: Option<String>,
+    app: AppHandle,
+    state: State<IntegrationState>,
+    db: State<Database>,
+) -> Result<IntegrationStatus, String> {
+    log::info!("set_integration_settings called: enabled {}, port {:?}", enabled, port);
+
+    let conn = db.get_conn()?;
+    set_integration_settings_internal(&conn, enabled, port, token.as_deref())?;
+
+    // Settings are saved either way; a failed start is reported in last_error
+    if let Err(e) = start_integration_server(&app) {
+        log::warn!("Integration listener not started: {}", e);
+    }
+    Ok(integration_status(&conn, &state))
+}
+
+pub(crate) fn set_integration_settings_internal(
+    conn: &Connection,
+    enabled: bool,
+    port: Option<u16>,
+    token: Option<&str>,
+) -> Result<(), String> {
+    if port.is_some_and(|port| port < 1024) {
+        return Err("Validation error: port must be between 1024 and 65535".to_string());
+    }
+    let token = token.map(str::trim);
+    if token.is_some_and(|token| token.len() < MIN_TOKEN_LENGTH) {
+        return Err(format!("Validation error: token must be at least {} characters", MIN_TOKEN_LENGTH));
+    }
+    if enabled && token.is_none() && configured_token(conn).is_none() {
+        return Err("Validation error: set a token before enabling the integration listener".to_string());
+    }
+
+    let mut values = vec![(ENABLED_KEY, enabled.to_string())];
+    if let Some(port) = port {
+        values.push((PORT_KEY, port.to_string()));
+    }
+    if let Some(token) = token {
+        values.push((TOKEN_KEY, token.to_string()));
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    for (key, value) in values {
+        tx.execute(
+            "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))
+             ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = datetime('now')",
+            params![key, value],
+        )
+        .map_err(|e| format!("Failed to save setting: {}", e))?;
+    }
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))
+}
+
+// =============================================
+// HTTP
+// =============================================
+
+#[derive(Debug)]
+pub(crate) struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub authorization: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub(crate) struct HttpResponse {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl HttpResponse {
+    fn error(status: u16, message: &str) -> Self {
+        HttpResponse { status, body: json!({ "error": message }) }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            413 => "Payload Too Large",
+            431 => "Request Header Fields Too Large",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+async fn serve_connection(app: AppHandle, mut stream: TcpStream) {
+    let response = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => {
+            let handled = tauri::async_runtime::spawn_blocking(move || {
+                let response = match app.state::<Database>().get_conn() {
+                    Ok(mut conn) => handle_request(&mut conn, &app.state::<MaintenanceState>(), &request),
+                    Err(e) => (HttpResponse::error(500, &e), None),
+                };
+                if let (_, Some(invoice_id)) = response {
+                    emit_data_changed(&app, DataEntity::Invoice, DataOperation::Created, invoice_id);
+                    if let Ok(conn) = app.state::<Database>().get_conn() {
+                        queue_invoice_created(&app, &conn, invoice_id);
+                        notify_stock_alerts(&app, &conn, invoice_id);
+                    }
+                }
+                response.0
+            })
+            .await;
+            handled.unwrap_or_else(|e| HttpResponse::error(500, &e.to_string()))
+        }
+        Ok(Err(response)) => response,
+        Err(_) => HttpResponse::error(408, "request timed out"),
+    };
+
+    let body = response.body.to_string();
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.reason(),
+        body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(body.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<HttpRequest, HttpResponse> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err(HttpResponse::error(431, "request headers too large"));
+        }
+        let read = stream.read(&mut chunk).await.map_err(|e| HttpResponse::error(400, &e.to_string()))?;
+        if read == 0 {
+            return Err(HttpResponse::error(400, "incomplete request"));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = std::str::from_utf8(&buffer[..head_end]).map_err(|_| HttpResponse::error(400, "malformed request"))?;
+    let (mut request, content_length) = parse_request_head(head)?;
+    if content_length > MAX_BODY_BYTES {
+        return Err(HttpResponse::error(413, "request body too large"));
+    }
+
+    let mut body = buffer[head_end + 4..].to_vec();
+    while body.len() < content_length {
+        let read = stream.read(&mut chunk).await.map_err(|e| HttpResponse::error(400, &e.to_string()))?;
+        if read == 0 {
+            return Err(HttpResponse::error(400, "incomplete request body"));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+    request.body = body;
+    Ok(request)
+}
+
+/// Request line and the headers the bridge uses; returns the request without its body and the
+/// Content-Length
+fn parse_request_head(head: &str) -> Result<(HttpRequest, usize), HttpResponse> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(path)) = (request_line.next(), request_line.next()) else {
+        return Err(HttpResponse::error(400, "malformed request line"));
+    };
+
+    let mut authorization = None;
+    let mut content_length = 0;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().map_err(|_| HttpResponse::error(400, "invalid Content-Length"))?;
+        }
+    }
+
+    Ok((
+        HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            authorization,
+            body: Vec::new(),
+        },
+        content_length,
+    ))
+}
+
+/// Compare without stopping at the first differing byte
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given.bytes().zip(expected.bytes()).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+// =============================================
+// JSON-RPC
+// =============================================
+
+fn rpc_error(id: &Value, code: i32, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "error": { "code": code, "message": message }, "id": id })
+}
+
+/// Authenticate, dispatch and log one request. Also returns the id of an invoice it created,
+/// for the data-changed event.
+pub(crate) fn handle_request(
+    conn: &mut Connection,
+    maintenance: &MaintenanceState,
+    request: &HttpRequest,
+) -> (HttpResponse, Option<i32>) {
+    let authorized = match (configured_token(conn), request.authorization.as_deref()) {
+        (Some(token), Some(header)) => header
+            .strip_prefix("Bearer ")
+            .is_some_and(|given| token_matches(given.trim(), &token)),
+        _ => false,
+    };
+    if !authorized {
+        // Not written to the activity log: anyone who can reach the port could flood it
+        log::warn!("Rejected unauthenticated integration request for {}", request.path);
+        return (HttpResponse::error(401, "missing or invalid bearer token"), None);
+    }
+    if request.path != "/rpc" {
+        log_request(conn, &request.path, "not found");
+        return (HttpResponse::error(404, "not found"), None);
+    }
+    if request.method != "POST" {
+        log_request(conn, &request.path, "method not allowed");
+        return (HttpResponse::error(405, "use POST"), None);
+    }
+
+    let call: Value = match serde_json::from_slice(&request.body) {
+        Ok(call) => call,
+        Err(e) => {
+            log_request(conn, &request.path, "parse error");
+            return (ok(rpc_error(&Value::Null, PARSE_ERROR, &e.to_string())), None);
+        }
+    };
+    let id = call.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = call.get("method").and_then(Value::as_str).filter(|_| call["jsonrpc"] == "2.0") else {
+        log_request(conn, &request.path, "invalid request");
+        return (ok(rpc_error(&id, INVALID_REQUEST, "expected a JSON-RPC 2.0 request")), None);
+    };
+    let params = call.get("params").cloned().unwrap_or_else(|| json!({}));
+
+    // Same rule as the invoke handler: writes wait for restores, imports and maintenance runs
+    if let Err(e) = maintenance.check_command_allowed(method) {
+        log_request(conn, method, &e);
+        return (ok(rpc_error(&id, COMMAND_ERROR, &e)), None);
+    }
+
+    let (response, created_invoice) = match dispatch(conn, method, params) {
+        Ok((result, created_invoice)) => {
+            log_request(conn, method, "ok");
+            (json!({ "jsonrpc": "2.0", "result": result, "id": id }), created_invoice)
+        }
+        Err((code, message)) => {
+            log_request(conn, method, &message);
+            (rpc_error(&id, code, &message), None)
+        }
+    };
+    (ok(response), created_invoice)
+}
+
+fn ok(body: Value) -> HttpResponse {
+    HttpResponse { status: 200, body }
+}
+
+#[derive(Deserialize)]
+struct SkuParams {
+    sku: String,
+}
+
+#[derive(Deserialize)]
+struct BarcodeParams {
+    barcode: String,
+}
+
+#[derive(Deserialize)]
+struct CustomerSearchParams {
+    search: Option<String>,
+    page: Option<i32>,
+    page_size: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct StockParams {
+    product_id: Option<i32>,
+    sku: Option<String>,
+}
+
+fn parse_params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, (i32, String)> {
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))
+}
+
+fn product_id_by_sku(conn: &Connection, sku: &str) -> Result<i32, (i32, String)> {
+    conn.query_row("SELECT id FROM products WHERE sku = ?1", [sku.trim()], |row| row.get(0))
+        .optional()
+        .map_err(|e| (COMMAND_ERROR, e.to_string()))?
+        .ok_or_else(|| (COMMAND_ERROR, format!("Product with SKU {} not found", sku.trim())))
+}
+
+fn command<T: Serialize>(result: Result<T, String>) -> Result<Value, (i32, String)> {
+    result
+        .and_then(|value| serde_json::to_value(value).map_err(|e| e.to_string()))
+        .map_err(|e| (COMMAND_ERROR, e))
+}
+
+/// Run one method; returns its result and the id of the invoice it created, if any
+fn dispatch(conn: &mut Connection, method: &str, params: Value) -> Result<(Value, Option<i32>), (i32, String)> {
+    match method {
+        "create_invoice" => {
+            let mut input: CreateInvoiceInput = parse_params(params)?;
+            // Going over a credit limit needs a manager at the till
+            input.allow_over_limit = false;
+            input.approved_by = None;
+            let invoice = create_invoice_internal(conn, input).map_err(|e| (COMMAND_ERROR, e))?;
+            let id = invoice.id;
+            Ok((command(Ok(invoice))?, Some(id)))
+        }
+        "get_product_by_sku" | "get_product_by_barcode" => {
+            let sku = if method == "get_product_by_sku" {
+                parse_params::<SkuParams>(params)?.sku
+            } else {
+                parse_params::<BarcodeParams>(params)?.barcode
+            };
+            let product_id = product_id_by_sku(conn, &sku)?;
+            Ok((command(get_product_internal(conn, product_id))?, None))
+        }
+        "get_customers" => {
+            let p: CustomerSearchParams = parse_params(params)?;
+            let customers = get_customers_internal(
+                conn,
+                p.search.as_deref(),
+                p.page.unwrap_or(1),
+                p.page_size.unwrap_or(20),
+                false,
+                None,
+                false,
+            );
+            Ok((command(customers)?, None))
+        }
+        "get_stock" => {
+            let p: StockParams = parse_params(params)?;
+            let product_id = match (p.product_id, p.sku.as_deref()) {
+                (Some(id), _) => id,
+                (None, Some(sku)) => product_id_by_sku(conn, sku)?,
+                (None, None) => return Err((INVALID_PARAMS, "product_id or sku is required".to_string())),
+            };
+            let (sku, stock_quantity): (String, f64) = conn
+                .query_row("SELECT sku, stock_quantity FROM products WHERE id = ?1", [product_id], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })
+                .map_err(|_| (COMMAND_ERROR, format!("Product with id {} not found", product_id)))?;
+            let available = available_quantity(conn, product_id).map_err(|e| (COMMAND_ERROR, e))?;
+            Ok((
+                json!({
+                    "product_id": product_id,
+                    "sku": sku,
+                    "stock_quantity": stock_quantity,
+                    "available_quantity": available,
+                }),
+                None,
+            ))
+        }
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+    }
+}
+
+/// Record a request in the activity log; logging never fails the request
+fn log_request(conn: &Connection, method: &str, outcome: &str) {
+    let field_changes = json!([{ "field": "result", "old": Value::Null, "new": outcome }]);
+    let logged = conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by)
+         VALUES ('integration', 0, ?1, 'integration_request', ?2, 'integration')",
+        params![method, field_changes.to_string()],
+    );
+    if let Err(e) = logged {
+        log::warn!("Failed to log integration request {}: {}", method, e);
+    }
+    log::info!("Integration request {}: {}", method, outcome);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::maintenance::MaintenanceOperation;
+    use crate::test_support::TestDb;
+
+    const TOKEN: &str = "0123456789abcdef";
+
+    fn rpc(method: &str, params: Value) -> HttpRequest {
+        HttpRequest {
+            method: "POST".to_string(),
+            path: "/rpc".to_string(),
+            authorization: Some(format!("Bearer {}", TOKEN)),
+            body: json!({ "jsonrpc": "2.0", "method": method, "params": params, "id": 7 }).to_string().into_bytes(),
+        }
+    }
+
+    #[test]
+    fn test_bridge_requires_the_token_and_delegates_to_commands() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let maintenance = MaintenanceState::default();
+        conn.execute(
+            "INSERT INTO products (name, sku, price, selling_price, stock_quantity) VALUES ('Widget', 'W-1', 5, 10, 8)",
+            [],
+        )
+        .unwrap();
+
+        assert!(set_integration_settings_internal(&conn, true, None, None).is_err());
+        assert!(set_integration_settings_internal(&conn, true, Some(80), Some(TOKEN)).is_err());
+        set_integration_settings_internal(&conn, true, Some(9100), Some(TOKEN)).unwrap();
+        assert_eq!(configured_port(&conn), 9100);
+
+        let mut unauthorized = rpc("get_stock", json!({ "sku": "W-1" }));
+        unauthorized.authorization = Some("Bearer wrong-token-0000".to_string());
+        for _ in 0..3 {
+            assert_eq!(handle_request(&mut conn, &maintenance, &unauthorized).0.status, 401);
+        }
+
+        let (response, _) = handle_request(&mut conn, &maintenance, &rpc("get_product_by_barcode", json!({ "barcode": "W-1" })));
+        assert_eq!(response.body["result"]["name"], "Widget");
+        assert_eq!(response.body["id"], 7);
+
+        let (response, _) = handle_request(&mut conn, &maintenance, &rpc("delete_product", json!({ "id": 1 })));
+        assert_eq!(response.body["error"]["code"], METHOD_NOT_FOUND);
+
+        // Orders go through create_invoice's validation and stock handling
+        let product_id: i32 = conn.query_row("SELECT id FROM products WHERE sku = 'W-1'", [], |r| r.get(0)).unwrap();
+        let order = json!({ "items": [{ "product_id": product_id, "quantity": 3, "unit_price": 10.0 }] });
+        let (response, created) = handle_request(&mut conn, &maintenance, &rpc("create_invoice", order.clone()));
+        assert!(created.is_some(), "{}", response.body);
+        let (response, _) = handle_request(&mut conn, &maintenance, &rpc("get_stock", json!({ "sku": "W-1" })));
+        assert_eq!(response.body["result"]["stock_quantity"], 5.0);
+
+        // Writes wait for the maintenance lock; reads don't
+        let _lock = maintenance.acquire(MaintenanceOperation::RestoreBackup).unwrap();
+        let (response, created) = handle_request(&mut conn, &maintenance, &rpc("create_invoice", order));
+        assert!(created.is_none());
+        assert_eq!(response.body["error"]["code"], COMMAND_ERROR);
+        let (response, _) = handle_request(&mut conn, &maintenance, &rpc("get_customers", json!({})));
+        assert_eq!(response.body["result"]["total_count"], 0);
+        let (response, _) = handle_request(&mut conn, &maintenance, &rpc("get_stock", json!({ "sku": "W-1" })));
+        assert_eq!(response.body["result"]["stock_quantity"], 5.0);
+
+        let logged: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM entity_modifications WHERE action = 'integration_request'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        // Rejected requests stay out of the activity log
+        assert_eq!(logged, 7);
+    }
+
+    #[test]
+    fn test_parse_request_head() {
+        let (request, length) =
+            parse_request_head("POST /rpc HTTP/1.1\r\nHost: 127.0.0.1\r\nauthorization: Bearer abc\r\nContent-Length: 42").unwrap();
+        assert_eq!((request.method.as_str(), request.path.as_str(), length), ("POST", "/rpc", 42));
+        assert_eq!(request.authorization.as_deref(), Some("Bearer abc"));
+        assert_eq!(parse_request_head("garbage").unwrap_err().status, 400);
+    }
+}
@@ -0,0 +1,545 @@
+/// Data Integrity Checks
+/// A battery of consistency checks over the books: batch stock against product stock, invoice
+/// totals against their lines, customer and supplier payments against what they pay for,
+/// orphaned foreign-key rows and AUTOINCREMENT counters behind their tables. Each run is stored
+/// in integrity_reports with every check's result and the rows it flagged, and a run with a
+/// failing check raises an "integrity-warning" event for the frontend to show.
+///
+/// With integrity_checks_nightly set to "true" the checks also run once per business day, at
+/// backup_time (02:00 by default) or on the first start after it.
+use std::time::{Duration, Instant};
+
+use chrono::{NaiveDateTime, NaiveTime};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::analytics::business_offset_minutes;
+use crate::commands::MaintenanceState;
+use crate::db::Database;
+use crate::services::{money, quantity};
+
+pub const INTEGRITY_WARNING_EVENT: &str = "integrity-warning";
+
+const NIGHTLY_KEY: &str = "integrity_checks_nightly";
+/// The nightly run goes with the backup, at backup_time
+const RUN_TIME_KEY: &str = "backup_time";
+const DEFAULT_RUN_TIME: &str = "02:00";
+/// How often the scheduler looks whether tonight's run is due
+const POLL_INTERVAL: Duration = Duration::from_secs(600);
+/// Offending rows kept per check; offending_count has the full number
+const MAX_OFFENDERS: usize = 100;
+
+/// A row a check flagged
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityOffender {
+    pub table: String,
+    pub id: i64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityCheck {
+    /// Stable name of the check, e.g. "batch_stock"
+    pub key: String,
+    pub description: String,
+    pub passed: bool,
+    pub offending_count: usize,
+    /// The first MAX_OFFENDERS offending rows
+    pub offenders: Vec<IntegrityOffender>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityReport {
+    pub id: i64,
+    /// "manual" or "scheduled"
+    pub source: String,
+    /// Business day of the run, "YYYY-MM-DD"
+    pub business_date: String,
+    pub passed: bool,
+    pub failed_count: i32,
+    pub checks: Vec<IntegrityCheck>,
+    pub duration_ms: i64,
+    pub created_at: String,
+}
+
+/// Payload of the "integrity-warning" event
+#[derive(Debug, Serialize)]
+pub struct IntegrityWarning {
+    pub report_id: i64,
+    pub source: String,
+    /// Descriptions of the failed checks
+    pub failed_checks: Vec<String>,
+}
+
+type CheckFn = fn(&Connection) -> Result<Vec<IntegrityOffender>, String>;
+
+const CHECKS: &[(&str, &str, CheckFn)] = &[
+    ("batch_stock", "Remaining batch quantities add up to product stock", check_batch_stock),
+    ("invoice_totals", "Invoice totals equal their items minus discount plus tax", check_invoice_totals),
+    ("customer_payments", "Customer payments don't exceed the invoice total", check_customer_payments),
+    ("supplier_payments", "Supplier payments don't exceed attributable purchases", check_supplier_payments),
+    ("orphan_rows", "No rows reference a missing parent row", check_orphan_rows),
+    ("sequences", "AUTOINCREMENT counters are at or past the highest id used", check_sequences),
+];
+
+fn offender(table: &str, id: i64, detail: String) -> IntegrityOffender {
+    IntegrityOffender { table: table.to_string(), id, detail }
+}
+
+/// Products whose stock differs from what their batches hold
+fn check_batch_stock(conn: &Connection) -> Result<Vec<IntegrityOffender>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.id, p.name, p.stock_quantity, COALESCE(SUM(ib.quantity_remaining), 0)
+             FROM products p
+             LEFT JOIN inventory_batches ib ON ib.product_id = p.id
+             GROUP BY p.id
+             ORDER BY p.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?, row.get::<_, f64>(3)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load batch totals: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|(_, _, stock, batches)| quantity::to_milli(*stock) != quantity::to_milli(*batches))
+        .map(|(id, name, stock, batches)| {
+            offender(
+                "products",
+                id,
+                format!(
+                    "{}: stock {}, batches hold {}",
+                    name,
+                    quantity::format_quantity(stock),
+                    quantity::format_quantity(batches)
+                ),
+            )
+        })
+        .collect())
+}
+
+/// Invoices whose total isn't their item total plus tax less discount (with round-off)
+fn check_invoice_totals(conn: &Connection) -> Result<Vec<IntegrityOffender>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.id, i.invoice_number, i.total_amount, COALESCE(i.tax_amount, 0), COALESCE(i.discount_amount, 0),
+                    COALESCE(i.round_off, 0)
+             FROM invoices i
+             ORDER BY i.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let invoices = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, f64>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, f64>(5)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load invoices: {}", e))?;
+
+    let mut item_stmt = conn
+        .prepare("SELECT quantity, unit_price FROM invoice_items WHERE invoice_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let mut offenders = Vec::new();
+    for (id, invoice_number, total, tax, discount, round_off) in invoices {
+        let lines = item_stmt
+            .query_map([id], |row| Ok(money::line_total(row.get(1)?, row.get(0)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to load invoice items: {}", e))?;
+        let expected = money::sum([money::sum(lines), tax, -discount, round_off]);
+        if money::to_paise(total) != money::to_paise(expected) {
+            offenders.push(offender(
+                "invoices",
+                id,
+                format!("{}: total {:.2}, items, tax and discount come to {:.2}", invoice_number, total, expected),
+            ));
+        }
+    }
+    Ok(offenders)
+}
+
+/// Invoices paid more than their total
+fn check_customer_payments(conn: &Connection) -> Result<Vec<IntegrityOffender>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.id, i.invoice_number, i.total_amount, SUM(cp.amount)
+             FROM customer_payments cp
+             JOIN invoices i ON i.id = cp.invoice_id
+             GROUP BY i.id
+             ORDER BY i.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?, row.get::<_, f64>(3)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load customer payments: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|(_, _, total, paid)| money::exceeds(*paid, *total))
+        .map(|(id, invoice_number, total, paid)| {
+            offender("invoices", id, format!("{}: paid {:.2} against a total of {:.2}", invoice_number, paid, total))
+        })
+        .collect())
+}
+
+/// Suppliers paid more than their purchases come to: PO lines (base currency, less returns set
+/// off against them) on POs that weren't cancelled, plus the opening stock of their products
+fn check_supplier_payments(conn: &Connection) -> Result<Vec<IntegrityOffender>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT s.id, s.name,
+                    (SELECT COALESCE(SUM(sp.amount), 0) FROM supplier_payments sp WHERE sp.supplier_id = s.id),
+                    (SELECT COALESCE(SUM(poi.total_cost * po.exchange_rate), 0)
+                     FROM purchase_order_items poi
+                     JOIN purchase_orders po ON po.id = poi.po_id
+                     WHERE po.supplier_id = s.id AND po.status != 'cancelled')
+                    - (SELECT COALESCE(SUM(pri.total_cost), 0)
+                       FROM purchase_return_items pri
+                       JOIN purchase_returns pr ON pr.id = pri.return_id
+                       WHERE pr.supplier_id = s.id AND pr.reduces_payable = 1)
+                    + (SELECT COALESCE(SUM(COALESCE(p.initial_stock, 0) * p.price), 0) FROM products p WHERE p.supplier_id = s.id)
+             FROM suppliers s
+             ORDER BY s.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?, row.get::<_, f64>(3)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load supplier payments: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|(_, _, paid, purchases)| money::exceeds(*paid, *purchases))
+        .map(|(id, name, paid, purchases)| {
+            offender("suppliers", id, format!("{}: paid {:.2} against purchases of {:.2}", name, paid, purchases))
+        })
+        .collect())
+}
+
+/// Rows whose foreign key points at a row that no longer exists
+fn check_orphan_rows(conn: &Connection) -> Result<Vec<IntegrityOffender>, String> {
+    let mut stmt = conn.prepare("PRAGMA foreign_key_check").map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?, row.get::<_, String>(2)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to check foreign keys: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(table, rowid, parent)| {
+            let detail = format!("{} row references a missing {} row", table, parent);
+            offender(&table, rowid.unwrap_or(0), detail)
+        })
+        .collect())
+}
+
+/// Tables whose sqlite_sequence counter is behind their highest id, so the next insert could
+/// reuse an id
+fn check_sequences(conn: &Connection) -> Result<Vec<IntegrityOffender>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT s.name, s.seq FROM sqlite_sequence s
+             JOIN sqlite_master m ON m.type = 'table' AND m.name = s.name
+             ORDER BY s.name",
+        )
+        .map_err(|e| e.to_string())?;
+    let sequences = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load sequences: {}", e))?;
+
+    let mut offenders = Vec::new();
+    for (table, seq) in sequences {
+        let max_id: Option<i64> = conn
+            .query_row(&format!("SELECT MAX(rowid) FROM \"{}\"", table.replace('"', "\"\"")), [], |row| row.get(0))
+            .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+        if let Some(max_id) = max_id.filter(|max_id| *max_id > seq) {
+            offenders.push(offender(&table, max_id, format!("{} counter is at {} but id {} is in use", table, seq, max_id)));
+        }
+    }
+    Ok(offenders)
+}
+
+/// Run every check and store the report
+pub(crate) fn run_integrity_checks_internal(conn: &Connection, source: &str, business_date: &str) -> Result<IntegrityReport, String> {
+    let started = Instant::now();
+    let mut checks = Vec::with_capacity(CHECKS.len());
+    for (key, description, check) in CHECKS {
+        let mut offenders = check(conn).map_err(|e| format!("Integrity check {} failed to run: {}", key, e))?;
+        let offending_count = offenders.len();
+        offenders.truncate(MAX_OFFENDERS);
+        checks.push(IntegrityCheck {
+            key: key.to_string(),
+            description: description.to_string(),
+            passed: offending_count == 0,
+            offending_count,
+            offenders,
+        });
+    }
+
+    let failed_count = checks.iter().filter(|check| !check.passed).count() as i32;
+    let checks_json = serde_json::to_string(&checks).map_err(|e| format!("Failed to serialize integrity report: {}", e))?;
+    conn.execute(
+        "INSERT INTO integrity_reports (source, business_date, passed, failed_count, checks, duration_ms)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![source, business_date, failed_count == 0, failed_count, checks_json, started.elapsed().as_millis() as i64],
+    )
+    .map_err(|e| format!("Failed to save integrity report: {}", e))?;
+    let id = conn.last_insert_rowid();
+
+    log::info!("Integrity checks ({}): {} of {} failed", source, failed_count, checks.len());
+    load_report(conn, id)?.ok_or_else(|| "Failed to load integrity report".to_string())
+}
+
+fn load_report(conn: &Connection, id: i64) -> Result<Option<IntegrityReport>, String> {
+    conn.query_row(
+        "SELECT id, source, business_date, passed, failed_count, checks, duration_ms, created_at
+         FROM integrity_reports WHERE id = ?1",
+        [id],
+        |row| {
+            let checks: String = row.get(5)?;
+            Ok(IntegrityReport {
+                id: row.get(0)?,
+                source: row.get(1)?,
+                business_date: row.get(2)?,
+                passed: row.get(3)?,
+                failed_count: row.get(4)?,
+                checks: serde_json::from_str(&checks).unwrap_or_default(),
+                duration_ms: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load integrity report: {}", e))
+}
+
+/// Business-local date and time now
+fn business_now(conn: &Connection) -> NaiveDateTime {
+    chrono::Utc::now().naive_utc() + chrono::Duration::minutes(business_offset_minutes(conn) as i64)
+}
+
+/// Raise an integrity-warning for a report with failed checks
+fn notify_integrity_warning(app: &AppHandle, report: &IntegrityReport) {
+    if report.passed {
+        return;
+    }
+    let warning = IntegrityWarning {
+        report_id: report.id,
+        source: report.source.clone(),
+        failed_checks: report
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.description.clone())
+            .collect(),
+    };
+    if let Err(e) = app.emit(INTEGRITY_WARNING_EVENT, &warning) {
+        log::warn!("Failed to emit {}: {}", INTEGRITY_WARNING_EVENT, e);
+    }
+}
+
+/// Run the integrity checks now and store the report
+#[tauri::command]
+pub fn run_integrity_checks(app: AppHandle, db: State<Database>) -> Result<IntegrityReport, String> {
+    log::info!("run_integrity_checks called");
+
+    let conn = db.get_conn()?;
+    let today = business_now(&conn).date().format("%Y-%m-%d").to_string();
+    let report = run_integrity_checks_internal(&conn, "manual", &today)?;
+    notify_integrity_warning(&app, &report);
+    Ok(report)
+}
+
+/// Get the most recent integrity report, if the checks have ever run
+#[tauri::command]
+pub fn get_latest_integrity_report(db: State<Database>) -> Result<Option<IntegrityReport>, String> {
+    log::info!("get_latest_integrity_report called");
+
+    let conn = db.get_conn()?;
+    get_latest_integrity_report_internal(&conn)
+}
+
+pub(crate) fn get_latest_integrity_report_internal(conn: &Connection) -> Result<Option<IntegrityReport>, String> {
+    let latest: Option<i64> = conn
+        .query_row("SELECT MAX(id) FROM integrity_reports", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to load integrity report: {}", e))?;
+    match latest {
+        Some(id) => load_report(conn, id),
+        None => Ok(None),
+    }
+}
+
+/// The scheduled run for the business day of `now`, when integrity_checks_nightly is on, the
+/// run time has passed and the day hasn't had one yet; None otherwise
+pub(crate) fn run_due_integrity_checks(conn: &Connection, now: NaiveDateTime) -> Result<Option<IntegrityReport>, String> {
+    let setting = |key: &str| -> Result<Option<String>, String> {
+        conn.query_row("SELECT value FROM app_settings WHERE key = ?1", [key], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to load setting {}: {}", key, e))
+    };
+    if setting(NIGHTLY_KEY)?.as_deref() != Some("true") {
+        return Ok(None);
+    }
+    let run_time = setting(RUN_TIME_KEY)?
+        .and_then(|time| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok())
+        .unwrap_or_else(|| NaiveTime::parse_from_str(DEFAULT_RUN_TIME, "%H:%M").expect("valid default run time"));
+    if now.time() < run_time {
+        return Ok(None);
+    }
+
+    let today = now.date().format("%Y-%m-%d").to_string();
+    let already_run: bool = conn
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM integrity_reports WHERE source = 'scheduled' AND business_date = ?1",
+            [&today],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to load integrity reports: {}", e))?;
+    if already_run {
+        return Ok(None);
+    }
+    run_integrity_checks_internal(conn, "scheduled", &today).map(Some)
+}
+
+/// Start the nightly scheduler; it looks every POLL_INTERVAL whether the day's run is due and
+/// stays out of the way of restores, imports and maintenance
+pub fn start_integrity_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if app.state::<MaintenanceState>().ensure_writable().is_ok() {
+                let due = app
+                    .state::<Database>()
+                    .get_conn()
+                    .and_then(|conn| run_due_integrity_checks(&conn, business_now(&conn)));
+                match due {
+                    Ok(Some(report)) => notify_integrity_warning(&app, &report),
+                    Ok(None) => {}
+                    Err(e) => log::warn!("Scheduled integrity checks: {}", e),
+                }
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::invoices::{create_invoice_internal, CreateInvoiceInput};
+    use crate::commands::products::create_product_internal;
+    use crate::test_support::{insert_customer, insert_supplier, invoice_input, product_input, TestDb};
+
+    fn failed(report: &IntegrityReport) -> Vec<(&str, Vec<i64>)> {
+        report
+            .checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| (check.key.as_str(), check.offenders.iter().map(|o| o.id).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_checks_flag_drifted_rows() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let customer_id = insert_customer(&conn, "Sharma Builders");
+        let supplier_id = insert_supplier(&conn, "Acme Traders");
+        let product_id = create_product_internal(&conn, product_input("KET-1", 60.0, 20, None)).unwrap().id;
+        let invoice = create_invoice_internal(
+            &mut conn,
+            CreateInvoiceInput {
+                tax_amount: Some(18.0),
+                discount_amount: Some(10.0),
+                ..invoice_input(Some(customer_id), vec![(product_id, 2, 100.0)])
+            },
+        )
+        .unwrap();
+
+        let report = run_integrity_checks_internal(&conn, "manual", "2025-06-01").unwrap();
+        assert!(report.passed, "{:?}", failed(&report));
+        assert_eq!(report.checks.len(), CHECKS.len());
+
+        // Drift each check's data behind the commands' backs
+        conn.execute("UPDATE products SET stock_quantity = stock_quantity + 1 WHERE id = ?1", [product_id]).unwrap();
+        conn.execute("UPDATE invoices SET total_amount = 200 WHERE id = ?1", [invoice.id]).unwrap();
+        conn.execute(
+            "INSERT INTO customer_payments (customer_id, invoice_id, amount) VALUES (?1, ?2, 250)",
+            params![customer_id, invoice.id],
+        )
+        .unwrap();
+        conn.execute("INSERT INTO supplier_payments (supplier_id, amount) VALUES (?1, 500)", [supplier_id]).unwrap();
+        conn.execute_batch(
+            "PRAGMA foreign_keys = OFF;
+             INSERT INTO invoice_items (invoice_id, product_id, quantity, unit_price, product_name) VALUES (9999, 1, 1, 1, 'Ghost');
+             PRAGMA foreign_keys = ON;",
+        )
+        .unwrap();
+        let orphan_id = conn.last_insert_rowid();
+        conn.execute("UPDATE sqlite_sequence SET seq = 0 WHERE name = 'suppliers'", []).unwrap();
+
+        let report = run_integrity_checks_internal(&conn, "manual", "2025-06-01").unwrap();
+        assert!(!report.passed);
+        assert_eq!(report.failed_count, 6);
+        let invoice_id = invoice.id as i64;
+        assert_eq!(
+            failed(&report),
+            vec![
+                ("batch_stock", vec![product_id as i64]),
+                ("invoice_totals", vec![invoice_id]),
+                ("customer_payments", vec![invoice_id]),
+                ("supplier_payments", vec![supplier_id as i64]),
+                ("orphan_rows", vec![orphan_id]),
+                ("sequences", vec![supplier_id as i64]),
+            ]
+        );
+
+        let latest = get_latest_integrity_report_internal(&conn).unwrap().unwrap();
+        assert_eq!((latest.id, latest.failed_count), (report.id, 6));
+        assert_eq!(latest.checks[0].offenders[0].table, "products");
+    }
+
+    #[test]
+    fn test_nightly_run_happens_once_per_day_after_the_run_time() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let at = |time: &str| NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap();
+
+        assert!(run_due_integrity_checks(&conn, at("2025-06-01 03:00")).unwrap().is_none());
+
+        conn.execute_batch(
+            "INSERT INTO app_settings (key, value) VALUES ('integrity_checks_nightly', 'true'), ('backup_time', '01:30');",
+        )
+        .unwrap();
+        assert!(run_due_integrity_checks(&conn, at("2025-06-01 01:00")).unwrap().is_none());
+        let report = run_due_integrity_checks(&conn, at("2025-06-01 01:45")).unwrap().unwrap();
+        assert_eq!((report.source.as_str(), report.business_date.as_str()), ("scheduled", "2025-06-01"));
+        assert!(run_due_integrity_checks(&conn, at("2025-06-01 23:00")).unwrap().is_none());
+        assert!(run_due_integrity_checks(&conn, at("2025-06-02 01:30")).unwrap().is_some());
+    }
+}
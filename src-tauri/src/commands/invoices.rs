@@ -1,19 +1,39 @@
-use crate::db::{Database, Invoice};
-use crate::commands::PaginatedResult;
-use crate::services::inventory_service;
-use chrono::Utc;
+use crate::db::{CustomerPayment, Database, Invoice};
+use crate::commands::{validate_pagination, PaginatedResult};
+use crate::commands::biometric::{action_requires_biometric, consume_action_approval, require_biometric_approval};
+use crate::commands::bundles;
+use crate::commands::day_closures;
+use crate::commands::analytics::{business_offset_minutes, business_today, parse_report_date, ReportRange};
+use crate::commands::customer_payments::{get_customer_credit_summary_internal, get_invoice_payments_internal};
+use crate::commands::price_overrides::{find_price_overrides, PRICE_OVERRIDE_REASON_REQUIRED};
+use crate::commands::price_tiers::{customer_price_tier, list_price, tier_unit_price};
+use crate::commands::products;
+use crate::commands::reservations;
+use crate::commands::stock_alerts;
+use crate::commands::webhooks;
+use crate::commands::settings::{allow_negative_stock, billing_defaults, BillingDefaults};
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use crate::services::{inventory_service, money, quantity};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, State};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateInvoiceItemInput {
     pub product_id: i32,
-    pub quantity: i32,
+    pub quantity: f64,
     pub unit_price: f64,
     pub discount_amount: Option<f64>, // Per-item weighted discount
+    /// GST rate (%) for this line; falls back to the invoice-level gst_rate
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
+    /// Stock reservation this line fulfils; its stock is sold and the reservation released
+    #[serde(default)]
+    pub reservation_id: Option<i64>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateInvoiceInput {
     pub customer_id: Option<i32>,
     pub items: Vec<CreateInvoiceItemInput>,
@@ -25,6 +45,61 @@ pub struct CreateInvoiceInput {
     pub town: Option<String>,
     // Credit payment fields
     pub initial_paid: Option<f64>,
+    /// Price tier the items were priced with; defaults to the customer's current tier
+    #[serde(default)]
+    pub price_tier_id: Option<i32>,
+    /// Invoice-level GST rate (%) for items that carry no tax_rate of their own
+    #[serde(default)]
+    pub gst_rate: Option<f64>,
+    /// Let a credit sale go over the customer's credit limit; needs `approved_by`
+    #[serde(default)]
+    pub allow_over_limit: bool,
+    /// Manager who approved going over the credit limit
+    #[serde(default)]
+    pub approved_by: Option<String>,
+    /// "YYYY-MM-DD" the credit is due; defaults to today plus the credit period setting
+    #[serde(default)]
+    pub due_date: Option<String>,
+    /// Split payment, e.g. part cash and part UPI. Must add up to the total, or to less when the
+    /// customer takes the rest on credit; payment_method and initial_paid then follow from it.
+    #[serde(default)]
+    pub payments: Option<Vec<InvoiceTender>>,
+    /// Why lines were billed under their list or tier price; needed once a line goes past the
+    /// price_override_tolerance_percent setting
+    #[serde(default)]
+    pub price_override_reason: Option<String>,
+    /// Approval token for the price overrides, when require_biometric_for lists price_override
+    #[serde(default)]
+    pub price_override_approval: Option<String>,
+    /// Cashier billing the sale, recorded with its price overrides
+    #[serde(default)]
+    pub created_by: Option<String>,
+}
+
+/// Part of an invoice's payment taken in one method (stored in invoice_tenders)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceTender {
+    pub method: String,
+    pub amount: f64,
+}
+
+/// payment_method of an invoice paid in full with more than one method
+pub const SPLIT_PAYMENT_METHOD: &str = "Split";
+
+/// Prefix of the error returned when a credit sale would exceed the customer's credit limit;
+/// the rest of the message is a JSON `CreditLimitExceeded`
+pub const CREDIT_LIMIT_EXCEEDED: &str = "CREDIT_LIMIT_EXCEEDED:";
+
+/// Note on the customer payment recording what a credit invoice's customer paid at billing
+pub(crate) const INITIAL_PAYMENT_NOTE: &str = "Initial payment at invoice creation";
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CreditLimitExceeded {
+    pub customer_id: i32,
+    pub credit_limit: f64,
+    pub current_outstanding: f64,
+    pub invoice_credit: f64,
+    pub overflow: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,6 +109,11 @@ pub struct UpdateInvoiceInput {
     pub payment_method: Option<String>,
     pub created_at: Option<String>,
     pub status: Option<String>, // Reserved for future use (e.g., 'paid', 'void')
+    #[serde(default)]
+    pub modified_by: Option<String>,
+    /// Manager who approved changing an invoice in a closed business day
+    #[serde(default)]
+    pub closed_day_override: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,20 +123,45 @@ pub struct InvoiceItemWithProduct {
     pub product_id: i32,
     pub product_name: String,
     pub product_sku: String,
-    pub quantity: i32,
+    pub quantity: f64,
     pub unit_price: f64,
     pub discount_amount: f64, // Per-item weighted discount
+    /// GST rate (%) applied to this line; None on invoices taxed only at invoice level
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
+    #[serde(default)]
+    pub tax_amount: Option<f64>,
+    /// Units sold past the stock on hand (allow_negative_stock), still to be supplied
+    #[serde(default)]
+    pub backordered_quantity: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct InvoiceWithItems {
     pub invoice: Invoice,
     pub items: Vec<InvoiceItemWithProduct>,
+    /// Customer payments applied to this invoice, newest first
+    #[serde(default)]
+    pub payments: Vec<CustomerPayment>,
+    /// Paid at the counter when a credit invoice was created
+    #[serde(default)]
+    pub initial_paid: f64,
+    /// Part of a credit invoice left on credit when it was created
+    #[serde(default)]
+    pub credit_amount: f64,
+    /// Non-credit invoices count as fully paid
+    #[serde(default)]
+    pub amount_paid: f64,
+    #[serde(default)]
+    pub balance_due: f64,
+    /// How a split payment was taken; empty for invoices paid with payment_method alone
+    #[serde(default)]
+    pub tenders: Vec<InvoiceTender>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProductSalesSummary {
-    pub total_quantity: i32,
+    pub total_quantity: f64,
     pub total_amount: f64,
     pub invoice_count: i32,
 }
@@ -65,7 +170,16 @@ pub struct ProductSalesSummary {
 pub struct UpdateInvoiceItemsInput {
     pub invoice_id: i32,
     pub items: Vec<CreateInvoiceItemInput>, // New list of items
+    /// New invoice-level tax; None keeps the invoice's tax (recomputed when it is taxed by rate)
+    #[serde(default)]
+    pub tax_amount: Option<f64>,
+    /// New invoice-level discount; None keeps the invoice's discount
+    #[serde(default)]
+    pub discount_amount: Option<f64>,
     pub modified_by: Option<String>,
+    /// Manager who approved changing an invoice in a closed business day
+    #[serde(default)]
+    pub closed_day_override: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -90,24 +204,73 @@ pub struct DeletedInvoice {
     pub deleted_by: Option<String>,
 }
 
-/// Get all invoices with pagination, search, and optional customer filter
+/// Amount paid against an invoice: payments recorded for a credit sale (the initial payment is
+/// recorded there too, so initial_paid is the floor for older bills), the full total otherwise
+pub(crate) const INVOICE_PAID_SQL: &str = "CASE WHEN i.credit_amount > 0 OR i.payment_method = 'Credit'
+    THEN MAX(COALESCE(i.initial_paid, 0),
+             COALESCE((SELECT SUM(cp.amount) FROM customer_payments cp WHERE cp.invoice_id = i.id), 0))
+    ELSE i.total_amount END";
+
+/// Invoice list filters beyond search and customer; every field is optional
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InvoiceListFilters {
+    /// Business-local "YYYY-MM-DD", inclusive
+    pub start_date: Option<String>,
+    /// Business-local "YYYY-MM-DD", inclusive
+    pub end_date: Option<String>,
+    /// Also matches invoices with a split payment that used the method
+    pub payment_method: Option<String>,
+    pub min_amount: Option<f64>,
+    pub max_amount: Option<f64>,
+    /// "paid", "partial" or "unpaid"
+    pub credit_status: Option<String>,
+    /// "date" (default), "amount" or "customer"
+    pub sort: Option<String>,
+    /// Defaults to newest/largest first for date and amount, A-Z for customer
+    pub descending: Option<bool>,
+}
+
+/// Get all invoices with pagination, search, optional customer filter and list filters
 #[tauri::command]
 pub fn get_invoices(
     page: i32,
     page_size: i32,
     search: Option<String>,
     customer_id: Option<i32>,
+    filters: Option<InvoiceListFilters>,
     db: State<Database>
 ) -> Result<PaginatedResult<Invoice>, String> {
-    log::info!("get_invoices called - page: {}, size: {}, search: {:?}, customer_id: {:?}", page, page_size, search, customer_id);
+    log::info!(
+        "get_invoices called - page: {}, size: {}, search: {:?}, customer_id: {:?}, filters: {:?}",
+        page, page_size, search, customer_id, filters
+    );
 
     let conn = db.get_conn()?;
+    get_invoices_internal(&conn, page, page_size, search.as_deref(), customer_id, &filters.unwrap_or_default())
+}
 
-    let offset = (page - 1) * page_size;
-    let limit = page_size;
+pub(crate) fn get_invoices_internal(
+    conn: &Connection,
+    page: i32,
+    page_size: i32,
+    search: Option<&str>,
+    customer_id: Option<i32>,
+    filters: &InvoiceListFilters,
+) -> Result<PaginatedResult<Invoice>, String> {
+    let (limit, offset) = validate_pagination(page, page_size)?;
 
     let mut invoices = Vec::new();
-    let total_count: i64;
+
+    // Whitelisted so the column name never comes from the caller
+    let (sort_column, default_descending) = match filters.sort.as_deref() {
+        None | Some("date") => ("i.created_at", true),
+        Some("amount") => ("i.total_amount", true),
+        Some("customer") => ("c.name COLLATE NOCASE", false),
+        Some(other) => return Err(format!("Invalid sort '{}', expected date, amount or customer", other)),
+    };
+    let direction = if filters.descending.unwrap_or(default_descending) { "DESC" } else { "ASC" };
+    let order_by = format!("ORDER BY {} {}, i.id {}", sort_column, direction, direction);
 
     // Base query with JOIN to get customer details
     let base_select = "
@@ -117,28 +280,76 @@ pub fn get_invoices(
             i.cgst_amount, i.fy_year, i.gst_rate, i.igst_amount, i.sgst_amount, 
             i.state, i.district, i.town,
             c.name as customer_name, c.phone as customer_phone,
-            (SELECT COUNT(*) FROM invoice_items WHERE invoice_id = i.id) as item_count
+            (SELECT COUNT(*) FROM invoice_items WHERE invoice_id = i.id) as item_count,
+            COALESCE(i.round_off, 0), i.due_date
         FROM invoices i
         LEFT JOIN customers c ON i.customer_id = c.id
     ";
 
     let count_select = "SELECT COUNT(*) FROM invoices i LEFT JOIN customers c ON i.customer_id = c.id";
 
-    let mut where_clauses = Vec::new();
+    let mut where_clauses: Vec<String> = Vec::new();
     let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
     if let Some(cust_id) = customer_id {
-        where_clauses.push("i.customer_id = ?");
+        where_clauses.push("i.customer_id = ?".to_string());
         params.push(Box::new(cust_id));
     }
 
     if let Some(search_term) = search {
-        where_clauses.push("(i.invoice_number LIKE ? OR c.name LIKE ?)");
+        // Item names are matched on the snapshot printed on the bill, so renamed and deleted
+        // products still find their invoices; EXISTS keeps one row per invoice
+        where_clauses.push(format!("(i.invoice_number LIKE ? OR c.name LIKE ? OR {})", INVOICE_HAS_ITEM_NAMED_SQL));
         let pattern = format!("%{}%", search_term);
         params.push(Box::new(pattern.clone()));
+        params.push(Box::new(pattern.clone()));
         params.push(Box::new(pattern));
     }
 
+    // Dates are business-local days; created_at is stored in UTC
+    let offset_minutes = business_offset_minutes(conn);
+    if let Some(start) = &filters.start_date {
+        let day = parse_report_date(start)?;
+        where_clauses.push("datetime(i.created_at) >= ?".to_string());
+        params.push(Box::new(ReportRange::new(day, day, offset_minutes).start_utc));
+    }
+    if let Some(end) = &filters.end_date {
+        let day = parse_report_date(end)?;
+        where_clauses.push("datetime(i.created_at) < ?".to_string());
+        params.push(Box::new(ReportRange::new(day, day, offset_minutes).end_utc));
+    }
+
+    // Split invoices match on any method they were partly paid with
+    if let Some(method) = &filters.payment_method {
+        where_clauses.push(
+            "(i.payment_method = ? COLLATE NOCASE
+              OR EXISTS (SELECT 1 FROM invoice_tenders t WHERE t.invoice_id = i.id AND t.method = ? COLLATE NOCASE))"
+                .to_string(),
+        );
+        params.push(Box::new(method.clone()));
+        params.push(Box::new(method.clone()));
+    }
+
+    if let Some(min) = filters.min_amount {
+        where_clauses.push("i.total_amount >= ?".to_string());
+        params.push(Box::new(min));
+    }
+    if let Some(max) = filters.max_amount {
+        where_clauses.push("i.total_amount <= ?".to_string());
+        params.push(Box::new(max));
+    }
+
+    // Half a paisa either side absorbs float noise in the payment sums
+    if let Some(status) = &filters.credit_status {
+        let condition = match status.as_str() {
+            "paid" => "({paid}) >= i.total_amount - 0.005",
+            "partial" => "({paid}) > 0.005 AND ({paid}) < i.total_amount - 0.005",
+            "unpaid" => "({paid}) <= 0.005 AND i.total_amount > 0.005",
+            other => return Err(format!("Invalid credit status '{}', expected paid, partial or unpaid", other)),
+        };
+        where_clauses.push(condition.replace("{paid}", INVOICE_PAID_SQL));
+    }
+
     let where_sql = if where_clauses.is_empty() {
         String::new()
     } else {
@@ -152,12 +363,12 @@ pub fn get_invoices(
     // rusqlite requires params as a slice of references
     let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
     
-    total_count = count_stmt
+    let total_count: i64 = count_stmt
         .query_row(rusqlite::params_from_iter(param_refs.iter()), |row| row.get(0))
         .map_err(|e| e.to_string())?;
 
     // Get paginated items
-    let query = format!("{} {} ORDER BY i.created_at DESC LIMIT ? OFFSET ?", base_select, where_sql);
+    let query = format!("{} {} {} LIMIT ? OFFSET ?", base_select, where_sql, order_by);
     let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
 
     // Add limit and offset to params
@@ -183,6 +394,8 @@ pub fn get_invoices(
                 gst_rate: row.get(10)?,
                 igst_amount: row.get(11)?,
                 sgst_amount: row.get(12)?,
+                round_off: row.get(19)?,
+                due_date: row.get(20)?,
                 state: row.get(13)?,
                 district: row.get(14)?,
                 town: row.get(15)?,
@@ -207,6 +420,41 @@ pub fn get_invoices(
 }
 
 
+/// Whether invoice `i` has a line whose product_name snapshot matches the next LIKE parameter
+pub(crate) const INVOICE_HAS_ITEM_NAMED_SQL: &str =
+    "EXISTS (SELECT 1 FROM invoice_items ii WHERE ii.invoice_id = i.id AND ii.product_name LIKE ?)";
+
+/// What a product's lines (gross `item_gross`, less their own discounts) earned on an invoice,
+/// after their weighted share of the invoice-level discount
+fn net_product_amount(
+    total_amount: f64,
+    tax_amount: f64,
+    global_discount: f64,
+    round_off: f64,
+    item_gross: f64,
+    item_discount: f64,
+) -> f64 {
+    // Reconstruct Invoice Gross Subtotal to calculate weight
+    // Invoice Total = Subtotal + Tax - Discount + Round Off
+    // Subtotal = Invoice Total - Round Off - Tax + Discount
+    let invoice_subtotal = total_amount - round_off - tax_amount + global_discount;
+
+    let weighted_global_discount = if invoice_subtotal > 0.0 && global_discount > 0.0 {
+        money::round_money((item_gross / invoice_subtotal) * global_discount)
+    } else {
+        0.0
+    };
+
+    money::sum([item_gross, -item_discount, -weighted_global_discount])
+}
+
+/// Invoice lines for product ?1, newest invoice first; looked up through idx_invoice_items_product
+pub(crate) const INVOICES_BY_PRODUCT_SQL: &str = "SELECT i.id, i.invoice_number, i.customer_id, i.total_amount, i.tax_amount, i.discount_amount, i.payment_method, i.created_at, i.cgst_amount, i.fy_year, i.gst_rate, i.igst_amount, i.sgst_amount, i.state, i.district, i.town, ii.quantity, ii.unit_price, ii.discount_amount, COALESCE(i.round_off, 0), i.due_date
+             FROM invoices i
+             JOIN invoice_items ii ON i.id = ii.invoice_id
+             WHERE ii.product_id = ?1
+             ORDER BY i.created_at DESC";
+
 /// Get all invoices containing a specific product
 #[tauri::command]
 pub fn get_invoices_by_product(product_id: i32, db: State<Database>) -> Result<Vec<Invoice>, String> {
@@ -215,40 +463,22 @@ pub fn get_invoices_by_product(product_id: i32, db: State<Database>) -> Result<V
     let conn = db.get_conn()?;
 
     // Query now fetches necessary fields to calculate weighted discount
-    let mut stmt = conn
-        .prepare(
-            "SELECT i.id, i.invoice_number, i.customer_id, i.total_amount, i.tax_amount, i.discount_amount, i.payment_method, i.created_at, i.cgst_amount, i.fy_year, i.gst_rate, i.igst_amount, i.sgst_amount, i.state, i.district, i.town, ii.quantity, ii.unit_price, ii.discount_amount
-             FROM invoices i
-             JOIN invoice_items ii ON i.id = ii.invoice_id
-             WHERE ii.product_id = ?1
-             ORDER BY i.created_at DESC"
-        )
-        .map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(INVOICES_BY_PRODUCT_SQL).map_err(|e| e.to_string())?;
 
     let invoice_iter = stmt
         .query_map([product_id], |row| {
             let total_amount: f64 = row.get(3)?;
             let tax_amount: f64 = row.get(4)?;
             let global_discount: f64 = row.get(5)?;
-            let qty: i32 = row.get(16)?;
+            let qty: f64 = row.get(16)?;
             let unit_price: f64 = row.get(17)?;
             let item_discount: f64 = row.get::<_, Option<f64>>(18)?.unwrap_or(0.0);
+            let round_off: f64 = row.get(19)?;
 
             // Calculate Net Product Amount applying both item and weighted global discount
-            let item_gross = qty as f64 * unit_price;
-            
-            // Reconstruct Invoice Gross Subtotal to calculate weight
-            // Invoice Total = Subtotal + Tax - Discount
-            // Subtotal = Invoice Total - Tax + Discount
-            let invoice_subtotal = total_amount - tax_amount + global_discount;
-
-            let weighted_global_discount = if invoice_subtotal > 0.0 && global_discount > 0.0 {
-                (item_gross / invoice_subtotal) * global_discount
-            } else {
-                0.0
-            };
-
-            let net_product_amount = item_gross - item_discount - weighted_global_discount;
+            let item_gross = money::line_total(unit_price, qty);
+            let net_product_amount =
+                net_product_amount(total_amount, tax_amount, global_discount, round_off, item_gross, item_discount);
 
             Ok(Invoice {
                 id: row.get(0)?,
@@ -264,6 +494,8 @@ pub fn get_invoices_by_product(product_id: i32, db: State<Database>) -> Result<V
                 gst_rate: row.get(10)?,
                 igst_amount: row.get(11)?,
                 sgst_amount: row.get(12)?,
+                round_off,
+                due_date: row.get(20)?,
                 state: row.get(13)?,
                 district: row.get(14)?,
                 town: row.get(15)?,
@@ -285,29 +517,141 @@ pub fn get_invoices_by_product(product_id: i32, db: State<Database>) -> Result<V
     Ok(invoices)
 }
 
+/// Get invoices with a line whose product name, as printed on the bill, matches `name_pattern`.
+/// Works for products that were renamed or deleted since; matching lines are summed per invoice.
+#[tauri::command]
+pub fn get_invoices_by_product_name(
+    name_pattern: String,
+    page: i32,
+    page_size: i32,
+    db: State<Database>,
+) -> Result<PaginatedResult<Invoice>, String> {
+    log::info!("get_invoices_by_product_name called with pattern: {}", name_pattern);
+
+    let conn = db.get_conn()?;
+    get_invoices_by_product_name_internal(&conn, &name_pattern, page, page_size)
+}
+
+pub(crate) fn get_invoices_by_product_name_internal(
+    conn: &Connection,
+    name_pattern: &str,
+    page: i32,
+    page_size: i32,
+) -> Result<PaginatedResult<Invoice>, String> {
+    let (limit, offset) = validate_pagination(page, page_size)?;
+    let name_pattern = name_pattern.trim();
+    if name_pattern.is_empty() {
+        return Err("Validation error: product name is required".to_string());
+    }
+    let pattern = format!("%{}%", name_pattern);
+
+    let total_count: i64 = conn
+        .query_row(
+            "SELECT COUNT(DISTINCT invoice_id) FROM invoice_items WHERE product_name LIKE ?1",
+            [&pattern],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.id, i.invoice_number, i.customer_id, i.total_amount, i.tax_amount, i.discount_amount,
+                    i.payment_method, i.created_at, i.cgst_amount, i.fy_year, i.gst_rate, i.igst_amount,
+                    i.sgst_amount, i.state, i.district, i.town, c.name, c.phone,
+                    (SELECT COUNT(*) FROM invoice_items WHERE invoice_id = i.id),
+                    COALESCE(i.round_off, 0), i.due_date,
+                    SUM(ii.quantity), SUM(ii.quantity * ii.unit_price), SUM(COALESCE(ii.discount_amount, 0))
+             FROM invoices i
+             JOIN invoice_items ii ON ii.invoice_id = i.id
+             LEFT JOIN customers c ON c.id = i.customer_id
+             WHERE ii.product_name LIKE ?1
+             GROUP BY i.id
+             ORDER BY i.created_at DESC, i.id DESC
+             LIMIT ?2 OFFSET ?3",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let invoices = stmt
+        .query_map(rusqlite::params![pattern, limit, offset], |row| {
+            let total_amount: f64 = row.get(3)?;
+            let tax_amount: f64 = row.get(4)?;
+            let global_discount: f64 = row.get(5)?;
+            let round_off: f64 = row.get(19)?;
+            let item_gross = money::round_money(row.get(22)?);
+            let item_discount: f64 = row.get(23)?;
+
+            Ok(Invoice {
+                id: row.get(0)?,
+                invoice_number: row.get(1)?,
+                customer_id: row.get(2)?,
+                total_amount,
+                tax_amount,
+                discount_amount: global_discount,
+                payment_method: row.get(6)?,
+                created_at: row.get(7)?,
+                cgst_amount: row.get(8)?,
+                fy_year: row.get(9)?,
+                gst_rate: row.get(10)?,
+                igst_amount: row.get(11)?,
+                sgst_amount: row.get(12)?,
+                round_off,
+                due_date: row.get(20)?,
+                state: row.get(13)?,
+                district: row.get(14)?,
+                town: row.get(15)?,
+                customer_name: row.get(16)?,
+                customer_phone: row.get(17)?,
+                item_count: row.get(18)?,
+                quantity: row.get(21)?,
+                product_amount: Some(net_product_amount(
+                    total_amount,
+                    tax_amount,
+                    global_discount,
+                    round_off,
+                    item_gross,
+                    item_discount,
+                )),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    log::info!("Returning {} invoices for product name '{}' (total {})", invoices.len(), name_pattern, total_count);
+    Ok(PaginatedResult { items: invoices, total_count })
+}
+
 /// Get a single invoice with its items
 #[tauri::command]
 pub fn get_invoice(id: i32, db: State<Database>) -> Result<InvoiceWithItems, String> {
     log::info!("get_invoice called with id: {}", id);
 
     let conn = db.get_conn()?;
+    get_invoice_internal(&conn, id)
+}
 
-    // Get invoice
-    let invoice = conn
+pub(crate) fn get_invoice_internal(conn: &Connection, id: i32) -> Result<InvoiceWithItems, String> {
+    // Get invoice with its credit fields and amount paid
+    let (invoice, initial_paid, credit_amount, amount_paid) = conn
         .query_row(
-            "SELECT 
-                i.id, i.invoice_number, i.customer_id, i.total_amount, i.tax_amount, 
-                i.discount_amount, i.payment_method, i.created_at, 
-                i.cgst_amount, i.fy_year, i.gst_rate, i.igst_amount, i.sgst_amount, 
-                i.state, i.district, i.town,
-                c.name as customer_name, c.phone as customer_phone,
-                (SELECT COUNT(*) FROM invoice_items WHERE invoice_id = i.id) as item_count
-            FROM invoices i
-            LEFT JOIN customers c ON i.customer_id = c.id
-            WHERE i.id = ?1",
+            &format!(
+                "SELECT 
+                    i.id, i.invoice_number, i.customer_id, i.total_amount, i.tax_amount, 
+                    i.discount_amount, i.payment_method, i.created_at, 
+                    i.cgst_amount, i.fy_year, i.gst_rate, i.igst_amount, i.sgst_amount, 
+                    i.state, i.district, i.town,
+                    c.name as customer_name, c.phone as customer_phone,
+                    (SELECT COUNT(*) FROM invoice_items WHERE invoice_id = i.id) as item_count,
+                    COALESCE(i.round_off, 0), i.due_date,
+                    COALESCE(i.initial_paid, 0), COALESCE(i.credit_amount, 0), {paid}
+                FROM invoices i
+                LEFT JOIN customers c ON i.customer_id = c.id
+                WHERE i.id = ?1",
+                paid = INVOICE_PAID_SQL
+            ),
             [id],
             |row| {
-                Ok(Invoice {
+                let invoice = Invoice {
                     id: row.get(0)?,
                     invoice_number: row.get(1)?,
                     customer_id: row.get(2)?,
@@ -321,6 +665,8 @@ pub fn get_invoice(id: i32, db: State<Database>) -> Result<InvoiceWithItems, Str
                     gst_rate: row.get(10)?,
                     igst_amount: row.get(11)?,
                     sgst_amount: row.get(12)?,
+                    round_off: row.get(19)?,
+                    due_date: row.get(20)?,
                     state: row.get(13)?,
                     district: row.get(14)?,
                     town: row.get(15)?,
@@ -329,7 +675,8 @@ pub fn get_invoice(id: i32, db: State<Database>) -> Result<InvoiceWithItems, Str
                     item_count: row.get(18)?,
                     quantity: None,
                     product_amount: None,
-                })
+                };
+                Ok((invoice, row.get::<_, f64>(21)?, row.get::<_, f64>(22)?, row.get::<_, f64>(23)?))
             },
         )
         .map_err(|e| format!("Invoice not found: {}", e))?;
@@ -337,7 +684,7 @@ pub fn get_invoice(id: i32, db: State<Database>) -> Result<InvoiceWithItems, Str
     // Get invoice items with product details
     let mut stmt = conn
         .prepare(
-            "SELECT ii.id, ii.invoice_id, ii.product_id, p.name, p.sku, ii.quantity, ii.unit_price, COALESCE(ii.discount_amount, 0)
+            "SELECT ii.id, ii.invoice_id, ii.product_id, p.name, p.sku, ii.quantity, ii.unit_price, COALESCE(ii.discount_amount, 0), ii.tax_rate, ii.tax_amount, ii.backordered_quantity
              FROM invoice_items ii
              JOIN products p ON ii.product_id = p.id
              WHERE ii.invoice_id = ?1"
@@ -355,6 +702,9 @@ pub fn get_invoice(id: i32, db: State<Database>) -> Result<InvoiceWithItems, Str
                 quantity: row.get(5)?,
                 unit_price: row.get(6)?,
                 discount_amount: row.get(7)?,
+                tax_rate: row.get(8)?,
+                tax_amount: row.get(9)?,
+                backordered_quantity: row.get(10)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -364,7 +714,291 @@ pub fn get_invoice(id: i32, db: State<Database>) -> Result<InvoiceWithItems, Str
         items.push(item.map_err(|e| e.to_string())?);
     }
 
-    Ok(InvoiceWithItems { invoice, items })
+    let payments = get_invoice_payments_internal(conn, id)?;
+    let tenders = invoice_tenders(conn, id)?;
+    // Sales returns aren't recorded against invoices yet; once they are, their value comes off
+    // the balance here as well
+    let amount_paid = money::round_money(amount_paid);
+    let balance_due = money::sub(invoice.total_amount, amount_paid).max(0.0);
+
+    Ok(InvoiceWithItems {
+        invoice,
+        items,
+        payments,
+        initial_paid,
+        credit_amount,
+        amount_paid,
+        balance_due,
+        tenders,
+    })
+}
+
+/// Something that changed for a line (or the customer) since the invoice being duplicated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateInvoiceWarning {
+    pub product_id: Option<i32>,
+    /// Name on the source invoice
+    pub name: String,
+    /// "customer_deleted", "product_deleted", "out_of_stock", "quantity_reduced" or "price_changed"
+    pub issue: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuplicatedInvoice {
+    pub source_invoice_id: i32,
+    pub source_invoice_number: String,
+    /// Unsaved draft for create_invoice
+    pub draft: CreateInvoiceInput,
+    /// Lines left out or changed, in the order of the source invoice
+    pub warnings: Vec<DuplicateInvoiceWarning>,
+}
+
+/// Draft a new sale from an earlier invoice, for "same as last time" orders. Nothing is saved
+/// or reserved; the cashier reviews the draft and bills it through create_invoice.
+#[tauri::command]
+pub fn duplicate_invoice(
+    source_invoice_id: i32,
+    keep_historical_prices: Option<bool>,
+    db: State<Database>,
+) -> Result<DuplicatedInvoice, String> {
+    log::info!(
+        "duplicate_invoice called for invoice {}, keep_historical_prices: {:?}",
+        source_invoice_id, keep_historical_prices
+    );
+
+    let conn = db.get_conn()?;
+    duplicate_invoice_internal(&conn, source_invoice_id, keep_historical_prices.unwrap_or(false))
+}
+
+/// Same customer and items as the source invoice. Quantities are capped at the stock now
+/// available (unless allow_negative_stock is on) and lines for deleted or sold-out products are
+/// left out with a warning. Prices are the customer's current tier or list price, or the
+/// invoice's own prices with `keep_historical_prices`, which also keeps its discounts. Tax and
+/// payment method come from the current billing defaults.
+pub(crate) fn duplicate_invoice_internal(
+    conn: &Connection,
+    source_invoice_id: i32,
+    keep_historical_prices: bool,
+) -> Result<DuplicatedInvoice, String> {
+    let (invoice_number, source_customer, customer_id, discount_amount, state, district, town) = conn
+        .query_row(
+            "SELECT i.invoice_number, i.customer_id, c.id, COALESCE(i.discount_amount, 0), i.state, i.district, i.town
+             FROM invoices i
+             LEFT JOIN customers c ON c.id = i.customer_id
+             WHERE i.id = ?1",
+            [source_invoice_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<i32>>(1)?,
+                    row.get::<_, Option<i32>>(2)?,
+                    row.get::<_, f64>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            },
+        )
+        .map_err(|e| format!("Invoice not found: {}", e))?;
+
+    let mut warnings = Vec::new();
+    if let (Some(source_customer), None) = (source_customer, customer_id) {
+        warnings.push(DuplicateInvoiceWarning {
+            product_id: None,
+            name: format!("Customer {}", source_customer),
+            issue: "customer_deleted".to_string(),
+            message: "The customer no longer exists; the draft is a walk-in sale".to_string(),
+        });
+    }
+    let price_tier = match customer_id {
+        Some(customer_id) if !keep_historical_prices => customer_price_tier(conn, customer_id)?,
+        _ => None,
+    };
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT ii.product_id, COALESCE(p.name, ii.product_name, 'Product ' || ii.product_id), p.id IS NOT NULL,
+                    ii.quantity, ii.unit_price, COALESCE(ii.discount_amount, 0)
+             FROM invoice_items ii
+             LEFT JOIN products p ON p.id = ii.product_id
+             WHERE ii.invoice_id = ?1
+             ORDER BY ii.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let lines = stmt
+        .query_map([source_invoice_id], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, bool>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, f64>(4)?,
+                row.get::<_, f64>(5)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load invoice items: {}", e))?;
+
+    let negative_stock = allow_negative_stock(conn)?;
+    // Stock already taken by earlier lines of the draft, per product
+    let mut drafted: Vec<(i32, f64)> = Vec::new();
+    let mut items = Vec::new();
+    for (product_id, name, exists, source_quantity, source_price, source_discount) in lines {
+        let warn = |issue: &str, message: String| DuplicateInvoiceWarning {
+            product_id: Some(product_id),
+            name: name.clone(),
+            issue: issue.to_string(),
+            message,
+        };
+        if !exists {
+            warnings.push(warn("product_deleted", "The product has been deleted".to_string()));
+            continue;
+        }
+
+        let mut quantity = source_quantity;
+        if !negative_stock {
+            let taken = drafted.iter().find(|(id, _)| *id == product_id).map_or(0.0, |(_, taken)| *taken);
+            let available = quantity::sub(reservations::available_quantity(conn, product_id)?, taken).max(0.0);
+            if available <= 0.0 {
+                warnings.push(warn("out_of_stock", "Out of stock".to_string()));
+                continue;
+            }
+            if quantity > available {
+                quantity = available;
+                warnings.push(warn(
+                    "quantity_reduced",
+                    format!(
+                        "Only {} of {} in stock",
+                        quantity::format_quantity(available),
+                        quantity::format_quantity(source_quantity)
+                    ),
+                ));
+            }
+        }
+        match drafted.iter_mut().find(|(id, _)| *id == product_id) {
+            Some((_, taken)) => *taken = quantity::sum([*taken, quantity]),
+            None => drafted.push((product_id, quantity)),
+        }
+
+        let (unit_price, discount_amount) = if keep_historical_prices {
+            let discount = money::round_money(source_discount * quantity / source_quantity);
+            (source_price, (discount > 0.0).then_some(discount))
+        } else {
+            let list = list_price(conn, product_id)?;
+            let price = match &price_tier {
+                Some((tier_id, _)) => tier_unit_price(conn, *tier_id, product_id, list)?.0,
+                None => list,
+            };
+            if money::to_paise(price) != money::to_paise(source_price) {
+                warnings.push(warn(
+                    "price_changed",
+                    format!("Price changed from {:.2} to {:.2}", source_price, price),
+                ));
+            }
+            (price, None)
+        };
+
+        items.push(CreateInvoiceItemInput {
+            product_id,
+            quantity,
+            unit_price,
+            discount_amount,
+            tax_rate: None,
+            reservation_id: None,
+        });
+    }
+
+    let draft = CreateInvoiceInput {
+        customer_id,
+        items,
+        tax_amount: None,
+        discount_amount: (keep_historical_prices && discount_amount > 0.0).then_some(discount_amount),
+        payment_method: None,
+        state,
+        district,
+        town,
+        initial_paid: None,
+        price_tier_id: price_tier.map(|(tier_id, _)| tier_id),
+        gst_rate: None,
+        allow_over_limit: false,
+        approved_by: None,
+        due_date: None,
+        payments: None,
+        price_override_reason: None,
+        price_override_approval: None,
+        created_by: None,
+    };
+
+    Ok(DuplicatedInvoice {
+        source_invoice_id,
+        source_invoice_number: invoice_number,
+        draft: apply_billing_defaults(draft, &billing_defaults(conn)?),
+        warnings,
+    })
+}
+
+/// The split payment recorded for an invoice, in the order it was entered
+pub(crate) fn invoice_tenders(conn: &Connection, invoice_id: i32) -> Result<Vec<InvoiceTender>, String> {
+    let mut stmt = conn
+        .prepare("SELECT method, amount FROM invoice_tenders WHERE invoice_id = ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([invoice_id], |row| Ok(InvoiceTender { method: row.get(0)?, amount: row.get(1)? }))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load invoice tenders: {}", e))
+}
+
+/// Check a split payment against the invoice total and work out the payment_method it gives:
+/// the one method when a single tender covers the total, Split for several, or Credit when the
+/// tenders fall short and the rest goes on the customer's account. Returns the method and the
+/// tenders with their amounts rounded.
+fn resolve_tenders(
+    tenders: &[InvoiceTender],
+    total_amount: f64,
+    customer_id: Option<i32>,
+) -> Result<(String, Vec<InvoiceTender>), String> {
+    if tenders.is_empty() {
+        return Err("Validation error: a split payment needs at least one payment".to_string());
+    }
+    let mut resolved = Vec::with_capacity(tenders.len());
+    for tender in tenders {
+        let method = tender.method.trim();
+        if method.is_empty() {
+            return Err("Validation error: each payment needs a method".to_string());
+        }
+        if method.eq_ignore_ascii_case("Credit") {
+            return Err("Validation error: credit is what the payments leave unpaid, not a payment".to_string());
+        }
+        if !tender.amount.is_finite() || tender.amount <= 0.0 {
+            return Err(format!("Validation error: the {} payment must be greater than 0", method));
+        }
+        resolved.push(InvoiceTender { method: method.to_string(), amount: money::round_money(tender.amount) });
+    }
+
+    let paid = money::sum(resolved.iter().map(|tender| tender.amount));
+    if money::exceeds(paid, total_amount) {
+        return Err(format!(
+            "Validation error: payments add up to {:.2}, more than the invoice total of {:.2}",
+            paid, total_amount
+        ));
+    }
+    let method = if money::exceeds(total_amount, paid) {
+        if customer_id.is_none() {
+            return Err(format!(
+                "Validation error: payments leave {:.2} unpaid, which needs a customer to put on credit",
+                money::sub(total_amount, paid)
+            ));
+        }
+        "Credit".to_string()
+    } else if resolved.len() == 1 {
+        resolved[0].method.clone()
+    } else {
+        SPLIT_PAYMENT_METHOD.to_string()
+    };
+    Ok((method, resolved))
 }
 
 /// Get aggregated sales summary for a specific product
@@ -383,43 +1017,44 @@ pub fn get_product_sales_summary(
     // Fetch individual item details to calculate correct weighted net amount
     let mut stmt = conn.prepare(
         "SELECT ii.quantity, ii.unit_price, ii.discount_amount,
-                i.total_amount, i.tax_amount, i.discount_amount, i.id
+                i.total_amount, i.tax_amount, i.discount_amount, i.id, COALESCE(i.round_off, 0)
          FROM invoice_items ii
          JOIN invoices i ON ii.invoice_id = i.id
          WHERE ii.product_id = ?1"
     ).map_err(|e| e.to_string())?;
 
     let sales_data = stmt.query_map([product_id], |row| {
-        let qty: i32 = row.get(0)?;
+        let qty: f64 = row.get(0)?;
         let unit_price: f64 = row.get(1)?;
         let item_discount: f64 = row.get::<_, Option<f64>>(2)?.unwrap_or(0.0);
         let invoice_total: f64 = row.get(3)?;
         let invoice_tax: f64 = row.get(4)?;
         let invoice_global_discount: f64 = row.get(5)?;
         let invoice_id: i32 = row.get(6)?;
+        let round_off: f64 = row.get(7)?;
 
-        let item_gross = qty as f64 * unit_price;
-        let invoice_subtotal = invoice_total - invoice_tax + invoice_global_discount;
+        let item_gross = money::line_total(unit_price, qty);
+        let invoice_subtotal = invoice_total - round_off - invoice_tax + invoice_global_discount;
 
         let weighted_global_discount = if invoice_subtotal > 0.0 && invoice_global_discount > 0.0 {
-            (item_gross / invoice_subtotal) * invoice_global_discount
+            money::round_money((item_gross / invoice_subtotal) * invoice_global_discount)
         } else {
             0.0
         };
 
-        let net_amount = item_gross - item_discount - weighted_global_discount;
+        let net_amount = money::sum([item_gross, -item_discount, -weighted_global_discount]);
 
         Ok((qty, net_amount, invoice_id))
     }).map_err(|e| e.to_string())?;
 
-    let mut total_qty = 0;
+    let mut total_qty = 0.0;
     let mut total_amount = 0.0;
     let mut invoice_ids = std::collections::HashSet::new();
 
     for result in sales_data {
         let (qty, amount, inv_id) = result.map_err(|e| e.to_string())?;
-        total_qty += qty;
-        total_amount += amount;
+        total_qty = quantity::sum([total_qty, qty]);
+        total_amount = money::sum([total_amount, amount]);
         invoice_ids.insert(inv_id);
     }
 
@@ -430,12 +1065,189 @@ pub fn get_product_sales_summary(
     })
 }
 
+/// GST for one line at `rate` percent, charged on the line value after its discount
+fn line_tax(item: &CreateInvoiceItemInput, rate: f64) -> f64 {
+    let taxable = money::sub(money::line_total(item.unit_price, item.quantity), item.discount_amount.unwrap_or(0.0));
+    money::percent_of(taxable.max(0.0), rate)
+}
+
+/// Split tax into (CGST, SGST, IGST). Sales to another state are IGST; sales within the
+/// home state, or where either state is unknown, are split evenly into CGST and SGST.
+fn split_gst(tax: f64, invoice_state: Option<&str>, home_state: Option<&str>) -> (f64, f64, f64) {
+    let inter_state = match (invoice_state.map(str::trim), home_state.map(str::trim)) {
+        (Some(state), Some(home)) if !state.is_empty() => !state.eq_ignore_ascii_case(home),
+        _ => false,
+    };
+    if inter_state {
+        (0.0, 0.0, tax)
+    } else {
+        let cgst = money::round_money(tax / 2.0);
+        (cgst, money::sub(tax, cgst), 0.0)
+    }
+}
+
+/// Fill the fields a client left out from the billing defaults. The default GST rate only
+/// applies when the input gives no rate and no tax amount at all.
+fn apply_billing_defaults(mut input: CreateInvoiceInput, defaults: &BillingDefaults) -> CreateInvoiceInput {
+    fn missing(value: &Option<String>) -> bool {
+        value.as_deref().map_or(true, |v| v.trim().is_empty())
+    }
+    if missing(&input.payment_method) {
+        input.payment_method = Some(defaults.payment_method.clone());
+    }
+    if missing(&input.state) {
+        input.state = defaults.state.clone();
+    }
+    if missing(&input.district) {
+        input.district = defaults.district.clone();
+    }
+    if missing(&input.town) {
+        input.town = defaults.town.clone();
+    }
+    let untaxed = input.gst_rate.is_none()
+        && input.tax_amount.is_none()
+        && input.items.iter().all(|item| item.tax_rate.is_none());
+    if untaxed {
+        input.gst_rate = defaults.gst_rate;
+    }
+    input
+}
+
+/// Tax, discount and total of a sale, worked out the same way when an invoice is created and
+/// when its items are edited
+struct SaleTotals {
+    /// (rate, tax) per line when the sale is taxed by rate
+    line_taxes: Option<Vec<(f64, f64)>>,
+    tax_amount: f64,
+    discount_amount: f64,
+    /// Recorded invoice rate: the invoice-level rate, or the one rate all items share
+    gst_rate: Option<f64>,
+    cgst_amount: f64,
+    sgst_amount: f64,
+    igst_amount: f64,
+    total_amount: f64,
+    round_off: f64,
+}
+
+fn sale_totals(
+    items: &[CreateInvoiceItemInput],
+    gst_rate: Option<f64>,
+    tax_amount: Option<f64>,
+    discount_amount: Option<f64>,
+    state: Option<&str>,
+    defaults: &BillingDefaults,
+) -> Result<SaleTotals, String> {
+    // Per-item tax: each line is taxed at its own rate, or the invoice-level rate.
+    // Without any rate the caller's tax_amount is used as before.
+    let line_taxes: Option<Vec<(f64, f64)>> = if gst_rate.is_some() || items.iter().any(|item| item.tax_rate.is_some()) {
+        let mut taxes = Vec::with_capacity(items.len());
+        for item in items {
+            let rate = item.tax_rate.or(gst_rate).unwrap_or(0.0);
+            if !(0.0..=100.0).contains(&rate) {
+                return Err(format!("Invalid tax rate {} for product {}", rate, item.product_id));
+            }
+            taxes.push((rate, line_tax(item, rate)));
+        }
+        Some(taxes)
+    } else {
+        None
+    };
+
+    // Calculate total amount (Final Payable)
+    let items_total = money::sum(items.iter().map(|item| money::line_total(item.unit_price, item.quantity)));
+    let tax_amount = match &line_taxes {
+        Some(taxes) => money::sum(taxes.iter().map(|(_, tax)| *tax)),
+        None => tax_amount.unwrap_or(0.0),
+    };
+    let discount_amount = discount_amount.unwrap_or(0.0);
+
+    let gst_rate = gst_rate.or_else(|| {
+        let taxes = line_taxes.as_ref()?;
+        let first = taxes.first()?.0;
+        taxes.iter().all(|(rate, _)| *rate == first).then_some(first)
+    });
+    let (cgst_amount, sgst_amount, igst_amount) = split_gst(tax_amount, state, defaults.home_state.as_deref());
+
+    // Final Amount = (Items Total + Tax) - Discount, rounded per the invoice_round_off setting
+    let (total_amount, round_off) = apply_round_off(money::sum([items_total, tax_amount, -discount_amount]), &defaults.round_off);
+
+    Ok(SaleTotals {
+        line_taxes,
+        tax_amount,
+        discount_amount,
+        gst_rate,
+        cgst_amount,
+        sgst_amount,
+        igst_amount,
+        total_amount,
+        round_off,
+    })
+}
+
+/// Round a total per `mode` ("nearest" rupee, "down" to the rupee, or "off" for exact totals),
+/// returning (rounded total, round off adjustment)
+fn apply_round_off(total: f64, mode: &str) -> (f64, f64) {
+    let exact = money::round_money(total);
+    let rounded = match mode {
+        "off" => return (exact, 0.0),
+        "down" => exact.floor(),
+        _ => exact.round(),
+    };
+    (rounded, money::sub(rounded, exact))
+}
+
+/// Whether adding `invoice_credit` to the customer's outstanding balance would go over their
+/// credit limit. Customers without a limit are never over it.
+pub(crate) fn check_credit_limit(
+    conn: &Connection,
+    customer_id: i32,
+    invoice_credit: f64,
+) -> Result<Option<CreditLimitExceeded>, String> {
+    let credit_limit: Option<f64> = conn
+        .query_row("SELECT credit_limit FROM customers WHERE id = ?1", [customer_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to get credit limit: {}", e))?;
+    let Some(credit_limit) = credit_limit else {
+        return Ok(None);
+    };
+
+    let current_outstanding = get_customer_credit_summary_internal(conn, customer_id)?.pending_amount;
+    let new_outstanding = money::sum([current_outstanding, invoice_credit]);
+    if !money::exceeds(new_outstanding, credit_limit) {
+        return Ok(None);
+    }
+
+    Ok(Some(CreditLimitExceeded {
+        customer_id,
+        credit_limit,
+        current_outstanding,
+        invoice_credit,
+        overflow: money::sub(new_outstanding, credit_limit),
+    }))
+}
+
 /// Create a new invoice with items and update stock
 #[tauri::command]
-pub fn create_invoice(input: CreateInvoiceInput, db: State<Database>) -> Result<Invoice, String> {
+pub fn create_invoice(input: CreateInvoiceInput, app: AppHandle, db: State<Database>) -> Result<Invoice, String> {
     log::info!("create_invoice called");
 
     let mut conn = db.get_conn()?;
+    let invoice = create_invoice_internal(&mut conn, input)?;
+    emit_data_changed(&app, DataEntity::Invoice, DataOperation::Created, invoice.id);
+    queue_invoice_created(&app, &conn, invoice.id);
+    stock_alerts::notify_stock_alerts(&app, &conn, invoice.id);
+    Ok(invoice)
+}
+
+/// Queue the invoice.created webhook with the full invoice (items and payments)
+pub(crate) fn queue_invoice_created(app: &AppHandle, conn: &Connection, invoice_id: i32) {
+    webhooks::queue_webhook_event(app, conn, webhooks::INVOICE_CREATED, Some(invoice_id), || {
+        serde_json::to_value(get_invoice_internal(conn, invoice_id)?).map_err(|e| e.to_string())
+    });
+}
+
+pub(crate) fn create_invoice_internal(conn: &mut Connection, input: CreateInvoiceInput) -> Result<Invoice, String> {
+    let defaults = billing_defaults(conn)?;
+    let mut input = apply_billing_defaults(input, &defaults);
 
     // Validate customer exists if provided
     if let Some(cid) = input.customer_id {
@@ -453,36 +1265,54 @@ pub fn create_invoice(input: CreateInvoiceInput, db: State<Database>) -> Result<
         }
     }
 
-    // Validate all products exist and have sufficient stock
-    for item in &input.items {
-        let product: Result<(i32, String), _> = conn.query_row(
-            "SELECT stock_quantity, name FROM products WHERE id = ?1",
-            [item.product_id],
-            |row| Ok((row.get(0)?, row.get(1)?)),
-        );
+    // Resolve the applied price tier; its name is snapshotted on the invoice
+    let price_tier: Option<(i32, String)> = match (input.price_tier_id, input.customer_id) {
+        (Some(tier_id), _) => Some(
+            conn.query_row(
+                "SELECT id, name FROM price_tiers WHERE id = ?1",
+                [tier_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|_| format!("Price tier with id {} not found", tier_id))?,
+        ),
+        (None, Some(cid)) => customer_price_tier(conn, cid)?,
+        (None, None) => None,
+    };
 
-        match product {
-            Ok((stock, name)) => {
-                if stock < item.quantity {
-                    return Err(format!(
-                        "Insufficient stock for product '{}'. Available: {}, Requested: {}",
-                        name, stock, item.quantity
-                    ));
-                }
-            }
-            Err(_) => {
-                return Err(format!("Product with id {} not found", item.product_id));
-            }
-        }
+    // Lines billed under their expected price need a reason (and may need a manager's approval)
+    let overridden = find_price_overrides(conn, &input.items, price_tier.as_ref().map(|(id, _)| *id))?;
+    let override_reason = input.price_override_reason.as_deref().map(str::trim).filter(|reason| !reason.is_empty());
+    if !overridden.is_empty() && override_reason.is_none() {
+        let details = serde_json::to_string(&overridden).map_err(|e| e.to_string())?;
+        return Err(format!("{}{}", PRICE_OVERRIDE_REASON_REQUIRED, details));
     }
 
-    // Calculate total amount (Final Payable)
-    let items_total: f64 = input.items.iter().map(|item| item.unit_price * item.quantity as f64).sum();
-    let tax_amount = input.tax_amount.unwrap_or(0.0);
-    let discount_amount = input.discount_amount.unwrap_or(0.0);
-    
-    // Final Amount = (Items Total + Tax) - Discount
-    let total_amount = items_total + tax_amount - discount_amount;
+    // Validate all products exist and have sufficient stock (bundles check their components).
+    // Reserved stock only counts for the lines that consume the reservation. With
+    // allow_negative_stock on, lines may sell past the stock and record what's backordered.
+    for item in &input.items {
+        products::check_product_quantity(conn, item.product_id, item.quantity)?;
+    }
+    let consumed_reservations = reservations::reservations_to_consume(conn, &input.items)?;
+    let requested: Vec<(i32, f64)> = input.items.iter().map(|item| (item.product_id, item.quantity)).collect();
+    let backordered = if allow_negative_stock(conn)? {
+        bundles::sale_shortfalls(conn, &requested, &consumed_reservations)?
+    } else {
+        bundles::validate_sale_stock(conn, &requested, &consumed_reservations)?;
+        vec![0.0; requested.len()]
+    };
+
+    let SaleTotals {
+        line_taxes,
+        tax_amount,
+        discount_amount,
+        gst_rate,
+        cgst_amount,
+        sgst_amount,
+        igst_amount,
+        total_amount,
+        round_off,
+    } = sale_totals(&input.items, input.gst_rate, input.tax_amount, input.discount_amount, input.state.as_deref(), &defaults)?;
 
     // Generate invoice number - get the highest number and increment
     let next_number: i32 = conn
@@ -494,47 +1324,155 @@ pub fn create_invoice(input: CreateInvoiceInput, db: State<Database>) -> Result<
         .unwrap_or(1);
     let invoice_number = format!("INV-{:06}", next_number);
 
-    // Start transaction
-    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    // A split payment sets the payment method, and what it leaves unpaid goes on credit
+    let tenders = match &input.payments {
+        Some(payments) => {
+            if input.initial_paid.is_some() {
+                return Err("Validation error: give either payments or initial_paid, not both".to_string());
+            }
+            let (method, tenders) = resolve_tenders(payments, total_amount, input.customer_id)?;
+            input.payment_method = Some(method);
+            input.initial_paid = Some(money::sum(tenders.iter().map(|tender| tender.amount)));
+            tenders
+        }
+        None => Vec::new(),
+    };
 
     // Handle credit payment calculations
     let is_credit = input.payment_method.as_deref() == Some("Credit");
     let initial_paid = if is_credit {
-        input.initial_paid.unwrap_or(0.0)
+        money::round_money(input.initial_paid.unwrap_or(0.0))
     } else {
         total_amount // Non-credit payments are fully paid
     };
     let credit_amount = if is_credit {
-        (total_amount - initial_paid).max(0.0)
+        money::sub(total_amount, initial_paid).max(0.0)
     } else {
         0.0
     };
 
+    // Credit is due on the requested date, or one credit period from today
+    let due_date = match input.due_date.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(date) => Some(
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("Validation error: invalid due date '{}', expected YYYY-MM-DD", date))?
+                .format("%Y-%m-%d")
+                .to_string(),
+        ),
+        None if credit_amount > 0.0 => {
+            let due = business_today(conn) + Duration::days(defaults.credit_period_days);
+            Some(due.format("%Y-%m-%d").to_string())
+        }
+        None => None,
+    };
+
+    let over_limit = match input.customer_id {
+        Some(cid) if credit_amount > 0.0 => check_credit_limit(conn, cid, credit_amount)?,
+        _ => None,
+    };
+    let limit_override = match (&over_limit, input.approved_by.as_deref().map(str::trim)) {
+        (None, _) => None,
+        (Some(exceeded), Some(approver)) if input.allow_over_limit && !approver.is_empty() => {
+            Some((exceeded, approver.to_string()))
+        }
+        (Some(exceeded), _) => {
+            let details = serde_json::to_string(exceeded).map_err(|e| e.to_string())?;
+            return Err(format!("{}{}", CREDIT_LIMIT_EXCEEDED, details));
+        }
+    };
+
+    let needs_override_approval = !overridden.is_empty()
+        && (action_requires_biometric(conn, "price_override") || input.price_override_approval.is_some());
+
+    // Start transaction
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
     // Create invoice
     let now = Utc::now().to_rfc3339();
     tx.execute(
-        "INSERT INTO invoices (invoice_number, customer_id, total_amount, tax_amount, discount_amount, payment_method, created_at, state, district, town, initial_paid, credit_amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
-        (&invoice_number, input.customer_id, total_amount, tax_amount, discount_amount, &input.payment_method, &now, &input.state, &input.district, &input.town, initial_paid, credit_amount),
+        "INSERT INTO invoices (invoice_number, customer_id, total_amount, tax_amount, discount_amount, payment_method, created_at, state, district, town, initial_paid, credit_amount, price_tier_id, price_tier_name, gst_rate, cgst_amount, sgst_amount, igst_amount, round_off, due_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)",
+        rusqlite::params![
+            &invoice_number,
+            input.customer_id,
+            total_amount,
+            tax_amount,
+            discount_amount,
+            &input.payment_method,
+            &now,
+            &input.state,
+            &input.district,
+            &input.town,
+            initial_paid,
+            credit_amount,
+            price_tier.as_ref().map(|(id, _)| *id),
+            price_tier.as_ref().map(|(_, name)| name.as_str()),
+            gst_rate,
+            cgst_amount,
+            sgst_amount,
+            igst_amount,
+            round_off,
+            &due_date,
+        ],
     )
     .map_err(|e| format!("Failed to create invoice: {}", e))?;
 
     let invoice_id = tx.last_insert_rowid() as i32;
 
-    // If credit payment with initial amount, create initial payment record
+    // Record who let this sale through over the customer's credit limit
+    if let Some((exceeded, approver)) = &limit_override {
+        let details = serde_json::to_string(&[
+            serde_json::json!({"field": "credit_limit", "old": null, "new": exceeded.credit_limit}),
+            serde_json::json!({"field": "current_outstanding", "old": null, "new": exceeded.current_outstanding}),
+            serde_json::json!({"field": "invoice_credit", "old": null, "new": exceeded.invoice_credit}),
+            serde_json::json!({"field": "overflow", "old": null, "new": exceeded.overflow}),
+        ])
+        .unwrap_or_default();
+        tx.execute(
+            "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            ("invoice", invoice_id, &invoice_number, "credit_limit_override", &details, approver),
+        )
+        .map_err(|e| format!("Failed to log credit limit override: {}", e))?;
+        log::info!("Invoice {} exceeds credit limit by {}, approved by {}", invoice_number, exceeded.overflow, approver);
+    }
+
+    for tender in &tenders {
+        tx.execute(
+            "INSERT INTO invoice_tenders (invoice_id, method, amount) VALUES (?1, ?2, ?3)",
+            (invoice_id, &tender.method, tender.amount),
+        )
+        .map_err(|e| format!("Failed to record invoice payment: {}", e))?;
+    }
+
+    // If credit payment with initial amount, create initial payment record (one per method of a
+    // split payment, so collections by method add up)
     if is_credit && initial_paid > 0.0 {
         if let Some(customer_id) = input.customer_id {
-            tx.execute(
-                "INSERT INTO customer_payments (customer_id, invoice_id, amount, payment_method, note, paid_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
-                (customer_id, invoice_id, initial_paid, "Cash", "Initial payment at invoice creation", &now),
-            )
-            .map_err(|e| format!("Failed to create initial payment record: {}", e))?;
+            let initial_payments = if tenders.is_empty() {
+                vec![InvoiceTender { method: "Cash".to_string(), amount: initial_paid }]
+            } else {
+                tenders.clone()
+            };
+            for payment in &initial_payments {
+                tx.execute(
+                    "INSERT INTO customer_payments (customer_id, invoice_id, amount, payment_method, note, paid_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
+                    (customer_id, invoice_id, payment.amount, &payment.method, INITIAL_PAYMENT_NOTE, &now),
+                )
+                .map_err(|e| format!("Failed to create initial payment record: {}", e))?;
+            }
         }
     }
 
     // Create invoice items, update stock, and record FIFO sales
     let sale_date = Utc::now().format("%Y-%m-%d").to_string();
 
-    for item in &input.items {
+    // The approval is used up inside the transaction, so a sale that fails rolls it back unused
+    let override_approver = if needs_override_approval {
+        Some(consume_action_approval(&tx, "price_override", input.price_override_approval.as_deref())?)
+    } else {
+        None
+    };
+
+    for (index, item) in input.items.iter().enumerate() {
         // Get product name for historical record
         let product_name: String = tx.query_row(
             "SELECT name FROM products WHERE id = ?1",
@@ -542,31 +1480,45 @@ pub fn create_invoice(input: CreateInvoiceInput, db: State<Database>) -> Result<
             |row| row.get(0),
         ).map_err(|e| format!("Failed to get product name: {}", e))?;
 
-        // Insert invoice item with per-item discount
+        // Insert invoice item with per-item discount and tax
         let item_discount = item.discount_amount.unwrap_or(0.0);
+        let (item_tax_rate, item_tax) = match &line_taxes {
+            Some(taxes) => (Some(taxes[index].0), Some(taxes[index].1)),
+            None => (None, None),
+        };
         tx.execute(
-            "INSERT INTO invoice_items (invoice_id, product_id, quantity, unit_price, product_name, discount_amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            (invoice_id, item.product_id, item.quantity, item.unit_price, product_name, item_discount),
+            "INSERT INTO invoice_items (invoice_id, product_id, quantity, unit_price, product_name, discount_amount, tax_rate, tax_amount, backordered_quantity) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            (invoice_id, item.product_id, item.quantity, item.unit_price, &product_name, item_discount, item_tax_rate, item_tax, backordered[index]),
         )
         .map_err(|e| format!("Failed to create invoice item: {}", e))?;
+        let invoice_item_id = tx.last_insert_rowid() as i32;
 
-        // Update product stock
-        tx.execute(
-            "UPDATE products SET stock_quantity = stock_quantity - ?1 WHERE id = ?2",
-            (item.quantity, item.product_id),
-        )
-        .map_err(|e| format!("Failed to update product stock: {}", e))?;
+        if let Some(line) = overridden.iter().find(|line| line.line == index) {
+            tx.execute(
+                "INSERT INTO price_overrides (invoice_id, invoice_number, invoice_item_id, product_id, product_name, quantity, list_price, charged_price, reason, cashier, approved_by)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                rusqlite::params![
+                    invoice_id,
+                    &invoice_number,
+                    invoice_item_id,
+                    item.product_id,
+                    &product_name,
+                    item.quantity,
+                    line.list_price,
+                    item.unit_price,
+                    override_reason,
+                    &input.created_by,
+                    &override_approver,
+                ],
+            )
+            .map_err(|e| format!("Failed to log price override: {}", e))?;
+        }
 
-        // Record FIFO sale (updates batches and creates transaction)
+        // Update product stock and record the FIFO sale (bundles deduct their components)
         // This will calculate COGS automatically using FIFO
-        inventory_service::record_sale_fifo(
-            &tx,
-            item.product_id,
-            item.quantity,
-            &sale_date,
-            invoice_id,
-        ).map_err(|e| format!("Failed to record FIFO sale: {}", e))?;
+        bundles::record_item_sale(&tx, invoice_item_id, item.product_id, item.quantity, &sale_date, invoice_id)?;
     }
+    reservations::consume_reservations(&tx, &consumed_reservations, invoice_id)?;
 
     // Commit transaction
     tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
@@ -580,11 +1532,13 @@ pub fn create_invoice(input: CreateInvoiceInput, db: State<Database>) -> Result<
         discount_amount,
         payment_method: input.payment_method.clone(),
         created_at: now,
-        cgst_amount: None,
+        cgst_amount: Some(cgst_amount),
         fy_year: None,
-        gst_rate: None,
-        igst_amount: None,
-        sgst_amount: None,
+        gst_rate,
+        igst_amount: Some(igst_amount),
+        sgst_amount: Some(sgst_amount),
+        round_off,
+        due_date: due_date.clone(),
         state: input.state.clone(),
         district: input.district.clone(),
         town: input.town.clone(),
@@ -601,69 +1555,210 @@ pub fn create_invoice(input: CreateInvoiceInput, db: State<Database>) -> Result<
 
 
 
-/// Update an invoice (Metadata only)
+/// Update an invoice's customer, payment method or date (metadata only). Each changed field is
+/// logged with its old and new value, as update_invoice_items does for items.
 #[tauri::command]
-pub fn update_invoice(input: UpdateInvoiceInput, db: State<Database>) -> Result<Invoice, String> {
-    log::info!("update_invoice called with id: {}", input.id);
+pub fn update_invoice(input: UpdateInvoiceInput, app: AppHandle, db: State<Database>) -> Result<Invoice, String> {
+    log::info!("update_invoice called with id: {}, modified_by: {:?}", input.id, input.modified_by);
 
     let mut conn = db.get_conn()?;
+    update_invoice_internal(&mut conn, &input)?;
 
-    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let invoice = get_invoice(input.id, db)?.invoice;
+    emit_data_changed(&app, DataEntity::Invoice, DataOperation::Updated, invoice.id);
+    Ok(invoice)
+}
 
-    // Prepare update query dynamically based on inputs
-    let mut updates = Vec::new();
-    let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+pub(crate) fn update_invoice_internal(conn: &mut Connection, input: &UpdateInvoiceInput) -> Result<(), String> {
+    if input.customer_id.is_none() && input.payment_method.is_none() && input.created_at.is_none() {
+        return Err("No fields to update".to_string());
+    }
 
-    if let Some(cid) = input.customer_id {
-        updates.push("customer_id = ?");
-        params.push(Box::new(cid));
+    let (invoice_number, customer_id, payment_method, created_at, due_date, credit_amount, initial_paid, payments_sum) = conn
+        .query_row(
+            "SELECT invoice_number, customer_id, payment_method, created_at, due_date,
+                    COALESCE(credit_amount, 0), COALESCE(initial_paid, 0),
+                    COALESCE((SELECT SUM(amount) FROM customer_payments WHERE invoice_id = invoices.id), 0)
+             FROM invoices WHERE id = ?1",
+            [input.id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<i32>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, f64>(5)?,
+                    row.get::<_, f64>(6)?,
+                    row.get::<_, f64>(7)?,
+                ))
+            },
+        )
+        .map_err(|_| format!("Invoice with id {} not found", input.id))?;
+
+    let new_customer_id = input.customer_id.or(customer_id);
+    let new_payment_method = input.payment_method.clone().or_else(|| payment_method.clone());
+    let new_created_at = match input.created_at.as_deref().map(str::trim) {
+        Some(value) => DateTime::parse_from_rfc3339(value)
+            .map_err(|_| format!("Validation error: invalid invoice date '{}', expected an RFC 3339 timestamp", value))?
+            .with_timezone(&Utc)
+            .to_rfc3339(),
+        None => created_at.clone(),
+    };
+
+    // Moving between Credit and other methods only works when the recorded payments agree with
+    // where the invoice ends up: nothing still owed when leaving Credit, and the amount paid up
+    // front on record (as create_invoice records it) when entering it
+    let was_credit = payment_method.as_deref() == Some("Credit");
+    let is_credit = new_payment_method.as_deref() == Some("Credit");
+    if new_payment_method != payment_method && !invoice_tenders(conn, input.id)?.is_empty() {
+        return Err(format!(
+            "Invoice {} was paid with a split payment, so its payment method can't be changed",
+            invoice_number
+        ));
+    }
+    if is_credit && new_customer_id.is_none() {
+        return Err("Validation error: a credit invoice needs a customer".to_string());
     }
-    if let Some(pm) = input.payment_method {
-        updates.push("payment_method = ?");
-        params.push(Box::new(pm));
+    if was_credit && !is_credit {
+        let outstanding = money::sub(credit_amount, money::sub(payments_sum, initial_paid)).max(0.0);
+        if outstanding > 0.0 {
+            return Err(format!(
+                "Invoice {} still has Rs.{:.2} of credit outstanding; record the payment before changing its payment method from Credit",
+                invoice_number, outstanding
+            ));
+        }
     }
-    if let Some(created_at) = input.created_at {
-        updates.push("created_at = ?");
-        params.push(Box::new(created_at));
+    if is_credit && !was_credit && money::exceeds(initial_paid, payments_sum) {
+        return Err(format!(
+            "Invoice {} was recorded as paid in full without payment records, so it can't be moved to Credit; \
+             delete it and bill it again on credit",
+            invoice_number
+        ));
     }
 
-    if updates.is_empty() {
-        return Err("No fields to update".to_string());
+    // Credit stays due the same number of days after the sale when the sale date moves
+    let mut new_due_date = due_date.clone();
+    if new_created_at != created_at {
+        let offset = Duration::minutes(business_offset_minutes(conn) as i64);
+        let local_day = |timestamp: &str| {
+            DateTime::parse_from_rfc3339(timestamp)
+                .ok()
+                .map(|parsed| (parsed.with_timezone(&Utc).naive_utc() + offset).date())
+        };
+        let due = due_date.as_deref().and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok());
+        if let (Some(due), Some(old_day), Some(new_day)) = (due, local_day(&created_at), local_day(&new_created_at)) {
+            new_due_date = Some((due + (new_day - old_day)).format("%Y-%m-%d").to_string());
+        }
     }
 
-    // Add ID to params
-    params.push(Box::new(input.id));
+    let mut field_changes: Vec<serde_json::Value> = Vec::new();
+    if new_customer_id != customer_id {
+        field_changes.push(serde_json::json!({"field": "customer_id", "old": customer_id, "new": new_customer_id}));
+    }
+    if new_payment_method != payment_method {
+        field_changes.push(serde_json::json!({"field": "payment_method", "old": payment_method, "new": new_payment_method}));
+    }
+    if new_created_at != created_at {
+        field_changes.push(serde_json::json!({"field": "created_at", "old": created_at, "new": new_created_at}));
+    }
+    if new_due_date != due_date {
+        field_changes.push(serde_json::json!({"field": "due_date", "old": due_date, "new": new_due_date}));
+    }
+    if field_changes.is_empty() {
+        log::info!("Invoice {} unchanged", input.id);
+        return Ok(());
+    }
 
-    let query = format!("UPDATE invoices SET {} WHERE id = ?", updates.join(", "));
-    
-    // Rusqlite params
-    let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
 
-    let rows_affected = tx.execute(&query, rusqlite::params_from_iter(param_refs.iter()))
-        .map_err(|e| format!("Failed to update invoice: {}", e))?;
+    // Neither the day the invoice leaves nor the day it moves to may be closed
+    let override_by = input.closed_day_override.as_deref();
+    day_closures::ensure_day_open(&tx, input.id, &created_at, override_by, "updated")?;
+    if new_created_at != created_at {
+        day_closures::ensure_day_open(&tx, input.id, &new_created_at, override_by, "moved into")?;
+    }
 
-    if rows_affected == 0 {
-        return Err(format!("Invoice with id {} not found", input.id));
+    tx.execute(
+        "UPDATE invoices SET customer_id = ?1, payment_method = ?2, created_at = ?3, due_date = ?4 WHERE id = ?5",
+        (new_customer_id, &new_payment_method, &new_created_at, &new_due_date, input.id),
+    )
+    .map_err(|e| format!("Failed to update invoice: {}", e))?;
+
+    // Payments follow the invoice to its new customer so credit summaries stay in step
+    if new_customer_id != customer_id {
+        tx.execute(
+            "UPDATE customer_payments SET customer_id = ?1 WHERE invoice_id = ?2",
+            (new_customer_id, input.id),
+        )
+        .map_err(|e| format!("Failed to move invoice payments: {}", e))?;
     }
 
-    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    let original_data = serde_json::json!({
+        "customer_id": customer_id,
+        "payment_method": payment_method,
+        "created_at": created_at,
+        "due_date": due_date,
+    });
+    let new_data = serde_json::json!({
+        "customer_id": new_customer_id,
+        "payment_method": new_payment_method,
+        "created_at": new_created_at,
+        "due_date": new_due_date,
+    });
+    tx.execute(
+        "INSERT INTO invoice_modifications (invoice_id, action, modified_by, original_data, new_data) VALUES (?1, ?2, ?3, ?4, ?5)",
+        (input.id, "metadata_modified", &input.modified_by, original_data.to_string(), new_data.to_string()),
+    )
+    .map_err(|e| format!("Failed to record modification: {}", e))?;
 
-    // Fetch and return updated invoice (skipping extended details for simplicity, or reusing existing query)
-    let invoice = get_invoice(input.id, db)?.invoice;
-    Ok(invoice)
+    let changes_json = serde_json::to_string(&field_changes).unwrap_or_default();
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        ("invoice", input.id, &invoice_number, "metadata_modified", &changes_json, &input.modified_by),
+    )
+    .map_err(|e| format!("Failed to log entity modification: {}", e))?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    log::info!("Updated invoice {} metadata ({} fields)", input.id, field_changes.len());
+    Ok(())
 }
 
 /// Delete an invoice and restore inventory
 #[tauri::command]
-pub fn delete_invoice(id: i32, deleted_by: Option<String>, db: State<Database>) -> Result<(), String> {
+pub fn delete_invoice(
+    id: i32,
+    deleted_by: Option<String>,
+    approval_token: Option<String>,
+    closed_day_override: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<(), String> {
     log::info!("delete_invoice called with id: {}, deleted_by: {:?}", id, deleted_by);
 
     let mut conn = db.get_conn()?;
+    require_biometric_approval(&conn, "delete_invoice", approval_token.as_deref())?;
+    let invoice_number: Option<String> = conn
+        .query_row("SELECT invoice_number FROM invoices WHERE id = ?1", [id], |row| row.get(0))
+        .ok();
+    delete_invoice_internal(&mut conn, id, deleted_by.clone(), closed_day_override.as_deref())?;
+    emit_data_changed(&app, DataEntity::Invoice, DataOperation::Deleted, id);
+    webhooks::queue_webhook_event(&app, &conn, webhooks::INVOICE_DELETED, Some(id), || {
+        Ok(serde_json::json!({ "id": id, "invoice_number": invoice_number, "deleted_by": deleted_by }))
+    });
+    Ok(())
+}
 
+pub(crate) fn delete_invoice_internal(
+    conn: &mut Connection,
+    id: i32,
+    deleted_by: Option<String>,
+    closed_day_override: Option<&str>,
+) -> Result<(), String> {
     // Get invoice data before deletion for audit trail
     // We fetch a simple Invoice struct
     let invoice = conn.query_row(
-        "SELECT id, invoice_number, customer_id, total_amount, tax_amount, discount_amount, payment_method, created_at, cgst_amount, fy_year, gst_rate, igst_amount, sgst_amount, state, district, town FROM invoices WHERE id = ?1",
+        "SELECT id, invoice_number, customer_id, total_amount, tax_amount, discount_amount, payment_method, created_at, cgst_amount, fy_year, gst_rate, igst_amount, sgst_amount, state, district, town, COALESCE(round_off, 0), due_date FROM invoices WHERE id = ?1",
         [id],
         |row| {
             Ok(Invoice {
@@ -680,6 +1775,8 @@ pub fn delete_invoice(id: i32, deleted_by: Option<String>, db: State<Database>)
                 gst_rate: row.get(10)?,
                 igst_amount: row.get(11)?,
                 sgst_amount: row.get(12)?,
+                round_off: row.get(16)?,
+                due_date: row.get(17)?,
                 state: row.get(13)?,
                 district: row.get(14)?,
                 town: row.get(15)?,
@@ -694,11 +1791,12 @@ pub fn delete_invoice(id: i32, deleted_by: Option<String>, db: State<Database>)
     .map_err(|e| format!("Invoice with id {} not found: {}", id, e))?;
 
     let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    day_closures::ensure_day_open(&tx, id, &invoice.created_at, closed_day_override, "deleted")?;
 
     // 1. Get invoice items (full details for archive + restocking)
     let items_details: Vec<InvoiceItemWithProduct> = {
         let mut stmt = tx.prepare(
-            "SELECT ii.id, ii.invoice_id, ii.product_id, p.name, p.sku, ii.quantity, ii.unit_price, COALESCE(ii.discount_amount, 0)
+            "SELECT ii.id, ii.invoice_id, ii.product_id, p.name, p.sku, ii.quantity, ii.unit_price, COALESCE(ii.discount_amount, 0), ii.tax_rate, ii.tax_amount, ii.backordered_quantity
              FROM invoice_items ii
              JOIN products p ON ii.product_id = p.id
              WHERE ii.invoice_id = ?1"
@@ -714,6 +1812,9 @@ pub fn delete_invoice(id: i32, deleted_by: Option<String>, db: State<Database>)
                 quantity: row.get(5)?,
                 unit_price: row.get(6)?,
                 discount_amount: row.get(7)?,
+                tax_rate: row.get(8)?,
+                tax_amount: row.get(9)?,
+                backordered_quantity: row.get(10)?,
             })
         }).map_err(|e| e.to_string())?;
 
@@ -735,12 +1836,17 @@ pub fn delete_invoice(id: i32, deleted_by: Option<String>, db: State<Database>)
 
     // 3. Restore stock for each item using FIFO reversal
     for item in &items_details {
-        inventory_service::restore_stock_from_invoice(&tx, item.product_id, item.quantity, id)?;
+        for (product_id, quantity) in bundles::consumed_stock(&tx, item.id, item.product_id, item.quantity)? {
+            inventory_service::restore_stock_from_invoice(&tx, product_id, quantity, id, item.backordered_quantity > 0.0)?;
+        }
     }
 
-    // 4. Delete invoice items
+    // 4. Delete invoice items and split payment; like credit payments the split isn't archived,
+    // so a restored invoice reports its payment_method alone
     tx.execute("DELETE FROM invoice_items WHERE invoice_id = ?", [id])
         .map_err(|e| format!("Failed to delete invoice items: {}", e))?;
+    tx.execute("DELETE FROM invoice_tenders WHERE invoice_id = ?", [id])
+        .map_err(|e| format!("Failed to delete invoice payments: {}", e))?;
 
     // 5. Delete invoice
     let rows_affected = tx.execute("DELETE FROM invoices WHERE id = ?", [id])
@@ -755,24 +1861,74 @@ pub fn delete_invoice(id: i32, deleted_by: Option<String>, db: State<Database>)
     Ok(())
 }
 
-/// Update invoice items (add/remove items with stock adjustments)
+/// The invoice fields update_invoice_items recomputes, as they were before the edit
+struct CurrentFinancials {
+    invoice_number: String,
+    total_amount: f64,
+    created_at: String,
+    tax_amount: f64,
+    discount_amount: f64,
+    gst_rate: Option<f64>,
+    state: Option<String>,
+    payment_method: Option<String>,
+    initial_paid: f64,
+    credit_amount: f64,
+    /// Everything recorded in customer_payments, the up-front payment included
+    payments_sum: f64,
+}
+
+/// Update invoice items (add/remove items with stock adjustments). The total is worked out as
+/// in create_invoice, keeping the invoice's tax and discount unless new ones are given.
 #[tauri::command]
-pub fn update_invoice_items(input: UpdateInvoiceItemsInput, db: State<Database>) -> Result<Invoice, String> {
+pub fn update_invoice_items(input: UpdateInvoiceItemsInput, app: AppHandle, db: State<Database>) -> Result<Invoice, String> {
     log::info!("update_invoice_items called for invoice_id: {}", input.invoice_id);
 
     let mut conn = db.get_conn()?;
+    update_invoice_items_internal(&mut conn, &input)?;
+
+    // Return updated invoice
+    let invoice = get_invoice(input.invoice_id, db)?.invoice;
+    log::info!("Updated invoice {} items", input.invoice_id);
+    emit_data_changed(&app, DataEntity::Invoice, DataOperation::Updated, invoice.id);
+    Ok(invoice)
+}
+
+pub(crate) fn update_invoice_items_internal(conn: &mut Connection, input: &UpdateInvoiceItemsInput) -> Result<(), String> {
+    if input.tax_amount.is_some_and(|tax| tax < 0.0) {
+        return Err("Validation error: tax amount cannot be negative".to_string());
+    }
+    if input.discount_amount.is_some_and(|discount| discount < 0.0) {
+        return Err("Validation error: discount amount cannot be negative".to_string());
+    }
 
     // Get current invoice and items for history
     let current_invoice = conn.query_row(
-        "SELECT id, invoice_number, total_amount FROM invoices WHERE id = ?1",
+        "SELECT invoice_number, total_amount, created_at, COALESCE(tax_amount, 0), COALESCE(discount_amount, 0),
+                gst_rate, state, payment_method, COALESCE(initial_paid, 0), COALESCE(credit_amount, 0),
+                COALESCE((SELECT SUM(amount) FROM customer_payments WHERE invoice_id = invoices.id), 0)
+         FROM invoices WHERE id = ?1",
         [input.invoice_id],
-        |row| Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?, row.get::<_, f64>(2)?)),
+        |row| {
+            Ok(CurrentFinancials {
+                invoice_number: row.get(0)?,
+                total_amount: row.get(1)?,
+                created_at: row.get(2)?,
+                tax_amount: row.get(3)?,
+                discount_amount: row.get(4)?,
+                gst_rate: row.get(5)?,
+                state: row.get(6)?,
+                payment_method: row.get(7)?,
+                initial_paid: row.get(8)?,
+                credit_amount: row.get(9)?,
+                payments_sum: row.get(10)?,
+            })
+        },
     ).map_err(|e| format!("Invoice not found: {}", e))?;
 
     // Get current items
     let current_items: Vec<InvoiceItemWithProduct> = {
         let mut stmt = conn.prepare(
-            "SELECT ii.id, ii.invoice_id, ii.product_id, p.name, p.sku, ii.quantity, ii.unit_price, COALESCE(ii.discount_amount, 0)
+            "SELECT ii.id, ii.invoice_id, ii.product_id, p.name, p.sku, ii.quantity, ii.unit_price, COALESCE(ii.discount_amount, 0), ii.tax_rate, ii.tax_amount, ii.backordered_quantity
              FROM invoice_items ii
              JOIN products p ON ii.product_id = p.id
              WHERE ii.invoice_id = ?1"
@@ -788,23 +1944,65 @@ pub fn update_invoice_items(input: UpdateInvoiceItemsInput, db: State<Database>)
                 quantity: row.get(5)?,
                 unit_price: row.get(6)?,
                 discount_amount: row.get(7)?,
+                tax_rate: row.get(8)?,
+                tax_amount: row.get(9)?,
+                backordered_quantity: row.get(10)?,
             })
         }).map_err(|e| e.to_string())?;
 
         rows.collect::<Result<Vec<_>, _>>().map_err(|e| e.to_string())?
     };
 
+    // New totals as create_invoice works them out. A tax amount given here replaces the
+    // invoice-level rate; lines with their own rate are still taxed at it.
+    let defaults = billing_defaults(conn)?;
+    let gst_rate = if input.tax_amount.is_some() { None } else { current_invoice.gst_rate };
+    let totals = sale_totals(
+        &input.items,
+        gst_rate,
+        input.tax_amount.or(Some(current_invoice.tax_amount)),
+        input.discount_amount.or(Some(current_invoice.discount_amount)),
+        current_invoice.state.as_deref(),
+        &defaults,
+    )?;
+    if money::exceeds(totals.discount_amount, money::sum([totals.total_amount, totals.discount_amount])) {
+        return Err("Validation error: discount amount is more than the invoice value".to_string());
+    }
+
+    // What was paid carries forward: a credit invoice keeps its up-front payment and owes the
+    // rest, any other invoice stays paid in full. Payments already taken can't exceed the total.
+    let is_credit = current_invoice.payment_method.as_deref() == Some("Credit");
+    let total_changed = money::to_paise(totals.total_amount) != money::to_paise(current_invoice.total_amount);
+    if total_changed && !invoice_tenders(conn, input.invoice_id)?.is_empty() {
+        return Err(format!(
+            "Invoice {} was paid with a split payment, so its total can't be changed",
+            current_invoice.invoice_number
+        ));
+    }
+    if money::exceeds(current_invoice.payments_sum, totals.total_amount) {
+        return Err(format!(
+            "Validation error: Rs.{:.2} has already been paid on invoice {}, more than its new total of Rs.{:.2}",
+            current_invoice.payments_sum, current_invoice.invoice_number, totals.total_amount
+        ));
+    }
+    let (initial_paid, credit_amount) = if is_credit {
+        let initial_paid = current_invoice.initial_paid.min(totals.total_amount);
+        (initial_paid, money::sub(totals.total_amount, initial_paid).max(0.0))
+    } else {
+        (totals.total_amount, 0.0)
+    };
+
     // Serialize for history
     let original_data = serde_json::to_string(&current_items).unwrap_or_default();
 
     let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    day_closures::ensure_day_open(&tx, input.invoice_id, &current_invoice.created_at, input.closed_day_override.as_deref(), "items updated")?;
 
-    // 1. Restore stock for all existing items
+    // 1. Restore stock for all existing items, back into the batches they were sold from
     for item in &current_items {
-        tx.execute(
-            "UPDATE products SET stock_quantity = stock_quantity + ?1 WHERE id = ?2",
-            (item.quantity, item.product_id),
-        ).map_err(|e| format!("Failed to restore stock: {}", e))?;
+        for (product_id, quantity) in bundles::consumed_stock(&tx, item.id, item.product_id, item.quantity)? {
+            inventory_service::restore_stock_from_invoice(&tx, product_id, quantity, input.invoice_id, item.backordered_quantity > 0.0)?;
+        }
     }
 
     // 2. Delete all existing invoice items
@@ -812,10 +2010,22 @@ pub fn update_invoice_items(input: UpdateInvoiceItemsInput, db: State<Database>)
         .map_err(|e| format!("Failed to delete items: {}", e))?;
 
     // 3. Add new items and deduct stock
-    let mut new_total: f64 = 0.0;
     let sale_date = Utc::now().format("%Y-%m-%d").to_string();
 
+    // Check stock (bundles check their components, reserved stock is held back)
     for item in &input.items {
+        products::check_product_quantity(&tx, item.product_id, item.quantity)?;
+    }
+    let consumed_reservations = reservations::reservations_to_consume(&tx, &input.items)?;
+    let requested: Vec<(i32, f64)> = input.items.iter().map(|item| (item.product_id, item.quantity)).collect();
+    let backordered = if allow_negative_stock(&tx)? {
+        bundles::sale_shortfalls(&tx, &requested, &consumed_reservations)?
+    } else {
+        bundles::validate_sale_stock(&tx, &requested, &consumed_reservations)?;
+        vec![0.0; requested.len()]
+    };
+
+    for (index, item) in input.items.iter().enumerate() {
         // Get product name
         let product_name: String = tx.query_row(
             "SELECT name FROM products WHERE id = ?1",
@@ -823,41 +2033,41 @@ pub fn update_invoice_items(input: UpdateInvoiceItemsInput, db: State<Database>)
             |row| row.get(0),
         ).map_err(|e| format!("Product not found: {}", e))?;
 
-        // Check stock
-        let stock: i32 = tx.query_row(
-            "SELECT stock_quantity FROM products WHERE id = ?1",
-            [item.product_id],
-            |row| row.get(0),
-        ).map_err(|e| format!("Failed to get stock: {}", e))?;
-
-        if stock < item.quantity {
-            return Err(format!("Insufficient stock for product '{}'. Available: {}, Requested: {}", product_name, stock, item.quantity));
-        }
-
-        // Insert new item with per-item discount
+        // Insert new item with per-item discount and tax
         let item_discount = item.discount_amount.unwrap_or(0.0);
+        let (item_tax_rate, item_tax) = match &totals.line_taxes {
+            Some(taxes) => (Some(taxes[index].0), Some(taxes[index].1)),
+            None => (None, None),
+        };
         tx.execute(
-            "INSERT INTO invoice_items (invoice_id, product_id, quantity, unit_price, product_name, discount_amount) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            (input.invoice_id, item.product_id, item.quantity, item.unit_price, &product_name, item_discount),
+            "INSERT INTO invoice_items (invoice_id, product_id, quantity, unit_price, product_name, discount_amount, tax_rate, tax_amount, backordered_quantity) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            (input.invoice_id, item.product_id, item.quantity, item.unit_price, &product_name, item_discount, item_tax_rate, item_tax, backordered[index]),
         ).map_err(|e| format!("Failed to insert item: {}", e))?;
+        let invoice_item_id = tx.last_insert_rowid() as i32;
 
-        // Deduct stock
-        tx.execute(
-            "UPDATE products SET stock_quantity = stock_quantity - ?1 WHERE id = ?2",
-            (item.quantity, item.product_id),
-        ).map_err(|e| format!("Failed to deduct stock: {}", e))?;
-
-        // Record FIFO sale
-        inventory_service::record_sale_fifo(&tx, item.product_id, item.quantity, &sale_date, input.invoice_id)
-            .map_err(|e| format!("Failed to record FIFO: {}", e))?;
-
-        new_total += item.unit_price * item.quantity as f64;
+        // Deduct stock and record FIFO sale
+        bundles::record_item_sale(&tx, invoice_item_id, item.product_id, item.quantity, &sale_date, input.invoice_id)?;
     }
+    reservations::consume_reservations(&tx, &consumed_reservations, input.invoice_id)?;
 
-    // 4. Update invoice total
+    // 4. Update invoice totals and what is still owed on it
     tx.execute(
-        "UPDATE invoices SET total_amount = ?1 WHERE id = ?2",
-        (new_total, input.invoice_id),
+        "UPDATE invoices SET total_amount = ?1, tax_amount = ?2, discount_amount = ?3, gst_rate = ?4, cgst_amount = ?5,
+                sgst_amount = ?6, igst_amount = ?7, round_off = ?8, initial_paid = ?9, credit_amount = ?10
+         WHERE id = ?11",
+        rusqlite::params![
+            totals.total_amount,
+            totals.tax_amount,
+            totals.discount_amount,
+            totals.gst_rate,
+            totals.cgst_amount,
+            totals.sgst_amount,
+            totals.igst_amount,
+            totals.round_off,
+            initial_paid,
+            credit_amount,
+            input.invoice_id,
+        ],
     ).map_err(|e| format!("Failed to update invoice total: {}", e))?;
 
     // 5. Record modification history (legacy table)
@@ -868,9 +2078,6 @@ pub fn update_invoice_items(input: UpdateInvoiceItemsInput, db: State<Database>)
     ).map_err(|e| format!("Failed to record modification: {}", e))?;
 
     // 6. Also record in unified entity_modifications table for Settings UI
-    // Build field changes showing item count diff
-    let old_items_count = current_items.len();
-    let new_items_count = input.items.len();
     let mut field_changes: Vec<serde_json::Value> = Vec::new();
     
     // Detect removed items
@@ -889,7 +2096,7 @@ pub fn update_invoice_items(input: UpdateInvoiceItemsInput, db: State<Database>)
     for new_item in &input.items {
         if let Some(old_item) = current_items.iter().find(|o| o.product_id == new_item.product_id) {
             // Item exists - check if qty/price changed
-            if old_item.quantity != new_item.quantity || (old_item.unit_price - new_item.unit_price).abs() > 0.01 {
+            if quantity::to_milli(old_item.quantity) != quantity::to_milli(new_item.quantity) || (old_item.unit_price - new_item.unit_price).abs() > 0.01 {
                 field_changes.push(serde_json::json!({
                     "field": format!("Item: {}", old_item.product_name),
                     "old": format!("{} x Rs.{}", old_item.quantity, old_item.unit_price),
@@ -907,29 +2114,32 @@ pub fn update_invoice_items(input: UpdateInvoiceItemsInput, db: State<Database>)
         }
     }
 
-    // Also log total change
-    if (current_invoice.2 - new_total).abs() > 0.01 {
-        field_changes.push(serde_json::json!({
-            "field": "Total Amount",
-            "old": format!("Rs.{:.2}", current_invoice.2),
-            "new": format!("Rs.{:.2}", new_total)
-        }));
+    // Also log the financial fields that changed
+    for (field, old, new) in [
+        ("Tax Amount", current_invoice.tax_amount, totals.tax_amount),
+        ("Discount Amount", current_invoice.discount_amount, totals.discount_amount),
+        ("Total Amount", current_invoice.total_amount, totals.total_amount),
+        ("Credit Amount", current_invoice.credit_amount, credit_amount),
+    ] {
+        if money::to_paise(old) != money::to_paise(new) {
+            field_changes.push(serde_json::json!({
+                "field": field,
+                "old": format!("Rs.{:.2}", old),
+                "new": format!("Rs.{:.2}", new)
+            }));
+        }
     }
 
     if !field_changes.is_empty() {
         let changes_json = serde_json::to_string(&field_changes).unwrap_or_default();
         tx.execute(
             "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            ("invoice", input.invoice_id, &current_invoice.1, "items_modified", &changes_json, &input.modified_by),
+            ("invoice", input.invoice_id, &current_invoice.invoice_number, "items_modified", &changes_json, &input.modified_by),
         ).map_err(|e| format!("Failed to log entity modification: {}", e))?;
     }
 
     tx.commit().map_err(|e| format!("Failed to commit: {}", e))?;
-
-    // Return updated invoice
-    let invoice = get_invoice(input.invoice_id, db)?.invoice;
-    log::info!("Updated invoice {} items", input.invoice_id);
-    Ok(invoice)
+    Ok(())
 }
 
 /// Get deleted invoices from audit trail
@@ -1016,3 +2226,905 @@ pub fn get_invoice_modifications(invoice_id: Option<i32>, db: State<Database>) -
     log::info!("Returning {} modifications", modifications.len());
     Ok(modifications)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::customer_payments::{
+        create_customer_payment_internal, delete_customer_payment_internal, get_credit_invoices_internal,
+        get_customer_credit_history_internal, get_customer_credit_summary_internal, get_customer_payments_internal,
+        get_overdue_invoices_internal, CreateCustomerPaymentInput, CreditInvoiceFilters, CreditInvoicePage,
+    };
+    use crate::commands::products::{create_product_internal, get_product_internal};
+    use crate::commands::purchase_orders::{
+        add_payment_to_purchase_order_internal, create_purchase_order_internal, get_product_purchase_history_internal,
+        PO_TOTAL_PAID_SQL,
+    };
+    use crate::commands::settings::set_billing_defaults_internal;
+    use crate::commands::suppliers::{delete_supplier_payment_internal, get_supplier_payment_summary_internal};
+    use crate::db::{CreatePurchaseOrderInput, PurchaseOrderItemInput};
+    use crate::test_support::{batch_quantity, insert_customer, insert_supplier, invoice_input, product_input, TestDb};
+
+    #[test]
+    fn test_purchase_sale_and_delete_flow() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme Traders");
+        let customer_id = insert_customer(&conn, "Ravi");
+
+        // Initial stock of 10 at 40, with 100 paid up front
+        let mut input = product_input("FLOW-1", 40.0, 10, Some(supplier_id));
+        input.amount_paid = Some(100.0);
+        let product = create_product_internal(&conn, input).unwrap();
+        assert_eq!(product.stock_quantity, 10.0);
+        assert_eq!(batch_quantity(&conn, product.id), 10.0);
+
+        // PO for 20 more at 50, 300 paid on creation and 200 later
+        let po = create_purchase_order_internal(
+            &conn,
+            CreatePurchaseOrderInput {
+                supplier_id,
+                items: vec![PurchaseOrderItemInput { product_id: product.id, quantity: 20.0, unit_cost: 50.0 }],
+                order_date: None,
+                expected_delivery_date: None,
+                notes: None,
+                initial_payment: Some(300.0),
+                currency: None,
+                exchange_rate: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(po.total_amount, 1000.0);
+        add_payment_to_purchase_order_internal(&mut conn, po.id, 200.0, None, None, None, None).unwrap();
+        assert!(add_payment_to_purchase_order_internal(&mut conn, po.id, 500.01, None, None, None, None).is_err());
+
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 30.0);
+        assert_eq!(batch_quantity(&conn, product.id), 30.0);
+
+        let supplier_summary = get_supplier_payment_summary_internal(&conn, supplier_id, product.id).unwrap();
+        assert_eq!(supplier_summary.total_payable, 1400.0);
+        assert_eq!(supplier_summary.total_paid, 600.0);
+        assert_eq!(supplier_summary.pending_amount, 800.0);
+
+        // Sell 15 on credit with 400 paid now; FIFO empties the first batch
+        let mut input = invoice_input(Some(customer_id), vec![(product.id, 15, 60.0)]);
+        input.payment_method = Some("Credit".to_string());
+        input.initial_paid = Some(400.0);
+        let invoice = create_invoice_internal(&mut conn, input).unwrap();
+        assert_eq!(invoice.total_amount, 900.0);
+        assert_eq!(invoice.round_off, 0.0);
+
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 15.0);
+        assert_eq!(batch_quantity(&conn, product.id), 15.0);
+        let open_batches: i32 = conn
+            .query_row("SELECT COUNT(*) FROM inventory_batches WHERE product_id = ?1", [product.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(open_batches, 1);
+
+        // Purchase history attributes the sale to the lots it was taken from
+        let history = get_product_purchase_history_internal(&conn, product.id).unwrap();
+        let sold: Vec<(Option<i32>, Option<f64>, Option<f64>)> =
+            history.iter().map(|lot| (lot.po_id, lot.quantity_sold, lot.sold_revenue)).collect();
+        assert!(sold.contains(&(None, Some(10.0), Some(600.0))));
+        assert!(sold.contains(&(Some(po.id), Some(5.0), Some(300.0))));
+
+        let details = get_invoice_internal(&conn, invoice.id).unwrap();
+        assert_eq!(details.items.len(), 1);
+        assert_eq!(details.items[0].quantity, 15.0);
+
+        let credit = get_customer_credit_summary_internal(&conn, customer_id).unwrap();
+        assert_eq!(credit.total_credit_amount, 500.0);
+        assert_eq!(credit.total_paid, 400.0);
+        assert_eq!(credit.pending_amount, 500.0);
+
+        create_customer_payment_internal(
+            &conn,
+            CreateCustomerPaymentInput {
+                customer_id,
+                invoice_id: invoice.id,
+                amount: 150.0,
+                payment_method: Some("UPI".to_string()),
+                note: None,
+                paid_at: None,
+            },
+        )
+        .unwrap();
+        let credit = get_customer_credit_summary_internal(&conn, customer_id).unwrap();
+        assert_eq!(credit.total_paid, 550.0);
+        assert_eq!(credit.pending_amount, 350.0);
+
+        let details = get_invoice_internal(&conn, invoice.id).unwrap();
+        assert_eq!(details.payments.len(), 2);
+        assert_eq!(details.initial_paid, 400.0);
+        assert_eq!(details.credit_amount, 500.0);
+        assert_eq!(details.amount_paid, 550.0);
+        assert_eq!(details.balance_due, 350.0);
+
+        // Deleting the invoice puts the stock back and drops its payments
+        delete_invoice_internal(&mut conn, invoice.id, Some("tester".to_string()), None).unwrap();
+        assert!(get_invoice_internal(&conn, invoice.id).is_err());
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 30.0);
+        assert_eq!(batch_quantity(&conn, product.id), 30.0);
+        let history = get_product_purchase_history_internal(&conn, product.id).unwrap();
+        assert!(history.iter().all(|lot| lot.quantity_sold == Some(0.0)));
+
+        let credit = get_customer_credit_summary_internal(&conn, customer_id).unwrap();
+        assert_eq!(credit.total_credit_amount, 0.0);
+        assert_eq!(credit.pending_amount, 0.0);
+
+        let archived: i32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM deleted_items WHERE entity_type = 'invoice' AND entity_id = ?1",
+                [invoice.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(archived, 1);
+    }
+
+    #[test]
+    fn test_invoice_totals_are_exact_to_the_paisa() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product = create_product_internal(&conn, product_input("PAISA-1", 5.0, 200, None)).unwrap();
+
+        let lines = (0..100).map(|_| (product.id, 1, 9.99)).collect();
+        let invoice = create_invoice_internal(&mut conn, invoice_input(None, lines)).unwrap();
+        assert_eq!(invoice.total_amount, 999.0);
+
+        // 18% GST on 999.00 is 179.82; the total is rounded to the rupee
+        let mut input = invoice_input(None, vec![(product.id, 100, 9.99)]);
+        input.gst_rate = Some(18.0);
+        let invoice = create_invoice_internal(&mut conn, input).unwrap();
+        assert_eq!(invoice.tax_amount, 179.82);
+        assert_eq!(invoice.total_amount, 1179.0);
+        assert_eq!(invoice.round_off, 0.18);
+        assert_eq!(
+            money::sum([
+                invoice.cgst_amount.unwrap_or(0.0),
+                invoice.sgst_amount.unwrap_or(0.0),
+                invoice.igst_amount.unwrap_or(0.0),
+            ]),
+            179.82
+        );
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 0.0);
+    }
+
+    #[test]
+    fn test_missing_fields_fall_back_to_billing_defaults() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product = create_product_internal(&conn, product_input("DEF-1", 50.0, 10, None)).unwrap();
+
+        let mut defaults = billing_defaults(&conn).unwrap();
+        assert_eq!(defaults.payment_method, "Cash");
+        assert_eq!(defaults.credit_period_days, 30);
+        defaults.gst_rate = Some(7.0);
+        defaults.state = Some("Kerala".to_string());
+        assert!(set_billing_defaults_internal(&mut conn, defaults.clone()).is_err());
+        defaults.gst_rate = Some(5.0);
+        defaults.state = Some("  ".to_string());
+        assert!(set_billing_defaults_internal(&mut conn, defaults.clone()).is_err());
+        defaults.state = Some("Kerala".to_string());
+        defaults.payment_method = "UPI".to_string();
+        set_billing_defaults_internal(&mut conn, defaults).unwrap();
+
+        let mut input = invoice_input(None, vec![(product.id, 2, 100.0)]);
+        input.payment_method = None;
+        let invoice = create_invoice_internal(&mut conn, input).unwrap();
+        assert_eq!(invoice.payment_method.as_deref(), Some("UPI"));
+        assert_eq!(invoice.state.as_deref(), Some("Kerala"));
+        assert_eq!(invoice.gst_rate, Some(5.0));
+        assert_eq!(invoice.tax_amount, 10.0);
+        assert_eq!(invoice.total_amount, 210.0);
+
+        // An explicit tax amount is kept as given
+        let mut input = invoice_input(None, vec![(product.id, 1, 100.0)]);
+        input.tax_amount = Some(0.0);
+        let invoice = create_invoice_internal(&mut conn, input).unwrap();
+        assert_eq!(invoice.total_amount, 100.0);
+    }
+
+    #[test]
+    fn test_split_payments_are_recorded_per_method() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product = create_product_internal(&conn, product_input("SPLIT-1", 50.0, 10, None)).unwrap();
+        let customer_id = insert_customer(&conn, "Split Payer");
+        let tender = |method: &str, amount: f64| InvoiceTender { method: method.to_string(), amount };
+
+        // Part cash and part UPI pays in full
+        let mut input = invoice_input(None, vec![(product.id, 2, 100.0)]);
+        input.payments = Some(vec![tender("Cash", 120.0), tender("UPI", 80.0)]);
+        let split = create_invoice_internal(&mut conn, input).unwrap();
+        assert_eq!(split.payment_method.as_deref(), Some(SPLIT_PAYMENT_METHOD));
+        let details = get_invoice_internal(&conn, split.id).unwrap();
+        assert_eq!(details.tenders, vec![tender("Cash", 120.0), tender("UPI", 80.0)]);
+        assert_eq!(details.balance_due, 0.0);
+
+        // Paying less puts the rest on the customer's credit
+        let mut input = invoice_input(Some(customer_id), vec![(product.id, 1, 100.0)]);
+        input.payments = Some(vec![tender("UPI", 30.0), tender("Cash", 20.0)]);
+        let credit = create_invoice_internal(&mut conn, input).unwrap();
+        assert_eq!(credit.payment_method.as_deref(), Some("Credit"));
+        let details = get_invoice_internal(&conn, credit.id).unwrap();
+        assert_eq!((details.initial_paid, details.credit_amount, details.balance_due), (50.0, 50.0, 50.0));
+        assert_eq!(details.payments.len(), 2);
+
+        // Too much, credit without a customer, or credit as a tender are refused
+        let mut input = invoice_input(None, vec![(product.id, 1, 100.0)]);
+        input.payments = Some(vec![tender("Cash", 60.0), tender("UPI", 60.0)]);
+        assert!(create_invoice_internal(&mut conn, input.clone()).is_err());
+        input.payments = Some(vec![tender("Cash", 60.0)]);
+        assert!(create_invoice_internal(&mut conn, input.clone()).is_err());
+        input.customer_id = Some(customer_id);
+        input.payments = Some(vec![tender("Cash", 60.0), tender("Credit", 40.0)]);
+        assert!(create_invoice_internal(&mut conn, input).is_err());
+
+        // Sales by method count each part, and a legacy invoice in full
+        create_invoice_internal(&mut conn, invoice_input(None, vec![(product.id, 1, 100.0)])).unwrap();
+        let range = ReportRange::new(business_today(&conn), business_today(&conn), business_offset_minutes(&conn));
+        let breakdown = crate::commands::analytics::payment_method_breakdown(&conn, &range).unwrap();
+        let by_method: Vec<(&str, f64, i32)> =
+            breakdown.iter().map(|m| (m.payment_method.as_str(), m.total_amount, m.order_count)).collect();
+        assert_eq!(by_method, vec![("Cash", 240.0, 3), ("UPI", 110.0, 2), ("Credit", 50.0, 1)]);
+
+        // The split is fixed once taken
+        let update = UpdateInvoiceInput {
+            id: split.id,
+            customer_id: None,
+            payment_method: Some("UPI".to_string()),
+            created_at: None,
+            status: None,
+            modified_by: None,
+            closed_day_override: None,
+        };
+        assert!(update_invoice_internal(&mut conn, &update).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_invoice_drafts_a_repeat_order() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let customer_id = insert_customer(&conn, "Regular");
+        let ids: Vec<i32> = [("DUP-A", 10), ("DUP-B", 5), ("DUP-C", 3), ("DUP-D", 2)]
+            .iter()
+            .map(|(sku, stock)| create_product_internal(&conn, product_input(sku, 50.0, *stock, None)).unwrap().id)
+            .collect();
+        let source = create_invoice_internal(
+            &mut conn,
+            invoice_input(Some(customer_id), vec![(ids[0], 2, 70.0), (ids[1], 4, 75.0), (ids[2], 3, 75.0), (ids[3], 1, 75.0)]),
+        )
+        .unwrap();
+        conn.execute_batch(&format!("PRAGMA foreign_keys = OFF; DELETE FROM products WHERE id = {}; PRAGMA foreign_keys = ON;", ids[3]))
+            .unwrap();
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('default_gst_rate', '5')", []).unwrap();
+
+        let duplicated = duplicate_invoice_internal(&conn, source.id, false).unwrap();
+        assert_eq!(duplicated.source_invoice_number, source.invoice_number);
+        assert_eq!(duplicated.draft.customer_id, Some(customer_id));
+        assert_eq!(duplicated.draft.gst_rate, Some(5.0));
+        let lines: Vec<(i32, f64, f64)> = duplicated
+            .draft
+            .items
+            .iter()
+            .map(|item| (item.product_id, item.quantity, item.unit_price))
+            .collect();
+        assert_eq!(lines, vec![(ids[0], 2.0, 75.0), (ids[1], 1.0, 75.0)]);
+        let issues: Vec<(Option<i32>, &str)> = duplicated
+            .warnings
+            .iter()
+            .map(|warning| (warning.product_id, warning.issue.as_str()))
+            .collect();
+        assert_eq!(
+            issues,
+            vec![
+                (Some(ids[0]), "price_changed"),
+                (Some(ids[1]), "quantity_reduced"),
+                (Some(ids[2]), "out_of_stock"),
+                (Some(ids[3]), "product_deleted"),
+            ]
+        );
+
+        let historical = duplicate_invoice_internal(&conn, source.id, true).unwrap();
+        assert_eq!(historical.draft.items[0].unit_price, 70.0);
+        assert_eq!(historical.warnings.len(), 3);
+
+        // The draft bills through the normal path
+        let invoice = create_invoice_internal(&mut conn, duplicated.draft).unwrap();
+        assert_eq!(invoice.total_amount, 236.0);
+        assert!(duplicate_invoice_internal(&conn, 9999, false).is_err());
+    }
+
+    #[test]
+    fn test_insufficient_stock_leaves_inventory_untouched() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product = create_product_internal(&conn, product_input("SHORT-1", 10.0, 5, None)).unwrap();
+
+        let result = create_invoice_internal(&mut conn, invoice_input(None, vec![(product.id, 6, 15.0)]));
+        assert!(result.is_err());
+
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 5.0);
+        assert_eq!(batch_quantity(&conn, product.id), 5.0);
+        let invoices: i32 = conn.query_row("SELECT COUNT(*) FROM invoices", [], |row| row.get(0)).unwrap();
+        assert_eq!(invoices, 0);
+    }
+
+    #[test]
+    fn test_credit_limit_blocks_until_approved() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let customer_id = insert_customer(&conn, "Limited");
+        conn.execute("UPDATE customers SET credit_limit = 500 WHERE id = ?1", [customer_id]).unwrap();
+        let product = create_product_internal(&conn, product_input("LIMIT-1", 60.0, 20, None)).unwrap();
+
+        let credit_sale = |quantity: i32| {
+            let mut input = invoice_input(Some(customer_id), vec![(product.id, quantity, 100.0)]);
+            input.payment_method = Some("Credit".to_string());
+            input
+        };
+
+        create_invoice_internal(&mut conn, credit_sale(3)).unwrap();
+
+        // 300 outstanding + 300 more goes 100 over the 500 limit
+        let err = create_invoice_internal(&mut conn, credit_sale(3)).unwrap_err();
+        let details = err.strip_prefix(CREDIT_LIMIT_EXCEEDED).expect("structured credit limit error");
+        let exceeded: CreditLimitExceeded = serde_json::from_str(details).unwrap();
+        assert_eq!(exceeded.credit_limit, 500.0);
+        assert_eq!(exceeded.current_outstanding, 300.0);
+        assert_eq!(exceeded.invoice_credit, 300.0);
+        assert_eq!(exceeded.overflow, 100.0);
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 17.0);
+
+        // The override needs the approving user's name
+        let mut input = credit_sale(3);
+        input.allow_over_limit = true;
+        assert!(create_invoice_internal(&mut conn, input).is_err());
+
+        let mut input = credit_sale(3);
+        input.allow_over_limit = true;
+        input.approved_by = Some("manager".to_string());
+        let invoice = create_invoice_internal(&mut conn, input).unwrap();
+
+        let (action, approver): (String, String) = conn
+            .query_row(
+                "SELECT action, modified_by FROM entity_modifications WHERE entity_type = 'invoice' AND entity_id = ?1",
+                [invoice.id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(action, "credit_limit_override");
+        assert_eq!(approver, "manager");
+        assert_eq!(get_customer_credit_summary_internal(&conn, customer_id).unwrap().pending_amount, 600.0);
+
+        // Paying up front keeps a sale within the limit
+        let mut input = invoice_input(Some(customer_id), vec![(product.id, 1, 100.0)]);
+        input.payment_method = Some("Cash".to_string());
+        create_invoice_internal(&mut conn, input).unwrap();
+    }
+
+    #[test]
+    fn test_update_invoice_logs_metadata_changes() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let ravi = insert_customer(&conn, "Ravi");
+        let asha = insert_customer(&conn, "Asha");
+        let product = create_product_internal(&conn, product_input("META-1", 60.0, 20, None)).unwrap();
+
+        let mut input = invoice_input(Some(ravi), vec![(product.id, 2, 100.0)]);
+        input.payment_method = Some("Credit".to_string());
+        input.initial_paid = Some(50.0);
+        input.due_date = Some("2024-04-14".to_string());
+        let invoice = create_invoice_internal(&mut conn, input).unwrap();
+        conn.execute("UPDATE invoices SET created_at = '2024-03-15T06:00:00+00:00' WHERE id = ?1", [invoice.id]).unwrap();
+
+        let update = |customer_id: Option<i32>, payment_method: Option<&str>, created_at: Option<&str>| UpdateInvoiceInput {
+            id: invoice.id,
+            customer_id,
+            payment_method: payment_method.map(str::to_string),
+            created_at: created_at.map(str::to_string),
+            status: None,
+            modified_by: Some("admin".to_string()),
+            closed_day_override: None,
+        };
+
+        // 150 still owed, so it can't stop being a credit sale yet
+        let err = update_invoice_internal(&mut conn, &update(None, Some("Cash"), None)).unwrap_err();
+        assert!(err.contains("Rs.150.00"), "{}", err);
+
+        // Moving the sale two days later moves the due date with it; payments follow the customer
+        update_invoice_internal(&mut conn, &update(Some(asha), None, Some("2024-03-17T06:00:00Z"))).unwrap();
+        let (created_at, due_date): (String, String) = conn
+            .query_row("SELECT created_at, due_date FROM invoices WHERE id = ?1", [invoice.id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((created_at.as_str(), due_date.as_str()), ("2024-03-17T06:00:00+00:00", "2024-04-16"));
+        assert_eq!(get_customer_credit_summary_internal(&conn, asha).unwrap().pending_amount, 150.0);
+        assert_eq!(get_customer_credit_summary_internal(&conn, ravi).unwrap().pending_amount, 0.0);
+
+        let (action, modified_by, changes): (String, String, String) = conn
+            .query_row(
+                "SELECT action, modified_by, field_changes FROM entity_modifications WHERE entity_type = 'invoice' AND entity_id = ?1",
+                [invoice.id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((action.as_str(), modified_by.as_str()), ("metadata_modified", "admin"));
+        let fields: Vec<String> = serde_json::from_str::<Vec<serde_json::Value>>(&changes)
+            .unwrap()
+            .iter()
+            .map(|change| change["field"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(fields, vec!["customer_id", "created_at", "due_date"]);
+        let logged: i32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM invoice_modifications WHERE invoice_id = ?1 AND action = 'metadata_modified'",
+                [invoice.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(logged, 1);
+
+        // Once settled it can move to Cash; a cash sale can't become credit without payment records
+        create_customer_payment_internal(
+            &conn,
+            CreateCustomerPaymentInput {
+                customer_id: asha,
+                invoice_id: invoice.id,
+                amount: 150.0,
+                payment_method: None,
+                note: None,
+                paid_at: None,
+            },
+        )
+        .unwrap();
+        update_invoice_internal(&mut conn, &update(None, Some("Cash"), None)).unwrap();
+        let cash_sale = create_invoice_internal(&mut conn, invoice_input(Some(ravi), vec![(product.id, 1, 100.0)])).unwrap();
+        let mut to_credit = update(None, Some("Credit"), None);
+        to_credit.id = cash_sale.id;
+        assert!(update_invoice_internal(&mut conn, &to_credit).is_err());
+    }
+
+    #[test]
+    fn test_editing_items_keeps_invoice_tax_and_discount() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let customer = insert_customer(&conn, "Ravi");
+        let kettle = create_product_internal(&conn, product_input("EDIT-1", 60.0, 20, None)).unwrap();
+        let mug = create_product_internal(&conn, product_input("EDIT-2", 30.0, 20, None)).unwrap();
+
+        let mut input = invoice_input(Some(customer), vec![(kettle.id, 2, 100.0), (mug.id, 2, 50.0)]);
+        // Per-item discounts are the lines' shares of the invoice discount
+        input.items[0].discount_amount = Some(16.0);
+        input.items[1].discount_amount = Some(4.0);
+        input.discount_amount = Some(20.0);
+        input.tax_amount = Some(18.0);
+        input.payment_method = Some("Credit".to_string());
+        input.initial_paid = Some(100.0);
+        let invoice = create_invoice_internal(&mut conn, input).unwrap();
+        assert_eq!(invoice.total_amount, 298.0);
+
+        let totals = |conn: &Connection| -> (f64, f64, f64, f64, f64) {
+            conn.query_row(
+                "SELECT total_amount, tax_amount, discount_amount, initial_paid, credit_amount FROM invoices WHERE id = ?1",
+                [invoice.id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)),
+            )
+            .unwrap()
+        };
+        let item = |product_id: i32, quantity: f64, unit_price: f64, discount: f64| CreateInvoiceItemInput {
+            product_id,
+            quantity,
+            unit_price,
+            discount_amount: Some(discount),
+            tax_rate: None,
+            reservation_id: None,
+        };
+
+        // Items change, the invoice keeps its own tax and discount: 250 + 18 - 20
+        update_invoice_items_internal(
+            &mut conn,
+            &UpdateInvoiceItemsInput {
+                invoice_id: invoice.id,
+                items: vec![item(kettle.id, 2.0, 100.0, 16.0), item(mug.id, 1.0, 50.0, 4.0)],
+                tax_amount: None,
+                discount_amount: None,
+                modified_by: Some("admin".to_string()),
+                closed_day_override: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(totals(&conn), (248.0, 18.0, 20.0, 100.0, 148.0));
+        assert_eq!(batch_quantity(&conn, kettle.id), 18.0);
+        assert_eq!(batch_quantity(&conn, mug.id), 19.0);
+        assert_eq!(get_customer_credit_summary_internal(&conn, customer).unwrap().pending_amount, 148.0);
+
+        // The invoice-level figures can be changed along with the items
+        let mut edit = UpdateInvoiceItemsInput {
+            invoice_id: invoice.id,
+            items: vec![item(kettle.id, 2.0, 100.0, 16.0), item(mug.id, 1.0, 50.0, 4.0)],
+            tax_amount: None,
+            discount_amount: Some(300.0),
+            modified_by: Some("admin".to_string()),
+            closed_day_override: None,
+        };
+        assert!(update_invoice_items_internal(&mut conn, &edit).is_err());
+        edit.discount_amount = Some(30.0);
+        update_invoice_items_internal(&mut conn, &edit).unwrap();
+        assert_eq!(totals(&conn), (238.0, 18.0, 30.0, 100.0, 138.0));
+
+        let changes: String = conn
+            .query_row(
+                "SELECT field_changes FROM entity_modifications WHERE entity_type = 'invoice' AND entity_id = ?1 ORDER BY id DESC LIMIT 1",
+                [invoice.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let changes: Vec<serde_json::Value> = serde_json::from_str(&changes).unwrap();
+        let logged: Vec<(&str, &str, &str)> = changes
+            .iter()
+            .map(|c| (c["field"].as_str().unwrap(), c["old"].as_str().unwrap(), c["new"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            logged,
+            vec![
+                ("Discount Amount", "Rs.20.00", "Rs.30.00"),
+                ("Total Amount", "Rs.248.00", "Rs.238.00"),
+                ("Credit Amount", "Rs.148.00", "Rs.138.00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_negative_stock_is_blocked_unless_backorders_are_allowed() {
+        use crate::commands::products::{get_inventory_batches_internal, reconcile_product_stock_internal};
+
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product = create_product_internal(&conn, product_input("BACK-1", 40.0, 3, None)).unwrap();
+        assert!(create_product_internal(&conn, product_input("BACK-2", 40.0, -5, None)).is_err());
+
+        assert!(create_invoice_internal(&mut conn, invoice_input(None, vec![(product.id, 5, 60.0)])).is_err());
+        let err = conn.execute("UPDATE products SET stock_quantity = -1 WHERE id = ?1", [product.id]).unwrap_err();
+        assert!(err.to_string().contains("Stock cannot go below zero"), "{}", err);
+
+        // With backorders allowed the two units stock can't cover are recorded on the line
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('allow_negative_stock', 'true')", []).unwrap();
+        let invoice = create_invoice_internal(&mut conn, invoice_input(None, vec![(product.id, 5, 60.0)])).unwrap();
+        let items = get_invoice_internal(&conn, invoice.id).unwrap().items;
+        assert_eq!((items[0].quantity, items[0].backordered_quantity), (5.0, 2.0));
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, -2.0);
+        assert_eq!(batch_quantity(&conn, product.id), 0.0);
+        assert!(!reconcile_product_stock_internal(&conn, product.id, None).unwrap().corrected);
+
+        // Deleting it puts back only the units that came out of batches
+        delete_invoice_internal(&mut conn, invoice.id, None, None).unwrap();
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 3.0);
+        assert_eq!(batch_quantity(&conn, product.id), 3.0);
+
+        conn.execute("UPDATE products SET stock_quantity = 10 WHERE id = ?1", [product.id]).unwrap();
+        let report = get_inventory_batches_internal(&conn, product.id).unwrap();
+        assert_eq!(report.batches.len(), 1);
+        assert_eq!(report.batches[0].source, "initial_stock");
+        assert_eq!((report.batches[0].original_quantity, report.total_remaining), (Some(3.0), 3.0));
+        assert_eq!((report.total_value, report.weighted_average_cost), (120.0, 40.0));
+        assert!(report.stock_mismatch);
+        let reconciliation = reconcile_product_stock_internal(&conn, product.id, Some("admin")).unwrap();
+        assert_eq!((reconciliation.recorded_quantity, reconciliation.batch_quantity), (10.0, 3.0));
+        assert!(reconciliation.corrected);
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 3.0);
+        assert!(!get_inventory_batches_internal(&conn, product.id).unwrap().stock_mismatch);
+        let logged: i32 = conn
+            .query_row("SELECT COUNT(*) FROM entity_modifications WHERE action = 'stock_reconciled'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(logged, 1);
+    }
+
+    #[test]
+    fn test_restock_after_a_backorder_reconciles_without_losing_it() {
+        use crate::commands::products::{get_inventory_batches_internal, reconcile_product_stock_internal};
+
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme");
+        let product = create_product_internal(&conn, product_input("BACK-3", 40.0, 3, None)).unwrap();
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('allow_negative_stock', 'true')", []).unwrap();
+        create_invoice_internal(&mut conn, invoice_input(None, vec![(product.id, 5, 60.0)])).unwrap();
+
+        // Ten arrive: stock is 8 while the new batch holds 10, two of which are owed
+        create_purchase_order_internal(
+            &conn,
+            CreatePurchaseOrderInput {
+                supplier_id,
+                items: vec![PurchaseOrderItemInput { product_id: product.id, quantity: 10.0, unit_cost: 40.0 }],
+                order_date: None,
+                expected_delivery_date: None,
+                notes: None,
+                initial_payment: None,
+                currency: None,
+                exchange_rate: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 8.0);
+        assert_eq!(batch_quantity(&conn, product.id), 10.0);
+        assert!(!get_inventory_batches_internal(&conn, product.id).unwrap().stock_mismatch);
+        let reconciliation = reconcile_product_stock_internal(&conn, product.id, None).unwrap();
+        assert_eq!(reconciliation.backordered_quantity, 2.0);
+        assert!(!reconciliation.corrected);
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 8.0);
+
+        // Stock further below the batches than the backorders is still corrected
+        conn.execute("UPDATE products SET stock_quantity = 5 WHERE id = ?1", [product.id]).unwrap();
+        let reconciliation = reconcile_product_stock_internal(&conn, product.id, None).unwrap();
+        assert_eq!((reconciliation.corrected, reconciliation.reconciled_quantity), (true, 8.0));
+    }
+
+    #[test]
+    fn test_overdue_credit_splits_pending_balance() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let customer_id = insert_customer(&conn, "Slow Payer");
+        let product = create_product_internal(&conn, product_input("DUE-1", 60.0, 20, None)).unwrap();
+
+        let credit_sale = |due_date: Option<&str>| {
+            let mut input = invoice_input(Some(customer_id), vec![(product.id, 2, 100.0)]);
+            input.payment_method = Some("Credit".to_string());
+            input.initial_paid = Some(50.0);
+            input.due_date = due_date.map(str::to_string);
+            input
+        };
+
+        let late = create_invoice_internal(&mut conn, credit_sale(Some("2020-01-01"))).unwrap();
+        assert_eq!(late.due_date.as_deref(), Some("2020-01-01"));
+
+        // Without a date the credit period (30 days by default) applies
+        let current = create_invoice_internal(&mut conn, credit_sale(None)).unwrap();
+        let expected = (crate::commands::analytics::business_today(&conn) + Duration::days(30)).format("%Y-%m-%d").to_string();
+        assert_eq!(current.due_date, Some(expected));
+
+        assert!(create_invoice_internal(&mut conn, credit_sale(Some("01/02/2020"))).is_err());
+
+        let overdue = get_overdue_invoices_internal(&conn, None).unwrap();
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].invoice_id, late.id);
+        assert_eq!(overdue[0].balance_remaining, 150.0);
+        assert_eq!(overdue[0].customer_name, "Slow Payer");
+        assert!(overdue[0].days_overdue > 0);
+
+        let summary = get_customer_credit_summary_internal(&conn, customer_id).unwrap();
+        assert_eq!(summary.pending_amount, 300.0);
+        assert_eq!(summary.overdue_amount, 150.0);
+        assert_eq!(summary.current_amount, 150.0);
+
+        // Settling the late invoice clears it from the overdue list
+        create_customer_payment_internal(
+            &conn,
+            CreateCustomerPaymentInput {
+                customer_id,
+                invoice_id: late.id,
+                amount: 150.0,
+                payment_method: Some("Cash".to_string()),
+                note: None,
+                paid_at: None,
+            },
+        )
+        .unwrap();
+        assert!(get_overdue_invoices_internal(&conn, Some(customer_id)).unwrap().is_empty());
+        assert_eq!(get_customer_credit_summary_internal(&conn, customer_id).unwrap().overdue_amount, 0.0);
+
+        // History and payments page independently of the summary
+        let history = get_customer_credit_history_internal(&conn, customer_id, 1, 1, None, None).unwrap();
+        assert_eq!(history.total_count, 2);
+        assert_eq!(history.items.len(), 1);
+        let cash = get_customer_payments_internal(&conn, customer_id, 1, 20, None, None, Some("cash")).unwrap();
+        // Both initial payments plus the settlement, newest first
+        assert_eq!(cash.total_count, 3);
+        assert_eq!(cash.items[0].invoice_number.as_deref(), Some(late.invoice_number.as_str()));
+        let upi = get_customer_payments_internal(&conn, customer_id, 1, 20, None, None, Some("UPI")).unwrap();
+        assert_eq!(upi.total_count, 0);
+        let tomorrow = (crate::commands::analytics::business_today(&conn) + Duration::days(1)).format("%Y-%m-%d").to_string();
+        let later = get_customer_payments_internal(&conn, customer_id, 1, 20, Some(&tomorrow), None, None).unwrap();
+        assert_eq!(later.total_count, 0);
+    }
+
+    #[test]
+    fn test_credit_invoices_list_paid_balance_and_age() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let ravi = insert_customer(&conn, "Ravi");
+        let asha = insert_customer(&conn, "Asha");
+        let product = create_product_internal(&conn, product_input("CRD-1", 60.0, 50, None)).unwrap();
+
+        let mut credit_sale = |customer_id: i32, quantity: i32, initial_paid: f64| {
+            let mut input = invoice_input(Some(customer_id), vec![(product.id, quantity, 100.0)]);
+            input.payment_method = Some("Credit".to_string());
+            input.initial_paid = Some(initial_paid);
+            create_invoice_internal(&mut conn, input).unwrap()
+        };
+        let partial = credit_sale(ravi, 2, 50.0);
+        let unpaid = credit_sale(ravi, 3, 0.0);
+        let settled = credit_sale(asha, 1, 0.0);
+        create_invoice_internal(&mut conn, invoice_input(Some(ravi), vec![(product.id, 1, 100.0)])).unwrap();
+        create_customer_payment_internal(
+            &conn,
+            CreateCustomerPaymentInput {
+                customer_id: asha,
+                invoice_id: settled.id,
+                amount: 100.0,
+                payment_method: None,
+                note: None,
+                paid_at: None,
+            },
+        )
+        .unwrap();
+        let ten_days_ago = (chrono::Utc::now() - Duration::days(10)).to_rfc3339();
+        conn.execute("UPDATE invoices SET created_at = ?1 WHERE id = ?2", rusqlite::params![ten_days_ago, partial.id]).unwrap();
+
+        let list = |filters: CreditInvoiceFilters| get_credit_invoices_internal(&conn, 1, 20, &filters).unwrap();
+        let ids = |page: &CreditInvoicePage| page.items.iter().map(|invoice| invoice.invoice_id).collect::<Vec<_>>();
+
+        // Cash sales are left out; oldest first by default
+        let all = list(CreditInvoiceFilters::default());
+        assert_eq!(ids(&all), vec![partial.id, unpaid.id, settled.id]);
+        assert_eq!((all.total_count, all.total_balance), (3, 450.0));
+        let oldest = &all.items[0];
+        assert_eq!((oldest.total_paid, oldest.balance_remaining, oldest.days_outstanding), (50.0, 150.0, 10));
+        let statuses: Vec<&str> = all.items.iter().map(|invoice| invoice.payment_status.as_str()).collect();
+        assert_eq!(statuses, vec!["partial", "unpaid", "paid"]);
+
+        let by_balance = list(CreditInvoiceFilters { sort: Some("balance".to_string()), ..Default::default() });
+        assert_eq!(ids(&by_balance), vec![unpaid.id, partial.id, settled.id]);
+
+        let status = |status: &str| CreditInvoiceFilters { status: Some(status.to_string()), ..Default::default() };
+        assert_eq!(ids(&list(status("partial"))), vec![partial.id]);
+        assert_eq!(ids(&list(status("unpaid"))), vec![unpaid.id]);
+        let paid = list(status("paid"));
+        assert_eq!((ids(&paid), paid.total_balance), (vec![settled.id], 0.0));
+        assert!(get_credit_invoices_internal(&conn, 1, 20, &status("overdue")).is_err());
+
+        // The balance total covers the whole filter, not just the page
+        let filters = CreditInvoiceFilters { customer_id: Some(ravi), ..Default::default() };
+        let first_page = get_credit_invoices_internal(&conn, 1, 1, &filters).unwrap();
+        assert_eq!(ids(&first_page), vec![partial.id]);
+        assert_eq!((first_page.total_count, first_page.total_balance), (2, 450.0));
+    }
+
+    #[test]
+    fn test_deleting_payments_rebalances_credit_and_po_totals() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme");
+        let customer_id = insert_customer(&conn, "Ravi");
+        let product = create_product_internal(&conn, product_input("DEL-1", 40.0, 20, Some(supplier_id))).unwrap();
+
+        let mut input = invoice_input(Some(customer_id), vec![(product.id, 2, 100.0)]);
+        input.payment_method = Some("Credit".to_string());
+        input.initial_paid = Some(50.0);
+        let invoice = create_invoice_internal(&mut conn, input).unwrap();
+
+        // Deleting the payment taken at billing moves it back onto the credit
+        let initial = get_invoice_payments_internal(&conn, invoice.id).unwrap();
+        delete_customer_payment_internal(&conn, initial[0].id, Some("admin".to_string())).unwrap();
+        let (initial_paid, credit_amount): (f64, f64) = conn
+            .query_row("SELECT initial_paid, credit_amount FROM invoices WHERE id = ?1", [invoice.id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap();
+        assert_eq!((initial_paid, credit_amount), (0.0, 200.0));
+        assert_eq!(get_customer_credit_summary_internal(&conn, customer_id).unwrap().pending_amount, 200.0);
+        let changes: String = conn
+            .query_row(
+                "SELECT field_changes FROM entity_modifications WHERE entity_type = 'customer_payment' AND action = 'deleted'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let changes: Vec<serde_json::Value> = serde_json::from_str(&changes).unwrap();
+        assert_eq!(changes[1]["field"], "balance_remaining");
+        assert_eq!((changes[1]["old"].as_f64(), changes[1]["new"].as_f64()), (Some(150.0), Some(200.0)));
+
+        // A PO's paid total is recomputed from the remaining payments
+        let po = create_purchase_order_internal(
+            &conn,
+            CreatePurchaseOrderInput {
+                supplier_id,
+                items: vec![PurchaseOrderItemInput { product_id: product.id, quantity: 10.0, unit_cost: 50.0 }],
+                order_date: None,
+                expected_delivery_date: None,
+                notes: None,
+                initial_payment: None,
+                currency: None,
+                exchange_rate: None,
+            },
+        )
+        .unwrap();
+        let payment_id = add_payment_to_purchase_order_internal(&mut conn, po.id, 200.0, None, None, None, None).unwrap();
+        let (_, po_ids) = delete_supplier_payment_internal(&conn, payment_id, None).unwrap();
+        assert_eq!(po_ids, vec![po.id]);
+        let paid: f64 = conn.query_row(PO_TOTAL_PAID_SQL, [po.id], |row| row.get(0)).unwrap();
+        assert_eq!(paid, 0.0);
+        let changes: String = conn
+            .query_row(
+                "SELECT field_changes FROM entity_modifications WHERE entity_type = 'supplier_payment' AND entity_id = ?1",
+                [payment_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(changes.contains("\"po_total_paid\""), "{}", changes);
+    }
+
+    #[test]
+    fn test_invoice_list_filters_and_sort() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let customer_id = insert_customer(&conn, "Meena");
+        let product = create_product_internal(&conn, product_input("LIST-1", 40.0, 50, None)).unwrap();
+
+        let sale = |quantity: i32, method: &str, initial_paid: Option<f64>| {
+            let mut input = invoice_input(Some(customer_id), vec![(product.id, quantity, 100.0)]);
+            input.payment_method = Some(method.to_string());
+            input.initial_paid = initial_paid;
+            input
+        };
+        let cash = create_invoice_internal(&mut conn, sale(1, "Cash", None)).unwrap();
+        let unpaid = create_invoice_internal(&mut conn, sale(2, "Credit", None)).unwrap();
+        let partial = create_invoice_internal(&mut conn, sale(3, "Credit", Some(100.0))).unwrap();
+        conn.execute("UPDATE invoices SET created_at = '2020-01-05T10:00:00+00:00' WHERE id = ?1", [cash.id]).unwrap();
+
+        let list = |filters: InvoiceListFilters| {
+            let result = get_invoices_internal(&conn, 1, 50, None, None, &filters).unwrap();
+            (result.total_count, result.items.iter().map(|i| i.id).collect::<Vec<_>>())
+        };
+
+        let status = |status: &str| InvoiceListFilters { credit_status: Some(status.to_string()), ..Default::default() };
+        assert_eq!(list(status("paid")), (1, vec![cash.id]));
+        assert_eq!(list(status("unpaid")), (1, vec![unpaid.id]));
+        assert_eq!(list(status("partial")), (1, vec![partial.id]));
+
+        let credit_over_250 = InvoiceListFilters {
+            payment_method: Some("credit".to_string()),
+            min_amount: Some(250.0),
+            ..Default::default()
+        };
+        assert_eq!(list(credit_over_250), (1, vec![partial.id]));
+
+        let january = InvoiceListFilters {
+            start_date: Some("2020-01-05".to_string()),
+            end_date: Some("2020-01-31".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(list(january), (1, vec![cash.id]));
+
+        let by_amount = InvoiceListFilters { sort: Some("amount".to_string()), descending: Some(false), ..Default::default() };
+        assert_eq!(list(by_amount), (3, vec![cash.id, unpaid.id, partial.id]));
+
+        // Sort columns come from a whitelist only
+        let injected = InvoiceListFilters { sort: Some("i.id; DROP TABLE invoices".to_string()), ..Default::default() };
+        assert!(get_invoices_internal(&conn, 1, 50, None, None, &injected).is_err());
+    }
+
+    #[test]
+    fn test_invoices_are_found_by_the_item_name_on_the_bill() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let tea = create_product_internal(&conn, product_input("TEA-1", 10.0, 20, None)).unwrap();
+        let other = create_product_internal(&conn, product_input("SUGAR-1", 10.0, 20, None)).unwrap();
+        conn.execute("UPDATE products SET name = 'Assam Tea' WHERE id = ?1", [tea.id]).unwrap();
+
+        // Two lines of the same product still make one match
+        let lines = vec![(tea.id, 2, 15.0), (tea.id, 1, 15.0), (other.id, 1, 15.0)];
+        let invoice = create_invoice_internal(&mut conn, invoice_input(None, lines)).unwrap();
+        create_invoice_internal(&mut conn, invoice_input(None, vec![(other.id, 1, 15.0)])).unwrap();
+        conn.execute("UPDATE products SET name = 'Darjeeling' WHERE id = ?1", [tea.id]).unwrap();
+
+        let found = get_invoices_internal(&conn, 1, 50, Some("assam"), None, &InvoiceListFilters::default()).unwrap();
+        assert_eq!((found.total_count, found.items.len()), (1, 1));
+        assert_eq!(found.items[0].id, invoice.id);
+        assert_eq!(get_invoices_internal(&conn, 1, 50, Some("Darjeeling"), None, &InvoiceListFilters::default()).unwrap().total_count, 0);
+
+        let by_name = get_invoices_by_product_name_internal(&conn, "Assam", 1, 50).unwrap();
+        assert_eq!(by_name.total_count, 1);
+        assert_eq!(by_name.items[0].quantity, Some(3.0));
+        assert_eq!(by_name.items[0].product_amount, Some(45.0));
+        assert!(get_invoices_by_product_name_internal(&conn, "  ", 1, 50).is_err());
+    }
+}
@@ -0,0 +1,918 @@
+/// Database Maintenance Commands
+/// Size statistics, integrity check, VACUUM and ANALYZE for inventory.db, and the maintenance
+/// lock that keeps other commands from writing while a restore, import or maintenance run owns
+/// the database
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::db::Database;
+
+/// Prefix of the error every database-writing command returns while the maintenance lock is
+/// held; the rest of the message is the operation holding it (see MaintenanceOperation)
+pub const MAINTENANCE_IN_PROGRESS: &str = "MAINTENANCE_IN_PROGRESS:";
+
+/// Commands that never write to the database, and so keep working while the maintenance lock
+/// is held. Every other command is treated as a write and rejected unless it belongs to the
+/// operation holding the lock, so a new command is blocked until it is listed here.
+/// Getters that write as a side effect stay off this list: the sales analytics that refresh
+/// daily_sales_summary, the audit-logged AI data commands, login and biometric checks.
+const READ_ONLY_COMMANDS: &[&str] = &[
+    // Products, stock and pricing
+    "get_products",
+    "get_product",
+    "get_products_by_supplier",
+    "get_inventory_batches",
+    "get_top_selling_products",
+    "get_products_by_ids",
+    "get_billing_snapshot",
+    "get_last_sold_price",
+    "get_product_price_history",
+    "get_product_price_timeline",
+    "get_reservations",
+    "get_held_sales",
+    "get_unique_categories",
+    "get_categories",
+    "get_product_variants",
+    "get_bundle",
+    "get_bundle_component_usage",
+    "get_price_tiers",
+    "get_effective_price",
+    "get_price_list",
+    "get_price_overrides",
+    "get_costing_method",
+    // Suppliers and purchases
+    "get_suppliers",
+    "get_supplier",
+    "get_supplier_payments",
+    "get_all_product_payments",
+    "get_supplier_payment_summary",
+    "get_all_product_payment_summary",
+    "get_supplier_payables_aging",
+    "get_supplier_product_purchase_history",
+    "get_supplier_ledger",
+    "get_supplier_report",
+    "get_purchase_orders",
+    "get_purchase_order_by_id",
+    "get_product_purchase_summary",
+    "get_incoming_stock",
+    "get_product_purchase_history",
+    "get_purchase_returns",
+    // Customers, invoices and payments
+    "get_customers",
+    "get_customer",
+    "customer_search",
+    "get_customer_report",
+    "get_invoices",
+    "get_invoices_by_product",
+    "get_invoices_by_product_name",
+    "get_invoice",
+    "duplicate_invoice",
+    "get_product_sales_summary",
+    "get_deleted_invoices",
+    "get_invoice_modifications",
+    "get_customer_payments",
+    "get_invoice_payments",
+    "get_customer_credit_history",
+    "get_customer_credit_summary",
+    "get_overdue_invoices",
+    "get_credit_invoices",
+    "get_payment_reminders",
+    "render_reminder_message",
+    "get_challans",
+    "get_challan",
+    "get_recurring_templates",
+    "get_expenses",
+    "get_day_closure",
+    "get_closure_history",
+    "print_receipt",
+    "get_share_templates",
+    "render_share_message",
+    "open_whatsapp_chat",
+    // Analytics that read the invoices directly
+    "get_dashboard_stats",
+    "get_low_stock_products",
+    "get_top_products",
+    "get_sales_by_category",
+    "get_sales_by_region",
+    "get_customer_analytics",
+    "get_top_customers",
+    "get_customer_trend",
+    "get_inventory_health",
+    "get_inventory_valuation",
+    "get_low_stock_alerts",
+    "get_slow_moving_stock",
+    "get_abc_analysis",
+    "get_sales_heatmap",
+    "get_purchase_analytics",
+    "get_cashflow_trend",
+    "get_top_suppliers",
+    "get_tax_summary",
+    "get_discount_analysis",
+    "get_sales_targets",
+    "get_sales_target_progress",
+    "get_stock_digests",
+    "render_stock_digest_message",
+    // Search, trash and exports
+    "omnisearch",
+    "get_deleted_items",
+    "validate_trash",
+    "export_products_csv",
+    "export_customers_csv",
+    "export_csv",
+    "export_products",
+    "export_settings_json",
+    "export_database_snapshot",
+    "scan_duplicates",
+    "preview_product_csv",
+    "preview_customer_csv",
+    "preview_supplier_csv",
+    "cancel_data_operation",
+    // Settings, users and biometrics
+    "get_users",
+    "get_app_setting",
+    "get_all_settings",
+    "get_billing_defaults",
+    "get_user_preference",
+    "get_all_user_preferences",
+    "get_biometric_status",
+    "get_biometric_status_by_username",
+    "has_any_biometric_enrollment",
+    // Images (files only)
+    "get_product_image_path",
+    "search_google_images",
+    "get_image_search_status",
+    "get_pictures_directory",
+    "scan_image_orphans",
+    "get_supplier_image_path",
+    "get_customer_image_path",
+    // AI sidecar
+    "start_ai_sidecar",
+    "stop_ai_sidecar",
+    "check_ai_sidecar_status",
+    "check_sidecar_downloaded",
+    "download_ai_sidecar",
+    "lock_ai_data",
+    // Database status; find_orphaned_rows checks the lock itself before repairing
+    "check_migration_status",
+    "validate_migration",
+    "get_schema_version",
+    "get_database_stats",
+    "find_orphaned_rows",
+    "get_maintenance_status",
+    "list_safety_snapshots",
+    "get_latest_integrity_report",
+    "get_integration_status",
+    "get_webhook_deliveries",
+    "get_performance_metrics",
+    // Integration API lookups (not invoke commands)
+    "get_product_by_sku",
+    "get_product_by_barcode",
+    "get_stock",
+];
+
+/// Tables reported by get_database_stats (missing ones are skipped)
+const STATS_TABLES: &[&str] = &[
+    "products",
+    "customers",
+    "suppliers",
+    "invoices",
+    "invoice_items",
+    "customer_payments",
+    "supplier_payments",
+    "purchase_orders",
+    "purchase_order_items",
+    "expenses",
+    "inventory_batches",
+    "inventory_transactions",
+    "deleted_items",
+    "entity_modifications",
+    "invoice_modifications",
+    "app_settings",
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableRowCount {
+    pub table: String,
+    pub rows: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatabaseStats {
+    pub file_path: String,
+    pub file_size_bytes: u64,
+    pub wal_size_bytes: u64,
+    pub page_size: i64,
+    pub page_count: i64,
+    pub freelist_pages: i64,
+    /// Bytes VACUUM could reclaim (freelist pages * page size)
+    pub reclaimable_bytes: i64,
+    pub tables: Vec<TableRowCount>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceProgress {
+    pub step: String, // "integrity_check", "vacuum", "analyze", "done"
+    pub message: String,
+    pub percentage: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceResult {
+    pub integrity_ok: bool,
+    /// "ok" or the problems reported by PRAGMA integrity_check
+    pub integrity_messages: Vec<String>,
+    pub vacuumed: bool,
+    pub analyzed: bool,
+    pub size_before_bytes: u64,
+    pub size_after_bytes: u64,
+    pub duration_ms: u128,
+}
+
+/// Long-running operations that take the maintenance lock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceOperation {
+    RestoreBackup,
+    CsvImport,
+    DatabaseMaintenance,
+}
+
+impl MaintenanceOperation {
+    fn as_str(self) -> &'static str {
+        match self {
+            MaintenanceOperation::RestoreBackup => "restore_backup",
+            MaintenanceOperation::CsvImport => "csv_import",
+            MaintenanceOperation::DatabaseMaintenance => "database_maintenance",
+        }
+    }
+
+    /// Commands that run as part of this operation and may keep writing while it holds the lock
+    fn owns_command(self, command: &str) -> bool {
+        match self {
+            MaintenanceOperation::RestoreBackup => {
+                matches!(
+                    command,
+                    "restore_from_backup" | "import_database_snapshot" | "restore_safety_snapshot"
+                )
+            }
+            MaintenanceOperation::CsvImport => {
+                matches!(
+                    command,
+                    "import_csv_chunk" | "import_products_csv" | "import_customers_csv" | "import_suppliers_csv"
+                )
+            }
+            MaintenanceOperation::DatabaseMaintenance => command == "run_database_maintenance",
+        }
+    }
+}
+
+/// Current holder of the maintenance lock
+struct LockHolder {
+    operation: MaintenanceOperation,
+    /// Guards currently alive for the operation (acquire is reentrant)
+    depth: usize,
+    started_at: String,
+    /// Keeps the lock between the calls of a multi-call operation such as a chunked import
+    session_until: Option<Instant>,
+}
+
+impl LockHolder {
+    fn is_active(&self) -> bool {
+        self.depth > 0 || self.session_until.is_some_and(|until| Instant::now() < until)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceStatus {
+    pub locked: bool,
+    pub operation: Option<MaintenanceOperation>,
+    pub started_at: Option<String>,
+}
+
+/// Guards against overlapping maintenance runs and holds the maintenance lock.
+///
+/// While the lock is held, database-writing commands fail with MAINTENANCE_IN_PROGRESS
+/// (checked in the invoke handler, see `check_command_allowed`); reads keep working.
+#[derive(Default)]
+pub struct MaintenanceState {
+    running: AtomicBool,
+    lock: Mutex<Option<LockHolder>>,
+}
+
+/// Releases one hold on the maintenance lock when dropped. A panic in the owning task drops
+/// the guard while unwinding, which releases the lock completely so the app can't stay locked.
+pub struct MaintenanceGuard<'a> {
+    state: &'a MaintenanceState,
+}
+
+impl Drop for MaintenanceGuard<'_> {
+    fn drop(&mut self) {
+        let mut holder = self.state.holder();
+        if let Some(current) = holder.as_mut() {
+            current.depth = current.depth.saturating_sub(1);
+            if std::thread::panicking() {
+                log::error!("{} panicked, releasing the maintenance lock", current.operation.as_str());
+                current.depth = 0;
+                current.session_until = None;
+            }
+            if !current.is_active() {
+                log::info!("Maintenance lock released by {}", current.operation.as_str());
+                *holder = None;
+            }
+        }
+    }
+}
+
+impl MaintenanceState {
+    /// A panic while the mutex was held must not leave the lock unusable
+    fn holder(&self) -> MutexGuard<'_, Option<LockHolder>> {
+        self.lock.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Take the maintenance lock for `operation`, or add a hold if it already owns it.
+    /// Fails with MAINTENANCE_IN_PROGRESS when another operation holds the lock.
+    pub(crate) fn acquire(&self, operation: MaintenanceOperation) -> Result<MaintenanceGuard<'_>, String> {
+        let mut holder = self.holder();
+        match holder.as_mut() {
+            Some(current) if current.is_active() && current.operation != operation => {
+                return Err(maintenance_error(current.operation));
+            }
+            Some(current) if current.is_active() => current.depth += 1,
+            _ => {
+                log::info!("Maintenance lock acquired by {}", operation.as_str());
+                *holder = Some(LockHolder {
+                    operation,
+                    depth: 1,
+                    started_at: Utc::now().to_rfc3339(),
+                    session_until: None,
+                });
+            }
+        }
+        Ok(MaintenanceGuard { state: self })
+    }
+
+    /// Keep holding the lock after the current call returns, until `end_session` or until
+    /// `lease` passes without another call renewing it
+    pub(crate) fn hold_session(&self, operation: MaintenanceOperation, lease: Duration) {
+        if let Some(current) = self.holder().as_mut().filter(|h| h.operation == operation) {
+            current.session_until = Some(Instant::now() + lease);
+        }
+    }
+
+    /// Stop holding the lock between calls; it is released once no guard is left
+    pub(crate) fn end_session(&self, operation: MaintenanceOperation) {
+        let mut holder = self.holder();
+        if let Some(current) = holder.as_mut().filter(|h| h.operation == operation) {
+            current.session_until = None;
+            if current.depth == 0 {
+                log::info!("Maintenance lock released by {}", operation.as_str());
+                *holder = None;
+            }
+        }
+    }
+
+    /// Fail with MAINTENANCE_IN_PROGRESS while any operation holds the lock.
+    /// For commands whose writes depend on their arguments (e.g. repair flags).
+    pub(crate) fn ensure_writable(&self) -> Result<(), String> {
+        match self.holder().as_ref().filter(|h| h.is_active()) {
+            Some(current) => Err(maintenance_error(current.operation)),
+            None => Ok(()),
+        }
+    }
+
+    /// Decide whether an invoked command may run: reads always may, everything else only when
+    /// the lock is free or held by the operation the command belongs to
+    pub(crate) fn check_command_allowed(&self, command: &str) -> Result<(), String> {
+        if READ_ONLY_COMMANDS.contains(&command) {
+            return Ok(());
+        }
+        match self.holder().as_ref().filter(|h| h.is_active()) {
+            Some(current) if !current.operation.owns_command(command) => Err(maintenance_error(current.operation)),
+            _ => Ok(()),
+        }
+    }
+
+    fn status(&self) -> MaintenanceStatus {
+        match self.holder().as_ref().filter(|h| h.is_active()) {
+            Some(current) => MaintenanceStatus {
+                locked: true,
+                operation: Some(current.operation),
+                started_at: Some(current.started_at.clone()),
+            },
+            None => MaintenanceStatus {
+                locked: false,
+                operation: None,
+                started_at: None,
+            },
+        }
+    }
+}
+
+fn maintenance_error(operation: MaintenanceOperation) -> String {
+    format!("{}{}", MAINTENANCE_IN_PROGRESS, operation.as_str())
+}
+
+/// Whether a restore, import or maintenance run currently holds the database, for the
+/// maintenance banner
+#[tauri::command]
+pub fn get_maintenance_status(maintenance: State<MaintenanceState>) -> Result<MaintenanceStatus, String> {
+    Ok(maintenance.status())
+}
+
+/// Resets the running flag when maintenance ends, including on error
+struct RunningGuard<'a>(&'a AtomicBool);
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+fn emit_maintenance_progress(app: &AppHandle, step: &str, message: &str, percentage: f32) {
+    let progress = MaintenanceProgress {
+        step: step.to_string(),
+        message: message.to_string(),
+        percentage,
+    };
+    let _ = app.emit("database-maintenance-progress", progress);
+}
+
+/// Path of the main database file, as reported by SQLite
+pub(crate) fn database_file_path(conn: &Connection) -> Result<PathBuf, String> {
+    conn.query_row("SELECT file FROM pragma_database_list WHERE name = 'main'", [], |row| {
+        row.get::<_, String>(0)
+    })
+    .map(PathBuf::from)
+    .map_err(|e| format!("Failed to locate database file: {}", e))
+}
+
+/// Size of the database file and its WAL file in bytes
+fn file_sizes(path: &Path) -> (u64, u64) {
+    let size = |p: &Path| std::fs::metadata(p).map(|m| m.len()).unwrap_or(0);
+    let mut wal_path = path.as_os_str().to_owned();
+    wal_path.push("-wal");
+    (size(path), size(Path::new(&wal_path)))
+}
+
+fn pragma_i64(conn: &Connection, pragma: &str) -> Result<i64, String> {
+    conn.query_row(&format!("PRAGMA {}", pragma), [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read PRAGMA {}: {}", pragma, e))
+}
+
+/// Get database file size, page usage and row counts for the main tables
+#[tauri::command]
+pub fn get_database_stats(db: State<Database>) -> Result<DatabaseStats, String> {
+    log::info!("get_database_stats called");
+
+    let conn = db.get_conn()?;
+    let path = database_file_path(&conn)?;
+    let (file_size_bytes, wal_size_bytes) = file_sizes(&path);
+
+    let page_size = pragma_i64(&conn, "page_size")?;
+    let page_count = pragma_i64(&conn, "page_count")?;
+    let freelist_pages = pragma_i64(&conn, "freelist_count")?;
+
+    let mut tables = Vec::new();
+    for table in STATS_TABLES {
+        let exists: bool = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [table],
+                |row| row.get::<_, i32>(0),
+            )
+            .map(|count| count > 0)
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            continue;
+        }
+
+        let rows: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+            .map_err(|e| format!("Failed to count rows in {}: {}", table, e))?;
+        tables.push(TableRowCount {
+            table: table.to_string(),
+            rows,
+        });
+    }
+
+    Ok(DatabaseStats {
+        file_path: path.to_string_lossy().to_string(),
+        file_size_bytes,
+        wal_size_bytes,
+        page_size,
+        page_count,
+        freelist_pages,
+        reclaimable_bytes: freelist_pages * page_size,
+        tables,
+    })
+}
+
+/// Run PRAGMA integrity_check, then VACUUM and ANALYZE.
+///
+/// Refuses to start while a CSV import or another maintenance run holds the maintenance lock,
+/// and holds it itself until done. VACUUM and ANALYZE are skipped when the integrity check reports problems, since rewriting
+/// a damaged file can make recovery harder. Emits "database-maintenance-progress" for each
+/// step and "database-maintenance-complete" with the result.
+#[tauri::command]
+pub async fn run_database_maintenance(
+    app: AppHandle,
+    maintenance: State<'_, MaintenanceState>,
+    db: State<'_, Database>,
+) -> Result<MaintenanceResult, String> {
+    log::info!("run_database_maintenance called");
+
+    if maintenance.running.swap(true, Ordering::SeqCst) {
+        return Err("Database maintenance is already running".to_string());
+    }
+    let _guard = RunningGuard(&maintenance.running);
+    let _lock = maintenance.acquire(MaintenanceOperation::DatabaseMaintenance)?;
+
+    let started = std::time::Instant::now();
+    let conn = db.get_conn()?;
+    let path = database_file_path(&conn)?;
+
+    // Fold the WAL into the main file so sizes before/after are comparable
+    let _ = conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);");
+    let (size_before, wal_before) = file_sizes(&path);
+    let size_before_bytes = size_before + wal_before;
+
+    emit_maintenance_progress(&app, "integrity_check", "Checking database integrity...", 0.0);
+    let mut stmt = conn
+        .prepare("PRAGMA integrity_check")
+        .map_err(|e| format!("Failed to run integrity check: {}", e))?;
+    let integrity_messages = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Failed to run integrity check: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read integrity check: {}", e))?;
+    drop(stmt);
+    let integrity_ok = integrity_messages.len() == 1 && integrity_messages[0] == "ok";
+
+    let mut vacuumed = false;
+    let mut analyzed = false;
+
+    if integrity_ok {
+        emit_maintenance_progress(&app, "vacuum", "Compacting database (VACUUM)...", 33.0);
+        conn.execute_batch("VACUUM;")
+            .map_err(|e| format!("VACUUM failed: {}", e))?;
+        vacuumed = true;
+
+        emit_maintenance_progress(&app, "analyze", "Updating query statistics (ANALYZE)...", 66.0);
+        conn.execute_batch("ANALYZE;")
+            .map_err(|e| format!("ANALYZE failed: {}", e))?;
+        analyzed = true;
+
+        let _ = conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);");
+    } else {
+        log::warn!("Integrity check reported problems: {:?}", integrity_messages);
+    }
+
+    let (size_after, wal_after) = file_sizes(&path);
+    let result = MaintenanceResult {
+        integrity_ok,
+        integrity_messages,
+        vacuumed,
+        analyzed,
+        size_before_bytes,
+        size_after_bytes: size_after + wal_after,
+        duration_ms: started.elapsed().as_millis(),
+    };
+
+    log::info!(
+        "Database maintenance finished: integrity_ok={}, {} -> {} bytes in {} ms",
+        result.integrity_ok,
+        result.size_before_bytes,
+        result.size_after_bytes,
+        result.duration_ms
+    );
+    emit_maintenance_progress(&app, "done", "Maintenance complete", 100.0);
+    let _ = app.emit("database-maintenance-complete", result.clone());
+
+    Ok(result)
+}
+
+/// A child-to-parent relationship checked by find_orphaned_rows
+struct OrphanCheck {
+    table: &'static str,
+    description: &'static str,
+    /// WHERE clause selecting the orphaned rows
+    condition: &'static str,
+    /// Column cleared on repair; None deletes the rows instead
+    nullable_column: Option<&'static str>,
+}
+
+const ORPHAN_CHECKS: &[OrphanCheck] = &[
+    OrphanCheck {
+        table: "supplier_payments",
+        description: "Supplier payments without a supplier",
+        condition: "supplier_id NOT IN (SELECT id FROM suppliers)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "supplier_payments",
+        description: "Supplier payments for a deleted product",
+        condition: "product_id IS NOT NULL AND product_id NOT IN (SELECT id FROM products)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "supplier_payments",
+        description: "Supplier payments linked to a deleted purchase order",
+        condition: "po_id IS NOT NULL AND po_id NOT IN (SELECT id FROM purchase_orders)",
+        nullable_column: Some("po_id"),
+    },
+    OrphanCheck {
+        table: "customer_payments",
+        description: "Customer payments without an invoice or customer",
+        condition: "invoice_id NOT IN (SELECT id FROM invoices) OR customer_id NOT IN (SELECT id FROM customers)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "invoice_items",
+        description: "Invoice items without an invoice",
+        condition: "invoice_id NOT IN (SELECT id FROM invoices)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "invoice_tenders",
+        description: "Split payment parts without an invoice",
+        condition: "invoice_id NOT IN (SELECT id FROM invoices)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "invoice_item_components",
+        description: "Bundle component records without an invoice item",
+        condition: "invoice_item_id NOT IN (SELECT id FROM invoice_items)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "purchase_order_items",
+        description: "Purchase order items without a purchase order",
+        condition: "po_id NOT IN (SELECT id FROM purchase_orders)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "inventory_batches",
+        description: "Inventory batches without a product",
+        condition: "product_id NOT IN (SELECT id FROM products)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "inventory_batches",
+        description: "Inventory batches linked to a deleted purchase order item",
+        condition: "po_item_id IS NOT NULL AND po_item_id NOT IN (SELECT id FROM purchase_order_items)",
+        nullable_column: Some("po_item_id"),
+    },
+    OrphanCheck {
+        table: "inventory_transactions",
+        description: "Inventory transactions without a product",
+        condition: "product_id NOT IN (SELECT id FROM products)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "products",
+        description: "Products linked to a deleted supplier",
+        condition: "supplier_id IS NOT NULL AND supplier_id NOT IN (SELECT id FROM suppliers)",
+        nullable_column: Some("supplier_id"),
+    },
+    OrphanCheck {
+        table: "customers",
+        description: "Customers linked to a deleted price tier",
+        condition: "price_tier_id IS NOT NULL AND price_tier_id NOT IN (SELECT id FROM price_tiers)",
+        nullable_column: Some("price_tier_id"),
+    },
+    OrphanCheck {
+        table: "price_tier_items",
+        description: "Tier prices without a tier or product",
+        condition: "tier_id NOT IN (SELECT id FROM price_tiers) OR product_id NOT IN (SELECT id FROM products)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "bundle_components",
+        description: "Bundle components without a bundle or component product",
+        condition: "bundle_id NOT IN (SELECT id FROM products) OR component_id NOT IN (SELECT id FROM products)",
+        nullable_column: None,
+    },
+    OrphanCheck {
+        table: "product_suppliers",
+        description: "Product supplier links without a product or supplier",
+        condition: "product_id NOT IN (SELECT id FROM products) OR supplier_id NOT IN (SELECT id FROM suppliers)",
+        nullable_column: None,
+    },
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrphanedRows {
+    pub table: String,
+    pub description: String,
+    pub count: i64,
+    /// "delete" or "unlink" (the reference is cleared and the row kept)
+    pub repair_action: String,
+    pub repaired: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrphanReport {
+    pub checks: Vec<OrphanedRows>,
+    pub total_orphans: i64,
+    pub repaired: bool,
+}
+
+pub(crate) fn find_orphaned_rows_internal(conn: &mut Connection, repair: bool) -> Result<OrphanReport, String> {
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut checks = Vec::new();
+    for check in ORPHAN_CHECKS {
+        let count: i64 = tx
+            .query_row(
+                &format!("SELECT COUNT(*) FROM {} WHERE {}", check.table, check.condition),
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to check {}: {}", check.table, e))?;
+
+        let repaired = repair && count > 0;
+        if repaired {
+            let sql = match check.nullable_column {
+                Some(column) => format!("UPDATE {} SET {} = NULL WHERE {}", check.table, column, check.condition),
+                None => format!("DELETE FROM {} WHERE {}", check.table, check.condition),
+            };
+            tx.execute(&sql, [])
+                .map_err(|e| format!("Failed to repair {}: {}", check.table, e))?;
+            log::info!("Repaired {} orphaned rows in {}: {}", count, check.table, check.description);
+        }
+
+        checks.push(OrphanedRows {
+            table: check.table.to_string(),
+            description: check.description.to_string(),
+            count,
+            repair_action: if check.nullable_column.is_some() { "unlink" } else { "delete" }.to_string(),
+            repaired,
+        });
+    }
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(OrphanReport {
+        total_orphans: checks.iter().map(|c| c.count).sum(),
+        repaired: repair,
+        checks,
+    })
+}
+
+/// Report rows whose parent row no longer exists (payments without suppliers, items without
+/// invoices, batches without products, ...). With `repair`, orphans are deleted, or unlinked
+/// where the row is still meaningful on its own.
+#[tauri::command]
+pub fn find_orphaned_rows(
+    repair: Option<bool>,
+    maintenance: State<MaintenanceState>,
+    db: State<Database>,
+) -> Result<OrphanReport, String> {
+    let repair = repair.unwrap_or(false);
+    log::info!("find_orphaned_rows called (repair: {})", repair);
+    if repair {
+        maintenance.ensure_writable()?;
+    }
+
+    let mut conn = db.get_conn()?;
+    find_orphaned_rows_internal(&mut conn, repair)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_supplier, TestDb};
+
+    #[test]
+    fn test_maintenance_lock_blocks_writes_and_releases() {
+        let state = MaintenanceState::default();
+        assert!(state.check_command_allowed("create_invoice").is_ok());
+
+        {
+            let _outer = state.acquire(MaintenanceOperation::CsvImport).unwrap();
+            // Reentrant for the owner, refused for anyone else
+            let inner = state.acquire(MaintenanceOperation::CsvImport).unwrap();
+            assert!(state.acquire(MaintenanceOperation::DatabaseMaintenance).is_err());
+            drop(inner);
+
+            let err = state.check_command_allowed("create_invoice").unwrap_err();
+            assert_eq!(err, format!("{}csv_import", MAINTENANCE_IN_PROGRESS));
+            assert!(state.check_command_allowed("get_invoices").is_ok());
+            assert!(state.check_command_allowed("import_products_csv").is_ok());
+            assert!(state.check_command_allowed("import_settings_json").is_err());
+            assert!(state.status().locked);
+
+            // A chunked import keeps the lock between calls
+            state.hold_session(MaintenanceOperation::CsvImport, Duration::from_secs(60));
+        }
+        assert!(state.ensure_writable().is_err());
+        state.end_session(MaintenanceOperation::CsvImport);
+        assert!(state.ensure_writable().is_ok());
+        assert!(!state.status().locked);
+
+        // A panicking owner releases the lock, session included
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _lock = state.acquire(MaintenanceOperation::CsvImport).unwrap();
+            state.hold_session(MaintenanceOperation::CsvImport, Duration::from_secs(60));
+            panic!("import failed");
+        }));
+        assert!(result.is_err());
+        assert!(state.check_command_allowed("create_invoice").is_ok());
+    }
+
+    /// Command names registered in the invoke handler in lib.rs
+    fn registered_commands() -> Vec<&'static str> {
+        let source = include_str!("../lib.rs");
+        let start = source.find("generate_handler![").expect("invoke handler not found");
+        let end = start + source[start..].find("];").expect("invoke handler not closed");
+        source[start..end]
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("commands::")?.strip_suffix(','))
+            .map(|path| path.rsplit("::").next().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_maintenance_lock_blocks_every_registered_writer() {
+        let registered = registered_commands();
+        assert!(registered.len() > 100);
+        for command in READ_ONLY_COMMANDS {
+            assert!(
+                registered.contains(command) || ["get_product_by_sku", "get_product_by_barcode", "get_stock"].contains(command),
+                "{} is not a registered command",
+                command
+            );
+        }
+
+        let state = MaintenanceState::default();
+        let _lock = state.acquire(MaintenanceOperation::DatabaseMaintenance).unwrap();
+        for command in registered {
+            let allowed = state.check_command_allowed(command).is_ok();
+            let expected = READ_ONLY_COMMANDS.contains(&command) || command == "run_database_maintenance";
+            assert_eq!(allowed, expected, "{} while the lock is held", command);
+        }
+
+        // Writers without a create_/update_-style name
+        for command in [
+            "disable_biometric",
+            "generate_biometric_token",
+            "verify_biometric_token",
+            "verify_action_biometric",
+            "download_product_image",
+            "login",
+            "logout",
+            "unlock_ai_data",
+            "get_ai_context_snapshot",
+            "run_ai_sql_readonly",
+            "get_sales_analytics",
+            "get_revenue_trend",
+            "get_sales_by_payment_method",
+            "get_profit_and_loss",
+            "export_analytics_report",
+            "get_all_modifications",
+            "get_entity_timeline",
+            "some_future_command",
+        ] {
+            assert!(state.check_command_allowed(command).is_err(), "{} must be blocked", command);
+        }
+    }
+
+    #[test]
+    fn test_find_and_repair_orphaned_rows() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme");
+
+        // Rows left behind by databases that ran without foreign keys
+        conn.execute_batch("PRAGMA foreign_keys = OFF").unwrap();
+        conn.execute_batch(&format!(
+            "INSERT INTO products (id, name, sku, price, stock_quantity, supplier_id) VALUES (1, 'Pen', 'PEN', 10, 0, 99);
+             INSERT INTO supplier_payments (supplier_id, amount) VALUES (99, 50);
+             INSERT INTO supplier_payments (supplier_id, amount) VALUES ({}, 25);
+             INSERT INTO invoice_items (invoice_id, product_id, quantity, unit_price) VALUES (42, 1, 1, 10);",
+            supplier_id
+        ))
+        .unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+
+        let report = find_orphaned_rows_internal(&mut conn, false).unwrap();
+        assert_eq!(report.total_orphans, 3);
+        assert!(report.checks.iter().all(|c| !c.repaired));
+
+        let report = find_orphaned_rows_internal(&mut conn, true).unwrap();
+        assert!(report.repaired);
+        assert_eq!(find_orphaned_rows_internal(&mut conn, false).unwrap().total_orphans, 0);
+
+        // Unlinked products are kept, orphaned payments and items are removed
+        let supplier: Option<i32> = conn.query_row("SELECT supplier_id FROM products WHERE id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(supplier, None);
+        let payments: i32 = conn.query_row("SELECT COUNT(*) FROM supplier_payments", [], |row| row.get(0)).unwrap();
+        assert_eq!(payments, 1);
+    }
+}
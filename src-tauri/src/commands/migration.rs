@@ -1,13 +1,52 @@
 /// Data Migration Commands
-/// Migrates existing products with initial_stock to the new Purchase Order and FIFO system
+/// Migrates existing products with initial_stock to the new Purchase Order and FIFO system.
+/// Products are migrated a chunk per transaction and the id of the last product committed is
+/// kept in app_settings, so a run cut short by a crash or the app closing picks up after it
+/// when started again instead of starting over.
 
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::db::{migrations, Database};
+use crate::services::{inventory_service, quantity};
+
+/// Products migrated or repaired per transaction unless the caller asks for another size
+const DEFAULT_CHUNK_SIZE: i32 = 200;
+/// app_settings key holding the id of the last product an unfinished migration run committed
+const MIGRATION_CURSOR_KEY: &str = "product_migration_cursor";
+/// Event emitted as a migration, validation or repair run makes progress
+pub const MIGRATION_PROGRESS_EVENT: &str = "product-migration-progress";
+
+/// Products with stock but no batches yet, past the cursor bound to ?1
+const PENDING_PRODUCTS_SQL: &str = "FROM products p
+     WHERE p.stock_quantity > 0
+     AND p.id > ?1
+     AND NOT EXISTS (
+         SELECT 1 FROM inventory_batches ib WHERE ib.product_id = p.id
+     )";
+
+/// Payload of MIGRATION_PROGRESS_EVENT
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationProgress {
+    /// "migrating", "validating" or "repairing"
+    pub phase: String,
+    pub processed: i32,
+    pub total: i32,
+    pub percentage: f32,
+}
 
-use crate::db::Database;
-use crate::services::inventory_service;
+impl MigrationProgress {
+    fn new(phase: &str, processed: i32, total: i32) -> Self {
+        MigrationProgress {
+            phase: phase.to_string(),
+            processed,
+            total,
+            percentage: if total > 0 { (processed as f32 / total as f32) * 100.0 } else { 100.0 },
+        }
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MigrationResult {
@@ -17,33 +56,92 @@ pub struct MigrationResult {
     pub transactions_created: i32,
     pub errors: Vec<String>,
     pub details: Vec<String>,
+    /// Last product committed by the interrupted run this one continued; None for a fresh run
+    pub resumed_after: Option<i32>,
 }
 
-/// Migrate existing products with initial_stock to Purchase Order system
-#[tauri::command]
-pub fn migrate_existing_products(db: State<Database>) -> Result<MigrationResult, String> {
-    let conn = db.get_conn()?;
+/// A product with stock still waiting for its migration PO and batch
+struct PendingProduct {
+    id: i32,
+    name: String,
+    sku: String,
+    stock_quantity: f64,
+    initial_stock: Option<f64>,
+    price: f64,
+    supplier_id: Option<i32>,
+}
+
+fn emit_migration_progress(app: &AppHandle, progress: &MigrationProgress) {
+    let _ = app.emit(MIGRATION_PROGRESS_EVENT, progress);
+}
+
+fn resolve_chunk_size(chunk_size: Option<i32>) -> Result<i32, String> {
+    match chunk_size {
+        None => Ok(DEFAULT_CHUNK_SIZE),
+        Some(size) if size > 0 => Ok(size),
+        Some(_) => Err("Validation error: chunk_size must be positive".to_string()),
+    }
+}
+
+fn read_migration_cursor(conn: &Connection) -> Result<Option<i32>, String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        [MIGRATION_CURSOR_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .map(|value| value.and_then(|v| v.trim().parse().ok()))
+    .map_err(|e| format!("Failed to read migration cursor: {}", e))
+}
 
-    // Start transaction
-    conn.execute("BEGIN TRANSACTION", [])
-        .map_err(|e| format!("Failed to begin transaction: {}", e))?;
+fn save_migration_cursor(conn: &Connection, product_id: i32) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))
+         ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = datetime('now')",
+        params![MIGRATION_CURSOR_KEY, product_id.to_string()],
+    )
+    .map_err(|e| format!("Failed to save migration cursor: {}", e))?;
+    Ok(())
+}
 
-    let result = migrate_existing_products_internal(&conn);
+fn clear_migration_cursor(conn: &Connection) -> Result<(), String> {
+    conn.execute("DELETE FROM app_settings WHERE key = ?1", [MIGRATION_CURSOR_KEY])
+        .map_err(|e| format!("Failed to clear migration cursor: {}", e))?;
+    Ok(())
+}
 
-    match result {
-        Ok(migration_result) => {
-            conn.execute("COMMIT", [])
-                .map_err(|e| format!("Failed to commit transaction: {}", e))?;
-            Ok(migration_result)
-        }
-        Err(e) => {
-            conn.execute("ROLLBACK", []).ok();
-            Err(e)
-        }
+/// Migrate existing products with initial_stock to Purchase Order system, `chunk_size`
+/// products per transaction (default 200). An interrupted run is continued unless `restart`
+/// is set. Emits MIGRATION_PROGRESS_EVENT after every chunk.
+#[tauri::command]
+pub async fn migrate_existing_products(
+    chunk_size: Option<i32>,
+    restart: Option<bool>,
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<MigrationResult, String> {
+    log::info!(
+        "migrate_existing_products called - chunk_size: {:?}, restart: {:?}",
+        chunk_size,
+        restart
+    );
+
+    let chunk_size = resolve_chunk_size(chunk_size)?;
+    let mut conn = db.get_conn()?;
+    if restart.unwrap_or(false) {
+        clear_migration_cursor(&conn)?;
     }
+
+    migrate_existing_products_internal(&mut conn, chunk_size, |progress| emit_migration_progress(&app, progress))
 }
 
-fn migrate_existing_products_internal(conn: &Connection) -> Result<MigrationResult, String> {
+/// `on_progress` runs once before the first chunk and after each committed chunk
+pub(crate) fn migrate_existing_products_internal(
+    conn: &mut Connection,
+    chunk_size: i32,
+    mut on_progress: impl FnMut(&MigrationProgress),
+) -> Result<MigrationResult, String> {
+    let resumed_after = read_migration_cursor(conn)?;
     let mut result = MigrationResult {
         products_migrated: 0,
         purchase_orders_created: 0,
@@ -51,73 +149,111 @@ fn migrate_existing_products_internal(conn: &Connection) -> Result<MigrationResu
         transactions_created: 0,
         errors: Vec::new(),
         details: Vec::new(),
+        resumed_after,
     };
 
     let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
     let migration_date = Utc::now().format("%Y-%m-%d").to_string();
 
-    // Get all products that have stock but no batches yet
-    let mut stmt = conn.prepare(
-        "SELECT p.id, p.name, p.sku, p.stock_quantity, p.initial_stock, p.price, p.supplier_id
-         FROM products p
-         WHERE p.stock_quantity > 0
-         AND NOT EXISTS (
-             SELECT 1 FROM inventory_batches ib WHERE ib.product_id = p.id
-         )"
-    ).map_err(|e| format!("Failed to prepare product query: {}", e))?;
-
-    let products: Vec<(i32, String, String, i32, Option<i32>, f64, Option<i32>)> = stmt
-        .query_map([], |row| {
-            Ok((
-                row.get(0)?,
-                row.get(1)?,
-                row.get(2)?,
-                row.get(3)?,
-                row.get(4)?,
-                row.get(5)?,
-                row.get(6)?,
-            ))
-        })
-        .map_err(|e| format!("Failed to query products: {}", e))?
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("Failed to collect products: {}", e))?;
-
-    result.details.push(format!("Found {} products to migrate", products.len()));
+    let mut last_id = resumed_after.unwrap_or(0);
+    let total: i32 = conn
+        .query_row(&format!("SELECT COUNT(*) {}", PENDING_PRODUCTS_SQL), [last_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to count products: {}", e))?;
+
+    match resumed_after {
+        Some(product_id) => result.details.push(format!(
+            "Resuming after product {}: {} products left to migrate",
+            product_id, total
+        )),
+        None => result.details.push(format!("Found {} products to migrate", total)),
+    }
 
     // Create a "Migration" supplier if it doesn't exist
     let migration_supplier_id = ensure_migration_supplier(conn)?;
     result.details.push(format!("Using migration supplier ID: {}", migration_supplier_id));
 
-    for (product_id, name, sku, stock_qty, initial_stock, price, supplier_id) in products {
-        match migrate_product(
-            conn,
-            product_id,
-            &name,
-            &sku,
-            stock_qty,
-            initial_stock,
-            price,
-            supplier_id,
-            migration_supplier_id,
-            &migration_date,
-            &now,
-        ) {
-            Ok(detail) => {
-                result.products_migrated += 1;
-                result.purchase_orders_created += 1;
-                result.batches_created += 1;
-                result.transactions_created += 1;
-                result.details.push(detail);
-            }
-            Err(e) => {
-                result.errors.push(format!("Product {} ({}): {}", product_id, name, e));
+    let mut processed = 0;
+    on_progress(&MigrationProgress::new("migrating", processed, total));
+
+    loop {
+        let mut tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+        let products = pending_products(&tx, last_id, chunk_size)?;
+        if products.is_empty() {
+            break;
+        }
+
+        for product in products {
+            // A product that fails rolls back on its own; the rest of the chunk still commits
+            let savepoint = tx
+                .savepoint()
+                .map_err(|e| format!("Failed to start savepoint: {}", e))?;
+            match migrate_product(&savepoint, &product, migration_supplier_id, &migration_date, &now) {
+                Ok(detail) => {
+                    savepoint
+                        .commit()
+                        .map_err(|e| format!("Failed to commit savepoint: {}", e))?;
+                    result.products_migrated += 1;
+                    result.purchase_orders_created += 1;
+                    result.batches_created += 1;
+                    result.transactions_created += 1;
+                    result.details.push(detail);
+                }
+                Err(e) => {
+                    result.errors.push(format!("Product {} ({}): {}", product.id, product.name, e));
+                }
             }
+            last_id = product.id;
+            processed += 1;
         }
+
+        // Saved with the chunk, so a rerun never sees the cursor without the chunk or the other way round
+        save_migration_cursor(&tx, last_id)?;
+        tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        on_progress(&MigrationProgress::new("migrating", processed, total));
     }
 
+    clear_migration_cursor(conn)?;
+    log::info!(
+        "Migrated {} products, {} errors",
+        result.products_migrated,
+        result.errors.len()
+    );
     Ok(result)
 }
 
+/// The next `limit` products waiting for migration after `after_id`, in id order
+fn pending_products(conn: &Connection, after_id: i32, limit: i32) -> Result<Vec<PendingProduct>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT p.id, p.name, p.sku, p.stock_quantity, p.initial_stock, p.price, p.supplier_id
+             {}
+             ORDER BY p.id
+             LIMIT ?2",
+            PENDING_PRODUCTS_SQL
+        ))
+        .map_err(|e| format!("Failed to prepare product query: {}", e))?;
+
+    let products = stmt
+        .query_map(params![after_id, limit], |row| {
+            Ok(PendingProduct {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                stock_quantity: row.get(3)?,
+                initial_stock: row.get(4)?,
+                price: row.get(5)?,
+                supplier_id: row.get(6)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query products: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect products: {}", e))?;
+
+    Ok(products)
+}
+
 fn ensure_migration_supplier(conn: &Connection) -> Result<i32, String> {
     // Check if migration supplier exists
     let existing: Option<i32> = conn
@@ -151,25 +287,19 @@ fn ensure_migration_supplier(conn: &Connection) -> Result<i32, String> {
 
 fn migrate_product(
     conn: &Connection,
-    product_id: i32,
-    name: &str,
-    sku: &str,
-    stock_qty: i32,
-    initial_stock: Option<i32>,
-    price: f64,
-    supplier_id: Option<i32>,
+    product: &PendingProduct,
     migration_supplier_id: i32,
     migration_date: &str,
     now: &str,
 ) -> Result<String, String> {
-    let quantity = initial_stock.unwrap_or(stock_qty);
-    let unit_cost = price; // Use the current price as historical cost
+    let quantity = product.initial_stock.unwrap_or(product.stock_quantity);
+    let unit_cost = product.price; // Use the current price as historical cost
 
     // Use product's supplier if available, otherwise use migration supplier
-    let po_supplier_id = supplier_id.unwrap_or(migration_supplier_id);
+    let po_supplier_id = product.supplier_id.unwrap_or(migration_supplier_id);
 
     // Generate migration PO number
-    let po_number = format!("PO-MIGRATED-{:06}", product_id);
+    let po_number = migration_po_number(product.id);
 
     // Check if this PO already exists (prevent duplicate migration)
     let existing: Option<i32> = conn
@@ -184,7 +314,7 @@ fn migrate_product(
         return Err(format!("Already migrated (PO {} exists)", po_number));
     }
 
-    let total_amount = quantity as f64 * unit_cost;
+    let total_amount = quantity * unit_cost;
 
     // Create purchase order
     conn.execute(
@@ -197,7 +327,7 @@ fn migrate_product(
             migration_date,
             migration_date,
             total_amount,
-            format!("Auto-migrated from existing stock. Original SKU: {}", sku),
+            format!("Auto-migrated from existing stock. Original SKU: {}", product.sku),
             now,
             now,
         ],
@@ -209,9 +339,9 @@ fn migrate_product(
     // Create PO item
     conn.execute(
         "INSERT INTO purchase_order_items
-         (po_id, product_id, quantity, unit_cost, total_cost, created_at)
-         VALUES (?, ?, ?, ?, ?, ?)",
-        params![po_id, product_id, quantity, unit_cost, total_amount, now],
+         (po_id, product_id, quantity, received_quantity, unit_cost, total_cost, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![po_id, product.id, quantity, quantity, unit_cost, total_amount, now],
     )
     .map_err(|e| format!("Failed to create PO item: {}", e))?;
 
@@ -220,7 +350,7 @@ fn migrate_product(
     // Create inventory batch using inventory service
     inventory_service::record_purchase(
         conn,
-        product_id,
+        product.id,
         quantity,
         unit_cost,
         Some(po_item_id),
@@ -229,29 +359,36 @@ fn migrate_product(
     .map_err(|e| format!("Failed to create batch: {}", e))?;
 
     // Verify batch matches stock
-    let batch_total: i32 = conn
-        .query_row(
-            "SELECT COALESCE(SUM(quantity_remaining), 0)
-             FROM inventory_batches
-             WHERE product_id = ?",
-            params![product_id],
-            |row| row.get(0),
-        )
-        .unwrap_or(0);
-
-    if batch_total != stock_qty {
+    let batch_total = batch_total(conn, product.id)?;
+    if quantity::to_milli(batch_total) != quantity::to_milli(product.stock_quantity) {
         return Err(format!(
             "Batch total ({}) doesn't match stock quantity ({})",
-            batch_total, stock_qty
+            batch_total, product.stock_quantity
         ));
     }
 
     Ok(format!(
         "✓ {} ({}) - {} units @ ₹{:.2} = ₹{:.2} → {}",
-        name, sku, quantity, unit_cost, total_amount, po_number
+        product.name, product.sku, quantity, unit_cost, total_amount, po_number
     ))
 }
 
+fn migration_po_number(product_id: i32) -> String {
+    format!("PO-MIGRATED-{:06}", product_id)
+}
+
+/// Units left in a product's batches
+fn batch_total(conn: &Connection, product_id: i32) -> Result<f64, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(quantity_remaining), 0)
+         FROM inventory_batches
+         WHERE product_id = ?",
+        params![product_id],
+        |row| row.get(0).map(quantity::round_quantity),
+    )
+    .map_err(|e| format!("Failed to get batch total: {}", e))
+}
+
 /// Check migration status - see which products need migration
 #[tauri::command]
 pub fn check_migration_status(db: State<Database>) -> Result<MigrationStatus, String> {
@@ -259,16 +396,7 @@ pub fn check_migration_status(db: State<Database>) -> Result<MigrationStatus, St
 
     // Count products with stock but no batches
     let needs_migration: i32 = conn
-        .query_row(
-            "SELECT COUNT(*)
-             FROM products p
-             WHERE p.stock_quantity > 0
-             AND NOT EXISTS (
-                 SELECT 1 FROM inventory_batches ib WHERE ib.product_id = p.id
-             )",
-            [],
-            |row| row.get(0),
-        )
+        .query_row(&format!("SELECT COUNT(*) {}", PENDING_PRODUCTS_SQL), [0], |row| row.get(0))
         .unwrap_or(0);
 
     // Count products with batches
@@ -301,6 +429,7 @@ pub fn check_migration_status(db: State<Database>) -> Result<MigrationStatus, St
         products_needing_migration: needs_migration,
         migration_supplier_exists,
         migration_required: needs_migration > 0,
+        resume_after: read_migration_cursor(&conn)?,
     })
 }
 
@@ -311,62 +440,143 @@ pub struct MigrationStatus {
     pub products_needing_migration: i32,
     pub migration_supplier_exists: bool,
     pub migration_required: bool,
+    /// Set while an interrupted run is waiting to be continued: the last product it committed
+    pub resume_after: Option<i32>,
 }
 
-/// Validate data consistency after migration
+/// Validate data consistency after migration: every product with stock or batches left,
+/// with its stock quantity against the units in its batches. The report's inconsistent
+/// products can be handed to repair_migration.
 #[tauri::command]
-pub fn validate_migration(db: State<Database>) -> Result<ValidationResult, String> {
-    let conn = db.get_conn()?;
+pub async fn validate_migration(app: AppHandle, db: State<'_, Database>) -> Result<ValidationResult, String> {
+    log::info!("validate_migration called");
 
-    let mut result = ValidationResult {
-        total_products_checked: 0,
-        consistent_products: 0,
-        inconsistent_products: Vec::new(),
-    };
+    let conn = db.get_conn()?;
+    validate_migration_internal(&conn, |progress| emit_migration_progress(&app, progress))
+}
 
-    // Get all products with stock
+pub(crate) fn validate_migration_internal(
+    conn: &Connection,
+    mut on_progress: impl FnMut(&MigrationProgress),
+) -> Result<ValidationResult, String> {
     let mut stmt = conn
         .prepare(
-            "SELECT id, name, sku, stock_quantity
-             FROM products
-             WHERE stock_quantity > 0",
+            "SELECT p.id, p.name, p.sku, p.stock_quantity,
+                    COALESCE(SUM(ib.quantity_remaining), 0), COUNT(ib.id)
+             FROM products p
+             LEFT JOIN inventory_batches ib ON ib.product_id = p.id
+             GROUP BY p.id
+             HAVING p.stock_quantity > 0 OR COALESCE(SUM(ib.quantity_remaining), 0) > 0
+             ORDER BY p.id",
         )
         .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let products: Vec<(i32, String, String, i32)> = stmt
-        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+    let products: Vec<ProductStockCheck> = stmt
+        .query_map([], |row| {
+            Ok(ProductStockCheck {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                stock_quantity: row.get(3)?,
+                batch_total: row.get::<_, f64>(4).map(quantity::round_quantity)?,
+                batch_count: row.get(5)?,
+            })
+        })
         .map_err(|e| format!("Failed to query products: {}", e))?
         .collect::<Result<Vec<_>, _>>()
         .map_err(|e| format!("Failed to collect products: {}", e))?;
 
-    result.total_products_checked = products.len() as i32;
+    let total = products.len() as i32;
+    let mut result = ValidationResult {
+        total_products_checked: total,
+        consistent_products: 0,
+        inconsistent_products: Vec::new(),
+        checked_at: Utc::now().to_rfc3339(),
+    };
 
-    for (id, name, sku, stock_qty) in products {
-        let batch_total: i32 = conn
+    for (index, product) in products.into_iter().enumerate() {
+        match product.into_inconsistency() {
+            Some(inconsistent) => result.inconsistent_products.push(inconsistent),
+            None => result.consistent_products += 1,
+        }
+        let processed = index as i32 + 1;
+        if processed % DEFAULT_CHUNK_SIZE == 0 || processed == total {
+            on_progress(&MigrationProgress::new("validating", processed, total));
+        }
+    }
+
+    Ok(result)
+}
+
+/// A product's stock quantity next to the units left in its batches
+struct ProductStockCheck {
+    id: i32,
+    name: String,
+    sku: String,
+    stock_quantity: f64,
+    batch_total: f64,
+    batch_count: i32,
+}
+
+impl ProductStockCheck {
+    fn load(conn: &Connection, product_id: i32) -> Result<Self, String> {
+        let (name, sku, stock_quantity) = conn
             .query_row(
-                "SELECT COALESCE(SUM(quantity_remaining), 0)
-                 FROM inventory_batches
-                 WHERE product_id = ?",
-                params![id],
+                "SELECT name, sku, stock_quantity FROM products WHERE id = ?",
+                params![product_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|e| format!("Product {} not found: {}", product_id, e))?;
+        let batch_count = conn
+            .query_row(
+                "SELECT COUNT(*) FROM inventory_batches WHERE product_id = ?",
+                params![product_id],
                 |row| row.get(0),
             )
-            .unwrap_or(0);
+            .map_err(|e| format!("Failed to count batches: {}", e))?;
+
+        Ok(ProductStockCheck {
+            id: product_id,
+            name,
+            sku,
+            stock_quantity,
+            batch_total: batch_total(conn, product_id)?,
+            batch_count,
+        })
+    }
 
-        if stock_qty == batch_total {
-            result.consistent_products += 1;
-        } else {
-            result.inconsistent_products.push(InconsistentProduct {
-                id,
-                name,
-                sku,
-                stock_quantity: stock_qty,
-                batch_total,
-                difference: stock_qty - batch_total,
-            });
-        }
+    /// Units the batches should hold: the stock quantity, or nothing when stock is negative
+    fn expected_batch_total(&self) -> f64 {
+        quantity::round_quantity(self.stock_quantity.max(0.0))
     }
 
-    Ok(result)
+    /// None when the batches match the stock quantity
+    fn into_inconsistency(self) -> Option<InconsistentProduct> {
+        let expected = self.expected_batch_total();
+        let difference = quantity::sub(expected, self.batch_total);
+        if quantity::to_milli(difference) == 0 {
+            return None;
+        }
+        let issue = if self.batch_count == 0 {
+            "missing_batches"
+        } else if difference > 0.0 {
+            "batch_shortfall"
+        } else {
+            "batch_excess"
+        };
+
+        Some(InconsistentProduct {
+            id: self.id,
+            name: self.name,
+            sku: self.sku,
+            stock_quantity: self.stock_quantity,
+            expected_batch_total: expected,
+            batch_total: self.batch_total,
+            batch_count: self.batch_count,
+            difference,
+            issue: issue.to_string(),
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -374,6 +584,8 @@ pub struct ValidationResult {
     pub total_products_checked: i32,
     pub consistent_products: i32,
     pub inconsistent_products: Vec<InconsistentProduct>,
+    /// RFC 3339 time the report was taken
+    pub checked_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -381,7 +593,349 @@ pub struct InconsistentProduct {
     pub id: i32,
     pub name: String,
     pub sku: String,
-    pub stock_quantity: i32,
-    pub batch_total: i32,
-    pub difference: i32,
+    pub stock_quantity: f64,
+    /// Units the batches should hold (the stock quantity, 0 for negative stock)
+    pub expected_batch_total: f64,
+    pub batch_total: f64,
+    pub batch_count: i32,
+    /// expected_batch_total - batch_total
+    pub difference: f64,
+    /// "missing_batches", "batch_shortfall" or "batch_excess"
+    pub issue: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RepairedProduct {
+    pub id: i32,
+    pub name: String,
+    pub issue: String,
+    pub batch_total_before: f64,
+    pub batch_total_after: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RepairResult {
+    pub repaired: Vec<RepairedProduct>,
+    /// Products that were already consistent when the repair reached them
+    pub already_consistent: Vec<i32>,
+    pub errors: Vec<String>,
+}
+
+/// Bring the batches of the given products (the inconsistent_products of a validate_migration
+/// report) in line with their stock quantity, which is taken as correct. Each product is
+/// checked again first, so a stale report only repairs what is still wrong. Missing batches
+/// are migrated as usual, a shortfall gets a batch at the product's average cost and an
+/// excess is taken off the newest batches. Emits MIGRATION_PROGRESS_EVENT after every chunk.
+#[tauri::command]
+pub async fn repair_migration(
+    product_ids: Vec<i32>,
+    repaired_by: Option<String>,
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<RepairResult, String> {
+    log::info!("repair_migration called for {} products", product_ids.len());
+
+    let mut conn = db.get_conn()?;
+    repair_migration_internal(&mut conn, &product_ids, &repaired_by, DEFAULT_CHUNK_SIZE, |progress| {
+        emit_migration_progress(&app, progress)
+    })
+}
+
+pub(crate) fn repair_migration_internal(
+    conn: &mut Connection,
+    product_ids: &[i32],
+    repaired_by: &Option<String>,
+    chunk_size: i32,
+    mut on_progress: impl FnMut(&MigrationProgress),
+) -> Result<RepairResult, String> {
+    let mut result = RepairResult {
+        repaired: Vec::new(),
+        already_consistent: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    let total = product_ids.len() as i32;
+    let mut processed = 0;
+    let migration_supplier_id = ensure_migration_supplier(conn)?;
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let repair_date = Utc::now().format("%Y-%m-%d").to_string();
+
+    for chunk in product_ids.chunks(chunk_size.max(1) as usize) {
+        let mut tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        for &product_id in chunk {
+            let savepoint = tx
+                .savepoint()
+                .map_err(|e| format!("Failed to start savepoint: {}", e))?;
+            match repair_product(&savepoint, product_id, migration_supplier_id, &repair_date, &now, repaired_by) {
+                Ok(Some(repaired)) => {
+                    savepoint
+                        .commit()
+                        .map_err(|e| format!("Failed to commit savepoint: {}", e))?;
+                    result.repaired.push(repaired);
+                }
+                Ok(None) => result.already_consistent.push(product_id),
+                Err(e) => result.errors.push(format!("Product {}: {}", product_id, e)),
+            }
+        }
+
+        tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+        processed += chunk.len() as i32;
+        on_progress(&MigrationProgress::new("repairing", processed, total));
+    }
+
+    log::info!(
+        "Repaired batches of {} products, {} errors",
+        result.repaired.len(),
+        result.errors.len()
+    );
+    Ok(result)
+}
+
+/// Repair one product's batches; None when they already match its stock
+fn repair_product(
+    conn: &Connection,
+    product_id: i32,
+    migration_supplier_id: i32,
+    repair_date: &str,
+    now: &str,
+    repaired_by: &Option<String>,
+) -> Result<Option<RepairedProduct>, String> {
+    let check = ProductStockCheck::load(conn, product_id)?;
+    let batch_total_before = check.batch_total;
+    let Some(inconsistent) = check.into_inconsistency() else {
+        return Ok(None);
+    };
+
+    let po_exists: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM purchase_orders WHERE po_number = ?)",
+            params![migration_po_number(product_id)],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    if inconsistent.issue == "missing_batches" && !po_exists {
+        let (price, supplier_id) = conn
+            .query_row(
+                "SELECT price, supplier_id FROM products WHERE id = ?",
+                params![product_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| e.to_string())?;
+        // Migrate what is on hand now; initial_stock may be long out of date
+        let product = PendingProduct {
+            id: product_id,
+            name: inconsistent.name.clone(),
+            sku: inconsistent.sku.clone(),
+            stock_quantity: inconsistent.stock_quantity,
+            initial_stock: None,
+            price,
+            supplier_id,
+        };
+        migrate_product(conn, &product, migration_supplier_id, repair_date, now)?;
+    } else if inconsistent.difference > 0.0 {
+        let mut unit_cost = inventory_service::get_average_cost(conn, product_id)?;
+        if unit_cost <= 0.0 {
+            unit_cost = conn
+                .query_row("SELECT price FROM products WHERE id = ?", params![product_id], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+        }
+        // Stock itself is right, so only the batch is added (record_purchase would count the units twice)
+        conn.execute(
+            "INSERT INTO inventory_batches
+             (product_id, po_item_id, quantity_remaining, original_quantity, unit_cost, purchase_date, created_at)
+             VALUES (?, NULL, ?, ?, ?, ?, ?)",
+            params![product_id, inconsistent.difference, inconsistent.difference, unit_cost, repair_date, now],
+        )
+        .map_err(|e| format!("Failed to create batch: {}", e))?;
+    } else {
+        trim_newest_batches(conn, product_id, quantity::sub(0.0, inconsistent.difference))?;
+    }
+
+    let batch_total_after = batch_total(conn, product_id)?;
+    let field_changes = serde_json::json!([{
+        "field": "batch_total",
+        "old": batch_total_before,
+        "new": batch_total_after,
+    }]);
+    conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by)
+         VALUES ('product', ?1, ?2, 'batches_repaired', ?3, ?4)",
+        params![product_id, inconsistent.name, field_changes.to_string(), repaired_by],
+    )
+    .map_err(|e| format!("Failed to log batch repair: {}", e))?;
+
+    Ok(Some(RepairedProduct {
+        id: product_id,
+        name: inconsistent.name,
+        issue: inconsistent.issue,
+        batch_total_before,
+        batch_total_after,
+    }))
+}
+
+/// Take `excess` units off a product's newest batches, deleting the ones left empty
+fn trim_newest_batches(conn: &Connection, product_id: i32, excess: f64) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, quantity_remaining FROM inventory_batches
+             WHERE product_id = ?
+             ORDER BY purchase_date DESC, id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let batches: Vec<(i32, f64)> = stmt
+        .query_map(params![product_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load batches: {}", e))?;
+
+    let mut left = excess;
+    for (batch_id, remaining) in batches {
+        if left <= 0.0 {
+            break;
+        }
+        let taken = remaining.min(left);
+        let updated = quantity::sub(remaining, taken);
+        if updated <= 0.0 {
+            conn.execute("DELETE FROM inventory_batches WHERE id = ?", params![batch_id])
+                .map_err(|e| format!("Failed to delete batch: {}", e))?;
+        } else {
+            conn.execute(
+                "UPDATE inventory_batches SET quantity_remaining = ? WHERE id = ?",
+                params![updated, batch_id],
+            )
+            .map_err(|e| format!("Failed to update batch: {}", e))?;
+        }
+        left = quantity::sub(left, taken);
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    pub version: i32,
+    pub description: String,
+    pub applied_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaVersionInfo {
+    pub current_version: i32,
+    pub latest_version: i32,
+    pub applied: Vec<AppliedMigration>,
+}
+
+/// Get the database schema version and the migrations applied so far
+#[tauri::command]
+pub fn get_schema_version(db: State<Database>) -> Result<SchemaVersionInfo, String> {
+    log::info!("get_schema_version called");
+
+    let conn = db.get_conn()?;
+
+    let current_version = migrations::current_version(&conn)
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+
+    let mut stmt = conn
+        .prepare("SELECT version, description, applied_at FROM schema_version ORDER BY version")
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+
+    let applied = stmt
+        .query_map([], |row| {
+            Ok(AppliedMigration {
+                version: row.get(0)?,
+                description: row.get(1)?,
+                applied_at: row.get(2)?,
+            })
+        })
+        .map_err(|e| format!("Failed to query migrations: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect migrations: {}", e))?;
+
+    Ok(SchemaVersionInfo {
+        current_version,
+        latest_version: migrations::latest_version(),
+        applied,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{batch_quantity, TestDb};
+
+    fn insert_product(conn: &Connection, sku: &str, stock: f64, initial_stock: Option<f64>) -> i32 {
+        conn.execute(
+            "INSERT INTO products (name, sku, price, stock_quantity, initial_stock) VALUES (?1, ?1, 10, ?2, ?3)",
+            params![sku, stock, initial_stock],
+        )
+        .unwrap();
+        conn.last_insert_rowid() as i32
+    }
+
+    #[test]
+    fn test_interrupted_migration_resumes_and_repair_fixes_mismatches() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        insert_product(&conn, "A", 4.0, None);
+        let b = insert_product(&conn, "B", 3.0, None);
+        let c = insert_product(&conn, "C", 4.0, None);
+        let d = insert_product(&conn, "D", 3.0, None);
+        let e = insert_product(&conn, "E", 5.0, Some(8.0));
+
+        // A run that committed the chunk ending at B before the app closed
+        migrate_existing_products_internal(&mut conn, 2, |_| {}).unwrap();
+        conn.execute("DELETE FROM inventory_batches WHERE product_id > ?1", [b]).unwrap();
+        conn.execute("DELETE FROM purchase_order_items WHERE product_id > ?1", [b]).unwrap();
+        conn.execute("DELETE FROM purchase_orders WHERE po_number > ?1", [migration_po_number(b)]).unwrap();
+        save_migration_cursor(&conn, b).unwrap();
+        assert_eq!(read_migration_cursor(&conn).unwrap(), Some(b));
+
+        let mut progress = Vec::new();
+        let result = migrate_existing_products_internal(&mut conn, 2, |p| progress.push((p.processed, p.total))).unwrap();
+        assert_eq!(result.resumed_after, Some(b));
+        assert_eq!(result.products_migrated, 2);
+        // E's initial stock no longer matches its stock, so it is rolled back on its own
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(progress, vec![(0, 3), (2, 3), (3, 3)]);
+        assert_eq!(read_migration_cursor(&conn).unwrap(), None);
+        let po_count = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT COUNT(*) FROM purchase_orders", [], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(po_count(&conn), 4);
+
+        // Rerunning is harmless
+        let rerun = migrate_existing_products_internal(&mut conn, 2, |_| {}).unwrap();
+        assert_eq!(rerun.products_migrated, 0);
+        assert_eq!(po_count(&conn), 4);
+
+        conn.execute("UPDATE products SET stock_quantity = 7 WHERE id = ?1", [c]).unwrap();
+        conn.execute("UPDATE products SET stock_quantity = 1 WHERE id = ?1", [d]).unwrap();
+        let report = validate_migration_internal(&conn, |_| {}).unwrap();
+        assert_eq!(report.total_products_checked, 5);
+        let issues: Vec<(i32, &str, f64)> = report
+            .inconsistent_products
+            .iter()
+            .map(|p| (p.id, p.issue.as_str(), p.difference))
+            .collect();
+        assert_eq!(issues, vec![(c, "batch_shortfall", 3.0), (d, "batch_excess", -2.0), (e, "missing_batches", 5.0)]);
+
+        let ids: Vec<i32> = report.inconsistent_products.iter().map(|p| p.id).collect();
+        let repair = repair_migration_internal(&mut conn, &ids, &Some("owner".to_string()), 2, |_| {}).unwrap();
+        assert_eq!(repair.repaired.len(), 3);
+        assert!(repair.errors.is_empty());
+        assert_eq!((batch_quantity(&conn, c), batch_quantity(&conn, d), batch_quantity(&conn, e)), (7.0, 1.0, 5.0));
+        assert!(validate_migration_internal(&conn, |_| {}).unwrap().inconsistent_products.is_empty());
+
+        // Repairing from the stale report again changes nothing
+        let again = repair_migration_internal(&mut conn, &ids, &None, 2, |_| {}).unwrap();
+        assert_eq!(again.already_consistent, ids);
+        let logged: i64 = conn
+            .query_row("SELECT COUNT(*) FROM entity_modifications WHERE action = 'batches_repaired'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(logged, 3);
+    }
 }
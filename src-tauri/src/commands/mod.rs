@@ -13,7 +13,41 @@ pub mod images;
 pub mod biometric;
 pub mod customer_payments;
 pub mod ai_chat;
+pub mod ai_data;
 pub mod data_management;
+pub mod csv_import;
+pub mod maintenance;
+pub mod snapshot;
+pub mod performance;
+pub mod user_preferences;
+pub mod price_history;
+pub mod reservations;
+pub mod share;
+pub mod printing;
+pub mod expenses;
+pub mod price_tiers;
+pub mod bundles;
+pub mod reminders;
+pub mod variants;
+pub mod categories;
+pub mod sales_summary;
+pub mod billing;
+pub mod held_sales;
+pub mod demo_data;
+pub mod product_export;
+pub mod purchase_returns;
+pub mod day_closures;
+pub mod safety_snapshots;
+pub mod costing;
+pub mod integration;
+pub mod webhooks;
+pub mod po_documents;
+pub mod recurring_invoices;
+pub mod sales_targets;
+pub mod stock_alerts;
+pub mod price_overrides;
+pub mod challans;
+pub mod integrity;
 
 
 use serde::{Deserialize, Serialize};
@@ -24,6 +58,30 @@ pub struct PaginatedResult<T> {
     pub total_count: i64,
 }
 
+/// Largest page (or top-N list) a command will return in one call
+pub const MAX_PAGE_SIZE: i32 = 500;
+
+/// Check a 1-based page and page size and turn them into `(limit, offset)` for `LIMIT ? OFFSET ?`
+pub fn validate_pagination(page: i32, page_size: i32) -> Result<(i64, i64), String> {
+    if page < 1 {
+        return Err(format!("Validation error: page must be at least 1 (got {})", page));
+    }
+    if !(1..=MAX_PAGE_SIZE).contains(&page_size) {
+        return Err(format!(
+            "Validation error: page_size must be between 1 and {} (got {})",
+            MAX_PAGE_SIZE, page_size
+        ));
+    }
+
+    let limit = page_size as i64;
+    Ok((limit, (page as i64 - 1) * limit))
+}
+
+/// Clamp a top-N limit into `1..=MAX_PAGE_SIZE`
+pub fn clamp_limit(limit: i32) -> i64 {
+    limit.clamp(1, MAX_PAGE_SIZE) as i64
+}
+
 pub use products::*;
 pub use suppliers::*;
 pub use customers::*;
@@ -39,5 +97,60 @@ pub use images::*;
 pub use biometric::*;
 pub use customer_payments::*;
 pub use ai_chat::*;
+pub use ai_data::*;
 pub use data_management::*;
+pub use csv_import::*;
+pub use maintenance::*;
+pub use snapshot::*;
+pub use performance::*;
+pub use user_preferences::*;
+pub use price_history::*;
+pub use reservations::*;
+pub use share::*;
+pub use printing::*;
+pub use expenses::*;
+pub use price_tiers::*;
+pub use bundles::*;
+pub use reminders::*;
+pub use variants::*;
+pub use categories::*;
+pub use sales_summary::*;
+pub use billing::*;
+pub use held_sales::*;
+pub use demo_data::*;
+pub use product_export::*;
+pub use purchase_returns::*;
+pub use day_closures::*;
+pub use safety_snapshots::*;
+pub use costing::*;
+pub use integration::*;
+pub use webhooks::*;
+pub use po_documents::*;
+pub use recurring_invoices::*;
+pub use sales_targets::*;
+pub use stock_alerts::*;
+pub use price_overrides::*;
+pub use challans::*;
+pub use integrity::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    #[test]
+    fn test_validate_pagination() {
+        assert_eq!(validate_pagination(1, 20), Ok((20, 0)));
+        assert_eq!(validate_pagination(3, 500), Ok((500, 1000)));
+        assert!(validate_pagination(0, 20).unwrap_err().starts_with("Validation error"));
+        assert!(validate_pagination(1, 0).is_err());
+        assert!(validate_pagination(1, 501).is_err());
+        assert_eq!(validate_pagination(i32::MAX, 500).unwrap().1, (i32::MAX as i64 - 1) * 500);
+    }
+
+    #[test]
+    fn test_clamp_limit() {
+        assert_eq!(clamp_limit(-5), 1);
+        assert_eq!(clamp_limit(10), 10);
+        assert_eq!(clamp_limit(10_000), 500);
+    }
+}
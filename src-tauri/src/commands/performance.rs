@@ -0,0 +1,18 @@
+/// Performance Metrics Commands
+/// Per-command timings recorded by the invoke handler (see services/metrics.rs)
+use tauri::State;
+
+use crate::services::metrics::{CommandMetrics, PerformanceMetrics};
+
+/// Get count, p50, p95, max and error rate per command since app start (or the last reset).
+/// With `reset`, the recorded timings are cleared after they are returned.
+#[tauri::command]
+pub fn get_performance_metrics(reset: Option<bool>, metrics: State<CommandMetrics>) -> Result<PerformanceMetrics, String> {
+    log::info!("get_performance_metrics called (reset: {:?})", reset);
+
+    let snapshot = metrics.snapshot()?;
+    if reset.unwrap_or(false) {
+        metrics.reset()?;
+    }
+    Ok(snapshot)
+}
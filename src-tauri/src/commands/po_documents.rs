@@ -0,0 +1,192 @@
+/// Purchase Order Documents
+/// PDFs are laid out in the frontend (lib/pdf-generator.ts, with the company profile from the
+/// invoice_* settings, a DRAFT watermark for draft orders and the item table split across pages).
+/// These commands store a generated PO PDF next to the database and record each time one is
+/// sent to the supplier, with the contact it went to, in entity_modifications as "sent".
+use std::path::PathBuf;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::maintenance::database_file_path;
+use crate::db::Database;
+
+const DOCUMENTS_DIR: &str = "documents/purchase_orders";
+/// Generated POs are a few hundred KB even with many pages
+const MAX_PDF_BYTES: usize = 20 * 1024 * 1024;
+const SEND_CHANNELS: &[&str] = &["whatsapp", "email"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurchaseOrderSend {
+    pub po_id: i32,
+    pub po_number: String,
+    /// "whatsapp" or "email"
+    pub channel: String,
+    /// Phone number or email address the PO went to
+    pub contact: String,
+    pub file_path: Option<String>,
+}
+
+fn documents_dir(conn: &Connection) -> Result<PathBuf, String> {
+    let db_path = database_file_path(conn)?;
+    let parent = db_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .ok_or_else(|| "Documents need a file-backed database".to_string())?;
+    Ok(parent.join(DOCUMENTS_DIR))
+}
+
+fn po_number(conn: &Connection, po_id: i32) -> Result<String, String> {
+    conn.query_row("SELECT po_number FROM purchase_orders WHERE id = ?1", [po_id], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to load purchase order: {}", e))?
+        .ok_or_else(|| format!("Purchase order {} not found", po_id))
+}
+
+/// Save a generated PO PDF as `PO_<number>.pdf`, replacing an earlier copy; returns its path
+#[tauri::command]
+pub fn save_purchase_order_pdf(po_id: i32, pdf_bytes: Vec<u8>, db: State<Database>) -> Result<String, String> {
+    log::info!("save_purchase_order_pdf called for po_id: {} ({} bytes)", po_id, pdf_bytes.len());
+
+    let conn = db.get_conn()?;
+    save_purchase_order_pdf_internal(&conn, po_id, &pdf_bytes)
+}
+
+pub(crate) fn save_purchase_order_pdf_internal(conn: &Connection, po_id: i32, pdf_bytes: &[u8]) -> Result<String, String> {
+    if !pdf_bytes.starts_with(b"%PDF-") {
+        return Err("Validation error: not a PDF document".to_string());
+    }
+    if pdf_bytes.len() > MAX_PDF_BYTES {
+        return Err(format!("Validation error: PDF is larger than {} MB", MAX_PDF_BYTES / (1024 * 1024)));
+    }
+
+    // PO numbers are generated ("PO-2025-0001") but may have been imported; keep the name safe
+    let file_name: String = po_number(conn, po_id)?
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let dir = documents_dir(conn)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create documents folder: {}", e))?;
+
+    let path = dir.join(format!("PO_{}.pdf", file_name));
+    std::fs::write(&path, pdf_bytes).map_err(|e| format!("Failed to save purchase order PDF: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Record that a PO was sent to its supplier. `contact` defaults to the supplier's phone
+/// (contact_info) for WhatsApp and email address for email.
+#[tauri::command]
+pub fn mark_purchase_order_sent(
+    po_id: i32,
+    channel: String,
+    contact: Option<String>,
+    file_path: Option<String>,
+    sent_by: Option<String>,
+    db: State<Database>,
+) -> Result<PurchaseOrderSend, String> {
+    log::info!("mark_purchase_order_sent called for po_id: {} via {}", po_id, channel);
+
+    let conn = db.get_conn()?;
+    mark_purchase_order_sent_internal(&conn, po_id, &channel, contact.as_deref(), file_path.as_deref(), sent_by.as_deref())
+}
+
+pub(crate) fn mark_purchase_order_sent_internal(
+    conn: &Connection,
+    po_id: i32,
+    channel: &str,
+    contact: Option<&str>,
+    file_path: Option<&str>,
+    sent_by: Option<&str>,
+) -> Result<PurchaseOrderSend, String> {
+    if !SEND_CHANNELS.contains(&channel) {
+        return Err(format!("Validation error: channel must be one of {}", SEND_CHANNELS.join(", ")));
+    }
+
+    let (po_number, phone, email): (String, Option<String>, Option<String>) = conn
+        .query_row(
+            "SELECT po.po_number, s.contact_info, s.email
+             FROM purchase_orders po
+             LEFT JOIN suppliers s ON s.id = po.supplier_id
+             WHERE po.id = ?1",
+            [po_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load purchase order: {}", e))?
+        .ok_or_else(|| format!("Purchase order {} not found", po_id))?;
+
+    let supplier_contact = if channel == "email" { email } else { phone };
+    let contact = contact
+        .map(str::to_string)
+        .or(supplier_contact)
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| format!("Validation error: the supplier has no {} contact", channel))?;
+
+    let field_changes = serde_json::json!([
+        { "field": "channel", "old": null, "new": channel },
+        { "field": "contact", "old": null, "new": contact },
+        { "field": "file_path", "old": null, "new": file_path },
+    ]);
+    conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by)
+         VALUES ('purchase_order', ?1, ?2, 'sent', ?3, ?4)",
+        params![po_id, po_number, field_changes.to_string(), sent_by],
+    )
+    .map_err(|e| format!("Failed to log purchase order send: {}", e))?;
+
+    Ok(PurchaseOrderSend {
+        po_id,
+        po_number,
+        channel: channel.to_string(),
+        contact,
+        file_path: file_path.map(str::to_string),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_supplier, TestDb};
+
+    #[test]
+    fn test_po_pdf_is_saved_and_sends_are_logged() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme");
+        conn.execute(
+            "UPDATE suppliers SET contact_info = '98765 43210', email = NULL WHERE id = ?1",
+            [supplier_id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO purchase_orders (po_number, supplier_id, order_date, status, total_amount)
+             VALUES ('PO/2025/7', ?1, '2025-01-01', 'draft', 0)",
+            [supplier_id],
+        )
+        .unwrap();
+        let po_id = conn.last_insert_rowid() as i32;
+
+        assert!(save_purchase_order_pdf_internal(&conn, po_id, b"not a pdf").is_err());
+        let path = save_purchase_order_pdf_internal(&conn, po_id, b"%PDF-1.3 test").unwrap();
+        assert!(path.ends_with("PO_PO_2025_7.pdf"));
+        assert_eq!(std::fs::read(&path).unwrap(), b"%PDF-1.3 test");
+
+        // The supplier's phone is used for WhatsApp; there's no email to fall back on
+        let sent = mark_purchase_order_sent_internal(&conn, po_id, "whatsapp", None, Some(&path), Some("admin")).unwrap();
+        assert_eq!(sent.contact, "98765 43210");
+        assert!(mark_purchase_order_sent_internal(&conn, po_id, "email", None, None, None).is_err());
+        assert!(mark_purchase_order_sent_internal(&conn, po_id, "fax", None, None, None).is_err());
+
+        let logged: String = conn
+            .query_row(
+                "SELECT field_changes FROM entity_modifications WHERE entity_type = 'purchase_order' AND action = 'sent'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let changes: serde_json::Value = serde_json::from_str(&logged).unwrap();
+        assert_eq!(changes[1]["new"], "98765 43210");
+    }
+}
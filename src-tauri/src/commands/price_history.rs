@@ -0,0 +1,454 @@
+/// Product Price History
+/// Every change to a product's cost price or selling price is recorded in product_price_history,
+/// whether it was made through update_product or by a scheduled change, so "when did this price
+/// go up?" is a query rather than a search through entity_modifications JSON.
+///
+/// Scheduled changes take effect at the start of their effective date in the business timezone.
+/// apply_due_price_changes applies every change whose time has come; it runs on app startup and
+/// right after a change is scheduled (so a change effective today applies at once).
+use chrono::{Duration, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::commands::analytics::{business_offset_minutes, business_today, parse_report_date};
+use crate::commands::biometric::{consume_action_approval, price_change_requires_biometric};
+use crate::db::Database;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+
+/// One recorded change to a price field
+#[derive(Debug, Serialize)]
+pub struct PriceChange {
+    pub id: i64,
+    /// "price" (cost) or "selling_price"
+    pub field: String,
+    pub old_value: Option<f64>,
+    pub new_value: Option<f64>,
+    pub changed_at: String,
+    pub changed_by: Option<String>,
+    /// The scheduled change that made it, if it wasn't an edit
+    pub scheduled_change_id: Option<i64>,
+}
+
+/// A price change set to apply later; a None price leaves that field as it is
+#[derive(Debug, Serialize)]
+pub struct ScheduledPriceChange {
+    pub id: i64,
+    pub product_id: i32,
+    pub price: Option<f64>,
+    pub selling_price: Option<f64>,
+    /// UTC "YYYY-MM-DD HH:MM:SS"
+    pub effective_at: String,
+    pub scheduled_by: Option<String>,
+    pub created_at: String,
+    pub applied_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SchedulePriceInput {
+    pub product_id: i32,
+    pub price: Option<f64>,
+    pub selling_price: Option<f64>,
+    /// "YYYY-MM-DD" in the business timezone; applies immediately when omitted
+    pub effective_from: Option<String>,
+    pub scheduled_by: Option<String>,
+}
+
+/// A product's price changes, oldest first, plus the changes still to come
+#[derive(Debug, Serialize)]
+pub struct PriceTimeline {
+    pub product_id: i32,
+    pub price: f64,
+    pub selling_price: Option<f64>,
+    pub changes: Vec<PriceChange>,
+    pub pending: Vec<ScheduledPriceChange>,
+}
+
+/// Same tolerance update_product uses when deciding whether a price changed
+fn price_changed(old: Option<f64>, new: Option<f64>) -> bool {
+    match (old, new) {
+        (Some(old), Some(new)) => (old - new).abs() > 0.001,
+        (None, None) => false,
+        _ => true,
+    }
+}
+
+/// Add a history row for `field` if the value actually changed
+pub(crate) fn record_price_change(
+    conn: &Connection,
+    product_id: i32,
+    field: &str,
+    old: Option<f64>,
+    new: Option<f64>,
+    changed_by: Option<&str>,
+    scheduled_change_id: Option<i64>,
+) -> Result<(), String> {
+    if !price_changed(old, new) {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO product_price_history (product_id, field, old_value, new_value, changed_by, scheduled_change_id)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![product_id, field, old, new, changed_by, scheduled_change_id],
+    )
+    .map_err(|e| format!("Failed to record price history: {}", e))?;
+    Ok(())
+}
+
+/// Price history and pending scheduled changes for a product
+#[tauri::command]
+pub fn get_product_price_timeline(product_id: i32, db: State<Database>) -> Result<PriceTimeline, String> {
+    log::info!("get_product_price_timeline called for product: {}", product_id);
+
+    let conn = db.get_conn()?;
+    get_product_price_timeline_internal(&conn, product_id)
+}
+
+pub(crate) fn get_product_price_timeline_internal(conn: &Connection, product_id: i32) -> Result<PriceTimeline, String> {
+    let (price, selling_price): (f64, Option<f64>) = conn
+        .query_row(
+            "SELECT price, selling_price FROM products WHERE id = ?1",
+            [product_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Product with id {} not found: {}", product_id, e))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, field, old_value, new_value, changed_at, changed_by, scheduled_change_id
+             FROM product_price_history
+             WHERE product_id = ?1
+             ORDER BY changed_at, id",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    let changes = stmt
+        .query_map([product_id], |row| {
+            Ok(PriceChange {
+                id: row.get(0)?,
+                field: row.get(1)?,
+                old_value: row.get(2)?,
+                new_value: row.get(3)?,
+                changed_at: row.get(4)?,
+                changed_by: row.get(5)?,
+                scheduled_change_id: row.get(6)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load price history: {}", e))?;
+
+    let pending = scheduled_changes(
+        conn,
+        "WHERE product_id = ?1 AND applied_at IS NULL ORDER BY effective_at, id",
+        params![product_id],
+    )?;
+
+    Ok(PriceTimeline { product_id, price, selling_price, changes, pending })
+}
+
+fn scheduled_changes(
+    conn: &Connection,
+    filter: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<ScheduledPriceChange>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, product_id, price, selling_price, effective_at, scheduled_by, created_at, applied_at
+             FROM scheduled_price_changes {}",
+            filter
+        ))
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    let changes = stmt
+        .query_map(params, |row| {
+            Ok(ScheduledPriceChange {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                price: row.get(2)?,
+                selling_price: row.get(3)?,
+                effective_at: row.get(4)?,
+                scheduled_by: row.get(5)?,
+                created_at: row.get(6)?,
+                applied_at: row.get(7)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load scheduled price changes: {}", e))?;
+    Ok(changes)
+}
+
+/// UTC time a change effective on `date` (business timezone) applies
+fn effective_at(date: NaiveDate, offset_minutes: i32) -> String {
+    (date.and_hms_opt(0, 0, 0).unwrap_or_default() - Duration::minutes(offset_minutes as i64))
+        .format("%Y-%m-%d %H:%M:%S")
+        .to_string()
+}
+
+/// Schedule a price change for a product, or apply it now when no effective date is given.
+/// Needs the same biometric approval as editing the price directly when the change is large
+/// enough to require one.
+#[tauri::command]
+pub fn set_scheduled_price(
+    input: SchedulePriceInput,
+    approval_token: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<ScheduledPriceChange, String> {
+    log::info!("set_scheduled_price called with: {:?}", input);
+
+    let conn = db.get_conn()?;
+    let change = set_scheduled_price_internal(&conn, &input, approval_token.as_deref())?;
+    if change.applied_at.is_some() {
+        emit_data_changed(&app, DataEntity::Product, DataOperation::Updated, change.product_id);
+    }
+    Ok(change)
+}
+
+pub(crate) fn set_scheduled_price_internal(
+    conn: &Connection,
+    input: &SchedulePriceInput,
+    approval_token: Option<&str>,
+) -> Result<ScheduledPriceChange, String> {
+    if input.price.is_none() && input.selling_price.is_none() {
+        return Err("Give a new price, a new selling price, or both".to_string());
+    }
+    for (label, value) in [("Price", input.price), ("Selling price", input.selling_price)] {
+        if value.is_some_and(|value| !value.is_finite() || value < 0.0) {
+            return Err(format!("{} must be a non-negative number", label));
+        }
+    }
+
+    let (current_price, current_selling_price): (f64, Option<f64>) = conn
+        .query_row(
+            "SELECT price, selling_price FROM products WHERE id = ?1",
+            [input.product_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Product with id {} not found: {}", input.product_id, e))?;
+
+    let effective_at = match input.effective_from.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(date) => {
+            let date = parse_report_date(date)?;
+            if date < business_today(conn) {
+                return Err(format!("Effective date {} is in the past", date));
+            }
+            effective_at(date, business_offset_minutes(conn))
+        }
+        None => Utc::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+
+    // Scheduling a large swing needs the approval update_product would ask for
+    let selling_price_needs_approval = match (current_selling_price, input.selling_price) {
+        (Some(old), Some(new)) => price_change_requires_biometric(conn, old, new),
+        _ => false,
+    };
+    let price_needs_approval = input
+        .price
+        .is_some_and(|new| price_change_requires_biometric(conn, current_price, new));
+    if selling_price_needs_approval || price_needs_approval {
+        let approved_by = consume_action_approval(conn, "price_change", approval_token)?;
+        log::info!("Scheduled price change on product {} approved by {} via biometric", input.product_id, approved_by);
+    }
+
+    conn.execute(
+        "INSERT INTO scheduled_price_changes (product_id, price, selling_price, effective_at, scheduled_by)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![input.product_id, input.price, input.selling_price, effective_at, input.scheduled_by],
+    )
+    .map_err(|e| format!("Failed to schedule price change: {}", e))?;
+    let id = conn.last_insert_rowid();
+
+    apply_due_price_changes(conn)?;
+
+    scheduled_changes(conn, "WHERE id = ?1", [id])?
+        .pop()
+        .ok_or_else(|| format!("Scheduled price change {} not found", id))
+}
+
+/// Cancel a scheduled change that hasn't been applied yet
+#[tauri::command]
+pub fn cancel_scheduled_price(id: i64, db: State<Database>) -> Result<(), String> {
+    log::info!("cancel_scheduled_price called for id: {}", id);
+
+    let conn = db.get_conn()?;
+    let deleted = conn
+        .execute("DELETE FROM scheduled_price_changes WHERE id = ?1 AND applied_at IS NULL", [id])
+        .map_err(|e| format!("Failed to cancel scheduled price change: {}", e))?;
+    if deleted == 0 {
+        return Err(format!("No pending scheduled price change with id {}", id));
+    }
+    Ok(())
+}
+
+/// Apply every scheduled change whose effective time has passed, oldest first, recording each in
+/// the price history and entity_modifications like an edit. Runs on app startup.
+pub fn apply_due_price_changes(conn: &Connection) -> Result<usize, String> {
+    let due = scheduled_changes(
+        conn,
+        "WHERE applied_at IS NULL AND effective_at <= ?1 ORDER BY effective_at, id",
+        [Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()],
+    )?;
+
+    for change in &due {
+        apply_price_change(conn, change)?;
+        log::info!("Applied scheduled price change {} to product {}", change.id, change.product_id);
+    }
+    Ok(due.len())
+}
+
+fn apply_price_change(conn: &Connection, change: &ScheduledPriceChange) -> Result<(), String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let product: Option<(String, f64, Option<f64>)> = tx
+        .query_row(
+            "SELECT name, price, selling_price FROM products WHERE id = ?1",
+            [change.product_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load product {}: {}", change.product_id, e))?;
+    let Some((name, old_price, old_selling_price)) = product else {
+        return Err(format!("Product with id {} not found", change.product_id));
+    };
+
+    let new_price = change.price.unwrap_or(old_price);
+    let new_selling_price = change.selling_price.or(old_selling_price);
+    tx.execute(
+        "UPDATE products SET price = ?1, selling_price = ?2, updated_at = datetime('now') WHERE id = ?3",
+        params![new_price, new_selling_price, change.product_id],
+    )
+    .map_err(|e| format!("Failed to update product: {}", e))?;
+
+    let changed_by = change.scheduled_by.as_deref();
+    let mut field_changes: Vec<serde_json::Value> = Vec::new();
+    if price_changed(Some(old_price), Some(new_price)) {
+        field_changes.push(serde_json::json!({"field": "price", "old": old_price, "new": new_price}));
+    }
+    if price_changed(old_selling_price, new_selling_price) {
+        field_changes.push(serde_json::json!({"field": "selling_price", "old": old_selling_price, "new": new_selling_price}));
+    }
+    record_price_change(&tx, change.product_id, "price", Some(old_price), Some(new_price), changed_by, Some(change.id))?;
+    record_price_change(
+        &tx,
+        change.product_id,
+        "selling_price",
+        old_selling_price,
+        new_selling_price,
+        changed_by,
+        Some(change.id),
+    )?;
+
+    if !field_changes.is_empty() {
+        let changes_json = serde_json::to_string(&field_changes).unwrap_or_default();
+        tx.execute(
+            "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            ("product", change.product_id, &name, "updated", &changes_json, changed_by),
+        )
+        .map_err(|e| format!("Failed to log modification: {}", e))?;
+    }
+
+    tx.execute(
+        "UPDATE scheduled_price_changes SET applied_at = datetime('now') WHERE id = ?1",
+        [change.id],
+    )
+    .map_err(|e| format!("Failed to mark scheduled price change applied: {}", e))?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    fn insert_product(conn: &Connection) -> i32 {
+        conn.execute(
+            "INSERT INTO products (name, sku, price, selling_price, stock_quantity) VALUES ('Pen', 'PEN', 10, 15, 5)",
+            [],
+        )
+        .unwrap();
+        conn.last_insert_rowid() as i32
+    }
+
+    fn schedule(product_id: i32, selling_price: f64, effective_from: Option<String>) -> SchedulePriceInput {
+        SchedulePriceInput {
+            product_id,
+            price: None,
+            selling_price: Some(selling_price),
+            effective_from,
+            scheduled_by: Some("owner".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_scheduled_change_applies_once_due() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let product_id = insert_product(&conn);
+
+        let tomorrow = (business_today(&conn) + Duration::days(1)).format("%Y-%m-%d").to_string();
+        let change = set_scheduled_price_internal(&conn, &schedule(product_id, 18.0, Some(tomorrow)), None).unwrap();
+        assert!(change.applied_at.is_none());
+        assert_eq!(apply_due_price_changes(&conn).unwrap(), 0);
+
+        let timeline = get_product_price_timeline_internal(&conn, product_id).unwrap();
+        assert_eq!(timeline.selling_price, Some(15.0));
+        assert!(timeline.changes.is_empty());
+        assert_eq!(timeline.pending.len(), 1);
+
+        conn.execute("UPDATE scheduled_price_changes SET effective_at = datetime('now', '-1 minutes')", []).unwrap();
+        assert_eq!(apply_due_price_changes(&conn).unwrap(), 1);
+        assert_eq!(apply_due_price_changes(&conn).unwrap(), 0);
+
+        let timeline = get_product_price_timeline_internal(&conn, product_id).unwrap();
+        assert_eq!((timeline.price, timeline.selling_price), (10.0, Some(18.0)));
+        assert!(timeline.pending.is_empty());
+        assert_eq!(timeline.changes.len(), 1);
+        let recorded = &timeline.changes[0];
+        assert_eq!(recorded.field, "selling_price");
+        assert_eq!((recorded.old_value, recorded.new_value), (Some(15.0), Some(18.0)));
+        assert_eq!(recorded.changed_by.as_deref(), Some("owner"));
+        assert_eq!(recorded.scheduled_change_id, Some(change.id));
+
+        let logged: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM entity_modifications WHERE entity_type = 'product' AND entity_id = ?1",
+                [product_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(logged, 1);
+    }
+
+    #[test]
+    fn test_schedule_without_date_applies_now() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let product_id = insert_product(&conn);
+
+        let input = SchedulePriceInput { price: Some(11.0), ..schedule(product_id, 15.0, None) };
+        let change = set_scheduled_price_internal(&conn, &input, None).unwrap();
+        assert!(change.applied_at.is_some());
+
+        // Only the cost price actually changed
+        let timeline = get_product_price_timeline_internal(&conn, product_id).unwrap();
+        assert_eq!(timeline.price, 11.0);
+        assert_eq!(timeline.changes.len(), 1);
+        assert_eq!(timeline.changes[0].field, "price");
+    }
+
+    #[test]
+    fn test_rejects_invalid_schedules() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let product_id = insert_product(&conn);
+
+        let yesterday = (business_today(&conn) - Duration::days(1)).format("%Y-%m-%d").to_string();
+        assert!(set_scheduled_price_internal(&conn, &schedule(product_id, 18.0, Some(yesterday)), None).is_err());
+        assert!(set_scheduled_price_internal(&conn, &schedule(product_id, -1.0, None), None).is_err());
+        assert!(set_scheduled_price_internal(&conn, &schedule(9999, 18.0, None), None).is_err());
+
+        let neither = SchedulePriceInput { selling_price: None, ..schedule(product_id, 0.0, None) };
+        assert!(set_scheduled_price_internal(&conn, &neither, None).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+/// Price Override Commands
+/// A line billed more than price_override_tolerance_percent under its expected price (the
+/// customer's tier price, else the list price) is a price override. create_invoice then needs a
+/// reason, and a manager approval when require_biometric_for lists price_override, and logs the
+/// line in price_overrides so the owner can review discounting per cashier.
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::analytics::ReportRange;
+use crate::commands::invoices::CreateInvoiceItemInput;
+use crate::commands::price_tiers::{list_price, tier_unit_price};
+use crate::commands::settings::price_override_tolerance;
+use crate::db::Database;
+use crate::services::money;
+
+/// Prefix of the error returned when a sale has price overrides but no reason; the rest of the
+/// message is a JSON array of `OverriddenLine`
+pub const PRICE_OVERRIDE_REASON_REQUIRED: &str = "PRICE_OVERRIDE_REASON_REQUIRED:";
+
+/// A sale line billed under its expected price
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OverriddenLine {
+    /// Index of the line in the sale's items
+    pub line: usize,
+    pub product_id: i32,
+    /// Tier price when the sale is priced with a tier, else the list price
+    pub list_price: f64,
+    pub unit_price: f64,
+}
+
+/// One logged price override
+#[derive(Debug, Serialize)]
+pub struct PriceOverride {
+    pub id: i64,
+    /// None once the invoice has been deleted
+    pub invoice_id: Option<i32>,
+    pub invoice_number: String,
+    pub product_id: i32,
+    pub product_name: String,
+    pub quantity: f64,
+    pub list_price: f64,
+    pub charged_price: f64,
+    /// (list_price - charged_price) * quantity
+    pub discount: f64,
+    pub reason: String,
+    pub cashier: Option<String>,
+    pub approved_by: Option<String>,
+    pub created_at: String,
+}
+
+/// Overrides given by one cashier over a report range
+#[derive(Debug, Serialize)]
+pub struct CashierOverrides {
+    pub cashier: Option<String>,
+    pub override_count: i64,
+    pub invoice_count: i64,
+    pub total_discount: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PriceOverrideReport {
+    pub start_date: String,
+    pub end_date: String,
+    /// Newest first
+    pub overrides: Vec<PriceOverride>,
+    /// Largest total discount first
+    pub by_cashier: Vec<CashierOverrides>,
+    pub total_discount: f64,
+}
+
+/// Lines of a sale billed under their expected price by more than the tolerance setting.
+/// Always empty while price_override_tolerance_percent is unset.
+pub(crate) fn find_price_overrides(
+    conn: &Connection,
+    items: &[CreateInvoiceItemInput],
+    tier_id: Option<i32>,
+) -> Result<Vec<OverriddenLine>, String> {
+    let Some(tolerance) = price_override_tolerance(conn)? else {
+        return Ok(Vec::new());
+    };
+
+    let mut overridden = Vec::new();
+    for (line, item) in items.iter().enumerate() {
+        let list = list_price(conn, item.product_id)?;
+        let expected = match tier_id {
+            Some(tier_id) => tier_unit_price(conn, tier_id, item.product_id, list)?.0,
+            None => list,
+        };
+        let lowest_allowed = money::sub(expected, money::percent_of(expected, tolerance));
+        if money::exceeds(lowest_allowed, item.unit_price) {
+            overridden.push(OverriddenLine {
+                line,
+                product_id: item.product_id,
+                list_price: expected,
+                unit_price: item.unit_price,
+            });
+        }
+    }
+    Ok(overridden)
+}
+
+/// Price overrides logged over a business-local date range, optionally for one cashier
+#[tauri::command]
+pub fn get_price_overrides(
+    start_date: String,
+    end_date: String,
+    cashier: Option<String>,
+    db: State<Database>,
+) -> Result<PriceOverrideReport, String> {
+    log::info!("get_price_overrides called: {} to {}, cashier {:?}", start_date, end_date, cashier);
+
+    let conn = db.get_conn()?;
+    get_price_overrides_internal(&conn, &start_date, &end_date, cashier.as_deref())
+}
+
+pub(crate) fn get_price_overrides_internal(
+    conn: &Connection,
+    start_date: &str,
+    end_date: &str,
+    cashier: Option<&str>,
+) -> Result<PriceOverrideReport, String> {
+    let range = ReportRange::load(conn, start_date, end_date)?;
+    let cashier = cashier.map(str::trim).filter(|name| !name.is_empty());
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, invoice_id, invoice_number, product_id, product_name, quantity, list_price,
+                    charged_price, reason, cashier, approved_by, created_at
+             FROM price_overrides
+             WHERE created_at >= ?1 AND created_at < ?2
+               AND (?3 IS NULL OR cashier = ?3 COLLATE NOCASE)
+             ORDER BY created_at DESC, id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let overrides = stmt
+        .query_map(rusqlite::params![range.start_utc, range.end_utc, cashier], |row| {
+            let quantity: f64 = row.get(5)?;
+            let list_price: f64 = row.get(6)?;
+            let charged_price: f64 = row.get(7)?;
+            Ok(PriceOverride {
+                id: row.get(0)?,
+                invoice_id: row.get(1)?,
+                invoice_number: row.get(2)?,
+                product_id: row.get(3)?,
+                product_name: row.get(4)?,
+                quantity,
+                list_price,
+                charged_price,
+                discount: money::sub(money::line_total(list_price, quantity), money::line_total(charged_price, quantity)),
+                reason: row.get(8)?,
+                cashier: row.get(9)?,
+                approved_by: row.get(10)?,
+                created_at: row.get(11)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load price overrides: {}", e))?;
+
+    let mut by_cashier: Vec<CashierOverrides> = Vec::new();
+    for entry in &overrides {
+        let index = match by_cashier.iter().position(|c| c.cashier == entry.cashier) {
+            Some(index) => index,
+            None => {
+                by_cashier.push(CashierOverrides {
+                    cashier: entry.cashier.clone(),
+                    override_count: 0,
+                    invoice_count: 0,
+                    total_discount: 0.0,
+                });
+                by_cashier.len() - 1
+            }
+        };
+        let summary = &mut by_cashier[index];
+        summary.override_count += 1;
+        summary.total_discount = money::sum([summary.total_discount, entry.discount]);
+    }
+    for summary in &mut by_cashier {
+        let mut invoices: Vec<&str> = overrides
+            .iter()
+            .filter(|entry| entry.cashier == summary.cashier)
+            .map(|entry| entry.invoice_number.as_str())
+            .collect();
+        invoices.sort_unstable();
+        invoices.dedup();
+        summary.invoice_count = invoices.len() as i64;
+    }
+    by_cashier.sort_by(|a, b| b.total_discount.total_cmp(&a.total_discount));
+
+    Ok(PriceOverrideReport {
+        start_date: range.start_date.format("%Y-%m-%d").to_string(),
+        end_date: range.end_date.format("%Y-%m-%d").to_string(),
+        total_discount: money::sum(overrides.iter().map(|entry| entry.discount)),
+        overrides,
+        by_cashier,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::analytics::business_today;
+    use crate::commands::biometric::{generate_biometric_token_internal, issue_action_approval, BIOMETRIC_APPROVAL_REQUIRED};
+    use crate::commands::invoices::{create_invoice_internal, CreateInvoiceInput};
+    use crate::commands::products::{create_product_internal, CreateProductInput};
+    use crate::test_support::{invoice_input, product_input, TestDb};
+
+    fn sale(product_id: i32, unit_price: f64, reason: Option<&str>) -> CreateInvoiceInput {
+        CreateInvoiceInput {
+            price_override_reason: reason.map(str::to_string),
+            created_by: Some("ravi".to_string()),
+            ..invoice_input(None, vec![(product_id, 2, unit_price)])
+        }
+    }
+
+    /// Bought at 60 and listed at 100
+    fn product(conn: &Connection, sku: &str) -> i32 {
+        let input = CreateProductInput { selling_price: Some(100.0), ..product_input(sku, 60.0, 20, None) };
+        create_product_internal(conn, input).unwrap().id
+    }
+
+    #[test]
+    fn test_underpriced_lines_need_a_reason_and_are_logged() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product_id = product(&conn, "KET-1");
+
+        // Without the tolerance setting any price goes through unlogged
+        create_invoice_internal(&mut conn, sale(product_id, 50.0, None)).unwrap();
+
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('price_override_tolerance_percent', '5')", [])
+            .unwrap();
+        create_invoice_internal(&mut conn, sale(product_id, 95.0, None)).unwrap();
+        let err = create_invoice_internal(&mut conn, sale(product_id, 80.0, None)).unwrap_err();
+        let lines: Vec<OverriddenLine> =
+            serde_json::from_str(err.strip_prefix(PRICE_OVERRIDE_REASON_REQUIRED).unwrap()).unwrap();
+        assert_eq!(lines, vec![OverriddenLine { line: 0, product_id, list_price: 100.0, unit_price: 80.0 }]);
+        create_invoice_internal(&mut conn, sale(product_id, 80.0, Some("Dented box"))).unwrap();
+
+        // Once the action is protected an override also needs a manager's approval
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('require_biometric_for', '[\"price_override\"]')", [])
+            .unwrap();
+        let err = create_invoice_internal(&mut conn, sale(product_id, 70.0, Some("Old stock"))).unwrap_err();
+        assert!(err.starts_with(BIOMETRIC_APPROVAL_REQUIRED));
+        conn.execute(
+            "INSERT INTO users (username, password, role, permissions) VALUES ('manager', 'x', 'admin', '[\"*\"]')",
+            [],
+        )
+        .unwrap();
+        let token = generate_biometric_token_internal(&conn, conn.last_insert_rowid() as i32, "device-a").unwrap();
+        let approval = issue_action_approval(&conn, "price_override", &token, "device-a").unwrap();
+        let mut approved = sale(product_id, 70.0, Some("Old stock"));
+        approved.price_override_approval = Some(approval.token);
+        approved.created_by = Some("meena".to_string());
+        create_invoice_internal(&mut conn, approved).unwrap();
+
+        let today = business_today(&conn).format("%Y-%m-%d").to_string();
+        let report = get_price_overrides_internal(&conn, &today, &today, None).unwrap();
+        let logged: Vec<(f64, &str, Option<&str>, Option<&str>)> = report
+            .overrides
+            .iter()
+            .map(|o| (o.charged_price, o.reason.as_str(), o.cashier.as_deref(), o.approved_by.as_deref()))
+            .collect();
+        assert_eq!(
+            logged,
+            vec![
+                (70.0, "Old stock", Some("meena"), Some("manager")),
+                (80.0, "Dented box", Some("ravi"), None),
+            ]
+        );
+        assert_eq!(report.total_discount, 100.0);
+        let by_cashier: Vec<(Option<&str>, i64, f64)> = report
+            .by_cashier
+            .iter()
+            .map(|c| (c.cashier.as_deref(), c.override_count, c.total_discount))
+            .collect();
+        assert_eq!(by_cashier, vec![(Some("meena"), 1, 60.0), (Some("ravi"), 1, 40.0)]);
+
+        let ravi = get_price_overrides_internal(&conn, &today, &today, Some("Ravi")).unwrap();
+        assert_eq!(ravi.overrides.len(), 1);
+    }
+
+    #[test]
+    fn test_failed_sale_keeps_the_approval() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let failing_product = product(&conn, "FAIL-1");
+        let kettle = product(&conn, "KET-1");
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES ('price_override_tolerance_percent', '5'), ('require_biometric_for', '[\"price_override\"]')",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO users (username, password, role, permissions) VALUES ('manager', 'x', 'admin', '[\"*\"]')",
+            [],
+        )
+        .unwrap();
+        let token = generate_biometric_token_internal(&conn, conn.last_insert_rowid() as i32, "device-a").unwrap();
+        let approval = issue_action_approval(&conn, "price_override", &token, "device-a").unwrap();
+
+        // A sale that fails once inside its transaction rolls the approval back unused
+        conn.execute(
+            &format!(
+                "CREATE TEMP TRIGGER fail_line BEFORE INSERT ON invoice_items WHEN NEW.product_id = {}
+                 BEGIN SELECT RAISE(ABORT, 'line rejected'); END",
+                failing_product
+            ),
+            [],
+        )
+        .unwrap();
+        let mut failing = sale(failing_product, 70.0, Some("Old stock"));
+        failing.price_override_approval = Some(approval.token.clone());
+        assert!(create_invoice_internal(&mut conn, failing).is_err());
+
+        let mut retried = sale(kettle, 70.0, Some("Old stock"));
+        retried.price_override_approval = Some(approval.token.clone());
+        create_invoice_internal(&mut conn, retried).unwrap();
+
+        let mut reused = sale(kettle, 70.0, Some("Old stock"));
+        reused.price_override_approval = Some(approval.token);
+        assert!(create_invoice_internal(&mut conn, reused).unwrap_err().starts_with(BIOMETRIC_APPROVAL_REQUIRED));
+    }
+}
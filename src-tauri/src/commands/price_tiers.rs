@@ -0,0 +1,380 @@
+/// Price Tier Commands
+/// Customer pricing tiers: a percentage off list price with optional per-product overrides
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::db::Database;
+use crate::services::money;
+
+/// List price used as the base for tier pricing (selling price, else cost price)
+const LIST_PRICE_SQL: &str = "COALESCE(p.selling_price, p.price)";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriceTier {
+    pub id: i32,
+    pub name: String,
+    pub discount_percent: f64,
+    pub override_count: i32,
+    pub customer_count: i32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriceTierInput {
+    pub name: String,
+    pub discount_percent: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EffectivePrice {
+    pub product_id: i32,
+    pub list_price: f64,
+    pub unit_price: f64,
+    pub tier_id: Option<i32>,
+    pub tier_name: Option<String>,
+    pub source: String, // "list", "tier_discount", "tier_override"
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriceListEntry {
+    pub product_id: i32,
+    pub name: String,
+    pub sku: String,
+    pub category: Option<String>,
+    pub list_price: f64,
+    pub tier_price: f64,
+    pub is_override: bool,
+}
+
+/// Tier price for a list price: the override when set, else the tier discount
+fn tier_price(list_price: f64, discount_percent: f64, override_price: Option<f64>) -> f64 {
+    match override_price {
+        Some(price) => price,
+        None => money::round_money(list_price * (1.0 - discount_percent / 100.0)),
+    }
+}
+
+fn validate_tier_input(input: &PriceTierInput) -> Result<String, String> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        return Err("Tier name is required".to_string());
+    }
+    if !(0.0..=100.0).contains(&input.discount_percent) {
+        return Err("Discount percent must be between 0 and 100".to_string());
+    }
+    Ok(name.to_string())
+}
+
+fn get_price_tier_by_id(conn: &Connection, id: i32) -> Result<PriceTier, String> {
+    conn.query_row(
+        "SELECT t.id, t.name, t.discount_percent,
+                (SELECT COUNT(*) FROM price_tier_items WHERE tier_id = t.id),
+                (SELECT COUNT(*) FROM customers WHERE price_tier_id = t.id),
+                t.created_at, t.updated_at
+         FROM price_tiers t WHERE t.id = ?1",
+        [id],
+        |row| {
+            Ok(PriceTier {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                discount_percent: row.get(2)?,
+                override_count: row.get(3)?,
+                customer_count: row.get(4)?,
+                created_at: row.get(5)?,
+                updated_at: row.get(6)?,
+            })
+        },
+    )
+    .map_err(|e| format!("Price tier not found: {}", e))
+}
+
+/// Tier currently assigned to a customer, as (id, name)
+pub(crate) fn customer_price_tier(conn: &Connection, customer_id: i32) -> Result<Option<(i32, String)>, String> {
+    conn.query_row(
+        "SELECT t.id, t.name FROM customers c JOIN price_tiers t ON c.price_tier_id = t.id WHERE c.id = ?1",
+        [customer_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to load customer price tier: {}", e))
+}
+
+/// A product's list price (selling price, else cost price)
+pub(crate) fn list_price(conn: &Connection, product_id: i32) -> Result<f64, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM products p WHERE p.id = ?1", LIST_PRICE_SQL),
+        [product_id],
+        |row| row.get(0),
+    )
+    .map_err(|_| format!("Product with id {} not found", product_id))
+}
+
+/// A product's price in a tier, and whether it comes from a per-product override
+pub(crate) fn tier_unit_price(conn: &Connection, tier_id: i32, product_id: i32, list_price: f64) -> Result<(f64, bool), String> {
+    let (discount_percent, override_price): (f64, Option<f64>) = conn
+        .query_row(
+            "SELECT t.discount_percent,
+                    (SELECT price FROM price_tier_items WHERE tier_id = t.id AND product_id = ?2)
+             FROM price_tiers t WHERE t.id = ?1",
+            params![tier_id, product_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to load tier price: {}", e))?;
+
+    Ok((tier_price(list_price, discount_percent, override_price), override_price.is_some()))
+}
+
+/// Get all price tiers
+#[tauri::command]
+pub fn get_price_tiers(db: State<Database>) -> Result<Vec<PriceTier>, String> {
+    log::info!("get_price_tiers called");
+
+    let conn = db.get_conn()?;
+
+    let mut stmt = conn
+        .prepare("SELECT id FROM price_tiers ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, i32>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    ids.into_iter().map(|id| get_price_tier_by_id(&conn, id)).collect()
+}
+
+/// Create a price tier
+#[tauri::command]
+pub fn create_price_tier(input: PriceTierInput, db: State<Database>) -> Result<PriceTier, String> {
+    log::info!("create_price_tier called: {} ({}%)", input.name, input.discount_percent);
+
+    let name = validate_tier_input(&input)?;
+    let conn = db.get_conn()?;
+
+    conn.execute(
+        "INSERT INTO price_tiers (name, discount_percent, created_at, updated_at)
+         VALUES (?1, ?2, datetime('now'), datetime('now'))",
+        params![name, input.discount_percent],
+    )
+    .map_err(|e| format!("Failed to create price tier: {}", e))?;
+
+    get_price_tier_by_id(&conn, conn.last_insert_rowid() as i32)
+}
+
+/// Update a price tier. Existing invoices keep the prices they were billed at.
+#[tauri::command]
+pub fn update_price_tier(id: i32, input: PriceTierInput, db: State<Database>) -> Result<PriceTier, String> {
+    log::info!("update_price_tier called for id: {}", id);
+
+    let name = validate_tier_input(&input)?;
+    let conn = db.get_conn()?;
+
+    let affected = conn
+        .execute(
+            "UPDATE price_tiers SET name = ?1, discount_percent = ?2, updated_at = datetime('now') WHERE id = ?3",
+            params![name, input.discount_percent, id],
+        )
+        .map_err(|e| format!("Failed to update price tier: {}", e))?;
+
+    if affected == 0 {
+        return Err(format!("Price tier with id {} not found", id));
+    }
+
+    get_price_tier_by_id(&conn, id)
+}
+
+/// Delete a price tier; its customers go back to list price
+#[tauri::command]
+pub fn delete_price_tier(id: i32, db: State<Database>) -> Result<(), String> {
+    log::info!("delete_price_tier called for id: {}", id);
+
+    let mut conn = db.get_conn()?;
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    tx.execute("UPDATE customers SET price_tier_id = NULL WHERE price_tier_id = ?1", [id])
+        .map_err(|e| format!("Failed to unassign customers: {}", e))?;
+    tx.execute("DELETE FROM price_tier_items WHERE tier_id = ?1", [id])
+        .map_err(|e| format!("Failed to delete tier prices: {}", e))?;
+    let affected = tx
+        .execute("DELETE FROM price_tiers WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to delete price tier: {}", e))?;
+
+    if affected == 0 {
+        return Err(format!("Price tier with id {} not found", id));
+    }
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    Ok(())
+}
+
+/// Set or clear (price = None) a per-product price override for a tier
+#[tauri::command]
+pub fn set_price_tier_override(
+    tier_id: i32,
+    product_id: i32,
+    price: Option<f64>,
+    db: State<Database>,
+) -> Result<(), String> {
+    log::info!(
+        "set_price_tier_override called: tier {}, product {}, price {:?}",
+        tier_id,
+        product_id,
+        price
+    );
+
+    let conn = db.get_conn()?;
+    get_price_tier_by_id(&conn, tier_id)?;
+
+    match price {
+        Some(price) => {
+            if price < 0.0 {
+                return Err("Price cannot be negative".to_string());
+            }
+            conn.execute(
+                "INSERT INTO price_tier_items (tier_id, product_id, price) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(tier_id, product_id) DO UPDATE SET price = excluded.price",
+                params![tier_id, product_id, price],
+            )
+            .map_err(|e| format!("Failed to set tier price: {}", e))?;
+        }
+        None => {
+            conn.execute(
+                "DELETE FROM price_tier_items WHERE tier_id = ?1 AND product_id = ?2",
+                params![tier_id, product_id],
+            )
+            .map_err(|e| format!("Failed to clear tier price: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Assign a customer to a price tier, or back to list price with None
+#[tauri::command]
+pub fn set_customer_price_tier(
+    customer_id: i32,
+    tier_id: Option<i32>,
+    modified_by: Option<String>,
+    db: State<Database>,
+) -> Result<(), String> {
+    log::info!("set_customer_price_tier called: customer {}, tier {:?}", customer_id, tier_id);
+
+    let conn = db.get_conn()?;
+
+    if let Some(tier_id) = tier_id {
+        get_price_tier_by_id(&conn, tier_id)?;
+    }
+
+    let (name, old_tier_id): (String, Option<i32>) = conn
+        .query_row(
+            "SELECT name, price_tier_id FROM customers WHERE id = ?1",
+            [customer_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|_| format!("Customer with id {} not found", customer_id))?;
+
+    if old_tier_id == tier_id {
+        return Ok(());
+    }
+
+    conn.execute(
+        "UPDATE customers SET price_tier_id = ?1, updated_at = ?2 WHERE id = ?3",
+        params![tier_id, chrono::Utc::now().to_rfc3339(), customer_id],
+    )
+    .map_err(|e| format!("Failed to update customer price tier: {}", e))?;
+
+    let changes = serde_json::json!([{"field": "price_tier_id", "old": old_tier_id, "new": tier_id}]);
+    conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        ("customer", customer_id, &name, "updated", changes.to_string(), &modified_by),
+    )
+    .map_err(|e| format!("Failed to log modification: {}", e))?;
+
+    Ok(())
+}
+
+/// Price to prefill on the billing screen for a product and (optional) customer
+#[tauri::command]
+pub fn get_effective_price(
+    product_id: i32,
+    customer_id: Option<i32>,
+    db: State<Database>,
+) -> Result<EffectivePrice, String> {
+    log::info!(
+        "get_effective_price called: product {}, customer {:?}",
+        product_id,
+        customer_id
+    );
+
+    let conn = db.get_conn()?;
+
+    let list_price = list_price(&conn, product_id)?;
+
+    let tier = match customer_id {
+        Some(customer_id) => customer_price_tier(&conn, customer_id)?,
+        None => None,
+    };
+
+    let Some((tier_id, tier_name)) = tier else {
+        return Ok(EffectivePrice {
+            product_id,
+            list_price,
+            unit_price: list_price,
+            tier_id: None,
+            tier_name: None,
+            source: "list".to_string(),
+        });
+    };
+
+    let (unit_price, is_override) = tier_unit_price(&conn, tier_id, product_id, list_price)?;
+
+    Ok(EffectivePrice {
+        product_id,
+        list_price,
+        unit_price,
+        tier_id: Some(tier_id),
+        tier_name: Some(tier_name),
+        source: if is_override { "tier_override" } else { "tier_discount" }.to_string(),
+    })
+}
+
+/// Every product with its list price and price in a tier, for printing a rate card
+#[tauri::command]
+pub fn get_price_list(tier_id: i32, db: State<Database>) -> Result<Vec<PriceListEntry>, String> {
+    log::info!("get_price_list called for tier_id: {}", tier_id);
+
+    let conn = db.get_conn()?;
+    let tier = get_price_tier_by_id(&conn, tier_id)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT p.id, p.name, p.sku, p.category, {}, pti.price
+             FROM products p
+             LEFT JOIN price_tier_items pti ON pti.product_id = p.id AND pti.tier_id = ?1
+             ORDER BY p.category COLLATE NOCASE, p.name COLLATE NOCASE",
+            LIST_PRICE_SQL
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let entries = stmt
+        .query_map([tier_id], |row| {
+            let list_price: f64 = row.get(4)?;
+            let override_price: Option<f64> = row.get(5)?;
+            Ok(PriceListEntry {
+                product_id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                category: row.get(3)?,
+                list_price,
+                tier_price: tier_price(list_price, tier.discount_percent, override_price),
+                is_override: override_price.is_some(),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(entries)
+}
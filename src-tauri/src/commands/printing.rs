@@ -0,0 +1,251 @@
+/// Receipt Printing Commands
+/// Renders invoices as ESC/POS receipts and sends them to a network thermal printer
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+use rusqlite::Connection;
+use tauri::State;
+
+use crate::commands::get_all_settings;
+use crate::db::Database;
+use crate::services::money;
+use crate::services::receipt_service::{self, ReceiptData, ReceiptLine, ReceiptOptions};
+
+/// Default raw printing port used by most network thermal printers
+const DEFAULT_PRINTER_PORT: u16 = 9100;
+const PRINTER_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrintReceiptResult {
+    pub sent_to_printer: bool,
+    pub printer_address: Option<String>,
+    pub copies: u32,
+    /// Raw ESC/POS bytes, returned when no network printer is used
+    pub bytes: Option<Vec<u8>>,
+}
+
+/// Print an invoice as a thermal receipt.
+///
+/// Settings used: receipt_printer_address ("IP" or "IP:port"), receipt_paper_width ("58"/"80"),
+/// receipt_charset (e.g. "PC437"), receipt_copies and receipt_footer. When no printer address
+/// is configured, or `return_bytes` is true, the bytes are returned for a local printer plugin.
+#[tauri::command]
+pub fn print_receipt(
+    invoice_id: i32,
+    return_bytes: Option<bool>,
+    db: State<Database>,
+) -> Result<PrintReceiptResult, String> {
+    log::info!("print_receipt called for invoice_id: {}", invoice_id);
+
+    let settings = get_all_settings(db.clone())?;
+    let data = load_receipt_data(&*db.get_conn()?, invoice_id, &settings)?;
+    let options = receipt_options(&settings);
+    let bytes = receipt_service::render_receipt(&data, &options);
+
+    let printer_address = settings
+        .get("receipt_printer_address")
+        .map(|a| a.trim().to_string())
+        .filter(|a| !a.is_empty());
+
+    match printer_address {
+        Some(address) if !return_bytes.unwrap_or(false) => {
+            send_to_network_printer(&address, &bytes)?;
+            log::info!("Sent receipt for invoice {} to printer {}", invoice_id, address);
+            Ok(PrintReceiptResult {
+                sent_to_printer: true,
+                printer_address: Some(address),
+                copies: options.copies,
+                bytes: None,
+            })
+        }
+        _ => Ok(PrintReceiptResult {
+            sent_to_printer: false,
+            printer_address: None,
+            copies: options.copies,
+            bytes: Some(bytes),
+        }),
+    }
+}
+
+fn receipt_options(settings: &HashMap<String, String>) -> ReceiptOptions {
+    let defaults = ReceiptOptions::default();
+    ReceiptOptions {
+        paper_width_mm: settings
+            .get("receipt_paper_width")
+            .and_then(|w| w.trim().trim_end_matches("mm").parse().ok())
+            .unwrap_or(defaults.paper_width_mm),
+        charset: settings
+            .get("receipt_charset")
+            .filter(|c| !c.trim().is_empty())
+            .cloned()
+            .unwrap_or(defaults.charset),
+        copies: settings
+            .get("receipt_copies")
+            .and_then(|c| c.trim().parse().ok())
+            .unwrap_or(defaults.copies)
+            .clamp(1, 5),
+        footer: settings.get("receipt_footer").cloned(),
+    }
+}
+
+/// The invoice header fields a receipt prints
+struct ReceiptInvoice {
+    invoice_number: String,
+    created_at: String,
+    total_amount: f64,
+    discount_amount: f64,
+    payment_method: Option<String>,
+    cgst: Option<f64>,
+    sgst: Option<f64>,
+    igst: Option<f64>,
+    customer_name: Option<String>,
+    round_off: f64,
+}
+
+fn load_receipt_data(
+    conn: &Connection,
+    invoice_id: i32,
+    settings: &HashMap<String, String>,
+) -> Result<ReceiptData, String> {
+    let invoice = conn
+        .query_row(
+            "SELECT i.invoice_number, i.created_at, i.total_amount, i.discount_amount, i.payment_method,
+                    i.cgst_amount, i.sgst_amount, i.igst_amount, c.name, COALESCE(i.round_off, 0)
+             FROM invoices i
+             LEFT JOIN customers c ON i.customer_id = c.id
+             WHERE i.id = ?1",
+            [invoice_id],
+            |row| {
+                Ok(ReceiptInvoice {
+                    invoice_number: row.get(0)?,
+                    created_at: row.get(1)?,
+                    total_amount: row.get(2)?,
+                    discount_amount: row.get(3)?,
+                    payment_method: row.get(4)?,
+                    cgst: row.get(5)?,
+                    sgst: row.get(6)?,
+                    igst: row.get(7)?,
+                    customer_name: row.get(8)?,
+                    round_off: row.get(9)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Invoice not found: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT COALESCE(ii.product_name, p.name, 'Item'), ii.quantity, ii.unit_price, COALESCE(ii.discount_amount, 0)
+             FROM invoice_items ii
+             LEFT JOIN products p ON ii.product_id = p.id
+             WHERE ii.invoice_id = ?1
+             ORDER BY ii.id",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let item_iter = stmt
+        .query_map([invoice_id], |row| {
+            let quantity: f64 = row.get(1)?;
+            let unit_price: f64 = row.get(2)?;
+            let discount: f64 = row.get(3)?;
+            Ok(ReceiptLine {
+                name: row.get(0)?,
+                quantity,
+                unit_price,
+                discount,
+                amount: money::sub(money::line_total(unit_price, quantity), discount),
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut items = Vec::new();
+    for item in item_iter {
+        items.push(item.map_err(|e| e.to_string())?);
+    }
+
+    // Line discounts are shares of the invoice discount; print only what the lines don't carry
+    let subtotal = money::sum(items.iter().map(|i| i.amount));
+    let discount = money::sub(invoice.discount_amount, money::sum(items.iter().map(|i| i.discount))).max(0.0);
+    let setting = |key: &str| settings.get(key).map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+    Ok(ReceiptData {
+        company_name: setting("invoice_company_name").unwrap_or_else(|| "Inventory System".to_string()),
+        company_address: setting("invoice_company_address"),
+        company_phone: setting("invoice_company_phone"),
+        date: invoice.created_at.get(..10).unwrap_or(&invoice.created_at).to_string(),
+        invoice_number: invoice.invoice_number,
+        customer_name: invoice.customer_name,
+        items,
+        subtotal,
+        discount,
+        cgst: invoice.cgst.unwrap_or(0.0),
+        sgst: invoice.sgst.unwrap_or(0.0),
+        igst: invoice.igst.unwrap_or(0.0),
+        round_off: invoice.round_off,
+        total: invoice.total_amount,
+        payment_method: invoice.payment_method,
+    })
+}
+
+fn send_to_network_printer(address: &str, bytes: &[u8]) -> Result<(), String> {
+    let target = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, DEFAULT_PRINTER_PORT)
+    };
+
+    let socket_addr = target
+        .to_socket_addrs()
+        .map_err(|e| format!("Invalid printer address '{}': {}", target, e))?
+        .next()
+        .ok_or_else(|| format!("Invalid printer address '{}'", target))?;
+
+    let timeout = Duration::from_secs(PRINTER_TIMEOUT_SECS);
+    let mut stream = TcpStream::connect_timeout(&socket_addr, timeout)
+        .map_err(|e| format!("Failed to connect to printer at {}: {}", target, e))?;
+    stream
+        .set_write_timeout(Some(timeout))
+        .map_err(|e| e.to_string())?;
+
+    stream
+        .write_all(bytes)
+        .and_then(|_| stream.flush())
+        .map_err(|e| format!("Failed to send receipt to printer: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[test]
+    fn test_receipt_nets_line_discounts() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        conn.execute("INSERT INTO products (name, sku, price, stock_quantity) VALUES ('Rice', 'RICE', 100, 10)", []).unwrap();
+        let product_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO invoices (invoice_number, total_amount, discount_amount) VALUES ('INV-1', 330, 70)",
+            [],
+        )
+        .unwrap();
+        let invoice_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO invoice_items (invoice_id, product_id, quantity, unit_price, product_name, discount_amount)
+             VALUES (?1, ?2, 2, 100, 'Rice', 50), (?1, ?2, 2, 100, 'Rice', NULL)",
+            [invoice_id, product_id],
+        )
+        .unwrap();
+
+        let data = load_receipt_data(&conn, invoice_id as i32, &HashMap::new()).unwrap();
+        assert_eq!((data.items[0].discount, data.items[0].amount), (50.0, 150.0));
+        assert_eq!((data.items[1].discount, data.items[1].amount), (0.0, 200.0));
+        // Subtotal - remaining discount still adds up to the invoice total
+        assert_eq!((data.subtotal, data.discount), (350.0, 20.0));
+        assert_eq!(money::sub(data.subtotal, data.discount), data.total);
+    }
+}
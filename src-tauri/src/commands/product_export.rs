@@ -0,0 +1,388 @@
+/// Product Export
+/// Column-selectable, sorted product export to CSV or XLSX. Derived columns reuse the SQL behind
+/// get_products (and the FIFO stock valuation from analytics), so exported figures match the UI.
+/// Rows are read with a cursor and written one at a time, with progress on "data-transfer-progress".
+use std::fs::File;
+
+use rusqlite::Connection;
+use rust_xlsxwriter::{Color, Format, FormatBorder, Workbook, Worksheet};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::analytics::{BATCH_COSTS_CTE, UNIT_COST_SQL};
+use crate::commands::data_management::{
+    emit_progress, to_ist, DataOperationState, DataTransferSummary, EXPORT_PROGRESS_INTERVAL,
+};
+use crate::commands::products::{
+    TOTAL_PURCHASED_COST_SQL, TOTAL_PURCHASED_QUANTITY_SQL, TOTAL_SOLD_AMOUNT_SQL, TOTAL_SOLD_SQL,
+};
+use crate::commands::variants::DISPLAY_NAME_SQL;
+use crate::db::Database;
+use crate::services::{money, quantity};
+
+/// Columns exported when the caller doesn't pick any
+const DEFAULT_COLUMNS: &[&str] = &["id", "name", "sku", "price", "selling_price", "stock_quantity", "supplier"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnKind {
+    Text,
+    Integer,
+    /// Stock or sold units, fractional for products sold by weight or length
+    Quantity,
+    Currency,
+    /// Stored UTC, exported in IST like the CSV export
+    DateTime,
+}
+
+struct ExportColumn {
+    key: &'static str,
+    header: &'static str,
+    kind: ColumnKind,
+    sql: String,
+}
+
+/// Whitelisted export columns; the key is all a caller can choose, never the SQL
+fn export_column(key: &str) -> Option<ExportColumn> {
+    let (key, header, kind, sql) = match key {
+        "id" => ("id", "ID", ColumnKind::Integer, "p.id".to_string()),
+        "name" => ("name", "Name", ColumnKind::Text, DISPLAY_NAME_SQL.to_string()),
+        "sku" => ("sku", "SKU", ColumnKind::Text, "p.sku".to_string()),
+        "category" => ("category", "Category", ColumnKind::Text, "COALESCE(cat.name, p.category)".to_string()),
+        "supplier" => ("supplier", "Supplier", ColumnKind::Text, "s.name".to_string()),
+        "price" => ("price", "Cost Price", ColumnKind::Currency, "p.price".to_string()),
+        "selling_price" => ("selling_price", "Selling Price", ColumnKind::Currency, "p.selling_price".to_string()),
+        "initial_stock" => ("initial_stock", "Initial Stock", ColumnKind::Quantity, "p.initial_stock".to_string()),
+        "stock_quantity" => ("stock_quantity", "Stock Quantity", ColumnKind::Quantity, "p.stock_quantity".to_string()),
+        "total_sold" => ("total_sold", "Total Sold", ColumnKind::Quantity, TOTAL_SOLD_SQL.to_string()),
+        "total_sold_amount" => ("total_sold_amount", "Sales Amount", ColumnKind::Currency, TOTAL_SOLD_AMOUNT_SQL.to_string()),
+        "total_purchased_quantity" => (
+            "total_purchased_quantity",
+            "Purchased Quantity",
+            ColumnKind::Quantity,
+            TOTAL_PURCHASED_QUANTITY_SQL.to_string(),
+        ),
+        "total_purchased_cost" => (
+            "total_purchased_cost",
+            "Purchased Cost",
+            ColumnKind::Currency,
+            TOTAL_PURCHASED_COST_SQL.to_string(),
+        ),
+        "stock_value" => (
+            "stock_value",
+            "Stock Value",
+            ColumnKind::Currency,
+            format!("p.stock_quantity * {}", UNIT_COST_SQL),
+        ),
+        "created_at" => ("created_at", "Created At", ColumnKind::DateTime, "p.created_at".to_string()),
+        "updated_at" => ("updated_at", "Updated At", ColumnKind::DateTime, "p.updated_at".to_string()),
+        _ => return None,
+    };
+    Some(ExportColumn { key, header, kind, sql })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductExportSort {
+    /// Any export column key
+    pub column: String,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProductExportOptions {
+    /// Column keys in output order; empty uses the default set
+    pub columns: Vec<String>,
+    /// Applied in order; empty sorts by name
+    pub sort: Vec<ProductExportSort>,
+    /// "csv" (default) or "xlsx"
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExportFormat {
+    Csv,
+    Xlsx,
+}
+
+enum Cell {
+    Empty,
+    Text(String),
+    Number(f64),
+}
+
+/// Where rows go: a CSV file written as it goes, or a worksheet saved at the end
+enum RowSink {
+    Csv(csv::Writer<File>),
+    Xlsx { worksheet: Box<Worksheet>, row: u32, currency: Format, integer: Format },
+}
+
+impl RowSink {
+    fn open(format: ExportFormat, path: &str, columns: &[ExportColumn]) -> Result<Self, String> {
+        match format {
+            ExportFormat::Csv => {
+                let mut wtr = csv::Writer::from_path(path).map_err(|e| format!("Failed to create export file: {}", e))?;
+                wtr.write_record(columns.iter().map(|c| c.header)).map_err(|e| e.to_string())?;
+                Ok(RowSink::Csv(wtr))
+            }
+            ExportFormat::Xlsx => {
+                let mut worksheet = Worksheet::new();
+                worksheet.set_name("Products").map_err(|e| e.to_string())?;
+                let header = Format::new()
+                    .set_bold()
+                    .set_background_color(Color::RGB(0xD9E1F2))
+                    .set_border_bottom(FormatBorder::Thin);
+                for (col, column) in columns.iter().enumerate() {
+                    worksheet
+                        .write_string_with_format(0, col as u16, column.header, &header)
+                        .map_err(|e| e.to_string())?;
+                    let width = match column.kind {
+                        ColumnKind::Text => 28.0,
+                        ColumnKind::DateTime => 20.0,
+                        _ => 14.0,
+                    };
+                    worksheet.set_column_width(col as u16, width).map_err(|e| e.to_string())?;
+                }
+                worksheet.set_freeze_panes(1, 0).map_err(|e| e.to_string())?;
+                Ok(RowSink::Xlsx {
+                    worksheet: Box::new(worksheet),
+                    row: 1,
+                    currency: Format::new().set_num_format("#,##0.00"),
+                    integer: Format::new().set_num_format("0"),
+                })
+            }
+        }
+    }
+
+    fn write_row(&mut self, columns: &[ExportColumn], cells: &[Cell]) -> Result<(), String> {
+        match self {
+            RowSink::Csv(wtr) => {
+                let record = cells.iter().zip(columns).map(|(cell, column)| match cell {
+                    Cell::Empty => String::new(),
+                    Cell::Text(text) => text.clone(),
+                    Cell::Number(n) if column.kind == ColumnKind::Currency => format!("{:.2}", n),
+                    Cell::Number(n) => n.to_string(),
+                });
+                wtr.write_record(record).map_err(|e| e.to_string())
+            }
+            RowSink::Xlsx { worksheet, row, currency, integer } => {
+                for (col, (cell, column)) in cells.iter().zip(columns).enumerate() {
+                    let col = col as u16;
+                    match cell {
+                        Cell::Empty => {}
+                        Cell::Text(text) => {
+                            worksheet.write_string(*row, col, text).map_err(|e| e.to_string())?;
+                        }
+                        Cell::Number(n) => {
+                            let format = if column.kind == ColumnKind::Currency { &*currency } else { &*integer };
+                            worksheet.write_number_with_format(*row, col, *n, format).map_err(|e| e.to_string())?;
+                        }
+                    }
+                }
+                *row += 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn finish(self, path: &str) -> Result<(), String> {
+        match self {
+            RowSink::Csv(mut wtr) => wtr.flush().map_err(|e| format!("Failed to write export file: {}", e)),
+            RowSink::Xlsx { worksheet, .. } => {
+                let mut workbook = Workbook::new();
+                workbook.push_worksheet(*worksheet);
+                workbook.save(path).map_err(|e| format!("Failed to write export file: {}", e))
+            }
+        }
+    }
+}
+
+fn read_cell(row: &rusqlite::Row, index: usize, kind: ColumnKind) -> rusqlite::Result<Cell> {
+    Ok(match kind {
+        ColumnKind::Text => row.get::<_, Option<String>>(index)?.map_or(Cell::Empty, Cell::Text),
+        ColumnKind::DateTime => row.get::<_, Option<String>>(index)?.map_or(Cell::Empty, |d| Cell::Text(to_ist(&d))),
+        ColumnKind::Integer => row.get::<_, Option<i64>>(index)?.map_or(Cell::Empty, |n| Cell::Number(n as f64)),
+        ColumnKind::Quantity => row.get::<_, Option<f64>>(index)?.map_or(Cell::Empty, |n| Cell::Number(quantity::round_quantity(n))),
+        ColumnKind::Currency => row.get::<_, Option<f64>>(index)?.map_or(Cell::Empty, |n| Cell::Number(money::round_money(n))),
+    })
+}
+
+/// Export products with the chosen columns and sort to `file_path` as CSV or XLSX.
+/// Returns the number of products written.
+#[tauri::command]
+pub async fn export_products(
+    options: ProductExportOptions,
+    file_path: String,
+    app: AppHandle,
+    ops: State<'_, DataOperationState>,
+    db: State<'_, Database>,
+) -> Result<i32, String> {
+    log::info!("export_products called with {:?} -> {}", options, file_path);
+
+    ops.reset_cancelled();
+    let conn = db.get_conn()?;
+
+    let result = export_products_internal(&conn, &options, &file_path, |processed, total| {
+        if ops.is_cancelled() {
+            return Err("Export cancelled".to_string());
+        }
+        if processed % EXPORT_PROGRESS_INTERVAL as i32 == 0 || processed == total {
+            emit_progress(&app, "export", "inventory", processed, total);
+        }
+        Ok(())
+    });
+
+    let total = match result {
+        Ok(total) => total,
+        Err(e) => {
+            let _ = std::fs::remove_file(&file_path);
+            return Err(e);
+        }
+    };
+
+    let _ = app.emit("data-transfer-complete", DataTransferSummary {
+        operation: "export".to_string(),
+        entity_type: "inventory".to_string(),
+        total,
+        inserted: total,
+        ..Default::default()
+    });
+
+    log::info!("Exported {} products to {}", total, file_path);
+    Ok(total)
+}
+
+/// `on_row(processed, total)` runs after each row; an error stops the export
+pub(crate) fn export_products_internal(
+    conn: &Connection,
+    options: &ProductExportOptions,
+    file_path: &str,
+    mut on_row: impl FnMut(i32, i32) -> Result<(), String>,
+) -> Result<i32, String> {
+    let format = match options.format.as_deref() {
+        None | Some("csv") => ExportFormat::Csv,
+        Some("xlsx") => ExportFormat::Xlsx,
+        Some(other) => return Err(format!("Unknown export format '{}', expected csv or xlsx", other)),
+    };
+
+    let keys: Vec<&str> = if options.columns.is_empty() {
+        DEFAULT_COLUMNS.to_vec()
+    } else {
+        options.columns.iter().map(String::as_str).collect()
+    };
+    let columns = keys
+        .iter()
+        .map(|key| export_column(key).ok_or_else(|| format!("Unknown export column '{}'", key)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut order_by = Vec::new();
+    for sort in &options.sort {
+        let column = export_column(&sort.column).ok_or_else(|| format!("Unknown sort column '{}'", sort.column))?;
+        order_by.push(format!("{} {}", column.sql, if sort.descending { "DESC" } else { "ASC" }));
+    }
+    order_by.push(format!("{} COLLATE NOCASE", DISPLAY_NAME_SQL));
+    order_by.push("p.id".to_string());
+
+    let total: i32 = conn
+        .query_row("SELECT COUNT(*) FROM products", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    let select = columns.iter().map(|c| format!("{} AS {}", c.sql, c.key)).collect::<Vec<_>>().join(",\n               ");
+    let query = format!(
+        "WITH {}
+        SELECT {}
+        FROM products p
+        LEFT JOIN invoice_items ii ON p.id = ii.product_id
+        LEFT JOIN batch_costs bc ON bc.product_id = p.id
+        LEFT JOIN suppliers s ON s.id = p.supplier_id
+        LEFT JOIN categories cat ON cat.id = p.category_id
+        GROUP BY p.id
+        ORDER BY {}",
+        BATCH_COSTS_CTE,
+        select,
+        order_by.join(", ")
+    );
+
+    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+
+    let mut sink = RowSink::open(format, file_path, &columns)?;
+    let mut written = 0;
+    on_row(written, total)?;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let cells = columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| read_cell(row, index, column.kind))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read product: {}", e))?;
+        sink.write_row(&columns, &cells)?;
+
+        written += 1;
+        on_row(written, total)?;
+    }
+    sink.finish(file_path)?;
+
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::invoices::create_invoice_internal;
+    use crate::commands::products::{create_product_internal, CreateProductInput};
+    use crate::test_support::{invoice_input, product_input, TestDb};
+
+    fn product(conn: &Connection, name: &str, price: f64, stock: i32) -> i32 {
+        let input = CreateProductInput {
+            name: name.to_string(),
+            selling_price: Some(price * 2.0),
+            ..product_input(&name.to_uppercase(), price, stock, None)
+        };
+        create_product_internal(conn, input).unwrap().id
+    }
+
+    #[test]
+    fn test_export_selected_columns_sorted() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let pen = product(&conn, "Pen", 10.0, 20);
+        let _ink = product(&conn, "Ink", 25.0, 4);
+        create_invoice_internal(&mut conn, invoice_input(None, vec![(pen, 5, 20.0)])).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("product_export_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv_path = dir.join("products.csv").to_string_lossy().to_string();
+
+        let options = ProductExportOptions {
+            columns: ["name", "total_sold", "total_purchased_cost", "stock_value"].map(String::from).to_vec(),
+            sort: vec![ProductExportSort { column: "stock_value".to_string(), descending: true }],
+            format: None,
+        };
+        let mut progress = Vec::new();
+        let written = export_products_internal(&conn, &options, &csv_path, |done, total| {
+            progress.push((done, total));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(written, 2);
+        assert_eq!(progress.last(), Some(&(2, 2)));
+
+        // Pen: 15 left at 10 = 150; Ink: 4 at 25 = 100
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "Name,Total Sold,Purchased Cost,Stock Value");
+        assert_eq!(lines[1], "Pen,5,200.00,150.00");
+        assert_eq!(lines[2], "Ink,0,100.00,100.00");
+
+        let xlsx_path = dir.join("products.xlsx").to_string_lossy().to_string();
+        let options = ProductExportOptions { format: Some("xlsx".to_string()), ..options };
+        export_products_internal(&conn, &options, &xlsx_path, |_, _| Ok(())).unwrap();
+        assert!(std::fs::read(&xlsx_path).unwrap().starts_with(b"PK"));
+
+        let bad = ProductExportOptions { columns: vec!["p.price; DROP TABLE products".to_string()], ..Default::default() };
+        assert!(export_products_internal(&conn, &bad, &csv_path, |_, _| Ok(())).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,9 +1,21 @@
-use crate::db::{Database, Product};
-use crate::commands::PaginatedResult;
+use crate::db::{Database, InventoryBatchWithDetails, Product};
+use crate::commands::{validate_pagination, PaginatedResult};
+use crate::commands::biometric::{consume_action_approval, price_change_requires_biometric};
+use crate::commands::bundles;
+use crate::commands::categories;
+use crate::commands::price_history;
+use crate::commands::purchase_orders;
+use crate::commands::reservations;
+use crate::commands::variants;
+use crate::commands::images::remove_image_files;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
 use crate::services::inventory_service;
+use crate::services::money;
+use crate::services::quantity;
 use chrono::Utc;
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, State};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateProductInput {
@@ -11,10 +23,15 @@ pub struct CreateProductInput {
     pub sku: String,
     pub price: f64,
     pub selling_price: Option<f64>,
-    pub stock_quantity: i32,
+    pub stock_quantity: f64,
     pub supplier_id: Option<i32>,
     pub amount_paid: Option<f64>,
     pub category: Option<String>,
+    /// One of services::quantity::UNITS; "pcs" when not given
+    #[serde(default)]
+    pub unit: Option<String>,
+    #[serde(default)]
+    pub allow_fractional: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,158 +41,176 @@ pub struct UpdateProductInput {
     pub sku: String,
     pub price: f64,
     pub selling_price: Option<f64>,
-    pub stock_quantity: i32,
+    pub stock_quantity: f64,
     pub supplier_id: Option<i32>,
     pub category: Option<String>,
+    /// Keeps the product's unit when not given
+    #[serde(default)]
+    pub unit: Option<String>,
+    /// Keeps the product's setting when not given
+    #[serde(default)]
+    pub allow_fractional: Option<bool>,
 }
 
-/// Get all products, optionally filtered by search query
-/// Get all products, optionally filtered by search query, with pagination
+// Derived product figures shown in the product list. The sold figures aggregate over
+// `LEFT JOIN invoice_items ii ON p.id = ii.product_id` grouped by p.id.
+
+/// Units sold across all invoices
+pub(crate) const TOTAL_SOLD_SQL: &str = "COALESCE(SUM(ii.quantity), 0)";
+
+/// Revenue after item discounts
+pub(crate) const TOTAL_SOLD_AMOUNT_SQL: &str =
+    "COALESCE(SUM(ii.quantity * ii.unit_price - COALESCE(ii.discount_amount, 0)), 0)";
+
+/// Opening stock at cost plus received purchase orders, in the base currency
+pub(crate) const TOTAL_PURCHASED_COST_SQL: &str = "(
+    COALESCE(p.initial_stock * p.price, 0) +
+    COALESCE((
+        SELECT SUM(poi.total_cost * po.exchange_rate)
+        FROM purchase_order_items poi
+        JOIN purchase_orders po ON poi.po_id = po.id
+        WHERE poi.product_id = p.id AND po.status = 'received'
+    ), 0)
+)";
+
+/// Opening stock plus units on received purchase orders
+pub(crate) const TOTAL_PURCHASED_QUANTITY_SQL: &str = "(
+    COALESCE(p.initial_stock, 0) +
+    COALESCE((
+        SELECT SUM(poi.quantity)
+        FROM purchase_order_items poi
+        JOIN purchase_orders po ON poi.po_id = po.id
+        WHERE poi.product_id = p.id AND po.status = 'received'
+    ), 0)
+)";
+
+/// Get all products, optionally filtered by search query and category, with pagination.
+/// `category_id` also matches products in its subcategories; the `category` text filter is
+/// deprecated in favour of it. Variants are listed individually unless `expand_variants` is
+/// false, in which case only parents are returned with their variants' stock and sales rolled
+/// into their figures.
 #[tauri::command]
 pub fn get_products(
     search: Option<String>,
     page: i32,
     page_size: i32,
+    expand_variants: Option<bool>,
+    category_id: Option<i32>,
+    category: Option<String>,
     db: State<Database>
 ) -> Result<PaginatedResult<Product>, String> {
-    log::info!("get_products called with search: {:?}, page: {}, page_size: {}", search, page, page_size);
+    log::info!(
+        "get_products called with search: {:?}, page: {}, page_size: {}, expand_variants: {:?}, category_id: {:?}, category: {:?}",
+        search, page, page_size, expand_variants, category_id, category
+    );
+    let roll_up_variants = !expand_variants.unwrap_or(true);
 
     let conn = db.get_conn()?;
 
-    let offset = (page - 1) * page_size;
-    let limit = page_size;
+    let (limit, offset) = validate_pagination(page, page_size)?;
+
+    // Search by name or SKU; variants also match on their parent's name, and rolled-up
+    // parents match on their variants' SKUs. NULL filters match everything, so the count and
+    // page queries share one statement shape.
+    let search_pattern = search.map(|term| format!("%{}%", term));
+    let filter = format!(
+        "WHERE {}
+           AND (?2 IS NULL OR p.category_id = ?2 OR p.category_id IN (SELECT id FROM categories WHERE parent_id = ?2))
+           AND (?3 IS NULL OR p.category = ?3 COLLATE NOCASE)",
+        if roll_up_variants {
+            "(?1 IS NULL OR p.name LIKE ?1 OR p.sku LIKE ?1
+                  OR p.id IN (SELECT parent_product_id FROM products WHERE sku LIKE ?1))
+             AND p.parent_product_id IS NULL"
+        } else {
+            "(?1 IS NULL OR p.name LIKE ?1 OR p.sku LIKE ?1
+                  OR p.parent_product_id IN (SELECT id FROM products WHERE name LIKE ?1))"
+        }
+    );
 
-    let mut products = Vec::new();
-    let total_count: i64;
+    let total_count: i64 = conn
+        .query_row(
+            &format!("SELECT COUNT(*) FROM products p {}", filter),
+            rusqlite::params![search_pattern, category_id, category],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
 
-    // Modified query to include total_sold, total_purchased_cost, total_purchased_quantity, and total_sold_amount
-    let base_query = "
+    // Includes total_sold, total_purchased_cost, total_purchased_quantity, and total_sold_amount
+    let query = format!("
         SELECT p.id, p.name, p.sku, p.price, p.selling_price, p.initial_stock, p.stock_quantity,
                p.supplier_id, p.created_at, p.updated_at, p.image_path, p.category,
-               COALESCE(SUM(ii.quantity), 0) as total_sold,
-               (
-                   COALESCE(p.initial_stock * p.price, 0) +
-                   COALESCE((
-                       SELECT SUM(poi.total_cost)
-                       FROM purchase_order_items poi
-                       JOIN purchase_orders po ON poi.po_id = po.id
-                       WHERE poi.product_id = p.id AND po.status = 'received'
-                   ), 0)
-               ) as total_purchased_cost,
-               (
-                   COALESCE(p.initial_stock, 0) +
-                   COALESCE((
-                       SELECT SUM(poi.quantity)
-                       FROM purchase_order_items poi
-                       JOIN purchase_orders po ON poi.po_id = po.id
-                       WHERE poi.product_id = p.id AND po.status = 'received'
-                   ), 0)
-               ) as total_purchased_quantity,
-               COALESCE(SUM(ii.quantity * ii.unit_price - COALESCE(ii.discount_amount, 0)), 0) as total_sold_amount
+               {} as total_sold,
+               {} as total_purchased_cost,
+               {} as total_purchased_quantity,
+               {} as total_sold_amount,
+               p.parent_product_id, p.variant_attributes, {} as display_name, p.category_id,
+               p.unit, p.allow_fractional
         FROM products p
         LEFT JOIN invoice_items ii ON p.id = ii.product_id
-    ";
-    
-    // We need to GROUP BY p.id to get correct SUM
-    let group_by = "GROUP BY p.id";
-
-    let count_query = "SELECT COUNT(DISTINCT p.id) FROM products p";
-
-    if let Some(search_term) = search {
-        // Search by name or SKU
-        let search_pattern = format!("%{}%", search_term);
-        let where_clause = "WHERE p.name LIKE ?1 OR p.sku LIKE ?1";
-        
-        // Get total count
-        let count_sql = format!("{} {}", count_query, where_clause);
-        total_count = conn
-            .query_row(&count_sql, [&search_pattern], |row| row.get(0))
-            .map_err(|e| e.to_string())?;
-
-        // Get paginated items
-        // Note: ORDER BY name is standard for search
-        let query = format!("{} {} {} ORDER BY p.created_at DESC, p.name ASC LIMIT ?2 OFFSET ?3", base_query, where_clause, group_by);
-        let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
-
-        let product_iter = stmt
-            .query_map(rusqlite::params![&search_pattern, limit, offset], |row| {
-                Ok(Product {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    sku: row.get(2)?,
-                    price: row.get(3)?,
-                    selling_price: row.get(4)?,
-                    initial_stock: row.get(5)?,
-                    stock_quantity: row.get(6)?,
-                    supplier_id: row.get(7)?,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                    image_path: row.get(10)?,
-                    category: row.get(11)?,
-                    total_sold: {
-                        let sold: i64 = row.get(12)?;
-                        if sold > 0 { Some(sold) } else { None }
-                    },
-                    initial_stock_sold: None,
-                    total_purchased_cost: row.get(13)?,
-                    total_purchased_quantity: row.get(14)?,
-                    total_sold_amount: {
-                        let amount: f64 = row.get(15)?;
-                        if amount > 0.0 { Some(amount) } else { None }
-                    },
-                    quantity_sold: None,
-                    sold_revenue: None,
-                })
-            })
-            .map_err(|e| e.to_string())?;
-
-        for product in product_iter {
-            products.push(product.map_err(|e| e.to_string())?);
-        }
-    } else {
-        // Get total count
-        total_count = conn
-            .query_row(count_query, [], |row| row.get(0))
-            .map_err(|e| e.to_string())?;
+        {}
+        GROUP BY p.id
+        ORDER BY p.created_at DESC, p.name ASC
+        LIMIT ?4 OFFSET ?5
+    ",
+        TOTAL_SOLD_SQL,
+        TOTAL_PURCHASED_COST_SQL,
+        TOTAL_PURCHASED_QUANTITY_SQL,
+        TOTAL_SOLD_AMOUNT_SQL,
+        variants::DISPLAY_NAME_SQL,
+        filter
+    );
 
-        // Get paginated items
-        let query = format!("{} {} ORDER BY p.created_at DESC, p.name ASC LIMIT ?1 OFFSET ?2", base_query, group_by);
-        let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
+    let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
 
-        let product_iter = stmt
-            .query_map([limit, offset], |row| {
-                Ok(Product {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    sku: row.get(2)?,
-                    price: row.get(3)?,
-                    selling_price: row.get(4)?,
-                    initial_stock: row.get(5)?,
-                    stock_quantity: row.get(6)?,
-                    supplier_id: row.get(7)?,
-                    created_at: row.get(8)?,
-                    updated_at: row.get(9)?,
-                    image_path: row.get(10)?,
-                    category: row.get(11)?,
-                    total_sold: {
-                        let sold: i64 = row.get(12)?;
-                        if sold > 0 { Some(sold) } else { None }
-                    },
-                    initial_stock_sold: None,
-                    total_purchased_cost: row.get(13)?,
-                    total_purchased_quantity: row.get(14)?,
-                    total_sold_amount: {
-                        let amount: f64 = row.get(15)?;
-                        if amount > 0.0 { Some(amount) } else { None }
-                    },
-                    quantity_sold: None,
-                    sold_revenue: None,
-                })
+    let product_iter = stmt
+        .query_map(rusqlite::params![search_pattern, category_id, category, limit, offset], |row| {
+            Ok(Product {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                price: row.get(3)?,
+                selling_price: row.get(4)?,
+                initial_stock: row.get(5)?,
+                stock_quantity: row.get(6)?,
+                supplier_id: row.get(7)?,
+                created_at: row.get(8)?,
+                updated_at: row.get(9)?,
+                image_path: row.get(10)?,
+                category: row.get(11)?,
+                category_id: row.get(19)?,
+                total_sold: {
+                    let sold = quantity::round_quantity(row.get(12)?);
+                    if sold > 0.0 { Some(sold) } else { None }
+                },
+                initial_stock_sold: None,
+                total_purchased_cost: row.get(13)?,
+                total_purchased_quantity: row.get::<_, Option<f64>>(14)?.map(quantity::round_quantity),
+                total_sold_amount: {
+                    let amount: f64 = row.get(15)?;
+                    if amount > 0.0 { Some(amount) } else { None }
+                },
+                available_quantity: None,
+                on_order_quantity: None,
+                quantity_sold: None,
+                parent_product_id: row.get(16)?,
+                variant_attributes: row.get(17)?,
+                display_name: row.get(18)?,
+                variant_count: None,
+                sold_revenue: None,
+                unit: row.get(20)?,
+                allow_fractional: row.get(21)?,
             })
-            .map_err(|e| e.to_string())?;
+        })
+        .map_err(|e| e.to_string())?;
 
-        for product in product_iter {
-            products.push(product.map_err(|e| e.to_string())?);
+    let mut products = Vec::new();
+    for product in product_iter {
+        products.push(product.map_err(|e| e.to_string())?);
+    }
+
+    if roll_up_variants {
+        for product in products.iter_mut() {
+            variants::roll_up(&conn, product)?;
         }
     }
 
@@ -192,27 +227,35 @@ pub fn get_product(id: i32, db: State<Database>) -> Result<Product, String> {
     log::info!("get_product called with id: {}", id);
 
     let conn = db.get_conn()?;
+    get_product_internal(&conn, id)
+}
 
-    let product = conn
+pub(crate) fn get_product_internal(conn: &Connection, id: i32) -> Result<Product, String> {
+    let mut product = conn
         .query_row(
-            "SELECT p.id, p.name, p.sku, p.price, p.selling_price, p.initial_stock, p.stock_quantity, 
-                    p.supplier_id, p.created_at, p.updated_at, p.image_path, p.category,
-                    COALESCE(SUM(ii.quantity), 0) as total_sold,
-                    (SELECT quantity_remaining FROM inventory_batches WHERE product_id = p.id AND po_item_id IS NULL LIMIT 1) as initial_remaining
-             FROM products p
-             LEFT JOIN invoice_items ii ON p.id = ii.product_id
-             WHERE p.id = ?1
-             GROUP BY p.id",
+            &format!(
+                "SELECT p.id, p.name, p.sku, p.price, p.selling_price, p.initial_stock, p.stock_quantity,
+                        p.supplier_id, p.created_at, p.updated_at, p.image_path, p.category,
+                        COALESCE(SUM(ii.quantity), 0) as total_sold,
+                        (SELECT quantity_remaining FROM inventory_batches WHERE product_id = p.id AND po_item_id IS NULL LIMIT 1) as initial_remaining,
+                        p.parent_product_id, p.variant_attributes, {} as display_name, p.category_id,
+                        p.unit, p.allow_fractional
+                 FROM products p
+                 LEFT JOIN invoice_items ii ON p.id = ii.product_id
+                 WHERE p.id = ?1
+                 GROUP BY p.id",
+                variants::DISPLAY_NAME_SQL
+            ),
             [id],
             |row| {
-                let initial_stock: Option<i32> = row.get(5)?;
-                let initial_remaining: Option<i32> = row.get(13)?;
+                let initial_stock: Option<f64> = row.get(5)?;
+                let initial_remaining: Option<f64> = row.get(13)?;
                 
                 let initial_stock_sold = match (initial_stock, initial_remaining) {
-                    (Some(stock), Some(remaining)) => Some(stock - remaining),
+                    (Some(stock), Some(remaining)) => Some(quantity::sub(stock, remaining)),
                     (Some(stock), None) => {
                         // If no batch found but we have initial stock, it means the batch was fully depleted and deleted.
-                        if stock > 0 { 
+                        if stock > 0.0 { 
                              Some(stock) 
                          } else { 
                              None 
@@ -235,20 +278,36 @@ pub fn get_product(id: i32, db: State<Database>) -> Result<Product, String> {
                     image_path: row.get(10)?,
                     category: row.get(11)?,
                     total_sold: {
-                        let sold: i64 = row.get(12)?;
-                        if sold > 0 { Some(sold) } else { None }
+                        let sold = quantity::round_quantity(row.get(12)?);
+                        if sold > 0.0 { Some(sold) } else { None }
                     },
                     initial_stock_sold,
                     quantity_sold: None,
+                    parent_product_id: row.get(14)?,
+                    variant_attributes: row.get(15)?,
+                    display_name: row.get(16)?,
+                    variant_count: None,
+                    category_id: row.get(17)?,
                     sold_revenue: None,
                     total_purchased_cost: None,
                     total_purchased_quantity: None,
                     total_sold_amount: None,
+                    available_quantity: None,
+                    on_order_quantity: None,
+                    unit: row.get(18)?,
+                    allow_fractional: row.get(19)?,
                 })
             },
         )
         .map_err(|e| format!("Product not found: {}", e))?;
 
+    // A bundle holds no stock; report how many can be assembled from its components
+    if bundles::is_bundle(conn, id)? {
+        product.stock_quantity = bundles::max_assemblable(conn, id)? as f64;
+    }
+    product.available_quantity = Some(reservations::available_quantity(conn, id)?);
+    product.on_order_quantity = Some(purchase_orders::on_order_quantity(conn, id)?);
+
     Ok(product)
 }
 
@@ -261,7 +320,15 @@ pub fn get_products_by_supplier(
     log::info!("get_products_by_supplier called with supplier_id: {}", supplier_id);
 
     let conn = db.get_conn()?;
+    let products = get_products_by_supplier_internal(&conn, supplier_id)?;
 
+    log::info!("Returning {} products for supplier {}", products.len(), supplier_id);
+    Ok(products)
+}
+
+/// Products bought from a supplier (as primary supplier or on one of their POs), with the
+/// stock, purchase quantity and purchase cost attributable to that supplier
+pub(crate) fn get_products_by_supplier_internal(conn: &Connection, supplier_id: i32) -> Result<Vec<Product>, String> {
     let mut stmt = conn
         .prepare(
             "SELECT 
@@ -282,7 +349,7 @@ pub fn get_products_by_supplier(
                 ) as stock_quantity,
                 (
                     COALESCE((
-                        SELECT SUM(poi.total_cost)
+                        SELECT SUM(poi.total_cost * po.exchange_rate)
                         FROM purchase_order_items poi
                         JOIN purchase_orders po ON poi.po_id = po.id
                         WHERE poi.product_id = p.id AND po.supplier_id = ?1 AND po.status = 'received'
@@ -306,7 +373,8 @@ pub fn get_products_by_supplier(
                 ) as total_purchased_quantity,
                 p.supplier_id, p.created_at, p.updated_at, p.image_path, p.category,
                 COALESCE(SUM(ii.quantity), 0) as total_sold,
-                COALESCE(SUM(ii.quantity * ii.unit_price - COALESCE(ii.discount_amount, 0)), 0) as total_sold_amount
+                COALESCE(SUM(ii.quantity * ii.unit_price - COALESCE(ii.discount_amount, 0)), 0) as total_sold_amount,
+                p.unit, p.allow_fractional
              FROM products p
              LEFT JOIN invoice_items ii ON p.id = ii.product_id
              WHERE p.supplier_id = ?1
@@ -330,25 +398,34 @@ pub fn get_products_by_supplier(
                 price: row.get(3)?,
                 selling_price: row.get(4)?,
                 initial_stock: row.get(5)?,
-                stock_quantity: row.get(6)?,
+                stock_quantity: quantity::round_quantity(row.get(6)?),
                 supplier_id: row.get(9)?,
                 created_at: row.get(10)?,
                 updated_at: row.get(11)?,
                 image_path: row.get(12)?,
                 category: row.get(13)?,
                 total_sold: {
-                    let sold: i64 = row.get(14)?;
-                    if sold > 0 { Some(sold) } else { None }
+                    let sold = quantity::round_quantity(row.get(14)?);
+                    if sold > 0.0 { Some(sold) } else { None }
                 },
                 initial_stock_sold: None,
                 quantity_sold: None,
+                parent_product_id: None,
+                variant_attributes: None,
+                display_name: None,
+                variant_count: None,
+                category_id: None,
                 sold_revenue: None,
                 total_purchased_cost: row.get(7)?,
-                total_purchased_quantity: Some(row.get(8)?),
+                total_purchased_quantity: Some(quantity::round_quantity(row.get(8)?)),
                 total_sold_amount: {
                     let val: f64 = row.get(15)?;
                     if val > 0.0 { Some(val) } else { None }
                 },
+                available_quantity: None,
+                on_order_quantity: None,
+                unit: row.get(16)?,
+                allow_fractional: row.get(17)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -358,18 +435,49 @@ pub fn get_products_by_supplier(
         products.push(product.map_err(|e| e.to_string())?);
     }
 
-    log::info!("Returning {} products for supplier {}", products.len(), supplier_id);
     Ok(products)
 }
 
+/// Stock typed in on a product can't be negative; only sales go below zero, and only with
+/// allow_negative_stock on. It has to be a whole number of the unit's steps.
+fn validate_stock_quantity(stock_quantity: f64, unit: &str, allow_fractional: bool) -> Result<(), String> {
+    if stock_quantity < 0.0 {
+        return Err(format!(
+            "Validation error: stock quantity cannot be negative (got {})",
+            quantity::format_quantity(stock_quantity)
+        ));
+    }
+    quantity::validate_quantity(stock_quantity, unit, allow_fractional)
+}
+
+/// Check a sold, bought or reserved quantity against the product's unit
+pub(crate) fn check_product_quantity(conn: &Connection, product_id: i32, qty: f64) -> Result<(), String> {
+    let (unit, allow_fractional): (String, bool) = conn
+        .query_row(
+            "SELECT unit, allow_fractional FROM products WHERE id = ?1",
+            [product_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|_| format!("Product with id {} not found", product_id))?;
+    quantity::validate_quantity(qty, &unit, allow_fractional)
+}
+
 /// Create a new product
 #[tauri::command]
-pub fn create_product(input: CreateProductInput, db: State<Database>) -> Result<Product, String> {
+pub fn create_product(input: CreateProductInput, app: AppHandle, db: State<Database>) -> Result<Product, String> {
     log::info!("create_product called with: {:?}", input);
 
     let conn = db.get_conn()?;
+    let product = create_product_internal(&conn, input)?;
+    emit_data_changed(&app, DataEntity::Product, DataOperation::Created, product.id);
+    Ok(product)
+}
 
-    let initial_qty = input.stock_quantity;
+pub(crate) fn create_product_internal(conn: &Connection, input: CreateProductInput) -> Result<Product, String> {
+    let unit = input.unit.as_deref().unwrap_or(quantity::DEFAULT_UNIT);
+    quantity::validate_unit(unit, input.allow_fractional)?;
+    validate_stock_quantity(input.stock_quantity, unit, input.allow_fractional)?;
+    let initial_qty = quantity::round_quantity(input.stock_quantity);
     let purchase_date = Utc::now().format("%Y-%m-%d").to_string();
 
     // Check if SKU already exists
@@ -386,9 +494,11 @@ pub fn create_product(input: CreateProductInput, db: State<Database>) -> Result<
         return Err(format!("Product with SKU '{}' already exists", input.sku));
     }
 
+    let (category_id, category) = categories::resolve_category(conn, input.category.as_deref())?;
+
     conn.execute(
-        "INSERT INTO products (name, sku, price, selling_price, initial_stock, stock_quantity, supplier_id, created_at, updated_at, category) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'), datetime('now'), ?8)",
-        (
+        "INSERT INTO products (name, sku, price, selling_price, initial_stock, stock_quantity, supplier_id, created_at, updated_at, category, category_id, unit, allow_fractional) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'), datetime('now'), ?8, ?9, ?10, ?11)",
+        rusqlite::params![
             &input.name,
             &input.sku,
             input.price,
@@ -396,8 +506,11 @@ pub fn create_product(input: CreateProductInput, db: State<Database>) -> Result<
             initial_qty, // capture the intended purchased stock
             0,           // start at 0 to avoid double-counting; batch will set real stock
             input.supplier_id,
-            input.category,
-        ),
+            category,
+            category_id,
+            unit,
+            input.allow_fractional,
+        ],
     )
     .map_err(|e| format!("Failed to create product: {}", e))?;
 
@@ -429,9 +542,9 @@ pub fn create_product(input: CreateProductInput, db: State<Database>) -> Result<
     }
 
     // Create an initial FIFO batch if starting stock > 0
-    if initial_qty > 0 {
+    if initial_qty > 0.0 {
         inventory_service::record_purchase(
-            &conn,
+            conn,
             id,
             initial_qty,
             input.price,
@@ -448,7 +561,7 @@ pub fn create_product(input: CreateProductInput, db: State<Database>) -> Result<
     }
 
     // Fetch the created product to get timestamps
-    let product_res = get_product(id, db.clone());
+    let product_res = get_product_internal(conn, id);
     
     match product_res {
         Ok(p) => {
@@ -461,19 +574,48 @@ pub fn create_product(input: CreateProductInput, db: State<Database>) -> Result<
 
 /// Update an existing product
 #[tauri::command]
-pub fn update_product(input: UpdateProductInput, modified_by: Option<String>, db: State<Database>) -> Result<Product, String> {
+pub fn update_product(
+    input: UpdateProductInput,
+    modified_by: Option<String>,
+    approval_token: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<Product, String> {
     log::info!("update_product called with: {:?}", input);
 
     let conn = db.get_conn()?;
 
     // Get old values first
-    let old_product: (String, String, f64, Option<f64>, i32, Option<i32>, Option<String>) = conn
+    let old_product: (String, String, f64, Option<f64>, f64, Option<i32>, Option<String>) = conn
         .query_row(
             "SELECT name, sku, price, selling_price, stock_quantity, supplier_id, category FROM products WHERE id = ?1",
             [input.id],
             |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?)),
         )
         .map_err(|e| format!("Product with id {} not found: {}", input.id, e))?;
+    let (old_unit, old_allow_fractional): (String, bool) = conn
+        .query_row(
+            "SELECT unit, allow_fractional FROM products WHERE id = ?1",
+            [input.id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Product with id {} not found: {}", input.id, e))?;
+
+    let unit = input.unit.clone().unwrap_or_else(|| old_unit.clone());
+    let allow_fractional = input.allow_fractional.unwrap_or(old_allow_fractional);
+    quantity::validate_unit(&unit, allow_fractional)?;
+    validate_stock_quantity(input.stock_quantity, &unit, allow_fractional)?;
+    let stock_quantity = quantity::round_quantity(input.stock_quantity);
+
+    // Large price swings may need a biometric approval (require_biometric_for: price_change:N)
+    let selling_price_changed = match (old_product.3, input.selling_price) {
+        (Some(old), Some(new)) => price_change_requires_biometric(&conn, old, new),
+        _ => false,
+    };
+    if selling_price_changed || price_change_requires_biometric(&conn, old_product.2, input.price) {
+        let approved_by = consume_action_approval(&conn, "price_change", approval_token.as_deref())?;
+        log::info!("Price change on product {} approved by {} via biometric", input.id, approved_by);
+    }
 
     // Check if SKU is already used by another product
     let sku_exists: bool = conn
@@ -489,6 +631,8 @@ pub fn update_product(input: UpdateProductInput, modified_by: Option<String>, db
         return Err(format!("Product with SKU '{}' already exists", input.sku));
     }
 
+    let (category_id, category) = categories::resolve_category(&conn, input.category.as_deref())?;
+
     // Build field changes array
     let mut field_changes: Vec<serde_json::Value> = Vec::new();
     
@@ -504,29 +648,38 @@ pub fn update_product(input: UpdateProductInput, modified_by: Option<String>, db
     if old_product.3 != input.selling_price {
         field_changes.push(serde_json::json!({"field": "selling_price", "old": old_product.3, "new": input.selling_price}));
     }
-    if old_product.4 != input.stock_quantity {
-        field_changes.push(serde_json::json!({"field": "stock_quantity", "old": old_product.4, "new": input.stock_quantity}));
+    if quantity::to_milli(old_product.4) != quantity::to_milli(stock_quantity) {
+        field_changes.push(serde_json::json!({"field": "stock_quantity", "old": old_product.4, "new": stock_quantity}));
     }
     if old_product.5 != input.supplier_id {
         field_changes.push(serde_json::json!({"field": "supplier_id", "old": old_product.5, "new": input.supplier_id}));
     }
-    if old_product.6 != input.category {
-        field_changes.push(serde_json::json!({"field": "category", "old": old_product.6, "new": input.category}));
+    if old_product.6 != category {
+        field_changes.push(serde_json::json!({"field": "category", "old": old_product.6, "new": category}));
+    }
+    if old_unit != unit {
+        field_changes.push(serde_json::json!({"field": "unit", "old": old_unit, "new": unit}));
+    }
+    if old_allow_fractional != allow_fractional {
+        field_changes.push(serde_json::json!({"field": "allow_fractional", "old": old_allow_fractional, "new": allow_fractional}));
     }
 
     let rows_affected = conn
         .execute(
-            "UPDATE products SET name = ?1, sku = ?2, price = ?3, selling_price = ?4, stock_quantity = ?5, supplier_id = ?6, updated_at = datetime('now'), category = ?7 WHERE id = ?8",
-            (
+            "UPDATE products SET name = ?1, sku = ?2, price = ?3, selling_price = ?4, stock_quantity = ?5, supplier_id = ?6, updated_at = datetime('now'), category = ?7, category_id = ?8, unit = ?9, allow_fractional = ?10 WHERE id = ?11",
+            rusqlite::params![
                 &input.name,
                 &input.sku,
                 input.price,
                 input.selling_price,
-                input.stock_quantity,
+                stock_quantity,
                 input.supplier_id,
-                input.category,
+                category,
+                category_id,
+                unit,
+                allow_fractional,
                 input.id,
-            ),
+            ],
         )
         .map_err(|e| format!("Failed to update product: {}", e))?;
 
@@ -534,6 +687,26 @@ pub fn update_product(input: UpdateProductInput, modified_by: Option<String>, db
         return Err(format!("Product with id {} not found", input.id));
     }
 
+    // Queryable price history alongside the entity_modifications entry
+    price_history::record_price_change(
+        &conn,
+        input.id,
+        "price",
+        Some(old_product.2),
+        Some(input.price),
+        modified_by.as_deref(),
+        None,
+    )?;
+    price_history::record_price_change(
+        &conn,
+        input.id,
+        "selling_price",
+        old_product.3,
+        input.selling_price,
+        modified_by.as_deref(),
+        None,
+    )?;
+
     // Log modification if there were actual changes
     if !field_changes.is_empty() {
         let changes_json = serde_json::to_string(&field_changes).unwrap_or_default();
@@ -545,13 +718,19 @@ pub fn update_product(input: UpdateProductInput, modified_by: Option<String>, db
     }
 
     // Fetch updated product
-    let product_res = get_product(input.id, db.clone());
-    product_res
+    let product = get_product(input.id, db.clone())?;
+    emit_data_changed(&app, DataEntity::Product, DataOperation::Updated, product.id);
+    Ok(product)
 }
 
 /// Delete a product by ID
 #[tauri::command]
-pub fn delete_product(id: i32, deleted_by: Option<String>, db: State<Database>) -> Result<(), String> {
+pub fn delete_product(
+    id: i32,
+    deleted_by: Option<String>,
+    app_handle: AppHandle,
+    db: State<Database>,
+) -> Result<(), String> {
     log::info!("delete_product called with id: {}, deleted_by: {:?}", id, deleted_by);
 
     let mut conn = db.get_conn()?;
@@ -572,10 +751,20 @@ pub fn delete_product(id: i32, deleted_by: Option<String>, db: State<Database>)
         ));
     }
 
+    let variant_count = variants::variant_count(&conn, id)?;
+    if variant_count > 0 {
+        return Err(format!(
+            "Cannot delete product: It has {} variant(s). Delete the variants first.",
+            variant_count
+        ));
+    }
+
     // Get product data before deletion for audit trail
     // We can use simple query here as we don't strictly need total_sold for audit
     let product = conn.query_row(
-        "SELECT id, name, sku, price, selling_price, initial_stock, stock_quantity, supplier_id, created_at, updated_at, image_path, category FROM products WHERE id = ?1",
+        "SELECT id, name, sku, price, selling_price, initial_stock, stock_quantity, supplier_id, created_at, updated_at, image_path, category,
+                parent_product_id, variant_attributes, unit, allow_fractional
+         FROM products WHERE id = ?1",
         [id],
         |row| {
             Ok(Product {
@@ -594,10 +783,19 @@ pub fn delete_product(id: i32, deleted_by: Option<String>, db: State<Database>)
                 total_sold: None,
                 initial_stock_sold: None,
                 quantity_sold: None,
+                parent_product_id: row.get(12)?,
+                variant_attributes: row.get(13)?,
+                display_name: None,
+                variant_count: None,
+                category_id: None,
                 sold_revenue: None,
                 total_purchased_cost: None,
                 total_purchased_quantity: None,
                 total_sold_amount: None,
+                available_quantity: None,
+                on_order_quantity: None,
+                unit: row.get(14)?,
+                allow_fractional: row.get(15)?,
             })
         },
     )
@@ -625,10 +823,412 @@ pub fn delete_product(id: i32, deleted_by: Option<String>, db: State<Database>)
 
     tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
+    remove_image_files(&app_handle, &conn, product.image_path.as_deref());
+    emit_data_changed(&app_handle, DataEntity::Product, DataOperation::Deleted, id);
+
     log::info!("Deleted product with id: {} and saved to trash", id);
     Ok(())
 }
 
+/// Merge a duplicate product (source) into another product (target).
+/// Sales, payments, PO lines, FIFO batches and inventory history are repointed to the
+/// target and stock is combined. The target keeps its SKU; the source's SKU is kept in
+/// the archive JSON so the merge can be traced from trash.
+#[tauri::command]
+pub fn merge_products(
+    source_id: i32,
+    target_id: i32,
+    merged_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<Product, String> {
+    log::info!("merge_products called: source_id: {}, target_id: {}", source_id, target_id);
+
+    if source_id == target_id {
+        return Err("Cannot merge a product into itself".to_string());
+    }
+
+    let mut conn = db.get_conn()?;
+
+    let load = |conn: &rusqlite::Connection, id: i32| {
+        conn.query_row(
+            "SELECT id, name, sku, price, selling_price, initial_stock, stock_quantity, supplier_id, created_at, updated_at, image_path, category,
+                    unit, allow_fractional
+             FROM products WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(Product {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    sku: row.get(2)?,
+                    price: row.get(3)?,
+                    selling_price: row.get(4)?,
+                    initial_stock: row.get(5)?,
+                    stock_quantity: row.get(6)?,
+                    supplier_id: row.get(7)?,
+                    created_at: row.get(8)?,
+                    updated_at: row.get(9)?,
+                    image_path: row.get(10)?,
+                    category: row.get(11)?,
+                    total_sold: None,
+                    initial_stock_sold: None,
+                    quantity_sold: None,
+                    parent_product_id: None,
+                    variant_attributes: None,
+                    display_name: None,
+                    variant_count: None,
+                    category_id: None,
+                    sold_revenue: None,
+                    total_purchased_cost: None,
+                    total_purchased_quantity: None,
+                    total_sold_amount: None,
+                    available_quantity: None,
+                    on_order_quantity: None,
+                    unit: row.get(12)?,
+                    allow_fractional: row.get(13)?,
+                })
+            },
+        )
+        .map_err(|e| format!("Product with id {} not found: {}", id, e))
+    };
+
+    let source = load(&conn, source_id)?;
+    let target = load(&conn, target_id)?;
+    if source.unit != target.unit {
+        return Err(format!(
+            "Cannot merge products sold in different units ({} and {})",
+            source.unit, target.unit
+        ));
+    }
+    let (source_qty_sold, source_sold_revenue): (Option<f64>, Option<f64>) = conn
+        .query_row(
+            "SELECT quantity_sold, sold_revenue FROM products WHERE id = ?1",
+            [source_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let tx = conn.transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut moved = serde_json::Map::new();
+    for table in [
+        "invoice_items",
+        "supplier_payments",
+        "purchase_order_items",
+        "inventory_batches",
+        "inventory_transactions",
+        "invoice_item_components",
+        "batch_consumptions",
+        "purchase_return_items",
+    ] {
+        let count = tx
+            .execute(
+                &format!("UPDATE {} SET product_id = ?1 WHERE product_id = ?2", table),
+                [target_id, source_id],
+            )
+            .map_err(|e| format!("Failed to repoint {}: {}", table, e))?;
+        moved.insert(table.to_string(), serde_json::json!(count));
+    }
+
+    // product_suppliers is unique per (product, supplier): keep the target's link when both exist
+    tx.execute(
+        "UPDATE OR IGNORE product_suppliers SET product_id = ?1 WHERE product_id = ?2",
+        [target_id, source_id],
+    )
+    .map_err(|e| format!("Failed to repoint product suppliers: {}", e))?;
+    tx.execute("DELETE FROM product_suppliers WHERE product_id = ?1", [source_id])
+        .map_err(|e| format!("Failed to clean up product suppliers: {}", e))?;
+
+    let new_stock = quantity::sum([target.stock_quantity, source.stock_quantity]);
+    let new_initial_stock = match (target.initial_stock, source.initial_stock) {
+        (None, None) => None,
+        (t, s) => Some(quantity::sum([t.unwrap_or(0.0), s.unwrap_or(0.0)])),
+    };
+
+    tx.execute(
+        "UPDATE products SET stock_quantity = ?1, initial_stock = ?2,
+                quantity_sold = CASE WHEN quantity_sold IS NULL AND ?3 IS NULL THEN NULL ELSE COALESCE(quantity_sold, 0) + COALESCE(?3, 0) END,
+                sold_revenue = CASE WHEN sold_revenue IS NULL AND ?4 IS NULL THEN NULL ELSE COALESCE(sold_revenue, 0) + COALESCE(?4, 0) END,
+                updated_at = datetime('now')
+         WHERE id = ?5",
+        rusqlite::params![
+            new_stock,
+            new_initial_stock,
+            source_qty_sold,
+            source_sold_revenue,
+            target_id
+        ],
+    )
+    .map_err(|e| format!("Failed to update target product: {}", e))?;
+
+    let mut conflicts = serde_json::Map::new();
+    conflicts.insert("sku".to_string(), serde_json::json!(source.sku));
+    if source.name != target.name {
+        conflicts.insert("name".to_string(), serde_json::json!(source.name));
+    }
+    if (source.price - target.price).abs() > 0.001 {
+        conflicts.insert("price".to_string(), serde_json::json!(source.price));
+    }
+    if source.selling_price != target.selling_price {
+        conflicts.insert("selling_price".to_string(), serde_json::json!(source.selling_price));
+    }
+
+    let merge_info = serde_json::json!({
+        "merged_into": target_id,
+        "rows_moved": moved,
+        "conflicting_fields": conflicts,
+    });
+
+    crate::db::archive::archive_entity(
+        &tx,
+        "product",
+        source_id,
+        &source,
+        Some(merge_info.to_string()),
+        merged_by.clone(),
+    )?;
+
+    tx.execute("DELETE FROM products WHERE id = ?1", [source_id])
+        .map_err(|e| format!("Failed to delete merged product: {}", e))?;
+
+    let mut field_changes: Vec<serde_json::Value> = vec![
+        serde_json::json!({"field": "stock_quantity", "old": target.stock_quantity, "new": new_stock}),
+    ];
+    if target.initial_stock != new_initial_stock {
+        field_changes.push(serde_json::json!({"field": "initial_stock", "old": target.initial_stock, "new": new_initial_stock}));
+    }
+    let changes_json = serde_json::to_string(&field_changes).unwrap_or_default();
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        ("product", target_id, format!("{} (merged from {} / {})", target.name, source.name, source.sku), "merged", &changes_json, &merged_by),
+    ).map_err(|e| format!("Failed to log modification: {}", e))?;
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Merged product {} into {}", source_id, target_id);
+    emit_data_changed(&app, DataEntity::Product, DataOperation::Deleted, source_id);
+    emit_data_changed(&app, DataEntity::Product, DataOperation::Updated, target_id);
+    get_product(target_id, db)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StockReconciliation {
+    pub product_id: i32,
+    /// stock_quantity before reconciling
+    pub recorded_quantity: f64,
+    /// Units left in the product's FIFO batches
+    pub batch_quantity: f64,
+    /// Units sold as backorders (allow_negative_stock) that no batch covered
+    pub backordered_quantity: f64,
+    /// Whether stock_quantity disagreed with the batches and was corrected
+    pub corrected: bool,
+    /// stock_quantity after reconciling
+    pub reconciled_quantity: f64,
+}
+
+/// Units of a product sold past its stock (backordered_quantity), on its own lines and as a
+/// bundle component. Restocks don't settle backorders, so they stay open on the lines.
+fn open_backordered_quantity(conn: &Connection, product_id: i32) -> Result<f64, String> {
+    conn.query_row(
+        "SELECT
+            COALESCE((SELECT SUM(backordered_quantity) FROM invoice_items WHERE product_id = ?1), 0)
+          + COALESCE((SELECT SUM(ii.backordered_quantity * bc.quantity)
+                      FROM invoice_items ii
+                      JOIN bundle_components bc ON bc.bundle_id = ii.product_id
+                      WHERE bc.component_id = ?1), 0)",
+        [product_id],
+        |row| row.get::<_, f64>(0),
+    )
+    .map(quantity::round_quantity)
+    .map_err(|e| format!("Failed to total backorders: {}", e))
+}
+
+/// The stock_quantity the product's batches allow, or None when it already agrees with them.
+/// Backordered units were sold without a batch, so stock may sit below the batches by up to
+/// the open backorders (a restock after a backorder leaves stock under the new batch).
+/// Negative stock with no batches left is an open backorder too, not a mismatch.
+fn stock_correction(recorded_quantity: f64, batch_quantity: f64, backordered_quantity: f64) -> Option<f64> {
+    let (recorded, batches) = (quantity::to_milli(recorded_quantity), quantity::to_milli(batch_quantity));
+    let lowest = batches - quantity::to_milli(backordered_quantity);
+    if recorded > batches {
+        Some(batch_quantity)
+    } else if recorded < lowest && !(recorded < 0 && batches == 0) {
+        Some(quantity::round_quantity(batch_quantity - backordered_quantity))
+    } else {
+        None
+    }
+}
+
+/// Check a product's stock_quantity against its FIFO batches and open backorders, correcting it
+/// when they disagree and logging the correction. Stock above the batches is set to the batch
+/// total; stock further below them than the open backorders is raised to batches - backorders.
+#[tauri::command]
+pub fn reconcile_product_stock(
+    product_id: i32,
+    modified_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<StockReconciliation, String> {
+    log::info!("reconcile_product_stock called for product {}", product_id);
+
+    let conn = db.get_conn()?;
+    let reconciliation = reconcile_product_stock_internal(&conn, product_id, modified_by.as_deref())?;
+    if reconciliation.corrected {
+        emit_data_changed(&app, DataEntity::Product, DataOperation::Updated, product_id);
+    }
+    Ok(reconciliation)
+}
+
+pub(crate) fn reconcile_product_stock_internal(
+    conn: &Connection,
+    product_id: i32,
+    modified_by: Option<&str>,
+) -> Result<StockReconciliation, String> {
+    if bundles::is_bundle(conn, product_id)? {
+        return Err("Bundles hold no stock of their own; reconcile their components instead".to_string());
+    }
+
+    let (name, recorded_quantity): (String, f64) = conn
+        .query_row("SELECT name, stock_quantity FROM products WHERE id = ?1", [product_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(|_| format!("Product with id {} not found", product_id))?;
+    let batch_quantity = quantity::round_quantity(
+        conn.query_row(
+            "SELECT COALESCE(SUM(quantity_remaining), 0) FROM inventory_batches WHERE product_id = ?1",
+            [product_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to total batches: {}", e))?,
+    );
+    let backordered_quantity = open_backordered_quantity(conn, product_id)?;
+
+    let Some(reconciled_quantity) = stock_correction(recorded_quantity, batch_quantity, backordered_quantity) else {
+        return Ok(StockReconciliation {
+            product_id,
+            recorded_quantity,
+            batch_quantity,
+            backordered_quantity,
+            corrected: false,
+            reconciled_quantity: recorded_quantity,
+        });
+    };
+
+    log::warn!(
+        "Product {} stock_quantity {} disagrees with its batches ({}, {} backordered); correcting to {}",
+        product_id,
+        recorded_quantity,
+        batch_quantity,
+        backordered_quantity,
+        reconciled_quantity
+    );
+    let tx = conn.unchecked_transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    tx.execute(
+        "UPDATE products SET stock_quantity = ?1, updated_at = datetime('now') WHERE id = ?2",
+        (reconciled_quantity, product_id),
+    )
+    .map_err(|e| format!("Failed to update stock: {}", e))?;
+    let changes = serde_json::json!([{"field": "stock_quantity", "old": recorded_quantity, "new": reconciled_quantity}]);
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES ('product', ?1, ?2, 'stock_reconciled', ?3, ?4)",
+        (product_id, &name, changes.to_string(), modified_by),
+    )
+    .map_err(|e| format!("Failed to log stock reconciliation: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(StockReconciliation {
+        product_id,
+        recorded_quantity,
+        batch_quantity,
+        backordered_quantity,
+        corrected: true,
+        reconciled_quantity,
+    })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InventoryBatchReport {
+    pub product_id: i32,
+    /// Batches with units left, oldest first (the order sales consume them)
+    pub batches: Vec<InventoryBatchWithDetails>,
+    pub total_remaining: f64,
+    pub total_value: f64,
+    /// Remaining value over remaining units; 0 with no stock in batches
+    pub weighted_average_cost: f64,
+    pub stock_quantity: f64,
+    /// stock_quantity disagrees with the batches and open backorders; reconcile_product_stock can fix it
+    pub stock_mismatch: bool,
+}
+
+/// Get the FIFO batches behind a product's stock, with where each came from and what is left
+#[tauri::command]
+pub fn get_inventory_batches(product_id: i32, db: State<Database>) -> Result<InventoryBatchReport, String> {
+    log::info!("get_inventory_batches called for product {}", product_id);
+
+    let conn = db.get_conn()?;
+    get_inventory_batches_internal(&conn, product_id)
+}
+
+pub(crate) fn get_inventory_batches_internal(conn: &Connection, product_id: i32) -> Result<InventoryBatchReport, String> {
+    let stock_quantity: f64 = conn
+        .query_row("SELECT stock_quantity FROM products WHERE id = ?1", [product_id], |row| row.get(0))
+        .map_err(|_| format!("Product with id {} not found", product_id))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT ib.id, ib.po_item_id, po.po_number, ib.original_quantity, ib.quantity_remaining, ib.unit_cost,
+                    ib.purchase_date, MAX(CAST(julianday('now') - julianday(ib.purchase_date) AS INTEGER), 0), ib.created_at
+             FROM inventory_batches ib
+             LEFT JOIN purchase_order_items poi ON poi.id = ib.po_item_id
+             LEFT JOIN purchase_orders po ON po.id = poi.po_id
+             WHERE ib.product_id = ?1 AND ib.quantity_remaining > 0
+             ORDER BY ib.purchase_date ASC, ib.id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let batches = stmt
+        .query_map([product_id], |row| {
+            let po_item_id: Option<i32> = row.get(1)?;
+            let quantity_remaining: f64 = row.get(4)?;
+            let unit_cost: f64 = row.get(5)?;
+            Ok(InventoryBatchWithDetails {
+                id: row.get(0)?,
+                product_id,
+                po_item_id,
+                po_number: row.get(2)?,
+                source: if po_item_id.is_some() { "purchase_order" } else { "initial_stock" }.to_string(),
+                original_quantity: row.get(3)?,
+                quantity_remaining,
+                unit_cost,
+                batch_value: money::line_total(unit_cost, quantity_remaining),
+                purchase_date: row.get(6)?,
+                age_days: row.get::<_, Option<i32>>(7)?.unwrap_or(0),
+                created_at: row.get(8)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let total_remaining = quantity::sum(batches.iter().map(|b| b.quantity_remaining));
+    let total_value = money::sum(batches.iter().map(|b| b.batch_value));
+    let weighted_average_cost = if total_remaining > 0.0 {
+        money::round_money(total_value / total_remaining)
+    } else {
+        0.0
+    };
+
+    Ok(InventoryBatchReport {
+        product_id,
+        batches,
+        total_remaining,
+        total_value,
+        weighted_average_cost,
+        stock_quantity,
+        stock_mismatch: stock_correction(stock_quantity, total_remaining, open_backordered_quantity(conn, product_id)?).is_some(),
+    })
+}
+
 /// Add mock product data for testing
 #[tauri::command]
 pub fn add_mock_products(db: State<Database>) -> Result<String, String> {
@@ -672,49 +1272,51 @@ pub fn add_mock_products(db: State<Database>) -> Result<String, String> {
     Ok(format!("Successfully added {} mock products", inserted))
 }
 
-/// Get top selling products based on invoice items, optionally filtered by category
-/// Get top selling products based on invoice items, optionally filtered by category
+/// Get top selling products based on invoice items, optionally filtered by category and supplier
 #[tauri::command]
-pub fn get_top_selling_products(page: i32, limit: i32, category: Option<String>, db: State<Database>) -> Result<PaginatedResult<Product>, String> {
-    log::info!("get_top_selling_products called with page: {}, limit: {}", page, limit);
+pub fn get_top_selling_products(
+    page: i32,
+    limit: i32,
+    category: Option<String>,
+    supplier_id: Option<i32>,
+    category_id: Option<i32>,
+    db: State<Database>,
+) -> Result<PaginatedResult<Product>, String> {
+    log::info!(
+        "get_top_selling_products called with page: {}, limit: {}, category: {:?}, supplier_id: {:?}, category_id: {:?}",
+        page, limit, category, supplier_id, category_id
+    );
 
     let conn = db.get_conn()?;
-    let offset = (page - 1) * limit;
-    
-    let category_filter = if let Some(cat) = &category {
-        format!("AND p.category = '{}'", cat.replace("'", "''")) 
-    } else {
-        String::new()
-    };
+    let (limit, offset) = validate_pagination(page, limit)?;
 
-    // Calculate total count for the filter
-    let count_query = format!("
-        SELECT COUNT(*) 
-        FROM products p 
-        WHERE p.stock_quantity > 0
-        {}
-    ", category_filter);
+    // NULL filters match everything, so both queries share one fixed statement shape
+    let filter = "WHERE p.stock_quantity > 0
+          AND (?1 IS NULL OR p.category = ?1)
+          AND (?2 IS NULL OR p.supplier_id = ?2)
+          AND (?3 IS NULL OR p.category_id = ?3 OR p.category_id IN (SELECT id FROM categories WHERE parent_id = ?3))";
+
+    let count_query = format!("SELECT COUNT(*) FROM products p {}", filter);
 
     let total_count: i64 = conn
-        .query_row(&count_query, [], |row| row.get(0))
+        .query_row(&count_query, rusqlite::params![category, supplier_id, category_id], |row| row.get(0))
         .map_err(|e| format!("Failed to get count: {}", e))?;
 
     let query = format!("
         SELECT p.id, p.name, p.sku, p.price, p.selling_price, p.initial_stock, p.stock_quantity, 
                p.supplier_id, p.created_at, p.updated_at, p.image_path, p.category,
-               COALESCE(SUM(ii.quantity), 0) as total_sold
+               COALESCE(SUM(ii.quantity), 0) as total_sold, p.category_id, p.unit, p.allow_fractional
         FROM products p
         LEFT JOIN invoice_items ii ON p.id = ii.product_id
-        WHERE p.stock_quantity > 0
         {}
         GROUP BY p.id
         ORDER BY total_sold DESC, p.name ASC
-        LIMIT ?1 OFFSET ?2
-    ", category_filter);
+        LIMIT ?4 OFFSET ?5
+    ", filter);
 
     let mut stmt = conn.prepare(&query).map_err(|e| e.to_string())?;
 
-    let product_iter = stmt.query_map([limit, offset], |row| {
+    let product_iter = stmt.query_map(rusqlite::params![category, supplier_id, category_id, limit, offset], |row| {
         Ok(Product {
             id: row.get(0)?,
             name: row.get(1)?,
@@ -729,15 +1331,24 @@ pub fn get_top_selling_products(page: i32, limit: i32, category: Option<String>,
             image_path: row.get(10)?,
             category: row.get(11)?,
             total_sold: {
-                let sold: i64 = row.get(12)?;
-                if sold > 0 { Some(sold) } else { None }
+                let sold = quantity::round_quantity(row.get(12)?);
+                if sold > 0.0 { Some(sold) } else { None }
             },
             initial_stock_sold: None,
             quantity_sold: None,
+            parent_product_id: None,
+            variant_attributes: None,
+            display_name: None,
+            variant_count: None,
+            category_id: row.get(13)?,
             sold_revenue: None,
             total_purchased_cost: None,
             total_purchased_quantity: None,
             total_sold_amount: None,
+            available_quantity: None,
+            on_order_quantity: None,
+            unit: row.get(14)?,
+            allow_fractional: row.get(15)?,
         })
     }).map_err(|e| e.to_string())?;
 
@@ -768,7 +1379,7 @@ pub fn get_products_by_ids(ids: Vec<i32>, db: State<Database>) -> Result<Vec<Pro
     let query = format!("
         SELECT p.id, p.name, p.sku, p.price, p.selling_price, p.initial_stock, p.stock_quantity, 
                p.supplier_id, p.created_at, p.updated_at, p.image_path, p.category,
-               COALESCE(SUM(ii.quantity), 0) as total_sold
+               COALESCE(SUM(ii.quantity), 0) as total_sold, p.unit, p.allow_fractional
         FROM products p
         LEFT JOIN invoice_items ii ON p.id = ii.product_id
         WHERE p.id IN ({})
@@ -795,15 +1406,24 @@ pub fn get_products_by_ids(ids: Vec<i32>, db: State<Database>) -> Result<Vec<Pro
             image_path: row.get(10)?,
             category: row.get(11)?,
             total_sold: {
-                let sold: i64 = row.get(12)?;
-                if sold > 0 { Some(sold) } else { None }
+                let sold = quantity::round_quantity(row.get(12)?);
+                if sold > 0.0 { Some(sold) } else { None }
             },
             initial_stock_sold: None,
             quantity_sold: None,
+            parent_product_id: None,
+            variant_attributes: None,
+            display_name: None,
+            variant_count: None,
+            category_id: None,
             sold_revenue: None,
             total_purchased_cost: None,
             total_purchased_quantity: None,
             total_sold_amount: None,
+            available_quantity: None,
+            on_order_quantity: None,
+            unit: row.get(13)?,
+            allow_fractional: row.get(14)?,
         })
     }).map_err(|e| e.to_string())?;
 
@@ -824,14 +1444,14 @@ pub fn get_products_by_ids(ids: Vec<i32>, db: State<Database>) -> Result<Vec<Pro
     Ok(ordered_products)
 }
 
-/// Get all unique categories
+/// Get all category names, alphabetically
 #[tauri::command]
 pub fn get_unique_categories(db: State<Database>) -> Result<Vec<String>, String> {
     log::info!("get_unique_categories called");
     let conn = db.get_conn()?;
     
     let mut stmt = conn
-        .prepare("SELECT DISTINCT category FROM products WHERE category IS NOT NULL AND category != '' ORDER BY category")
+        .prepare("SELECT name FROM categories ORDER BY name COLLATE NOCASE")
         .map_err(|e| e.to_string())?;
 
     let cat_iter = stmt
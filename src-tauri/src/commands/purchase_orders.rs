@@ -3,20 +3,69 @@
 
 use rusqlite::{params, Connection, OptionalExtension};
 use chrono::Utc;
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use crate::db::models::{
     PurchaseOrder, PurchaseOrderWithDetails, PurchaseOrderItemWithProduct,
     CreatePurchaseOrderInput, PurchaseOrderComplete, Supplier, SupplierPayment,
 };
+use crate::commands::products;
+use crate::commands::suppliers::{check_payment_allocations, save_payment_allocations, PaymentAllocationInput};
 use crate::db::Database;
-use crate::services::inventory_service;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use crate::services::{inventory_service, money, quantity};
+
+/// Currency that totals, FIFO batch costs and supplier payments are kept in
+const BASE_CURRENCY: &str = "INR";
+/// app_settings key for the symbol shown next to base currency amounts
+const BASE_CURRENCY_SYMBOL_KEY: &str = "base_currency_symbol";
+const DEFAULT_BASE_CURRENCY_SYMBOL: &str = "₹";
+
+/// Units on open (ordered or partially received) POs not yet received, for queries over `products p`
+pub(crate) const ON_ORDER_QUANTITY_SQL: &str = "(SELECT COALESCE(SUM(MAX(poi.quantity - poi.received_quantity, 0)), 0)
+      FROM purchase_order_items poi
+      JOIN purchase_orders po ON po.id = poi.po_id
+      WHERE poi.product_id = p.id AND po.status IN ('ordered', 'partially_received'))";
 
 // =============================================
 // HELPER FUNCTIONS
 // =============================================
 
+/// Validate a PO currency and exchange rate; the base currency always uses a rate of 1
+fn resolve_currency(currency: Option<&str>, exchange_rate: Option<f64>) -> Result<(String, f64), String> {
+    let currency = currency
+        .map(|c| c.trim().to_uppercase())
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| BASE_CURRENCY.to_string());
+
+    if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("Invalid currency code '{}'", currency));
+    }
+    if currency == BASE_CURRENCY {
+        return Ok((currency, 1.0));
+    }
+
+    match exchange_rate {
+        Some(rate) if rate.is_finite() && rate > 0.0 => Ok((currency, rate)),
+        Some(_) => Err("Exchange rate must be greater than 0".to_string()),
+        None => Err(format!("Exchange rate to {} is required for {} purchase orders", BASE_CURRENCY, currency)),
+    }
+}
+
+/// Symbol used when formatting base currency amounts in messages
+fn base_currency_symbol(conn: &Connection) -> String {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        [BASE_CURRENCY_SYMBOL_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .filter(|s| !s.trim().is_empty())
+    .unwrap_or_else(|| DEFAULT_BASE_CURRENCY_SYMBOL.to_string())
+}
+
 /// Generate next PO number (PO-YYYY-NNN format)
 fn generate_po_number(conn: &Connection) -> Result<String, String> {
     let current_year = Utc::now().format("%Y").to_string();
@@ -50,6 +99,7 @@ fn generate_po_number(conn: &Connection) -> Result<String, String> {
 #[tauri::command]
 pub fn create_purchase_order(
     input: CreatePurchaseOrderInput,
+    app: AppHandle,
     db: State<Database>,
 ) -> Result<PurchaseOrder, String> {
     let conn = db.get_conn()?;
@@ -64,6 +114,7 @@ pub fn create_purchase_order(
         Ok(po) => {
             conn.execute("COMMIT", [])
                 .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+            emit_data_changed(&app, DataEntity::PurchaseOrder, DataOperation::Created, po.id);
             Ok(po)
         }
         Err(e) => {
@@ -73,7 +124,7 @@ pub fn create_purchase_order(
     }
 }
 
-fn create_purchase_order_internal(
+pub(crate) fn create_purchase_order_internal(
     conn: &Connection,
     input: CreatePurchaseOrderInput,
 ) -> Result<PurchaseOrder, String> {
@@ -95,8 +146,11 @@ fn create_purchase_order_internal(
         return Err(format!("Supplier with ID {} not found", input.supplier_id));
     }
 
-    // Validate all products exist and calculate total
-    let mut total_amount = 0.0;
+    let (currency, exchange_rate) =
+        resolve_currency(input.currency.as_deref(), input.exchange_rate)?;
+
+    // Validate all products exist and calculate total (item costs are in the PO currency)
+    let mut foreign_total_amount = 0.0;
     for item in &input.items {
         let product_exists: bool = conn
             .query_row(
@@ -110,16 +164,18 @@ fn create_purchase_order_internal(
             return Err(format!("Product with ID {} not found", item.product_id));
         }
 
-        if item.quantity <= 0 {
+        if item.quantity <= 0.0 {
             return Err("Item quantity must be greater than 0".to_string());
         }
+        products::check_product_quantity(conn, item.product_id, item.quantity)?;
 
         if item.unit_cost < 0.0 {
             return Err("Item unit cost cannot be negative".to_string());
         }
 
-        total_amount += item.quantity as f64 * item.unit_cost;
+        foreign_total_amount = money::sum([foreign_total_amount, item.quantity * item.unit_cost]);
     }
+    let total_amount = money::round_money(foreign_total_amount * exchange_rate);
 
     // Generate PO number
     let po_number = generate_po_number(conn)?;
@@ -127,8 +183,9 @@ fn create_purchase_order_internal(
     // Create purchase order
     conn.execute(
         "INSERT INTO purchase_orders
-         (po_number, supplier_id, order_date, expected_delivery_date, status, total_amount, notes, created_at, updated_at)
-         VALUES (?, ?, ?, ?, 'received', ?, ?, ?, ?)",
+         (po_number, supplier_id, order_date, expected_delivery_date, status, total_amount, notes, created_at, updated_at,
+          currency, exchange_rate, foreign_total_amount)
+         VALUES (?, ?, ?, ?, 'received', ?, ?, ?, ?, ?, ?, ?)",
         params![
             po_number,
             input.supplier_id,
@@ -138,6 +195,9 @@ fn create_purchase_order_internal(
             input.notes,
             now,
             now,
+            currency,
+            exchange_rate,
+            foreign_total_amount,
         ],
     )
     .map_err(|e| format!("Failed to create purchase order: {}", e))?;
@@ -146,14 +206,14 @@ fn create_purchase_order_internal(
 
     // Create PO items and update inventory
     for item in &input.items {
-        let total_cost = item.quantity as f64 * item.unit_cost;
+        let total_cost = item.quantity * item.unit_cost;
 
         // Create PO item
         conn.execute(
             "INSERT INTO purchase_order_items
-             (po_id, product_id, quantity, unit_cost, total_cost, created_at)
-             VALUES (?, ?, ?, ?, ?, ?)",
-            params![po_id, item.product_id, item.quantity, item.unit_cost, total_cost, now],
+             (po_id, product_id, quantity, received_quantity, unit_cost, total_cost, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![po_id, item.product_id, item.quantity, item.quantity, item.unit_cost, total_cost, now],
         )
         .map_err(|e| format!("Failed to create PO item: {}", e))?;
 
@@ -161,17 +221,17 @@ fn create_purchase_order_internal(
 
         // Update product stock
         conn.execute(
-            "UPDATE products SET stock_quantity = stock_quantity + ?, updated_at = ? WHERE id = ?",
+            "UPDATE products SET stock_quantity = ROUND(stock_quantity + ?, 3), updated_at = ? WHERE id = ?",
             params![item.quantity, now, item.product_id],
         )
         .map_err(|e| format!("Failed to update product stock: {}", e))?;
 
-        // Create inventory batch and transaction using inventory service
+        // Create inventory batch and transaction using inventory service (FIFO costs in base currency)
         inventory_service::record_purchase(
             conn,
             item.product_id,
             item.quantity,
-            item.unit_cost,
+            item.unit_cost * exchange_rate,
             Some(po_item_id),
             &order_date,
         )?;
@@ -180,12 +240,6 @@ fn create_purchase_order_internal(
     // Handle initial payment if provided
     if let Some(payment_amount) = input.initial_payment {
         if payment_amount > 0.0 {
-            // Ensure po_id column exists
-            let _ = conn.execute(
-                "ALTER TABLE supplier_payments ADD COLUMN po_id INTEGER REFERENCES purchase_orders(id)",
-                [],
-            );
-
             conn.execute(
                 "INSERT INTO supplier_payments
                     (supplier_id, po_id, product_id, amount, payment_method, note, paid_at, created_at)
@@ -209,7 +263,8 @@ fn create_purchase_order_internal(
     let po = conn
         .query_row(
             "SELECT id, po_number, supplier_id, order_date, expected_delivery_date,
-                    received_date, status, total_amount, notes, created_at, updated_at
+                    received_date, status, total_amount, notes, created_at, updated_at,
+                    currency, exchange_rate, COALESCE(foreign_total_amount, total_amount)
              FROM purchase_orders WHERE id = ?",
             params![po_id],
             |row| {
@@ -225,6 +280,9 @@ fn create_purchase_order_internal(
                     notes: row.get(8)?,
                     created_at: row.get(9)?,
                     updated_at: row.get(10)?,
+                    currency: row.get(11)?,
+                    exchange_rate: row.get(12)?,
+                    foreign_total_amount: row.get(13)?,
                 })
             },
         )
@@ -239,53 +297,162 @@ fn create_purchase_order_internal(
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ProductPurchaseSummary {
-    pub total_quantity: i64,
+    pub total_quantity: f64,
     pub total_value: f64,
     pub purchase_orders: i64,
+    /// Units on ordered and partially received POs still to arrive, not part of the totals
+    pub on_order_quantity: f64,
+    pub on_order_value: f64,
 }
 
 /// Get total purchased quantity/value for a product (all suppliers)
 /// Includes:
 /// - Initial stock (initial_stock * product price)
-/// - All purchase order items for this product
+/// - Items on received purchase orders, like total_purchased_cost on the product list
+///
+/// What open POs have still to deliver is reported separately as on order; draft and cancelled
+/// POs are left out.
 #[tauri::command]
 pub fn get_product_purchase_summary(
     product_id: i32,
     db: State<Database>,
 ) -> Result<ProductPurchaseSummary, String> {
     let conn = db.get_conn()?;
+    get_product_purchase_summary_internal(&conn, product_id)
+}
 
-    let (initial_stock, price): (i64, f64) = conn
+pub(crate) fn get_product_purchase_summary_internal(
+    conn: &Connection,
+    product_id: i32,
+) -> Result<ProductPurchaseSummary, String> {
+    let (initial_stock, price): (f64, f64) = conn
         .query_row(
             "SELECT COALESCE(initial_stock, 0), price FROM products WHERE id = ?1",
             params![product_id],
-            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)),
+            |row| Ok((row.get::<_, f64>(0)?, row.get::<_, f64>(1)?)),
         )
-        .unwrap_or((0, 0.0));
+        .unwrap_or((0.0, 0.0));
 
-    let (po_qty, po_value, po_count): (i64, f64, i64) = conn
+    let (po_qty, po_value, po_count): (f64, f64, i64) = conn
         .query_row(
             "SELECT
                 COALESCE(SUM(poi.quantity), 0) AS qty,
-                COALESCE(SUM(poi.quantity * poi.unit_cost), 0.0) AS value,
+                COALESCE(SUM(poi.quantity * poi.unit_cost * po.exchange_rate), 0.0) AS value,
                 COUNT(DISTINCT poi.po_id) AS po_count
              FROM purchase_order_items poi
-             WHERE poi.product_id = ?1",
+             JOIN purchase_orders po ON po.id = poi.po_id
+             WHERE poi.product_id = ?1 AND po.status = 'received'",
             params![product_id],
             |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
         )
-        .unwrap_or((0, 0.0, 0));
+        .map_err(|e| format!("Failed to sum purchase orders: {}", e))?;
+
+    let (on_order_quantity, on_order_value): (f64, f64) = conn
+        .query_row(
+            "SELECT
+                COALESCE(SUM(MAX(poi.quantity - poi.received_quantity, 0)), 0),
+                COALESCE(SUM(MAX(poi.quantity - poi.received_quantity, 0) * poi.unit_cost * po.exchange_rate), 0.0)
+             FROM purchase_order_items poi
+             JOIN purchase_orders po ON po.id = poi.po_id
+             WHERE poi.product_id = ?1 AND po.status IN ('ordered', 'partially_received')",
+            params![product_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to sum open purchase orders: {}", e))?;
 
-    let total_quantity = initial_stock + po_qty;
-    let total_value = (initial_stock as f64 * price) + po_value;
+    let total_quantity = quantity::sum([initial_stock, po_qty]);
+    let total_value = (initial_stock * price) + po_value;
 
     Ok(ProductPurchaseSummary {
         total_quantity,
         total_value,
         purchase_orders: po_count,
+        on_order_quantity: quantity::round_quantity(on_order_quantity),
+        on_order_value: money::round_money(on_order_value),
     })
 }
 
+// =============================================
+// INCOMING STOCK PER PRODUCT
+// =============================================
+
+/// An open PO line for a product, with what is still to arrive
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IncomingStock {
+    pub po_id: i32,
+    pub po_item_id: i32,
+    pub po_number: String,
+    pub supplier_id: i32,
+    pub supplier_name: String,
+    /// 'ordered' or 'partially_received'
+    pub status: String,
+    pub order_date: String,
+    pub expected_delivery_date: Option<String>,
+    pub ordered_quantity: f64,
+    pub received_quantity: f64,
+    pub remaining_quantity: f64,
+}
+
+/// Units still on order for a product (see ON_ORDER_QUANTITY_SQL)
+pub(crate) fn on_order_quantity(conn: &Connection, product_id: i32) -> Result<f64, String> {
+    conn.query_row(
+        &format!("SELECT {} FROM products p WHERE p.id = ?1", ON_ORDER_QUANTITY_SQL),
+        [product_id],
+        |row| row.get::<_, f64>(0),
+    )
+    .map(quantity::round_quantity)
+    .map_err(|e| format!("Failed to sum stock on order: {}", e))
+}
+
+/// Get the open PO lines for a product that still have units to arrive, soonest due first
+#[tauri::command]
+pub fn get_incoming_stock(product_id: i32, db: State<Database>) -> Result<Vec<IncomingStock>, String> {
+    log::info!("get_incoming_stock called for product_id: {}", product_id);
+
+    let conn = db.get_conn()?;
+    get_incoming_stock_internal(&conn, product_id)
+}
+
+pub(crate) fn get_incoming_stock_internal(conn: &Connection, product_id: i32) -> Result<Vec<IncomingStock>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT po.id, poi.id, po.po_number, po.supplier_id, s.name, po.status, po.order_date,
+                    po.expected_delivery_date, poi.quantity, poi.received_quantity
+             FROM purchase_order_items poi
+             JOIN purchase_orders po ON po.id = poi.po_id
+             JOIN suppliers s ON s.id = po.supplier_id
+             WHERE poi.product_id = ?1
+               AND po.status IN ('ordered', 'partially_received')
+               AND poi.received_quantity < poi.quantity
+             ORDER BY po.expected_delivery_date IS NULL, po.expected_delivery_date, po.order_date, po.id",
+        )
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+    let lines = stmt
+        .query_map([product_id], |row| {
+            let ordered_quantity: f64 = row.get(8)?;
+            let received_quantity: f64 = row.get(9)?;
+            Ok(IncomingStock {
+                po_id: row.get(0)?,
+                po_item_id: row.get(1)?,
+                po_number: row.get(2)?,
+                supplier_id: row.get(3)?,
+                supplier_name: row.get(4)?,
+                status: row.get(5)?,
+                order_date: row.get(6)?,
+                expected_delivery_date: row.get(7)?,
+                ordered_quantity,
+                received_quantity,
+                remaining_quantity: quantity::sub(ordered_quantity, received_quantity),
+            })
+        })
+        .map_err(|e| format!("Failed to query incoming stock: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect incoming stock: {}", e))?;
+
+    Ok(lines)
+}
+
 // =============================================
 // GET PURCHASE ORDERS (LIST)
 // =============================================
@@ -336,7 +503,7 @@ pub fn get_purchase_orders(
         .query_map(params_refs.as_slice(), |row| {
             let total_amount: f64 = row.get(8)?;
             let total_paid: f64 = row.get(13)?;
-            let total_pending = total_amount - total_paid;
+            let total_pending = money::sub(total_amount, total_paid);
 
             Ok(PurchaseOrderWithDetails {
                 id: row.get(0)?,
@@ -378,7 +545,8 @@ pub fn get_purchase_order_by_id(
     let po: PurchaseOrder = conn
         .query_row(
             "SELECT id, po_number, supplier_id, order_date, expected_delivery_date,
-                    received_date, status, total_amount, notes, created_at, updated_at
+                    received_date, status, total_amount, notes, created_at, updated_at,
+                    currency, exchange_rate, COALESCE(foreign_total_amount, total_amount)
              FROM purchase_orders WHERE id = ?",
             params![po_id],
             |row| {
@@ -394,6 +562,9 @@ pub fn get_purchase_order_by_id(
                     notes: row.get(8)?,
                     created_at: row.get(9)?,
                     updated_at: row.get(10)?,
+                    currency: row.get(11)?,
+                    exchange_rate: row.get(12)?,
+                    foreign_total_amount: row.get(13)?,
                 })
             },
         )
@@ -402,7 +573,7 @@ pub fn get_purchase_order_by_id(
     // Get supplier
     let supplier: Supplier = conn
         .query_row(
-            "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at
+            "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at, opening_balance
              FROM suppliers WHERE id = ?",
             params![po.supplier_id],
             |row| {
@@ -419,6 +590,7 @@ pub fn get_purchase_order_by_id(
                     image_path: row.get(9)?,
                     created_at: row.get(10)?,
                     updated_at: row.get(11)?,
+                    opening_balance: row.get(12)?,
                 })
             },
         )
@@ -455,6 +627,7 @@ pub fn get_purchase_order_by_id(
                 sold_revenue: None,
                 created_at: row.get(8)?,
                 po_number: Some(po_number_clone.clone()),
+                is_return: false,
             })
         })
         .map_err(|e| format!("Failed to query items: {}", e))?
@@ -491,7 +664,7 @@ pub fn get_purchase_order_by_id(
         .map_err(|e| format!("Failed to collect payments: {}", e))?;
 
     let total_paid: f64 = payments.iter().map(|p| p.amount).sum();
-    let total_pending = po.total_amount - total_paid;
+    let total_pending = money::sub(po.total_amount, total_paid);
 
     Ok(PurchaseOrderComplete {
         purchase_order: po,
@@ -512,12 +685,13 @@ pub fn update_purchase_order_status(
     po_id: i32,
     status: String,
     received_date: Option<String>,
+    app: AppHandle,
     db: State<Database>,
 ) -> Result<PurchaseOrder, String> {
     let conn = db.get_conn()?;
 
     // Validate status
-    let valid_statuses = ["draft", "ordered", "received", "cancelled"];
+    let valid_statuses = ["draft", "ordered", "partially_received", "received", "cancelled"];
     if !valid_statuses.contains(&status.as_str()) {
         return Err(format!(
             "Invalid status. Must be one of: {}",
@@ -536,11 +710,21 @@ pub fn update_purchase_order_status(
     )
     .map_err(|e| format!("Failed to update purchase order status: {}", e))?;
 
+    // A received PO has nothing left on order
+    if status == "received" {
+        conn.execute(
+            "UPDATE purchase_order_items SET received_quantity = quantity WHERE po_id = ?",
+            params![po_id],
+        )
+        .map_err(|e| format!("Failed to update received quantities: {}", e))?;
+    }
+
     // Retrieve and return updated PO
     let po = conn
         .query_row(
             "SELECT id, po_number, supplier_id, order_date, expected_delivery_date,
-                    received_date, status, total_amount, notes, created_at, updated_at
+                    received_date, status, total_amount, notes, created_at, updated_at,
+                    currency, exchange_rate, COALESCE(foreign_total_amount, total_amount)
              FROM purchase_orders WHERE id = ?",
             params![po_id],
             |row| {
@@ -556,11 +740,15 @@ pub fn update_purchase_order_status(
                     notes: row.get(8)?,
                     created_at: row.get(9)?,
                     updated_at: row.get(10)?,
+                    currency: row.get(11)?,
+                    exchange_rate: row.get(12)?,
+                    foreign_total_amount: row.get(13)?,
                 })
             },
         )
         .map_err(|e| format!("Failed to retrieve updated PO: {}", e))?;
 
+    emit_data_changed(&app, DataEntity::PurchaseOrder, DataOperation::Updated, po.id);
     Ok(po)
 }
 
@@ -575,10 +763,37 @@ pub fn add_payment_to_purchase_order(
     payment_method: Option<String>,
     note: Option<String>,
     paid_at: Option<String>,
-    db: State<Database>,
+    allocations: Option<Vec<PaymentAllocationInput>>,
+    app: AppHandle,
 ) -> Result<i32, String> {
-    let mut conn = db.get_conn()?;
+    // Database comes from the handle's managed state to keep the argument count down
+    let mut conn = app.state::<Database>().get_conn()?;
+    let payment_id =
+        add_payment_to_purchase_order_internal(&mut conn, po_id, amount, payment_method, note, paid_at, allocations)?;
+    emit_data_changed(&app, DataEntity::PurchaseOrder, DataOperation::Updated, po_id);
+    Ok(payment_id)
+}
 
+/// Amount paid on PO ?1: payments made against it plus allocations to its lines from payments
+/// made elsewhere. Uses idx_supplier_payments_po and idx_po_items_po.
+pub(crate) const PO_TOTAL_PAID_SQL: &str = "SELECT COALESCE((SELECT SUM(amount) FROM supplier_payments WHERE po_id = ?1), 0)
+                  + COALESCE((SELECT SUM(a.amount)
+                              FROM supplier_payment_allocations a
+                              JOIN purchase_order_items poi ON poi.id = a.po_item_id
+                              JOIN supplier_payments sp ON sp.id = a.payment_id
+                              WHERE poi.po_id = ?1 AND sp.po_id IS NOT ?1), 0)";
+
+/// Pay against a PO. Without allocations the payment is split over the PO's lines in
+/// proportion to their value; with them it is kept as one payment set against those lines.
+pub(crate) fn add_payment_to_purchase_order_internal(
+    conn: &mut Connection,
+    po_id: i32,
+    amount: f64,
+    payment_method: Option<String>,
+    note: Option<String>,
+    paid_at: Option<String>,
+    allocations: Option<Vec<PaymentAllocationInput>>,
+) -> Result<i32, String> {
     if amount <= 0.0 {
         return Err("Payment amount must be greater than 0".to_string());
     }
@@ -592,37 +807,56 @@ pub fn add_payment_to_purchase_order(
         )
         .map_err(|e| format!("Purchase order not found: {}", e))?;
 
-    // Check total paid so far
+    // Check total paid so far, including allocations to its lines from payments made elsewhere
     let total_paid: f64 = conn
         .query_row(
-            "SELECT COALESCE(SUM(amount), 0) FROM supplier_payments WHERE po_id = ?",
+            PO_TOTAL_PAID_SQL,
             params![po_id],
             |row| row.get(0),
         )
         .unwrap_or(0.0);
 
-    // Allow small float tolerance
-    if total_paid + amount > total_amount + 0.01 {
+    // Payments and total_amount are both in the base currency; compared to the paisa
+    let amount = money::round_money(amount);
+    if money::exceeds(money::sum([total_paid, amount]), total_amount) {
+        let symbol = base_currency_symbol(conn);
         return Err(format!(
-            "Payment amount exceeds remaining balance. Total: ₹{:.2}, Paid: ₹{:.2}, Remaining: ₹{:.2}",
+            "Payment amount exceeds remaining balance. Total: {s}{:.2}, Paid: {s}{:.2}, Remaining: {s}{:.2}",
             total_amount,
             total_paid,
-            total_amount - total_paid
+            money::sub(total_amount, total_paid),
+            s = symbol
         ));
     }
 
     let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
     let payment_date = paid_at.unwrap_or_else(|| Utc::now().format("%Y-%m-%d").to_string());
 
-    // Add po_id column to supplier_payments if it doesn't exist
-    let _ = conn.execute(
-        "ALTER TABLE supplier_payments ADD COLUMN po_id INTEGER REFERENCES purchase_orders(id)",
-        [],
-    );
+    if let Some(allocations) = allocations.filter(|allocations| !allocations.is_empty()) {
+        let per_item = check_payment_allocations(conn, supplier_id, Some(po_id), amount, &allocations)?;
+
+        let tx = conn.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
+        tx.execute(
+            "INSERT INTO supplier_payments
+             (supplier_id, po_id, product_id, amount, payment_method, note, paid_at, created_at)
+             VALUES (?, ?, NULL, ?, ?, ?, ?, ?)",
+            params![supplier_id, po_id, amount, payment_method, note, payment_date, now],
+        ).map_err(|e| format!("Failed to create payment: {}", e))?;
+        let payment_id = tx.last_insert_rowid() as i32;
+        save_payment_allocations(&tx, payment_id, &per_item)?;
+        tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+        return Ok(payment_id);
+    }
 
     // Fetch PO items to split payment proportionally
     let items: Vec<(i32, f64)> = {
-        let mut stmt = conn.prepare("SELECT product_id, total_cost FROM purchase_order_items WHERE po_id = ?")
+        let mut stmt = conn.prepare(
+            "SELECT poi.product_id, poi.total_cost * po.exchange_rate
+             FROM purchase_order_items poi
+             JOIN purchase_orders po ON po.id = poi.po_id
+             WHERE poi.po_id = ?",
+        )
             .map_err(|e| format!("Failed to prepare items query: {}", e))?;
         
         let rows = stmt.query_map([po_id], |row| Ok((row.get(0)?, row.get(1)?)))
@@ -634,7 +868,6 @@ pub fn add_payment_to_purchase_order(
 
     let tx = conn.transaction().map_err(|e| format!("Failed to begin transaction: {}", e))?;
 
-    let mut remaining_payment = amount;
     let mut last_id = 0;
     
     // If no items (shouldn't happen for valid PO), assign to NULL product
@@ -647,27 +880,11 @@ pub fn add_payment_to_purchase_order(
         ).map_err(|e| format!("Failed to create payment: {}", e))?;
         last_id = tx.last_insert_rowid() as i32;
     } else {
-        // Distribute payment
-        for (i, (product_id, item_cost)) in items.iter().enumerate() {
-            let is_last = i == items.len() - 1;
-            
-            let share = if is_last {
-                remaining_payment
-            } else {
-                if total_amount > 0.0 {
-                    let calc = (item_cost / total_amount) * amount;
-                    // Round to 2 decimals to avoid weird splits, but ensure total matches
-                    (calc * 100.0).round() / 100.0
-                } else {
-                    0.0
-                }
-            };
-            
-            // Safety check for last item to absorb rounding errors negatively if needed, usually positive
-            // Just use remaining_payment for last item.
-            
-            remaining_payment -= share;
+        // Distribute payment in proportion to item cost; shares add up to exactly the payment
+        let weights: Vec<f64> = items.iter().map(|(_, item_cost)| *item_cost).collect();
+        let shares = money::allocate(amount, &weights);
 
+        for ((product_id, _), share) in items.iter().zip(shares) {
             if share > 0.0 {
                 tx.execute(
                     "INSERT INTO supplier_payments
@@ -689,38 +906,77 @@ pub fn add_payment_to_purchase_order(
 // GET PURCHASE HISTORY FOR PRODUCT
 // =============================================
 
+/// A product's purchase order lines in base currency, looked up through idx_po_items_product
+pub(crate) const PRODUCT_PO_ITEMS_SQL: &str = "SELECT poi.id, poi.po_id, poi.quantity,
+                poi.unit_cost * COALESCE(po.exchange_rate, 1), poi.total_cost * COALESCE(po.exchange_rate, 1),
+                poi.created_at, p.name, p.sku, p.selling_price, po.po_number
+         FROM purchase_order_items poi
+         JOIN products p ON poi.product_id = p.id
+         LEFT JOIN purchase_orders po ON poi.po_id = po.id
+         WHERE poi.product_id = ?";
+
+/// Purchase lots for a product (initial stock and PO lines, newest first) with how many units
+/// of each were sold and for how much. Sales are attributed from batch_consumptions, which
+/// record_sale_fifo writes at sale time. Initial stock is returned as a row with po_id None, and
+/// purchase returns as rows with is_return set and a negative quantity.
 #[tauri::command]
 pub fn get_product_purchase_history(
     product_id: i32,
     db: State<Database>,
 ) -> Result<Vec<PurchaseOrderItemWithProduct>, String> {
     let conn = db.get_conn()?;
+    get_product_purchase_history_internal(&conn, product_id)
+}
 
-    // 1. Get Initial Stock info
-    let initial_stock_info: Option<(i32, f64, String, f64)> = conn.query_row(
-        "SELECT initial_stock, price, created_at, selling_price FROM products WHERE id = ?",
-        params![product_id],
-        |row| Ok((
-            row.get::<_, Option<i32>>(0)?.unwrap_or(0),
-            row.get::<_, f64>(1)?,
-            row.get::<_, String>(2)?,
-            row.get::<_, Option<f64>>(3)?.unwrap_or(0.0),
-        )),
-    ).optional().map_err(|e| format!("Failed to get product info: {}", e))?;
+pub(crate) fn get_product_purchase_history_internal(
+    conn: &Connection,
+    product_id: i32,
+) -> Result<Vec<PurchaseOrderItemWithProduct>, String> {
+    // 1. Units sold and revenue per source lot (po_item_id; NULL for initial stock).
+    // Revenue is the sale price less the line's proportional share of the invoice discount.
+    // Units consumed by bundle sales count as sold but carry no revenue of their own.
+    let mut sold_stmt = conn.prepare(
+        "WITH invoice_subtotals AS (
+             SELECT invoice_id, SUM(quantity * unit_price) AS subtotal
+             FROM invoice_items
+             WHERE invoice_id IN (SELECT invoice_id FROM batch_consumptions WHERE product_id = ?1)
+             GROUP BY invoice_id
+         )
+         SELECT bc.po_item_id,
+                SUM(bc.quantity),
+                SUM(CASE WHEN ii.product_id = bc.product_id THEN
+                        bc.quantity * (ii.unit_price - CASE WHEN s.subtotal > 0
+                            THEN COALESCE(i.discount_amount, 0) * ii.unit_price / s.subtotal
+                            ELSE 0 END)
+                    ELSE 0 END)
+         FROM batch_consumptions bc
+         JOIN invoices i ON bc.invoice_id = i.id
+         LEFT JOIN invoice_items ii ON bc.invoice_item_id = ii.id
+         LEFT JOIN invoice_subtotals s ON s.invoice_id = bc.invoice_id
+         WHERE bc.product_id = ?1
+         GROUP BY bc.po_item_id"
+    ).map_err(|e| format!("Failed to prepare sales stmt: {}", e))?;
 
-    // 2. Get Purchase Order Items (Batches)
-    let mut po_items_stmt = conn.prepare(
-        "SELECT poi.id, poi.po_id, poi.quantity, poi.unit_cost, poi.total_cost, poi.created_at, p.name, p.sku, p.selling_price, po.po_number
-         FROM purchase_order_items poi
-         JOIN products p ON poi.product_id = p.id
-         LEFT JOIN purchase_orders po ON poi.po_id = po.id
-         WHERE poi.product_id = ?
-         ORDER BY poi.created_at ASC"
-    ).map_err(|e| format!("Failed to prepare PO items stmt: {}", e))?;
+    let sold: HashMap<Option<i32>, (f64, f64)> = sold_stmt.query_map(params![product_id], |row| {
+        Ok((row.get::<_, Option<i32>>(0)?, (quantity::round_quantity(row.get(1)?), row.get::<_, f64>(2)?)))
+    }).map_err(|e| format!("Failed to query sales: {}", e))?
+    .collect::<Result<_, _>>()
+    .map_err(|e| format!("Failed to collect sales: {}", e))?;
+
+    let sold_for = |po_item_id: Option<i32>| {
+        let (quantity, revenue) = sold.get(&po_item_id).copied().unwrap_or((0.0, 0.0));
+        (Some(quantity), Some(money::round_money(revenue)))
+    };
+
+    // 2. Purchase Order Items (Batches)
+    let mut po_items_stmt = conn.prepare(PRODUCT_PO_ITEMS_SQL)
+        .map_err(|e| format!("Failed to prepare PO items stmt: {}", e))?;
 
-    let po_batches = po_items_stmt.query_map(params![product_id], |row| {
+    let mut history = po_items_stmt.query_map(params![product_id], |row| {
+        let id: i32 = row.get(0)?;
+        let (quantity_sold, sold_revenue) = sold_for(Some(id));
         Ok(PurchaseOrderItemWithProduct {
-            id: row.get(0)?,
+            id,
             po_id: row.get(1)?,
             product_id,
             product_name: row.get(6)?,
@@ -729,159 +985,86 @@ pub fn get_product_purchase_history(
             unit_cost: row.get(3)?,
             total_cost: row.get(4)?,
             selling_price: row.get(8)?,
-            quantity_sold: Some(0), // Will calculate
-            sold_revenue: Some(0.0), // Will calculate
+            quantity_sold,
+            sold_revenue,
             created_at: row.get(5)?,
             po_number: row.get(9)?,
+            is_return: false,
         })
     }).map_err(|e| format!("Failed to query PO items: {}", e))?
     .collect::<Result<Vec<_>, _>>()
     .map_err(|e| format!("Failed to collect PO items: {}", e))?;
 
-    // 3. Get Sales (Invoice Items) with proportional discount
-    // Discount is distributed proportionally: item_discount = (item_value / invoice_subtotal) * invoice_discount
-    let mut sales_stmt = conn.prepare(
-        "SELECT ii.quantity, ii.unit_price,
-                COALESCE(i.discount_amount, 0) as invoice_discount,
-                (SELECT SUM(ii2.quantity * ii2.unit_price) FROM invoice_items ii2 WHERE ii2.invoice_id = i.id) as invoice_subtotal
-         FROM invoice_items ii
-         JOIN invoices i ON ii.invoice_id = i.id
-         WHERE ii.product_id = ?
-         ORDER BY i.created_at ASC"
-    ).map_err(|e| format!("Failed to prepare sales stmt: {}", e))?;
-
-    // (quantity, unit_price, item_discount_share)
-    let sales: Vec<(i32, f64, f64)> = sales_stmt.query_map(params![product_id], |row| {
-        let qty: i32 = row.get(0)?;
-        let unit_price: f64 = row.get(1)?;
-        let invoice_discount: f64 = row.get(2)?;
-        let invoice_subtotal: f64 = row.get::<_, Option<f64>>(3)?.unwrap_or(1.0); // Avoid div by 0
-        
-        let item_value = qty as f64 * unit_price;
-        let discount_share = if invoice_subtotal > 0.0 {
-            (item_value / invoice_subtotal) * invoice_discount
-        } else {
-            0.0
-        };
-        
-        Ok((qty, unit_price, discount_share))
-    }).map_err(|e| format!("Failed to query sales: {}", e))?
-    .collect::<Result<Vec<_>, _>>()
-    .map_err(|e| format!("Failed to collect sales: {}", e))?;
-
-    // 4. Combine Initial Stock + PO Batches into a tracking list
-    struct BatchTracker {
-        item: PurchaseOrderItemWithProduct,
-        is_initial: bool,
-        remaining_qty: i32,
-    }
+    // 3. Initial stock as a pseudo PO item (id 0, no PO); the frontend shows it like any other lot
+    let initial_stock = conn.query_row(
+        "SELECT initial_stock, price, created_at, selling_price, name, sku FROM products WHERE id = ?",
+        params![product_id],
+        |row| Ok((
+            row.get::<_, Option<f64>>(0)?.unwrap_or(0.0),
+            row.get::<_, f64>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<f64>>(3)?.unwrap_or(0.0),
+            row.get::<_, String>(4)?,
+            row.get::<_, String>(5)?,
+        )),
+    ).optional().map_err(|e| format!("Failed to get product info: {}", e))?;
 
-    let mut trackers: Vec<BatchTracker> = Vec::new();
-
-    // Add Initial Stock if it exists
-    if let Some((qty, cost, date, selling_price)) = initial_stock_info {
-        if qty > 0 {
-            // Create a pseudo-PO item for Initial Stock
-            // We use id=-1 or similar to mark it, but frontend uses logic to identify it?
-            // Actually, we don't return this in the PO list usually?
-            // Wait, the frontend grid handles "Initial Stock" separately via get_product?
-            // No, the previous logic relied on inventory_batches which might have covered it.
-            // But get_product_purchase_history usually returns PO items.
-            // Let's create a tracker for it but we might not return it in the result list 
-            // if this function is strictly for "Purchase Orders".
-            // HOWEVER, if we consume sales against it, we must track it first.
-            // AND the user wants "Initial Stock" in the table.
-            // Previously, `get_product` returned initial stock info and `get_product_purchase_history` returned POs.
-            // The frontend merged them.
-            // PROBLEM: If we do FIFO here, we need to return the "Initial Stock" sold stats too?
-            // Yes, but `get_product_purchase_history` signature returns `Vec<PurchaseOrderItemWithProduct>`.
-            // We can treat Initial Stock as a `PurchaseOrderItemWithProduct` with po_id=None (or logic).
-            // But `get_product` ALREADY returns `initial_stock_sold`.
-            // Use that? No, we need precise revenue now.
-            // FIX: We will do the calculation for ALL batches including Initial, 
-            // BUT we can only update the PO items in the return list.
-            // For Initial Stock, we might need a separate way to return it? 
-            // OR: We return it here as a dummy PO item? 
-            // The frontend:
-            /*
-                const displayPurchaseHistory: PurchaseRow[] = [
-                ...(product?.initial_stock ? [...] : []),
-                ...purchases.map(...)
-            */
-            // So frontend constructs Initial Stock row itself.
-            // If I calculate `sold_revenue` for initial stock here, I verify it, but I can't easily pass it back 
-            // unless I change the return type or add it to the list.
-            // IF I add it to the list, I should remove the frontend `product.initial_stock` mapping to avoid dupe.
-            // Let's add it to the list! `po_id: None` works.
-            
-            trackers.push(BatchTracker {
-                item: PurchaseOrderItemWithProduct {
-                    id: 0, // Placeholder
-                    po_id: None,
-                    product_id,
-                    product_name: po_batches.first().map(|b| b.product_name.clone()).unwrap_or_default(),
-                    sku: po_batches.first().map(|b| b.sku.clone()).unwrap_or_default(),
-                    quantity: qty,
-                    unit_cost: cost,
-                    total_cost: cost * qty as f64,
-                    selling_price: Some(selling_price),
-                    quantity_sold: Some(0),
-                    sold_revenue: Some(0.0),
-                    created_at: date,
-                    po_number: None,
-                },
-                is_initial: true,
-                remaining_qty: qty,
+    if let Some((quantity, cost, created_at, selling_price, name, sku)) = initial_stock {
+        if quantity > 0.0 {
+            let (quantity_sold, sold_revenue) = sold_for(None);
+            history.push(PurchaseOrderItemWithProduct {
+                id: 0,
+                po_id: None,
+                product_id,
+                product_name: name,
+                sku,
+                quantity,
+                unit_cost: cost,
+                total_cost: cost * quantity,
+                selling_price: Some(selling_price),
+                quantity_sold,
+                sold_revenue,
+                created_at,
+                po_number: None,
+                is_return: false,
             });
         }
     }
 
-    // Add PO Batches
-    for batch in po_batches {
-        trackers.push(BatchTracker {
-            remaining_qty: batch.quantity, // Init with full quantity
-            is_initial: false,
-            item: batch,
-        });
-    }
-
-    // 5. Run FIFO Simulation
-    // Sales: (sale_qty, sale_price, discount_share for entire item)
-    for (mut sale_qty, sale_price, discount_share) in sales {
-        // Calculate discount per unit for this sale
-        let discount_per_unit = if sale_qty > 0 { discount_share / sale_qty as f64 } else { 0.0 };
-        
-        for tracker in &mut trackers {
-            if sale_qty <= 0 { break; }
-            if tracker.remaining_qty > 0 {
-                let take = sale_qty.min(tracker.remaining_qty);
-                tracker.remaining_qty -= take;
-                sale_qty -= take;
-                
-                // Update stats: revenue = (price - discount_per_unit) * qty
-                let effective_price = sale_price - discount_per_unit;
-                tracker.item.quantity_sold = Some(tracker.item.quantity_sold.unwrap_or(0) + take);
-                tracker.item.sold_revenue = Some(tracker.item.sold_revenue.unwrap_or(0.0) + (take as f64 * effective_price));
-            }
-        }
-    }
+    // 4. Returns to the supplier as negative lots, so received less sold still adds up to stock
+    let mut returns_stmt = conn.prepare(
+        "SELECT pri.id, pr.po_id, pr.return_number, pri.quantity, pri.unit_cost, pri.total_cost,
+                pr.created_at, p.name, p.sku, p.selling_price
+         FROM purchase_return_items pri
+         JOIN purchase_returns pr ON pr.id = pri.return_id
+         JOIN products p ON p.id = pri.product_id
+         WHERE pri.product_id = ?"
+    ).map_err(|e| format!("Failed to prepare returns stmt: {}", e))?;
+
+    let returns = returns_stmt.query_map(params![product_id], |row| {
+        Ok(PurchaseOrderItemWithProduct {
+            id: row.get(0)?,
+            po_id: row.get(1)?,
+            product_id,
+            product_name: row.get(7)?,
+            sku: row.get(8)?,
+            quantity: -row.get::<_, f64>(3)?,
+            unit_cost: row.get(4)?,
+            total_cost: -row.get::<_, f64>(5)?,
+            selling_price: row.get(9)?,
+            quantity_sold: None,
+            sold_revenue: None,
+            created_at: row.get(6)?,
+            po_number: row.get(2)?,
+            is_return: true,
+        })
+    }).map_err(|e| format!("Failed to query returns: {}", e))?
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|e| format!("Failed to collect returns: {}", e))?;
+    history.extend(returns);
 
-    // 6. Return values
-    // We want to return ALL items, including the Initial Stock one if we added it.
-    // Frontend expects "Initial Stock" to be handled.
-    // If I return it here, frontend will duplicate it if I don't remove the frontend logic.
-    // The user's goal is to fix the numbers.
-    // If I return the Initial Stock item here, I must instruct frontend to NOT construct it from `product` info manually.
-    // OR: I only return PO items, but how do I update Initial Stock stats? 
-    // `get_product` is called separately. 
-    // Ideally, `get_product` should return `initial_stock_sold_revenue` too.
-    // This implies `get_product` needs this simulation too? Expensive to run twice.
-    // DECISION: Return `Initial Stock` as a `PurchaseOrderItem` in this list. 
-    // And I will Update Frontend to REMOVE the manual Initial Stock row and rely on this list.
-    // This unifies the logic.
-    
     // Sort descending by date (newest first) for UI
-    trackers.sort_by(|a, b| b.item.created_at.cmp(&a.item.created_at));
+    history.sort_by(|a, b| b.created_at.cmp(&a.created_at));
 
-    Ok(trackers.into_iter().map(|t| t.item).collect())
+    Ok(history)
 }
@@ -0,0 +1,465 @@
+/// Purchase Returns
+/// Goods sent back to a supplier (supplier credit notes). A return is either against a purchase
+/// order or against a product's opening stock from its primary supplier. Returned units leave
+/// stock through the FIFO layer, and the return value either comes off what is owed to the
+/// supplier or is recorded as a refund the supplier owes us.
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::commands::products;
+use crate::db::Database;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use crate::services::{inventory_service, money, quantity};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurchaseReturnItemInput {
+    pub product_id: i32,
+    pub quantity: f64,
+    /// Cost per unit credited by the supplier, in base currency
+    pub unit_cost: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePurchaseReturnInput {
+    /// Purchase order the goods came in on
+    pub po_id: Option<i32>,
+    /// Supplier and product for opening stock that didn't come in on a purchase order
+    pub supplier_id: Option<i32>,
+    pub product_id: Option<i32>,
+    pub items: Vec<PurchaseReturnItemInput>,
+    pub reason: String,
+    /// true: set off against the supplier's payable; false: the supplier refunds the value
+    pub reduces_payable: bool,
+    /// YYYY-MM-DD, defaults to today
+    pub return_date: Option<String>,
+    pub created_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurchaseReturnItem {
+    pub id: i32,
+    pub product_id: i32,
+    pub po_item_id: Option<i32>,
+    pub quantity: f64,
+    pub unit_cost: f64,
+    pub total_cost: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurchaseReturn {
+    pub id: i32,
+    pub return_number: String,
+    pub supplier_id: i32,
+    pub po_id: Option<i32>,
+    pub reason: String,
+    pub reduces_payable: bool,
+    pub total_amount: f64,
+    pub return_date: String,
+    pub created_by: Option<String>,
+    pub created_at: String,
+    pub items: Vec<PurchaseReturnItem>,
+}
+
+/// Returned value for a product, split by how it is settled
+pub(crate) struct ProductReturnTotals {
+    /// Set off against the payable
+    pub payable_reduction: f64,
+    /// Owed back to us by the supplier
+    pub refundable_credit: f64,
+}
+
+/// Returned value for a product, from one supplier or (None) all of them
+pub(crate) fn product_return_totals(
+    conn: &Connection,
+    supplier_id: Option<i32>,
+    product_id: i32,
+) -> Result<ProductReturnTotals, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(CASE WHEN pr.reduces_payable = 1 THEN pri.total_cost ELSE 0 END), 0.0),
+                COALESCE(SUM(CASE WHEN pr.reduces_payable = 0 THEN pri.total_cost ELSE 0 END), 0.0)
+         FROM purchase_return_items pri
+         JOIN purchase_returns pr ON pr.id = pri.return_id
+         WHERE pri.product_id = ?1 AND (?2 IS NULL OR pr.supplier_id = ?2)",
+        params![product_id, supplier_id],
+        |row| Ok(ProductReturnTotals { payable_reduction: row.get(0)?, refundable_credit: row.get(1)? }),
+    )
+    .map_err(|e| format!("Failed to load purchase returns: {}", e))
+}
+
+/// Next return number (PR-YYYY-NNN)
+fn generate_return_number(conn: &Connection) -> Result<String, String> {
+    let prefix = format!("PR-{}-", Utc::now().format("%Y"));
+    let max_seq: i32 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(CAST(substr(return_number, ?2) AS INTEGER)), 0)
+             FROM purchase_returns WHERE return_number LIKE ?1 || '%'",
+            params![prefix, prefix.len() as i32 + 1],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to generate return number: {}", e))?;
+    Ok(format!("{}{:03}", prefix, max_seq + 1))
+}
+
+/// Record goods returned to a supplier
+#[tauri::command]
+pub fn create_purchase_return(
+    input: CreatePurchaseReturnInput,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<PurchaseReturn, String> {
+    log::info!(
+        "create_purchase_return called for po_id: {:?}, supplier_id: {:?}, {} item(s)",
+        input.po_id,
+        input.supplier_id,
+        input.items.len()
+    );
+
+    let conn = db.get_conn()?;
+    let purchase_return = create_purchase_return_internal(&conn, input)?;
+    match purchase_return.po_id {
+        Some(po_id) => emit_data_changed(&app, DataEntity::PurchaseOrder, DataOperation::Updated, po_id),
+        None => {
+            for item in &purchase_return.items {
+                emit_data_changed(&app, DataEntity::Product, DataOperation::Updated, item.product_id);
+            }
+        }
+    }
+    Ok(purchase_return)
+}
+
+pub(crate) fn create_purchase_return_internal(
+    conn: &Connection,
+    input: CreatePurchaseReturnInput,
+) -> Result<PurchaseReturn, String> {
+    let reason = input.reason.trim();
+    if reason.is_empty() {
+        return Err("A reason is required for a purchase return".to_string());
+    }
+    if input.items.is_empty() {
+        return Err("A purchase return needs at least one item".to_string());
+    }
+    for item in &input.items {
+        if item.quantity <= 0.0 {
+            return Err("Item quantity must be greater than 0".to_string());
+        }
+        products::check_product_quantity(conn, item.product_id, item.quantity)?;
+        if item.unit_cost < 0.0 {
+            return Err("Item unit cost cannot be negative".to_string());
+        }
+    }
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let supplier_id = match (input.po_id, input.supplier_id, input.product_id) {
+        (Some(po_id), _, _) => tx
+            .query_row("SELECT supplier_id FROM purchase_orders WHERE id = ?1", [po_id], |row| row.get::<_, i32>(0))
+            .optional()
+            .map_err(|e| format!("Failed to load purchase order: {}", e))?
+            .ok_or_else(|| format!("Purchase order with ID {} not found", po_id))?,
+        (None, Some(supplier_id), Some(product_id)) => {
+            if input.items.iter().any(|item| item.product_id != product_id) {
+                return Err("Every item of an opening stock return must be the selected product".to_string());
+            }
+            supplier_id
+        }
+        _ => return Err("Select a purchase order, or a supplier and product".to_string()),
+    };
+
+    // Each line is returned against the PO line it came in on (None for opening stock), and can't
+    // exceed what came in on it less what was already returned
+    let mut lines: Vec<(&PurchaseReturnItemInput, Option<i32>)> = Vec::new();
+    for item in &input.items {
+        let (po_item_id, received) = match input.po_id {
+            Some(po_id) => {
+                let (po_item_id, quantity) = tx
+                    .query_row(
+                        "SELECT id, quantity FROM purchase_order_items WHERE po_id = ?1 AND product_id = ?2
+                         ORDER BY id LIMIT 1",
+                        [po_id, item.product_id],
+                        |row| Ok((row.get::<_, i32>(0)?, row.get::<_, f64>(1)?)),
+                    )
+                    .optional()
+                    .map_err(|e| format!("Failed to load purchase order item: {}", e))?
+                    .ok_or_else(|| format!("Product {} is not on purchase order {}", item.product_id, po_id))?;
+                (Some(po_item_id), quantity)
+            }
+            None => {
+                let initial_stock = tx
+                    .query_row(
+                        "SELECT COALESCE(initial_stock, 0) FROM products WHERE id = ?1 AND supplier_id = ?2",
+                        [item.product_id, supplier_id],
+                        |row| row.get::<_, f64>(0),
+                    )
+                    .optional()
+                    .map_err(|e| format!("Failed to load product: {}", e))?
+                    .ok_or_else(|| {
+                        format!("Product {} has no opening stock from supplier {}", item.product_id, supplier_id)
+                    })?;
+                (None, initial_stock)
+            }
+        };
+
+        let already_returned: f64 = tx
+            .query_row(
+                "SELECT COALESCE(SUM(pri.quantity), 0)
+                 FROM purchase_return_items pri
+                 JOIN purchase_returns pr ON pr.id = pri.return_id
+                 WHERE pri.product_id = ?1 AND pri.po_item_id IS ?2 AND pr.supplier_id = ?3",
+                params![item.product_id, po_item_id, supplier_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to load earlier returns: {}", e))?;
+        let in_this_return = quantity::sum(
+            lines
+                .iter()
+                .filter(|(line, id)| line.product_id == item.product_id && *id == po_item_id)
+                .map(|(line, _)| line.quantity),
+        );
+
+        let returnable = quantity::sum([received, -already_returned, -in_this_return]);
+        if quantity::to_milli(item.quantity) > quantity::to_milli(returnable) {
+            return Err(format!(
+                "Cannot return {} units of product {}: only {} left to return",
+                quantity::format_quantity(item.quantity),
+                item.product_id,
+                quantity::format_quantity(returnable.max(0.0))
+            ));
+        }
+        lines.push((item, po_item_id));
+    }
+
+    let return_date = input.return_date.unwrap_or_else(|| Utc::now().format("%Y-%m-%d").to_string());
+    let total_amount = money::sum(input.items.iter().map(|item| money::line_total(item.unit_cost, item.quantity)));
+    let return_number = generate_return_number(&tx)?;
+
+    tx.execute(
+        "INSERT INTO purchase_returns
+         (return_number, supplier_id, po_id, reason, reduces_payable, total_amount, return_date, created_by, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now'))",
+        params![
+            return_number,
+            supplier_id,
+            input.po_id,
+            reason,
+            input.reduces_payable,
+            total_amount,
+            return_date,
+            input.created_by,
+        ],
+    )
+    .map_err(|e| format!("Failed to create purchase return: {}", e))?;
+    let return_id = tx.last_insert_rowid() as i32;
+
+    for (item, po_item_id) in lines {
+        tx.execute(
+            "INSERT INTO purchase_return_items (return_id, product_id, po_item_id, quantity, unit_cost, total_cost)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                return_id,
+                item.product_id,
+                po_item_id,
+                item.quantity,
+                item.unit_cost,
+                money::line_total(item.unit_cost, item.quantity),
+            ],
+        )
+        .map_err(|e| format!("Failed to create purchase return item: {}", e))?;
+
+        inventory_service::record_purchase_return(
+            &tx,
+            item.product_id,
+            item.quantity,
+            item.unit_cost,
+            po_item_id,
+            return_id,
+            &return_date,
+        )?;
+    }
+
+    let purchase_return = get_purchase_return_internal(&tx, return_id)?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Created purchase return {} for supplier {}", purchase_return.return_number, supplier_id);
+    Ok(purchase_return)
+}
+
+/// Get purchase returns for a supplier, newest first
+#[tauri::command]
+pub fn get_purchase_returns(supplier_id: i32, db: State<Database>) -> Result<Vec<PurchaseReturn>, String> {
+    log::info!("get_purchase_returns called for supplier_id: {}", supplier_id);
+
+    let conn = db.get_conn()?;
+    let ids = {
+        let mut stmt = conn
+            .prepare("SELECT id FROM purchase_returns WHERE supplier_id = ?1 ORDER BY return_date DESC, id DESC")
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map([supplier_id], |row| row.get::<_, i32>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>();
+        ids.map_err(|e| format!("Failed to load purchase returns: {}", e))?
+    };
+
+    ids.into_iter().map(|id| get_purchase_return_internal(&conn, id)).collect()
+}
+
+pub(crate) fn get_purchase_return_internal(conn: &Connection, id: i32) -> Result<PurchaseReturn, String> {
+    let mut purchase_return = conn
+        .query_row(
+            "SELECT id, return_number, supplier_id, po_id, reason, reduces_payable, total_amount,
+                    return_date, created_by, created_at
+             FROM purchase_returns WHERE id = ?1",
+            [id],
+            |row| {
+                Ok(PurchaseReturn {
+                    id: row.get(0)?,
+                    return_number: row.get(1)?,
+                    supplier_id: row.get(2)?,
+                    po_id: row.get(3)?,
+                    reason: row.get(4)?,
+                    reduces_payable: row.get(5)?,
+                    total_amount: row.get(6)?,
+                    return_date: row.get(7)?,
+                    created_by: row.get(8)?,
+                    created_at: row.get(9)?,
+                    items: Vec::new(),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load purchase return: {}", e))?
+        .ok_or_else(|| format!("Purchase return with ID {} not found", id))?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, product_id, po_item_id, quantity, unit_cost, total_cost
+             FROM purchase_return_items WHERE return_id = ?1 ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let items = stmt
+        .query_map([id], |row| {
+            Ok(PurchaseReturnItem {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                po_item_id: row.get(2)?,
+                quantity: row.get(3)?,
+                unit_cost: row.get(4)?,
+                total_cost: row.get(5)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>();
+    purchase_return.items = items.map_err(|e| format!("Failed to load purchase return items: {}", e))?;
+
+    Ok(purchase_return)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::products::{create_product_internal, CreateProductInput};
+    use crate::commands::purchase_orders::{create_purchase_order_internal, get_product_purchase_history_internal};
+    use crate::commands::suppliers::{get_all_product_payment_summary_internal, get_supplier_payment_summary_internal};
+    use crate::db::models::{CreatePurchaseOrderInput, PurchaseOrderItemInput};
+    use crate::test_support::{batch_quantity, insert_supplier, TestDb};
+
+    fn return_input(po_id: Option<i32>, product_id: i32, quantity: i32, unit_cost: f64) -> CreatePurchaseReturnInput {
+        CreatePurchaseReturnInput {
+            po_id,
+            supplier_id: None,
+            product_id: None,
+            items: vec![PurchaseReturnItemInput { product_id, quantity: quantity as f64, unit_cost }],
+            reason: "Damaged in transit".to_string(),
+            reduces_payable: true,
+            return_date: None,
+            created_by: None,
+        }
+    }
+
+    #[test]
+    fn test_returns_deduct_stock_and_payable() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme Traders");
+
+        // Opening stock of 10 at 40, then a PO for 20 at 50
+        let product = create_product_internal(
+            &conn,
+            CreateProductInput {
+                name: "Kettle".to_string(),
+                sku: "KET-1".to_string(),
+                price: 40.0,
+                selling_price: Some(70.0),
+                stock_quantity: 10.0,
+                supplier_id: Some(supplier_id),
+                amount_paid: None,
+                category: None,
+                unit: None,
+                allow_fractional: false,
+            },
+        )
+        .unwrap();
+        let po = create_purchase_order_internal(
+            &conn,
+            CreatePurchaseOrderInput {
+                supplier_id,
+                items: vec![PurchaseOrderItemInput { product_id: product.id, quantity: 20.0, unit_cost: 50.0 }],
+                order_date: None,
+                expected_delivery_date: None,
+                notes: None,
+                initial_payment: None,
+                currency: None,
+                exchange_rate: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(get_supplier_payment_summary_internal(&conn, supplier_id, product.id).unwrap().total_payable, 1400.0);
+
+        // 5 back against the PO come out of the PO's batch, not the older opening stock
+        let first = create_purchase_return_internal(&conn, return_input(Some(po.id), product.id, 5, 50.0)).unwrap();
+        assert_eq!(first.supplier_id, supplier_id);
+        assert_eq!(first.total_amount, 250.0);
+        assert!(first.items[0].po_item_id.is_some());
+        let opening_left: f64 = conn
+            .query_row(
+                "SELECT SUM(quantity_remaining) FROM inventory_batches WHERE product_id = ?1 AND po_item_id IS NULL",
+                [product.id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(opening_left, 10.0);
+        assert_eq!(batch_quantity(&conn, product.id), 25.0);
+
+        // Only 15 of the PO line are left to return
+        assert!(create_purchase_return_internal(&conn, return_input(Some(po.id), product.id, 16, 50.0)).is_err());
+
+        // 3 of the opening stock for a refund instead of a set-off
+        let mut input = return_input(None, product.id, 3, 40.0);
+        input.supplier_id = Some(supplier_id);
+        input.product_id = Some(product.id);
+        input.reduces_payable = false;
+        let second = create_purchase_return_internal(&conn, input).unwrap();
+        assert_ne!(second.return_number, first.return_number);
+
+        let summary = get_supplier_payment_summary_internal(&conn, supplier_id, product.id).unwrap();
+        assert_eq!(summary.total_payable, 1150.0);
+        assert_eq!(summary.pending_amount, 1150.0);
+        assert_eq!(summary.refundable_credit, 120.0);
+        assert_eq!(get_all_product_payment_summary_internal(&conn, product.id).unwrap().total_payable, 1150.0);
+
+        let stock: f64 = conn
+            .query_row("SELECT stock_quantity FROM products WHERE id = ?1", [product.id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stock, 22.0);
+        assert_eq!(batch_quantity(&conn, product.id), 22.0);
+
+        // Received less returned on the purchase history screen matches stock
+        let history = get_product_purchase_history_internal(&conn, product.id).unwrap();
+        assert_eq!(history.iter().filter(|lot| lot.is_return).count(), 2);
+        assert_eq!(history.iter().map(|lot| lot.quantity).sum::<f64>(), stock);
+    }
+}
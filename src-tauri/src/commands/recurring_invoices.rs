@@ -0,0 +1,462 @@
+/// Recurring Invoices
+/// A template keeps a CreateInvoiceInput and a weekly or monthly schedule. When its next_run_date
+/// comes round (in the business timezone) process_due_recurring_invoices raises a real invoice
+/// through create_invoice and moves the date on past today. It runs on app startup and on demand.
+///
+/// A template whose items lack stock is skipped and flagged with last_error; it stays due, so the
+/// next run after restocking bills it. last_generated_date is claimed before the invoice is made,
+/// so running twice in a day (or crashing halfway) never bills a template twice.
+use chrono::{Duration, Months, NaiveDate};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::commands::analytics::{business_today, parse_report_date};
+use crate::commands::bundles;
+use crate::commands::invoices::{create_invoice_internal, queue_invoice_created, CreateInvoiceInput};
+use crate::commands::products;
+use crate::commands::stock_alerts::notify_stock_alerts;
+use crate::db::Database;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use crate::services::quantity;
+
+const FREQUENCIES: &[&str] = &["weekly", "monthly"];
+
+#[derive(Debug, Serialize)]
+pub struct RecurringInvoiceTemplate {
+    pub id: i64,
+    pub name: String,
+    pub customer_id: Option<i32>,
+    pub customer_name: Option<String>,
+    /// The invoice raised on each run
+    pub input: CreateInvoiceInput,
+    /// "weekly" or "monthly"
+    pub frequency: String,
+    /// "YYYY-MM-DD" in the business timezone
+    pub next_run_date: String,
+    pub active: bool,
+    pub last_generated_date: Option<String>,
+    pub last_invoice_id: Option<i32>,
+    /// Why the last due run was skipped; cleared once an invoice is raised
+    pub last_error: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRecurringTemplateInput {
+    pub name: String,
+    pub input: CreateInvoiceInput,
+    pub frequency: String,
+    /// "YYYY-MM-DD"; defaults to today, so the first invoice is raised on the next run
+    pub next_run_date: Option<String>,
+    pub created_by: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GeneratedRecurringInvoice {
+    pub template_id: i64,
+    pub invoice_id: i32,
+    pub invoice_number: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedRecurringTemplate {
+    pub template_id: i64,
+    pub name: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct RecurringRunSummary {
+    pub generated: Vec<GeneratedRecurringInvoice>,
+    pub skipped: Vec<SkippedRecurringTemplate>,
+}
+
+fn next_date(date: NaiveDate, frequency: &str) -> NaiveDate {
+    match frequency {
+        "monthly" => date.checked_add_months(Months::new(1)).unwrap_or(date + Duration::days(30)),
+        _ => date + Duration::days(7),
+    }
+}
+
+fn load_template(row: &rusqlite::Row) -> rusqlite::Result<RecurringInvoiceTemplate> {
+    let payload: String = row.get(4)?;
+    let input = serde_json::from_str(&payload).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e))
+    })?;
+    Ok(RecurringInvoiceTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        customer_id: row.get(2)?,
+        customer_name: row.get(3)?,
+        input,
+        frequency: row.get(5)?,
+        next_run_date: row.get(6)?,
+        active: row.get(7)?,
+        last_generated_date: row.get(8)?,
+        last_invoice_id: row.get(9)?,
+        last_error: row.get(10)?,
+        created_by: row.get(11)?,
+        created_at: row.get(12)?,
+    })
+}
+
+fn templates(conn: &Connection, filter: &str, params: impl rusqlite::Params) -> Result<Vec<RecurringInvoiceTemplate>, String> {
+    let sql = format!(
+        "SELECT t.id, t.name, t.customer_id, c.name, t.payload, t.frequency, t.next_run_date, t.active,
+                t.last_generated_date, t.last_invoice_id, t.last_error, t.created_by, t.created_at
+         FROM recurring_invoice_templates t
+         LEFT JOIN customers c ON c.id = t.customer_id
+         {}",
+        filter
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let rows = stmt
+        .query_map(params, load_template)
+        .map_err(|e| format!("Failed to query recurring templates: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect recurring templates: {}", e))
+}
+
+fn get_template(conn: &Connection, id: i64) -> Result<RecurringInvoiceTemplate, String> {
+    templates(conn, "WHERE t.id = ?1", [id])?
+        .pop()
+        .ok_or_else(|| format!("Recurring template {} not found", id))
+}
+
+// ===== TEMPLATE MANAGEMENT =====
+
+/// Create a recurring invoice template
+#[tauri::command]
+pub fn create_recurring_template(
+    input: CreateRecurringTemplateInput,
+    db: State<Database>,
+) -> Result<RecurringInvoiceTemplate, String> {
+    log::info!("create_recurring_template called: {}", input.name);
+
+    let conn = db.get_conn()?;
+    create_recurring_template_internal(&conn, input)
+}
+
+pub(crate) fn create_recurring_template_internal(
+    conn: &Connection,
+    input: CreateRecurringTemplateInput,
+) -> Result<RecurringInvoiceTemplate, String> {
+    let name = input.name.trim();
+    if name.is_empty() {
+        return Err("Validation error: template name is required".to_string());
+    }
+    if !FREQUENCIES.contains(&input.frequency.as_str()) {
+        return Err(format!("Validation error: frequency must be one of {}", FREQUENCIES.join(", ")));
+    }
+    let next_run_date = match input.next_run_date.as_deref() {
+        Some(date) => parse_report_date(date)?,
+        None => business_today(conn),
+    };
+
+    let invoice = &input.input;
+    if invoice.items.is_empty() {
+        return Err("Validation error: a recurring invoice needs at least one item".to_string());
+    }
+    for item in &invoice.items {
+        if item.quantity <= 0.0 {
+            return Err(format!("Validation error: quantity for product {} must be positive", item.product_id));
+        }
+        // Reservations expire; each run sells from free stock
+        if item.reservation_id.is_some() {
+            return Err("Validation error: recurring invoices can't use stock reservations".to_string());
+        }
+        let exists: bool = conn
+            .query_row("SELECT COUNT(*) > 0 FROM products WHERE id = ?1", [item.product_id], |row| row.get(0))
+            .map_err(|e| format!("Failed to check product: {}", e))?;
+        if !exists {
+            return Err(format!("Product with id {} not found", item.product_id));
+        }
+        products::check_product_quantity(conn, item.product_id, item.quantity)?;
+    }
+    if let Some(customer_id) = invoice.customer_id {
+        let exists: bool = conn
+            .query_row("SELECT COUNT(*) > 0 FROM customers WHERE id = ?1", [customer_id], |row| row.get(0))
+            .map_err(|e| format!("Failed to check customer: {}", e))?;
+        if !exists {
+            return Err(format!("Customer with id {} not found", customer_id));
+        }
+    }
+
+    let payload = serde_json::to_string(invoice).map_err(|e| format!("Failed to serialize invoice: {}", e))?;
+    conn.execute(
+        "INSERT INTO recurring_invoice_templates (name, customer_id, payload, frequency, next_run_date, created_by)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            name,
+            invoice.customer_id,
+            payload,
+            input.frequency,
+            next_run_date.format("%Y-%m-%d").to_string(),
+            input.created_by
+        ],
+    )
+    .map_err(|e| format!("Failed to create recurring template: {}", e))?;
+
+    get_template(conn, conn.last_insert_rowid())
+}
+
+/// Get all recurring invoice templates, soonest due first
+#[tauri::command]
+pub fn get_recurring_templates(db: State<Database>) -> Result<Vec<RecurringInvoiceTemplate>, String> {
+    log::info!("get_recurring_templates called");
+
+    let conn = db.get_conn()?;
+    templates(&conn, "ORDER BY t.active DESC, t.next_run_date, t.id", [])
+}
+
+/// Pause or resume a recurring template
+#[tauri::command]
+pub fn set_recurring_template_active(id: i64, active: bool, db: State<Database>) -> Result<RecurringInvoiceTemplate, String> {
+    log::info!("set_recurring_template_active called for id: {} ({})", id, active);
+
+    let conn = db.get_conn()?;
+    let updated = conn
+        .execute(
+            "UPDATE recurring_invoice_templates SET active = ?1, updated_at = datetime('now') WHERE id = ?2",
+            params![active, id],
+        )
+        .map_err(|e| format!("Failed to update recurring template: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Recurring template {} not found", id));
+    }
+    get_template(&conn, id)
+}
+
+/// Delete a recurring template; invoices it already raised are kept
+#[tauri::command]
+pub fn delete_recurring_template(id: i64, db: State<Database>) -> Result<(), String> {
+    log::info!("delete_recurring_template called for id: {}", id);
+
+    let conn = db.get_conn()?;
+    let deleted = conn
+        .execute("DELETE FROM recurring_invoice_templates WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to delete recurring template: {}", e))?;
+    if deleted == 0 {
+        return Err(format!("Recurring template {} not found", id));
+    }
+    Ok(())
+}
+
+// ===== DUE RUNS =====
+
+/// Raise the invoices of every active template that is due
+#[tauri::command]
+pub fn process_due_recurring_invoices(app: AppHandle, db: State<Database>) -> Result<RecurringRunSummary, String> {
+    log::info!("process_due_recurring_invoices called");
+
+    let mut conn = db.get_conn()?;
+    let summary = process_due_recurring_invoices_internal(&mut conn)?;
+    for generated in &summary.generated {
+        emit_data_changed(&app, DataEntity::Invoice, DataOperation::Created, generated.invoice_id);
+        queue_invoice_created(&app, &conn, generated.invoice_id);
+        notify_stock_alerts(&app, &conn, generated.invoice_id);
+    }
+    Ok(summary)
+}
+
+/// Raise every due template's invoice, oldest due first. Runs on app startup.
+pub fn process_due_recurring_invoices_internal(conn: &mut Connection) -> Result<RecurringRunSummary, String> {
+    let today = business_today(conn);
+    let today_str = today.format("%Y-%m-%d").to_string();
+    let due = templates(
+        conn,
+        "WHERE t.active = 1 AND t.next_run_date <= ?1
+           AND (t.last_generated_date IS NULL OR t.last_generated_date < ?1)
+         ORDER BY t.next_run_date, t.id",
+        [&today_str],
+    )?;
+
+    let mut summary = RecurringRunSummary::default();
+    for template in due {
+        match run_template(conn, &template, today) {
+            Ok(generated) => {
+                log::info!("Recurring template {} raised invoice {}", template.id, generated.invoice_number);
+                summary.generated.push(generated);
+            }
+            Err(reason) => {
+                log::warn!("Recurring template {} skipped: {}", template.id, reason);
+                conn.execute(
+                    "UPDATE recurring_invoice_templates SET last_error = ?1, updated_at = datetime('now') WHERE id = ?2",
+                    params![reason, template.id],
+                )
+                .map_err(|e| format!("Failed to flag recurring template: {}", e))?;
+                summary.skipped.push(SkippedRecurringTemplate { template_id: template.id, name: template.name, reason });
+            }
+        }
+    }
+    Ok(summary)
+}
+
+fn run_template(
+    conn: &mut Connection,
+    template: &RecurringInvoiceTemplate,
+    today: NaiveDate,
+) -> Result<GeneratedRecurringInvoice, String> {
+    let requested: Vec<(i32, f64)> = template.input.items.iter().map(|item| (item.product_id, item.quantity)).collect();
+    let shortfalls = bundles::sale_shortfalls(conn, &requested, &[])?;
+    let short: Vec<String> = requested
+        .iter()
+        .zip(&shortfalls)
+        .filter(|(_, short)| **short > 0.0)
+        .map(|((product_id, _), short)| format!("product {} is {} short", product_id, quantity::format_quantity(*short)))
+        .collect();
+    if !short.is_empty() {
+        return Err(format!("Insufficient stock: {}", short.join(", ")));
+    }
+
+    // Claim today before raising the invoice; a crash after this skips the run rather than
+    // billing it twice
+    let today_str = today.format("%Y-%m-%d").to_string();
+    conn.execute(
+        "UPDATE recurring_invoice_templates SET last_generated_date = ?1 WHERE id = ?2",
+        params![today_str, template.id],
+    )
+    .map_err(|e| format!("Failed to update recurring template: {}", e))?;
+
+    let invoice = match create_invoice_internal(conn, template.input.clone()) {
+        Ok(invoice) => invoice,
+        Err(e) => {
+            conn.execute(
+                "UPDATE recurring_invoice_templates SET last_generated_date = ?1 WHERE id = ?2",
+                params![template.last_generated_date, template.id],
+            )
+            .map_err(|e| format!("Failed to update recurring template: {}", e))?;
+            return Err(e);
+        }
+    };
+
+    // Move past today; runs missed while the app was closed aren't billed again
+    let mut next_run = parse_report_date(&template.next_run_date)?;
+    while next_run <= today {
+        next_run = next_date(next_run, &template.frequency);
+    }
+
+    let tx = conn.unchecked_transaction().map_err(|e| format!("Failed to start transaction: {}", e))?;
+    tx.execute(
+        "UPDATE recurring_invoice_templates
+         SET next_run_date = ?1, last_invoice_id = ?2, last_error = NULL, updated_at = datetime('now')
+         WHERE id = ?3",
+        params![next_run.format("%Y-%m-%d").to_string(), invoice.id, template.id],
+    )
+    .map_err(|e| format!("Failed to update recurring template: {}", e))?;
+
+    let field_changes = serde_json::json!([
+        { "field": "recurring_template_id", "old": null, "new": template.id },
+        { "field": "recurring_template", "old": null, "new": template.name },
+    ]);
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by)
+         VALUES ('invoice', ?1, ?2, 'recurring_generated', ?3, 'system')",
+        params![invoice.id, invoice.invoice_number, field_changes.to_string()],
+    )
+    .map_err(|e| format!("Failed to log recurring invoice: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(GeneratedRecurringInvoice {
+        template_id: template.id,
+        invoice_id: invoice.id,
+        invoice_number: invoice.invoice_number,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::invoices::CreateInvoiceItemInput;
+    use crate::commands::products::{create_product_internal, get_product_internal};
+    use crate::test_support::{insert_customer, invoice_input, product_input, TestDb};
+
+    fn basket(customer_id: i32, product_id: i32, quantity: i32) -> CreateInvoiceInput {
+        invoice_input(Some(customer_id), vec![(product_id, quantity, 20.0)])
+    }
+
+    #[test]
+    fn test_due_templates_invoice_once_per_day_and_skip_without_stock() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let customer_id = insert_customer(&conn, "Weekly");
+        let product = create_product_internal(&conn, product_input("MILK", 10.0, 5, None)).unwrap();
+
+        let today = business_today(&conn);
+        let weekly = create_recurring_template_internal(
+            &conn,
+            CreateRecurringTemplateInput {
+                name: "Weekly milk".to_string(),
+                input: basket(customer_id, product.id, 3),
+                frequency: "weekly".to_string(),
+                next_run_date: Some((today - Duration::days(14)).format("%Y-%m-%d").to_string()),
+                created_by: None,
+            },
+        )
+        .unwrap();
+        let too_big = create_recurring_template_internal(
+            &conn,
+            CreateRecurringTemplateInput {
+                name: "Bulk milk".to_string(),
+                input: basket(customer_id, product.id, 50),
+                frequency: "monthly".to_string(),
+                next_run_date: None,
+                created_by: None,
+            },
+        )
+        .unwrap();
+
+        let mut reserved = basket(customer_id, product.id, 1);
+        reserved.items = vec![CreateInvoiceItemInput {
+            product_id: product.id,
+            quantity: 1.0,
+            unit_price: 20.0,
+            discount_amount: None,
+            tax_rate: None,
+            reservation_id: Some(1),
+        }];
+        assert!(create_recurring_template_internal(
+            &conn,
+            CreateRecurringTemplateInput {
+                name: "Reserved".to_string(),
+                input: reserved,
+                frequency: "weekly".to_string(),
+                next_run_date: None,
+                created_by: None,
+            },
+        )
+        .is_err());
+
+        let summary = process_due_recurring_invoices_internal(&mut conn).unwrap();
+        assert_eq!(summary.generated.len(), 1);
+        assert_eq!(summary.generated[0].template_id, weekly.id);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.skipped[0].template_id, too_big.id);
+        assert_eq!(get_product_internal(&conn, product.id).unwrap().stock_quantity, 2.0);
+
+        // Missed weeks aren't billed again and the next run is a week out
+        let weekly = get_template(&conn, weekly.id).unwrap();
+        assert_eq!(weekly.next_run_date, (today + Duration::days(7)).format("%Y-%m-%d").to_string());
+        assert_eq!(weekly.last_invoice_id, Some(summary.generated[0].invoice_id));
+        assert!(get_template(&conn, too_big.id).unwrap().last_error.unwrap().starts_with("Insufficient stock"));
+
+        // Running again the same day raises nothing new; the flagged template stays due
+        let again = process_due_recurring_invoices_internal(&mut conn).unwrap();
+        assert!(again.generated.is_empty());
+        assert_eq!(again.skipped.len(), 1);
+
+        let invoices: i64 = conn.query_row("SELECT COUNT(*) FROM invoices", [], |row| row.get(0)).unwrap();
+        assert_eq!(invoices, 1);
+        let logged: String = conn
+            .query_row(
+                "SELECT field_changes FROM entity_modifications WHERE action = 'recurring_generated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let changes: serde_json::Value = serde_json::from_str(&logged).unwrap();
+        assert_eq!(changes[0]["new"], weekly.id);
+    }
+}
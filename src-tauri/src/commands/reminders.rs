@@ -0,0 +1,281 @@
+/// Payment Reminders
+/// A daily "who to chase" list built from overdue credit invoices, reminder messages for
+/// WhatsApp, and a log of when each customer was last reminded.
+
+use crate::commands::analytics::business_today;
+use crate::commands::customer_payments::get_overdue_invoices_internal;
+use crate::db::Database;
+use crate::services::money;
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// app_settings key holding the reminder message template
+const REMINDER_TEMPLATE_KEY: &str = "reminder_template";
+
+/// Used when no reminder template has been saved
+const DEFAULT_REMINDER_TEMPLATE: &str = "Hi {name}, a gentle reminder that ₹{overdue} of your ₹{outstanding} balance is overdue.\n{invoices}\nPlease clear it at the earliest. Thank you!";
+
+/// A customer with overdue credit, for the reminders list
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaymentReminder {
+    pub customer_id: i32,
+    pub customer_name: String,
+    pub customer_phone: Option<String>,
+    pub overdue_total: f64,
+    pub overdue_invoice_count: i32,
+    pub oldest_invoice_date: String,
+    pub max_days_overdue: i64,
+    /// "YYYY-MM-DD" of the last reminder sent, if any
+    pub last_reminded_on: Option<String>,
+    pub days_since_reminder: Option<i64>,
+}
+
+/// Get customers with overdue balances, largest overdue total first
+#[tauri::command]
+pub fn get_payment_reminders(db: State<Database>) -> Result<Vec<PaymentReminder>, String> {
+    log::info!("get_payment_reminders called");
+
+    let conn = db.get_conn()?;
+    get_payment_reminders_internal(&conn)
+}
+
+pub(crate) fn get_payment_reminders_internal(conn: &Connection) -> Result<Vec<PaymentReminder>, String> {
+    let today = business_today(conn);
+
+    let mut reminders: Vec<PaymentReminder> = Vec::new();
+    for invoice in get_overdue_invoices_internal(conn, None)? {
+        match reminders.iter_mut().find(|r| r.customer_id == invoice.customer_id) {
+            Some(reminder) => {
+                reminder.overdue_total = money::sum([reminder.overdue_total, invoice.balance_remaining]);
+                reminder.overdue_invoice_count += 1;
+                if invoice.invoice_date < reminder.oldest_invoice_date {
+                    reminder.oldest_invoice_date = invoice.invoice_date;
+                }
+                reminder.max_days_overdue = reminder.max_days_overdue.max(invoice.days_overdue);
+            }
+            None => reminders.push(PaymentReminder {
+                customer_id: invoice.customer_id,
+                customer_name: invoice.customer_name,
+                customer_phone: invoice.customer_phone,
+                overdue_total: invoice.balance_remaining,
+                overdue_invoice_count: 1,
+                oldest_invoice_date: invoice.invoice_date,
+                max_days_overdue: invoice.days_overdue,
+                last_reminded_on: None,
+                days_since_reminder: None,
+            }),
+        }
+    }
+
+    for reminder in reminders.iter_mut() {
+        let last: Option<String> = conn
+            .query_row(
+                "SELECT MAX(reminder_date) FROM payment_reminders WHERE customer_id = ?1",
+                [reminder.customer_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to get last reminder: {}", e))?;
+
+        reminder.days_since_reminder = last
+            .as_deref()
+            .and_then(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .map(|date| (today - date).num_days());
+        reminder.last_reminded_on = last;
+    }
+
+    reminders.sort_by(|a, b| {
+        b.overdue_total
+            .partial_cmp(&a.overdue_total)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.customer_name.cmp(&b.customer_name))
+    });
+
+    Ok(reminders)
+}
+
+/// Render a reminder message for a customer from the reminder_template setting.
+///
+/// Supported placeholders: {name}, {phone}, {outstanding}, {overdue}, {invoice_count} and
+/// {invoices} (one line per unpaid credit invoice with its due date and balance). The result
+/// can be passed straight to open_whatsapp_chat.
+#[tauri::command]
+pub fn render_reminder_message(customer_id: i32, db: State<Database>) -> Result<String, String> {
+    log::info!("render_reminder_message called for customer_id: {}", customer_id);
+
+    let conn = db.get_conn()?;
+    render_reminder_message_internal(&conn, customer_id)
+}
+
+pub(crate) fn render_reminder_message_internal(conn: &Connection, customer_id: i32) -> Result<String, String> {
+    let template: String = conn
+        .query_row("SELECT value FROM app_settings WHERE key = ?1", [REMINDER_TEMPLATE_KEY], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to load template: {}", e))?
+        .filter(|t: &String| !t.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_REMINDER_TEMPLATE.to_string());
+
+    let (name, phone): (String, Option<String>) = conn
+        .query_row("SELECT name, phone FROM customers WHERE id = ?1", [customer_id], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })
+        .map_err(|e| format!("Customer not found: {}", e))?;
+
+    let today = business_today(conn).format("%Y-%m-%d").to_string();
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.invoice_number, i.created_at, i.due_date, i.total_amount,
+                    COALESCE((SELECT SUM(cp.amount) FROM customer_payments cp WHERE cp.invoice_id = i.id), 0)
+             FROM invoices i
+             WHERE i.customer_id = ?1 AND (i.credit_amount > 0 OR i.payment_method = 'Credit')
+             ORDER BY i.created_at ASC, i.id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([customer_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, f64>(3)?,
+                row.get::<_, f64>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut lines = Vec::new();
+    let mut outstanding = 0.0;
+    let mut overdue = 0.0;
+    for row in rows {
+        let (invoice_number, created_at, due_date, total, paid) = row.map_err(|e| e.to_string())?;
+        let balance = money::sub(total, paid);
+        if balance <= 0.0 {
+            continue;
+        }
+
+        let date = created_at.get(..10).unwrap_or(&created_at);
+        let is_overdue = due_date.as_deref().is_some_and(|due| due < today.as_str());
+        let due = match (&due_date, is_overdue) {
+            (Some(due), true) => format!(", overdue since {}", due),
+            (Some(due), false) => format!(", due {}", due),
+            (None, _) => String::new(),
+        };
+        lines.push(format!("• {} dated {}{}: ₹{:.2}", invoice_number, date, due, balance));
+
+        outstanding = money::sum([outstanding, balance]);
+        if is_overdue {
+            overdue = money::sum([overdue, balance]);
+        }
+    }
+
+    if lines.is_empty() {
+        return Err(format!("{} has no outstanding balance", name));
+    }
+
+    let values = [
+        ("{name}", name),
+        ("{phone}", phone.unwrap_or_default()),
+        ("{outstanding}", format!("{:.2}", outstanding)),
+        ("{overdue}", format!("{:.2}", overdue)),
+        ("{invoice_count}", lines.len().to_string()),
+        ("{invoices}", lines.join("\n")),
+    ];
+
+    let mut message = template;
+    for (placeholder, value) in values.iter() {
+        message = message.replace(placeholder, value);
+    }
+
+    Ok(message)
+}
+
+/// Record that a customer was reminded today. Reminding again on the same day updates that
+/// day's entry rather than adding another.
+#[tauri::command]
+pub fn mark_reminder_sent(
+    customer_id: i32,
+    channel: Option<String>,
+    sent_by: Option<String>,
+    db: State<Database>,
+) -> Result<(), String> {
+    log::info!("mark_reminder_sent called for customer_id: {}, channel: {:?}", customer_id, channel);
+
+    let conn = db.get_conn()?;
+    mark_reminder_sent_internal(&conn, customer_id, channel.as_deref(), sent_by.as_deref())
+}
+
+pub(crate) fn mark_reminder_sent_internal(
+    conn: &Connection,
+    customer_id: i32,
+    channel: Option<&str>,
+    sent_by: Option<&str>,
+) -> Result<(), String> {
+    let today = business_today(conn).format("%Y-%m-%d").to_string();
+
+    conn.execute(
+        "INSERT INTO payment_reminders (customer_id, reminder_date, channel, sent_by)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (customer_id, reminder_date)
+         DO UPDATE SET channel = excluded.channel, sent_by = excluded.sent_by, created_at = datetime('now')",
+        rusqlite::params![customer_id, today, channel.unwrap_or("whatsapp"), sent_by],
+    )
+    .map_err(|e| format!("Failed to record reminder: {}", e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_customer, TestDb};
+
+    fn credit_invoice(conn: &Connection, customer_id: i32, number: &str, total: f64, due_date: &str) {
+        conn.execute(
+            "INSERT INTO invoices (invoice_number, customer_id, total_amount, tax_amount, discount_amount,
+                                   payment_method, created_at, initial_paid, credit_amount, due_date)
+             VALUES (?1, ?2, ?3, 0, 0, 'Credit', '2024-01-05T10:00:00+00:00', 0, ?3, ?4)",
+            rusqlite::params![number, customer_id, total, due_date],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_reminders_group_overdue_invoices_per_customer() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let ravi = insert_customer(&conn, "Ravi");
+        let meena = insert_customer(&conn, "Meena");
+        credit_invoice(&conn, ravi, "INV-000001", 100.0, "2024-02-01");
+        credit_invoice(&conn, ravi, "INV-000002", 250.5, "2024-03-01");
+        credit_invoice(&conn, ravi, "INV-000003", 80.0, "2999-01-01");
+        credit_invoice(&conn, meena, "INV-000004", 500.0, "2024-02-15");
+
+        let reminders = get_payment_reminders_internal(&conn).unwrap();
+        assert_eq!(reminders.len(), 2);
+        assert_eq!(reminders[0].customer_name, "Meena");
+        assert_eq!(reminders[1].overdue_total, 350.5);
+        assert_eq!(reminders[1].overdue_invoice_count, 2);
+        assert!(reminders[1].last_reminded_on.is_none());
+
+        mark_reminder_sent_internal(&conn, ravi, None, Some("admin")).unwrap();
+        mark_reminder_sent_internal(&conn, ravi, Some("call"), Some("admin")).unwrap();
+        let count: i32 = conn
+            .query_row("SELECT COUNT(*) FROM payment_reminders WHERE customer_id = ?1", [ravi], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let reminders = get_payment_reminders_internal(&conn).unwrap();
+        assert_eq!(reminders[1].days_since_reminder, Some(0));
+
+        let message = render_reminder_message_internal(&conn, ravi).unwrap();
+        assert!(message.starts_with("Hi Ravi,"));
+        assert!(message.contains("₹350.50 of your ₹430.50 balance is overdue"));
+        assert!(message.contains("• INV-000001 dated 2024-01-05, overdue since 2024-02-01: ₹100.00"));
+        assert!(message.contains("• INV-000003 dated 2024-01-05, due 2999-01-01: ₹80.00"));
+
+        let walk_in = insert_customer(&conn, "Paid Up");
+        assert!(render_reminder_message_internal(&conn, walk_in).is_err());
+    }
+}
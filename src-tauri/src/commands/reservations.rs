@@ -0,0 +1,402 @@
+/// Stock Reservations
+/// Stock held for a customer who has confirmed an order but not paid yet. A reservation takes
+/// its quantity out of what other sales can use until it expires, is released, or is consumed by
+/// the invoice that fulfils it (an invoice item naming the reservation_id), which releases it.
+///
+/// Expired reservations stop counting as soon as they expire; the rows themselves are purged on
+/// startup and whenever a new reservation is made. Reserving, releasing and purging touch the
+/// product's updated_at so incremental billing snapshots pick up the new available quantity.
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::{AppHandle, State};
+
+use crate::commands::analytics::business_offset_minutes;
+use crate::commands::bundles;
+use crate::commands::invoices::CreateInvoiceItemInput;
+use crate::commands::products;
+use crate::db::Database;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use crate::services::quantity;
+
+/// Quantity held by a product's active reservations, for queries over `products p`
+pub(crate) const RESERVED_QUANTITY_SQL: &str = "(SELECT COALESCE(SUM(r.quantity), 0) FROM stock_reservations r
+      WHERE r.product_id = p.id AND r.expires_at > datetime('now'))";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StockReservation {
+    pub id: i64,
+    pub product_id: i32,
+    pub product_name: String,
+    pub quantity: f64,
+    /// Who or what the stock is held for, e.g. "Sharma order #12"
+    pub reference: String,
+    /// UTC "YYYY-MM-DD HH:MM:SS"
+    pub expires_at: String,
+    pub created_by: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReserveStockInput {
+    pub product_id: i32,
+    pub quantity: f64,
+    pub reference: String,
+    /// RFC 3339, or "YYYY-MM-DD HH:MM" in the business timezone
+    pub expires_at: String,
+    pub created_by: Option<String>,
+}
+
+const RESERVATION_SELECT: &str = "SELECT r.id, r.product_id, p.name, r.quantity, r.reference, r.expires_at, r.created_by, r.created_at
+     FROM stock_reservations r
+     JOIN products p ON r.product_id = p.id";
+
+fn reservation_from_row(row: &rusqlite::Row) -> rusqlite::Result<StockReservation> {
+    Ok(StockReservation {
+        id: row.get(0)?,
+        product_id: row.get(1)?,
+        product_name: row.get(2)?,
+        quantity: row.get(3)?,
+        reference: row.get(4)?,
+        expires_at: row.get(5)?,
+        created_by: row.get(6)?,
+        created_at: row.get(7)?,
+    })
+}
+
+/// Parse an expiry into UTC "YYYY-MM-DD HH:MM:SS"; times without an offset are business time
+fn parse_expiry(conn: &Connection, expires_at: &str) -> Result<String, String> {
+    let expires_at = expires_at.trim();
+    let utc = match DateTime::parse_from_rfc3339(expires_at) {
+        Ok(parsed) => parsed.with_timezone(&Utc).naive_utc(),
+        Err(_) => {
+            let local = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+                .iter()
+                .find_map(|format| NaiveDateTime::parse_from_str(expires_at, format).ok())
+                .ok_or_else(|| format!("Invalid expiry '{}', expected YYYY-MM-DD HH:MM", expires_at))?;
+            local - Duration::minutes(business_offset_minutes(conn) as i64)
+        }
+    };
+    if utc <= Utc::now().naive_utc() {
+        return Err("Reservation expiry must be in the future".to_string());
+    }
+    Ok(utc.format("%Y-%m-%d %H:%M:%S").to_string())
+}
+
+fn reserved_quantity(conn: &Connection, product_id: i32) -> Result<f64, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(quantity), 0) FROM stock_reservations WHERE product_id = ?1 AND expires_at > datetime('now')",
+        [product_id],
+        |row| row.get(0).map(quantity::round_quantity),
+    )
+    .map_err(|e| format!("Failed to load reservations: {}", e))
+}
+
+/// Stock not held by an active reservation; for bundles, how many can be assembled from the
+/// components' available stock
+pub(crate) fn available_quantity(conn: &Connection, product_id: i32) -> Result<f64, String> {
+    if bundles::is_bundle(conn, product_id)? {
+        let mut available: Option<f64> = None;
+        for (component_id, per_bundle) in bundles::bundle_components(conn, product_id)? {
+            let component = (available_quantity(conn, component_id)?.max(0.0) / per_bundle.max(1) as f64).floor();
+            available = Some(available.map_or(component, |a| a.min(component)));
+        }
+        return Ok(available.unwrap_or(0.0));
+    }
+
+    let stock: f64 = conn
+        .query_row("SELECT stock_quantity FROM products WHERE id = ?1", [product_id], |row| row.get(0))
+        .map_err(|_| format!("Product with id {} not found", product_id))?;
+    Ok(quantity::sub(stock, reserved_quantity(conn, product_id)?))
+}
+
+/// Quantity of `product_id` held by reservations other than those in `excluding`
+pub(crate) fn reserved_excluding(
+    conn: &Connection,
+    product_id: i32,
+    excluding: &[StockReservation],
+) -> Result<f64, String> {
+    let consumed = quantity::sum(
+        excluding
+            .iter()
+            .filter(|reservation| reservation.product_id == product_id)
+            .map(|reservation| reservation.quantity),
+    );
+    Ok(quantity::sub(reserved_quantity(conn, product_id)?, consumed))
+}
+
+/// Hold stock of a product until `expires_at`
+#[tauri::command]
+pub fn reserve_stock(input: ReserveStockInput, app: AppHandle, db: State<Database>) -> Result<StockReservation, String> {
+    log::info!("reserve_stock called with: {:?}", input);
+
+    let conn = db.get_conn()?;
+    let reservation = reserve_stock_internal(&conn, &input)?;
+    emit_data_changed(&app, DataEntity::Product, DataOperation::Updated, reservation.product_id);
+    Ok(reservation)
+}
+
+pub(crate) fn reserve_stock_internal(conn: &Connection, input: &ReserveStockInput) -> Result<StockReservation, String> {
+    if input.quantity <= 0.0 {
+        return Err("Reservation quantity must be positive".to_string());
+    }
+    let reference = input.reference.trim();
+    if reference.is_empty() {
+        return Err("Reservation reference is required".to_string());
+    }
+    let expires_at = parse_expiry(conn, &input.expires_at)?;
+
+    if bundles::is_bundle(conn, input.product_id)? {
+        return Err("Bundles hold no stock of their own; reserve their components instead".to_string());
+    }
+    products::check_product_quantity(conn, input.product_id, input.quantity)?;
+
+    purge_expired_reservations(conn)?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let available = available_quantity(&tx, input.product_id)?;
+    if quantity::to_milli(available) < quantity::to_milli(input.quantity) {
+        return Err(format!(
+            "Cannot reserve {} units; only {} available",
+            quantity::format_quantity(input.quantity),
+            quantity::format_quantity(available.max(0.0))
+        ));
+    }
+
+    let id = insert_reservation(&tx, input.product_id, input.quantity, reference, &expires_at, input.created_by.as_deref())?;
+
+    let reservation = tx
+        .query_row(&format!("{} WHERE r.id = ?1", RESERVATION_SELECT), [id], reservation_from_row)
+        .map_err(|e| format!("Failed to load reservation: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Reserved {} of product {} for '{}' until {}", input.quantity, input.product_id, reference, expires_at);
+    Ok(reservation)
+}
+
+/// Insert a reservation row without checking stock; `expires_at` is UTC "YYYY-MM-DD HH:MM:SS"
+pub(crate) fn insert_reservation(
+    conn: &Connection,
+    product_id: i32,
+    quantity: f64,
+    reference: &str,
+    expires_at: &str,
+    created_by: Option<&str>,
+) -> Result<i64, String> {
+    conn.execute(
+        "INSERT INTO stock_reservations (product_id, quantity, reference, expires_at, created_by) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![product_id, quantity, reference, expires_at, created_by],
+    )
+    .map_err(|e| format!("Failed to create reservation: {}", e))?;
+    let id = conn.last_insert_rowid();
+    touch_product(conn, product_id)?;
+    Ok(id)
+}
+
+/// Active reservations, soonest to expire first, optionally for one product
+#[tauri::command]
+pub fn get_reservations(product_id: Option<i32>, db: State<Database>) -> Result<Vec<StockReservation>, String> {
+    log::info!("get_reservations called with product_id: {:?}", product_id);
+
+    let conn = db.get_conn()?;
+    get_reservations_internal(&conn, product_id)
+}
+
+pub(crate) fn get_reservations_internal(conn: &Connection, product_id: Option<i32>) -> Result<Vec<StockReservation>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} WHERE r.expires_at > datetime('now') AND (?1 IS NULL OR r.product_id = ?1)
+             ORDER BY r.expires_at, r.id",
+            RESERVATION_SELECT
+        ))
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    let reservations = stmt
+        .query_map([product_id], reservation_from_row)
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to load reservations: {}", e))?;
+    Ok(reservations)
+}
+
+/// Release a reservation, making its stock available again
+#[tauri::command]
+pub fn release_reservation(id: i64, app: AppHandle, db: State<Database>) -> Result<(), String> {
+    log::info!("release_reservation called for id: {}", id);
+
+    let conn = db.get_conn()?;
+    let product_id = release_reservation_internal(&conn, id)?;
+    emit_data_changed(&app, DataEntity::Product, DataOperation::Updated, product_id);
+    Ok(())
+}
+
+/// Delete a reservation and return its product
+pub(crate) fn release_reservation_internal(conn: &Connection, id: i64) -> Result<i32, String> {
+    let product_id: i32 = conn
+        .query_row("DELETE FROM stock_reservations WHERE id = ?1 RETURNING product_id", [id], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to release reservation: {}", e))?
+        .ok_or_else(|| format!("Reservation with id {} not found", id))?;
+    touch_product(conn, product_id)?;
+    Ok(product_id)
+}
+
+/// Reservations named by the items of an invoice, checked to be active and for the item's product
+pub(crate) fn reservations_to_consume(
+    conn: &Connection,
+    items: &[CreateInvoiceItemInput],
+) -> Result<Vec<StockReservation>, String> {
+    let mut reservations: HashMap<i64, StockReservation> = HashMap::new();
+    for item in items {
+        let Some(id) = item.reservation_id else { continue };
+        let reservation = conn
+            .query_row(
+                &format!("{} WHERE r.id = ?1 AND r.expires_at > datetime('now')", RESERVATION_SELECT),
+                [id],
+                reservation_from_row,
+            )
+            .optional()
+            .map_err(|e| format!("Failed to load reservation: {}", e))?
+            .ok_or_else(|| format!("Reservation with id {} not found or expired", id))?;
+        if reservation.product_id != item.product_id {
+            return Err(format!(
+                "Reservation {} is for '{}', not product {}",
+                id, reservation.product_name, item.product_id
+            ));
+        }
+        reservations.insert(id, reservation);
+    }
+    Ok(reservations.into_values().collect())
+}
+
+/// Delete reservations fulfilled by an invoice
+pub(crate) fn consume_reservations(
+    conn: &Connection,
+    reservations: &[StockReservation],
+    invoice_id: i32,
+) -> Result<(), String> {
+    for reservation in reservations {
+        conn.execute("DELETE FROM stock_reservations WHERE id = ?1", [reservation.id])
+            .map_err(|e| format!("Failed to release reservation: {}", e))?;
+        log::info!(
+            "Reservation {} ('{}') consumed by invoice {}",
+            reservation.id,
+            reservation.reference,
+            invoice_id
+        );
+    }
+    Ok(())
+}
+
+/// Delete expired reservations. Runs on startup and before each new reservation.
+pub fn purge_expired_reservations(conn: &Connection) -> Result<usize, String> {
+    conn.execute(
+        "UPDATE products SET updated_at = datetime('now')
+         WHERE id IN (SELECT product_id FROM stock_reservations WHERE expires_at <= datetime('now'))",
+        [],
+    )
+    .map_err(|e| format!("Failed to purge reservations: {}", e))?;
+    conn.execute("DELETE FROM stock_reservations WHERE expires_at <= datetime('now')", [])
+        .map_err(|e| format!("Failed to purge reservations: {}", e))
+}
+
+/// Bump updated_at so incremental billing snapshots include the product
+fn touch_product(conn: &Connection, product_id: i32) -> Result<(), String> {
+    conn.execute("UPDATE products SET updated_at = datetime('now') WHERE id = ?1", [product_id])
+        .map_err(|e| format!("Failed to update product: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::invoices::{create_invoice_internal, CreateInvoiceInput};
+    use crate::commands::products::{create_product_internal, get_product_internal};
+    use crate::test_support::{invoice_input, product_input, TestDb};
+
+    fn insert_product(conn: &Connection, sku: &str, stock: i32) -> i32 {
+        create_product_internal(conn, product_input(sku, 10.0, stock, None)).unwrap().id
+    }
+
+    fn reserve(conn: &Connection, product_id: i32, quantity: i32) -> Result<StockReservation, String> {
+        let expires_at = (Utc::now() + Duration::days(1)).to_rfc3339();
+        let input = ReserveStockInput {
+            product_id,
+            quantity: quantity as f64,
+            reference: "Sharma order".to_string(),
+            expires_at,
+            created_by: None,
+        };
+        reserve_stock_internal(conn, &input)
+    }
+
+    fn sale(product_id: i32, quantity: i32, reservation_id: Option<i64>) -> CreateInvoiceInput {
+        let mut input = invoice_input(None, vec![(product_id, quantity, 15.0)]);
+        input.items[0].reservation_id = reservation_id;
+        input
+    }
+
+    #[test]
+    fn test_reserved_stock_is_held_from_other_sales() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product_id = insert_product(&conn, "PEN", 10);
+
+        let reservation = reserve(&conn, product_id, 7).unwrap();
+        assert!(reserve(&conn, product_id, 4).is_err());
+        assert_eq!(get_product_internal(&conn, product_id).unwrap().available_quantity, Some(3.0));
+
+        // A walk-in can only buy what isn't reserved
+        let err = create_invoice_internal(&mut conn, sale(product_id, 4, None)).unwrap_err();
+        assert!(err.contains("Available: 3"), "{}", err);
+        create_invoice_internal(&mut conn, sale(product_id, 3, None)).unwrap();
+
+        // The reserving customer's invoice draws on the reservation and releases it
+        create_invoice_internal(&mut conn, sale(product_id, 7, Some(reservation.id))).unwrap();
+        assert!(get_reservations_internal(&conn, Some(product_id)).unwrap().is_empty());
+        let product = get_product_internal(&conn, product_id).unwrap();
+        assert_eq!((product.stock_quantity, product.available_quantity), (0.0, Some(0.0)));
+    }
+
+    #[test]
+    fn test_expired_reservations_are_ignored_and_purged() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product_id = insert_product(&conn, "PEN", 5);
+        let reservation = reserve(&conn, product_id, 5).unwrap();
+
+        conn.execute("UPDATE stock_reservations SET expires_at = datetime('now', '-1 minutes')", []).unwrap();
+        assert_eq!(available_quantity(&conn, product_id).unwrap(), 5.0);
+        assert!(get_reservations_internal(&conn, None).unwrap().is_empty());
+        assert!(create_invoice_internal(&mut conn, sale(product_id, 1, Some(reservation.id))).is_err());
+
+        assert_eq!(purge_expired_reservations(&conn).unwrap(), 1);
+        assert!(release_reservation_internal(&conn, reservation.id).is_err());
+    }
+
+    #[test]
+    fn test_release_and_validation() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product_id = insert_product(&conn, "PEN", 5);
+        let other_id = insert_product(&conn, "INK", 5);
+
+        let reservation = reserve(&conn, product_id, 2).unwrap();
+        assert!(create_invoice_internal(&mut conn, sale(other_id, 1, Some(reservation.id))).is_err());
+
+        assert_eq!(release_reservation_internal(&conn, reservation.id).unwrap(), product_id);
+        assert_eq!(available_quantity(&conn, product_id).unwrap(), 5.0);
+
+        assert!(reserve(&conn, product_id, 0).is_err());
+        let past = ReserveStockInput {
+            product_id,
+            quantity: 1.0,
+            reference: "x".to_string(),
+            expires_at: "2020-01-01 10:00".to_string(),
+            created_by: None,
+        };
+        assert!(reserve_stock_internal(&conn, &past).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+/// Safety Snapshots
+/// Automatic copies of the database taken just before an operation that can destroy data
+/// (snapshot imports, large CSV imports, clearing the trash or the change history, schema
+/// migrations). They are written in the snapshot file format to a "safety" folder next to
+/// inventory.db, so restoring one goes through the same verified import as a manual snapshot.
+/// Only the newest safety_snapshot_count (default 5) are kept.
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::commands::maintenance::{database_file_path, MaintenanceOperation, MaintenanceState};
+use crate::commands::snapshot::{
+    emit_snapshot_imported, export_database_snapshot_internal, import_database_snapshot_internal,
+    SnapshotImportResult,
+};
+use crate::db::Database;
+
+const SAFETY_DIR: &str = "safety";
+const SAFETY_EXTENSION: &str = "invsnap";
+const SAFETY_SNAPSHOT_COUNT_KEY: &str = "safety_snapshot_count";
+const DEFAULT_SAFETY_SNAPSHOT_COUNT: usize = 5;
+
+/// CSV imports of at least this many rows get a safety snapshot before their first chunk
+pub const LARGE_IMPORT_ROWS: usize = 1000;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SafetySnapshot {
+    pub file_name: String,
+    pub file_path: String,
+    /// Command or event the snapshot was taken before
+    pub operation: String,
+    pub created_at: String,
+    pub size_bytes: u64,
+}
+
+/// Folder holding the safety snapshots of the database `conn` is open on
+fn safety_dir(conn: &Connection) -> Result<PathBuf, String> {
+    let db_path = database_file_path(conn)?;
+    let parent = db_path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .ok_or_else(|| "Safety snapshots need a file-backed database".to_string())?;
+    Ok(parent.join(SAFETY_DIR))
+}
+
+/// `<timestamp>-<operation>.invsnap`, with anything but letters, digits and `_` dropped from the operation
+fn snapshot_file_name(operation: &str) -> String {
+    let operation: String = operation
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    format!("{}-{}.{}", Utc::now().format("%Y%m%dT%H%M%S%3f"), operation, SAFETY_EXTENSION)
+}
+
+/// Read a snapshot's details back from its file name and metadata
+fn describe(path: &Path) -> Option<SafetySnapshot> {
+    if path.extension().and_then(|e| e.to_str()) != Some(SAFETY_EXTENSION) {
+        return None;
+    }
+    let file_name = path.file_name()?.to_str()?.to_string();
+    let (stamp, operation) = path.file_stem()?.to_str()?.split_once('-')?;
+    let created_at = chrono::NaiveDateTime::parse_from_str(stamp, "%Y%m%dT%H%M%S%3f")
+        .ok()?
+        .and_utc()
+        .to_rfc3339();
+
+    Some(SafetySnapshot {
+        file_path: path.to_string_lossy().to_string(),
+        file_name,
+        operation: operation.to_string(),
+        created_at,
+        size_bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+    })
+}
+
+/// Safety snapshots in `dir`, newest first
+fn snapshots_in(dir: &Path) -> Vec<SafetySnapshot> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<SafetySnapshot> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| describe(&entry.path()))
+        .collect();
+    // The timestamp prefix sorts chronologically
+    snapshots.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+    snapshots
+}
+
+fn keep_count(conn: &Connection) -> usize {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        [SAFETY_SNAPSHOT_COUNT_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|v| v.trim().parse::<usize>().ok())
+    .filter(|count| *count > 0)
+    .unwrap_or(DEFAULT_SAFETY_SNAPSHOT_COUNT)
+}
+
+/// Copy the database into the safety folder before `operation` runs, drop the snapshots
+/// beyond the configured count and record the snapshot in the activity log.
+///
+/// Must be called outside a transaction (the copy is a VACUUM INTO).
+pub(crate) fn take_safety_snapshot(conn: &Connection, operation: &str) -> Result<SafetySnapshot, String> {
+    let snapshot = create_safety_snapshot(conn, operation)?;
+    log_safety_snapshot(conn, &snapshot)?;
+    Ok(snapshot)
+}
+
+/// Write and prune a safety snapshot without logging it, for operations that replace the
+/// activity log themselves; they call `log_safety_snapshot` once they are done.
+pub(crate) fn create_safety_snapshot(conn: &Connection, operation: &str) -> Result<SafetySnapshot, String> {
+    let dir = safety_dir(conn)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create safety snapshot folder: {}", e))?;
+
+    // Fold the WAL into the main file first so the copy is as current as the database
+    let _ = conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE)");
+
+    let path = dir.join(snapshot_file_name(operation));
+    export_database_snapshot_internal(conn, &path)
+        .map_err(|e| format!("Failed to take safety snapshot before {}: {}", operation, e))?;
+    let snapshot = describe(&path).ok_or_else(|| "Failed to read back safety snapshot".to_string())?;
+
+    for old in snapshots_in(&dir).into_iter().skip(keep_count(conn)) {
+        if let Err(e) = std::fs::remove_file(&old.file_path) {
+            log::warn!("Failed to remove old safety snapshot {}: {}", old.file_name, e);
+        }
+    }
+    Ok(snapshot)
+}
+
+/// Record a safety snapshot in the activity log
+pub(crate) fn log_safety_snapshot(conn: &Connection, snapshot: &SafetySnapshot) -> Result<(), String> {
+    let summary = format!("Safety snapshot before {} ({})", snapshot.operation, snapshot.file_name);
+    conn.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES ('database', 0, ?1, 'safety_snapshot', NULL, 'system')",
+        [&summary],
+    )
+    .map_err(|e| format!("Failed to log safety snapshot: {}", e))?;
+
+    log::info!("{} - {} bytes", summary, snapshot.size_bytes);
+    Ok(())
+}
+
+/// List the safety snapshots kept for this database, newest first
+#[tauri::command]
+pub fn list_safety_snapshots(db: State<Database>) -> Result<Vec<SafetySnapshot>, String> {
+    log::info!("list_safety_snapshots called");
+
+    let conn = db.get_conn()?;
+    Ok(snapshots_in(&safety_dir(&conn)?))
+}
+
+/// Replace all data with a safety snapshot. The current data is itself snapshotted first,
+/// so a restore can be undone.
+#[tauri::command]
+pub async fn restore_safety_snapshot(
+    file_name: String,
+    app: AppHandle,
+    maintenance: State<'_, MaintenanceState>,
+    db: State<'_, Database>,
+) -> Result<SnapshotImportResult, String> {
+    log::info!("restore_safety_snapshot called <- {}", file_name);
+
+    let _lock = maintenance.acquire(MaintenanceOperation::RestoreBackup)?;
+    let mut conn = db.get_conn()?;
+    let result = restore_safety_snapshot_internal(&mut conn, &file_name)?;
+
+    emit_snapshot_imported(&app, &result);
+    Ok(result)
+}
+
+pub(crate) fn restore_safety_snapshot_internal(
+    conn: &mut Connection,
+    file_name: &str,
+) -> Result<SnapshotImportResult, String> {
+    // Only bare names from list_safety_snapshots, never a path out of the folder
+    let path = safety_dir(conn)?.join(file_name);
+    if Path::new(file_name).file_name().and_then(|n| n.to_str()) != Some(file_name) || describe(&path).is_none() {
+        return Err(format!("Validation error: {} is not a safety snapshot", file_name));
+    }
+    if !path.is_file() {
+        return Err(format!("Safety snapshot {} not found", file_name));
+    }
+
+    import_database_snapshot_internal(conn, &path, "restore_safety_snapshot")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_supplier, TestDb};
+
+    #[test]
+    fn test_safety_snapshots_are_pruned_and_restorable() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        conn.execute(
+            "INSERT INTO app_settings (key, value) VALUES (?1, '2')",
+            [SAFETY_SNAPSHOT_COUNT_KEY],
+        )
+        .unwrap();
+
+        insert_supplier(&conn, "Acme");
+        let first = take_safety_snapshot(&conn, "clear_trash").unwrap();
+        assert_eq!(first.operation, "clear_trash");
+        insert_supplier(&conn, "Globex");
+        take_safety_snapshot(&conn, "import_csv").unwrap();
+        take_safety_snapshot(&conn, "clear_modifications_history").unwrap();
+
+        // Only the newest two are kept, and each one was logged
+        let kept = snapshots_in(&safety_dir(&conn).unwrap());
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].operation, "clear_modifications_history");
+        let logged: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM entity_modifications WHERE action = 'safety_snapshot'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(logged, 3);
+
+        assert!(restore_safety_snapshot_internal(&mut conn, "../inventory.db")
+            .unwrap_err()
+            .starts_with("Validation error"));
+
+        // Restoring the oldest kept snapshot brings back both suppliers; the data it replaced
+        // is snapshotted first, and that snapshot is logged in the restored activity log
+        restore_safety_snapshot_internal(&mut conn, &kept[1].file_name).unwrap();
+        let suppliers: i64 = conn
+            .query_row("SELECT COUNT(*) FROM suppliers", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(suppliers, 2);
+        let kept = snapshots_in(&safety_dir(&conn).unwrap());
+        assert_eq!(kept[0].operation, "restore_safety_snapshot");
+        let logged: String = conn
+            .query_row(
+                "SELECT entity_name FROM entity_modifications WHERE action = 'safety_snapshot' ORDER BY id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(logged.contains(&kept[0].file_name));
+    }
+}
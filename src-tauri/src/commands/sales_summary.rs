@@ -0,0 +1,307 @@
+/// Daily Sales Summary
+/// Per-day invoice totals cached in daily_sales_summary so trends and period totals don't
+/// re-aggregate the whole invoices table on every dashboard open. Days are local business days.
+/// Triggers on invoices, invoice_items and inventory_transactions drop the cached days around a
+/// changed invoice inside the writing transaction; missing days are recomputed on the next read.
+/// Today is always aggregated live.
+use chrono::{Duration, NaiveDate};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::State;
+
+use crate::commands::analytics::{business_offset_minutes, business_today, ReportRange};
+use crate::db::Database;
+use crate::services::money;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DayTotals {
+    /// Sum of invoice totals (after discounts, including tax)
+    pub revenue: f64,
+    pub orders: i64,
+    pub tax: f64,
+    pub discount: f64,
+    /// FIFO cost of the goods sold, from the invoices' sale stock movements
+    pub cogs: f64,
+}
+
+impl DayTotals {
+    pub(crate) fn add(&mut self, other: &DayTotals) {
+        self.revenue = money::sum([self.revenue, other.revenue]);
+        self.orders += other.orders;
+        self.tax = money::sum([self.tax, other.tax]);
+        self.discount = money::sum([self.discount, other.discount]);
+        self.cogs = money::sum([self.cogs, other.cogs]);
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SalesSummaryRebuild {
+    /// Days written, including days without sales
+    pub days: usize,
+    pub first_date: Option<String>,
+    pub last_date: Option<String>,
+}
+
+fn parse_day(day: &str) -> rusqlite::Result<NaiveDate> {
+    NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+/// Invoice totals per local day (?3 modifier) in a UTC range (?1..?2), served by
+/// idx_invoices_created_utc
+pub(crate) const LIVE_DAILY_TOTALS_SQL: &str = "SELECT
+                date(created_at, ?3) as day,
+                COALESCE(SUM(total_amount), 0.0),
+                COUNT(*),
+                COALESCE(SUM(tax_amount), 0.0),
+                COALESCE(SUM(discount_amount), 0.0)
+             FROM invoices
+             WHERE datetime(created_at) >= ?1
+               AND datetime(created_at) < ?2
+             GROUP BY day";
+
+/// Per-day totals aggregated straight from invoices. Days without sales are absent.
+pub(crate) fn live_daily_totals(conn: &Connection, range: &ReportRange) -> Result<BTreeMap<NaiveDate, DayTotals>, String> {
+    let mut days: BTreeMap<NaiveDate, DayTotals> = BTreeMap::new();
+
+    let mut stmt = conn.prepare(LIVE_DAILY_TOTALS_SQL).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([&range.start_utc, &range.end_utc, &range.local_modifier], |row| {
+            Ok((
+                parse_day(&row.get::<_, String>(0)?)?,
+                DayTotals {
+                    revenue: money::round_money(row.get(1)?),
+                    orders: row.get(2)?,
+                    tax: money::round_money(row.get(3)?),
+                    discount: money::round_money(row.get(4)?),
+                    cogs: 0.0,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (day, totals) = row.map_err(|e| format!("Failed to calculate revenue: {}", e))?;
+        days.insert(day, totals);
+    }
+
+    // Sale transactions store the average FIFO cost per unit for that invoice line
+    let mut stmt = conn
+        .prepare(
+            "SELECT date(i.created_at, ?3) as day, COALESCE(SUM(-t.quantity_change * COALESCE(t.unit_cost, 0)), 0.0)
+             FROM inventory_transactions t
+             JOIN invoices i ON t.reference_id = i.id
+             WHERE t.transaction_type = 'sale'
+               AND t.reference_type = 'invoice'
+               AND datetime(i.created_at) >= ?1
+               AND datetime(i.created_at) < ?2
+             GROUP BY day",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([&range.start_utc, &range.end_utc, &range.local_modifier], |row| {
+            Ok((parse_day(&row.get::<_, String>(0)?)?, row.get::<_, f64>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (day, cogs) = row.map_err(|e| format!("Failed to calculate COGS: {}", e))?;
+        days.entry(day).or_default().cogs = money::round_money(cogs);
+    }
+
+    Ok(days)
+}
+
+fn cached_days(
+    conn: &Connection,
+    start: NaiveDate,
+    end: NaiveDate,
+    offset_minutes: i32,
+) -> rusqlite::Result<BTreeMap<NaiveDate, DayTotals>> {
+    let mut stmt = conn.prepare(
+        "SELECT date, revenue, orders, tax, discount, cogs
+         FROM daily_sales_summary
+         WHERE date >= ?1 AND date <= ?2 AND offset_minutes = ?3",
+    )?;
+    let rows = stmt.query_map(
+        params![start.format("%Y-%m-%d").to_string(), end.format("%Y-%m-%d").to_string(), offset_minutes],
+        |row| {
+            Ok((
+                parse_day(&row.get::<_, String>(0)?)?,
+                DayTotals {
+                    revenue: row.get(1)?,
+                    orders: row.get(2)?,
+                    tax: row.get(3)?,
+                    discount: row.get(4)?,
+                    cogs: row.get(5)?,
+                },
+            ))
+        },
+    )?;
+    rows.collect()
+}
+
+fn store_day(conn: &Connection, day: NaiveDate, totals: &DayTotals, offset_minutes: i32) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO daily_sales_summary (date, revenue, orders, tax, discount, cogs, offset_minutes, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'))",
+        params![
+            day.format("%Y-%m-%d").to_string(),
+            totals.revenue,
+            totals.orders,
+            totals.tax,
+            totals.discount,
+            totals.cogs,
+            offset_minutes
+        ],
+    )
+}
+
+/// Per-day totals for every local day in the range that had sales. Past days come from the
+/// summary table, recomputing (and caching) any that are missing or were computed under a
+/// different business UTC offset; today and later are aggregated live.
+pub(crate) fn daily_totals(conn: &Connection, range: &ReportRange) -> Result<BTreeMap<NaiveDate, DayTotals>, String> {
+    let offset = range.offset_minutes;
+    let cache_end = range.end_date.min(business_today(conn) - Duration::days(1));
+    let mut days = BTreeMap::new();
+
+    if range.start_date <= cache_end {
+        match cached_days(conn, range.start_date, cache_end, offset) {
+            Ok(cached) => {
+                let missing: Vec<NaiveDate> = range
+                    .start_date
+                    .iter_days()
+                    .take_while(|day| *day <= cache_end)
+                    .filter(|day| !cached.contains_key(day))
+                    .collect();
+                days.extend(cached);
+
+                if let (Some(first), Some(last)) = (missing.first(), missing.last()) {
+                    let live = live_daily_totals(conn, &ReportRange::new(*first, *last, offset))?;
+                    for day in missing {
+                        let totals = live.get(&day).copied().unwrap_or_default();
+                        if let Err(e) = store_day(conn, day, &totals, offset) {
+                            log::warn!("Failed to cache sales summary for {}: {}", day, e);
+                        }
+                        days.insert(day, totals);
+                    }
+                }
+            }
+            Err(e) => {
+                log::warn!("Sales summary unavailable, aggregating live: {}", e);
+                days.extend(live_daily_totals(conn, &ReportRange::new(range.start_date, cache_end, offset))?);
+            }
+        }
+    }
+
+    let live_start = range.start_date.max(cache_end + Duration::days(1));
+    if live_start <= range.end_date {
+        days.extend(live_daily_totals(conn, &ReportRange::new(live_start, range.end_date, offset))?);
+    }
+
+    days.retain(|_, totals| totals.orders > 0 || totals.revenue != 0.0);
+    Ok(days)
+}
+
+/// Recompute the whole summary from invoices, from the first sale up to yesterday
+#[tauri::command]
+pub fn rebuild_sales_summary(db: State<Database>) -> Result<SalesSummaryRebuild, String> {
+    log::info!("rebuild_sales_summary called");
+
+    let conn = db.get_conn()?;
+    rebuild_sales_summary_internal(&conn)
+}
+
+pub(crate) fn rebuild_sales_summary_internal(conn: &Connection) -> Result<SalesSummaryRebuild, String> {
+    let offset = business_offset_minutes(conn);
+    let yesterday = business_today(conn) - Duration::days(1);
+
+    conn.execute("DELETE FROM daily_sales_summary", [])
+        .map_err(|e| format!("Failed to clear sales summary: {}", e))?;
+
+    let first: Option<String> = conn
+        .query_row(
+            "SELECT MIN(date(created_at, ?1)) FROM invoices",
+            [format!("{:+} minutes", offset)],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to find first sale: {}", e))?;
+
+    let first = match first.as_deref().map(parse_day) {
+        Some(Ok(first)) if first <= yesterday => first,
+        _ => return Ok(SalesSummaryRebuild { days: 0, first_date: None, last_date: None }),
+    };
+
+    daily_totals(conn, &ReportRange::new(first, yesterday, offset))?;
+    let days: i64 = conn
+        .query_row("SELECT COUNT(*) FROM daily_sales_summary", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+
+    log::info!("Rebuilt sales summary for {} day(s) from {} to {}", days, first, yesterday);
+    Ok(SalesSummaryRebuild {
+        days: days as usize,
+        first_date: Some(first.format("%Y-%m-%d").to_string()),
+        last_date: Some(yesterday.format("%Y-%m-%d").to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::invoices::{create_invoice_internal, delete_invoice_internal};
+    use crate::commands::products::create_product_internal;
+    use crate::test_support::{invoice_input, product_input, TestDb};
+
+    #[test]
+    fn test_summary_matches_live_after_random_creates_and_deletes() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let product = create_product_internal(&conn, product_input("TEA", 40.0, 10_000, None)).unwrap();
+
+        let today = business_today(&conn);
+        let offset = business_offset_minutes(&conn);
+        let range = ReportRange::new(today - Duration::days(6), today, offset);
+
+        // Small deterministic LCG so the sequence is random-looking but reproducible
+        let mut seed: u64 = 0x2545_F491_4F6C_DD1D;
+        let mut next = |bound: u64| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) % bound
+        };
+
+        let mut invoices: Vec<i32> = Vec::new();
+        for step in 0..60 {
+            if invoices.is_empty() || next(3) > 0 {
+                let quantity = next(5) as i32 + 1;
+                let price = 55.0 + next(10) as f64;
+                let invoice = create_invoice_internal(&mut conn, invoice_input(None, vec![(product.id, quantity, price)])).unwrap();
+                // Spread sales over the past week by backdating them (noon local time)
+                let day = today - Duration::days(next(7) as i64);
+                let created_at = (day.and_hms_opt(12, 0, 0).unwrap() - Duration::minutes(offset as i64))
+                    .format("%Y-%m-%dT%H:%M:%S+00:00")
+                    .to_string();
+                conn.execute("UPDATE invoices SET created_at = ?1 WHERE id = ?2", params![created_at, invoice.id])
+                    .unwrap();
+                invoices.push(invoice.id);
+            } else {
+                let invoice_id = invoices.remove(next(invoices.len() as u64) as usize);
+                delete_invoice_internal(&mut conn, invoice_id, None, None).unwrap();
+            }
+
+            if step % 5 == 0 {
+                let mut live = live_daily_totals(&conn, &range).unwrap();
+                live.retain(|_, totals| totals.orders > 0);
+                assert_eq!(daily_totals(&conn, &range).unwrap(), live, "mismatch after step {}", step);
+            }
+        }
+
+        let mut live = live_daily_totals(&conn, &range).unwrap();
+        live.retain(|_, totals| totals.orders > 0);
+        assert_eq!(daily_totals(&conn, &range).unwrap(), live);
+        let cached: i64 = conn.query_row("SELECT COUNT(*) FROM daily_sales_summary", [], |row| row.get(0)).unwrap();
+        assert_eq!(cached, 6);
+
+        let rebuilt = rebuild_sales_summary_internal(&conn).unwrap();
+        assert_eq!(rebuilt.last_date, Some((today - Duration::days(1)).format("%Y-%m-%d").to_string()));
+        assert_eq!(daily_totals(&conn, &range).unwrap(), live);
+    }
+}
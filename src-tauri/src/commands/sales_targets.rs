@@ -0,0 +1,282 @@
+/// Sales Targets
+/// A revenue goal for a month ("2025-03") or a quarter ("2025-Q1"). Progress is computed from
+/// invoices in the business timezone each time it's asked for, so targets can be changed or
+/// deleted without touching the actuals.
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::State;
+
+use crate::commands::analytics::{business_offset_minutes, business_today, ReportRange};
+use crate::db::Database;
+use crate::services::money;
+
+const PERIOD_TYPES: &[&str] = &["monthly", "quarterly"];
+
+#[derive(Debug, Serialize)]
+pub struct SalesTargetProgress {
+    pub id: i64,
+    /// "monthly" or "quarterly"
+    pub period_type: String,
+    /// "YYYY-MM" or "YYYY-Qn"
+    pub period_key: String,
+    pub start_date: String,
+    pub end_date: String,
+    pub target_amount: f64,
+    pub actual_revenue: f64,
+    pub invoice_count: i64,
+    pub percent_achieved: f64,
+    pub days_total: i64,
+    /// Days of the period before today
+    pub days_elapsed: i64,
+    /// Days left in the period, today included
+    pub days_remaining: i64,
+    /// Revenue at the end of the period if sales keep their pace so far (today included);
+    /// the actual revenue once the period is over
+    pub projected_revenue: f64,
+    /// What has to be sold each remaining day to reach the target
+    pub required_daily_amount: f64,
+}
+
+/// First and last day of a period, checking the key matches its type
+fn period_bounds(period_type: &str, period_key: &str) -> Result<(NaiveDate, NaiveDate), String> {
+    let invalid = || format!("Validation error: invalid {} period '{}'", period_type, period_key);
+    let (start, months) = match period_type {
+        "monthly" => {
+            let start = NaiveDate::parse_from_str(&format!("{}-01", period_key), "%Y-%m-%d").map_err(|_| invalid())?;
+            (start, 1)
+        }
+        "quarterly" => {
+            let (year, quarter) = period_key.split_once("-Q").ok_or_else(invalid)?;
+            let year: i32 = year.parse().map_err(|_| invalid())?;
+            let quarter: u32 = quarter.parse().map_err(|_| invalid())?;
+            if !(1..=4).contains(&quarter) {
+                return Err(invalid());
+            }
+            (NaiveDate::from_ymd_opt(year, quarter * 3 - 2, 1).ok_or_else(invalid)?, 3)
+        }
+        _ => return Err(format!("Validation error: period type must be one of {}", PERIOD_TYPES.join(", "))),
+    };
+    // Keys are written the canonical way ("2025-03", not "2025-3") so they stay unique
+    let canonical = match period_type {
+        "monthly" => start.format("%Y-%m").to_string(),
+        _ => format!("{}-Q{}", start.year(), start.month0() / 3 + 1),
+    };
+    if canonical != period_key {
+        return Err(invalid());
+    }
+    let end = start.checked_add_months(Months::new(months)).ok_or_else(invalid)? - Duration::days(1);
+    Ok((start, end))
+}
+
+fn target_progress(
+    conn: &Connection,
+    id: i64,
+    period_type: String,
+    period_key: String,
+    target_amount: f64,
+    today: NaiveDate,
+) -> Result<SalesTargetProgress, String> {
+    let (start, end) = period_bounds(&period_type, &period_key)?;
+    let range = ReportRange::new(start, end, business_offset_minutes(conn));
+    let (actual_revenue, invoice_count): (f64, i64) = conn
+        .query_row(
+            "SELECT COALESCE(SUM(total_amount), 0.0), COUNT(*) FROM invoices WHERE created_at >= ?1 AND created_at < ?2",
+            params![range.start_utc, range.end_utc],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| format!("Failed to compute revenue: {}", e))?;
+
+    let days_total = (end - start).num_days() + 1;
+    let days_elapsed = (today - start).num_days().clamp(0, days_total);
+    let days_remaining = days_total - days_elapsed;
+    let projected_revenue = if days_remaining == 0 {
+        actual_revenue
+    } else if today >= start {
+        money::round_money(actual_revenue / (days_elapsed + 1) as f64 * days_total as f64)
+    } else {
+        0.0
+    };
+    let shortfall = money::sub(target_amount, actual_revenue).max(0.0);
+    let required_daily_amount = if days_remaining > 0 { money::round_money(shortfall / days_remaining as f64) } else { 0.0 };
+
+    Ok(SalesTargetProgress {
+        id,
+        period_type,
+        period_key,
+        start_date: start.format("%Y-%m-%d").to_string(),
+        end_date: end.format("%Y-%m-%d").to_string(),
+        target_amount,
+        actual_revenue,
+        invoice_count,
+        percent_achieved: if target_amount > 0.0 { actual_revenue / target_amount * 100.0 } else { 0.0 },
+        days_total,
+        days_elapsed,
+        days_remaining,
+        projected_revenue,
+        required_daily_amount,
+    })
+}
+
+/// Set (or replace) the revenue target for a month or quarter
+#[tauri::command]
+pub fn set_sales_target(
+    period_type: String,
+    period_key: String,
+    amount: f64,
+    db: State<Database>,
+) -> Result<SalesTargetProgress, String> {
+    log::info!("set_sales_target called: {} {} = {}", period_type, period_key, amount);
+
+    let conn = db.get_conn()?;
+    set_sales_target_internal(&conn, &period_type, &period_key, amount)
+}
+
+pub(crate) fn set_sales_target_internal(
+    conn: &Connection,
+    period_type: &str,
+    period_key: &str,
+    amount: f64,
+) -> Result<SalesTargetProgress, String> {
+    period_bounds(period_type, period_key)?;
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err("Validation error: target amount must be positive".to_string());
+    }
+
+    conn.execute(
+        "INSERT INTO sales_targets (period_type, period_key, amount) VALUES (?1, ?2, ?3)
+         ON CONFLICT(period_key) DO UPDATE SET amount = ?3, updated_at = datetime('now')",
+        params![period_type, period_key, money::round_money(amount)],
+    )
+    .map_err(|e| format!("Failed to save sales target: {}", e))?;
+
+    get_sales_target_progress_internal(conn, period_key, business_today(conn))?
+        .ok_or_else(|| format!("Sales target for {} not found", period_key))
+}
+
+/// Target vs actual for one period; None when no target is set for it
+#[tauri::command]
+pub fn get_sales_target_progress(period_key: String, db: State<Database>) -> Result<Option<SalesTargetProgress>, String> {
+    log::info!("get_sales_target_progress called for {}", period_key);
+
+    let conn = db.get_conn()?;
+    get_sales_target_progress_internal(&conn, &period_key, business_today(&conn))
+}
+
+pub(crate) fn get_sales_target_progress_internal(
+    conn: &Connection,
+    period_key: &str,
+    today: NaiveDate,
+) -> Result<Option<SalesTargetProgress>, String> {
+    let target: Option<(i64, String, f64)> = conn
+        .query_row(
+            "SELECT id, period_type, amount FROM sales_targets WHERE period_key = ?1",
+            [period_key],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load sales target: {}", e))?;
+
+    target
+        .map(|(id, period_type, amount)| target_progress(conn, id, period_type, period_key.to_string(), amount, today))
+        .transpose()
+}
+
+/// All targets with their actuals, oldest period first, for a target-vs-actual chart
+#[tauri::command]
+pub fn get_sales_targets(period_type: Option<String>, db: State<Database>) -> Result<Vec<SalesTargetProgress>, String> {
+    log::info!("get_sales_targets called");
+
+    let conn = db.get_conn()?;
+    let today = business_today(&conn);
+    let targets: Vec<(i64, String, String, f64)> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, period_type, period_key, amount FROM sales_targets
+                 WHERE ?1 IS NULL OR period_type = ?1
+                 ORDER BY period_key, period_type",
+            )
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt
+            .query_map([&period_type], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+            .map_err(|e| format!("Failed to query sales targets: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect sales targets: {}", e))?
+    };
+
+    targets
+        .into_iter()
+        .map(|(id, period_type, period_key, amount)| target_progress(&conn, id, period_type, period_key, amount, today))
+        .collect()
+}
+
+/// Delete a target; the invoices behind its actuals are untouched
+#[tauri::command]
+pub fn delete_sales_target(id: i64, db: State<Database>) -> Result<(), String> {
+    log::info!("delete_sales_target called for id: {}", id);
+
+    let conn = db.get_conn()?;
+    let deleted = conn
+        .execute("DELETE FROM sales_targets WHERE id = ?1", [id])
+        .map_err(|e| format!("Failed to delete sales target: {}", e))?;
+    if deleted == 0 {
+        return Err(format!("Sales target {} not found", id));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    #[test]
+    fn test_period_keys_are_checked() {
+        let march = period_bounds("monthly", "2025-03").unwrap();
+        assert_eq!(march, (NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(), NaiveDate::from_ymd_opt(2025, 3, 31).unwrap()));
+        let q4 = period_bounds("quarterly", "2024-Q4").unwrap();
+        assert_eq!(q4, (NaiveDate::from_ymd_opt(2024, 10, 1).unwrap(), NaiveDate::from_ymd_opt(2024, 12, 31).unwrap()));
+
+        assert!(period_bounds("monthly", "2025-3").is_err());
+        assert!(period_bounds("monthly", "2025-Q1").is_err());
+        assert!(period_bounds("quarterly", "2025-Q5").is_err());
+        assert!(period_bounds("yearly", "2025").is_err());
+    }
+
+    #[test]
+    fn test_progress_projects_run_rate_and_daily_need() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('business_utc_offset', '+00:00')", []).unwrap();
+        for (number, amount, created_at) in [
+            ("INV-1", 30000.0, "2025-04-02 10:00:00"),
+            ("INV-2", 20000.0, "2025-04-09 10:00:00"),
+            ("INV-3", 99999.0, "2025-05-01 10:00:00"),
+        ] {
+            conn.execute(
+                "INSERT INTO invoices (invoice_number, total_amount, created_at) VALUES (?1, ?2, ?3)",
+                params![number, amount, created_at],
+            )
+            .unwrap();
+        }
+        set_sales_target_internal(&conn, "monthly", "2025-04", 300000.0).unwrap();
+
+        // 10 April: 9 days done, 50,000 sold; 21 days left to find 250,000
+        let today = NaiveDate::from_ymd_opt(2025, 4, 10).unwrap();
+        let progress = get_sales_target_progress_internal(&conn, "2025-04", today).unwrap().unwrap();
+        assert_eq!((progress.actual_revenue, progress.invoice_count), (50000.0, 2));
+        assert!((progress.percent_achieved - 16.666).abs() < 0.01);
+        assert_eq!((progress.days_total, progress.days_elapsed, progress.days_remaining), (30, 9, 21));
+        assert_eq!(progress.projected_revenue, 150000.0);
+        assert_eq!(progress.required_daily_amount, 11904.76);
+
+        // Once the month is over the projection is the actual
+        let later = get_sales_target_progress_internal(&conn, "2025-04", NaiveDate::from_ymd_opt(2025, 6, 1).unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!((later.projected_revenue, later.required_daily_amount, later.days_remaining), (50000.0, 0.0, 0));
+
+        assert!(get_sales_target_progress_internal(&conn, "2025-05", today).unwrap().is_none());
+        assert!(set_sales_target_internal(&conn, "monthly", "2025-04", 0.0).is_err());
+    }
+}
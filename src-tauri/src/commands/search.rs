@@ -1,3 +1,4 @@
+use crate::commands::variants::DISPLAY_NAME_SQL;
 use crate::db::Database;
 use serde::{Deserialize, Serialize};
 use tauri::State;
@@ -8,6 +9,7 @@ pub struct SearchResult {
     pub customers: Vec<SearchCustomer>,
     pub suppliers: Vec<SearchSupplier>,
     pub invoices: Vec<SearchInvoice>,
+    pub challans: Vec<SearchChallan>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -16,7 +18,9 @@ pub struct SearchProduct {
     pub name: String,
     pub sku: String,
     pub price: f64,
-    pub stock_quantity: i32,
+    pub stock_quantity: f64,
+    /// "Parent — L / Blue" for variants, the plain name otherwise
+    pub display_name: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -47,6 +51,16 @@ pub struct SearchInvoice {
     pub created_at: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SearchChallan {
+    pub id: i32,
+    pub challan_number: String,
+    pub customer_name: Option<String>,
+    /// "open", "invoiced" or "returned"
+    pub status: String,
+    pub created_at: String,
+}
+
 /// OmniSearch: Search across all entities
 #[tauri::command]
 pub fn omnisearch(query: String, db: State<Database>) -> Result<SearchResult, String> {
@@ -59,7 +73,14 @@ pub fn omnisearch(query: String, db: State<Database>) -> Result<SearchResult, St
     // Search products
     let mut products = Vec::new();
     let mut stmt = conn
-        .prepare("SELECT id, name, sku, price, stock_quantity FROM products WHERE name LIKE ?1 OR sku LIKE ?1 LIMIT 10")
+        .prepare(&format!(
+            "SELECT p.id, p.name, p.sku, p.price, p.stock_quantity, {}
+             FROM products p
+             WHERE p.name LIKE ?1 OR p.sku LIKE ?1
+                OR p.parent_product_id IN (SELECT id FROM products WHERE name LIKE ?1)
+             LIMIT 10",
+            DISPLAY_NAME_SQL
+        ))
         .map_err(|e| e.to_string())?;
 
     let product_iter = stmt
@@ -70,6 +91,7 @@ pub fn omnisearch(query: String, db: State<Database>) -> Result<SearchResult, St
                 sku: row.get(2)?,
                 price: row.get(3)?,
                 stock_quantity: row.get(4)?,
+                display_name: row.get(5)?,
             })
         })
         .map_err(|e| e.to_string())?;
@@ -124,10 +146,16 @@ pub fn omnisearch(query: String, db: State<Database>) -> Result<SearchResult, St
         suppliers.push(supplier.map_err(|e| e.to_string())?);
     }
 
-    // Search invoices
+    // Search invoices, by number or by an item name as printed on the bill
     let mut invoices = Vec::new();
     let mut stmt = conn
-        .prepare("SELECT id, invoice_number, total_amount, created_at FROM invoices WHERE invoice_number LIKE ?1 LIMIT 10")
+        .prepare(
+            "SELECT i.id, i.invoice_number, i.total_amount, i.created_at FROM invoices i
+             WHERE i.invoice_number LIKE ?1
+                OR EXISTS (SELECT 1 FROM invoice_items ii WHERE ii.invoice_id = i.id AND ii.product_name LIKE ?1)
+             ORDER BY i.created_at DESC
+             LIMIT 10",
+        )
         .map_err(|e| e.to_string())?;
 
     let invoice_iter = stmt
@@ -145,15 +173,46 @@ pub fn omnisearch(query: String, db: State<Database>) -> Result<SearchResult, St
         invoices.push(invoice.map_err(|e| e.to_string())?);
     }
 
+    // Search delivery challans, by number, customer or an item name
+    let mut challans = Vec::new();
+    let mut stmt = conn
+        .prepare(
+            "SELECT dc.id, dc.challan_number, c.name, dc.status, dc.created_at FROM delivery_challans dc
+             LEFT JOIN customers c ON c.id = dc.customer_id
+             WHERE dc.challan_number LIKE ?1 OR c.name LIKE ?1
+                OR EXISTS (SELECT 1 FROM delivery_challan_items ci WHERE ci.challan_id = dc.id AND ci.product_name LIKE ?1)
+             ORDER BY dc.created_at DESC
+             LIMIT 10",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let challan_iter = stmt
+        .query_map([&search_pattern], |row| {
+            Ok(SearchChallan {
+                id: row.get(0)?,
+                challan_number: row.get(1)?,
+                customer_name: row.get(2)?,
+                status: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    for challan in challan_iter {
+        challans.push(challan.map_err(|e| e.to_string())?);
+    }
+
     let result = SearchResult {
         products,
         customers,
         suppliers,
         invoices,
+        challans,
     };
 
     log::info!("omnisearch returning {} total results",
-        result.products.len() + result.customers.len() + result.suppliers.len() + result.invoices.len());
+        result.products.len() + result.customers.len() + result.suppliers.len() + result.invoices.len()
+            + result.challans.len());
 
     Ok(result)
 }
@@ -177,7 +236,7 @@ pub fn export_products_csv(db: State<Database>) -> Result<String, String> {
             let name: String = row.get(1)?;
             let sku: String = row.get(2)?;
             let price: f64 = row.get(3)?;
-            let stock_quantity: i32 = row.get(4)?;
+            let stock_quantity: f64 = row.get(4)?;
             let supplier_id: Option<i32> = row.get(5)?;
 
             Ok((id, name, sku, price, stock_quantity, supplier_id))
@@ -1,6 +1,19 @@
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tauri::State;
 use crate::db::Database;
+use crate::commands::analytics::parse_utc_offset;
+use crate::commands::biometric::REQUIRE_BIOMETRIC_KEY;
+use crate::commands::challans::CHALLAN_STOCK_MODES;
+use crate::commands::user_preferences::{self, ImportedPreference, USER_PREFERENCES_EXPORT_KEY};
+use crate::services::inventory_service::COSTING_METHOD_KEY;
+
+/// GST slab rates (%) accepted as the default invoice rate
+const GST_RATES: &[&str] = &["0", "0.25", "3", "5", "12", "18", "28", "40"];
+/// Payment methods offered on the billing screen
+const PAYMENT_METHODS: &[&str] = &["Cash", "Credit Card", "Online", "UPI", "NetBanking", "Wallet", "Credit"];
+const ROUND_OFF_MODES: &[&str] = &["nearest", "down", "off"];
 
 /// Get a single app setting by key
 #[tauri::command]
@@ -19,15 +32,31 @@ pub fn get_app_setting(key: String, db: State<Database>) -> Result<Option<String
     Ok(result)
 }
 
+/// Settings with their own command; writing them directly would skip what that command does
+fn check_not_managed(key: &str) -> Result<(), String> {
+    if key == COSTING_METHOD_KEY {
+        return Err(format!("Validation error: {} can only be changed with convert_costing_method", key));
+    }
+    if key == REQUIRE_BIOMETRIC_KEY {
+        return Err(format!("Validation error: {} can only be changed with set_protected_actions", key));
+    }
+    Ok(())
+}
+
 /// Set an app setting (insert or update)
 #[tauri::command]
 pub fn set_app_setting(key: String, value: String, db: State<Database>) -> Result<(), String> {
     let conn = db.get_conn()?;
+    set_app_setting_internal(&conn, &key, &value)
+}
+
+pub(crate) fn set_app_setting_internal(conn: &Connection, key: &str, value: &str) -> Result<(), String> {
+    check_not_managed(key)?;
 
     conn.execute(
         "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))
          ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = datetime('now')",
-        [&key, &value],
+        [key, value],
     )
     .map_err(|e| format!("Failed to save setting: {}", e))?;
 
@@ -63,53 +92,569 @@ pub fn get_all_settings(db: State<Database>) -> Result<HashMap<String, String>,
 #[tauri::command]
 pub fn delete_app_setting(key: String, db: State<Database>) -> Result<(), String> {
     let conn = db.get_conn()?;
+    delete_app_setting_internal(&conn, &key)
+}
 
-    conn.execute("DELETE FROM app_settings WHERE key = ?1", [&key])
+pub(crate) fn delete_app_setting_internal(conn: &Connection, key: &str) -> Result<(), String> {
+    check_not_managed(key)?;
+
+    conn.execute("DELETE FROM app_settings WHERE key = ?1", [key])
         .map_err(|e| format!("Failed to delete setting: {}", e))?;
 
     Ok(())
 }
 
-/// Export all settings as a JSON string
+/// Export all settings as a JSON string.
+///
+/// Per-user preferences are left out unless `include_user_preferences` is true, in which case
+/// they're added under the "user_preferences" key.
 #[tauri::command]
-pub fn export_settings_json(db: State<Database>) -> Result<String, String> {
-    let settings = get_all_settings(db)?;
-    serde_json::to_string_pretty(&settings).map_err(|e| format!("Failed to serialize settings: {}", e))
+pub fn export_settings_json(include_user_preferences: Option<bool>, db: State<Database>) -> Result<String, String> {
+    let include_user_preferences = include_user_preferences.unwrap_or(false);
+    let user_preferences = if include_user_preferences {
+        let conn = db.get_conn()?;
+        Some(user_preferences::export_user_preferences(&conn)?)
+    } else {
+        None
+    };
+
+    let mut exported: serde_json::Map<String, serde_json::Value> = get_all_settings(db)?
+        .into_iter()
+        .map(|(key, value)| (key, serde_json::Value::String(value)))
+        .collect();
+    if let Some(preferences) = user_preferences {
+        exported.insert(USER_PREFERENCES_EXPORT_KEY.to_string(), preferences);
+    }
+
+    serde_json::to_string_pretty(&exported).map_err(|e| format!("Failed to serialize settings: {}", e))
+}
+
+/// Expected value format for a known setting
+#[derive(Debug, Clone, Copy)]
+enum SettingKind {
+    Text,
+    Bool,
+    Integer { min: i64, max: i64 },
+    Number,
+    /// 24-hour "HH:MM"
+    Time,
+    /// UTC offset such as "+05:30"
+    UtcOffset,
+    Json,
+    OneOf(&'static [&'static str]),
+}
+
+/// Registry entry describing a setting that can appear in an exported settings file
+struct SettingSpec {
+    key: &'static str,
+    /// Match every key starting with `key` (e.g. share templates)
+    prefix: bool,
+    kind: SettingKind,
+    importable: bool,
+    /// Credentials or API keys; importable, but flagged in the report
+    secret: bool,
+}
+
+const fn spec(key: &'static str, kind: SettingKind) -> SettingSpec {
+    SettingSpec { key, prefix: false, kind, importable: true, secret: false }
+}
+
+const fn secret(key: &'static str, kind: SettingKind) -> SettingSpec {
+    SettingSpec { key, prefix: false, kind, importable: true, secret: true }
+}
+
+const KNOWN_SETTINGS: &[SettingSpec] = &[
+    // Location defaults
+    spec("default_state", SettingKind::Text),
+    spec("default_district", SettingKind::Text),
+    spec("default_town", SettingKind::Text),
+    // State the business is registered in, for CGST/SGST vs IGST (falls back to default_state)
+    spec("home_state", SettingKind::Text),
+    // Billing defaults applied by create_invoice when the input leaves them out
+    spec("default_gst_rate", SettingKind::OneOf(GST_RATES)),
+    spec("default_payment_method", SettingKind::OneOf(PAYMENT_METHODS)),
+    spec("credit_period_days", SettingKind::Integer { min: 0, max: 3650 }),
+    // How far (%) under its list or tier price a line may be billed before the sale needs a
+    // price override reason (commands::price_overrides); unset turns the check off
+    spec("price_override_tolerance_percent", SettingKind::Number),
+    // Sell past the stock on hand, recording the shortfall on the invoice line as backordered.
+    // Also read by the database triggers that keep stock from going negative (migration 43).
+    spec("allow_negative_stock", SettingKind::Bool),
+    // Low stock: the reorder level for products without their own, how long a product waits
+    // before raising another stock-alert, and whether stock on open POs counts towards the level
+    // (commands::stock_alerts)
+    spec("low_stock_threshold", SettingKind::Integer { min: 0, max: 1_000_000 }),
+    spec("stock_alert_cooldown_hours", SettingKind::Integer { min: 0, max: 720 }),
+    spec("low_stock_counts_on_order", SettingKind::Bool),
+    // How a new delivery challan holds its stock until invoiced or returned (commands::challans)
+    spec("challan_stock_mode", SettingKind::OneOf(CHALLAN_STOCK_MODES)),
+    // Image search and storage
+    secret("google_api_key", SettingKind::Text),
+    spec("google_cx_id", SettingKind::Text),
+    spec("image_search_providers", SettingKind::Text),
+    spec("bing_search_endpoint", SettingKind::Text),
+    spec("searxng_url", SettingKind::Text),
+    secret("bing_api_key", SettingKind::Text),
+    spec("image_max_dimension", SettingKind::Integer { min: 100, max: 10000 }),
+    spec("image_quality", SettingKind::Integer { min: 1, max: 100 }),
+    // Backups
+    secret("gdrive_credentials_json", SettingKind::Json),
+    spec("backup_time", SettingKind::Time),
+    spec("retention_days", SettingKind::Integer { min: 1, max: 3650 }),
+    spec("safety_snapshot_count", SettingKind::Integer { min: 1, max: 50 }),
+    // Run the data integrity checks nightly at backup_time (commands::integrity)
+    spec("integrity_checks_nightly", SettingKind::Bool),
+    // Local integration listener (commands::integration)
+    spec("integration_enabled", SettingKind::Bool),
+    spec("integration_port", SettingKind::Integer { min: 1024, max: 65535 }),
+    secret("integration_token", SettingKind::Text),
+    // Outbound webhooks (commands::webhooks); webhook_events is a comma-separated event list
+    spec("webhook_url", SettingKind::Text),
+    secret("webhook_secret", SettingKind::Text),
+    spec("webhook_events", SettingKind::Text),
+    // Reports and currency
+    spec("business_utc_offset", SettingKind::UtcOffset),
+    spec("base_currency_symbol", SettingKind::Text),
+    spec("inventory_valuation_method", SettingKind::OneOf(&["last_price", "fifo"])),
+    // Changing it re-costs stock, so it goes through convert_costing_method, never an import
+    SettingSpec {
+        key: COSTING_METHOD_KEY,
+        prefix: false,
+        kind: SettingKind::OneOf(&["fifo", "weighted_average"]),
+        importable: false,
+        secret: false,
+    },
+    // Loosening it needs a manager's approval, so it goes through set_protected_actions
+    SettingSpec {
+        key: REQUIRE_BIOMETRIC_KEY,
+        prefix: false,
+        kind: SettingKind::Json,
+        importable: false,
+        secret: false,
+    },
+    // Trash
+    spec("trash_retention_days", SettingKind::Integer { min: 0, max: 3650 }),
+    // Activity log
+    spec("modifications_retention_days", SettingKind::Integer { min: 0, max: 3650 }),
+    // Held sales (commands::held_sales)
+    spec("held_sale_retention_hours", SettingKind::Integer { min: 0, max: 8760 }),
+    // Invoice numbering and PDF layout
+    spec("invoice_auto_fy", SettingKind::Bool),
+    spec("invoice_prefix", SettingKind::Text),
+    spec("invoice_separator", SettingKind::Text),
+    spec("invoice_start_number", SettingKind::Integer { min: 1, max: i64::MAX }),
+    spec("invoice_reset_rule", SettingKind::OneOf(&["yearly", "monthly", "never"])),
+    spec("invoice_round_off", SettingKind::OneOf(ROUND_OFF_MODES)),
+    spec("invoice_company_name", SettingKind::Text),
+    spec("invoice_company_address", SettingKind::Text),
+    spec("invoice_company_email", SettingKind::Text),
+    spec("invoice_company_phone", SettingKind::Text),
+    spec("invoice_company_comments", SettingKind::Text),
+    spec("invoice_font_size_body", SettingKind::Number),
+    spec("invoice_font_size_header", SettingKind::Number),
+    spec("invoice_header_align", SettingKind::OneOf(&["left", "center", "right"])),
+    spec("invoice_header_x", SettingKind::Number),
+    spec("invoice_header_y", SettingKind::Number),
+    spec("invoice_logo_path", SettingKind::Text),
+    spec("invoice_logo_width", SettingKind::Number),
+    spec("invoice_logo_x", SettingKind::Number),
+    spec("invoice_logo_y", SettingKind::Number),
+    spec("invoice_padding", SettingKind::Number),
+    spec("quick_add_ids", SettingKind::Json),
+    // Receipt printing
+    spec("receipt_printer_address", SettingKind::Text),
+    spec("receipt_paper_width", SettingKind::OneOf(&["58", "80"])),
+    spec("receipt_charset", SettingKind::Text),
+    spec("receipt_copies", SettingKind::Integer { min: 1, max: 5 }),
+    spec("receipt_footer", SettingKind::Text),
+    // Message sent to customers with overdue credit (commands::reminders)
+    spec("reminder_template", SettingKind::Text),
+    // Share message templates
+    SettingSpec {
+        key: "share_template_",
+        prefix: true,
+        kind: SettingKind::Text,
+        importable: true,
+        secret: false,
+    },
+];
+
+fn find_setting_spec(key: &str) -> Option<&'static SettingSpec> {
+    KNOWN_SETTINGS
+        .iter()
+        .find(|s| if s.prefix { key.starts_with(s.key) } else { key == s.key })
+}
+
+/// Convert a JSON value from a settings file into the stored string form and validate it
+fn validate_setting_value(kind: SettingKind, value: &serde_json::Value) -> Result<String, String> {
+    use serde_json::Value;
+
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => return Err("value is null".to_string()),
+        Value::Array(_) | Value::Object(_) => match kind {
+            SettingKind::Json => value.to_string(),
+            _ => return Err("expected a string, got a JSON object/array".to_string()),
+        },
+    };
+
+    match kind {
+        SettingKind::Text => Ok(text),
+        SettingKind::Bool => match text.as_str() {
+            "true" | "false" => Ok(text),
+            _ => Err(format!("expected \"true\" or \"false\", got \"{}\"", text)),
+        },
+        SettingKind::Integer { min, max } => match text.trim().parse::<i64>() {
+            Ok(n) if n >= min && n <= max => Ok(n.to_string()),
+            Ok(n) => Err(format!("{} is outside the allowed range {}..={}", n, min, max)),
+            Err(_) => Err(format!("expected a whole number, got \"{}\"", text)),
+        },
+        SettingKind::Number => match text.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(text.trim().to_string()),
+            _ => Err(format!("expected a number, got \"{}\"", text)),
+        },
+        SettingKind::Time => {
+            let valid = text.len() == 5
+                && chrono::NaiveTime::parse_from_str(&text, "%H:%M").is_ok();
+            if valid {
+                Ok(text)
+            } else {
+                Err(format!("expected a time as HH:MM, got \"{}\"", text))
+            }
+        }
+        SettingKind::UtcOffset => match parse_utc_offset(&text) {
+            Some(_) => Ok(text),
+            None => Err(format!("expected a UTC offset like +05:30, got \"{}\"", text)),
+        },
+        SettingKind::Json => serde_json::from_str::<serde_json::Value>(&text)
+            .map(|_| text)
+            .map_err(|e| format!("invalid JSON: {}", e)),
+        SettingKind::OneOf(allowed) => {
+            if allowed.contains(&text.as_str()) {
+                Ok(text)
+            } else {
+                Err(format!("expected one of {}, got \"{}\"", allowed.join(", "), text))
+            }
+        }
+    }
+}
+
+/// Settings create_invoice falls back to, read together with typed values
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BillingDefaults {
+    /// GST rate (%) for invoices that give neither a rate nor a tax amount; None leaves them untaxed
+    pub gst_rate: Option<f64>,
+    pub payment_method: String,
+    /// Location recorded on invoices that don't give one (default_state/district/town)
+    pub state: Option<String>,
+    pub district: Option<String>,
+    pub town: Option<String>,
+    /// State the business is registered in, for CGST/SGST vs IGST; falls back to `state`
+    pub home_state: Option<String>,
+    /// Days after the sale a credit invoice falls due
+    pub credit_period_days: i64,
+    /// Rounding of invoice totals: "nearest" rupee, "down" to the rupee, or "off"
+    pub round_off: String,
 }
 
-/// Import settings from a JSON string
+const BILLING_DEFAULT_KEYS: &[&str] = &[
+    "default_gst_rate",
+    "default_payment_method",
+    "default_state",
+    "default_district",
+    "default_town",
+    "home_state",
+    "credit_period_days",
+    "invoice_round_off",
+];
+
+/// Read the billing defaults; missing or invalid values fall back to Cash, no GST rate,
+/// a 30 day credit period and rounding to the nearest rupee
+pub(crate) fn billing_defaults(conn: &Connection) -> Result<BillingDefaults, String> {
+    let placeholders = vec!["?"; BILLING_DEFAULT_KEYS.len()].join(", ");
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT key, value FROM app_settings WHERE key IN ({}) AND TRIM(value) != ''",
+            placeholders
+        ))
+        .map_err(|e| e.to_string())?;
+    let values: HashMap<String, String> = stmt
+        .query_map(rusqlite::params_from_iter(BILLING_DEFAULT_KEYS), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?.trim().to_string()))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to read billing defaults: {}", e))?;
+
+    let text = |key: &str| values.get(key).cloned();
+    let state = text("default_state");
+    Ok(BillingDefaults {
+        gst_rate: text("default_gst_rate")
+            .filter(|rate| GST_RATES.contains(&rate.as_str()))
+            .and_then(|rate| rate.parse().ok()),
+        payment_method: text("default_payment_method")
+            .filter(|method| PAYMENT_METHODS.contains(&method.as_str()))
+            .unwrap_or_else(|| "Cash".to_string()),
+        home_state: text("home_state").or_else(|| state.clone()),
+        state,
+        district: text("default_district"),
+        town: text("default_town"),
+        credit_period_days: text("credit_period_days")
+            .and_then(|days| days.parse::<i64>().ok())
+            .filter(|days| *days >= 0)
+            .unwrap_or(30),
+        round_off: text("invoice_round_off")
+            .map(|mode| mode.to_lowercase())
+            .filter(|mode| ROUND_OFF_MODES.contains(&mode.as_str()))
+            .unwrap_or_else(|| "nearest".to_string()),
+    })
+}
+
+/// Whether sales may take stock below zero (the allow_negative_stock setting, off by default)
+pub(crate) fn allow_negative_stock(conn: &Connection) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM app_settings WHERE key = 'allow_negative_stock' AND value = 'true')",
+        [],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to read allow_negative_stock: {}", e))
+}
+
+/// The price_override_tolerance_percent setting, or None when it isn't set (no override checks)
+pub(crate) fn price_override_tolerance(conn: &Connection) -> Result<Option<f64>, String> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = 'price_override_tolerance_percent' AND TRIM(value) != ''",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read price_override_tolerance_percent: {}", e))?;
+    Ok(value
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|percent| percent.is_finite() && *percent >= 0.0))
+}
+
+/// Get the defaults create_invoice applies when its input leaves a field out
 #[tauri::command]
-pub fn import_settings_json(json_content: String, db: State<Database>) -> Result<usize, String> {
-    let settings: HashMap<String, String> = serde_json::from_str(&json_content)
-        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+pub fn get_billing_defaults(db: State<Database>) -> Result<BillingDefaults, String> {
+    log::info!("get_billing_defaults called");
 
     let conn = db.get_conn()?;
-    let mut count = 0;
+    billing_defaults(&conn)
+}
+
+/// Validate and save the billing defaults. The GST rate must be a GST slab and the state must
+/// be set; optional fields left empty clear their setting.
+#[tauri::command]
+pub fn set_billing_defaults(defaults: BillingDefaults, db: State<Database>) -> Result<BillingDefaults, String> {
+    log::info!("set_billing_defaults called");
+
+    let mut conn = db.get_conn()?;
+    set_billing_defaults_internal(&mut conn, defaults)
+}
+
+pub(crate) fn set_billing_defaults_internal(
+    conn: &mut Connection,
+    defaults: BillingDefaults,
+) -> Result<BillingDefaults, String> {
+    let check = |key: &str, value: String| -> Result<String, String> {
+        let kind = find_setting_spec(key).map(|spec| spec.kind).unwrap_or(SettingKind::Text);
+        validate_setting_value(kind, &serde_json::Value::String(value))
+            .map_err(|reason| format!("Validation error: {}: {}", key, reason))
+    };
+    let optional = |value: Option<String>| value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+    let state = optional(defaults.state).ok_or_else(|| "Validation error: state is required".to_string())?;
+    let values: Vec<(&str, Option<String>)> = vec![
+        ("default_gst_rate", defaults.gst_rate.map(|rate| check("default_gst_rate", rate.to_string())).transpose()?),
+        ("default_payment_method", Some(check("default_payment_method", defaults.payment_method.trim().to_string())?)),
+        ("default_state", Some(state)),
+        ("default_district", optional(defaults.district)),
+        ("default_town", optional(defaults.town)),
+        ("home_state", optional(defaults.home_state)),
+        ("credit_period_days", Some(check("credit_period_days", defaults.credit_period_days.to_string())?)),
+        ("invoice_round_off", Some(check("invoice_round_off", defaults.round_off.trim().to_lowercase())?)),
+    ];
 
-    // Use a transaction to ensure all or nothing
-    conn.execute_batch("BEGIN TRANSACTION;")
+    let tx = conn
+        .transaction()
         .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    for (key, value) in &values {
+        let saved = match value {
+            Some(value) => tx.execute(
+                "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))
+                 ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = datetime('now')",
+                [*key, value.as_str()],
+            ),
+            None => tx.execute("DELETE FROM app_settings WHERE key = ?1", [*key]),
+        };
+        saved.map_err(|e| format!("Failed to save setting '{}': {}", key, e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
-    for (key, value) in settings {
-        // We use set_app_setting logic inline or call it if we could, but let's just do the insert/update here
-        // to avoid borrowing issues if we reused the public fn which takes State
-        let result = conn.execute(
-            "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))
-             ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = datetime('now')",
-            [&key, &value],
-        );
+    billing_defaults(conn)
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SettingImportEntry {
+    pub key: String,
+    /// "will_import", "imported", "skipped_unknown", "not_importable" or "invalid_value"
+    pub status: String,
+    pub reason: Option<String>,
+    pub secret: bool,
+}
+
+#[derive(Debug, Default, serde::Serialize)]
+pub struct SettingsImportReport {
+    /// True when the settings were written (confirm=true)
+    pub applied: bool,
+    pub importable_count: usize,
+    pub skipped_count: usize,
+    pub invalid_count: usize,
+    pub entries: Vec<SettingImportEntry>,
+}
+
+/// Import settings from a JSON string.
+///
+/// Runs as a dry run unless `confirm` is true: every key is checked against the known
+/// settings registry and reported as will_import, skipped_unknown, not_importable or
+/// invalid_value. Unknown keys are only kept when `allow_unknown` is true. Nothing is
+/// written if any value is invalid. A "user_preferences" section is imported for the users
+/// that exist on this machine; preferences of other users are skipped.
+#[tauri::command]
+pub fn import_settings_json(
+    json_content: String,
+    confirm: Option<bool>,
+    allow_unknown: Option<bool>,
+    db: State<Database>,
+) -> Result<SettingsImportReport, String> {
+    let confirm = confirm.unwrap_or(false);
+    let allow_unknown = allow_unknown.unwrap_or(false);
+    log::info!(
+        "import_settings_json called (confirm: {}, allow_unknown: {})",
+        confirm,
+        allow_unknown
+    );
 
-        if let Err(e) = result {
-            let _ = conn.execute_batch("ROLLBACK;");
-            return Err(format!("Failed to save setting '{}': {}", key, e));
+    let settings: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&json_content)
+        .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+
+    let mut report = SettingsImportReport::default();
+    let mut to_write: Vec<(String, String)> = Vec::new();
+    let mut preferences: Vec<ImportedPreference> = Vec::new();
+
+    let mut keys: Vec<&String> = settings.keys().collect();
+    keys.sort();
+
+    for key in keys {
+        let value = &settings[key];
+        if key == USER_PREFERENCES_EXPORT_KEY {
+            let (status, reason) = match user_preferences::parse_exported_preferences(value) {
+                Ok(parsed) => {
+                    let reason = format!("{} per-user preference(s)", parsed.len());
+                    preferences = parsed;
+                    report.importable_count += 1;
+                    ("will_import", reason)
+                }
+                Err(reason) => {
+                    report.invalid_count += 1;
+                    ("invalid_value", reason)
+                }
+            };
+            report.entries.push(SettingImportEntry {
+                key: key.clone(),
+                status: status.to_string(),
+                reason: Some(reason),
+                secret: false,
+            });
+            continue;
         }
-        count += 1;
+
+        let (status, reason, is_secret) = match find_setting_spec(key) {
+            Some(spec) if !spec.importable => {
+                ("not_importable", Some("setting can't be changed by an import".to_string()), spec.secret)
+            }
+            Some(spec) => match validate_setting_value(spec.kind, value) {
+                Ok(stored) => {
+                    to_write.push((key.clone(), stored));
+                    let reason = if spec.secret {
+                        Some("contains credentials".to_string())
+                    } else {
+                        None
+                    };
+                    ("will_import", reason, spec.secret)
+                }
+                Err(reason) => ("invalid_value", Some(reason), spec.secret),
+            },
+            None if allow_unknown => match validate_setting_value(SettingKind::Text, value) {
+                Ok(stored) => {
+                    to_write.push((key.clone(), stored));
+                    ("will_import", Some("unknown key kept as-is".to_string()), false)
+                }
+                Err(reason) => ("invalid_value", Some(reason), false),
+            },
+            None => ("skipped_unknown", Some("not a known setting".to_string()), false),
+        };
+
+        match status {
+            "will_import" => report.importable_count += 1,
+            "invalid_value" => report.invalid_count += 1,
+            _ => report.skipped_count += 1,
+        }
+
+        report.entries.push(SettingImportEntry {
+            key: key.clone(),
+            status: status.to_string(),
+            reason,
+            secret: is_secret,
+        });
     }
 
-    conn.execute_batch("COMMIT;")
+    if !confirm {
+        return Ok(report);
+    }
+
+    if report.invalid_count > 0 {
+        return Err(format!(
+            "Settings file has {} invalid value(s); fix them before importing",
+            report.invalid_count
+        ));
+    }
+
+    let mut conn = db.get_conn()?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    for (key, value) in &to_write {
+        tx.execute(
+            "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, datetime('now'))
+             ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = datetime('now')",
+            [key, value],
+        )
+        .map_err(|e| format!("Failed to save setting '{}': {}", key, e))?;
+    }
+    let (imported_preferences, skipped_preferences) = user_preferences::import_user_preferences(&tx, &preferences)?;
+
+    tx.commit()
         .map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
-    Ok(count)
+    for entry in report.entries.iter_mut().filter(|e| e.status == "will_import") {
+        entry.status = "imported".to_string();
+    }
+    report.applied = true;
+
+    log::info!(
+        "Imported {} settings and {} user preference(s) ({} for unknown users skipped)",
+        to_write.len(),
+        imported_preferences,
+        skipped_preferences
+    );
+    Ok(report)
 }
 
 // Add the optional extension trait for rusqlite queries
@@ -0,0 +1,186 @@
+use crate::db::Database;
+use crate::services::money;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashMap;
+use tauri::State;
+
+/// app_settings key prefix for share message templates (e.g. "share_template_default")
+const SHARE_TEMPLATE_PREFIX: &str = "share_template_";
+
+/// Used when no template has been saved under the requested key
+const DEFAULT_SHARE_TEMPLATE: &str =
+    "Hi {name}, your invoice {invoice_number} for ₹{total} is ready. Balance due: ₹{balance}.";
+
+/// Country code prefixed to 10-digit local numbers for wa.me links
+const DEFAULT_COUNTRY_CODE: &str = "91";
+
+/// Get all saved share templates keyed by template name (prefix stripped)
+#[tauri::command]
+pub fn get_share_templates(db: State<Database>) -> Result<HashMap<String, String>, String> {
+    log::info!("get_share_templates called");
+
+    let conn = db.get_conn()?;
+
+    let mut stmt = conn
+        .prepare("SELECT key, value FROM app_settings WHERE key LIKE ?1")
+        .map_err(|e| e.to_string())?;
+
+    let template_iter = stmt
+        .query_map([format!("{}%", SHARE_TEMPLATE_PREFIX)], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut templates = HashMap::new();
+    for template in template_iter {
+        let (key, value) = template.map_err(|e| e.to_string())?;
+        templates.insert(key.trim_start_matches(SHARE_TEMPLATE_PREFIX).to_string(), value);
+    }
+
+    Ok(templates)
+}
+
+/// Render a share message for an invoice from a stored template.
+///
+/// Supported placeholders: {name}, {phone}, {invoice_number}, {date}, {total}, {paid},
+/// {balance}, {item_count}, {payment_method}. Walk-in invoices (no customer) fall back to
+/// "Customer" and empty strings; unknown placeholders are left as typed.
+#[tauri::command]
+pub fn render_share_message(
+    invoice_id: i32,
+    template_key: Option<String>,
+    db: State<Database>,
+) -> Result<String, String> {
+    log::info!(
+        "render_share_message called for invoice_id: {}, template_key: {:?}",
+        invoice_id,
+        template_key
+    );
+
+    let conn = db.get_conn()?;
+
+    let template = share_template(&conn, template_key.as_deref().unwrap_or("default"), DEFAULT_SHARE_TEMPLATE)?;
+
+    let (invoice_number, created_at, total_amount, payment_method, customer_name, customer_phone, item_count): (
+        String,
+        String,
+        f64,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        i32,
+    ) = conn
+        .query_row(
+            "SELECT i.invoice_number, i.created_at, i.total_amount, i.payment_method, c.name, c.phone,
+                    (SELECT COUNT(*) FROM invoice_items WHERE invoice_id = i.id)
+             FROM invoices i
+             LEFT JOIN customers c ON i.customer_id = c.id
+             WHERE i.id = ?1",
+            [invoice_id],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ))
+            },
+        )
+        .map_err(|e| format!("Invoice not found: {}", e))?;
+
+    // Credit invoices are settled through customer_payments (including the initial payment)
+    let is_credit = payment_method.as_deref() == Some("Credit");
+    let paid = if is_credit {
+        conn.query_row(
+            "SELECT COALESCE(SUM(amount), 0) FROM customer_payments WHERE invoice_id = ?1",
+            [invoice_id],
+            |row| row.get::<_, f64>(0),
+        )
+        .unwrap_or(0.0)
+    } else {
+        total_amount
+    };
+    let balance = money::sub(total_amount, paid).max(0.0);
+
+    let date = created_at.get(..10).unwrap_or(&created_at).to_string();
+
+    let values = [
+        ("{name}", customer_name.unwrap_or_else(|| "Customer".to_string())),
+        ("{phone}", customer_phone.unwrap_or_default()),
+        ("{invoice_number}", invoice_number),
+        ("{date}", date),
+        ("{total}", format!("{:.2}", total_amount)),
+        ("{paid}", format!("{:.2}", paid)),
+        ("{balance}", format!("{:.2}", balance)),
+        ("{item_count}", item_count.to_string()),
+        ("{payment_method}", payment_method.unwrap_or_default()),
+    ];
+
+    Ok(fill_placeholders(template, &values))
+}
+
+/// The share template saved under `template_key`, or `default` when none (or a blank one) is
+pub(crate) fn share_template(conn: &Connection, template_key: &str, default: &str) -> Result<String, String> {
+    let key = format!("{}{}", SHARE_TEMPLATE_PREFIX, template_key);
+    let template = conn
+        .query_row("SELECT value FROM app_settings WHERE key = ?1", [&key], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to load template: {}", e))?
+        .filter(|t: &String| !t.trim().is_empty())
+        .unwrap_or_else(|| default.to_string());
+    Ok(template)
+}
+
+/// Replace each placeholder in a template; unknown placeholders are left as typed
+pub(crate) fn fill_placeholders(template: String, values: &[(&str, String)]) -> String {
+    let mut message = template;
+    for (placeholder, value) in values.iter() {
+        message = message.replace(placeholder, value);
+    }
+    message
+}
+
+/// Open a WhatsApp chat with an optional pre-rendered message.
+/// Returns the wa.me URL that was opened.
+#[tauri::command]
+pub fn open_whatsapp_chat(phone: String, message: Option<String>) -> Result<String, String> {
+    log::info!("open_whatsapp_chat called for phone: {}", phone);
+
+    let mut digits: String = phone.chars().filter(|c| c.is_ascii_digit()).collect();
+    if digits.is_empty() {
+        return Err("Phone number is required".to_string());
+    }
+    if digits.len() == 10 {
+        digits = format!("{}{}", DEFAULT_COUNTRY_CODE, digits);
+    }
+
+    let url = match message.filter(|m| !m.is_empty()) {
+        Some(text) => format!("https://wa.me/{}?text={}", digits, urlencoding::encode(&text)),
+        None => format!("https://wa.me/{}", digits),
+    };
+
+    open_url(&url)?;
+    Ok(url)
+}
+
+/// Open a URL in the system browser. The URL is passed as a single argument and never through
+/// a shell: cmd would split the message text at `&` and treat `^` as an escape.
+fn open_url(url: &str) -> Result<(), String> {
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("open").arg(url).spawn();
+
+    #[cfg(target_os = "windows")]
+    let result = std::process::Command::new("rundll32")
+        .args(["url.dll,FileProtocolHandler", url])
+        .spawn();
+
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let result = std::process::Command::new("xdg-open").arg(url).spawn();
+
+    result
+        .map(|_| ())
+        .map_err(|e| format!("Failed to open URL: {}", e))
+}
@@ -0,0 +1,432 @@
+/// Database Snapshot Commands
+/// Export the whole database as one self-contained SQLite file (a checkpointed VACUUM INTO copy,
+/// so there is no -wal file to forget) and import such a file back, replacing the current data.
+/// The snapshot carries a snapshot_meta table with the schema version and a SHA-256 checksum of
+/// every table's rows, checked before anything is replaced.
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, State};
+
+use crate::commands::maintenance::{database_file_path, MaintenanceOperation, MaintenanceState, TableRowCount};
+use crate::commands::safety_snapshots::{create_safety_snapshot, log_safety_snapshot};
+use crate::db::{migrations, Database};
+use crate::services::events::{emit_bulk_data_changed, DataEntity, DataOperation};
+
+/// Bumped if the snapshot layout changes in a way older imports can't read
+const SNAPSHOT_FORMAT_VERSION: &str = "1";
+const SNAPSHOT_META_TABLE: &str = "snapshot_meta";
+
+/// Tables never copied by an import: the target keeps its own schema version, and the sales
+/// summary is a cache rebuilt on the next read
+const IMPORT_SKIPPED_TABLES: &[&str] = &["schema_version", "daily_sales_summary"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatabaseSnapshot {
+    pub file_path: String,
+    pub checksum: String,
+    pub schema_version: i32,
+    pub created_at: String,
+    pub size_bytes: u64,
+    pub tables: Vec<TableRowCount>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotImportResult {
+    pub checksum: String,
+    /// Schema version the snapshot was taken at; older snapshots are migrated on import
+    pub snapshot_schema_version: i32,
+    pub snapshot_created_at: String,
+    /// Rows now in each table
+    pub tables: Vec<TableRowCount>,
+    pub total_rows: i64,
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// User tables of `schema` ("main" or an attached name), sorted by name
+fn table_names(conn: &Connection, schema: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT name FROM {}.sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            quote_ident(schema)
+        ))
+        .map_err(|e| e.to_string())?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>();
+    names.map_err(|e| format!("Failed to list tables: {}", e))
+}
+
+/// SHA-256 over every row of every table except snapshot_meta, in table name and rowid order,
+/// plus the row count of each table
+fn snapshot_checksum(conn: &Connection) -> Result<(String, Vec<TableRowCount>), String> {
+    let mut hasher = Sha256::new();
+    let mut tables = Vec::new();
+
+    for table in table_names(conn, "main")? {
+        if table == SNAPSHOT_META_TABLE {
+            continue;
+        }
+        hasher.update(table.as_bytes());
+        hasher.update([0u8]);
+
+        let mut stmt = conn
+            .prepare(&format!("SELECT * FROM {} ORDER BY rowid", quote_ident(&table)))
+            .map_err(|e| format!("Failed to read {}: {}", table, e))?;
+        let columns = stmt.column_count();
+        let mut rows = stmt.query([]).map_err(|e| format!("Failed to read {}: {}", table, e))?;
+        let mut count = 0i64;
+        while let Some(row) = rows.next().map_err(|e| format!("Failed to read {}: {}", table, e))? {
+            for index in 0..columns {
+                match row.get_ref(index).map_err(|e| e.to_string())? {
+                    ValueRef::Null => hasher.update([0u8]),
+                    ValueRef::Integer(value) => {
+                        hasher.update([1u8]);
+                        hasher.update(value.to_le_bytes());
+                    }
+                    ValueRef::Real(value) => {
+                        hasher.update([2u8]);
+                        hasher.update(value.to_bits().to_le_bytes());
+                    }
+                    ValueRef::Text(bytes) | ValueRef::Blob(bytes) => {
+                        hasher.update([3u8]);
+                        hasher.update((bytes.len() as u64).to_le_bytes());
+                        hasher.update(bytes);
+                    }
+                }
+            }
+            count += 1;
+        }
+        tables.push(TableRowCount { table, rows: count });
+    }
+
+    Ok((hex::encode(hasher.finalize()), tables))
+}
+
+fn meta_value(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        &format!("SELECT value FROM {} WHERE key = ?1", SNAPSHOT_META_TABLE),
+        [key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to read snapshot metadata: {}", e))
+}
+
+/// Write a snapshot of the database to `file_path`, replacing any file already there
+#[tauri::command]
+pub fn export_database_snapshot(file_path: String, db: State<Database>) -> Result<DatabaseSnapshot, String> {
+    log::info!("export_database_snapshot called -> {}", file_path);
+
+    let conn = db.get_conn()?;
+    export_database_snapshot_internal(&conn, Path::new(&file_path))
+}
+
+pub(crate) fn export_database_snapshot_internal(conn: &Connection, path: &Path) -> Result<DatabaseSnapshot, String> {
+    // VACUUM INTO refuses to overwrite, and a half-written file must never sit at the target
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let _ = std::fs::remove_file(&partial);
+
+    let result = (|| -> Result<DatabaseSnapshot, String> {
+        conn.execute("VACUUM INTO ?1", [partial.to_string_lossy().to_string()])
+            .map_err(|e| format!("Failed to copy database: {}", e))?;
+
+        let copy = Connection::open(&partial).map_err(|e| format!("Failed to open snapshot: {}", e))?;
+        let schema_version = migrations::current_version(&copy)
+            .map_err(|e| format!("Failed to read schema version: {}", e))?;
+        let (checksum, tables) = snapshot_checksum(&copy)?;
+        let created_at = Utc::now().to_rfc3339();
+
+        copy.execute_batch(&format!(
+            "CREATE TABLE {} (key TEXT PRIMARY KEY, value TEXT NOT NULL)",
+            SNAPSHOT_META_TABLE
+        ))
+        .map_err(|e| format!("Failed to write snapshot metadata: {}", e))?;
+        let meta = [
+            ("format_version", SNAPSHOT_FORMAT_VERSION.to_string()),
+            ("schema_version", schema_version.to_string()),
+            ("app_version", env!("CARGO_PKG_VERSION").to_string()),
+            ("created_at", created_at.clone()),
+            ("checksum", checksum.clone()),
+        ];
+        for (key, value) in &meta {
+            copy.execute(
+                &format!("INSERT INTO {} (key, value) VALUES (?1, ?2)", SNAPSHOT_META_TABLE),
+                [*key, value.as_str()],
+            )
+            .map_err(|e| format!("Failed to write snapshot metadata: {}", e))?;
+        }
+        drop(copy);
+
+        std::fs::rename(&partial, path).map_err(|e| format!("Failed to save snapshot: {}", e))?;
+        let size_bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+        Ok(DatabaseSnapshot {
+            file_path: path.to_string_lossy().to_string(),
+            checksum,
+            schema_version,
+            created_at,
+            size_bytes,
+            tables,
+        })
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial);
+    }
+    if let Ok(snapshot) = &result {
+        log::info!("Exported database snapshot ({} bytes, checksum {})", snapshot.size_bytes, snapshot.checksum);
+    }
+    result
+}
+
+/// Replace all data with the contents of a snapshot file.
+///
+/// The snapshot's checksum and schema version are verified first; snapshots from an older
+/// version are migrated before their rows are copied. Holds the maintenance lock throughout,
+/// so no other command writes while the data is swapped.
+#[tauri::command]
+pub async fn import_database_snapshot(
+    file_path: String,
+    app: AppHandle,
+    maintenance: State<'_, MaintenanceState>,
+    db: State<'_, Database>,
+) -> Result<SnapshotImportResult, String> {
+    log::info!("import_database_snapshot called <- {}", file_path);
+
+    let _lock = maintenance.acquire(MaintenanceOperation::RestoreBackup)?;
+    let mut conn = db.get_conn()?;
+    let result = import_database_snapshot_internal(&mut conn, Path::new(&file_path), "import_database_snapshot")?;
+
+    emit_snapshot_imported(&app, &result);
+    Ok(result)
+}
+
+/// Announce the tables a snapshot import replaced
+pub(crate) fn emit_snapshot_imported(app: &AppHandle, result: &SnapshotImportResult) {
+    let entities = [
+        ("invoices", DataEntity::Invoice),
+        ("products", DataEntity::Product),
+        ("customers", DataEntity::Customer),
+        ("suppliers", DataEntity::Supplier),
+        ("purchase_orders", DataEntity::PurchaseOrder),
+    ];
+    for (table, entity) in entities {
+        if let Some(count) = result.tables.iter().find(|t| t.table == table) {
+            emit_bulk_data_changed(app, entity, DataOperation::Imported, count.rows as usize);
+        }
+    }
+}
+
+/// `operation` names the command in the safety snapshot taken of the current data before
+/// it is replaced.
+pub(crate) fn import_database_snapshot_internal(
+    conn: &mut Connection,
+    path: &Path,
+    operation: &str,
+) -> Result<SnapshotImportResult, String> {
+    // Verify the snapshot as-is, read-only
+    let (checksum, snapshot_schema_version, snapshot_created_at) = {
+        let snapshot = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| format!("Failed to open snapshot: {}", e))?;
+        let has_meta = table_names(&snapshot, "main")
+            .map_err(|_| "Not a database snapshot file".to_string())?
+            .iter()
+            .any(|t| t == SNAPSHOT_META_TABLE);
+        if !has_meta {
+            return Err("Not a database snapshot file (no snapshot metadata)".to_string());
+        }
+        if meta_value(&snapshot, "format_version")?.as_deref() != Some(SNAPSHOT_FORMAT_VERSION) {
+            return Err("Snapshot format is not supported by this version of the app".to_string());
+        }
+
+        let expected = meta_value(&snapshot, "checksum")?.unwrap_or_default();
+        let (actual, _) = snapshot_checksum(&snapshot)?;
+        if actual != expected {
+            return Err("Snapshot checksum does not match; the file is damaged or was modified".to_string());
+        }
+
+        let version: i32 = meta_value(&snapshot, "schema_version")?
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| "Snapshot has no schema version".to_string())?;
+        if version > migrations::latest_version() {
+            return Err(format!(
+                "Snapshot was created by a newer version of the app (schema {}, this app supports {})",
+                version,
+                migrations::latest_version()
+            ));
+        }
+        (actual, version, meta_value(&snapshot, "created_at")?.unwrap_or_default())
+    };
+
+    // Bring a working copy up to the current schema so its columns line up with ours
+    let mut working = database_file_path(conn)?.as_os_str().to_owned();
+    working.push(".snapshot-import");
+    let working = PathBuf::from(working);
+    std::fs::copy(path, &working).map_err(|e| format!("Failed to copy snapshot: {}", e))?;
+
+    let result = (|| -> Result<Vec<TableRowCount>, String> {
+        // Taken after the copy, so pruning old safety snapshots can't remove the one being restored
+        let safety = create_safety_snapshot(conn, operation)?;
+
+        {
+            let mut copy = Connection::open(&working).map_err(|e| format!("Failed to open snapshot: {}", e))?;
+            migrations::run_migrations(&mut copy).map_err(|e| format!("Failed to upgrade snapshot: {}", e))?;
+        }
+
+        // foreign_keys can only change outside a transaction; rows are copied parents and
+        // children alike, so checks are off until the swap is done
+        conn.execute_batch("PRAGMA foreign_keys = OFF")
+            .map_err(|e| e.to_string())?;
+        conn.execute("ATTACH DATABASE ?1 AS snapshot", [working.to_string_lossy().to_string()])
+            .map_err(|e| format!("Failed to attach snapshot: {}", e))?;
+
+        let copied = replace_data(conn);
+
+        let _ = conn.execute_batch("DETACH DATABASE snapshot");
+        let _ = conn.execute_batch("PRAGMA foreign_keys = ON");
+
+        // The activity log was replaced with the snapshot's, so the safety snapshot is logged now
+        let logged = log_safety_snapshot(conn, &safety);
+        let copied = copied?;
+        logged?;
+        Ok(copied)
+    })();
+
+    let _ = std::fs::remove_file(&working);
+    let mut wal = working.into_os_string();
+    wal.push("-wal");
+    let _ = std::fs::remove_file(&wal);
+
+    let tables = result?;
+    let total_rows = tables.iter().map(|t| t.rows).sum();
+    log::info!("Imported database snapshot: {} rows across {} tables", total_rows, tables.len());
+
+    Ok(SnapshotImportResult {
+        checksum,
+        snapshot_schema_version,
+        snapshot_created_at,
+        tables,
+        total_rows,
+    })
+}
+
+/// Replace every table in main with the rows of the attached snapshot, copying the columns
+/// both sides have. Tables the snapshot lacks are left empty.
+fn replace_data(conn: &mut Connection) -> Result<Vec<TableRowCount>, String> {
+    let snapshot_tables = table_names(conn, "snapshot")?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let column_names = |schema: &str, table: &str| -> Result<Vec<String>, String> {
+        let mut stmt = tx
+            .prepare("SELECT name FROM pragma_table_info(?1, ?2)")
+            .map_err(|e| e.to_string())?;
+        let names = stmt
+            .query_map([table, schema], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>();
+        names.map_err(|e| format!("Failed to read columns of {}: {}", table, e))
+    };
+
+    let mut tables = Vec::new();
+    for table in table_names(&tx, "main")? {
+        if IMPORT_SKIPPED_TABLES.contains(&table.as_str()) {
+            continue;
+        }
+        tx.execute(&format!("DELETE FROM main.{}", quote_ident(&table)), [])
+            .map_err(|e| format!("Failed to clear {}: {}", table, e))?;
+
+        if snapshot_tables.contains(&table) {
+            let theirs = column_names("snapshot", &table)?;
+            let shared: Vec<String> = column_names("main", &table)?
+                .into_iter()
+                .filter(|column| theirs.contains(column))
+                .map(|column| quote_ident(&column))
+                .collect();
+            if !shared.is_empty() {
+                let columns = shared.join(", ");
+                tx.execute(
+                    &format!(
+                        "INSERT INTO main.{table} ({columns}) SELECT {columns} FROM snapshot.{table}",
+                        table = quote_ident(&table),
+                        columns = columns
+                    ),
+                    [],
+                )
+                .map_err(|e| format!("Failed to copy {}: {}", table, e))?;
+            }
+        }
+
+        let rows: i64 = tx
+            .query_row(&format!("SELECT COUNT(*) FROM main.{}", quote_ident(&table)), [], |row| row.get(0))
+            .map_err(|e| e.to_string())?;
+        tables.push(TableRowCount { table, rows });
+    }
+
+    // Copied invoice rows fired the summary triggers already; clear whatever is left
+    tx.execute("DELETE FROM main.daily_sales_summary", [])
+        .map_err(|e| format!("Failed to clear sales summary: {}", e))?;
+
+    tx.commit()
+        .map_err(|e| format!("Failed to commit snapshot import: {}", e))?;
+    Ok(tables)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{insert_customer, insert_supplier, TestDb};
+
+    #[test]
+    fn test_snapshot_round_trip_replaces_data() {
+        let source = TestDb::new();
+        let conn = source.conn();
+        insert_supplier(&conn, "Acme");
+        insert_customer(&conn, "Asha");
+        insert_customer(&conn, "Ravi");
+
+        let dir = database_file_path(&conn).unwrap().parent().unwrap().to_path_buf();
+        let path = dir.join("backup.invsnap");
+        let snapshot = export_database_snapshot_internal(&conn, &path).unwrap();
+        assert_eq!(snapshot.schema_version, migrations::latest_version());
+        assert_eq!(snapshot.tables.iter().find(|t| t.table == "customers").unwrap().rows, 2);
+
+        let target = TestDb::new();
+        let mut target_conn = target.conn();
+        insert_customer(&target_conn, "Someone else");
+        let result = import_database_snapshot_internal(&mut target_conn, &path, "import_database_snapshot").unwrap();
+        assert_eq!(result.checksum, snapshot.checksum);
+
+        let names: Vec<String> = target_conn
+            .prepare("SELECT name FROM customers ORDER BY name")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(names, vec!["Asha", "Ravi"]);
+        let foreign_keys: i32 = target_conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+        assert_eq!(foreign_keys, 1);
+
+        // A modified snapshot is refused and the current data is kept
+        {
+            let tampered = Connection::open(&path).unwrap();
+            tampered.execute("UPDATE customers SET name = 'Mallory'", []).unwrap();
+        }
+        assert!(import_database_snapshot_internal(&mut target_conn, &path, "import_database_snapshot").is_err());
+        let count: i32 = target_conn.query_row("SELECT COUNT(*) FROM customers", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+    }
+}
@@ -0,0 +1,418 @@
+/// Stock Alerts
+/// A product is low on stock once it drops below its reorder level (products.reorder_level, or
+/// the low_stock_threshold setting, 10 by default, for products without one). With
+/// low_stock_counts_on_order set to "true", units still to arrive on open purchase orders count
+/// towards the level, so stock already reordered doesn't alert again.
+///
+/// After an invoice is created, each product it sold that is now below its level raises a
+/// "stock-alert" event for the frontend to toast, unless it already alerted within
+/// stock_alert_cooldown_hours (24 by default). The daily digest keeps one summary of low and
+/// out-of-stock products per business day; it is generated on startup when today's is missing,
+/// and can be rendered through the share templates (key "stock_digest") to send on.
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::commands::analytics::business_today;
+use crate::commands::purchase_orders::ON_ORDER_QUANTITY_SQL;
+use crate::commands::share::{fill_placeholders, share_template};
+use crate::db::Database;
+use crate::services::quantity;
+
+pub const STOCK_ALERT_EVENT: &str = "stock-alert";
+
+const LOW_STOCK_THRESHOLD_KEY: &str = "low_stock_threshold";
+const DEFAULT_LOW_STOCK_THRESHOLD: i32 = 10;
+const COOLDOWN_HOURS_KEY: &str = "stock_alert_cooldown_hours";
+const DEFAULT_COOLDOWN_HOURS: i32 = 24;
+const COUNT_ON_ORDER_KEY: &str = "low_stock_counts_on_order";
+
+/// Share template key for the digest message (app_settings "share_template_stock_digest")
+const DIGEST_TEMPLATE_KEY: &str = "stock_digest";
+const DEFAULT_DIGEST_TEMPLATE: &str =
+    "Stock digest for {date}: {low_stock_count} products low, {out_of_stock_count} out of stock.\n{products}";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LowStockItem {
+    pub product_id: i32,
+    pub name: String,
+    pub sku: String,
+    pub stock_quantity: f64,
+    pub reorder_level: i32,
+    /// Units on open purchase orders still to arrive
+    #[serde(default)]
+    pub on_order_quantity: f64,
+}
+
+/// Payload of the "stock-alert" event
+#[derive(Debug, Serialize)]
+pub struct StockAlert {
+    pub invoice_id: i32,
+    pub products: Vec<LowStockItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StockDigest {
+    pub id: i64,
+    /// Business day, "YYYY-MM-DD"
+    pub digest_date: String,
+    pub low_stock_count: i32,
+    pub out_of_stock_count: i32,
+    /// Products below their reorder level, emptiest first
+    pub products: Vec<LowStockItem>,
+    pub created_at: String,
+}
+
+fn integer_setting(conn: &Connection, key: &str, default: i32) -> i32 {
+    conn.query_row("SELECT value FROM app_settings WHERE key = ?1", [key], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+/// Whether stock on order counts towards reorder levels (low_stock_counts_on_order, off by default)
+pub(crate) fn counts_on_order(conn: &Connection) -> bool {
+    conn.query_row("SELECT value FROM app_settings WHERE key = ?1", [COUNT_ON_ORDER_KEY], |row| row.get::<_, String>(0))
+        .is_ok_and(|v| v == "true")
+}
+
+/// Products below their reorder level, emptiest first; `only` narrows it to the given ids
+fn low_stock_items(conn: &Connection, only: Option<&[i32]>) -> Result<Vec<LowStockItem>, String> {
+    let threshold = integer_setting(conn, LOW_STOCK_THRESHOLD_KEY, DEFAULT_LOW_STOCK_THRESHOLD);
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT id, name, sku, stock_quantity, level, on_order
+             FROM (SELECT p.id, p.name, p.sku, p.stock_quantity, COALESCE(p.reorder_level, ?1) AS level,
+                          {} AS on_order
+                   FROM products p)
+             WHERE stock_quantity + CASE WHEN ?2 THEN on_order ELSE 0 END < level
+             ORDER BY stock_quantity, name",
+            ON_ORDER_QUANTITY_SQL
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let items = stmt
+        .query_map(params![threshold, counts_on_order(conn)], |row| {
+            Ok(LowStockItem {
+                product_id: row.get(0)?,
+                name: row.get(1)?,
+                sku: row.get(2)?,
+                stock_quantity: row.get(3)?,
+                reorder_level: row.get(4)?,
+                on_order_quantity: quantity::round_quantity(row.get(5)?),
+            })
+        })
+        .map_err(|e| format!("Failed to query low stock: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect low stock: {}", e))?;
+
+    Ok(match only {
+        Some(ids) => items.into_iter().filter(|item| ids.contains(&item.product_id)).collect(),
+        None => items,
+    })
+}
+
+// ===== ALERTS =====
+
+/// Emit a stock-alert for an invoice's products that are now low, if any
+pub(crate) fn notify_stock_alerts(app: &AppHandle, conn: &Connection, invoice_id: i32) {
+    match check_invoice_stock_alerts(conn, invoice_id) {
+        Ok(Some(alert)) => {
+            if let Err(e) = app.emit(STOCK_ALERT_EVENT, &alert) {
+                log::warn!("Failed to emit {}: {}", STOCK_ALERT_EVENT, e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to check stock alerts for invoice {}: {}", invoice_id, e),
+    }
+}
+
+/// The products an invoice sold that are below their reorder level and haven't alerted within
+/// the cooldown; records them as alerted
+pub(crate) fn check_invoice_stock_alerts(conn: &Connection, invoice_id: i32) -> Result<Option<StockAlert>, String> {
+    let sold: Vec<i32> = {
+        let mut stmt = conn
+            .prepare("SELECT DISTINCT product_id FROM invoice_items WHERE invoice_id = ?1")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt
+            .query_map([invoice_id], |row| row.get(0))
+            .map_err(|e| format!("Failed to query invoice items: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect invoice items: {}", e))?
+    };
+    if sold.is_empty() {
+        return Ok(None);
+    }
+
+    let cooldown = format!("-{} hours", integer_setting(conn, COOLDOWN_HOURS_KEY, DEFAULT_COOLDOWN_HOURS).max(0));
+    let mut products = Vec::new();
+    for item in low_stock_items(conn, Some(&sold))? {
+        let recently_alerted: bool = conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM stock_alert_log WHERE product_id = ?1 AND alerted_at > datetime('now', ?2)",
+                params![item.product_id, cooldown],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to check stock alert log: {}", e))?;
+        if recently_alerted {
+            continue;
+        }
+        conn.execute(
+            "INSERT INTO stock_alert_log (product_id, stock_quantity, alerted_at) VALUES (?1, ?2, datetime('now'))
+             ON CONFLICT(product_id) DO UPDATE SET stock_quantity = ?2, alerted_at = datetime('now')",
+            params![item.product_id, item.stock_quantity],
+        )
+        .map_err(|e| format!("Failed to record stock alert: {}", e))?;
+        products.push(item);
+    }
+
+    Ok((!products.is_empty()).then_some(StockAlert { invoice_id, products }))
+}
+
+/// Set a product's reorder level; None falls back to the low_stock_threshold setting
+#[tauri::command]
+pub fn set_product_reorder_level(product_id: i32, reorder_level: Option<i32>, db: State<Database>) -> Result<(), String> {
+    log::info!("set_product_reorder_level called for product {}: {:?}", product_id, reorder_level);
+
+    if reorder_level.is_some_and(|level| level < 0) {
+        return Err("Validation error: reorder level can't be negative".to_string());
+    }
+    let conn = db.get_conn()?;
+    let updated = conn
+        .execute(
+            "UPDATE products SET reorder_level = ?1, updated_at = datetime('now') WHERE id = ?2",
+            params![reorder_level, product_id],
+        )
+        .map_err(|e| format!("Failed to update reorder level: {}", e))?;
+    if updated == 0 {
+        return Err(format!("Product with id {} not found", product_id));
+    }
+    Ok(())
+}
+
+// ===== DAILY DIGEST =====
+
+fn load_digest(conn: &Connection, filter: &str, param: impl rusqlite::ToSql) -> Result<Option<StockDigest>, String> {
+    let sql = format!(
+        "SELECT id, digest_date, low_stock_count, out_of_stock_count, products, created_at FROM stock_digests WHERE {}",
+        filter
+    );
+    let row: Option<(i64, String, i32, i32, String, String)> = conn
+        .query_row(&sql, [param], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?))
+        })
+        .optional()
+        .map_err(|e| format!("Failed to load stock digest: {}", e))?;
+
+    row.map(|(id, digest_date, low_stock_count, out_of_stock_count, products, created_at)| {
+        Ok(StockDigest {
+            id,
+            digest_date,
+            low_stock_count,
+            out_of_stock_count,
+            products: serde_json::from_str(&products).map_err(|e| format!("Failed to read stock digest: {}", e))?,
+            created_at,
+        })
+    })
+    .transpose()
+}
+
+/// Generate today's stock digest, replacing one already made today
+#[tauri::command]
+pub fn generate_daily_stock_digest(db: State<Database>) -> Result<StockDigest, String> {
+    log::info!("generate_daily_stock_digest called");
+
+    let conn = db.get_conn()?;
+    let today = business_today(&conn);
+    generate_daily_stock_digest_internal(&conn, today, true)?
+        .ok_or_else(|| "Failed to generate stock digest".to_string())
+}
+
+/// Store the digest for `date`. With `replace` false an existing digest for the day is kept and
+/// None returned, which makes the startup run a no-op after the first one of the day.
+pub fn generate_daily_stock_digest_internal(
+    conn: &Connection,
+    date: NaiveDate,
+    replace: bool,
+) -> Result<Option<StockDigest>, String> {
+    let date = date.format("%Y-%m-%d").to_string();
+    if !replace && load_digest(conn, "digest_date = ?1", &date)?.is_some() {
+        return Ok(None);
+    }
+
+    let products = low_stock_items(conn, None)?;
+    let out_of_stock_count = products.iter().filter(|item| item.stock_quantity <= 0.0).count() as i32;
+    let products_json = serde_json::to_string(&products).map_err(|e| format!("Failed to serialize stock digest: {}", e))?;
+    conn.execute(
+        "INSERT INTO stock_digests (digest_date, low_stock_count, out_of_stock_count, products) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(digest_date) DO UPDATE SET low_stock_count = ?2, out_of_stock_count = ?3, products = ?4,
+                                                created_at = datetime('now')",
+        params![date, products.len() as i32, out_of_stock_count, products_json],
+    )
+    .map_err(|e| format!("Failed to save stock digest: {}", e))?;
+
+    load_digest(conn, "digest_date = ?1", &date)
+}
+
+/// Get the latest stock digests, newest first
+#[tauri::command]
+pub fn get_stock_digests(limit: Option<i32>, db: State<Database>) -> Result<Vec<StockDigest>, String> {
+    log::info!("get_stock_digests called");
+
+    let conn = db.get_conn()?;
+    let ids: Vec<i64> = {
+        let mut stmt = conn
+            .prepare("SELECT id FROM stock_digests ORDER BY digest_date DESC LIMIT ?1")
+            .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+        let rows = stmt
+            .query_map([limit.unwrap_or(30).clamp(1, 365)], |row| row.get(0))
+            .map_err(|e| format!("Failed to query stock digests: {}", e))?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect stock digests: {}", e))?
+    };
+
+    let mut digests = Vec::with_capacity(ids.len());
+    for id in ids {
+        digests.extend(load_digest(&conn, "id = ?1", id)?);
+    }
+    Ok(digests)
+}
+
+/// Render a digest as a message for WhatsApp or email.
+///
+/// Placeholders: {date}, {low_stock_count}, {out_of_stock_count} and {products} (one
+/// "name (SKU): stock/level" line per product).
+#[tauri::command]
+pub fn render_stock_digest_message(digest_id: i64, template_key: Option<String>, db: State<Database>) -> Result<String, String> {
+    log::info!("render_stock_digest_message called for digest {}", digest_id);
+
+    let conn = db.get_conn()?;
+    render_stock_digest_message_internal(&conn, digest_id, template_key.as_deref())
+}
+
+pub(crate) fn render_stock_digest_message_internal(
+    conn: &Connection,
+    digest_id: i64,
+    template_key: Option<&str>,
+) -> Result<String, String> {
+    let digest = load_digest(conn, "id = ?1", digest_id)?.ok_or_else(|| format!("Stock digest {} not found", digest_id))?;
+    let template = share_template(conn, template_key.unwrap_or(DIGEST_TEMPLATE_KEY), DEFAULT_DIGEST_TEMPLATE)?;
+
+    let products = digest
+        .products
+        .iter()
+        .map(|item| format!("- {} ({}): {}/{}", item.name, item.sku, quantity::format_quantity(item.stock_quantity), item.reorder_level))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let values = [
+        ("{date}", digest.digest_date),
+        ("{low_stock_count}", digest.low_stock_count.to_string()),
+        ("{out_of_stock_count}", digest.out_of_stock_count.to_string()),
+        ("{products}", products),
+    ];
+    Ok(fill_placeholders(template, &values))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    fn insert_product(conn: &Connection, sku: &str, stock: i32, reorder_level: Option<i32>) -> i32 {
+        conn.execute(
+            "INSERT INTO products (name, sku, price, stock_quantity, reorder_level) VALUES (?1, ?1, 10, ?2, ?3)",
+            params![sku, stock, reorder_level],
+        )
+        .unwrap();
+        conn.last_insert_rowid() as i32
+    }
+
+    fn sell(conn: &Connection, invoice_id: i32, product_ids: &[i32]) {
+        conn.execute(
+            "INSERT INTO invoices (id, invoice_number, total_amount) VALUES (?1, ?2, 0)",
+            params![invoice_id, format!("INV-{}", invoice_id)],
+        )
+        .unwrap();
+        for product_id in product_ids {
+            conn.execute(
+                "INSERT INTO invoice_items (invoice_id, product_id, quantity, unit_price) VALUES (?1, ?2, 1, 10)",
+                params![invoice_id, product_id],
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_alerts_respect_reorder_levels_and_cooldown() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let low = insert_product(&conn, "LOW", 4, None);
+        let plenty = insert_product(&conn, "PLENTY", 40, None);
+        let own_level = insert_product(&conn, "OWN", 40, Some(50));
+
+        sell(&conn, 1, &[low, plenty, own_level]);
+        let alert = check_invoice_stock_alerts(&conn, 1).unwrap().unwrap();
+        let alerted: Vec<i32> = alert.products.iter().map(|item| item.product_id).collect();
+        assert_eq!(alerted, vec![low, own_level]);
+
+        // Within the cooldown the same products stay quiet
+        sell(&conn, 2, &[low]);
+        assert!(check_invoice_stock_alerts(&conn, 2).unwrap().is_none());
+
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('stock_alert_cooldown_hours', '0')", []).unwrap();
+        conn.execute("UPDATE stock_alert_log SET alerted_at = datetime('now', '-1 minute')", []).unwrap();
+        sell(&conn, 3, &[low]);
+        assert_eq!(check_invoice_stock_alerts(&conn, 3).unwrap().unwrap().products.len(), 1);
+    }
+
+    #[test]
+    fn test_daily_digest_is_made_once_and_rendered() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        insert_product(&conn, "EMPTY", 0, None);
+        insert_product(&conn, "LOW", 3, None);
+        insert_product(&conn, "FINE", 30, None);
+        let today = NaiveDate::from_ymd_opt(2025, 6, 1).unwrap();
+
+        let digest = generate_daily_stock_digest_internal(&conn, today, false).unwrap().unwrap();
+        assert_eq!((digest.low_stock_count, digest.out_of_stock_count), (2, 1));
+        assert!(generate_daily_stock_digest_internal(&conn, today, false).unwrap().is_none());
+
+        let message = render_stock_digest_message_internal(&conn, digest.id, None).unwrap();
+        assert!(message.starts_with("Stock digest for 2025-06-01: 2 products low, 1 out of stock."));
+        assert!(message.contains("- LOW (LOW): 3/10"));
+    }
+
+    #[test]
+    fn test_stock_on_order_counts_towards_the_level_when_enabled() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let low = insert_product(&conn, "LOW", 4, None);
+        let supplier_id = crate::test_support::insert_supplier(&conn, "Acme Traders");
+        conn.execute(
+            "INSERT INTO purchase_orders (id, po_number, supplier_id, order_date, expected_delivery_date, status, total_amount)
+             VALUES (1, 'PO-1', ?1, '2025-06-01', '2025-06-06', 'partially_received', 200)",
+            [supplier_id],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO purchase_order_items (po_id, product_id, quantity, received_quantity, unit_cost, total_cost)
+             VALUES (1, ?1, 20, 8, 10, 200)",
+            [low],
+        )
+        .unwrap();
+
+        let incoming = crate::commands::purchase_orders::get_incoming_stock_internal(&conn, low).unwrap();
+        assert_eq!(incoming.len(), 1);
+        assert_eq!((incoming[0].remaining_quantity, incoming[0].expected_delivery_date.as_deref()), (12.0, Some("2025-06-06")));
+        let product = crate::commands::products::get_product_internal(&conn, low).unwrap();
+        assert_eq!(product.on_order_quantity, Some(12.0));
+
+        // Raw alerts by default; with the setting on, 4 in stock + 12 incoming clears the level
+        let items = low_stock_items(&conn, None).unwrap();
+        assert_eq!((items.len(), items[0].on_order_quantity), (1, 12.0));
+        conn.execute("INSERT INTO app_settings (key, value) VALUES ('low_stock_counts_on_order', 'true')", []).unwrap();
+        assert!(low_stock_items(&conn, None).unwrap().is_empty());
+        assert!(crate::commands::analytics::get_low_stock_alerts_internal(&conn).unwrap().is_empty());
+    }
+}
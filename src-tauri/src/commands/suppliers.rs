@@ -1,9 +1,15 @@
 use crate::db::{Database, Supplier, SupplierPayment};
-use crate::commands::PaginatedResult;
-use chrono::Utc;
+use crate::commands::{validate_pagination, PaginatedResult};
+use crate::commands::images::remove_image_files;
+use crate::commands::products::get_products_by_supplier_internal;
+use crate::commands::purchase_orders::PO_TOTAL_PAID_SQL;
+use crate::commands::purchase_returns::product_return_totals;
+use crate::services::events::{emit_data_changed, DataEntity, DataOperation};
+use crate::services::{money, payment_shares, quantity};
+use chrono::{NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use tauri::State;
-use rusqlite::OptionalExtension;
+use tauri::{AppHandle, State};
+use rusqlite::{Connection, OptionalExtension};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateSupplierInput {
@@ -28,6 +34,9 @@ pub struct UpdateSupplierInput {
     pub state: Option<String>,
     pub district: Option<String>,
     pub town: Option<String>,
+    /// Pre-app debt owed to the supplier. None leaves the current value unchanged.
+    #[serde(default)]
+    pub opening_balance: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -39,13 +48,163 @@ pub struct CreateSupplierPaymentInput {
     pub note: Option<String>,
     /// Optional explicit paid_at timestamp (RFC3339). If None, current time is used.
     pub paid_at: Option<String>,
+    /// Optional split over PO lines; must add up to the amount
+    #[serde(default)]
+    pub allocations: Option<Vec<PaymentAllocationInput>>,
+}
+
+/// Part of a supplier payment set against one PO line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentAllocationInput {
+    pub po_item_id: i32,
+    pub amount: f64,
+}
+
+/// Per PO line value (less returns set off against it) and amount paid, as po_item_balances
+/// (po_item_id, po_id, supplier_id, product_id, order_date, value, paid). Paid is the line's
+/// explicit allocations, plus payment rows for its product on the PO, plus a value-proportional
+/// share of unallocated PO-level payments.
+pub(crate) const PO_ITEM_BALANCES_CTE: &str = "po_item_balances AS (
+    SELECT poi.id AS po_item_id, poi.po_id, po.supplier_id, poi.product_id, po.order_date,
+           poi.total_cost * po.exchange_rate
+             - COALESCE((SELECT SUM(pri.total_cost)
+                         FROM purchase_return_items pri
+                         JOIN purchase_returns pr ON pr.id = pri.return_id
+                         WHERE pri.po_item_id = poi.id AND pr.reduces_payable = 1), 0) AS value,
+           COALESCE((SELECT SUM(a.amount) FROM supplier_payment_allocations a WHERE a.po_item_id = poi.id), 0)
+             + COALESCE((SELECT SUM(CASE WHEN sp.product_id IS NULL
+                                    THEN sp.amount * poi.total_cost * po.exchange_rate
+                                         / CASE WHEN po.total_amount = 0 THEN 1 ELSE po.total_amount END
+                                    ELSE sp.amount END)
+                         FROM supplier_payments sp
+                         WHERE sp.po_id = poi.po_id
+                           AND (sp.product_id IS NULL OR sp.product_id = poi.product_id)
+                           AND NOT EXISTS (SELECT 1 FROM supplier_payment_allocations a WHERE a.payment_id = sp.id)), 0) AS paid
+    FROM purchase_order_items poi
+    JOIN purchase_orders po ON po.id = poi.po_id
+)";
+
+/// Filter for supplier_payments rows (aliased sp) that have no explicit allocations
+const UNALLOCATED_SQL: &str = "NOT EXISTS (SELECT 1 FROM supplier_payment_allocations a WHERE a.payment_id = sp.id)";
+
+/// Check a payment's allocations against the supplier's PO lines, before the payment is saved.
+/// Every line must belong to one of the supplier's POs (to `po_id` when given), no line may be
+/// paid beyond its remaining balance, and the allocations must add up to the payment amount.
+/// Returns the total per PO line.
+pub(crate) fn check_payment_allocations(
+    conn: &Connection,
+    supplier_id: i32,
+    po_id: Option<i32>,
+    amount: f64,
+    allocations: &[PaymentAllocationInput],
+) -> Result<Vec<(i32, f64)>, String> {
+    let mut per_item: Vec<(i32, f64)> = Vec::new();
+    for allocation in allocations {
+        if allocation.amount <= 0.0 {
+            return Err("Allocation amounts must be greater than zero".to_string());
+        }
+        match per_item.iter_mut().find(|(id, _)| *id == allocation.po_item_id) {
+            Some((_, total)) => *total = money::sum([*total, allocation.amount]),
+            None => per_item.push((allocation.po_item_id, money::round_money(allocation.amount))),
+        }
+    }
+
+    let allocated = money::sum(per_item.iter().map(|(_, total)| *total));
+    if money::to_paise(allocated) != money::to_paise(amount) {
+        return Err(format!(
+            "Allocations add up to {:.2} but the payment is {:.2}",
+            allocated, amount
+        ));
+    }
+
+    for (po_item_id, total) in &per_item {
+        let balance: Option<(i32, i32, f64, f64)> = conn
+            .query_row(
+                &format!(
+                    "WITH {} SELECT supplier_id, po_id, value, paid FROM po_item_balances WHERE po_item_id = ?1",
+                    PO_ITEM_BALANCES_CTE
+                ),
+                [po_item_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to load PO item balance: {}", e))?;
+
+        let remaining = match balance {
+            Some((item_supplier, item_po, value, paid))
+                if item_supplier == supplier_id && po_id.map_or(true, |po_id| po_id == item_po) =>
+            {
+                money::sub(value, paid).max(0.0)
+            }
+            _ => return Err(format!("PO item {} is not on a purchase order from this supplier", po_item_id)),
+        };
+        if money::exceeds(*total, remaining) {
+            return Err(format!(
+                "Allocation of {:.2} to PO item {} exceeds its remaining balance of {:.2}",
+                total, po_item_id, remaining
+            ));
+        }
+    }
+
+    Ok(per_item)
+}
+
+/// Total explicitly allocated to a product's PO lines, by one supplier or (None) all of them
+fn allocated_to_product(conn: &Connection, supplier_id: Option<i32>, product_id: i32) -> Result<f64, String> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(a.amount), 0.0)
+         FROM supplier_payment_allocations a
+         JOIN supplier_payments sp ON sp.id = a.payment_id
+         JOIN purchase_order_items poi ON poi.id = a.po_item_id
+         WHERE poi.product_id = ?1 AND (?2 IS NULL OR sp.supplier_id = ?2)",
+        rusqlite::params![product_id, supplier_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to load payment allocations: {}", e))
+}
+
+/// Store allocations returned by check_payment_allocations for a saved payment
+pub(crate) fn save_payment_allocations(conn: &Connection, payment_id: i32, per_item: &[(i32, f64)]) -> Result<(), String> {
+    for (po_item_id, amount) in per_item {
+        conn.execute(
+            "INSERT INTO supplier_payment_allocations (payment_id, po_item_id, amount) VALUES (?1, ?2, ?3)",
+            (payment_id, po_item_id, amount),
+        )
+        .map_err(|e| format!("Failed to save payment allocation: {}", e))?;
+    }
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SupplierPaymentSummary {
+    /// Purchase value less returns set off against it
     pub total_payable: f64,
     pub total_paid: f64,
     pub pending_amount: f64,
+    /// Value of returns the supplier is to refund rather than set off
+    pub refundable_credit: f64,
+}
+
+/// A single debit/credit line in a supplier's account
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierLedgerEntry {
+    pub date: String,
+    pub entry_type: String, // "purchase_order", "initial_stock" or "payment"
+    pub reference: Option<String>, // PO number, SKU or payment method
+    pub description: String,
+    pub debit: f64,  // Amount owed to the supplier
+    pub credit: f64, // Amount paid to the supplier
+    pub balance: f64, // Running balance after this entry
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierLedger {
+    pub supplier_id: i32,
+    pub opening_balance: f64, // Balance carried into the range (includes pre-app debt)
+    pub entries: Vec<SupplierLedgerEntry>,
+    pub total_debit: f64,
+    pub total_credit: f64,
+    pub closing_balance: f64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -74,14 +233,13 @@ pub fn get_suppliers(
 
     let conn = db.get_conn()?;
 
-    let offset = (page - 1) * page_size;
-    let limit = page_size;
+    let (limit, offset) = validate_pagination(page, page_size)?;
 
     let mut suppliers = Vec::new();
     let total_count: i64;
 
     let base_query = "
-        SELECT s.id, s.name, s.contact_info, s.address, s.email, s.comments, s.state, s.district, s.town, s.image_path, s.created_at, s.updated_at,
+        SELECT s.id, s.name, s.contact_info, s.address, s.email, s.comments, s.state, s.district, s.town, s.image_path, s.created_at, s.updated_at, s.opening_balance,
                (SELECT MAX(created_at) FROM products WHERE supplier_id = s.id) as last_purchase_at
         FROM suppliers s";
     let count_query = "SELECT COUNT(*) FROM suppliers";
@@ -116,6 +274,7 @@ pub fn get_suppliers(
                     image_path: row.get(9)?,
                     created_at: row.get(10)?,
                     updated_at: row.get(11)?,
+                    opening_balance: row.get(12)?,
                 })
             })
             .map_err(|e| e.to_string())?;
@@ -148,6 +307,7 @@ pub fn get_suppliers(
                     image_path: row.get(9)?,
                     created_at: row.get(10)?,
                     updated_at: row.get(11)?,
+                    opening_balance: row.get(12)?,
                 })
             })
             .map_err(|e| e.to_string())?;
@@ -170,10 +330,13 @@ pub fn get_supplier(id: i32, db: State<Database>) -> Result<Supplier, String> {
     log::info!("get_supplier called with id: {}", id);
 
     let conn = db.get_conn()?;
+    get_supplier_internal(&conn, id)
+}
 
+pub(crate) fn get_supplier_internal(conn: &Connection, id: i32) -> Result<Supplier, String> {
     let supplier = conn
         .query_row(
-            "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at FROM suppliers WHERE id = ?1",
+            "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at, opening_balance FROM suppliers WHERE id = ?1",
             [id],
             |row| {
                 Ok(Supplier {
@@ -189,6 +352,7 @@ pub fn get_supplier(id: i32, db: State<Database>) -> Result<Supplier, String> {
                     image_path: row.get(9)?,
                     created_at: row.get(10)?,
                     updated_at: row.get(11)?,
+                    opening_balance: row.get(12)?,
                 })
             },
         )
@@ -199,11 +363,16 @@ pub fn get_supplier(id: i32, db: State<Database>) -> Result<Supplier, String> {
 
 /// Create a new supplier
 #[tauri::command]
-pub fn create_supplier(input: CreateSupplierInput, db: State<Database>) -> Result<Supplier, String> {
+pub fn create_supplier(input: CreateSupplierInput, app: AppHandle, db: State<Database>) -> Result<Supplier, String> {
     log::info!("create_supplier called with: {:?}", input);
 
     let conn = db.get_conn()?;
+    let supplier = create_supplier_internal(&conn, input)?;
+    emit_data_changed(&app, DataEntity::Supplier, DataOperation::Created, supplier.id);
+    Ok(supplier)
+}
 
+pub(crate) fn create_supplier_internal(conn: &Connection, input: CreateSupplierInput) -> Result<Supplier, String> {
     conn.execute(
         "INSERT INTO suppliers (name, contact_info, address, email, comments, state, district, town, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, datetime('now'), datetime('now'))",
         (&input.name, &input.contact_info, &input.address, &input.email, &input.comments, &input.state, &input.district, &input.town),
@@ -214,7 +383,7 @@ pub fn create_supplier(input: CreateSupplierInput, db: State<Database>) -> Resul
 
     // Fetch the created supplier to get timestamps
     let supplier = conn.query_row(
-        "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at FROM suppliers WHERE id = ?1",
+        "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at, opening_balance FROM suppliers WHERE id = ?1",
         [id],
         |row| {
             Ok(Supplier {
@@ -230,6 +399,7 @@ pub fn create_supplier(input: CreateSupplierInput, db: State<Database>) -> Resul
                 image_path: row.get(9)?,
                 created_at: row.get(10)?,
                 updated_at: row.get(11)?,
+                opening_balance: row.get(12)?,
             })
         },
     ).map_err(|e| format!("Failed to fetch created supplier: {}", e))?;
@@ -240,7 +410,12 @@ pub fn create_supplier(input: CreateSupplierInput, db: State<Database>) -> Resul
 
 /// Update an existing supplier
 #[tauri::command]
-pub fn update_supplier(input: UpdateSupplierInput, modified_by: Option<String>, db: State<Database>) -> Result<Supplier, String> {
+pub fn update_supplier(
+    input: UpdateSupplierInput,
+    modified_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<Supplier, String> {
     log::info!("update_supplier called with: {:?}", input);
 
     let conn = db.get_conn()?;
@@ -248,7 +423,7 @@ pub fn update_supplier(input: UpdateSupplierInput, modified_by: Option<String>,
     // Get old values first
     let old_supplier: Supplier = conn
         .query_row(
-            "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at FROM suppliers WHERE id = ?1",
+            "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at, opening_balance FROM suppliers WHERE id = ?1",
             [input.id],
             |row| {
                 Ok(Supplier {
@@ -264,6 +439,7 @@ pub fn update_supplier(input: UpdateSupplierInput, modified_by: Option<String>,
                     image_path: row.get(9)?,
                     created_at: row.get(10)?,
                     updated_at: row.get(11)?,
+                    opening_balance: row.get(12)?,
                 })
             },
         )
@@ -296,11 +472,14 @@ pub fn update_supplier(input: UpdateSupplierInput, modified_by: Option<String>,
     if old_supplier.town != input.town {
         field_changes.push(serde_json::json!({"field": "town", "old": old_supplier.town, "new": input.town}));
     }
+    if input.opening_balance.is_some() && old_supplier.opening_balance != input.opening_balance {
+        field_changes.push(serde_json::json!({"field": "opening_balance", "old": old_supplier.opening_balance, "new": input.opening_balance}));
+    }
 
     let rows_affected = conn
         .execute(
-            "UPDATE suppliers SET name = ?1, contact_info = ?2, address = ?3, email = ?4, comments = ?5, state = ?6, district = ?7, town = ?8, updated_at = datetime('now') WHERE id = ?9",
-            (&input.name, &input.contact_info, &input.address, &input.email, &input.comments, &input.state, &input.district, &input.town, input.id),
+            "UPDATE suppliers SET name = ?1, contact_info = ?2, address = ?3, email = ?4, comments = ?5, state = ?6, district = ?7, town = ?8, opening_balance = COALESCE(?9, opening_balance), updated_at = datetime('now') WHERE id = ?10",
+            (&input.name, &input.contact_info, &input.address, &input.email, &input.comments, &input.state, &input.district, &input.town, input.opening_balance, input.id),
         )
         .map_err(|e| format!("Failed to update supplier: {}", e))?;
 
@@ -320,7 +499,7 @@ pub fn update_supplier(input: UpdateSupplierInput, modified_by: Option<String>,
 
     // Fetch updated supplier to get new timestamp
     let supplier = conn.query_row(
-        "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at FROM suppliers WHERE id = ?1",
+        "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at, opening_balance FROM suppliers WHERE id = ?1",
         [input.id],
         |row| {
             Ok(Supplier {
@@ -336,24 +515,31 @@ pub fn update_supplier(input: UpdateSupplierInput, modified_by: Option<String>,
                 image_path: row.get(9)?,
                 created_at: row.get(10)?,
                 updated_at: row.get(11)?,
+                opening_balance: row.get(12)?,
             })
         },
     ).map_err(|e| format!("Failed to fetch updated supplier: {}", e))?;
 
     log::info!("Updated supplier with id: {}", input.id);
+    emit_data_changed(&app, DataEntity::Supplier, DataOperation::Updated, supplier.id);
     Ok(supplier)
 }
 
 /// Delete a supplier by ID
 #[tauri::command]
-pub fn delete_supplier(id: i32, deleted_by: Option<String>, db: State<Database>) -> Result<(), String> {
+pub fn delete_supplier(
+    id: i32,
+    deleted_by: Option<String>,
+    app_handle: AppHandle,
+    db: State<Database>,
+) -> Result<(), String> {
     log::info!("delete_supplier called with id: {}", id);
 
     let mut conn = db.get_conn()?;
 
     // Get supplier data before deletion for audit trail
     let supplier = conn.query_row(
-        "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at FROM suppliers WHERE id = ?1",
+        "SELECT id, name, contact_info, address, email, comments, state, district, town, image_path, created_at, updated_at, opening_balance FROM suppliers WHERE id = ?1",
         [id],
         |row| {
             Ok(Supplier {
@@ -369,6 +555,7 @@ pub fn delete_supplier(id: i32, deleted_by: Option<String>, db: State<Database>)
                 image_path: row.get(9)?,
                 created_at: row.get(10)?,
                 updated_at: row.get(11)?,
+                opening_balance: row.get(12)?,
             })
         },
     )
@@ -421,6 +608,9 @@ pub fn delete_supplier(id: i32, deleted_by: Option<String>, db: State<Database>)
 
     tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
 
+    remove_image_files(&app_handle, &conn, supplier.image_path.as_deref());
+    emit_data_changed(&app_handle, DataEntity::Supplier, DataOperation::Deleted, id);
+
     log::info!("Deleted supplier with id: {} and saved to trash", id);
     Ok(())
 }
@@ -429,6 +619,7 @@ pub fn delete_supplier(id: i32, deleted_by: Option<String>, db: State<Database>)
 #[tauri::command]
 pub fn create_supplier_payment(
     input: CreateSupplierPaymentInput,
+    app: AppHandle,
     db: State<Database>,
 ) -> Result<SupplierPayment, String> {
     log::info!(
@@ -437,21 +628,56 @@ pub fn create_supplier_payment(
         input.amount
     );
 
+    let conn = db.get_conn()?;
+    let payment = create_supplier_payment_internal(&conn, input)?;
+    // Payments change what the supplier is owed
+    emit_data_changed(&app, DataEntity::Supplier, DataOperation::Updated, payment.supplier_id);
+    Ok(payment)
+}
+
+pub(crate) fn create_supplier_payment_internal(
+    conn: &Connection,
+    input: CreateSupplierPaymentInput,
+) -> Result<SupplierPayment, String> {
     if input.amount <= 0.0 {
         return Err("Amount must be greater than zero".into());
     }
 
-    let conn = db.get_conn()?;
-
     let paid_at = input
         .paid_at
         .unwrap_or_else(|| Utc::now().to_rfc3339());
+    let allocations = match input.allocations.as_deref() {
+        Some(allocations) if !allocations.is_empty() => {
+            check_payment_allocations(conn, input.supplier_id, None, input.amount, allocations)?
+        }
+        _ => Vec::new(),
+    };
 
-    conn.execute(
-        "INSERT INTO supplier_payments (supplier_id, product_id, amount, payment_method, note, paid_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, datetime('now'))",
+    // A payment allocated entirely to one PO is recorded against that PO
+    let mut allocated_pos: Vec<i32> = Vec::new();
+    for (po_item_id, _) in &allocations {
+        let po_id: i32 = conn
+            .query_row("SELECT po_id FROM purchase_order_items WHERE id = ?1", [po_item_id], |row| row.get(0))
+            .map_err(|e| format!("Failed to load PO item: {}", e))?;
+        if !allocated_pos.contains(&po_id) {
+            allocated_pos.push(po_id);
+        }
+    }
+    let po_id = match allocated_pos.as_slice() {
+        [po_id] => Some(*po_id),
+        _ => None,
+    };
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    tx.execute(
+        "INSERT INTO supplier_payments (supplier_id, product_id, po_id, amount, payment_method, note, paid_at, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, datetime('now'))",
         (
             input.supplier_id,
             input.product_id,
+            po_id,
             input.amount,
             input.payment_method.as_deref(),
             input.note.as_deref(),
@@ -460,11 +686,16 @@ pub fn create_supplier_payment(
     )
     .map_err(|e| format!("Failed to create supplier payment: {}", e))?;
 
-    let id = conn.last_insert_rowid() as i32;
+    let id = tx.last_insert_rowid() as i32;
+
+    save_payment_allocations(&tx, id, &allocations)?;
 
-    let payment = conn
+    let payment = tx
         .query_row(
-            "SELECT id, supplier_id, product_id, amount, payment_method, note, paid_at, created_at FROM supplier_payments WHERE id = ?1",
+            "SELECT sp.id, sp.supplier_id, sp.product_id, sp.amount, sp.payment_method, sp.note, sp.paid_at, sp.created_at, sp.po_id, po.po_number
+             FROM supplier_payments sp
+             LEFT JOIN purchase_orders po ON sp.po_id = po.id
+             WHERE sp.id = ?1",
             [id],
             |row| {
                 Ok(SupplierPayment {
@@ -476,17 +707,18 @@ pub fn create_supplier_payment(
                     note: row.get(5)?,
                     paid_at: row.get(6)?,
                     created_at: row.get(7)?,
-                    po_id: None,
-                    po_number: None,
+                    po_id: row.get(8)?,
+                    po_number: row.get(9)?,
                 })
             },
         )
         .map_err(|e| format!("Failed to fetch created supplier payment: {}", e))?;
 
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
     Ok(payment)
 }
 
-/// Get all payments for a supplier (direct + proportional PO share)
+/// Get all payments for a supplier (direct + proportional PO share, or the explicitly allocated share)
 #[tauri::command]
 pub fn get_supplier_payments(
     supplier_id: i32,
@@ -499,15 +731,26 @@ pub fn get_supplier_payments(
     );
 
     let conn = db.get_conn()?;
+    get_supplier_payments_internal(&conn, supplier_id, product_id)
+}
+
+pub(crate) fn get_supplier_payments_internal(
+    conn: &Connection,
+    supplier_id: i32,
+    product_id: i32,
+) -> Result<Vec<SupplierPayment>, String> {
     let mut payments = Vec::new();
 
     // 1. Fetch direct payments for this product
     let mut direct_stmt = conn
         .prepare(
-            "SELECT sp.id, sp.supplier_id, sp.product_id, sp.amount, sp.payment_method, sp.note, sp.paid_at, sp.created_at, sp.po_id, po.po_number
-             FROM supplier_payments sp
-             LEFT JOIN purchase_orders po ON sp.po_id = po.id
-             WHERE sp.supplier_id = ?1 AND sp.product_id = ?2",
+            &format!(
+                "SELECT sp.id, sp.supplier_id, sp.product_id, sp.amount, sp.payment_method, sp.note, sp.paid_at, sp.created_at, sp.po_id, po.po_number
+                 FROM supplier_payments sp
+                 LEFT JOIN purchase_orders po ON sp.po_id = po.id
+                 WHERE sp.supplier_id = ?1 AND sp.product_id = ?2 AND {}",
+                UNALLOCATED_SQL
+            ),
         )
         .map_err(|e| e.to_string())?;
 
@@ -532,62 +775,68 @@ pub fn get_supplier_payments(
         payments.push(payment.map_err(|e| e.to_string())?);
     }
 
-    // 2. Fetch PO-level payments (product_id IS NULL) and calculate share
+    // 2. PO-level payments (product_id IS NULL), as this product's share of each
     let mut indirect_stmt = conn
         .prepare(
-            "SELECT sp.id, sp.supplier_id, sp.amount, sp.payment_method, sp.note, sp.paid_at, sp.created_at, sp.po_id, po.po_number,
-                    po.total_amount, poi.total_cost
+            "SELECT sp.id, sp.supplier_id, sp.payment_method, sp.note, sp.paid_at, sp.created_at, sp.po_id, po.po_number
              FROM supplier_payments sp
-             JOIN purchase_orders po ON sp.po_id = po.id
-             JOIN purchase_order_items poi ON poi.po_id = po.id
-             WHERE sp.supplier_id = ?1 
-               AND sp.product_id IS NULL
-               AND poi.product_id = ?2",
+             LEFT JOIN purchase_orders po ON sp.po_id = po.id
+             WHERE sp.id = ?1",
         )
         .map_err(|e| e.to_string())?;
 
-    let indirect_iter = indirect_stmt
-        .query_map(rusqlite::params![supplier_id, product_id], |row| {
-            let id: i32 = row.get(0)?;
-            let supplier_id: i32 = row.get(1)?;
-            let amount: f64 = row.get(2)?;
-            let payment_method: Option<String> = row.get(3)?;
-            let note: Option<String> = row.get(4)?;
-            let paid_at: String = row.get(5)?;
-            let created_at: String = row.get(6)?;
-            let po_id: Option<i32> = row.get(7)?;
-            let po_number: Option<String> = row.get(8)?;
-            let po_total: f64 = row.get(9)?;
-            let item_total: f64 = row.get(10)?;
-
-            // Calculate proportional share
-            let share = if po_total > 0.0 {
-                let calc = (item_total / po_total) * amount;
-                (calc * 100.0).round() / 100.0
-            } else {
-                0.0
-            };
+    for share in payment_shares::product_payment_shares(conn, Some(supplier_id), product_id)? {
+        let payment = indirect_stmt
+            .query_row([share.payment_id], |row| {
+                Ok(SupplierPayment {
+                    id: row.get(0)?,
+                    supplier_id: row.get(1)?,
+                    product_id: Some(product_id), // Masquerade as product payment
+                    amount: share.share,
+                    payment_method: row.get(2)?,
+                    note: row.get(3)?,
+                    paid_at: row.get(4)?,
+                    created_at: row.get(5)?,
+                    po_id: row.get(6)?,
+                    po_number: row.get(7)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        payments.push(payment);
+    }
+
+    // 3. Payments with explicit allocations: the part allocated to this product's PO lines
+    let mut allocated_stmt = conn
+        .prepare(
+            "SELECT sp.id, sp.supplier_id, SUM(a.amount), sp.payment_method, sp.note, sp.paid_at, sp.created_at, sp.po_id, po.po_number
+             FROM supplier_payment_allocations a
+             JOIN supplier_payments sp ON sp.id = a.payment_id
+             JOIN purchase_order_items poi ON poi.id = a.po_item_id
+             LEFT JOIN purchase_orders po ON sp.po_id = po.id
+             WHERE sp.supplier_id = ?1 AND poi.product_id = ?2
+             GROUP BY sp.id",
+        )
+        .map_err(|e| e.to_string())?;
 
+    let allocated_iter = allocated_stmt
+        .query_map(rusqlite::params![supplier_id, product_id], |row| {
             Ok(SupplierPayment {
-                id,
-                supplier_id,
-                product_id: Some(product_id), // Masquerade as product payment
-                amount: share,
-                payment_method,
-                note,
-                paid_at,
-                created_at,
-                po_id,
-                po_number,
+                id: row.get(0)?,
+                supplier_id: row.get(1)?,
+                product_id: Some(product_id),
+                amount: row.get(2)?,
+                payment_method: row.get(3)?,
+                note: row.get(4)?,
+                paid_at: row.get(5)?,
+                created_at: row.get(6)?,
+                po_id: row.get(7)?,
+                po_number: row.get(8)?,
             })
         })
         .map_err(|e| e.to_string())?;
 
-    for payment in indirect_iter {
-        let p = payment.map_err(|e| e.to_string())?;
-        if p.amount > 0.0 {
-            payments.push(p);
-        }
+    for payment in allocated_iter {
+        payments.push(payment.map_err(|e| e.to_string())?);
     }
 
     // Sort by paid_at DESC, id DESC
@@ -647,10 +896,31 @@ pub fn get_all_product_payments(
 
 /// Delete a single supplier payment by ID
 #[tauri::command]
-pub fn delete_supplier_payment(id: i32, deleted_by: Option<String>, db: State<Database>) -> Result<(), String> {
+pub fn delete_supplier_payment(
+    id: i32,
+    deleted_by: Option<String>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<(), String> {
     log::info!("delete_supplier_payment called with id: {}, deleted_by: {:?}", id, deleted_by);
-    let mut conn = db.get_conn()?;
+    let conn = db.get_conn()?;
+    let (payment, po_ids) = delete_supplier_payment_internal(&conn, id, deleted_by)?;
+
+    emit_data_changed(&app, DataEntity::Supplier, DataOperation::Updated, payment.supplier_id);
+    for po_id in po_ids {
+        emit_data_changed(&app, DataEntity::PurchaseOrder, DataOperation::Updated, po_id);
+    }
+    Ok(())
+}
 
+/// PO totals paid and supplier pending amounts are summed from supplier_payments on read, so
+/// archiving, deleting and logging in one transaction leaves nothing stale. Returns the payment
+/// and the POs it counted towards.
+pub(crate) fn delete_supplier_payment_internal(
+    conn: &Connection,
+    id: i32,
+    deleted_by: Option<String>,
+) -> Result<(SupplierPayment, Vec<i32>), String> {
     // 1. Fetch payment details for audit
     let payment = conn.query_row(
         "SELECT sp.id, sp.supplier_id, sp.product_id, sp.amount, sp.payment_method, sp.note, sp.paid_at, sp.created_at, sp.po_id, po.po_number 
@@ -674,7 +944,27 @@ pub fn delete_supplier_payment(id: i32, deleted_by: Option<String>, db: State<Da
         }
     ).map_err(|e| format!("Payment not found: {}", e))?;
 
-    let tx = conn.transaction().map_err(|e| format!("Transaction failed: {}", e))?;
+    let tx = conn.unchecked_transaction().map_err(|e| format!("Transaction failed: {}", e))?;
+
+    // POs this payment counts towards, directly or through line allocations
+    let po_ids: Vec<i32> = tx
+        .prepare(
+            "SELECT ?2 WHERE ?2 IS NOT NULL
+             UNION
+             SELECT poi.po_id FROM supplier_payment_allocations a
+             JOIN purchase_order_items poi ON poi.id = a.po_item_id
+             WHERE a.payment_id = ?1",
+        )
+        .and_then(|mut stmt| {
+            stmt.query_map(rusqlite::params![id, payment.po_id], |row| row.get(0))?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| format!("Failed to load payment allocations: {}", e))?;
+    let po_paid = |tx: &rusqlite::Transaction, po_id: i32| -> Result<f64, String> {
+        tx.query_row(PO_TOTAL_PAID_SQL, [po_id], |row| row.get(0))
+            .map_err(|e| format!("Failed to recompute purchase order payments: {}", e))
+    };
+    let paid_before = po_ids.iter().map(|po_id| po_paid(&tx, *po_id)).collect::<Result<Vec<_>, _>>()?;
 
     // 2. Archive
     crate::db::archive::archive_entity(
@@ -683,10 +973,10 @@ pub fn delete_supplier_payment(id: i32, deleted_by: Option<String>, db: State<Da
         id,
         &payment,
         None,
-        deleted_by
+        deleted_by.clone()
     )?;
 
-    // 3. Delete
+    // 3. Delete (allocations cascade)
     let rows_affected = tx.execute("DELETE FROM supplier_payments WHERE id = ?1", [id])
         .map_err(|e| format!("Failed to delete supplier payment: {}", e))?;
 
@@ -694,8 +984,28 @@ pub fn delete_supplier_payment(id: i32, deleted_by: Option<String>, db: State<Da
          return Err(format!("Supplier payment with id {} not found", id));
     }
 
+    // 4. Log, with what each affected PO has been paid before and after
+    let mut field_changes = vec![serde_json::json!({"field": "amount", "old": payment.amount, "new": null})];
+    for (po_id, before) in po_ids.iter().zip(paid_before) {
+        field_changes.push(serde_json::json!({
+            "field": "po_total_paid",
+            "po_id": po_id,
+            "old": before,
+            "new": po_paid(&tx, *po_id)?,
+        }));
+    }
+    let entity_name = match &payment.po_number {
+        Some(po_number) => format!("Payment on {}", po_number),
+        None => format!("Payment #{}", id),
+    };
+    tx.execute(
+        "INSERT INTO entity_modifications (entity_type, entity_id, entity_name, action, field_changes, modified_by) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        ("supplier_payment", id, &entity_name, "deleted", serde_json::Value::from(field_changes).to_string(), &deleted_by),
+    )
+    .map_err(|e| format!("Failed to log entity modification: {}", e))?;
+
     tx.commit().map_err(|e| format!("Commit failed: {}", e))?;
-    Ok(())
+    Ok((payment, po_ids))
 }
 
 /// Get payment summary for a supplier (total payable, total paid, pending)
@@ -711,71 +1021,75 @@ pub fn get_supplier_payment_summary(
     );
 
     let conn = db.get_conn()?;
+    get_supplier_payment_summary_internal(&conn, supplier_id, product_id)
+}
 
+pub(crate) fn get_supplier_payment_summary_internal(
+    conn: &Connection,
+    supplier_id: i32,
+    product_id: i32,
+) -> Result<SupplierPaymentSummary, String> {
     // Total payable is the purchase value for this specific product from this supplier.
-    // Use purchase_order_items to sum actual quantities and costs, plus initial stock value.
-    let (po_total_value, _po_total_qty): (f64, i64) = conn
+    // Use items on received POs to sum actual quantities and costs, plus initial stock value.
+    // Draft, ordered and cancelled POs are not owed yet (or at all).
+    let (po_total_value, _po_total_qty): (f64, f64) = conn
         .query_row(
             "SELECT
-                COALESCE(SUM(poi.quantity * poi.unit_cost), 0.0),
+                COALESCE(SUM(poi.quantity * poi.unit_cost * po.exchange_rate), 0.0),
                 COALESCE(SUM(poi.quantity), 0)
              FROM purchase_order_items poi
              JOIN purchase_orders po ON po.id = poi.po_id
-             WHERE poi.product_id = ?1 AND po.supplier_id = ?2",
+             WHERE poi.product_id = ?1 AND po.supplier_id = ?2 AND po.status = 'received'",
             (product_id, supplier_id),
             |row| Ok((row.get(0)?, row.get(1)?)),
         )
-        .unwrap_or((0.0, 0));
+        .unwrap_or((0.0, 0.0));
 
-    let (initial_stock, price, primary_supplier_id): (i64, f64, Option<i32>) = conn
+    let (initial_stock, price, primary_supplier_id): (f64, f64, Option<i32>) = conn
         .query_row(
             "SELECT COALESCE(initial_stock, 0), price, supplier_id FROM products WHERE id = ?1",
             [product_id],
-            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?, row.get::<_, Option<i32>>(2)?)),
+            |row| Ok((row.get::<_, f64>(0)?, row.get::<_, f64>(1)?, row.get::<_, Option<i32>>(2)?)),
         )
-        .unwrap_or((0, 0.0, None));
+        .unwrap_or((0.0, 0.0, None));
 
     // Only include initial stock value if this is the primary supplier for the product
     let initial_stock_val = if primary_supplier_id == Some(supplier_id) {
-        initial_stock as f64 * price
+        initial_stock * price
     } else {
         0.0
     };
 
-    let total_payable: f64 = po_total_value + initial_stock_val;
+    let returns = product_return_totals(conn, Some(supplier_id), product_id)?;
+    let total_payable = money::sub(po_total_value + initial_stock_val, returns.payable_reduction);
 
     // 1. Direct Payments
     let direct_paid: f64 = conn
         .query_row(
-            "SELECT COALESCE(SUM(amount), 0) FROM supplier_payments WHERE supplier_id = ?1 AND product_id = ?2",
+            &format!(
+                "SELECT COALESCE(SUM(amount), 0) FROM supplier_payments sp WHERE supplier_id = ?1 AND product_id = ?2 AND {}",
+                UNALLOCATED_SQL
+            ),
             (supplier_id, product_id),
             |row| row.get(0),
         )
         .unwrap_or(0.0);
 
     // 2. Indirect (PO) Payments
-    let indirect_paid: f64 = conn
-        .query_row(
-            "SELECT COALESCE(SUM((poi.total_cost / CASE WHEN po.total_amount = 0 THEN 1 ELSE po.total_amount END) * sp.amount), 0.0)
-             FROM supplier_payments sp
-             JOIN purchase_orders po ON sp.po_id = po.id
-             JOIN purchase_order_items poi ON poi.po_id = po.id
-             WHERE sp.supplier_id = ?1
-               AND sp.product_id IS NULL
-               AND poi.product_id = ?2",
-            (supplier_id, product_id),
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+    let indirect_paid = payment_shares::product_payment_share_total(conn, Some(supplier_id), product_id)?;
+
+    // 3. Payments explicitly allocated to this product's PO lines (instead of 1 and 2)
+    let allocated_paid = allocated_to_product(conn, Some(supplier_id), product_id)?;
 
-    let total_paid = direct_paid + indirect_paid;
+    let total_paid = money::sum([direct_paid, indirect_paid, allocated_paid]);
 
-    let pending = (total_payable - total_paid).max(0.0);
+    let pending = money::sub(total_payable, total_paid).max(0.0);
 
     Ok(SupplierPaymentSummary {
         total_payable,
         total_paid,
         pending_amount: pending,
+        refundable_credit: returns.refundable_credit,
     })
 }
 
@@ -791,67 +1105,316 @@ pub fn get_all_product_payment_summary(
     );
 
     let conn = db.get_conn()?;
+    get_all_product_payment_summary_internal(&conn, product_id)
+}
 
-    // Total payable: Sum of ALL PO items for this product + Initial Stock Value
-    let (po_total_value, _po_total_qty): (f64, i64) = conn
+pub(crate) fn get_all_product_payment_summary_internal(
+    conn: &Connection,
+    product_id: i32,
+) -> Result<SupplierPaymentSummary, String> {
+    // Total payable: Sum of received PO items for this product + Initial Stock Value
+    let (po_total_value, _po_total_qty): (f64, f64) = conn
         .query_row(
             "SELECT
-                COALESCE(SUM(poi.quantity * poi.unit_cost), 0.0),
+                COALESCE(SUM(poi.quantity * poi.unit_cost * po.exchange_rate), 0.0),
                 COALESCE(SUM(poi.quantity), 0)
              FROM purchase_order_items poi
-             WHERE poi.product_id = ?1",
+             JOIN purchase_orders po ON po.id = poi.po_id
+             WHERE poi.product_id = ?1 AND po.status = 'received'",
             [product_id],
             |row| Ok((row.get(0)?, row.get(1)?)),
         )
-        .unwrap_or((0.0, 0));
+        .unwrap_or((0.0, 0.0));
 
     // Initial stock value
-    let (initial_stock, price): (i64, f64) = conn
+    let (initial_stock, price): (f64, f64) = conn
         .query_row(
             "SELECT COALESCE(initial_stock, 0), price FROM products WHERE id = ?1",
             [product_id],
-            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, f64>(1)?)),
+            |row| Ok((row.get::<_, f64>(0)?, row.get::<_, f64>(1)?)),
         )
-        .unwrap_or((0, 0.0));
+        .unwrap_or((0.0, 0.0));
 
-    let initial_stock_val = initial_stock as f64 * price;
-    let total_payable: f64 = po_total_value + initial_stock_val;
+    let initial_stock_val = initial_stock * price;
+    let returns = product_return_totals(conn, None, product_id)?;
+    let total_payable = money::sub(po_total_value + initial_stock_val, returns.payable_reduction);
 
     // 1. Direct Payments
     let direct_paid: f64 = conn
         .query_row(
-            "SELECT COALESCE(SUM(amount), 0) FROM supplier_payments WHERE product_id = ?1",
+            &format!(
+                "SELECT COALESCE(SUM(amount), 0) FROM supplier_payments sp WHERE product_id = ?1 AND {}",
+                UNALLOCATED_SQL
+            ),
             [product_id],
             |row| row.get(0),
         )
         .unwrap_or(0.0);
 
     // 2. Indirect (PO) Payments
-    let indirect_paid: f64 = conn
-        .query_row(
-            "SELECT COALESCE(SUM((poi.total_cost / CASE WHEN po.total_amount = 0 THEN 1 ELSE po.total_amount END) * sp.amount), 0.0)
-             FROM supplier_payments sp
-             JOIN purchase_orders po ON sp.po_id = po.id
-             JOIN purchase_order_items poi ON poi.po_id = po.id
-             WHERE sp.product_id IS NULL
-               AND poi.product_id = ?1",
-            [product_id],
-            |row| row.get(0),
-        )
-        .unwrap_or(0.0);
+    let indirect_paid = payment_shares::product_payment_share_total(conn, None, product_id)?;
+
+    // 3. Explicitly allocated payments
+    let allocated_paid = allocated_to_product(conn, None, product_id)?;
 
-    let total_paid = direct_paid + indirect_paid;
+    let total_paid = money::sum([direct_paid, indirect_paid, allocated_paid]);
 
-    let pending = (total_payable - total_paid).max(0.0);
+    let pending = money::sub(total_payable, total_paid).max(0.0);
 
     Ok(SupplierPaymentSummary {
         total_payable,
         total_paid,
         pending_amount: pending,
+        refundable_credit: returns.refundable_credit,
     })
 }
 
-/// Get purchase history (PO items) for a specific product and supplier
+/// Unpaid PO balance owed to a supplier, bucketed by days since the order date
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierPayablesAging {
+    pub supplier_id: i32,
+    pub supplier_name: String,
+    pub days_0_30: f64,
+    pub days_31_60: f64,
+    pub days_61_90: f64,
+    pub days_over_90: f64,
+    pub total: f64,
+}
+
+/// Get unpaid PO balances per supplier by age, oldest debt first. Line balances are those of
+/// PO_ITEM_BALANCES_CTE; payments made against a product rather than a PO aren't counted.
+#[tauri::command]
+pub fn get_supplier_payables_aging(db: State<Database>) -> Result<Vec<SupplierPayablesAging>, String> {
+    log::info!("get_supplier_payables_aging called");
+
+    let conn = db.get_conn()?;
+    get_supplier_payables_aging_internal(&conn)
+}
+
+pub(crate) fn get_supplier_payables_aging_internal(conn: &Connection) -> Result<Vec<SupplierPayablesAging>, String> {
+    let today = crate::commands::analytics::business_today(conn).to_string();
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "WITH {},
+             open_items AS (
+                 SELECT supplier_id, MAX(value - paid, 0) AS balance,
+                        CAST(julianday(?1) - julianday(date(order_date)) AS INTEGER) AS age
+                 FROM po_item_balances
+             )
+             SELECT s.id, s.name,
+                    COALESCE(SUM(CASE WHEN oi.age <= 30 THEN oi.balance END), 0) AS days_0_30,
+                    COALESCE(SUM(CASE WHEN oi.age BETWEEN 31 AND 60 THEN oi.balance END), 0) AS days_31_60,
+                    COALESCE(SUM(CASE WHEN oi.age BETWEEN 61 AND 90 THEN oi.balance END), 0) AS days_61_90,
+                    COALESCE(SUM(CASE WHEN oi.age > 90 THEN oi.balance END), 0) AS days_over_90,
+                    SUM(oi.balance) AS total
+             FROM open_items oi
+             JOIN suppliers s ON s.id = oi.supplier_id
+             WHERE oi.balance >= 0.005
+             GROUP BY s.id
+             ORDER BY days_over_90 DESC, days_61_90 DESC, days_31_60 DESC, total DESC",
+            PO_ITEM_BALANCES_CTE
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let aging = stmt
+        .query_map([today], |row| {
+            Ok(SupplierPayablesAging {
+                supplier_id: row.get(0)?,
+                supplier_name: row.get(1)?,
+                days_0_30: money::round_money(row.get(2)?),
+                days_31_60: money::round_money(row.get(3)?),
+                days_61_90: money::round_money(row.get(4)?),
+                days_over_90: money::round_money(row.get(5)?),
+                total: money::round_money(row.get(6)?),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>();
+
+    aging.map_err(|e| format!("Failed to load payables aging: {}", e))
+}
+
+/// Get a chronological account for a supplier: PO totals and initial stock as debits,
+/// payments as credits, with a running balance.
+///
+/// Attribution matches get_supplier_payment_summary: initial stock is only charged to the
+/// product's primary supplier, and PO-level payments are counted once at their full amount
+/// (the per-product proportional shares add back up to the same total).
+/// Dates are `YYYY-MM-DD`; everything before `from_date` is folded into `opening_balance`.
+#[tauri::command]
+pub fn get_supplier_ledger(
+    supplier_id: i32,
+    from_date: Option<String>,
+    to_date: Option<String>,
+    db: State<Database>,
+) -> Result<SupplierLedger, String> {
+    log::info!(
+        "get_supplier_ledger called for supplier_id: {}, from: {:?}, to: {:?}",
+        supplier_id, from_date, to_date
+    );
+
+    let conn = db.get_conn()?;
+
+    let pre_app_balance: f64 = conn
+        .query_row(
+            "SELECT COALESCE(opening_balance, 0) FROM suppliers WHERE id = ?1",
+            [supplier_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Supplier not found: {}", e))?;
+
+    let mut entries: Vec<SupplierLedgerEntry> = Vec::new();
+
+    // Debits: purchase order totals (same item sum as get_supplier_payment_summary)
+    let mut po_stmt = conn
+        .prepare(
+            "SELECT COALESCE(date(po.order_date), po.order_date), po.po_number, po.status, COALESCE(SUM(poi.quantity * poi.unit_cost * po.exchange_rate), 0.0)
+             FROM purchase_orders po
+             JOIN purchase_order_items poi ON poi.po_id = po.id
+             WHERE po.supplier_id = ?1 AND po.status = 'received'
+             GROUP BY po.id
+             ORDER BY po.order_date, po.id",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let po_iter = po_stmt
+        .query_map([supplier_id], |row| {
+            let po_number: String = row.get(1)?;
+            let status: String = row.get(2)?;
+            Ok(SupplierLedgerEntry {
+                date: row.get(0)?,
+                entry_type: "purchase_order".to_string(),
+                reference: Some(po_number.clone()),
+                description: format!("Purchase order {} ({})", po_number, status),
+                debit: row.get(3)?,
+                credit: 0.0,
+                balance: 0.0,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    for entry in po_iter {
+        entries.push(entry.map_err(|e| e.to_string())?);
+    }
+
+    // Debits: initial stock valuation, only for products where this is the primary supplier
+    let mut stock_stmt = conn
+        .prepare(
+            "SELECT COALESCE(date(created_at), created_at), name, sku, initial_stock, price
+             FROM products
+             WHERE supplier_id = ?1 AND COALESCE(initial_stock, 0) > 0
+             ORDER BY created_at, id",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let stock_iter = stock_stmt
+        .query_map([supplier_id], |row| {
+            let name: String = row.get(1)?;
+            let initial_stock: f64 = row.get(3)?;
+            let price: f64 = row.get(4)?;
+            Ok(SupplierLedgerEntry {
+                date: row.get(0)?,
+                entry_type: "initial_stock".to_string(),
+                reference: row.get(2)?,
+                description: format!("Initial stock: {} x {}", quantity::format_quantity(initial_stock), name),
+                debit: initial_stock * price,
+                credit: 0.0,
+                balance: 0.0,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    for entry in stock_iter {
+        entries.push(entry.map_err(|e| e.to_string())?);
+    }
+
+    // Credits: every payment to this supplier, whether product-linked, PO-linked or general
+    let mut pay_stmt = conn
+        .prepare(
+            "SELECT COALESCE(date(sp.paid_at), sp.paid_at), sp.amount, sp.payment_method, sp.note, po.po_number, p.name
+             FROM supplier_payments sp
+             LEFT JOIN purchase_orders po ON sp.po_id = po.id
+             LEFT JOIN products p ON sp.product_id = p.id
+             WHERE sp.supplier_id = ?1
+             ORDER BY sp.paid_at, sp.id",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let pay_iter = pay_stmt
+        .query_map([supplier_id], |row| {
+            let payment_method: Option<String> = row.get(2)?;
+            let note: Option<String> = row.get(3)?;
+            let po_number: Option<String> = row.get(4)?;
+            let product_name: Option<String> = row.get(5)?;
+
+            let mut description = match (&po_number, &product_name) {
+                (Some(po), _) => format!("Payment for {}", po),
+                (None, Some(name)) => format!("Payment for {}", name),
+                (None, None) => "Payment".to_string(),
+            };
+            if let Some(n) = note.filter(|n| !n.is_empty()) {
+                description = format!("{} - {}", description, n);
+            }
+
+            Ok(SupplierLedgerEntry {
+                date: row.get(0)?,
+                entry_type: "payment".to_string(),
+                reference: po_number.or(payment_method),
+                description,
+                debit: 0.0,
+                credit: row.get(1)?,
+                balance: 0.0,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    for entry in pay_iter {
+        entries.push(entry.map_err(|e| e.to_string())?);
+    }
+
+    // Chronological order; on the same day debits come before credits
+    entries.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| b.debit.total_cmp(&a.debit)));
+
+    let mut opening_balance = pre_app_balance;
+    let mut in_range: Vec<SupplierLedgerEntry> = Vec::new();
+
+    for entry in entries {
+        if let Some(from) = from_date.as_deref() {
+            if entry.date.as_str() < from {
+                opening_balance += entry.debit - entry.credit;
+                continue;
+            }
+        }
+        if let Some(to) = to_date.as_deref() {
+            if entry.date.as_str() > to {
+                continue;
+            }
+        }
+        in_range.push(entry);
+    }
+
+    let mut balance = opening_balance;
+    let mut total_debit = 0.0;
+    let mut total_credit = 0.0;
+    for entry in in_range.iter_mut() {
+        balance += entry.debit - entry.credit;
+        total_debit += entry.debit;
+        total_credit += entry.credit;
+        entry.balance = (balance * 100.0).round() / 100.0;
+    }
+
+    Ok(SupplierLedger {
+        supplier_id,
+        opening_balance,
+        entries: in_range,
+        total_debit,
+        total_credit,
+        closing_balance: balance,
+    })
+}
+
+/// Get purchase history (items on received POs) for a specific product and supplier
 #[tauri::command]
 pub fn get_supplier_product_purchase_history(
     supplier_id: i32,
@@ -864,13 +1427,21 @@ pub fn get_supplier_product_purchase_history(
     );
 
     let conn = db.get_conn()?;
+    get_supplier_product_purchase_history_internal(&conn, supplier_id, product_id)
+}
 
+pub(crate) fn get_supplier_product_purchase_history_internal(
+    conn: &Connection,
+    supplier_id: i32,
+    product_id: i32,
+) -> Result<Vec<crate::db::models::PurchaseOrderItemWithProduct>, String> {
     let mut stmt = conn.prepare(
-        "SELECT poi.id, poi.po_id, poi.quantity, poi.unit_cost, poi.total_cost, poi.created_at, p.name, p.sku, po.po_number
+        "SELECT poi.id, poi.po_id, poi.quantity, poi.unit_cost * po.exchange_rate, poi.total_cost * po.exchange_rate,
+                poi.created_at, p.name, p.sku, po.po_number
          FROM purchase_order_items poi
          JOIN purchase_orders po ON po.id = poi.po_id
          JOIN products p ON poi.product_id = p.id
-         WHERE poi.product_id = ?1 AND po.supplier_id = ?2
+         WHERE poi.product_id = ?1 AND po.supplier_id = ?2 AND po.status = 'received'
          ORDER BY poi.created_at DESC"
     ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
@@ -889,13 +1460,14 @@ pub fn get_supplier_product_purchase_history(
             sold_revenue: None,
             created_at: row.get(5)?,
             po_number: row.get(8)?,
+            is_return: false,
         })
     }).map_err(|e| format!("Failed to query PO items: {}", e))?
     .collect::<Result<Vec<_>, _>>()
     .map_err(|e| format!("Failed to collect PO items: {}", e))?;
 
     // Check for Initial Stock (if this supplier is the source)
-    let initial_stock_res: Option<(i32, f64, String, String, String)> = conn.query_row(
+    let initial_stock_res: Option<(f64, f64, String, String, String)> = conn.query_row(
         "SELECT COALESCE(initial_stock, 0), price, created_at, name, sku FROM products WHERE id = ?1 AND supplier_id = ?2",
         [product_id, supplier_id],
         |row| Ok((
@@ -908,7 +1480,7 @@ pub fn get_supplier_product_purchase_history(
     ).optional().map_err(|e| format!("Failed to fetch initial stock: {}", e))?;
 
     if let Some((stock, price, date, name, sku)) = initial_stock_res {
-        if stock > 0 {
+        if stock > 0.0 {
              items.push(crate::db::models::PurchaseOrderItemWithProduct {
                  id: 0, // Mock ID for initial stock
                  po_id: None,
@@ -917,12 +1489,13 @@ pub fn get_supplier_product_purchase_history(
                  sku,
                  quantity: stock,
                  unit_cost: price,
-                 total_cost: (stock as f64 * price),
+                 total_cost: money::line_total(price, stock),
                  selling_price: None,
                  quantity_sold: None,
                  sold_revenue: None,
                  created_at: date,
                  po_number: None,
+                 is_return: false,
              });
         }
     }
@@ -930,6 +1503,209 @@ pub fn get_supplier_product_purchase_history(
     Ok(items)
 }
 
+/// One purchase order in a supplier report, in the base currency
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierReportPurchaseOrder {
+    pub id: i32,
+    pub po_number: String,
+    pub order_date: String,
+    pub status: String,
+    pub total_amount: f64,
+    /// Payments against the PO plus allocations to its lines from payments made elsewhere
+    pub total_paid: f64,
+    pub total_pending: f64,
+}
+
+/// A product bought from the supplier, with the quantities and value attributed to them as
+/// in get_products_by_supplier
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierReportProduct {
+    pub product_id: i32,
+    pub name: String,
+    pub sku: String,
+    pub unit: String,
+    /// Received PO quantity plus initial stock when this is the primary supplier
+    pub purchased_quantity: f64,
+    pub purchased_value: f64,
+    /// Paid for this product, as get_supplier_payment_summary: direct payments, its share of
+    /// PO-level payments and explicit allocations
+    pub amount_paid: f64,
+    /// Units still in batches bought from this supplier
+    pub current_stock: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierReportStats {
+    /// Received POs plus initial stock of products this is the primary supplier of
+    pub total_purchased: f64,
+    /// Returns set off against what is owed
+    pub total_returned: f64,
+    pub total_paid: f64,
+    /// Opening balance + purchased - returned - paid, never below zero
+    pub pending_amount: f64,
+    pub purchase_order_count: i32,
+    /// Mean gap between consecutive non-cancelled POs; None with fewer than two
+    pub average_days_between_orders: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SupplierReport {
+    pub supplier: Supplier,
+    /// Newest first
+    pub purchase_orders: Vec<SupplierReportPurchaseOrder>,
+    /// Direct, product and PO-linked payments, newest first
+    pub payments: Vec<SupplierPayment>,
+    pub products: Vec<SupplierReportProduct>,
+    pub stats: SupplierReportStats,
+}
+
+/// Get detailed report for a single supplier by ID: POs with their payment status, payments,
+/// products supplied and headline totals. Purchase and payment totals follow get_supplier_ledger.
+#[tauri::command]
+pub fn get_supplier_report(supplier_id: i32, db: State<Database>) -> Result<SupplierReport, String> {
+    log::info!("get_supplier_report called with supplier_id: {}", supplier_id);
+
+    let conn = db.get_conn()?;
+    let report = get_supplier_report_internal(&conn, supplier_id)?;
+
+    log::info!("Returning report for supplier id: {}", supplier_id);
+    Ok(report)
+}
+
+pub(crate) fn get_supplier_report_internal(conn: &Connection, supplier_id: i32) -> Result<SupplierReport, String> {
+    let supplier = get_supplier_internal(conn, supplier_id)?;
+
+    let mut po_stmt = conn
+        .prepare(&format!(
+            "SELECT po.id, po.po_number, po.order_date, po.status, po.total_amount, ({})
+             FROM purchase_orders po
+             WHERE po.supplier_id = ?1
+             ORDER BY po.order_date DESC, po.id DESC",
+            PO_TOTAL_PAID_SQL.replace("?1", "po.id")
+        ))
+        .map_err(|e| e.to_string())?;
+    let purchase_orders = po_stmt
+        .query_map([supplier_id], |row| {
+            let total_amount: f64 = row.get(4)?;
+            let total_paid = money::round_money(row.get(5)?);
+            Ok(SupplierReportPurchaseOrder {
+                id: row.get(0)?,
+                po_number: row.get(1)?,
+                order_date: row.get(2)?,
+                status: row.get(3)?,
+                total_amount,
+                total_paid,
+                total_pending: money::sub(total_amount, total_paid).max(0.0),
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load purchase orders: {}", e))?;
+
+    let mut pay_stmt = conn
+        .prepare(
+            "SELECT sp.id, sp.supplier_id, sp.product_id, sp.po_id, po.po_number, sp.amount, sp.payment_method, sp.note, sp.paid_at, sp.created_at
+             FROM supplier_payments sp
+             LEFT JOIN purchase_orders po ON sp.po_id = po.id
+             WHERE sp.supplier_id = ?1
+             ORDER BY sp.paid_at DESC, sp.id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let payments = pay_stmt
+        .query_map([supplier_id], |row| {
+            Ok(SupplierPayment {
+                id: row.get(0)?,
+                supplier_id: row.get(1)?,
+                product_id: row.get(2)?,
+                po_id: row.get(3)?,
+                po_number: row.get(4)?,
+                amount: row.get(5)?,
+                payment_method: row.get(6)?,
+                note: row.get(7)?,
+                paid_at: row.get(8)?,
+                created_at: row.get(9)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load supplier payments: {}", e))?;
+
+    let products: Vec<SupplierReportProduct> = get_products_by_supplier_internal(conn, supplier_id)?
+        .into_iter()
+        .map(|product| {
+            let amount_paid = get_supplier_payment_summary_internal(conn, supplier_id, product.id)?.total_paid;
+            Ok(SupplierReportProduct {
+                product_id: product.id,
+                name: product.name,
+                sku: product.sku,
+                unit: product.unit,
+                purchased_quantity: product.total_purchased_quantity.unwrap_or(0.0),
+                purchased_value: money::round_money(product.total_purchased_cost.unwrap_or(0.0)),
+                amount_paid,
+                current_stock: product.stock_quantity,
+            })
+        })
+        .collect::<Result<_, String>>()?;
+
+    // Same debits as the ledger: received PO lines plus initial stock of primary-supplier products
+    let total_purchased: f64 = conn
+        .query_row(
+            "SELECT COALESCE((SELECT SUM(poi.quantity * poi.unit_cost * po.exchange_rate)
+                              FROM purchase_order_items poi
+                              JOIN purchase_orders po ON po.id = poi.po_id
+                              WHERE po.supplier_id = ?1 AND po.status = 'received'), 0)
+                  + COALESCE((SELECT SUM(initial_stock * price) FROM products
+                              WHERE supplier_id = ?1 AND COALESCE(initial_stock, 0) > 0), 0)",
+            [supplier_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to total purchases: {}", e))?;
+    let total_returned: f64 = conn
+        .query_row(
+            "SELECT COALESCE(SUM(pri.total_cost), 0)
+             FROM purchase_return_items pri
+             JOIN purchase_returns pr ON pr.id = pri.return_id
+             WHERE pr.supplier_id = ?1 AND pr.reduces_payable = 1",
+            [supplier_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to total purchase returns: {}", e))?;
+    let total_paid = money::sum(payments.iter().map(|payment| payment.amount));
+
+    let total_purchased = money::round_money(total_purchased);
+    let total_returned = money::round_money(total_returned);
+    let owed = money::sum([supplier.opening_balance.unwrap_or(0.0), total_purchased]);
+    let pending_amount = money::sub(money::sub(owed, total_returned), total_paid).max(0.0);
+
+    let order_days: Vec<NaiveDate> = purchase_orders
+        .iter()
+        .filter(|po| po.status != "cancelled")
+        .filter_map(|po| po.order_date.get(..10).and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()))
+        .collect();
+    let average_days_between_orders = match (order_days.iter().min(), order_days.iter().max()) {
+        (Some(first), Some(last)) if order_days.len() > 1 => {
+            let span = (*last - *first).num_days() as f64;
+            Some((span / (order_days.len() - 1) as f64 * 10.0).round() / 10.0)
+        }
+        _ => None,
+    };
+
+    Ok(SupplierReport {
+        stats: SupplierReportStats {
+            total_purchased,
+            total_returned,
+            total_paid,
+            pending_amount,
+            purchase_order_count: purchase_orders.len() as i32,
+            average_days_between_orders,
+        },
+        supplier,
+        purchase_orders,
+        payments,
+        products,
+    })
+}
+
 /// Add mock supplier data for testing
 #[tauri::command]
 pub fn add_mock_suppliers(db: State<Database>) -> Result<String, String> {
@@ -967,3 +1743,244 @@ pub fn add_mock_suppliers(db: State<Database>) -> Result<String, String> {
     log::info!("Added {} mock suppliers", inserted);
     Ok(format!("Successfully added {} mock suppliers", inserted))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::products::{create_product_internal, CreateProductInput};
+    use crate::commands::purchase_orders::{add_payment_to_purchase_order_internal, create_purchase_order_internal};
+    use crate::db::{CreatePurchaseOrderInput, PurchaseOrderItemInput};
+    use crate::test_support::{insert_supplier, TestDb};
+
+    fn product(conn: &Connection, sku: &str) -> i32 {
+        create_product_internal(
+            conn,
+            CreateProductInput {
+                name: sku.to_string(),
+                sku: sku.to_string(),
+                price: 0.0,
+                selling_price: None,
+                stock_quantity: 0.0,
+                supplier_id: None,
+                amount_paid: None,
+                category: None,
+                unit: None,
+                allow_fractional: false,
+            },
+        )
+        .unwrap()
+        .id
+    }
+
+    fn purchase_order(conn: &Connection, supplier_id: i32, order_date: String, items: &[(i32, i32, f64)]) -> i32 {
+        create_purchase_order_internal(
+            conn,
+            CreatePurchaseOrderInput {
+                supplier_id,
+                items: items
+                    .iter()
+                    .map(|&(product_id, quantity, unit_cost)| PurchaseOrderItemInput { product_id, quantity: quantity as f64, unit_cost })
+                    .collect(),
+                order_date: Some(order_date),
+                expected_delivery_date: None,
+                notes: None,
+                initial_payment: None,
+                currency: None,
+                exchange_rate: None,
+            },
+        )
+        .unwrap()
+        .id
+    }
+
+    fn po_item(conn: &Connection, po_id: i32, product_id: i32) -> i32 {
+        conn.query_row(
+            "SELECT id FROM purchase_order_items WHERE po_id = ?1 AND product_id = ?2",
+            [po_id, product_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_allocated_payments_and_aging() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme Traders");
+        let kettle = product(&conn, "KETTLE");
+        let toaster = product(&conn, "TOASTER");
+
+        let today = Utc::now().date_naive();
+        let old_po = purchase_order(
+            &conn,
+            supplier_id,
+            (today - chrono::Duration::days(100)).to_string(),
+            &[(kettle, 10, 100.0), (toaster, 10, 50.0)],
+        );
+        let new_po = purchase_order(&conn, supplier_id, today.to_string(), &[(kettle, 5, 100.0)]);
+        let toaster_line = po_item(&conn, old_po, toaster);
+
+        // Pay off the toaster line alone instead of a third of each line
+        let allocation = |po_item_id, amount| Some(vec![PaymentAllocationInput { po_item_id, amount }]);
+        add_payment_to_purchase_order_internal(&mut conn, old_po, 500.0, None, None, None, allocation(toaster_line, 500.0))
+            .unwrap();
+        let toaster_summary = get_supplier_payment_summary_internal(&conn, supplier_id, toaster).unwrap();
+        assert_eq!(toaster_summary.total_paid, 500.0);
+        assert_eq!(toaster_summary.pending_amount, 0.0);
+        assert_eq!(get_supplier_payment_summary_internal(&conn, supplier_id, kettle).unwrap().total_paid, 0.0);
+
+        // Allocations must fit the line's remaining balance and add up to the payment
+        let kettle_line = po_item(&conn, old_po, kettle);
+        assert!(add_payment_to_purchase_order_internal(&mut conn, old_po, 10.0, None, None, None, allocation(toaster_line, 10.0))
+            .is_err());
+        assert!(add_payment_to_purchase_order_internal(&mut conn, old_po, 300.0, None, None, None, allocation(kettle_line, 200.0))
+            .is_err());
+
+        // A supplier payment allocated to one PO line is recorded against that PO
+        let payment = create_supplier_payment_internal(
+            &conn,
+            CreateSupplierPaymentInput {
+                supplier_id,
+                product_id: None,
+                amount: 200.0,
+                payment_method: None,
+                note: None,
+                paid_at: None,
+                allocations: Some(vec![PaymentAllocationInput { po_item_id: po_item(&conn, new_po, kettle), amount: 200.0 }]),
+            },
+        )
+        .unwrap();
+        assert_eq!(payment.po_id, Some(new_po));
+        assert_eq!(get_all_product_payment_summary_internal(&conn, kettle).unwrap().pending_amount, 1300.0);
+
+        let aging = get_supplier_payables_aging_internal(&conn).unwrap();
+        assert_eq!(aging.len(), 1);
+        assert_eq!(aging[0].days_over_90, 1000.0);
+        assert_eq!(aging[0].days_0_30, 300.0);
+        assert_eq!(aging[0].total, 1300.0);
+    }
+
+    #[test]
+    fn test_purchase_totals_count_received_pos_only() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme Traders");
+        let kettle = product(&conn, "KETTLE");
+        let today = Utc::now().date_naive().to_string();
+
+        let received = purchase_order(&conn, supplier_id, today.clone(), &[(kettle, 10, 100.0)]);
+        let cancelled = purchase_order(&conn, supplier_id, today.clone(), &[(kettle, 4, 100.0)]);
+        let ordered = purchase_order(&conn, supplier_id, today.clone(), &[(kettle, 3, 100.0)]);
+        conn.execute("UPDATE purchase_orders SET status = 'cancelled' WHERE id = ?1", [cancelled]).unwrap();
+        conn.execute("UPDATE purchase_orders SET status = 'partially_received' WHERE id = ?1", [ordered]).unwrap();
+        conn.execute("UPDATE purchase_order_items SET received_quantity = 1 WHERE po_id = ?1", [ordered]).unwrap();
+
+        // The product list, the product page, the supplier screens and the dashboard all agree
+        let listed: f64 = conn
+            .query_row(
+                &format!("SELECT {} FROM products p WHERE p.id = ?1", crate::commands::products::TOTAL_PURCHASED_COST_SQL),
+                [kettle],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(listed, 1000.0);
+
+        let summary = crate::commands::purchase_orders::get_product_purchase_summary_internal(&conn, kettle).unwrap();
+        assert_eq!((summary.total_quantity, summary.total_value, summary.purchase_orders), (10.0, 1000.0, 1));
+        assert_eq!((summary.on_order_quantity, summary.on_order_value), (2.0, 200.0));
+
+        assert_eq!(get_supplier_payment_summary_internal(&conn, supplier_id, kettle).unwrap().total_payable, 1000.0);
+        assert_eq!(get_all_product_payment_summary_internal(&conn, kettle).unwrap().total_payable, 1000.0);
+        let analytics = crate::commands::analytics::get_purchase_analytics_internal(&conn, &today, &today).unwrap();
+        assert_eq!(analytics.total_purchases, 1000.0);
+
+        let history = get_supplier_product_purchase_history_internal(&conn, supplier_id, kettle).unwrap();
+        assert_eq!(history.iter().map(|item| item.po_id).collect::<Vec<_>>(), vec![Some(received)]);
+    }
+
+    #[test]
+    fn test_po_payment_shares_agree_across_screens() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme Traders");
+        let products = [product(&conn, "KETTLE"), product(&conn, "MUG"), product(&conn, "TRAY")];
+        let po_id = purchase_order(
+            &conn,
+            supplier_id,
+            Utc::now().date_naive().to_string(),
+            &[(products[0], 1, 100.0), (products[1], 1, 100.0), (products[2], 1, 100.0)],
+        );
+        // A PO-level payment with no allocations, as recorded before allocations existed
+        conn.execute(
+            "INSERT INTO supplier_payments (supplier_id, product_id, po_id, amount, paid_at) VALUES (?1, NULL, ?2, 100.0, datetime('now'))",
+            (supplier_id, po_id),
+        )
+        .unwrap();
+
+        let mut listed = Vec::new();
+        for &product_id in &products {
+            let payments = get_supplier_payments_internal(&conn, supplier_id, product_id).unwrap();
+            let paid = money::sum(payments.iter().map(|payment| payment.amount));
+            assert_eq!(get_supplier_payment_summary_internal(&conn, supplier_id, product_id).unwrap().total_paid, paid);
+            assert_eq!(get_all_product_payment_summary_internal(&conn, product_id).unwrap().total_paid, paid);
+            listed.push(paid);
+        }
+        assert_eq!(listed, vec![33.34, 33.33, 33.33]);
+        assert_eq!(money::sum(listed), 100.0);
+
+        let report = get_supplier_report_internal(&conn, supplier_id).unwrap();
+        assert_eq!(money::sum(report.products.iter().map(|p| p.amount_paid)), report.stats.total_paid);
+    }
+
+    #[test]
+    fn test_supplier_report_brings_pos_payments_and_products_together() {
+        let db = TestDb::new();
+        let mut conn = db.conn();
+        let supplier_id = insert_supplier(&conn, "Acme Traders");
+        let kettle = product(&conn, "KETTLE");
+        let mug = create_product_internal(
+            &conn,
+            CreateProductInput {
+                name: "MUG".to_string(),
+                sku: "MUG".to_string(),
+                price: 20.0,
+                selling_price: None,
+                stock_quantity: 5.0,
+                supplier_id: Some(supplier_id),
+                amount_paid: None,
+                category: None,
+                unit: None,
+                allow_fractional: false,
+            },
+        )
+        .unwrap()
+        .id;
+
+        let today = Utc::now().date_naive();
+        let old_po = purchase_order(&conn, supplier_id, (today - chrono::Duration::days(10)).to_string(), &[(kettle, 10, 100.0)]);
+        let new_po = purchase_order(&conn, supplier_id, today.to_string(), &[(kettle, 5, 100.0)]);
+        add_payment_to_purchase_order_internal(&mut conn, old_po, 300.0, None, None, None, None).unwrap();
+
+        let report = get_supplier_report_internal(&conn, supplier_id).unwrap();
+        assert_eq!(report.supplier.name, "Acme Traders");
+        let pos: Vec<(i32, f64, f64)> = report.purchase_orders.iter().map(|po| (po.id, po.total_paid, po.total_pending)).collect();
+        assert_eq!(pos, vec![(new_po, 0.0, 500.0), (old_po, 300.0, 700.0)]);
+        assert_eq!(report.payments.len(), 1);
+        assert_eq!(report.payments[0].po_id, Some(old_po));
+
+        let products: Vec<(i32, f64, f64, f64)> = report
+            .products
+            .iter()
+            .map(|p| (p.product_id, p.purchased_quantity, p.purchased_value, p.amount_paid))
+            .collect();
+        assert_eq!(products, vec![(kettle, 15.0, 1500.0, 300.0), (mug, 5.0, 100.0, 0.0)]);
+
+        assert_eq!(report.stats.total_purchased, 1600.0);
+        assert_eq!(report.stats.total_paid, 300.0);
+        assert_eq!(report.stats.pending_amount, 1300.0);
+        assert_eq!(report.stats.purchase_order_count, 2);
+        assert_eq!(report.stats.average_days_between_orders, Some(10.0));
+
+        assert!(get_supplier_report_internal(&conn, 9999).is_err());
+    }
+}
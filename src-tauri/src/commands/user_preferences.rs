@@ -0,0 +1,346 @@
+/// User Preferences
+/// Per-user UI state (column layouts, saved filters, page sizes) kept in the database so it
+/// follows the user to any machine. Values are opaque JSON to the backend, checked only for being
+/// valid and at most MAX_PREFERENCE_BYTES. Preferences are deleted with their user and are left
+/// out of the settings export unless asked for.
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::db::Database;
+
+/// Largest stored value, in bytes of JSON text
+pub const MAX_PREFERENCE_BYTES: usize = 64 * 1024;
+
+const MAX_KEY_LENGTH: usize = 100;
+
+/// Key the preferences are stored under in an exported settings file:
+/// { "user_preferences": { "<username>": { "<key>": <value>, ... }, ... } }
+pub const USER_PREFERENCES_EXPORT_KEY: &str = "user_preferences";
+
+fn validate_key(key: &str) -> Result<&str, String> {
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("Preference key is required".to_string());
+    }
+    if key.chars().count() > MAX_KEY_LENGTH {
+        return Err(format!("Preference key must be at most {} characters", MAX_KEY_LENGTH));
+    }
+    if key.chars().any(char::is_control) {
+        return Err("Preference key must not contain control characters".to_string());
+    }
+    Ok(key)
+}
+
+/// Parse the value and return it in compact form, ready to store
+fn validate_value(key: &str, json_value: &str) -> Result<String, String> {
+    let value: serde_json::Value = serde_json::from_str(json_value)
+        .map_err(|e| format!("Preference '{}' is not valid JSON: {}", key, e))?;
+    let stored = value.to_string();
+    if stored.len() > MAX_PREFERENCE_BYTES {
+        return Err(format!(
+            "Preference '{}' is {} bytes; the limit is {} KB",
+            key,
+            stored.len(),
+            MAX_PREFERENCE_BYTES / 1024
+        ));
+    }
+    Ok(stored)
+}
+
+/// Id of the user with this username (case-insensitive), if there is one
+fn find_user_id(conn: &Connection, username: &str) -> Result<Option<i32>, String> {
+    conn.query_row(
+        "SELECT id FROM users WHERE LOWER(username) = LOWER(?1)",
+        [username.trim()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Failed to look up user: {}", e))
+}
+
+fn user_id(conn: &Connection, username: &str) -> Result<i32, String> {
+    find_user_id(conn, username)?.ok_or_else(|| format!("User '{}' not found", username))
+}
+
+fn parse_stored(key: &str, value: &str) -> serde_json::Value {
+    serde_json::from_str(value).unwrap_or_else(|e| {
+        log::warn!("Stored preference '{}' is not valid JSON ({}); returning it as a string", key, e);
+        serde_json::Value::String(value.to_string())
+    })
+}
+
+/// Get one preference for a user; None when it has never been set
+#[tauri::command]
+pub fn get_user_preference(
+    username: String,
+    key: String,
+    db: State<Database>,
+) -> Result<Option<serde_json::Value>, String> {
+    log::info!("get_user_preference called for user: {}, key: {}", username, key);
+
+    let conn = db.get_conn()?;
+    get_user_preference_internal(&conn, &username, &key)
+}
+
+pub(crate) fn get_user_preference_internal(
+    conn: &Connection,
+    username: &str,
+    key: &str,
+) -> Result<Option<serde_json::Value>, String> {
+    let key = validate_key(key)?;
+    let user_id = user_id(conn, username)?;
+
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM user_preferences WHERE user_id = ?1 AND key = ?2",
+            params![user_id, key],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to get preference: {}", e))?;
+
+    Ok(value.map(|value| parse_stored(key, &value)))
+}
+
+/// Save a preference for a user; `json_value` is the JSON text of the value
+#[tauri::command]
+pub fn set_user_preference(
+    username: String,
+    key: String,
+    json_value: String,
+    db: State<Database>,
+) -> Result<(), String> {
+    log::info!("set_user_preference called for user: {}, key: {}", username, key);
+
+    let conn = db.get_conn()?;
+    set_user_preference_internal(&conn, &username, &key, &json_value)
+}
+
+pub(crate) fn set_user_preference_internal(
+    conn: &Connection,
+    username: &str,
+    key: &str,
+    json_value: &str,
+) -> Result<(), String> {
+    let key = validate_key(key)?;
+    let stored = validate_value(key, json_value)?;
+    let user_id = user_id(conn, username)?;
+
+    save_preference(conn, user_id, key, &stored)
+}
+
+fn save_preference(conn: &Connection, user_id: i32, key: &str, stored: &str) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO user_preferences (user_id, key, value, updated_at) VALUES (?1, ?2, ?3, datetime('now'))
+         ON CONFLICT(user_id, key) DO UPDATE SET value = ?3, updated_at = datetime('now')",
+        params![user_id, key, stored],
+    )
+    .map_err(|e| format!("Failed to save preference '{}': {}", key, e))?;
+    Ok(())
+}
+
+/// Every preference a user has set, loaded once at app start
+#[tauri::command]
+pub fn get_all_user_preferences(
+    username: String,
+    db: State<Database>,
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    log::info!("get_all_user_preferences called for user: {}", username);
+
+    let conn = db.get_conn()?;
+    get_all_user_preferences_internal(&conn, &username)
+}
+
+pub(crate) fn get_all_user_preferences_internal(
+    conn: &Connection,
+    username: &str,
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    let user_id = user_id(conn, username)?;
+
+    let mut stmt = conn
+        .prepare("SELECT key, value FROM user_preferences WHERE user_id = ?1")
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    let rows = stmt
+        .query_map([user_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| format!("Failed to query preferences: {}", e))?;
+
+    let mut preferences = HashMap::new();
+    for row in rows {
+        let (key, value) = row.map_err(|e| format!("Failed to read preference: {}", e))?;
+        let value = parse_stored(&key, &value);
+        preferences.insert(key, value);
+    }
+
+    Ok(preferences)
+}
+
+/// All users' preferences for the settings export, as { username: { key: value } }
+pub(crate) fn export_user_preferences(conn: &Connection) -> Result<serde_json::Value, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT u.username, p.key, p.value
+             FROM user_preferences p
+             JOIN users u ON u.id = p.user_id
+             ORDER BY u.username, p.key",
+        )
+        .map_err(|e| format!("Failed to prepare query: {}", e))?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?))
+        })
+        .map_err(|e| format!("Failed to query preferences: {}", e))?;
+
+    let mut by_user = serde_json::Map::new();
+    for row in rows {
+        let (username, key, value) = row.map_err(|e| format!("Failed to read preference: {}", e))?;
+        let value = parse_stored(&key, &value);
+        if let serde_json::Value::Object(preferences) = by_user
+            .entry(username)
+            .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()))
+        {
+            preferences.insert(key, value);
+        }
+    }
+
+    Ok(serde_json::Value::Object(by_user))
+}
+
+/// One preference read from a settings file, validated and ready to store
+#[derive(Debug)]
+pub(crate) struct ImportedPreference {
+    pub username: String,
+    pub key: String,
+    pub value: String,
+}
+
+/// Validate the user_preferences section of a settings file
+pub(crate) fn parse_exported_preferences(section: &serde_json::Value) -> Result<Vec<ImportedPreference>, String> {
+    let users = section
+        .as_object()
+        .ok_or_else(|| "expected an object of users".to_string())?;
+
+    let mut preferences = Vec::new();
+    for (username, values) in users {
+        let values = values
+            .as_object()
+            .ok_or_else(|| format!("preferences for '{}' must be an object", username))?;
+        for (key, value) in values {
+            let key = validate_key(key)?;
+            let value = validate_value(key, &value.to_string())?;
+            preferences.push(ImportedPreference { username: username.clone(), key: key.to_string(), value });
+        }
+    }
+
+    Ok(preferences)
+}
+
+/// Store imported preferences for the users that exist here; returns (imported, skipped)
+pub(crate) fn import_user_preferences(
+    conn: &Connection,
+    preferences: &[ImportedPreference],
+) -> Result<(usize, usize), String> {
+    let mut imported = 0;
+    let mut skipped = 0;
+    for preference in preferences {
+        match find_user_id(conn, &preference.username)? {
+            Some(user_id) => {
+                save_preference(conn, user_id, &preference.key, &preference.value)?;
+                imported += 1;
+            }
+            None => skipped += 1,
+        }
+    }
+    Ok((imported, skipped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    fn insert_user(conn: &Connection, username: &str) -> i32 {
+        conn.execute(
+            "INSERT INTO users (username, password, role, permissions) VALUES (?1, 'pw', 'user', '[]')",
+            [username],
+        )
+        .unwrap();
+        conn.last_insert_rowid() as i32
+    }
+
+    #[test]
+    fn test_set_and_get_preferences() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        insert_user(&conn, "cashier");
+
+        assert_eq!(get_user_preference_internal(&conn, "cashier", "invoices.columns").unwrap(), None);
+
+        set_user_preference_internal(&conn, "cashier", "invoices.columns", r#"["number", "total"]"#).unwrap();
+        set_user_preference_internal(&conn, "Cashier", " invoices.columns ", r#"["number"]"#).unwrap();
+        set_user_preference_internal(&conn, "cashier", "products.page_size", "50").unwrap();
+
+        assert_eq!(
+            get_user_preference_internal(&conn, "cashier", "invoices.columns").unwrap(),
+            Some(serde_json::json!(["number"]))
+        );
+        let all = get_all_user_preferences_internal(&conn, "cashier").unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all["products.page_size"], serde_json::json!(50));
+
+        assert!(get_all_user_preferences_internal(&conn, "nobody").is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_and_oversized_values() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        insert_user(&conn, "cashier");
+
+        assert!(set_user_preference_internal(&conn, "cashier", "layout", "{not json").is_err());
+        assert!(set_user_preference_internal(&conn, "cashier", "  ", "1").is_err());
+        assert!(set_user_preference_internal(&conn, "cashier", "bad\nkey", "1").is_err());
+
+        let oversized = serde_json::Value::String("x".repeat(MAX_PREFERENCE_BYTES)).to_string();
+        let err = set_user_preference_internal(&conn, "cashier", "layout", &oversized).unwrap_err();
+        assert!(err.contains("limit"));
+
+        let fits = serde_json::Value::String("x".repeat(MAX_PREFERENCE_BYTES - 2)).to_string();
+        set_user_preference_internal(&conn, "cashier", "layout", &fits).unwrap();
+    }
+
+    #[test]
+    fn test_preferences_are_deleted_with_the_user() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let user_id = insert_user(&conn, "cashier");
+        insert_user(&conn, "owner");
+        set_user_preference_internal(&conn, "cashier", "layout", "{}").unwrap();
+        set_user_preference_internal(&conn, "owner", "layout", "{}").unwrap();
+
+        conn.execute("DELETE FROM users WHERE id = ?1", [user_id]).unwrap();
+
+        let remaining: i64 = conn.query_row("SELECT COUNT(*) FROM user_preferences", [], |row| row.get(0)).unwrap();
+        assert_eq!(remaining, 1);
+    }
+
+    #[test]
+    fn test_export_and_import_round_trip() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        insert_user(&conn, "cashier");
+        set_user_preference_internal(&conn, "cashier", "layout", r#"{"density": "compact"}"#).unwrap();
+
+        let exported = export_user_preferences(&conn).unwrap();
+        assert_eq!(exported, serde_json::json!({ "cashier": { "layout": { "density": "compact" } } }));
+
+        conn.execute("DELETE FROM user_preferences", []).unwrap();
+        let mut section = exported.clone();
+        section["former_employee"] = serde_json::json!({ "layout": {} });
+        let parsed = parse_exported_preferences(&section).unwrap();
+        assert_eq!(import_user_preferences(&conn, &parsed).unwrap(), (1, 1));
+        assert_eq!(export_user_preferences(&conn).unwrap(), exported);
+
+        assert!(parse_exported_preferences(&serde_json::json!({ "cashier": [1, 2] })).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+/// Product Variants
+/// Sizes/colors of one product. Each variant is an ordinary product row with its own SKU, stock
+/// and price, linked to its parent through parent_product_id, so invoices, FIFO batches and
+/// purchase orders keep working on the concrete variant.
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::commands::bundles;
+use crate::commands::products::{create_product_internal, get_product_internal, CreateProductInput};
+use crate::db::{Database, Product};
+use crate::services::events::{emit_bulk_data_changed, DataEntity, DataOperation};
+use crate::services::{money, quantity};
+
+/// SQL expression for a product's display label: "Parent — L / Blue" for variants and the plain
+/// name otherwise. Expects the product table aliased as `p`.
+pub(crate) const DISPLAY_NAME_SQL: &str = "COALESCE((
+        SELECT parent.name || ' — ' || (SELECT group_concat(attr.value, ' / ') FROM json_each(p.variant_attributes) attr)
+        FROM products parent WHERE parent.id = p.parent_product_id
+    ), p.name)";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VariantAttribute {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VariantInput {
+    /// In display order, e.g. size then color
+    pub attributes: Vec<VariantAttribute>,
+    /// Defaults to the parent SKU followed by the attribute values, e.g. SHIRT-L-BLUE
+    pub sku: Option<String>,
+    /// Cost price; defaults to the parent's
+    pub price: Option<f64>,
+    /// Defaults to the parent's selling price
+    pub selling_price: Option<f64>,
+    /// Opening stock, recorded as a FIFO batch like any new product
+    pub stock_quantity: Option<f64>,
+}
+
+/// Number of variants under a product
+pub(crate) fn variant_count(conn: &Connection, product_id: i32) -> Result<i64, String> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM products WHERE parent_product_id = ?1",
+        [product_id],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Failed to count variants: {}", e))
+}
+
+/// Fold a parent's variants into its stock and sales figures for the rolled-up product list
+pub(crate) fn roll_up(conn: &Connection, product: &mut Product) -> Result<(), String> {
+    let (count, stock, sold, sold_amount): (i64, f64, f64, f64) = conn
+        .query_row(
+            "SELECT COUNT(*),
+                    COALESCE(SUM(v.stock_quantity), 0),
+                    COALESCE(SUM((SELECT SUM(ii.quantity) FROM invoice_items ii WHERE ii.product_id = v.id)), 0),
+                    COALESCE(SUM((SELECT SUM(ii.quantity * ii.unit_price - COALESCE(ii.discount_amount, 0))
+                                   FROM invoice_items ii WHERE ii.product_id = v.id)), 0)
+             FROM products v
+             WHERE v.parent_product_id = ?1",
+            [product.id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(|e| format!("Failed to roll up variants: {}", e))?;
+
+    if count == 0 {
+        return Ok(());
+    }
+
+    product.variant_count = Some(count);
+    product.stock_quantity = quantity::sum([product.stock_quantity, stock]);
+    let total_sold = quantity::sum([product.total_sold.unwrap_or(0.0), sold]);
+    product.total_sold = (total_sold > 0.0).then_some(total_sold);
+    let total_sold_amount = money::sum([product.total_sold_amount.unwrap_or(0.0), sold_amount]);
+    product.total_sold_amount = (total_sold_amount > 0.0).then_some(total_sold_amount);
+    Ok(())
+}
+
+/// Serialize attributes as a JSON object, keeping the given order so labels read "L / Blue"
+fn attributes_json(attributes: &[VariantAttribute]) -> Result<String, String> {
+    if attributes.is_empty() {
+        return Err("A variant needs at least one attribute".to_string());
+    }
+
+    let mut seen: Vec<String> = Vec::new();
+    let mut fields = Vec::new();
+    for attribute in attributes {
+        let name = attribute.name.trim();
+        let value = attribute.value.trim();
+        if name.is_empty() || value.is_empty() {
+            return Err("Variant attribute names and values cannot be empty".to_string());
+        }
+        if seen.contains(&name.to_lowercase()) {
+            return Err(format!("Variant attribute '{}' is given more than once", name));
+        }
+        seen.push(name.to_lowercase());
+
+        let name = serde_json::to_string(name).map_err(|e| e.to_string())?;
+        let value = serde_json::to_string(value).map_err(|e| e.to_string())?;
+        fields.push(format!("{}:{}", name, value));
+    }
+
+    Ok(format!("{{{}}}", fields.join(",")))
+}
+
+/// Order-insensitive identity of an attribute set, for duplicate checks
+fn combination_key(attributes_json: &str) -> Vec<(String, String)> {
+    let map: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(attributes_json).unwrap_or_default();
+    let mut key: Vec<(String, String)> = map
+        .into_iter()
+        .map(|(name, value)| {
+            let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+            (name.to_lowercase(), value.to_lowercase())
+        })
+        .collect();
+    key.sort();
+    key
+}
+
+fn default_sku(parent_sku: &str, attributes: &[VariantAttribute]) -> String {
+    let suffix: Vec<String> = attributes
+        .iter()
+        .map(|a| {
+            a.value
+                .trim()
+                .chars()
+                .map(|c| if c.is_alphanumeric() { c.to_ascii_uppercase() } else { '-' })
+                .collect()
+        })
+        .collect();
+    format!("{}-{}", parent_sku, suffix.join("-"))
+}
+
+/// Create variants of a product. Each variant inherits the parent's supplier and category, and
+/// its price unless overridden. All variants are created or none are.
+#[tauri::command]
+pub fn create_product_variants(
+    parent_id: i32,
+    variants: Vec<VariantInput>,
+    app: AppHandle,
+    db: State<Database>,
+) -> Result<Vec<Product>, String> {
+    log::info!("create_product_variants called for parent_id: {} with {} variant(s)", parent_id, variants.len());
+
+    let conn = db.get_conn()?;
+    let created = create_product_variants_internal(&conn, parent_id, variants)?;
+    emit_bulk_data_changed(&app, DataEntity::Product, DataOperation::Created, created.len());
+    Ok(created)
+}
+
+pub(crate) fn create_product_variants_internal(
+    conn: &Connection,
+    parent_id: i32,
+    variants: Vec<VariantInput>,
+) -> Result<Vec<Product>, String> {
+    if variants.is_empty() {
+        return Err("No variants given".to_string());
+    }
+
+    let parent = get_product_internal(conn, parent_id)?;
+    if parent.parent_product_id.is_some() {
+        return Err(format!("'{}' is itself a variant; add variants to its parent instead", parent.name));
+    }
+    if bundles::is_bundle(conn, parent_id)? {
+        return Err("Bundles cannot have variants".to_string());
+    }
+
+    let mut existing: Vec<Vec<(String, String)>> = {
+        let mut stmt = conn
+            .prepare("SELECT variant_attributes FROM products WHERE parent_product_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([parent_id], |row| row.get::<_, Option<String>>(0))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        rows.into_iter().flatten().map(|json| combination_key(&json)).collect()
+    };
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut ids = Vec::new();
+    for variant in variants {
+        let attributes = attributes_json(&variant.attributes)?;
+        let label = variant
+            .attributes
+            .iter()
+            .map(|a| a.value.trim())
+            .collect::<Vec<_>>()
+            .join(" / ");
+
+        let key = combination_key(&attributes);
+        if existing.contains(&key) {
+            return Err(format!("Variant '{}' of '{}' already exists", label, parent.name));
+        }
+        existing.push(key);
+
+        let stock_quantity = variant.stock_quantity.unwrap_or(0.0);
+        if stock_quantity < 0.0 {
+            return Err(format!("Stock for variant '{}' cannot be negative", label));
+        }
+
+        let sku = variant
+            .sku
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| default_sku(&parent.sku, &variant.attributes));
+
+        let product = create_product_internal(
+            &tx,
+            CreateProductInput {
+                name: format!("{} — {}", parent.name, label),
+                sku,
+                price: variant.price.unwrap_or(parent.price),
+                selling_price: variant.selling_price.or(parent.selling_price),
+                stock_quantity,
+                supplier_id: parent.supplier_id,
+                amount_paid: None,
+                category: parent.category.clone(),
+                unit: Some(parent.unit.clone()),
+                allow_fractional: parent.allow_fractional,
+            },
+        )?;
+
+        tx.execute(
+            "UPDATE products SET parent_product_id = ?1, variant_attributes = ?2 WHERE id = ?3",
+            params![parent_id, attributes, product.id],
+        )
+        .map_err(|e| format!("Failed to link variant: {}", e))?;
+        ids.push(product.id);
+    }
+
+    tx.commit().map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    log::info!("Created {} variant(s) of product {}", ids.len(), parent_id);
+    ids.into_iter().map(|id| get_product_internal(conn, id)).collect()
+}
+
+/// Get the variants of a product, oldest first
+#[tauri::command]
+pub fn get_product_variants(parent_id: i32, db: State<Database>) -> Result<Vec<Product>, String> {
+    log::info!("get_product_variants called for parent_id: {}", parent_id);
+
+    let conn = db.get_conn()?;
+    get_product_variants_internal(&conn, parent_id)
+}
+
+pub(crate) fn get_product_variants_internal(conn: &Connection, parent_id: i32) -> Result<Vec<Product>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM products WHERE parent_product_id = ?1 ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([parent_id], |row| row.get::<_, i32>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    ids.into_iter().map(|id| get_product_internal(conn, id)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    fn attrs(pairs: &[(&str, &str)]) -> Vec<VariantAttribute> {
+        pairs
+            .iter()
+            .map(|(name, value)| VariantAttribute { name: name.to_string(), value: value.to_string() })
+            .collect()
+    }
+
+    fn variant(pairs: &[(&str, &str)], stock: i32) -> VariantInput {
+        VariantInput { attributes: attrs(pairs), sku: None, price: None, selling_price: Some(450.0), stock_quantity: Some(stock as f64) }
+    }
+
+    #[test]
+    fn test_variants_are_labelled_and_rolled_up() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let parent = create_product_internal(
+            &conn,
+            CreateProductInput {
+                name: "Oxford Shirt".to_string(),
+                sku: "SHIRT".to_string(),
+                price: 300.0,
+                selling_price: Some(500.0),
+                stock_quantity: 0.0,
+                supplier_id: None,
+                amount_paid: None,
+                category: Some("Apparel".to_string()),
+                unit: None,
+                allow_fractional: false,
+            },
+        )
+        .unwrap();
+
+        let created = create_product_variants_internal(
+            &conn,
+            parent.id,
+            vec![variant(&[("size", "L"), ("color", "Blue")], 4), variant(&[("size", "M"), ("color", "Blue")], 6)],
+        )
+        .unwrap();
+        assert_eq!(created.len(), 2);
+        assert_eq!(created[0].sku, "SHIRT-L-BLUE");
+        assert_eq!(created[0].display_name.as_deref(), Some("Oxford Shirt — L / Blue"));
+        assert_eq!(created[0].variant_attributes.as_deref(), Some(r#"{"size":"L","color":"Blue"}"#));
+        assert_eq!(created[0].category.as_deref(), Some("Apparel"));
+        assert_eq!(created[0].price, 300.0);
+        assert_eq!(created[0].stock_quantity, 4.0);
+
+        // Same combination in a different order is a duplicate
+        let err = create_product_variants_internal(&conn, parent.id, vec![variant(&[("color", "blue"), ("size", "l")], 1)])
+            .unwrap_err();
+        assert!(err.contains("already exists"));
+        assert!(create_product_variants_internal(&conn, created[0].id, vec![variant(&[("fit", "Slim")], 1)]).is_err());
+
+        // Renaming the parent relabels its variants
+        conn.execute("UPDATE products SET name = 'Oxford Shirt Classic' WHERE id = ?1", [parent.id]).unwrap();
+        let variants = get_product_variants_internal(&conn, parent.id).unwrap();
+        assert_eq!(variants.len(), 2);
+        assert_eq!(variants[1].display_name.as_deref(), Some("Oxford Shirt Classic — M / Blue"));
+
+        let mut rolled = get_product_internal(&conn, parent.id).unwrap();
+        roll_up(&conn, &mut rolled).unwrap();
+        assert_eq!(rolled.variant_count, Some(2));
+        assert_eq!(rolled.stock_quantity, 10.0);
+    }
+}
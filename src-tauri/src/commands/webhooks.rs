@@ -0,0 +1,433 @@
+/// Outbound Webhooks
+/// When webhook_url is set, the events selected in webhook_events (comma-separated; all of
+/// invoice.created, invoice.deleted and payment.created when unset) are queued in webhook_outbox
+/// once the command that produced them has committed. A background task POSTs them as JSON:
+///
+///   { "event": "invoice.created", "created_at": "...", "data": { ... } }
+///
+/// with X-Webhook-Event, X-Webhook-Delivery (the outbox id) and, when webhook_secret is set,
+/// X-Webhook-Signature: "sha256=<hex HMAC-SHA256 of the body>". Failed deliveries are retried
+/// with exponential backoff up to MAX_ATTEMPTS times, then marked failed until retried by hand.
+/// Queueing is one insert, so the producing command never waits on the network.
+use std::time::Duration;
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Notify;
+
+use crate::commands::{validate_pagination, PaginatedResult};
+use crate::db::Database;
+
+pub const INVOICE_CREATED: &str = "invoice.created";
+pub const INVOICE_DELETED: &str = "invoice.deleted";
+pub const PAYMENT_CREATED: &str = "payment.created";
+const ALL_EVENTS: &[&str] = &[INVOICE_CREATED, INVOICE_DELETED, PAYMENT_CREATED];
+
+const URL_KEY: &str = "webhook_url";
+const SECRET_KEY: &str = "webhook_secret";
+const EVENTS_KEY: &str = "webhook_events";
+
+/// Attempts before a delivery is marked failed
+const MAX_ATTEMPTS: i32 = 8;
+/// Wait after the first failed attempt; doubles with each further one
+const BASE_BACKOFF_SECS: i64 = 30;
+const MAX_BACKOFF_SECS: i64 = 3600;
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(15);
+/// How often the sender looks for due retries when nothing wakes it
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Characters of a failed response body kept in last_error
+const ERROR_BODY_CHARS: usize = 300;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub event_type: String,
+    pub entity_id: Option<i32>,
+    /// The JSON body sent
+    pub payload: String,
+    /// "pending", "delivered" or "failed"
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: String,
+    pub last_attempt_at: Option<String>,
+    /// HTTP status of the last attempt; None when it got no response
+    pub response_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
+
+/// Wakes the sender when an event is queued or retried
+#[derive(Default)]
+pub struct WebhookState {
+    wake: Notify,
+}
+
+const DELIVERY_COLUMNS: &str = "id, event_type, entity_id, payload, status, attempts, next_attempt_at, last_attempt_at,
+    response_code, last_error, created_at, delivered_at";
+
+fn row_to_delivery(row: &rusqlite::Row) -> rusqlite::Result<WebhookDelivery> {
+    Ok(WebhookDelivery {
+        id: row.get(0)?,
+        event_type: row.get(1)?,
+        entity_id: row.get(2)?,
+        payload: row.get(3)?,
+        status: row.get(4)?,
+        attempts: row.get(5)?,
+        next_attempt_at: row.get(6)?,
+        last_attempt_at: row.get(7)?,
+        response_code: row.get(8)?,
+        last_error: row.get(9)?,
+        created_at: row.get(10)?,
+        delivered_at: row.get(11)?,
+    })
+}
+
+fn setting(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row("SELECT value FROM app_settings WHERE key = ?1", [key], |row| row.get::<_, String>(0))
+        .optional()
+        .ok()
+        .flatten()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Whether `event` should be sent: a URL is set and the event is selected
+fn event_enabled(conn: &Connection, event: &str) -> bool {
+    if setting(conn, URL_KEY).is_none() {
+        return false;
+    }
+    match setting(conn, EVENTS_KEY) {
+        Some(events) => events.split(',').any(|e| e.trim() == event),
+        None => ALL_EVENTS.contains(&event),
+    }
+}
+
+/// Queue `event` when it is enabled; returns the outbox id
+pub(crate) fn enqueue_webhook_event(
+    conn: &Connection,
+    event: &str,
+    entity_id: Option<i32>,
+    data: impl FnOnce() -> Result<Value, String>,
+) -> Result<Option<i64>, String> {
+    if !event_enabled(conn, event) {
+        return Ok(None);
+    }
+
+    let payload = json!({
+        "event": event,
+        "created_at": Utc::now().to_rfc3339(),
+        "data": data()?,
+    });
+    conn.execute(
+        "INSERT INTO webhook_outbox (event_type, entity_id, payload) VALUES (?1, ?2, ?3)",
+        params![event, entity_id, payload.to_string()],
+    )
+    .map_err(|e| format!("Failed to queue webhook event: {}", e))?;
+    Ok(Some(conn.last_insert_rowid()))
+}
+
+/// Queue an event once the command that produced it has committed, and wake the sender.
+/// A failure is only logged; the command has already succeeded.
+pub(crate) fn queue_webhook_event(
+    app: &AppHandle,
+    conn: &Connection,
+    event: &str,
+    entity_id: Option<i32>,
+    data: impl FnOnce() -> Result<Value, String>,
+) {
+    match enqueue_webhook_event(conn, event, entity_id, data) {
+        Ok(Some(_)) => {
+            if let Some(state) = app.try_state::<WebhookState>() {
+                state.wake.notify_one();
+            }
+        }
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to queue {} webhook: {}", event, e),
+    }
+}
+
+/// HMAC-SHA256 (RFC 2104)
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let inner_pad: Vec<u8> = block.iter().map(|b| b ^ 0x36).collect();
+    let outer_pad: Vec<u8> = block.iter().map(|b| b ^ 0x5c).collect();
+    let inner = Sha256::new().chain_update(&inner_pad).chain_update(message).finalize();
+    Sha256::new().chain_update(&outer_pad).chain_update(inner).finalize().into()
+}
+
+/// Seconds to wait after the `attempts`-th failed attempt
+fn backoff_secs(attempts: i32) -> i64 {
+    let doublings = (attempts - 1).clamp(0, 20) as u32;
+    BASE_BACKOFF_SECS.saturating_mul(1 << doublings).min(MAX_BACKOFF_SECS)
+}
+
+/// Record the outcome of an attempt: delivered on a 2xx response, otherwise rescheduled with
+/// backoff, or failed once MAX_ATTEMPTS is reached
+pub(crate) fn record_attempt(
+    conn: &Connection,
+    id: i64,
+    response_code: Option<u16>,
+    error: Option<&str>,
+) -> Result<(), String> {
+    let attempts: i32 = conn
+        .query_row("SELECT attempts FROM webhook_outbox WHERE id = ?1", [id], |row| row.get(0))
+        .map_err(|e| format!("Failed to load webhook delivery: {}", e))?;
+    let attempts = attempts + 1;
+    let delivered = response_code.is_some_and(|code| (200..300).contains(&code));
+
+    let (status, delay) = if delivered {
+        ("delivered", 0)
+    } else if attempts >= MAX_ATTEMPTS {
+        ("failed", 0)
+    } else {
+        ("pending", backoff_secs(attempts))
+    };
+
+    conn.execute(
+        "UPDATE webhook_outbox
+         SET status = ?1, attempts = ?2, response_code = ?3, last_error = ?4,
+             last_attempt_at = datetime('now'),
+             next_attempt_at = datetime('now', '+' || ?5 || ' seconds'),
+             delivered_at = CASE WHEN ?1 = 'delivered' THEN datetime('now') ELSE NULL END
+         WHERE id = ?6",
+        params![status, attempts, response_code, error, delay, id],
+    )
+    .map_err(|e| format!("Failed to record webhook attempt: {}", e))?;
+
+    if status == "failed" {
+        log::warn!("Webhook delivery {} failed after {} attempts: {:?}", id, attempts, error);
+    }
+    Ok(())
+}
+
+/// Oldest pending delivery that is due, with the URL and secret to send it with
+fn next_due(conn: &Connection) -> Result<Option<(WebhookDelivery, String, Option<String>)>, String> {
+    let Some(url) = setting(conn, URL_KEY) else {
+        return Ok(None);
+    };
+    let delivery = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM webhook_outbox
+                 WHERE status = 'pending' AND next_attempt_at <= datetime('now')
+                 ORDER BY next_attempt_at, id LIMIT 1",
+                DELIVERY_COLUMNS
+            ),
+            [],
+            row_to_delivery,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load due webhooks: {}", e))?;
+    Ok(delivery.map(|d| (d, url, setting(conn, SECRET_KEY))))
+}
+
+async fn send(client: &reqwest::Client, delivery: &WebhookDelivery, url: &str, secret: Option<&str>) -> (Option<u16>, Option<String>) {
+    let mut request = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Webhook-Event", &delivery.event_type)
+        .header("X-Webhook-Delivery", delivery.id.to_string())
+        .body(delivery.payload.clone());
+    if let Some(secret) = secret {
+        let signature = hmac_sha256(secret.as_bytes(), delivery.payload.as_bytes());
+        request = request.header("X-Webhook-Signature", format!("sha256={}", hex::encode(signature)));
+    }
+
+    match request.send().await {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+        Ok(response) => {
+            let code = response.status().as_u16();
+            let body: String = response.text().await.unwrap_or_default().chars().take(ERROR_BODY_CHARS).collect();
+            (Some(code), Some(format!("HTTP {}: {}", code, body)))
+        }
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+/// Start the background sender; it delivers whatever is due, then sleeps until an event is
+/// queued or POLL_INTERVAL passes
+pub fn start_webhook_worker(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        loop {
+            loop {
+                let due = app.state::<Database>().get_conn().and_then(|conn| next_due(&conn));
+                let (delivery, url, secret) = match due {
+                    Ok(Some(due)) => due,
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::warn!("Webhook sender: {}", e);
+                        break;
+                    }
+                };
+                let (code, error) = send(&client, &delivery, &url, secret.as_deref()).await;
+                let recorded = app
+                    .state::<Database>()
+                    .get_conn()
+                    .and_then(|conn| record_attempt(&conn, delivery.id, code, error.as_deref()));
+                if let Err(e) = recorded {
+                    log::warn!("Webhook sender: {}", e);
+                    break;
+                }
+            }
+            let _ = tokio::time::timeout(POLL_INTERVAL, app.state::<WebhookState>().wake.notified()).await;
+        }
+    });
+}
+
+/// Get webhook deliveries, newest first, optionally only those with `status`
+#[tauri::command]
+pub fn get_webhook_deliveries(
+    page: i32,
+    page_size: i32,
+    status: Option<String>,
+    db: State<Database>,
+) -> Result<PaginatedResult<WebhookDelivery>, String> {
+    log::info!("get_webhook_deliveries called: page {}, page_size {}, status {:?}", page, page_size, status);
+
+    let conn = db.get_conn()?;
+    get_webhook_deliveries_internal(&conn, page, page_size, status.as_deref())
+}
+
+pub(crate) fn get_webhook_deliveries_internal(
+    conn: &Connection,
+    page: i32,
+    page_size: i32,
+    status: Option<&str>,
+) -> Result<PaginatedResult<WebhookDelivery>, String> {
+    let (limit, offset) = validate_pagination(page, page_size)?;
+
+    let total_count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM webhook_outbox WHERE ?1 IS NULL OR status = ?1",
+            [status],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to count webhook deliveries: {}", e))?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM webhook_outbox WHERE ?1 IS NULL OR status = ?1 ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+            DELIVERY_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to prepare statement: {}", e))?;
+    let items = stmt
+        .query_map(params![status, limit, offset], row_to_delivery)
+        .map_err(|e| format!("Failed to query webhook deliveries: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to collect webhook deliveries: {}", e))?;
+
+    Ok(PaginatedResult { items, total_count })
+}
+
+/// Send a failed or pending delivery again now, with a fresh set of attempts
+#[tauri::command]
+pub fn retry_webhook_delivery(id: i64, app: AppHandle, db: State<Database>) -> Result<WebhookDelivery, String> {
+    log::info!("retry_webhook_delivery called with id: {}", id);
+
+    let conn = db.get_conn()?;
+    let delivery = retry_webhook_delivery_internal(&conn, id)?;
+    app.state::<WebhookState>().wake.notify_one();
+    Ok(delivery)
+}
+
+pub(crate) fn retry_webhook_delivery_internal(conn: &Connection, id: i64) -> Result<WebhookDelivery, String> {
+    let updated = conn
+        .execute(
+            "UPDATE webhook_outbox SET status = 'pending', attempts = 0, next_attempt_at = datetime('now')
+             WHERE id = ?1 AND status != 'delivered'",
+            [id],
+        )
+        .map_err(|e| format!("Failed to retry webhook delivery: {}", e))?;
+
+    let delivery = conn
+        .query_row(
+            &format!("SELECT {} FROM webhook_outbox WHERE id = ?1", DELIVERY_COLUMNS),
+            [id],
+            row_to_delivery,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to load webhook delivery: {}", e))?
+        .ok_or_else(|| format!("Webhook delivery {} not found", id))?;
+    if updated == 0 {
+        return Err(format!("Validation error: webhook delivery {} was already delivered", id));
+    }
+    Ok(delivery)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    fn set(conn: &Connection, key: &str, value: &str) {
+        conn.execute("INSERT OR REPLACE INTO app_settings (key, value) VALUES (?1, ?2)", [key, value])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_hmac_sha256_matches_rfc_4231() {
+        let mac = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(hex::encode(mac), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    }
+
+    #[test]
+    fn test_outbox_queues_retries_and_gives_up() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let data = || -> Result<Value, String> { Ok(json!({ "id": 1 })) };
+
+        // Nothing is queued until a URL is set, and then only the selected events
+        assert_eq!(enqueue_webhook_event(&conn, INVOICE_CREATED, Some(1), data).unwrap(), None);
+        set(&conn, URL_KEY, "http://127.0.0.1:9/hook");
+        set(&conn, EVENTS_KEY, "invoice.created, payment.created");
+        assert_eq!(enqueue_webhook_event(&conn, INVOICE_DELETED, Some(1), data).unwrap(), None);
+        let id = enqueue_webhook_event(&conn, INVOICE_CREATED, Some(1), data).unwrap().unwrap();
+
+        let (due, url, secret) = next_due(&conn).unwrap().unwrap();
+        assert_eq!((due.id, url.as_str(), secret), (id, "http://127.0.0.1:9/hook", None));
+        let payload: Value = serde_json::from_str(&due.payload).unwrap();
+        assert_eq!(payload["event"], INVOICE_CREATED);
+        assert_eq!(payload["data"]["id"], 1);
+
+        // A failure is retried later, not straight away
+        record_attempt(&conn, id, Some(500), Some("HTTP 500: boom")).unwrap();
+        assert!(next_due(&conn).unwrap().is_none());
+        let page = get_webhook_deliveries_internal(&conn, 1, 10, Some("pending")).unwrap();
+        assert_eq!(page.total_count, 1);
+        assert_eq!((page.items[0].attempts, page.items[0].response_code), (1, Some(500)));
+
+        for _ in 1..MAX_ATTEMPTS {
+            record_attempt(&conn, id, None, Some("connection refused")).unwrap();
+        }
+        let failed = get_webhook_deliveries_internal(&conn, 1, 10, Some("failed")).unwrap();
+        assert_eq!(failed.items[0].attempts, MAX_ATTEMPTS);
+
+        // A manual retry makes it due again with a fresh set of attempts
+        let retried = retry_webhook_delivery_internal(&conn, id).unwrap();
+        assert_eq!((retried.status.as_str(), retried.attempts), ("pending", 0));
+        assert_eq!(next_due(&conn).unwrap().unwrap().0.id, id);
+
+        record_attempt(&conn, id, Some(204), None).unwrap();
+        assert!(retry_webhook_delivery_internal(&conn, id).unwrap_err().starts_with("Validation error"));
+        assert_eq!(backoff_secs(1), 30);
+        assert_eq!(backoff_secs(3), 120);
+        assert_eq!(backoff_secs(20), MAX_BACKOFF_SECS);
+    }
+}
@@ -1,7 +1,17 @@
 use rusqlite::{params, Transaction};
+use serde::de::DeserializeOwned;
 use serde::Serialize;
+use serde_json::{Map, Value};
 use chrono::Utc;
 
+/// Layout version of the entity_data (and list-shaped related_data) that archive_entity writes.
+/// Rows archived before payloads were versioned are version 1 (migration 45).
+///
+/// When a field is added to or removed from an archived model, bump this and teach
+/// `upgrade_step` how to bring the previous layout up to date, so restores of older trash rows
+/// keep working.
+pub const ARCHIVE_SCHEMA_VERSION: i32 = 3;
+
 /// Archive an entity to the deleted_items table.
 /// This function should be called within a transaction just before the DELETE operation.
 pub fn archive_entity<T: Serialize>(
@@ -18,18 +28,154 @@ pub fn archive_entity<T: Serialize>(
     let now = Utc::now().to_rfc3339();
 
     tx.execute(
-        "INSERT INTO deleted_items (entity_type, entity_id, entity_data, related_data, deleted_at, deleted_by)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        "INSERT INTO deleted_items (entity_type, entity_id, entity_data, related_data, deleted_at, deleted_by, schema_version)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
         params![
             entity_type,
             entity_id,
             entity_json,
             related_data,
             now,
-            deleted_by
+            deleted_by,
+            ARCHIVE_SCHEMA_VERSION
         ],
     )
     .map_err(|e| format!("Failed to archive {}: {}", entity_type, e))?;
 
     Ok(())
 }
+
+/// Bring an archived record written at `schema_version` up to the current layout.
+/// `kind` is the entity_type, or "invoice_item" for the items archived with an invoice.
+pub fn upgrade_archived(kind: &str, schema_version: i32, data: Value) -> Result<Value, String> {
+    if schema_version > ARCHIVE_SCHEMA_VERSION {
+        return Err(format!(
+            "{} was archived by a newer version of the app (layout {}, this version reads up to {})",
+            kind, schema_version, ARCHIVE_SCHEMA_VERSION
+        ));
+    }
+    let Value::Object(mut fields) = data else {
+        return Err(format!("Archived {} data is not an object", kind));
+    };
+    for version in schema_version.max(1)..ARCHIVE_SCHEMA_VERSION {
+        upgrade_step(version, kind, &mut fields);
+    }
+    Ok(Value::Object(fields))
+}
+
+/// Parse archived JSON into the current model, upgrading it from `schema_version` first
+pub fn parse_archived<T: DeserializeOwned>(kind: &str, schema_version: i32, json: &str) -> Result<T, String> {
+    let data = serde_json::from_str(json).map_err(|e| format!("Failed to parse {} data: {}", kind, e))?;
+    serde_json::from_value(upgrade_archived(kind, schema_version, data)?)
+        .map_err(|e| format!("Failed to parse {} data: {}", kind, e))
+}
+
+/// Parse an archived JSON array of records (an invoice's items, a customer's invoices)
+pub fn parse_archived_list<T: DeserializeOwned>(kind: &str, schema_version: i32, json: &str) -> Result<Vec<T>, String> {
+    let data: Vec<Value> = serde_json::from_str(json).map_err(|e| format!("Failed to parse {} list: {}", kind, e))?;
+    data.into_iter()
+        .map(|record| {
+            serde_json::from_value(upgrade_archived(kind, schema_version, record)?)
+                .map_err(|e| format!("Failed to parse {} data: {}", kind, e))
+        })
+        .collect()
+}
+
+/// Upgrade a record from layout `from_version` to `from_version + 1`
+fn upgrade_step(from_version: i32, kind: &str, fields: &mut Map<String, Value>) {
+    match from_version {
+        1 => upgrade_unversioned(kind, fields),
+        // Products gained a unit and the fractional quantity flag (migration 53)
+        2 if kind == "product" => {
+            fill(fields, "unit", Value::String("pcs".to_string()));
+            fill(fields, "allow_fractional", Value::Bool(false));
+        }
+        _ => {}
+    }
+}
+
+/// Set `field` unless the record already has it
+fn fill(fields: &mut Map<String, Value>, field: &str, value: Value) {
+    fields.entry(field).or_insert(value);
+}
+
+/// Version 1 is whatever the model looked like when the row was deleted. Columns added since the
+/// first schema are filled with their defaults and renamed ones are carried over, so every
+/// unversioned payload reads as the current layout.
+fn upgrade_unversioned(kind: &str, fields: &mut Map<String, Value>) {
+    let now = Value::String(Utc::now().to_rfc3339());
+    match kind {
+        "product" => {
+            for column in ["selling_price", "initial_stock", "image_path", "category", "category_id", "parent_product_id", "variant_attributes"] {
+                fill(fields, column, Value::Null);
+            }
+            fill(fields, "stock_quantity", Value::from(0));
+            fill(fields, "created_at", now.clone());
+            fill(fields, "updated_at", now);
+        }
+        "customer" => {
+            for column in ["email", "phone", "address", "place", "state", "district", "image_path", "credit_limit"] {
+                fill(fields, column, Value::Null);
+            }
+            // Migration 11 turned the free-text place into the town
+            let town = fields.get("place").cloned().unwrap_or(Value::Null);
+            fill(fields, "town", town);
+            fill(fields, "created_at", now.clone());
+            fill(fields, "updated_at", now);
+        }
+        "supplier" => {
+            // district replaced place (migration 2)
+            if let Some(place) = fields.remove("place") {
+                fill(fields, "district", place);
+            }
+            for column in ["contact_info", "address", "email", "comments", "state", "district", "town", "image_path", "opening_balance"] {
+                fill(fields, column, Value::Null);
+            }
+            fill(fields, "created_at", now.clone());
+            fill(fields, "updated_at", now);
+        }
+        "invoice" => {
+            fill(fields, "tax_amount", Value::from(0.0));
+            fill(fields, "discount_amount", Value::from(0.0));
+            fill(fields, "round_off", Value::from(0.0));
+        }
+        "invoice_item" => {
+            fill(fields, "discount_amount", Value::from(0.0));
+            fill(fields, "product_sku", Value::String(String::new()));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{Product, Supplier};
+
+    #[test]
+    fn test_unversioned_payloads_read_as_the_current_layout() {
+        // A product from before selling prices, images and categories
+        let product: Product = parse_archived(
+            "product",
+            1,
+            r#"{"id": 7, "name": "Kettle", "sku": "KET-1", "price": 450.0, "stock_quantity": 3, "supplier_id": null}"#,
+        )
+        .unwrap();
+        assert_eq!(product.sku, "KET-1");
+        assert_eq!(product.category, None);
+        assert_eq!((product.unit.as_str(), product.allow_fractional), ("pcs", false));
+        assert!(!product.created_at.is_empty());
+
+        // A supplier from when district was still called place
+        let supplier: Supplier = parse_archived("supplier", 1, r#"{"id": 2, "name": "Acme", "place": "North"}"#).unwrap();
+        assert_eq!(supplier.district.as_deref(), Some("North"));
+
+        // Current payloads pass through untouched, newer ones are refused
+        let current = r#"{"id": 2, "name": "Acme", "created_at": "2024-01-01", "updated_at": "2024-01-01"}"#;
+        let supplier: Supplier = parse_archived("supplier", ARCHIVE_SCHEMA_VERSION, current).unwrap();
+        assert_eq!(supplier.created_at, "2024-01-01");
+        assert!(parse_archived::<Product>("product", ARCHIVE_SCHEMA_VERSION + 1, "{}")
+            .unwrap_err()
+            .contains("newer version"));
+    }
+}
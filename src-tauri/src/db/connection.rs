@@ -3,8 +3,7 @@ use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::Result;
 use std::path::PathBuf;
 
-use super::schema::CREATE_TABLES_SQL;
-use super::schema::purchase_order_migration::PURCHASE_ORDER_MIGRATION_SQL;
+use super::migrations;
 
 /// Type alias for the connection pool
 pub type SqlitePool = Pool<SqliteConnectionManager>;
@@ -49,6 +48,9 @@ impl Database {
                 // Optimize for read-heavy workloads
                 c.pragma_update(None, "read_uncommitted", "1")?;
 
+                // Log slow statements (shape only, no values)
+                c.profile(Some(crate::services::metrics::log_slow_statement));
+
                 Ok(())
             });
 
@@ -82,183 +84,28 @@ impl Database {
 
     /// Initialize database tables
     fn init_tables(&self) -> Result<()> {
-        let conn = self.pool.get().map_err(|e| {
+        let mut conn = self.pool.get().map_err(|e| {
             rusqlite::Error::InvalidParameterName(format!("Pool error: {}", e))
         })?;
 
-        conn.execute_batch(CREATE_TABLES_SQL)?;
-
-        // Migration: Add place column to customers if it doesn't exist
-        let place_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('customers') WHERE name = 'place'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !place_exists {
-            log::info!("Migrating: Adding place column to customers table");
-            conn.execute("ALTER TABLE customers ADD COLUMN place TEXT", [])?;
-        }
-
-        // Migration: Add address, email, comments columns to suppliers if they don't exist
-        let supplier_address_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'address'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_address_exists {
-            log::info!("Migrating: Adding address column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN address TEXT", [])?;
-        }
-
-        let supplier_email_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'email'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_email_exists {
-            log::info!("Migrating: Adding email column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN email TEXT", [])?;
-        }
-
-        let supplier_comments_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'comments'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_comments_exists {
-            log::info!("Migrating: Adding comments column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN comments TEXT", [])?;
-        }
-
-        let supplier_state_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'state'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_state_exists {
-            log::info!("Migrating: Adding state column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN state TEXT", [])?;
-        }
-
-        let supplier_place_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'place'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_place_exists {
-            log::info!("Migrating: Adding place column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN place TEXT", [])?;
-        }
-
-        // Migration: Add district and town columns to suppliers
-        let supplier_district_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'district'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_district_exists {
-            log::info!("Migrating: Adding district column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN district TEXT", [])?;
-
-            // Copy place data to district for existing records
-            log::info!("Migrating: Copying place data to district column");
-            conn.execute("UPDATE suppliers SET district = place WHERE place IS NOT NULL", [])?;
-        }
-
-        let supplier_town_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'town'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_town_exists {
-            log::info!("Migrating: Adding town column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN town TEXT", [])?;
-        }
-
-        // Migration: Add state, district, and town columns to invoices
-        let invoice_state_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('invoices') WHERE name = 'state'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !invoice_state_exists {
-            log::info!("Migrating: Adding state column to invoices table");
-            conn.execute("ALTER TABLE invoices ADD COLUMN state TEXT", [])?;
+        // An existing database gets a safety snapshot before its schema is changed
+        let has_data = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'products'", [], |row| {
+                row.get::<_, i32>(0)
+            })
+            .unwrap_or(0)
+            > 0;
+        let version = migrations::current_version(&conn).unwrap_or(0);
+        if has_data && version < migrations::latest_version() {
+            let operation = format!("migration_{}_to_{}", version, migrations::latest_version());
+            if let Err(e) = crate::commands::safety_snapshots::take_safety_snapshot(&conn, &operation) {
+                log::warn!("{}", e);
+            }
         }
 
-        let invoice_district_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('invoices') WHERE name = 'district'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
+        // Schema changes live in numbered migrations (see db/migrations.rs)
+        migrations::run_migrations(&mut conn)?;
 
-        if !invoice_district_exists {
-            log::info!("Migrating: Adding district column to invoices table");
-            conn.execute("ALTER TABLE invoices ADD COLUMN district TEXT", [])?;
-        }
-
-        let invoice_town_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('invoices') WHERE name = 'town'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !invoice_town_exists {
-            log::info!("Migrating: Adding town column to invoices table");
-            conn.execute("ALTER TABLE invoices ADD COLUMN town TEXT", [])?;
-        }
-
-        // Migration: Add product_name to invoice_items (snapshot of name at time of sale)
-        let invoice_items_product_name_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('invoice_items') WHERE name = 'product_name'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !invoice_items_product_name_exists {
-            log::info!("Migrating: Adding product_name column to invoice_items table");
-            conn.execute("ALTER TABLE invoice_items ADD COLUMN product_name TEXT", [])?;
-            
-            // Optional: Backfill product names for existing items if possible
-            // This is complex as it depends on product_id existing, but safe to skip for now or do simple update
-            log::info!("Migrating: Backfilling product_name from products table");
-            conn.execute("UPDATE invoice_items SET product_name = (SELECT name FROM products WHERE products.id = invoice_items.product_id)", [])?;
-        }
-
-        // Migration: Create users table if it doesn't exist (handled by init_tables but good to be safe or for specific updates)
         // Seed default admin user if users table is empty
         let user_count: i32 = conn
             .query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))
@@ -277,271 +124,6 @@ impl Database {
             )?;
         }
 
-        // Migration: Add created_at and updated_at to products
-        let product_created_at_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('products') WHERE name = 'created_at'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !product_created_at_exists {
-            log::info!("Migrating: Adding created_at column to products table");
-            // Add as nullable first, then update with current timestamp
-            conn.execute("ALTER TABLE products ADD COLUMN created_at TEXT", [])?;
-        }
-
-        // Always update NULL values (in case migration was interrupted)
-        conn.execute("UPDATE products SET created_at = datetime('now') WHERE created_at IS NULL", [])?;
-
-        let product_updated_at_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('products') WHERE name = 'updated_at'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !product_updated_at_exists {
-            log::info!("Migrating: Adding updated_at column to products table");
-            conn.execute("ALTER TABLE products ADD COLUMN updated_at TEXT", [])?;
-        }
-
-        // Always update NULL values (in case migration was interrupted)
-        conn.execute("UPDATE products SET updated_at = datetime('now') WHERE updated_at IS NULL", [])?;
-
-        // Migration: Add created_at and updated_at to suppliers
-        let supplier_created_at_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'created_at'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_created_at_exists {
-            log::info!("Migrating: Adding created_at column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN created_at TEXT", [])?;
-        }
-
-        // Always update NULL values (in case migration was interrupted)
-        conn.execute("UPDATE suppliers SET created_at = datetime('now') WHERE created_at IS NULL", [])?;
-
-        let supplier_updated_at_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'updated_at'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_updated_at_exists {
-            log::info!("Migrating: Adding updated_at column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN updated_at TEXT", [])?;
-        }
-
-        // Always update NULL values (in case migration was interrupted)
-        conn.execute("UPDATE suppliers SET updated_at = datetime('now') WHERE updated_at IS NULL", [])?;
-
-        // Migration: Add selling_price column to products
-        let selling_price_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('products') WHERE name = 'selling_price'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !selling_price_exists {
-            log::info!("Migrating: Adding selling_price column to products table");
-            conn.execute("ALTER TABLE products ADD COLUMN selling_price REAL", [])?;
-        }
-
-        // Migration: Add initial_stock column to products
-        let initial_stock_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('products') WHERE name = 'initial_stock'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !initial_stock_exists {
-            log::info!("Migrating: Adding initial_stock column to products table");
-            conn.execute("ALTER TABLE products ADD COLUMN initial_stock INTEGER", [])?;
-        }
-
-        // Migration: Add product_id column to supplier_payments (for per-product payment tracking)
-        let supplier_payments_product_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('supplier_payments') WHERE name = 'product_id'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_payments_product_exists {
-            log::info!("Migrating: Adding product_id column to supplier_payments table");
-            conn.execute("ALTER TABLE supplier_payments ADD COLUMN product_id INTEGER", [])?;
-        }
-
-        // Ensure indexes for supplier_payments with product_id exist (now that column is present)
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_supplier_payments_product ON supplier_payments(product_id)",
-            [],
-        )?;
-        conn.execute(
-            "CREATE INDEX IF NOT EXISTS idx_supplier_payments_supplier_product ON supplier_payments(supplier_id, product_id)",
-            [],
-        )?;
-
-        // Migration: Add po_id column to supplier_payments (for linking payments to purchase orders)
-        let supplier_payments_po_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('supplier_payments') WHERE name = 'po_id'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_payments_po_exists {
-            log::info!("Migrating: Adding po_id column to supplier_payments table");
-            conn.execute("ALTER TABLE supplier_payments ADD COLUMN po_id INTEGER REFERENCES purchase_orders(id)", [])?;
-            // Add index for the new column
-            conn.execute("CREATE INDEX IF NOT EXISTS idx_supplier_payments_po ON supplier_payments(po_id)", [])?;
-        }
-
-        // Migration: Add po_id column to supplier_payments (for linking payments to purchase orders)
-        let supplier_payments_po_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('supplier_payments') WHERE name = 'po_id'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_payments_po_exists {
-            log::info!("Migrating: Adding po_id column to supplier_payments table");
-            conn.execute("ALTER TABLE supplier_payments ADD COLUMN po_id INTEGER REFERENCES purchase_orders(id)", [])?;
-        }
-
-        // Migration: Run Purchase Order and FIFO Inventory System migration
-        log::info!("Running Purchase Order and FIFO migration...");
-        conn.execute_batch(PURCHASE_ORDER_MIGRATION_SQL)?;
-        log::info!("Purchase Order and FIFO migration completed successfully");
-
-        // Migration: Create app_settings table for Google API configuration
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS app_settings (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL,
-                updated_at TEXT NOT NULL DEFAULT (datetime('now'))
-            )",
-            [],
-        )?;
-        log::info!("app_settings table created/verified");
-
-        // Migration: Add image_path column to products
-        let image_path_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('products') WHERE name = 'image_path'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !image_path_exists {
-            log::info!("Migrating: Adding image_path column to products table");
-            conn.execute("ALTER TABLE products ADD COLUMN image_path TEXT", [])?;
-        }
-
-        // Migration: Add category to products
-        let category_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('products') WHERE name = 'category'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !category_exists {
-            log::info!("Migrating: Adding category column to products table");
-            conn.execute("ALTER TABLE products ADD COLUMN category TEXT", [])?;
-        }
-
-        // Migration: Add image_path to suppliers
-        let supplier_image_path_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('suppliers') WHERE name = 'image_path'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !supplier_image_path_exists {
-            log::info!("Migrating: Adding image_path column to suppliers table");
-            conn.execute("ALTER TABLE suppliers ADD COLUMN image_path TEXT", [])?;
-        }
-
-        // Migration: Add image_path to customers
-        let customer_image_path_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('customers') WHERE name = 'image_path'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !customer_image_path_exists {
-            log::info!("Migrating: Adding image_path column to customers table");
-            conn.execute("ALTER TABLE customers ADD COLUMN image_path TEXT", [])?;
-        }
-
-        // Migration: Add state, district, and town columns to customers
-        let customer_state_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('customers') WHERE name = 'state'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !customer_state_exists {
-            log::info!("Migrating: Adding state column to customers table");
-            conn.execute("ALTER TABLE customers ADD COLUMN state TEXT", [])?;
-        }
-
-        let customer_district_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('customers') WHERE name = 'district'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !customer_district_exists {
-            log::info!("Migrating: Adding district column to customers table");
-            conn.execute("ALTER TABLE customers ADD COLUMN district TEXT", [])?;
-        }
-
-        let customer_town_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('customers') WHERE name = 'town'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !customer_town_exists {
-            log::info!("Migrating: Adding town column to customers table");
-            conn.execute("ALTER TABLE customers ADD COLUMN town TEXT", [])?;
-
-            // Migration: Copy place data to town for existing records as a best effort
-            log::info!("Migrating: Copying place data to town column for customers");
-            conn.execute("UPDATE customers SET town = place WHERE place IS NOT NULL", [])?;
-        }
-
         // Security Enforcement: Master Admin Reset
         log::info!("Enforcing Master Admin credentials and removing other users");
         
@@ -568,76 +150,6 @@ impl Database {
             )?;
         }
 
-        // Migration: Add biometric_enabled column to users table
-        let biometric_enabled_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('users') WHERE name = 'biometric_enabled'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !biometric_enabled_exists {
-            log::info!("Migrating: Adding biometric_enabled column to users table");
-            conn.execute("ALTER TABLE users ADD COLUMN biometric_enabled INTEGER NOT NULL DEFAULT 0", [])?;
-        }
-
-        // Migration: Add biometric_token_hash column to users table
-        let biometric_token_hash_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('users') WHERE name = 'biometric_token_hash'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !biometric_token_hash_exists {
-            log::info!("Migrating: Adding biometric_token_hash column to users table");
-            conn.execute("ALTER TABLE users ADD COLUMN biometric_token_hash TEXT", [])?;
-        }
-
-        // Migration: Add initial_paid column to invoices (for credit/partial payments)
-        let invoice_initial_paid_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('invoices') WHERE name = 'initial_paid'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !invoice_initial_paid_exists {
-            log::info!("Migrating: Adding initial_paid column to invoices table");
-            conn.execute("ALTER TABLE invoices ADD COLUMN initial_paid REAL DEFAULT 0", [])?;
-        }
-
-        // Migration: Add credit_amount column to invoices (for credit/partial payments)
-        let invoice_credit_amount_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('invoices') WHERE name = 'credit_amount'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !invoice_credit_amount_exists {
-            log::info!("Migrating: Adding credit_amount column to invoices table");
-            conn.execute("ALTER TABLE invoices ADD COLUMN credit_amount REAL DEFAULT 0", [])?;
-        }
-
-        // Migration: Add discount_amount column to invoice_items (for per-item weighted discount)
-        let invoice_items_discount_exists: bool = conn
-            .query_row(
-                "SELECT COUNT(*) FROM pragma_table_info('invoice_items') WHERE name = 'discount_amount'",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0) > 0;
-
-        if !invoice_items_discount_exists {
-            log::info!("Migrating: Adding discount_amount column to invoice_items table");
-            conn.execute("ALTER TABLE invoice_items ADD COLUMN discount_amount REAL DEFAULT 0", [])?;
-        }
-
         Ok(())
     }
 }
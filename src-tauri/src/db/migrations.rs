@@ -0,0 +1,1495 @@
+/// Schema Migrations
+/// Numbered, ordered schema changes recorded in the schema_version table. Each pending migration
+/// runs in its own transaction at startup. Every step is guarded (IF NOT EXISTS / column checks)
+/// so databases created before versioning existed upgrade cleanly from whatever state they are in.
+///
+/// To change the schema, append a new migration with the next version number. Never edit or
+/// reorder a migration that has shipped.
+
+use rusqlite::{Connection, Result};
+
+use super::schema::purchase_order_migration::PURCHASE_ORDER_MIGRATION_SQL;
+use super::schema::CREATE_TABLES_SQL;
+
+pub struct Migration {
+    pub version: i32,
+    pub description: &'static str,
+    pub up: fn(&Connection) -> Result<()>,
+}
+
+/// All migrations in the order they are applied
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, description: "Initial schema", up: initial_schema },
+    Migration { version: 2, description: "Customer and supplier contact and location columns", up: contact_columns },
+    Migration { version: 3, description: "Invoice location columns", up: invoice_location },
+    Migration { version: 4, description: "Product name snapshot on invoice items", up: invoice_item_product_name },
+    Migration { version: 5, description: "Product and supplier timestamps", up: timestamps },
+    Migration { version: 6, description: "Product selling price and initial stock", up: product_pricing },
+    Migration { version: 7, description: "Supplier payments per product and purchase order", up: supplier_payment_links },
+    Migration { version: 8, description: "Purchase orders and FIFO inventory", up: purchase_orders },
+    Migration { version: 9, description: "App settings", up: app_settings },
+    Migration { version: 10, description: "Product category and image paths", up: images_and_category },
+    Migration { version: 11, description: "Customer location columns", up: customer_location },
+    Migration { version: 12, description: "Biometric login columns", up: biometric_columns },
+    Migration { version: 13, description: "Invoice credit columns", up: invoice_credit },
+    Migration { version: 14, description: "Per-item invoice discount", up: invoice_item_discount },
+    Migration { version: 15, description: "Supplier opening balance", up: supplier_opening_balance },
+    Migration { version: 16, description: "Expenses", up: expenses },
+    Migration { version: 17, description: "Purchase order currency", up: purchase_order_currency },
+    Migration { version: 18, description: "Price tiers", up: price_tiers },
+    Migration { version: 19, description: "Product bundles", up: bundles },
+    Migration { version: 20, description: "Per-item invoice tax", up: invoice_item_tax },
+    Migration { version: 21, description: "Invoice round off", up: invoice_round_off },
+    Migration { version: 22, description: "Foreign key delete behavior", up: foreign_key_actions },
+    Migration { version: 23, description: "Batch consumptions", up: batch_consumptions },
+    Migration { version: 24, description: "Customer credit limit", up: customer_credit_limit },
+    Migration { version: 25, description: "Invoice due dates", up: invoice_due_date },
+    Migration { version: 26, description: "Payment reminders", up: payment_reminders },
+    Migration { version: 27, description: "Biometric action approvals", up: action_approvals },
+    Migration { version: 28, description: "Image content hashes", up: image_content_hash },
+    Migration { version: 29, description: "Product variants", up: product_variants },
+    Migration { version: 30, description: "Product categories", up: categories },
+    Migration { version: 31, description: "Daily sales summary", up: daily_sales_summary },
+    Migration { version: 32, description: "Held sales", up: held_sales },
+    Migration { version: 33, description: "Demo data markers", up: demo_records },
+    Migration { version: 34, description: "Archived customers", up: customer_archived },
+    Migration { version: 35, description: "Purchase returns", up: purchase_returns },
+    Migration { version: 36, description: "Supplier payment allocations", up: supplier_payment_allocations },
+    Migration { version: 37, description: "Invoice item price lookup index", up: invoice_item_price_index },
+    Migration { version: 38, description: "Biometric device binding and token expiry", up: biometric_device_binding },
+    Migration { version: 39, description: "Invoice date range index", up: invoice_created_utc_index },
+    Migration { version: 40, description: "Per-user preferences", up: user_preferences },
+    Migration { version: 41, description: "Product price history and scheduled price changes", up: product_price_history },
+    Migration { version: 42, description: "Stock reservations", up: stock_reservations },
+    Migration { version: 43, description: "Negative stock guard and backordered quantities", up: negative_stock_guard },
+    Migration { version: 44, description: "Day closures (Z-reports)", up: day_closures },
+    Migration { version: 45, description: "Versioned trash payloads", up: archive_schema_version },
+    Migration { version: 46, description: "Batch original quantities", up: batch_original_quantity },
+    Migration { version: 47, description: "Weighted average costing", up: weighted_average_costing },
+    Migration { version: 48, description: "Webhook outbox", up: webhook_outbox },
+    Migration { version: 49, description: "Recurring invoice templates", up: recurring_invoice_templates },
+    Migration { version: 50, description: "Sales targets", up: sales_targets },
+    Migration { version: 51, description: "Invoice item name search index", up: invoice_item_name_index },
+    Migration { version: 52, description: "Stock alerts and daily digests", up: stock_alerts },
+    Migration { version: 53, description: "Product units and fractional quantities", up: product_units },
+    Migration { version: 54, description: "Received quantities on PO lines", up: po_received_quantity },
+    Migration { version: 55, description: "Split invoice payments", up: invoice_tenders },
+    Migration { version: 56, description: "Price override log", up: price_overrides },
+    Migration { version: 57, description: "Modification history indexes", up: modification_indexes },
+    Migration { version: 58, description: "Delivery challans", up: delivery_challans },
+    Migration { version: 59, description: "Integrity reports", up: integrity_reports },
+];
+
+/// Version the schema reaches once every migration has run
+pub fn latest_version() -> i32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// Highest applied migration version (0 for a database that predates versioning)
+pub fn current_version(conn: &Connection) -> Result<i32> {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+}
+
+/// Apply every migration newer than the recorded schema version.
+///
+/// Foreign keys are switched off while migrations run (the pragma is a no-op inside a
+/// transaction) so tables can be rebuilt without cascading deletes into their children.
+pub fn run_migrations(conn: &mut Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+    )?;
+
+    let current = current_version(conn)?;
+    if current >= latest_version() {
+        return Ok(());
+    }
+
+    let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+    conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+    let result = apply_pending(conn, current);
+    if foreign_keys {
+        conn.execute_batch("PRAGMA foreign_keys = ON")?;
+    }
+    result?;
+
+    // Constraints only apply to new writes; rows orphaned before they existed are left for
+    // the find_orphaned_rows command to report and repair
+    let violations: i64 = conn.query_row("SELECT COUNT(*) FROM pragma_foreign_key_check", [], |row| row.get(0))?;
+    if violations > 0 {
+        log::warn!("Database has {} rows with broken foreign key references", violations);
+    }
+
+    log::info!("Database schema at version {}", latest_version());
+    Ok(())
+}
+
+fn apply_pending(conn: &mut Connection, current: i32) -> Result<()> {
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        log::info!("Applying migration {}: {}", migration.version, migration.description);
+
+        let tx = conn.transaction()?;
+        (migration.up)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_version (version, description) VALUES (?1, ?2)",
+            (migration.version, migration.description),
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let count: i32 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// Add a column unless it is already there; returns whether it was added
+fn add_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<bool> {
+    if column_exists(conn, table, column)? {
+        return Ok(false);
+    }
+
+    log::info!("Migrating: Adding {} column to {} table", column, table);
+    conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    Ok(true)
+}
+
+/// Give `column`'s reference to `parent(id)` the ON DELETE `action`, adding the constraint
+/// when the column has none. Definitions that already carry an action are left alone.
+fn with_foreign_key(sql: &str, column: &str, parent: &str, action: &str) -> String {
+    let reference = format!("REFERENCES {}(id)", parent);
+    for existing in [
+        format!("FOREIGN KEY ({}) {}", column, reference),
+        format!("{} INTEGER {}", column, reference),
+    ] {
+        if let Some(pos) = sql.find(&existing) {
+            let end = pos + existing.len();
+            if sql[end..].trim_start().starts_with("ON DELETE") {
+                return sql.to_string();
+            }
+            return format!("{} ON DELETE {}{}", &sql[..end], action, &sql[end..]);
+        }
+    }
+
+    match sql.rfind(')') {
+        Some(close) => format!(
+            "{},\n    FOREIGN KEY ({}) {} ON DELETE {}\n{}",
+            sql[..close].trim_end(),
+            column,
+            reference,
+            action,
+            &sql[close..]
+        ),
+        None => sql.to_string(),
+    }
+}
+
+/// Recreate `table` with the given (column, parent, action) foreign keys. SQLite cannot alter
+/// constraints in place.
+fn rebuild_with_foreign_keys(conn: &Connection, table: &str, keys: &[(&str, &str, &str)]) -> Result<()> {
+    rebuild_table(conn, table, "foreign key actions", |sql| {
+        keys.iter()
+            .fold(sql.to_string(), |sql, (column, parent, action)| with_foreign_key(&sql, column, parent, action))
+    })
+}
+
+/// Declare `column` with `new_type` instead of INTEGER. Column names are matched whole, so
+/// "quantity" leaves "stock_quantity" alone.
+fn with_column_type(sql: &str, column: &str, new_type: &str) -> String {
+    let declaration = format!("{} INTEGER", column);
+    let mut search_from = 0;
+    while let Some(found) = sql[search_from..].find(&declaration) {
+        let pos = search_from + found;
+        let end = pos + declaration.len();
+        let starts_word = sql[..pos].chars().last().map_or(true, |c| !c.is_alphanumeric() && c != '_');
+        let ends_word = sql[end..].chars().next().map_or(true, |c| !c.is_alphanumeric() && c != '_');
+        if starts_word && ends_word {
+            return format!("{}{} {}{}", &sql[..pos], column, new_type, &sql[end..]);
+        }
+        search_from = end;
+    }
+    sql.to_string()
+}
+
+/// Recreate `table` with the CREATE statement `edit` makes of its current one, keeping its
+/// rows, indexes, triggers and AUTOINCREMENT counter. Nothing happens when the statement is
+/// unchanged. Must run with foreign keys off, or dropping the old table would cascade into
+/// its children.
+fn rebuild_table(conn: &Connection, table: &str, reason: &str, edit: impl Fn(&str) -> String) -> Result<()> {
+    let sql: String = conn.query_row(
+        "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        [table],
+        |row| row.get(0),
+    )?;
+    let edited = edit(&sql);
+    let body = match edited.find('(') {
+        Some(open) if edited != sql => &edited[open..],
+        _ => return Ok(()),
+    };
+
+    log::info!("Migrating: Rebuilding {} table with {}", table, reason);
+
+    // Triggers are dropped with the table, so they are recreated along with the indexes
+    let dependents: Vec<String> = conn
+        .prepare("SELECT sql FROM sqlite_master WHERE type IN ('index', 'trigger') AND tbl_name = ?1 AND sql IS NOT NULL")?
+        .query_map([table], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+    let columns: Vec<String> = conn
+        .prepare("SELECT name FROM pragma_table_info(?1)")?
+        .query_map([table], |row| row.get::<_, String>(0))?
+        .map(|name| name.map(|name| format!("\"{}\"", name)))
+        .collect::<Result<_>>()?;
+    let columns = columns.join(", ");
+    let sequence: Option<i64> = conn
+        .query_row("SELECT seq FROM sqlite_sequence WHERE name = ?1", [table], |row| row.get(0))
+        .ok();
+
+    // Views that read the table would fail to resolve while it is missing, which the rename
+    // checks for unless the legacy behaviour is on; they pick up the new table by name
+    let rebuilt = format!("{}_rebuild", table);
+    conn.execute_batch(&format!(
+        "PRAGMA legacy_alter_table = ON;
+         DROP TABLE IF EXISTS {rebuilt};
+         CREATE TABLE {rebuilt} {body};
+         INSERT INTO {rebuilt} ({columns}) SELECT {columns} FROM {table};
+         DROP TABLE {table};
+         ALTER TABLE {rebuilt} RENAME TO {table};
+         PRAGMA legacy_alter_table = OFF;",
+    ))?;
+    for dependent in dependents {
+        conn.execute_batch(&dependent)?;
+    }
+    if let Some(seq) = sequence {
+        conn.execute("UPDATE sqlite_sequence SET seq = MAX(seq, ?1) WHERE name = ?2", (seq, table))?;
+    }
+    Ok(())
+}
+
+fn initial_schema(conn: &Connection) -> Result<()> {
+    conn.execute_batch(CREATE_TABLES_SQL)
+}
+
+fn contact_columns(conn: &Connection) -> Result<()> {
+    add_column(conn, "customers", "place", "TEXT")?;
+    add_column(conn, "suppliers", "address", "TEXT")?;
+    add_column(conn, "suppliers", "email", "TEXT")?;
+    add_column(conn, "suppliers", "comments", "TEXT")?;
+    add_column(conn, "suppliers", "state", "TEXT")?;
+    add_column(conn, "suppliers", "place", "TEXT")?;
+
+    // district replaces place; copy existing values across
+    if add_column(conn, "suppliers", "district", "TEXT")? {
+        conn.execute("UPDATE suppliers SET district = place WHERE place IS NOT NULL", [])?;
+    }
+    add_column(conn, "suppliers", "town", "TEXT")?;
+    Ok(())
+}
+
+fn invoice_location(conn: &Connection) -> Result<()> {
+    add_column(conn, "invoices", "state", "TEXT")?;
+    add_column(conn, "invoices", "district", "TEXT")?;
+    add_column(conn, "invoices", "town", "TEXT")?;
+    Ok(())
+}
+
+fn invoice_item_product_name(conn: &Connection) -> Result<()> {
+    if add_column(conn, "invoice_items", "product_name", "TEXT")? {
+        conn.execute(
+            "UPDATE invoice_items SET product_name = (SELECT name FROM products WHERE products.id = invoice_items.product_id)",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn timestamps(conn: &Connection) -> Result<()> {
+    // Added nullable, then filled in for existing rows
+    for table in ["products", "suppliers"] {
+        for column in ["created_at", "updated_at"] {
+            add_column(conn, table, column, "TEXT")?;
+            conn.execute(
+                &format!("UPDATE {} SET {} = datetime('now') WHERE {} IS NULL", table, column, column),
+                [],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn product_pricing(conn: &Connection) -> Result<()> {
+    add_column(conn, "products", "selling_price", "REAL")?;
+    add_column(conn, "products", "initial_stock", "INTEGER")?;
+    Ok(())
+}
+
+fn supplier_payment_links(conn: &Connection) -> Result<()> {
+    add_column(conn, "supplier_payments", "product_id", "INTEGER")?;
+    add_column(conn, "supplier_payments", "po_id", "INTEGER REFERENCES purchase_orders(id)")?;
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_supplier_payments_product ON supplier_payments(product_id);
+         CREATE INDEX IF NOT EXISTS idx_supplier_payments_supplier_product ON supplier_payments(supplier_id, product_id);
+         CREATE INDEX IF NOT EXISTS idx_supplier_payments_po ON supplier_payments(po_id);",
+    )
+}
+
+fn purchase_orders(conn: &Connection) -> Result<()> {
+    conn.execute_batch(PURCHASE_ORDER_MIGRATION_SQL)
+}
+
+fn app_settings(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        )",
+    )
+}
+
+fn images_and_category(conn: &Connection) -> Result<()> {
+    add_column(conn, "products", "image_path", "TEXT")?;
+    add_column(conn, "products", "category", "TEXT")?;
+    add_column(conn, "suppliers", "image_path", "TEXT")?;
+    add_column(conn, "customers", "image_path", "TEXT")?;
+    Ok(())
+}
+
+fn customer_location(conn: &Connection) -> Result<()> {
+    add_column(conn, "customers", "state", "TEXT")?;
+    add_column(conn, "customers", "district", "TEXT")?;
+
+    // Best effort: the old free-text place becomes the town
+    if add_column(conn, "customers", "town", "TEXT")? {
+        conn.execute("UPDATE customers SET town = place WHERE place IS NOT NULL", [])?;
+    }
+    Ok(())
+}
+
+fn biometric_columns(conn: &Connection) -> Result<()> {
+    add_column(conn, "users", "biometric_enabled", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column(conn, "users", "biometric_token_hash", "TEXT")?;
+    Ok(())
+}
+
+fn invoice_credit(conn: &Connection) -> Result<()> {
+    add_column(conn, "invoices", "initial_paid", "REAL DEFAULT 0")?;
+    add_column(conn, "invoices", "credit_amount", "REAL DEFAULT 0")?;
+    Ok(())
+}
+
+fn invoice_item_discount(conn: &Connection) -> Result<()> {
+    add_column(conn, "invoice_items", "discount_amount", "REAL DEFAULT 0")?;
+    Ok(())
+}
+
+fn supplier_opening_balance(conn: &Connection) -> Result<()> {
+    // Pre-app debt, seeds the supplier ledger
+    add_column(conn, "suppliers", "opening_balance", "REAL")?;
+    Ok(())
+}
+
+fn expenses(conn: &Connection) -> Result<()> {
+    // Operating expenses for the P&L report
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS expenses (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            expense_date TEXT NOT NULL,
+            category TEXT NOT NULL,
+            amount REAL NOT NULL,
+            note TEXT,
+            payment_method TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_expenses_date ON expenses(expense_date);",
+    )
+}
+
+fn purchase_order_currency(conn: &Connection) -> Result<()> {
+    // Item costs stay in the PO currency; total_amount and batch costs are in the base currency
+    if add_column(conn, "purchase_orders", "currency", "TEXT NOT NULL DEFAULT 'INR'")? {
+        add_column(conn, "purchase_orders", "exchange_rate", "REAL NOT NULL DEFAULT 1")?;
+        add_column(conn, "purchase_orders", "foreign_total_amount", "REAL")?;
+        conn.execute("UPDATE purchase_orders SET foreign_total_amount = total_amount", [])?;
+    }
+    Ok(())
+}
+
+fn price_tiers(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS price_tiers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            discount_percent REAL NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE TABLE IF NOT EXISTS price_tier_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            tier_id INTEGER NOT NULL REFERENCES price_tiers(id) ON DELETE CASCADE,
+            product_id INTEGER NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+            price REAL NOT NULL,
+            UNIQUE(tier_id, product_id)
+        );",
+    )?;
+
+    add_column(conn, "customers", "price_tier_id", "INTEGER")?;
+
+    // The tier applied at billing time is snapshotted on the invoice, so later tier
+    // changes never alter existing invoices
+    add_column(conn, "invoices", "price_tier_id", "INTEGER")?;
+    add_column(conn, "invoices", "price_tier_name", "TEXT")?;
+    Ok(())
+}
+
+fn bundles(conn: &Connection) -> Result<()> {
+    // A bundle holds no stock; selling it deducts its components, and the consumption is
+    // recorded per invoice line
+    add_column(conn, "products", "is_bundle", "INTEGER NOT NULL DEFAULT 0")?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS bundle_components (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            bundle_id INTEGER NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+            component_id INTEGER NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+            quantity INTEGER NOT NULL CHECK (quantity > 0),
+            UNIQUE(bundle_id, component_id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_bundle_components_component ON bundle_components(component_id);
+        CREATE TABLE IF NOT EXISTS invoice_item_components (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            invoice_item_id INTEGER NOT NULL REFERENCES invoice_items(id) ON DELETE CASCADE,
+            product_id INTEGER NOT NULL,
+            quantity INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_invoice_item_components_item ON invoice_item_components(invoice_item_id);",
+    )
+}
+
+fn invoice_item_tax(conn: &Connection) -> Result<()> {
+    // NULL on invoices taxed only at invoice level
+    add_column(conn, "invoice_items", "tax_rate", "REAL")?;
+    add_column(conn, "invoice_items", "tax_amount", "REAL")?;
+    Ok(())
+}
+
+fn invoice_round_off(conn: &Connection) -> Result<()> {
+    // Rounding adjustment included in total_amount
+    add_column(conn, "invoices", "round_off", "REAL NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+fn foreign_key_actions(conn: &Connection) -> Result<()> {
+    // Deleting a supplier or price tier unlinks its products and customers
+    rebuild_with_foreign_keys(conn, "products", &[("supplier_id", "suppliers", "SET NULL")])?;
+    rebuild_with_foreign_keys(conn, "customers", &[("price_tier_id", "price_tiers", "SET NULL")])?;
+    // Payments and FIFO batches outlive the purchase order they came from
+    rebuild_with_foreign_keys(conn, "supplier_payments", &[("po_id", "purchase_orders", "SET NULL")])?;
+    rebuild_with_foreign_keys(conn, "inventory_batches", &[("po_item_id", "purchase_order_items", "SET NULL")])?;
+    // Components consumed by sold bundles are needed to restock a deleted invoice
+    rebuild_with_foreign_keys(conn, "invoice_item_components", &[("product_id", "products", "RESTRICT")])?;
+    Ok(())
+}
+
+fn batch_consumptions(conn: &Connection) -> Result<()> {
+    // Which purchase batch each sold unit came from, written by record_sale_fifo. Purchase
+    // history and restock-on-delete read this instead of re-simulating FIFO.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS batch_consumptions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            invoice_id INTEGER NOT NULL REFERENCES invoices(id) ON DELETE CASCADE,
+            invoice_item_id INTEGER REFERENCES invoice_items(id) ON DELETE CASCADE,
+            product_id INTEGER NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+            batch_id INTEGER, -- no FK: batches are deleted once empty
+            po_item_id INTEGER REFERENCES purchase_order_items(id) ON DELETE SET NULL,
+            quantity INTEGER NOT NULL,
+            unit_cost REAL NOT NULL,
+            purchase_date TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_batch_consumptions_product ON batch_consumptions(product_id, po_item_id);
+        CREATE INDEX IF NOT EXISTS idx_batch_consumptions_invoice ON batch_consumptions(invoice_id, product_id);
+        CREATE INDEX IF NOT EXISTS idx_batch_consumptions_item ON batch_consumptions(invoice_item_id);",
+    )?;
+
+    let already_recorded: i64 = conn.query_row("SELECT COUNT(*) FROM batch_consumptions", [], |row| row.get(0))?;
+    if already_recorded > 0 {
+        return Ok(());
+    }
+
+    let product_ids: Vec<i32> = conn
+        .prepare("SELECT DISTINCT product_id FROM invoice_items")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+    for product_id in product_ids {
+        backfill_consumptions(conn, product_id)?;
+    }
+    Ok(())
+}
+
+/// Reconstruct consumptions for sales made before they were recorded by replaying FIFO:
+/// initial stock first, then purchase order lines, against sales in invoice order
+fn backfill_consumptions(conn: &Connection, product_id: i32) -> Result<()> {
+    // (po_item_id, remaining quantity, unit cost, purchase date)
+    let mut batches: Vec<(Option<i32>, i32, f64, String)> = Vec::new();
+
+    let initial: Option<(i32, f64, String)> = conn
+        .query_row(
+            "SELECT COALESCE(initial_stock, 0), price, substr(created_at, 1, 10) FROM products WHERE id = ?1",
+            [product_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .ok();
+    if let Some((quantity, cost, date)) = initial {
+        if quantity > 0 {
+            batches.push((None, quantity, cost, date));
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT poi.id, poi.quantity, poi.unit_cost * COALESCE(po.exchange_rate, 1),
+                COALESCE(po.order_date, substr(poi.created_at, 1, 10))
+         FROM purchase_order_items poi
+         LEFT JOIN purchase_orders po ON poi.po_id = po.id
+         WHERE poi.product_id = ?1
+         ORDER BY poi.created_at ASC, poi.id ASC",
+    )?;
+    let po_batches = stmt
+        .query_map([product_id], |row| Ok((Some(row.get(0)?), row.get(1)?, row.get(2)?, row.get(3)?)))?
+        .collect::<Result<Vec<_>>>()?;
+    batches.extend(po_batches);
+
+    let mut stmt = conn.prepare(
+        "SELECT ii.id, ii.invoice_id, ii.quantity
+         FROM invoice_items ii
+         JOIN invoices i ON ii.invoice_id = i.id
+         WHERE ii.product_id = ?1
+         ORDER BY i.created_at ASC, ii.id ASC",
+    )?;
+    let sales = stmt
+        .query_map([product_id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, i32>(2)?)))?
+        .collect::<Result<Vec<_>>>()?;
+
+    // Batches are only ever drained front to back
+    let mut next = 0;
+    for (item_id, invoice_id, mut quantity) in sales {
+        while quantity > 0 && next < batches.len() {
+            let (po_item_id, remaining, unit_cost, date) = &mut batches[next];
+            let take = quantity.min(*remaining);
+            conn.execute(
+                "INSERT INTO batch_consumptions
+                 (invoice_id, invoice_item_id, product_id, po_item_id, quantity, unit_cost, purchase_date)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                rusqlite::params![invoice_id, item_id, product_id, *po_item_id, take, *unit_cost, date.as_str()],
+            )?;
+            *remaining -= take;
+            quantity -= take;
+            if *remaining == 0 {
+                next += 1;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn customer_credit_limit(conn: &Connection) -> Result<()> {
+    add_column(conn, "customers", "credit_limit", "REAL")?;
+    Ok(())
+}
+
+fn invoice_due_date(conn: &Connection) -> Result<()> {
+    if add_column(conn, "invoices", "due_date", "TEXT")? {
+        // Existing credit invoices fall due one credit period after they were raised
+        conn.execute(
+            "UPDATE invoices
+             SET due_date = date(created_at, '+' || COALESCE(
+                 (SELECT CAST(value AS INTEGER) FROM app_settings WHERE key = 'credit_period_days'), 30
+             ) || ' days')
+             WHERE credit_amount > 0 OR payment_method = 'Credit'",
+            [],
+        )?;
+    }
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_invoices_due_date ON invoices(due_date)", [])?;
+    Ok(())
+}
+
+fn payment_reminders(conn: &Connection) -> Result<()> {
+    // One row per customer per day a reminder went out
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS payment_reminders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            customer_id INTEGER NOT NULL REFERENCES customers(id) ON DELETE CASCADE,
+            reminder_date TEXT NOT NULL,
+            channel TEXT,
+            sent_by TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            UNIQUE (customer_id, reminder_date)
+        );",
+    )
+}
+
+fn action_approvals(conn: &Connection) -> Result<()> {
+    // Single-use biometric approvals for protected actions; only the token hash is stored
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS action_approvals (
+            token_hash TEXT PRIMARY KEY,
+            action TEXT NOT NULL,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            expires_at TEXT NOT NULL,
+            used_at TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );",
+    )
+}
+
+fn image_content_hash(conn: &Connection) -> Result<()> {
+    // SHA-256 of the stored image file, so identical uploads share one file
+    for table in ["products", "suppliers", "customers"] {
+        add_column(conn, table, "image_hash", "TEXT")?;
+    }
+    Ok(())
+}
+
+fn product_variants(conn: &Connection) -> Result<()> {
+    // A variant is a regular product (own SKU, stock and price) linked to the product it is a
+    // size/color of; variant_attributes holds e.g. {"size":"L","color":"Blue"}
+    add_column(conn, "products", "parent_product_id", "INTEGER REFERENCES products(id) ON DELETE RESTRICT")?;
+    add_column(conn, "products", "variant_attributes", "TEXT")?;
+    conn.execute_batch("CREATE INDEX IF NOT EXISTS idx_products_parent ON products(parent_product_id);")
+}
+
+fn categories(conn: &Connection) -> Result<()> {
+    // Categories become records with one level of nesting. Existing free-text categories are
+    // folded case-insensitively (the most used spelling wins) and products are linked by id;
+    // products.category keeps the canonical name for callers still filtering by text.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS categories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            parent_id INTEGER REFERENCES categories(id) ON DELETE SET NULL,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE UNIQUE INDEX IF NOT EXISTS idx_categories_name ON categories(name COLLATE NOCASE);
+        CREATE INDEX IF NOT EXISTS idx_categories_parent ON categories(parent_id);",
+    )?;
+    add_column(conn, "products", "category_id", "INTEGER REFERENCES categories(id) ON DELETE SET NULL")?;
+    conn.execute_batch(
+        "INSERT OR IGNORE INTO categories (name)
+         SELECT TRIM(category) FROM products
+         WHERE category IS NOT NULL AND TRIM(category) <> ''
+         GROUP BY TRIM(category)
+         ORDER BY COUNT(*) DESC, TRIM(category);
+
+         UPDATE products
+         SET category_id = (SELECT c.id FROM categories c WHERE c.name = TRIM(products.category) COLLATE NOCASE)
+         WHERE category IS NOT NULL AND TRIM(category) <> '';
+
+         UPDATE products
+         SET category = (SELECT c.name FROM categories c WHERE c.id = products.category_id)
+         WHERE category_id IS NOT NULL;
+
+         CREATE INDEX IF NOT EXISTS idx_products_category ON products(category_id);",
+    )
+}
+
+fn daily_sales_summary(conn: &Connection) -> Result<()> {
+    // Cached per-day invoice totals keyed by local business date. The triggers drop the cached
+    // days around an invoice whenever it, its items or its stock movements change; the ±1 day
+    // window covers any business UTC offset. Dropped days are recomputed on the next read.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS daily_sales_summary (
+            date TEXT PRIMARY KEY,
+            revenue REAL NOT NULL DEFAULT 0,
+            orders INTEGER NOT NULL DEFAULT 0,
+            tax REAL NOT NULL DEFAULT 0,
+            discount REAL NOT NULL DEFAULT 0,
+            cogs REAL NOT NULL DEFAULT 0,
+            offset_minutes INTEGER NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+
+        CREATE TRIGGER IF NOT EXISTS trg_sales_summary_invoice_insert AFTER INSERT ON invoices
+        BEGIN
+            DELETE FROM daily_sales_summary
+            WHERE date BETWEEN date(NEW.created_at, '-1 day') AND date(NEW.created_at, '+1 day');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_sales_summary_invoice_update AFTER UPDATE ON invoices
+        BEGIN
+            DELETE FROM daily_sales_summary
+            WHERE date BETWEEN date(OLD.created_at, '-1 day') AND date(OLD.created_at, '+1 day')
+               OR date BETWEEN date(NEW.created_at, '-1 day') AND date(NEW.created_at, '+1 day');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_sales_summary_invoice_delete AFTER DELETE ON invoices
+        BEGIN
+            DELETE FROM daily_sales_summary
+            WHERE date BETWEEN date(OLD.created_at, '-1 day') AND date(OLD.created_at, '+1 day');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_sales_summary_item_insert AFTER INSERT ON invoice_items
+        BEGIN
+            DELETE FROM daily_sales_summary
+            WHERE date BETWEEN (SELECT date(created_at, '-1 day') FROM invoices WHERE id = NEW.invoice_id)
+                           AND (SELECT date(created_at, '+1 day') FROM invoices WHERE id = NEW.invoice_id);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_sales_summary_item_update AFTER UPDATE ON invoice_items
+        BEGIN
+            DELETE FROM daily_sales_summary
+            WHERE date BETWEEN (SELECT date(created_at, '-1 day') FROM invoices WHERE id = NEW.invoice_id)
+                           AND (SELECT date(created_at, '+1 day') FROM invoices WHERE id = NEW.invoice_id);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_sales_summary_item_delete AFTER DELETE ON invoice_items
+        BEGIN
+            DELETE FROM daily_sales_summary
+            WHERE date BETWEEN (SELECT date(created_at, '-1 day') FROM invoices WHERE id = OLD.invoice_id)
+                           AND (SELECT date(created_at, '+1 day') FROM invoices WHERE id = OLD.invoice_id);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_sales_summary_stock_insert AFTER INSERT ON inventory_transactions
+        WHEN NEW.reference_type = 'invoice'
+        BEGIN
+            DELETE FROM daily_sales_summary
+            WHERE date BETWEEN (SELECT date(created_at, '-1 day') FROM invoices WHERE id = NEW.reference_id)
+                           AND (SELECT date(created_at, '+1 day') FROM invoices WHERE id = NEW.reference_id);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_sales_summary_stock_delete AFTER DELETE ON inventory_transactions
+        WHEN OLD.reference_type = 'invoice'
+        BEGIN
+            DELETE FROM daily_sales_summary
+            WHERE date BETWEEN (SELECT date(created_at, '-1 day') FROM invoices WHERE id = OLD.reference_id)
+                           AND (SELECT date(created_at, '+1 day') FROM invoices WHERE id = OLD.reference_id);
+        END;",
+    )
+}
+
+fn held_sales(conn: &Connection) -> Result<()> {
+    // Parked billing drafts; draft is the CreateInvoiceInput JSON the bill will be created from
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS held_sales (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            label TEXT NOT NULL,
+            draft TEXT NOT NULL,
+            item_count INTEGER NOT NULL DEFAULT 0,
+            subtotal REAL NOT NULL DEFAULT 0,
+            held_by TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_held_sales_created ON held_sales(created_at);",
+    )
+}
+
+fn demo_records(conn: &Connection) -> Result<()> {
+    // Rows created by generate_demo_data, so clear_demo_data removes only those
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS demo_records (
+            entity_type TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (entity_type, entity_id)
+        );",
+    )
+}
+
+fn customer_archived(conn: &Connection) -> Result<()> {
+    // Customers with invoice or payment history are archived instead of deleted
+    add_column(conn, "customers", "is_archived", "INTEGER NOT NULL DEFAULT 0")?;
+    Ok(())
+}
+
+fn purchase_returns(conn: &Connection) -> Result<()> {
+    // Goods sent back to a supplier. reduces_payable = 0 means the supplier owes a refund instead
+    // of the return being set off against what we owe them.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS purchase_returns (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            return_number TEXT NOT NULL UNIQUE,
+            supplier_id INTEGER NOT NULL REFERENCES suppliers(id) ON DELETE CASCADE,
+            po_id INTEGER REFERENCES purchase_orders(id) ON DELETE SET NULL,
+            reason TEXT NOT NULL,
+            reduces_payable INTEGER NOT NULL DEFAULT 1,
+            total_amount REAL NOT NULL,
+            return_date TEXT NOT NULL,
+            created_by TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE TABLE IF NOT EXISTS purchase_return_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            return_id INTEGER NOT NULL REFERENCES purchase_returns(id) ON DELETE CASCADE,
+            product_id INTEGER NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+            po_item_id INTEGER REFERENCES purchase_order_items(id) ON DELETE SET NULL,
+            quantity INTEGER NOT NULL,
+            unit_cost REAL NOT NULL,
+            total_cost REAL NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_purchase_returns_supplier ON purchase_returns(supplier_id);
+        CREATE INDEX IF NOT EXISTS idx_purchase_return_items_return ON purchase_return_items(return_id);
+        CREATE INDEX IF NOT EXISTS idx_purchase_return_items_product ON purchase_return_items(product_id);",
+    )
+}
+
+fn supplier_payment_allocations(conn: &Connection) -> Result<()> {
+    // Explicit split of a supplier payment over PO lines; payments without rows here are
+    // spread over their PO in proportion to line value
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS supplier_payment_allocations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            payment_id INTEGER NOT NULL REFERENCES supplier_payments(id) ON DELETE CASCADE,
+            po_item_id INTEGER NOT NULL REFERENCES purchase_order_items(id) ON DELETE CASCADE,
+            amount REAL NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_supplier_payment_allocations_payment ON supplier_payment_allocations(payment_id);
+        CREATE INDEX IF NOT EXISTS idx_supplier_payment_allocations_item ON supplier_payment_allocations(po_item_id);",
+    )
+}
+
+fn invoice_item_price_index(conn: &Connection) -> Result<()> {
+    // Last-sold price and price history read a product's lines newest invoice first
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_invoice_items_product_invoice ON invoice_items(product_id, invoice_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn biometric_device_binding(conn: &Connection) -> Result<()> {
+    add_column(conn, "users", "biometric_device_id", "TEXT")?;
+    add_column(conn, "users", "biometric_token_expires_at", "TEXT")?;
+    // Existing enrollments get one token lifetime (90 days) from the upgrade and stay unbound
+    // until their first verification, which binds them to that device. Enrollments without a
+    // token hash can never verify, so they are switched off.
+    conn.execute_batch(
+        "UPDATE users SET biometric_enabled = 0 WHERE biometric_enabled = 1 AND biometric_token_hash IS NULL;
+         UPDATE users SET biometric_token_expires_at = datetime('now', '+90 days')
+         WHERE biometric_enabled = 1 AND biometric_token_expires_at IS NULL;",
+    )
+}
+
+fn invoice_created_utc_index(conn: &Connection) -> Result<()> {
+    // Date-range reports compare datetime(created_at), which normalizes the stored RFC 3339 text
+    // but can't use idx_invoices_created_at; indexing the expression itself turns those full
+    // scans into range searches (checked in db/query_plans.rs)
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_invoices_created_utc ON invoices(datetime(created_at))",
+        [],
+    )?;
+    Ok(())
+}
+
+fn user_preferences(conn: &Connection) -> Result<()> {
+    // Opaque JSON values (column layouts, filters) keyed per user; removed along with the user
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS user_preferences (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            UNIQUE(user_id, key)
+        );",
+    )
+}
+
+fn product_price_history(conn: &Connection) -> Result<()> {
+    // History has no foreign key so it survives a product's trip through the trash (restores keep
+    // the id); pending scheduled changes are dropped with the product
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS product_price_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL,
+            field TEXT NOT NULL CHECK (field IN ('price', 'selling_price')),
+            old_value REAL,
+            new_value REAL,
+            changed_at TEXT NOT NULL DEFAULT (datetime('now')),
+            changed_by TEXT,
+            scheduled_change_id INTEGER
+        );
+        CREATE INDEX IF NOT EXISTS idx_price_history_product ON product_price_history(product_id, changed_at);
+
+        CREATE TABLE IF NOT EXISTS scheduled_price_changes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+            price REAL,
+            selling_price REAL,
+            effective_at TEXT NOT NULL,
+            scheduled_by TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            applied_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_scheduled_price_changes_due
+            ON scheduled_price_changes(effective_at) WHERE applied_at IS NULL;
+        CREATE INDEX IF NOT EXISTS idx_scheduled_price_changes_product ON scheduled_price_changes(product_id);",
+    )
+}
+
+fn stock_reservations(conn: &Connection) -> Result<()> {
+    // Stock held for unpaid orders until expires_at (UTC "YYYY-MM-DD HH:MM:SS"); released and
+    // consumed reservations are deleted
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS stock_reservations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            product_id INTEGER NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+            quantity INTEGER NOT NULL CHECK (quantity > 0),
+            reference TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            created_by TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_stock_reservations_product ON stock_reservations(product_id, expires_at);
+        CREATE INDEX IF NOT EXISTS idx_stock_reservations_expires ON stock_reservations(expires_at);",
+    )
+}
+
+fn negative_stock_guard(conn: &Connection) -> Result<()> {
+    // Units of a sale line that stock didn't cover when it was billed (allow_negative_stock)
+    add_column(conn, "invoice_items", "backordered_quantity", "INTEGER NOT NULL DEFAULT 0")?;
+
+    // SQLite can't add a CHECK to an existing table, and the limit has to give way when the
+    // allow_negative_stock setting is on, so triggers stand in for it. Only writes that lower
+    // stock below zero are refused; products already negative can still be restocked or edited.
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS trg_products_stock_insert_not_negative BEFORE INSERT ON products
+        WHEN NEW.stock_quantity < 0
+         AND NOT EXISTS (SELECT 1 FROM app_settings WHERE key = 'allow_negative_stock' AND value = 'true')
+        BEGIN
+            SELECT RAISE(ABORT, 'Stock cannot go below zero');
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS trg_products_stock_update_not_negative BEFORE UPDATE OF stock_quantity ON products
+        WHEN NEW.stock_quantity < 0 AND NEW.stock_quantity < OLD.stock_quantity
+         AND NOT EXISTS (SELECT 1 FROM app_settings WHERE key = 'allow_negative_stock' AND value = 'true')
+        BEGIN
+            SELECT RAISE(ABORT, 'Stock cannot go below zero');
+        END;",
+    )
+}
+
+fn day_closures(conn: &Connection) -> Result<()> {
+    // One row per closed local business day ("YYYY-MM-DD"). The totals are a snapshot taken at
+    // closing; payment_methods is the JSON payment-method breakdown of that day's sales.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS day_closures (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            business_date TEXT NOT NULL UNIQUE,
+            invoice_count INTEGER NOT NULL DEFAULT 0,
+            revenue REAL NOT NULL DEFAULT 0,
+            payment_methods TEXT NOT NULL DEFAULT '[]',
+            cash_sales REAL NOT NULL DEFAULT 0,
+            credit_collections REAL NOT NULL DEFAULT 0,
+            cash_expenses REAL NOT NULL DEFAULT 0,
+            expected_cash REAL NOT NULL DEFAULT 0,
+            counted_cash REAL NOT NULL,
+            difference REAL NOT NULL,
+            notes TEXT,
+            closed_by TEXT,
+            closed_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );",
+    )
+}
+
+fn archive_schema_version(conn: &Connection) -> Result<()> {
+    // Layout of deleted_items.entity_data; rows archived before this are version 1 and are
+    // upgraded on restore (db::archive)
+    add_column(conn, "deleted_items", "schema_version", "INTEGER NOT NULL DEFAULT 1")?;
+    Ok(())
+}
+
+fn batch_original_quantity(conn: &Connection) -> Result<()> {
+    // Units a batch started with; existing batches get back what their recorded sales consumed
+    if add_column(conn, "inventory_batches", "original_quantity", "INTEGER")? {
+        conn.execute(
+            "UPDATE inventory_batches
+             SET original_quantity = quantity_remaining
+                 + COALESCE((SELECT SUM(bc.quantity) FROM batch_consumptions bc WHERE bc.batch_id = inventory_batches.id), 0)",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn weighted_average_costing(conn: &Connection) -> Result<()> {
+    // Running average unit cost, kept up to date while costing_method is "weighted_average".
+    // Seeded from the remaining batches so switching methods doesn't change stock value.
+    if add_column(conn, "products", "average_cost", "REAL")? {
+        conn.execute(
+            "UPDATE products
+             SET average_cost = (SELECT SUM(ib.quantity_remaining * ib.unit_cost) / SUM(ib.quantity_remaining)
+                                 FROM inventory_batches ib
+                                 WHERE ib.product_id = products.id AND ib.quantity_remaining > 0)",
+            [],
+        )?;
+    }
+
+    // Batches priced by the active costing method: their own cost under FIFO, the product's
+    // running average under weighted average. Stock valuation reads this instead of the table.
+    // product_values is the JSON per-product snapshot taken by convert_costing_method.
+    conn.execute_batch(
+        "CREATE VIEW IF NOT EXISTS inventory_batch_costs AS
+        SELECT ib.id, ib.product_id, ib.quantity_remaining,
+               CASE WHEN (SELECT value FROM app_settings WHERE key = 'costing_method') = 'weighted_average'
+                    THEN COALESCE(p.average_cost, ib.unit_cost)
+                    ELSE ib.unit_cost END AS unit_cost
+        FROM inventory_batches ib
+        LEFT JOIN products p ON p.id = ib.product_id;
+
+        CREATE TABLE IF NOT EXISTS costing_method_changes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            from_method TEXT NOT NULL,
+            to_method TEXT NOT NULL,
+            value_before REAL NOT NULL,
+            value_after REAL NOT NULL,
+            product_values TEXT NOT NULL DEFAULT '[]',
+            changed_by TEXT,
+            changed_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );",
+    )
+}
+
+fn webhook_outbox(conn: &Connection) -> Result<()> {
+    // Outbound webhook events waiting for or done with delivery (commands::webhooks). status is
+    // "pending" until a 2xx response ("delivered") or the last retry fails ("failed").
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS webhook_outbox (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            event_type TEXT NOT NULL,
+            entity_id INTEGER,
+            payload TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'pending',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at TEXT NOT NULL DEFAULT (datetime('now')),
+            last_attempt_at TEXT,
+            response_code INTEGER,
+            last_error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            delivered_at TEXT
+        );
+        CREATE INDEX IF NOT EXISTS idx_webhook_outbox_due ON webhook_outbox(status, next_attempt_at);",
+    )
+}
+
+fn recurring_invoice_templates(conn: &Connection) -> Result<()> {
+    // Invoices raised on a schedule (commands::recurring_invoices). payload is a CreateInvoiceInput
+    // as JSON; last_generated_date keeps a template to one invoice per day however often the due
+    // run happens, and last_error flags a run that was skipped (e.g. for lack of stock).
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS recurring_invoice_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            customer_id INTEGER,
+            payload TEXT NOT NULL,
+            frequency TEXT NOT NULL CHECK (frequency IN ('weekly', 'monthly')),
+            next_run_date TEXT NOT NULL,
+            active INTEGER NOT NULL DEFAULT 1,
+            last_generated_date TEXT,
+            last_invoice_id INTEGER,
+            last_error TEXT,
+            created_by TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (customer_id) REFERENCES customers(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_recurring_invoice_templates_due
+            ON recurring_invoice_templates(active, next_run_date);",
+    )
+}
+
+fn sales_targets(conn: &Connection) -> Result<()> {
+    // Revenue goals per month ("2025-03") or quarter ("2025-Q1"); actuals are always computed
+    // from invoices, so deleting a target loses nothing else (commands::sales_targets)
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS sales_targets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            period_type TEXT NOT NULL CHECK (period_type IN ('monthly', 'quarterly')),
+            period_key TEXT NOT NULL UNIQUE,
+            amount REAL NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );",
+    )
+}
+
+fn invoice_item_name_index(conn: &Connection) -> Result<()> {
+    // Invoice search matches the product_name snapshot on each line, so bills stay findable by
+    // what's printed on them after a product is renamed or deleted. Lines from before the
+    // snapshot existed take the name of their product, if it's still there.
+    conn.execute_batch(
+        "UPDATE invoice_items SET product_name = (SELECT name FROM products WHERE products.id = invoice_items.product_id)
+         WHERE product_name IS NULL;
+        CREATE INDEX IF NOT EXISTS idx_invoice_items_product_name ON invoice_items(product_name COLLATE NOCASE);",
+    )
+}
+
+fn stock_alerts(conn: &Connection) -> Result<()> {
+    // Per-product reorder level; products without one use the low_stock_threshold setting.
+    // stock_alert_log holds when each product last raised a stock-alert (for the cooldown) and
+    // stock_digests one low-stock summary per business day (commands::stock_alerts).
+    add_column(conn, "products", "reorder_level", "INTEGER")?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS stock_alert_log (
+            product_id INTEGER PRIMARY KEY REFERENCES products(id) ON DELETE CASCADE,
+            stock_quantity INTEGER NOT NULL,
+            alerted_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE TABLE IF NOT EXISTS stock_digests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            digest_date TEXT NOT NULL UNIQUE,
+            low_stock_count INTEGER NOT NULL,
+            out_of_stock_count INTEGER NOT NULL,
+            products TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );",
+    )
+}
+
+fn product_units(conn: &Connection) -> Result<()> {
+    // Unit a product is stocked and sold in (services::quantity::UNITS). Only products with
+    // allow_fractional set may hold or sell part of a unit, e.g. 3.5 m of cable.
+    add_column(conn, "products", "unit", "TEXT NOT NULL DEFAULT 'pcs'")?;
+    add_column(conn, "products", "allow_fractional", "INTEGER NOT NULL DEFAULT 0")?;
+
+    // Quantities that can now be fractional are declared REAL; whole quantities already stored
+    // read back the same
+    let columns: &[(&str, &[&str])] = &[
+        ("products", &["stock_quantity", "initial_stock", "quantity_sold"]),
+        ("invoice_items", &["quantity", "backordered_quantity"]),
+        ("invoice_item_components", &["quantity"]),
+        ("purchase_order_items", &["quantity"]),
+        ("inventory_batches", &["quantity_remaining", "original_quantity"]),
+        ("inventory_transactions", &["quantity_change", "balance_after"]),
+        ("batch_consumptions", &["quantity"]),
+        ("purchase_return_items", &["quantity"]),
+        ("stock_reservations", &["quantity"]),
+        ("stock_alert_log", &["stock_quantity"]),
+    ];
+    for (table, names) in columns {
+        rebuild_table(conn, table, "fractional quantities", |sql| {
+            names.iter().fold(sql.to_string(), |sql, name| with_column_type(&sql, name, "REAL"))
+        })?;
+    }
+    Ok(())
+}
+
+fn po_received_quantity(conn: &Connection) -> Result<()> {
+    // Units of a PO line received so far; the rest of an ordered or partially received PO is
+    // on order. POs were only ever created as received, so their lines arrived in full.
+    if add_column(conn, "purchase_order_items", "received_quantity", "REAL NOT NULL DEFAULT 0")? {
+        conn.execute(
+            "UPDATE purchase_order_items SET received_quantity = quantity
+             WHERE po_id IN (SELECT id FROM purchase_orders WHERE status = 'received')",
+            [],
+        )?;
+    }
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_purchase_orders_status ON purchase_orders(status)", [])?;
+    Ok(())
+}
+
+fn invoice_tenders(conn: &Connection) -> Result<()> {
+    // How an invoice paid with several methods (cash + UPI) was paid. Invoices without rows here
+    // were paid entirely by invoices.payment_method.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS invoice_tenders (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            invoice_id INTEGER NOT NULL REFERENCES invoices(id) ON DELETE CASCADE,
+            method TEXT NOT NULL,
+            amount REAL NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_invoice_tenders_invoice ON invoice_tenders(invoice_id);",
+    )
+}
+
+fn price_overrides(conn: &Connection) -> Result<()> {
+    // Lines billed under their list (or tier) price, with who did it and why. The invoice and
+    // product are snapshotted so the log survives the invoice being deleted.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS price_overrides (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            invoice_id INTEGER REFERENCES invoices(id) ON DELETE SET NULL,
+            invoice_number TEXT NOT NULL,
+            invoice_item_id INTEGER,
+            product_id INTEGER NOT NULL,
+            product_name TEXT NOT NULL,
+            quantity REAL NOT NULL,
+            list_price REAL NOT NULL,
+            charged_price REAL NOT NULL,
+            reason TEXT NOT NULL,
+            cashier TEXT,
+            approved_by TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_price_overrides_created ON price_overrides(created_at);",
+    )
+}
+
+fn modification_indexes(conn: &Connection) -> Result<()> {
+    // The modifications history filters by entity and pages by date; entity timelines also
+    // look up an entity's trash rows
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_entity_modifications_entity ON entity_modifications(entity_type, entity_id);
+        CREATE INDEX IF NOT EXISTS idx_entity_modifications_modified_at ON entity_modifications(modified_at);
+        CREATE INDEX IF NOT EXISTS idx_deleted_items_entity ON deleted_items(entity_type, entity_id);",
+    )
+}
+
+fn delivery_challans(conn: &Connection) -> Result<()> {
+    // Goods sent to a customer before they are invoiced. An open challan holds its stock either
+    // as stock_reservations (reservation_id on the line) or by taking it out of stock, in which
+    // case challan_batch_consumptions records the batches it came from for a return.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS delivery_challans (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            challan_number TEXT NOT NULL UNIQUE,
+            customer_id INTEGER REFERENCES customers(id) ON DELETE SET NULL,
+            status TEXT NOT NULL DEFAULT 'open' CHECK (status IN ('open', 'invoiced', 'returned')),
+            stock_mode TEXT NOT NULL CHECK (stock_mode IN ('reserve', 'deduct')),
+            notes TEXT,
+            invoice_id INTEGER REFERENCES invoices(id) ON DELETE SET NULL,
+            created_by TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            closed_at TEXT
+        );
+        CREATE TABLE IF NOT EXISTS delivery_challan_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            challan_id INTEGER NOT NULL REFERENCES delivery_challans(id) ON DELETE CASCADE,
+            product_id INTEGER NOT NULL REFERENCES products(id),
+            product_name TEXT NOT NULL,
+            quantity REAL NOT NULL CHECK (quantity > 0),
+            unit_price REAL NOT NULL,
+            reservation_id INTEGER
+        );
+        CREATE TABLE IF NOT EXISTS challan_batch_consumptions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            challan_item_id INTEGER NOT NULL REFERENCES delivery_challan_items(id) ON DELETE CASCADE,
+            product_id INTEGER NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+            batch_id INTEGER, -- no FK: batches are deleted once empty
+            po_item_id INTEGER REFERENCES purchase_order_items(id) ON DELETE SET NULL,
+            quantity REAL NOT NULL,
+            unit_cost REAL NOT NULL,
+            purchase_date TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_delivery_challans_status ON delivery_challans(status, created_at);
+        CREATE INDEX IF NOT EXISTS idx_delivery_challan_items_challan ON delivery_challan_items(challan_id);
+        CREATE INDEX IF NOT EXISTS idx_challan_batch_consumptions_item ON challan_batch_consumptions(challan_item_id);",
+    )
+}
+
+fn integrity_reports(conn: &Connection) -> Result<()> {
+    // One row per run of the data integrity checks; checks holds each check's result and the
+    // rows it flagged as JSON (commands::integrity)
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS integrity_reports (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source TEXT NOT NULL CHECK (source IN ('manual', 'scheduled')),
+            business_date TEXT NOT NULL,
+            passed INTEGER NOT NULL,
+            failed_count INTEGER NOT NULL,
+            checks TEXT NOT NULL DEFAULT '[]',
+            duration_ms INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL DEFAULT (datetime('now'))
+        );
+        CREATE INDEX IF NOT EXISTS idx_integrity_reports_date ON integrity_reports(source, business_date);",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categories_are_folded_from_product_text() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE products (id INTEGER PRIMARY KEY, name TEXT, category TEXT);
+             INSERT INTO products (name, category) VALUES
+                ('TV', 'Electronics'), ('Radio', 'electronics '), ('Phone', 'Electronics'),
+                ('Pen', 'Stationery'), ('Misc', ''), ('Loose', NULL);",
+        )
+        .unwrap();
+        categories(&conn).unwrap();
+
+        let names: Vec<String> = conn
+            .prepare("SELECT name FROM categories ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(names, vec!["Electronics", "Stationery"]);
+
+        let (linked, category): (i32, String) = conn
+            .query_row("SELECT category_id, category FROM products WHERE name = 'Radio'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((linked, category.as_str()), (1, "Electronics"));
+        let unlinked: i32 = conn
+            .query_row("SELECT COUNT(*) FROM products WHERE category_id IS NULL", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(unlinked, 2);
+    }
+
+    #[test]
+    fn test_existing_biometric_enrollments_get_an_expiry() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, username TEXT, biometric_enabled INTEGER, biometric_token_hash TEXT);
+             INSERT INTO users (username, biometric_enabled, biometric_token_hash) VALUES
+                ('enrolled', 1, 'abc'), ('broken', 1, NULL), ('none', 0, NULL);",
+        )
+        .unwrap();
+        biometric_device_binding(&conn).unwrap();
+
+        let rows: Vec<(String, i32, bool, bool)> = conn
+            .prepare(
+                "SELECT username, biometric_enabled, biometric_token_expires_at > datetime('now'), biometric_device_id IS NULL
+                 FROM users ORDER BY id",
+            )
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get::<_, Option<bool>>(2)?.unwrap_or(false), row.get(3)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("enrolled".to_string(), 1, true, true),
+                ("broken".to_string(), 0, false, true),
+                ("none".to_string(), 0, false, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_versions_are_ordered_and_unique() {
+        for pair in MIGRATIONS.windows(2) {
+            assert_eq!(pair[1].version, pair[0].version + 1);
+        }
+        assert_eq!(MIGRATIONS[0].version, 1);
+    }
+
+    #[test]
+    fn test_fresh_database_reaches_latest_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(column_exists(&conn, "invoices", "round_off").unwrap());
+
+        // A second run is a no-op
+        run_migrations(&mut conn).unwrap();
+        let applied: i32 = conn.query_row("SELECT COUNT(*) FROM schema_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(applied, latest_version());
+    }
+
+    #[test]
+    fn test_unversioned_database_upgrades() {
+        // An install from before versioning: old tables, some columns already added by hand
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES_SQL).unwrap();
+        conn.execute("ALTER TABLE invoices ADD COLUMN initial_paid REAL DEFAULT 0", []).unwrap();
+        conn.execute_batch(
+            "INSERT INTO products (id, name, sku, price, stock_quantity) VALUES (1, 'Pen', 'PEN', 10, 5);
+             INSERT INTO invoices (id, invoice_number, total_amount) VALUES (1, 'INV-000001', 10);
+             INSERT INTO invoice_items (invoice_id, product_id, quantity, unit_price) VALUES (1, 1, 1, 10);",
+        )
+        .unwrap();
+
+        run_migrations(&mut conn).unwrap();
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert!(column_exists(&conn, "invoices", "credit_amount").unwrap());
+        assert!(column_exists(&conn, "supplier_payments", "po_id").unwrap());
+
+        // Existing rows pick up column defaults
+        let discount: f64 = conn.query_row("SELECT discount_amount FROM invoice_items", [], |row| row.get(0)).unwrap();
+        assert_eq!(discount, 0.0);
+    }
+
+    #[test]
+    fn test_with_foreign_key() {
+        let sql = "CREATE TABLE t (id INTEGER, supplier_id INTEGER,\n    FOREIGN KEY (supplier_id) REFERENCES suppliers(id)\n)";
+        let edited = with_foreign_key(sql, "supplier_id", "suppliers", "SET NULL");
+        assert!(edited.contains("FOREIGN KEY (supplier_id) REFERENCES suppliers(id) ON DELETE SET NULL"));
+        assert_eq!(with_foreign_key(&edited, "supplier_id", "suppliers", "CASCADE"), edited);
+
+        let sql = "CREATE TABLE t (id INTEGER, po_id INTEGER REFERENCES purchase_orders(id))";
+        assert_eq!(
+            with_foreign_key(sql, "po_id", "purchase_orders", "SET NULL"),
+            "CREATE TABLE t (id INTEGER, po_id INTEGER REFERENCES purchase_orders(id) ON DELETE SET NULL)"
+        );
+
+        let sql = "CREATE TABLE t (id INTEGER, tier_id INTEGER)";
+        assert_eq!(
+            with_foreign_key(sql, "tier_id", "price_tiers", "SET NULL"),
+            "CREATE TABLE t (id INTEGER, tier_id INTEGER,\n    FOREIGN KEY (tier_id) REFERENCES price_tiers(id) ON DELETE SET NULL\n)"
+        );
+    }
+
+    #[test]
+    fn test_with_column_type() {
+        let sql = "CREATE TABLE t (id INTEGER, stock_quantity INTEGER NOT NULL, quantity INTEGER NOT NULL)";
+        assert_eq!(
+            with_column_type(sql, "quantity", "REAL"),
+            "CREATE TABLE t (id INTEGER, stock_quantity INTEGER NOT NULL, quantity REAL NOT NULL)"
+        );
+        assert_eq!(with_column_type(sql, "quantity_sold", "REAL"), sql);
+    }
+
+    #[test]
+    fn test_fractional_quantities_keep_triggers_and_views() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO products (id, name, sku, price, stock_quantity, unit, allow_fractional)
+             VALUES (1, 'Cable', 'CBL', 40, 96.5, 'm', 1);
+             INSERT INTO inventory_batches (product_id, quantity_remaining, unit_cost, purchase_date)
+             VALUES (1, 96.5, 40, '2025-01-01');",
+        )
+        .unwrap();
+
+        let stock: f64 = conn.query_row("SELECT stock_quantity FROM products WHERE id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(stock, 96.5);
+        let value: f64 = conn.query_row("SELECT SUM(quantity_remaining * unit_cost) FROM inventory_batch_costs", [], |row| row.get(0)).unwrap();
+        assert_eq!(value, 3860.0);
+        // The negative stock guard survived the products rebuild
+        assert!(conn.execute("UPDATE products SET stock_quantity = -0.5 WHERE id = 1", []).is_err());
+    }
+
+    #[test]
+    fn test_rebuilt_tables_keep_rows_and_indexes() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(CREATE_TABLES_SQL).unwrap();
+        conn.execute_batch(
+            "INSERT INTO suppliers (id, name) VALUES (1, 'Acme');
+             INSERT INTO products (id, name, sku, price, stock_quantity, supplier_id) VALUES (7, 'Pen', 'PEN', 10, 5, 1);
+             DELETE FROM products WHERE id = 7;
+             INSERT INTO products (id, name, sku, price, stock_quantity, supplier_id) VALUES (3, 'Ink', 'INK', 20, 2, 1);",
+        )
+        .unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        let index_count: i32 = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'idx_products_sku'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(index_count, 1);
+
+        // Deleted ids are not reused
+        conn.execute("INSERT INTO products (name, sku, price, stock_quantity) VALUES ('Pad', 'PAD', 5, 1)", []).unwrap();
+        assert_eq!(conn.last_insert_rowid(), 8);
+
+        conn.execute_batch("PRAGMA foreign_keys = ON; DELETE FROM suppliers WHERE id = 1;").unwrap();
+        let supplier_id: Option<i32> = conn.query_row("SELECT supplier_id FROM products WHERE id = 3", [], |row| row.get(0)).unwrap();
+        assert_eq!(supplier_id, None);
+    }
+}
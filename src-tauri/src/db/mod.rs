@@ -1,6 +1,9 @@
 pub mod connection;
+pub mod migrations;
 pub mod models;
 pub mod schema;
+#[cfg(test)]
+mod query_plans;
 
 pub use connection::Database;
 pub use models::*;
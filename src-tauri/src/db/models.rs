@@ -8,25 +8,50 @@ pub struct Product {
     pub sku: String,
     pub price: f64,
     pub selling_price: Option<f64>,
-    pub initial_stock: Option<i32>,
-    pub stock_quantity: i32,
-    pub quantity_sold: Option<i32>,
+    pub initial_stock: Option<f64>,
+    pub stock_quantity: f64,
+    pub quantity_sold: Option<f64>,
     pub sold_revenue: Option<f64>, // Added for actual revenue tracking
     pub supplier_id: Option<i32>,
     pub created_at: String,
     pub updated_at: String,
     pub image_path: Option<String>,
     pub category: Option<String>,
+    /// Linked category record; `category` holds its name
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub total_sold: Option<i64>,
+    pub category_id: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub initial_stock_sold: Option<i32>,
+    pub total_sold: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_stock_sold: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_purchased_cost: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub total_purchased_quantity: Option<i64>,
+    pub total_purchased_quantity: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_sold_amount: Option<f64>, // Actual revenue after discounts
+    /// Parent product when this product is a variant, e.g. one size of a shirt
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_product_id: Option<i32>,
+    /// Variant attributes as a JSON object, e.g. {"size":"L","color":"Blue"}
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant_attributes: Option<String>,
+    /// "Parent — L / Blue" for variants, the plain name otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// Number of variants rolled up into this product's stock and sales figures
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant_count: Option<i64>,
+    /// Stock not held by active reservations (set by get_product)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available_quantity: Option<f64>,
+    /// Units on open purchase orders still to arrive (set by get_product)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_order_quantity: Option<f64>,
+    /// "pcs", "kg", "g", "m" or "L"
+    pub unit: String,
+    /// Whether stock and sales may be part of a unit, e.g. 3.5 m
+    pub allow_fractional: bool,
 }
 
 /// Supplier model matching Prisma schema
@@ -44,6 +69,8 @@ pub struct Supplier {
     pub image_path: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    #[serde(default)]
+    pub opening_balance: Option<f64>, // Amount owed to the supplier before using the app
 }
 
 /// Customer model matching Prisma schema
@@ -60,6 +87,13 @@ pub struct Customer {
     pub town: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub image_path: Option<String>,
+    /// Maximum outstanding credit; None means unlimited
+    pub credit_limit: Option<f64>,
+    /// Deleted while it still had invoices or payments: hidden from the customer list but kept
+    /// so old bills still show the name
+    #[serde(default)]
+    pub is_archived: bool,
 }
 
 /// Invoice model matching Prisma schema
@@ -79,6 +113,12 @@ pub struct Invoice {
     pub gst_rate: Option<f64>,
     pub igst_amount: Option<f64>,
     pub sgst_amount: Option<f64>,
+    /// Rounding adjustment included in total_amount (e.g. -0.35 or +0.40)
+    #[serde(default)]
+    pub round_off: f64,
+    /// Date ("YYYY-MM-DD") a credit invoice is due to be paid by
+    #[serde(default)]
+    pub due_date: Option<String>,
     // Location fields
     pub state: Option<String>,
     pub district: Option<String>,
@@ -87,7 +127,7 @@ pub struct Invoice {
     pub customer_name: Option<String>,
     pub customer_phone: Option<String>,
     pub item_count: Option<i32>,
-    pub quantity: Option<i32>, // Quantity of specific product (context-dependent)
+    pub quantity: Option<f64>, // Quantity of specific product (context-dependent)
     pub product_amount: Option<f64>, // Amount for specific product after discount (context-dependent)
 }
 
@@ -97,7 +137,7 @@ pub struct InvoiceItem {
     pub id: i32,
     pub invoice_id: i32,
     pub product_id: i32,
-    pub quantity: i32,
+    pub quantity: f64,
     pub unit_price: f64,
 }
 
@@ -108,7 +148,7 @@ pub struct InvoiceItemWithProduct {
     pub product_id: i32,
     pub product_name: String,
     pub sku: String,
-    pub quantity: i32,
+    pub quantity: f64,
     pub unit_price: f64,
     pub total: f64,
     pub discount_amount: f64, // Per-item weighted discount
@@ -143,6 +183,18 @@ pub struct CustomerPayment {
     pub created_at: String,
 }
 
+/// Operating expense (rent, salaries, utilities...) used in the profit & loss report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Expense {
+    pub id: i32,
+    pub expense_date: String, // YYYY-MM-DD, business-local date
+    pub category: String,
+    pub amount: f64,
+    pub note: Option<String>,
+    pub payment_method: Option<String>,
+    pub created_at: String,
+}
+
 /// Customer invoice credit summary (for credit history display)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CustomerInvoiceCreditSummary {
@@ -163,6 +215,48 @@ pub struct CustomerCreditSummary {
     pub total_credit_amount: f64,
     pub total_paid: f64,
     pub pending_amount: f64,
+    /// Part of pending_amount not yet past its due date
+    pub current_amount: f64,
+    /// Part of pending_amount on invoices past their due date
+    pub overdue_amount: f64,
+}
+
+/// Credit invoice with an unpaid balance past its due date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OverdueInvoice {
+    pub invoice_id: i32,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub due_date: String,
+    pub days_overdue: i64,
+    pub bill_amount: f64,
+    pub total_paid: f64,
+    pub balance_remaining: f64,
+    pub customer_id: i32,
+    pub customer_name: String,
+    pub customer_phone: Option<String>,
+    pub customer_email: Option<String>,
+    pub customer_address: Option<String>,
+}
+
+/// Credit invoice with what has been paid against it (for the pending credit screen)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreditInvoice {
+    pub invoice_id: i32,
+    pub invoice_number: String,
+    pub invoice_date: String,
+    pub due_date: Option<String>,
+    /// Business days since the invoice date
+    pub days_outstanding: i64,
+    pub customer_id: Option<i32>,
+    pub customer_name: Option<String>,
+    pub customer_phone: Option<String>,
+    pub bill_amount: f64,
+    pub initial_paid: f64,
+    /// Payments so far, including the amount paid at billing
+    pub total_paid: f64,
+    pub balance_remaining: f64,
+    pub payment_status: String, // "paid", "partial" or "unpaid"
 }
 
 /// Deleted Item model for audit trail
@@ -200,11 +294,14 @@ pub struct PurchaseOrder {
     pub order_date: String,
     pub expected_delivery_date: Option<String>,
     pub received_date: Option<String>,
-    pub status: String, // 'draft', 'ordered', 'received', 'cancelled'
-    pub total_amount: f64,
+    pub status: String, // 'draft', 'ordered', 'partially_received', 'received', 'cancelled'
+    pub total_amount: f64, // Base currency (converted at exchange_rate)
     pub notes: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    pub currency: String, // Currency the supplier billed in, e.g. "USD"
+    pub exchange_rate: f64, // Base currency per unit of `currency`, pinned at creation
+    pub foreign_total_amount: f64, // Total in `currency`
 }
 
 /// Purchase Order with supplier details (for display)
@@ -233,7 +330,7 @@ pub struct PurchaseOrderItem {
     pub id: i32,
     pub po_id: i32,
     pub product_id: i32,
-    pub quantity: i32,
+    pub quantity: f64,
     pub unit_cost: f64,
     pub total_cost: f64,
     pub created_at: String,
@@ -248,13 +345,16 @@ pub struct PurchaseOrderItemWithProduct {
     pub product_id: i32,
     pub product_name: String,
     pub sku: String,
-    pub quantity: i32,
+    pub quantity: f64,
     pub unit_cost: f64,
     pub total_cost: f64,
     pub selling_price: Option<f64>,
-    pub quantity_sold: Option<i32>,
+    pub quantity_sold: Option<f64>,
     pub sold_revenue: Option<f64>,
     pub created_at: String,
+    /// A purchase return line (negative quantity and cost, po_number is the return number)
+    #[serde(default)]
+    pub is_return: bool,
 }
 
 /// Input model for creating purchase orders
@@ -265,14 +365,18 @@ pub struct CreatePurchaseOrderInput {
     pub order_date: Option<String>,
     pub expected_delivery_date: Option<String>,
     pub notes: Option<String>,
-    pub initial_payment: Option<f64>,
+    pub initial_payment: Option<f64>, // Base currency
+    #[serde(default)]
+    pub currency: Option<String>, // Defaults to the base currency; item unit costs are in this currency
+    #[serde(default)]
+    pub exchange_rate: Option<f64>, // Required for foreign currencies
 }
 
 /// Input model for purchase order items
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PurchaseOrderItemInput {
     pub product_id: i32,
-    pub quantity: i32,
+    pub quantity: f64,
     pub unit_cost: f64,
 }
 
@@ -297,7 +401,7 @@ pub struct InventoryBatch {
     pub id: i32,
     pub product_id: i32,
     pub po_item_id: Option<i32>,
-    pub quantity_remaining: i32,
+    pub quantity_remaining: f64,
     pub unit_cost: f64,
     pub purchase_date: String,
     pub created_at: String,
@@ -310,10 +414,16 @@ pub struct InventoryBatchWithDetails {
     pub product_id: i32,
     pub po_item_id: Option<i32>,
     pub po_number: Option<String>,
-    pub quantity_remaining: i32,
+    /// "purchase_order" or "initial_stock"
+    pub source: String,
+    /// Units the batch started with (None for batches written before this was tracked)
+    pub original_quantity: Option<f64>,
+    pub quantity_remaining: f64,
     pub unit_cost: f64,
     pub batch_value: f64, // quantity_remaining * unit_cost
     pub purchase_date: String,
+    /// Whole days since purchase_date
+    pub age_days: i32,
     pub created_at: String,
 }
 
@@ -323,11 +433,11 @@ pub struct InventoryTransaction {
     pub id: i32,
     pub product_id: i32,
     pub transaction_type: String, // 'purchase', 'sale', 'adjustment'
-    pub quantity_change: i32,    // positive for purchases, negative for sales
+    pub quantity_change: f64,    // positive for purchases, negative for sales
     pub unit_cost: Option<f64>,
     pub reference_type: Option<String>, // 'purchase_order', 'invoice', 'adjustment'
     pub reference_id: Option<i32>,
-    pub balance_after: i32,
+    pub balance_after: f64,
     pub transaction_date: String,
     pub notes: Option<String>,
     pub created_at: String,
@@ -337,7 +447,7 @@ pub struct InventoryTransaction {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FifoCostBreakdown {
     pub batch_id: i32,
-    pub quantity_used: i32,
+    pub quantity_used: f64,
     pub unit_cost: f64,
     pub subtotal: f64,
 }
@@ -414,7 +524,7 @@ pub struct ProductWithInventoryValue {
     pub id: i32,
     pub name: String,
     pub sku: String,
-    pub stock_quantity: i32,
+    pub stock_quantity: f64,
     pub fifo_value: f64,
     pub average_cost: f64,
     pub batches_count: i32,
@@ -0,0 +1,144 @@
+/// Query plan checks for the hot query paths.
+///
+/// Each test runs EXPLAIN QUERY PLAN on the SQL a command actually executes (the statements are
+/// shared as constants with the commands) and fails if a table that should be searched through
+/// an index gets scanned instead. Rewriting one of these queries so that it no longer uses its
+/// index — wrapping an indexed column in a function, say — breaks the matching test.
+///
+/// Indexes these rely on:
+/// - idx_invoices_created_utc on datetime(created_at): every date-range report (migration 39)
+/// - idx_invoices_customer_created: a customer's invoices
+/// - idx_invoice_items_product / idx_invoice_items_product_invoice: a product's sales
+/// - idx_invoice_items_invoice: an invoice's lines
+/// - idx_po_items_product, idx_po_items_po: purchase order lines by product and by order
+/// - idx_supplier_payments_po: payments against a purchase order
+/// - idx_customers_name_id: the customer list in its default order
+///
+/// `benchmark_hot_queries` (ignored by default) times the date-range queries with and without
+/// idx_invoices_created_utc on a large generated dataset:
+/// `cargo test --release benchmark_hot_queries -- --ignored --nocapture`
+use rusqlite::Connection;
+use std::time::{Duration, Instant};
+
+use crate::commands::analytics::TOP_PRODUCTS_SQL;
+use crate::commands::customer_payments::CUSTOMER_CREDIT_INVOICES_SQL;
+use crate::commands::customers::{customers_page_sql, CustomerSort};
+use crate::commands::demo_data::{generate_demo_data_internal, DemoDataOptions};
+use crate::commands::invoices::INVOICES_BY_PRODUCT_SQL;
+use crate::commands::purchase_orders::{PO_TOTAL_PAID_SQL, PRODUCT_PO_ITEMS_SQL};
+use crate::commands::sales_summary::LIVE_DAILY_TOTALS_SQL;
+use crate::test_support::TestDb;
+
+/// The detail column of EXPLAIN QUERY PLAN, one entry per step
+fn query_plan(conn: &Connection, sql: &str) -> Vec<String> {
+    let mut stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql)).unwrap();
+    // Parameters stay unbound; the plan doesn't depend on their values
+    let mut rows = stmt.raw_query();
+    let mut plan = Vec::new();
+    while let Some(row) = rows.next().unwrap() {
+        plan.push(row.get::<_, String>(3).unwrap());
+    }
+    plan
+}
+
+/// Fail unless `table` (the name or alias used in the query) is searched through one of `indexes`
+fn assert_searches(plan: &[String], table: &str, indexes: &[&str]) {
+    let step = plan
+        .iter()
+        .find(|step| step.starts_with(&format!("SEARCH {} ", table)) || step.starts_with(&format!("SCAN {}", table)));
+    let uses_index = step.is_some_and(|step| {
+        indexes.iter().any(|index| {
+            step.contains(&format!("USING INDEX {} ", index)) || step.contains(&format!("USING COVERING INDEX {} ", index))
+        })
+    });
+    assert!(uses_index, "expected {} to be searched through one of {:?}, plan was:\n{}", table, indexes, plan.join("\n"));
+}
+
+#[test]
+fn test_date_range_reports_use_the_created_at_expression_index() {
+    let db = TestDb::new();
+    let conn = db.conn();
+
+    assert_searches(&query_plan(&conn, TOP_PRODUCTS_SQL), "i", &["idx_invoices_created_utc"]);
+    assert_searches(&query_plan(&conn, TOP_PRODUCTS_SQL), "ii", &["idx_invoice_items_invoice", "idx_invoice_items_invoice_product"]);
+    assert_searches(&query_plan(&conn, LIVE_DAILY_TOTALS_SQL), "invoices", &["idx_invoices_created_utc"]);
+}
+
+#[test]
+fn test_product_and_customer_lookups_use_their_indexes() {
+    let db = TestDb::new();
+    let conn = db.conn();
+
+    assert_searches(
+        &query_plan(&conn, INVOICES_BY_PRODUCT_SQL),
+        "ii",
+        &["idx_invoice_items_product", "idx_invoice_items_product_invoice"],
+    );
+    assert_searches(&query_plan(&conn, PRODUCT_PO_ITEMS_SQL), "poi", &["idx_po_items_product"]);
+    assert_searches(&query_plan(&conn, CUSTOMER_CREDIT_INVOICES_SQL), "i", &["idx_invoices_customer_created", "idx_invoices_customer"]);
+
+    let plan = query_plan(&conn, PO_TOTAL_PAID_SQL);
+    assert_searches(&plan, "supplier_payments", &["idx_supplier_payments_po"]);
+    assert_searches(&plan, "poi", &["idx_po_items_po"]);
+}
+
+#[test]
+fn test_customer_list_without_stats_reads_only_customers() {
+    let db = TestDb::new();
+    let conn = db.conn();
+
+    // One pass over the name index: no invoices, no sort step
+    let plan = query_plan(&conn, &customers_page_sql(CustomerSort::Name, false));
+    assert_eq!(plan, vec!["SCAN c USING INDEX idx_customers_name_id".to_string()]);
+
+    let plan = query_plan(&conn, &customers_page_sql(CustomerSort::RecentlyActive, true));
+    assert_searches(&plan, "i", &["idx_invoices_customer_created", "idx_invoices_customer"]);
+}
+
+/// Average time for `sql` bound to `params` over a few runs
+fn time_query(conn: &Connection, sql: &str, params: &[&dyn rusqlite::ToSql]) -> Duration {
+    const RUNS: u32 = 5;
+    let mut stmt = conn.prepare(sql).unwrap();
+    let started = Instant::now();
+    for _ in 0..RUNS {
+        let mut rows = stmt.query(params).unwrap();
+        while rows.next().unwrap().is_some() {}
+    }
+    started.elapsed() / RUNS
+}
+
+#[test]
+#[ignore = "generates a large dataset; run with --release -- --ignored --nocapture"]
+fn benchmark_hot_queries() {
+    let db = TestDb::new();
+    let mut conn = db.conn();
+    let options = DemoDataOptions {
+        products: Some(500),
+        customers: Some(2000),
+        purchase_orders: Some(200),
+        invoices: Some(100_000),
+        months: Some(24),
+        seed: Some(7),
+        ..Default::default()
+    };
+    let counts = generate_demo_data_internal(&mut conn, options).unwrap();
+    conn.execute_batch("ANALYZE").unwrap();
+    println!("{} invoices over 24 months", counts.invoices);
+
+    let now = chrono::Utc::now().naive_utc();
+    let start = (now - chrono::Duration::days(30)).format("%Y-%m-%d %H:%M:%S").to_string();
+    let end = now.format("%Y-%m-%d %H:%M:%S").to_string();
+    let run = |conn: &Connection| {
+        (
+            time_query(conn, TOP_PRODUCTS_SQL, &[&start, &end, &10]),
+            time_query(conn, LIVE_DAILY_TOTALS_SQL, &[&start, &end, &"+330 minutes"]),
+        )
+    };
+
+    let with_index = run(&conn);
+    conn.execute_batch("DROP INDEX idx_invoices_created_utc").unwrap();
+    let without_index = run(&conn);
+
+    println!("top products, last 30 days: {:?} without the index, {:?} with it", without_index.0, with_index.0);
+    println!("daily totals, last 30 days: {:?} without the index, {:?} with it", without_index.1, with_index.1);
+}
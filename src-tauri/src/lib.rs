@@ -1,12 +1,298 @@
 mod commands;
 mod db;
 mod services;
+#[cfg(test)]
+mod test_support;
 
 use db::Database;
 use tauri::{Manager, Emitter, menu::{MenuBuilder, MenuItemBuilder, SubmenuBuilder}};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+  let handler: fn(tauri::ipc::Invoke) -> bool = tauri::generate_handler![
+          commands::products::get_products,
+          commands::products::get_product,
+          commands::products::get_products_by_supplier,
+          commands::products::create_product,
+          commands::products::update_product,
+          commands::products::delete_product,
+          commands::products::merge_products,
+          commands::products::reconcile_product_stock,
+          commands::products::get_inventory_batches,
+          commands::products::add_mock_products,
+          commands::products::get_top_selling_products,
+          commands::products::get_products_by_ids,
+          commands::get_billing_snapshot,
+          commands::get_last_sold_price,
+          commands::get_product_price_history,
+          commands::price_history::get_product_price_timeline,
+          commands::price_history::set_scheduled_price,
+          commands::price_history::cancel_scheduled_price,
+          commands::reservations::reserve_stock,
+          commands::reservations::get_reservations,
+          commands::reservations::release_reservation,
+          commands::held_sales::hold_sale,
+          commands::held_sales::get_held_sales,
+          commands::held_sales::resume_held_sale,
+          commands::held_sales::discard_held_sale,
+          commands::products::get_unique_categories,
+    commands::get_suppliers,
+    commands::get_supplier,
+    commands::create_supplier,
+    commands::update_supplier,
+    commands::delete_supplier,
+    commands::add_mock_suppliers,
+    commands::create_supplier_payment,
+    commands::get_supplier_payments,
+    commands::get_all_product_payments,
+    commands::get_supplier_payment_summary,
+    commands::get_all_product_payment_summary,
+    commands::get_supplier_payables_aging,
+    commands::get_supplier_product_purchase_history,
+    commands::get_supplier_ledger,
+    commands::get_supplier_report,
+    commands::delete_supplier_payment,
+    commands::get_customers,
+    commands::get_customer,
+    commands::create_customer,
+    commands::update_customer,
+    commands::delete_customer,
+    commands::merge_customers,
+    commands::add_mock_customers,
+    commands::generate_demo_data,
+    commands::clear_demo_data,
+    commands::get_dashboard_stats,
+    commands::get_low_stock_products,
+    commands::customer_search,
+    commands::get_customer_report,
+    // New analytics commands
+    commands::get_sales_analytics,
+    commands::get_revenue_trend,
+    commands::get_top_products,
+    commands::get_sales_by_payment_method,
+    commands::get_sales_by_category,
+    commands::get_sales_by_region,
+    commands::get_customer_analytics,
+    commands::get_top_customers,
+    commands::get_customer_trend,
+    commands::get_inventory_health,
+    commands::get_inventory_valuation,
+    commands::get_low_stock_alerts,
+    commands::get_slow_moving_stock,
+    commands::get_abc_analysis,
+    commands::get_sales_heatmap,
+    commands::get_purchase_analytics,
+    commands::get_cashflow_trend,
+    commands::get_top_suppliers,
+    commands::get_tax_summary,
+    commands::get_discount_analysis,
+    commands::get_profit_and_loss,
+    commands::export_analytics_report,
+    commands::rebuild_sales_summary,
+    commands::get_invoices,
+    commands::get_invoices_by_product,
+    commands::get_invoices_by_product_name,
+    commands::get_invoice,
+    commands::duplicate_invoice,
+    commands::get_product_sales_summary,
+    commands::create_invoice,
+    commands::delete_invoice,
+    commands::update_invoice,
+    commands::update_invoice_items,
+    commands::get_deleted_invoices,
+    commands::get_invoice_modifications,
+    commands::omnisearch,
+    commands::export_products_csv,
+    commands::export_customers_csv,
+    commands::get_deleted_items,
+    commands::restore_customer,
+    commands::restore_product,
+    commands::restore_supplier,
+    commands::restore_invoice,
+    commands::permanently_delete_item,
+    commands::clear_trash,
+    commands::purge_expired_trash,
+    commands::validate_trash,
+    commands::get_all_modifications,
+    commands::get_entity_timeline,
+    commands::restore_modification,
+    commands::permanently_delete_modification,
+    commands::clear_modifications_history,
+    commands::login,
+    commands::logout,
+    commands::get_users,
+    commands::create_user,
+    commands::update_user,
+    commands::delete_user,
+    commands::create_purchase_order,
+    commands::get_purchase_orders,
+    commands::get_purchase_order_by_id,
+    commands::update_purchase_order_status,
+    commands::add_payment_to_purchase_order,
+    commands::get_product_purchase_summary,
+    commands::get_incoming_stock,
+    commands::get_product_purchase_history,
+    commands::create_purchase_return,
+    commands::get_purchase_returns,
+    commands::migrate_existing_products,
+    commands::check_migration_status,
+    commands::validate_migration,
+    commands::repair_migration,
+    commands::get_schema_version,
+    // Settings commands
+    commands::get_app_setting,
+    commands::set_app_setting,
+    commands::get_all_settings,
+    commands::delete_app_setting,
+    commands::export_settings_json,
+    commands::import_settings_json,
+    commands::get_billing_defaults,
+    commands::set_billing_defaults,
+    // User preference commands
+    commands::get_user_preference,
+    commands::set_user_preference,
+    commands::get_all_user_preferences,
+    // Image commands
+    commands::save_product_image,
+    commands::download_product_image,
+    commands::get_product_image_path,
+    commands::delete_product_image,
+    commands::search_google_images,
+    commands::get_image_search_status,
+    commands::set_image_search_key,
+    commands::get_pictures_directory,
+    commands::migrate_images,
+    commands::scan_image_orphans,
+    commands::cleanup_image_orphans,
+    commands::migrate_compress_existing_images,
+    // Supplier & Customer Image commands
+    commands::save_supplier_image,
+    commands::get_supplier_image_path,
+    commands::delete_supplier_image,
+    commands::save_customer_image,
+    commands::get_customer_image_path,
+    commands::delete_customer_image,
+    // Biometric authentication commands
+    commands::generate_biometric_token,
+    commands::verify_biometric_token,
+    commands::verify_action_biometric,
+    commands::set_protected_actions,
+    commands::disable_biometric,
+    commands::get_biometric_status,
+    commands::get_biometric_status_by_username,
+    commands::has_any_biometric_enrollment,
+    // Customer payment/credit commands
+    commands::create_customer_payment,
+    commands::get_customer_payments,
+    commands::get_invoice_payments,
+    commands::get_customer_credit_history,
+    commands::get_customer_credit_summary,
+    commands::get_overdue_invoices,
+    commands::get_credit_invoices,
+    commands::delete_customer_payment,
+    // AI Chat commands
+    commands::start_ai_sidecar,
+    commands::stop_ai_sidecar,
+    commands::check_ai_sidecar_status,
+    commands::check_sidecar_downloaded,
+    commands::download_ai_sidecar,
+    commands::unlock_ai_data,
+    commands::lock_ai_data,
+    commands::get_ai_context_snapshot,
+    commands::run_ai_sql_readonly,
+    commands::export_csv,
+    commands::export_products,
+    commands::import_csv_chunk,
+    commands::scan_duplicates,
+    commands::cancel_data_operation,
+    commands::preview_product_csv,
+    commands::import_products_csv,
+    commands::preview_customer_csv,
+    commands::preview_supplier_csv,
+    commands::import_customers_csv,
+    commands::import_suppliers_csv,
+    // Database maintenance commands
+    commands::get_database_stats,
+    commands::run_database_maintenance,
+    commands::find_orphaned_rows,
+    commands::get_maintenance_status,
+    commands::export_database_snapshot,
+    commands::import_database_snapshot,
+    commands::list_safety_snapshots,
+    commands::restore_safety_snapshot,
+    commands::get_costing_method,
+    commands::convert_costing_method,
+    commands::get_integration_status,
+    commands::set_integration_settings,
+    commands::get_webhook_deliveries,
+    commands::retry_webhook_delivery,
+    commands::save_purchase_order_pdf,
+    commands::mark_purchase_order_sent,
+    commands::create_recurring_template,
+    commands::get_recurring_templates,
+    commands::set_recurring_template_active,
+    commands::delete_recurring_template,
+    commands::process_due_recurring_invoices,
+    commands::set_sales_target,
+    commands::get_sales_target_progress,
+    commands::get_sales_targets,
+    commands::delete_sales_target,
+    commands::set_product_reorder_level,
+    commands::generate_daily_stock_digest,
+    commands::get_stock_digests,
+    commands::render_stock_digest_message,
+    commands::get_performance_metrics,
+    // Share commands
+    commands::get_share_templates,
+    commands::render_share_message,
+    commands::open_whatsapp_chat,
+    // Payment reminders
+    commands::get_payment_reminders,
+    commands::render_reminder_message,
+    commands::mark_reminder_sent,
+    // Receipt printing
+    commands::print_receipt,
+    // Expenses
+    commands::create_expense,
+    commands::get_expenses,
+    commands::delete_expense,
+    // Day closures (Z-reports)
+    commands::close_business_day,
+    commands::get_day_closure,
+    commands::get_closure_history,
+    // Price Tiers
+    commands::get_price_tiers,
+    commands::create_price_tier,
+    commands::update_price_tier,
+    commands::delete_price_tier,
+    commands::set_price_tier_override,
+    commands::set_customer_price_tier,
+    commands::get_effective_price,
+    commands::get_price_list,
+    commands::get_price_overrides,
+    // Bundles
+    commands::get_bundle,
+    commands::set_bundle_components,
+    commands::get_bundle_component_usage,
+    commands::create_product_variants,
+    commands::get_product_variants,
+    commands::get_categories,
+    commands::create_category,
+    commands::update_category,
+    commands::delete_category,
+    commands::reassign_products_category,
+    commands::merge_categories,
+    // Delivery challans
+    commands::create_challan,
+    commands::get_challans,
+    commands::get_challan,
+    commands::convert_challan_to_invoice,
+    commands::return_challan,
+    // Data integrity checks
+    commands::run_integrity_checks,
+    commands::get_latest_integrity_report,
+  ];
+
   tauri::Builder::default()
     .plugin(tauri_plugin_log::Builder::default().build())
     .plugin(tauri_plugin_dialog::init())
@@ -28,12 +314,99 @@ pub fn run() {
       let db = Database::new(db_path)
         .expect("Failed to initialize database");
 
+      // Drop trash items past the retention window
+      match db.get_conn().and_then(|conn| commands::purge_expired_trash_items(&conn)) {
+        Ok(purged) if purged > 0 => log::info!("Purged {} expired trash items", purged),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to purge expired trash: {}", e),
+      }
+
+      // Drop modification history past the retention window
+      match db.get_conn().and_then(|conn| commands::purge_expired_modifications(&conn)) {
+        Ok(purged) if purged > 0 => log::info!("Purged {} expired modification records", purged),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to purge modification history: {}", e),
+      }
+
+      // Drop parked bills nobody came back for
+      match db.get_conn().and_then(|conn| commands::purge_stale_held_sales(&conn)) {
+        Ok(purged) if purged > 0 => log::info!("Purged {} stale held sales", purged),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to purge held sales: {}", e),
+      }
+
+      // Drop stock reservations that have run out
+      match db.get_conn().and_then(|conn| commands::purge_expired_reservations(&conn)) {
+        Ok(purged) if purged > 0 => log::info!("Purged {} expired stock reservations", purged),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to purge stock reservations: {}", e),
+      }
+
+      // Apply scheduled price changes that came due while the app was closed
+      match db.get_conn().and_then(|conn| commands::apply_due_price_changes(&conn)) {
+        Ok(applied) if applied > 0 => log::info!("Applied {} scheduled price changes", applied),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to apply scheduled price changes: {}", e),
+      }
+
+      // Raise recurring invoices that came due while the app was closed
+      match db.get_conn().and_then(|mut conn| commands::process_due_recurring_invoices_internal(&mut conn)) {
+        Ok(summary) if !summary.generated.is_empty() || !summary.skipped.is_empty() => log::info!(
+          "Recurring invoices: {} raised, {} skipped",
+          summary.generated.len(),
+          summary.skipped.len()
+        ),
+        Ok(_) => {}
+        Err(e) => log::warn!("Failed to process recurring invoices: {}", e),
+      }
+
+      // Summarise low stock once per business day
+      match db.get_conn().and_then(|conn| {
+        let today = commands::analytics::business_today(&conn);
+        commands::generate_daily_stock_digest_internal(&conn, today, false)
+      }) {
+        Ok(Some(digest)) => log::info!("Stock digest for {}: {} products low", digest.digest_date, digest.low_stock_count),
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to generate stock digest: {}", e),
+      }
+
       // Store database in app state
       app.manage(db);
 
       // Initialize AI sidecar state
       app.manage(commands::AiSidecarState::default());
 
+      // Initialize AI data access (locked until unlocked with password or biometrics)
+      app.manage(commands::AiDataAccess::default());
+
+      // Initialize image search result cache
+      app.manage(commands::ImageSearchCache::default());
+
+      // Initialize CSV import/export cancellation state
+      app.manage(commands::DataOperationState::default());
+
+      // Initialize database maintenance state
+      app.manage(commands::MaintenanceState::default());
+
+      // Initialize the login session (set by login and biometric verification)
+      app.manage(commands::AuthSession::default());
+
+      // Initialize command timing (recorded by the invoke handler below)
+      app.manage(services::metrics::CommandMetrics::default());
+
+      // Start the local integration listener when it is enabled in settings
+      app.manage(commands::IntegrationState::default());
+      if let Err(e) = commands::start_integration_server(app.handle()) {
+        log::warn!("Integration listener not started: {}", e);
+      }
+
+      // Start the outbound webhook sender (idle unless a webhook URL is set)
+      app.manage(commands::WebhookState::default());
+      commands::start_webhook_worker(app.handle());
+
+      // Start the nightly integrity checks (idle unless integrity_checks_nightly is on)
+      commands::start_integrity_scheduler(app.handle());
+
       // Create Settings menu item
       let settings_item = MenuItemBuilder::with_id("settings", "Settings...").build(app)?;
 
@@ -74,142 +447,28 @@ pub fn run() {
       log::info!("Application initialized successfully");
       Ok(())
     })
-    .invoke_handler(tauri::generate_handler![
-            commands::products::get_products,
-            commands::products::get_product,
-            commands::products::get_products_by_supplier,
-            commands::products::create_product,
-            commands::products::update_product,
-            commands::products::delete_product,
-            commands::products::add_mock_products,
-            commands::products::get_top_selling_products,
-            commands::products::get_products_by_ids,
-            commands::products::get_unique_categories,
-      commands::get_suppliers,
-      commands::get_supplier,
-      commands::create_supplier,
-      commands::update_supplier,
-      commands::delete_supplier,
-      commands::add_mock_suppliers,
-      commands::create_supplier_payment,
-      commands::get_supplier_payments,
-      commands::get_all_product_payments,
-      commands::get_supplier_payment_summary,
-      commands::get_all_product_payment_summary,
-      commands::get_supplier_product_purchase_history,
-      commands::delete_supplier_payment,
-      commands::get_customers,
-      commands::get_customer,
-      commands::create_customer,
-      commands::update_customer,
-      commands::delete_customer,
-      commands::add_mock_customers,
-      commands::get_dashboard_stats,
-      commands::get_low_stock_products,
-      commands::customer_search,
-      commands::get_customer_report,
-      // New analytics commands
-      commands::get_sales_analytics,
-      commands::get_revenue_trend,
-      commands::get_top_products,
-      commands::get_sales_by_payment_method,
-      commands::get_sales_by_region,
-      commands::get_customer_analytics,
-      commands::get_top_customers,
-      commands::get_customer_trend,
-      commands::get_inventory_health,
-      commands::get_low_stock_alerts,
-      commands::get_purchase_analytics,
-      commands::get_cashflow_trend,
-      commands::get_top_suppliers,
-      commands::get_tax_summary,
-      commands::get_discount_analysis,
-      commands::get_invoices,
-      commands::get_invoices_by_product,
-      commands::get_invoice,
-      commands::get_product_sales_summary,
-      commands::create_invoice,
-      commands::delete_invoice,
-      commands::update_invoice,
-      commands::update_invoice_items,
-      commands::get_deleted_invoices,
-      commands::get_invoice_modifications,
-      commands::omnisearch,
-      commands::export_products_csv,
-      commands::export_customers_csv,
-      commands::get_deleted_items,
-      commands::restore_customer,
-      commands::restore_product,
-      commands::restore_supplier,
-      commands::permanently_delete_item,
-      commands::restore_supplier,
-      commands::permanently_delete_item,
-      commands::clear_trash,
-      commands::get_all_modifications,
-      commands::restore_modification,
-      commands::permanently_delete_modification,
-      commands::clear_modifications_history,
-      commands::login,
-      commands::get_users,
-      commands::create_user,
-      commands::update_user,
-      commands::delete_user,
-      commands::create_purchase_order,
-      commands::get_purchase_orders,
-      commands::get_purchase_order_by_id,
-      commands::update_purchase_order_status,
-      commands::add_payment_to_purchase_order,
-      commands::get_product_purchase_summary,
-      commands::get_product_purchase_history,
-      commands::migrate_existing_products,
-      commands::check_migration_status,
-      commands::validate_migration,
-      // Settings commands
-      commands::get_app_setting,
-      commands::set_app_setting,
-      commands::get_all_settings,
-      commands::delete_app_setting,
-      commands::export_settings_json,
-      commands::import_settings_json,
-      // Image commands
-      commands::save_product_image,
-      commands::download_product_image,
-      commands::get_product_image_path,
-      commands::delete_product_image,
-      commands::search_google_images,
-      commands::get_pictures_directory,
-      commands::migrate_images,
-      // Supplier & Customer Image commands
-      commands::save_supplier_image,
-      commands::get_supplier_image_path,
-      commands::delete_supplier_image,
-      commands::save_customer_image,
-      commands::get_customer_image_path,
-      commands::delete_customer_image,
-      // Biometric authentication commands
-      commands::generate_biometric_token,
-      commands::verify_biometric_token,
-      commands::disable_biometric,
-      commands::get_biometric_status,
-      commands::get_biometric_status_by_username,
-      commands::has_any_biometric_enrollment,
-      // Customer payment/credit commands
-      commands::create_customer_payment,
-      commands::get_customer_payments,
-      commands::get_invoice_payments,
-      commands::get_customer_credit_history,
-      commands::get_customer_credit_summary,
-      commands::delete_customer_payment,
-      // AI Chat commands
-      commands::start_ai_sidecar,
-      commands::stop_ai_sidecar,
-      commands::check_ai_sidecar_status,
-      commands::check_sidecar_downloaded,
-      commands::download_ai_sidecar,
-      commands::export_csv,
-      commands::import_csv_chunk,
-      commands::scan_duplicates,
-    ])
+    // Writes are refused while a restore, import or maintenance run holds the maintenance lock,
+    // and every command is timed for get_performance_metrics
+    .invoke_handler(move |invoke| {
+      let webview = invoke.message.webview();
+      let metrics = webview.state::<services::metrics::CommandMetrics>();
+      let command = invoke.message.command().to_string();
+      let started = std::time::Instant::now();
+
+      let allowed = webview
+        .state::<commands::MaintenanceState>()
+        .check_command_allowed(&command);
+      if let Err(message) = allowed {
+        log::warn!("Rejected {} during maintenance", command);
+        invoke.resolver.reject(message);
+        metrics.record(&command, started.elapsed(), false);
+        return true;
+      }
+
+      let handled = handler(invoke);
+      metrics.record(&command, started.elapsed(), handled);
+      handled
+    })
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
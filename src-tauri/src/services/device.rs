@@ -0,0 +1,36 @@
+/// Device Identity
+/// A random identifier for this installation, generated on first use and kept in the OS
+/// keyring so it stays with this machine and never travels with a copy of the database.
+/// Biometric enrollments are bound to it.
+use std::sync::OnceLock;
+use uuid::Uuid;
+
+use super::image_search::KEYRING_SERVICE;
+
+const DEVICE_ID_ENTRY: &str = "device_id";
+
+static DEVICE_ID: OnceLock<String> = OnceLock::new();
+
+/// This device's identifier, created and saved in the keyring the first time it's needed
+pub fn device_id() -> Result<String, String> {
+    if let Some(id) = DEVICE_ID.get() {
+        return Ok(id.clone());
+    }
+
+    let entry = keyring::Entry::new(KEYRING_SERVICE, DEVICE_ID_ENTRY)
+        .map_err(|e| format!("Keyring unavailable: {}", e))?;
+    let id = match entry.get_password() {
+        Ok(id) if !id.trim().is_empty() => id,
+        Ok(_) | Err(keyring::Error::NoEntry) => {
+            let id = Uuid::new_v4().to_string();
+            entry
+                .set_password(&id)
+                .map_err(|e| format!("Failed to save device id to keyring: {}", e))?;
+            log::info!("Generated a new device id");
+            id
+        }
+        Err(e) => return Err(format!("Failed to read device id from keyring: {}", e)),
+    };
+
+    Ok(DEVICE_ID.get_or_init(|| id).clone())
+}
@@ -0,0 +1,63 @@
+/// Data Change Events
+/// Commands emit a "data-changed" event after a change is committed, so every open window can
+/// refresh what it shows. The payload is a DataChanged:
+///
+///   { entity: "invoice" | "product" | "customer" | "supplier" | "purchase_order",
+///     operation: "created" | "updated" | "deleted" | "restored" | "imported",
+///     id: number | null,   // the record changed; null for batch operations
+///     count: number }      // records changed (1 unless it's a batch operation)
+///
+/// Batch operations (imports, bulk creates) emit one event with id null and the count.
+/// Stock movements caused by invoices and purchase orders (including returns against a PO) are
+/// announced under those entities rather than as product updates, so stock views should listen
+/// for "product", "invoice" and "purchase_order".
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+pub const DATA_CHANGED_EVENT: &str = "data-changed";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataEntity {
+    Invoice,
+    Product,
+    Customer,
+    Supplier,
+    PurchaseOrder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataOperation {
+    Created,
+    Updated,
+    Deleted,
+    Restored,
+    Imported,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DataChanged {
+    pub entity: DataEntity,
+    pub operation: DataOperation,
+    pub id: Option<i32>,
+    pub count: usize,
+}
+
+fn emit(app: &AppHandle, payload: DataChanged) {
+    if let Err(e) = app.emit(DATA_CHANGED_EVENT, &payload) {
+        log::warn!("Failed to emit {} for {:?}: {}", DATA_CHANGED_EVENT, payload, e);
+    }
+}
+
+/// Announce a change to one record
+pub fn emit_data_changed(app: &AppHandle, entity: DataEntity, operation: DataOperation, id: i32) {
+    emit(app, DataChanged { entity, operation, id: Some(id), count: 1 });
+}
+
+/// Announce a batch change as a single event; nothing is sent when no record changed
+pub fn emit_bulk_data_changed(app: &AppHandle, entity: DataEntity, operation: DataOperation, count: usize) {
+    if count > 0 {
+        emit(app, DataChanged { entity, operation, id: None, count });
+    }
+}
@@ -0,0 +1,412 @@
+/// Image Search Service
+/// Image search providers (Google Custom Search, Bing Image Search, a SearxNG instance) tried
+/// in the configured order, with their API keys kept in the OS keyring.
+
+use crate::commands::images::GoogleImageResult;
+use rusqlite::Connection;
+use std::fmt;
+
+/// Keyring service name the provider API keys are stored under
+pub(crate) const KEYRING_SERVICE: &str = "inventory_tauri";
+
+/// app_settings key: comma-separated providers to try in order, e.g. "google,searxng"
+pub const PROVIDER_ORDER_KEY: &str = "image_search_providers";
+const DEFAULT_PROVIDER_ORDER: &str = "google";
+
+/// app_settings key for the Bing endpoint (Azure resources can have their own)
+const BING_ENDPOINT_KEY: &str = "bing_search_endpoint";
+const DEFAULT_BING_ENDPOINT: &str = "https://api.bing.microsoft.com/v7.0/images/search";
+
+/// app_settings key for the SearxNG base URL, e.g. "http://localhost:8888"
+const SEARXNG_URL_KEY: &str = "searxng_url";
+
+pub const PROVIDERS: &[&str] = &["google", "bing", "searxng"];
+
+/// Why a provider couldn't return results
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageSearchError {
+    NotConfigured(String),
+    QuotaExceeded(String),
+    AuthFailed(String),
+    Network(String),
+    Provider(String),
+}
+
+impl ImageSearchError {
+    /// Prefix the frontend can match on
+    pub fn code(&self) -> &'static str {
+        match self {
+            ImageSearchError::NotConfigured(_) => "IMAGE_SEARCH_NOT_CONFIGURED",
+            ImageSearchError::QuotaExceeded(_) => "IMAGE_SEARCH_QUOTA_EXCEEDED",
+            ImageSearchError::AuthFailed(_) => "IMAGE_SEARCH_AUTH_FAILED",
+            ImageSearchError::Network(_) => "IMAGE_SEARCH_NETWORK_ERROR",
+            ImageSearchError::Provider(_) => "IMAGE_SEARCH_PROVIDER_ERROR",
+        }
+    }
+
+    fn message(&self) -> &str {
+        match self {
+            ImageSearchError::NotConfigured(m)
+            | ImageSearchError::QuotaExceeded(m)
+            | ImageSearchError::AuthFailed(m)
+            | ImageSearchError::Network(m)
+            | ImageSearchError::Provider(m) => m,
+        }
+    }
+}
+
+impl fmt::Display for ImageSearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
+
+/// A configured image search backend
+#[derive(Debug, Clone)]
+pub enum ImageSearchProvider {
+    Google { api_key: String, cx_id: String },
+    Bing { api_key: String, endpoint: String },
+    Searxng { base_url: String },
+}
+
+fn setting(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row("SELECT value FROM app_settings WHERE key = ?1", [key], |row| row.get::<_, String>(0))
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+fn keyring_entry(provider: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, &format!("image_search_{}", provider))
+        .map_err(|e| format!("Keyring unavailable: {}", e))
+}
+
+/// app_settings key older versions kept the provider's API key in
+fn legacy_key_setting(provider: &str) -> String {
+    format!("{}_api_key", provider)
+}
+
+/// A provider's API key: the keyring first, then a key still saved in app_settings
+pub fn api_key(conn: &Connection, provider: &str) -> Option<String> {
+    keyring_entry(provider)
+        .ok()
+        .and_then(|entry| entry.get_password().ok())
+        .filter(|k| !k.trim().is_empty())
+        .or_else(|| setting(conn, &legacy_key_setting(provider)))
+}
+
+/// Whether the provider's key is held in the keyring (rather than app_settings)
+pub fn key_in_keyring(provider: &str) -> bool {
+    keyring_entry(provider)
+        .ok()
+        .and_then(|entry| entry.get_password().ok())
+        .is_some()
+}
+
+/// Save a provider's API key in the keyring, or remove it when `api_key` is empty.
+/// Any copy left in app_settings is deleted either way.
+pub fn store_api_key(conn: &Connection, provider: &str, api_key: &str) -> Result<(), String> {
+    if !PROVIDERS.contains(&provider) {
+        return Err(format!("Unknown image search provider '{}'", provider));
+    }
+
+    let entry = keyring_entry(provider)?;
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        match entry.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(format!("Failed to remove key from keyring: {}", e)),
+        }
+    } else {
+        entry
+            .set_password(api_key)
+            .map_err(|e| format!("Failed to save key to keyring: {}", e))?;
+    }
+
+    conn.execute("DELETE FROM app_settings WHERE key = ?1", [legacy_key_setting(provider)])
+        .map_err(|e| format!("Failed to remove stored key: {}", e))?;
+    Ok(())
+}
+
+/// Provider names from the image_search_providers setting, in order
+pub fn provider_order(conn: &Connection) -> Vec<String> {
+    let order = setting(conn, PROVIDER_ORDER_KEY).unwrap_or_else(|| DEFAULT_PROVIDER_ORDER.to_string());
+    let mut providers: Vec<String> = Vec::new();
+    for name in order.split(',').map(|p| p.trim().to_lowercase()) {
+        if PROVIDERS.contains(&name.as_str()) && !providers.contains(&name) {
+            providers.push(name);
+        }
+    }
+    providers
+}
+
+impl ImageSearchProvider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageSearchProvider::Google { .. } => "google",
+            ImageSearchProvider::Bing { .. } => "bing",
+            ImageSearchProvider::Searxng { .. } => "searxng",
+        }
+    }
+
+    /// Load a provider's settings and key; NotConfigured when something is missing
+    pub fn load(conn: &Connection, name: &str) -> Result<Self, ImageSearchError> {
+        let missing = |what: &str| ImageSearchError::NotConfigured(format!("{}: {} not configured", name, what));
+
+        match name {
+            "google" => Ok(ImageSearchProvider::Google {
+                api_key: api_key(conn, "google").ok_or_else(|| missing("Google API key"))?,
+                cx_id: setting(conn, "google_cx_id").ok_or_else(|| missing("Google CX ID"))?,
+            }),
+            "bing" => Ok(ImageSearchProvider::Bing {
+                api_key: api_key(conn, "bing").ok_or_else(|| missing("Bing API key"))?,
+                endpoint: setting(conn, BING_ENDPOINT_KEY).unwrap_or_else(|| DEFAULT_BING_ENDPOINT.to_string()),
+            }),
+            "searxng" => Ok(ImageSearchProvider::Searxng {
+                base_url: setting(conn, SEARXNG_URL_KEY).ok_or_else(|| missing("SearxNG URL"))?,
+            }),
+            _ => Err(ImageSearchError::NotConfigured(format!("Unknown image search provider '{}'", name))),
+        }
+    }
+
+    pub async fn search(
+        &self,
+        client: &reqwest::Client,
+        query: &str,
+        limit: i32,
+    ) -> Result<Vec<GoogleImageResult>, ImageSearchError> {
+        let request = match self {
+            ImageSearchProvider::Google { api_key, cx_id } => client.get(format!(
+                "https://www.googleapis.com/customsearch/v1?key={}&cx={}&q={}&searchType=image&num={}",
+                api_key, cx_id, urlencoding::encode(query), limit
+            )),
+            ImageSearchProvider::Bing { api_key, endpoint } => client
+                .get(format!("{}?q={}&count={}", endpoint, urlencoding::encode(query), limit))
+                .header("Ocp-Apim-Subscription-Key", api_key),
+            ImageSearchProvider::Searxng { base_url } => client.get(format!(
+                "{}/search?q={}&categories=images&format=json",
+                base_url.trim_end_matches('/'),
+                urlencoding::encode(query)
+            )),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ImageSearchError::Network(format!("{}: {}", self.name(), e)))?;
+
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ImageSearchError::Network(format!("{}: {}", self.name(), e)))?;
+        if !status.is_success() {
+            return Err(classify_failure(self.name(), status.as_u16(), &body));
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&body)
+            .map_err(|e| ImageSearchError::Provider(format!("{}: invalid response: {}", self.name(), e)))?;
+        let mut results = match self {
+            ImageSearchProvider::Google { .. } => parse_google(&json),
+            ImageSearchProvider::Bing { .. } => parse_bing(&json),
+            ImageSearchProvider::Searxng { .. } => parse_searxng(&json),
+        };
+        results.truncate(limit.max(0) as usize);
+        Ok(results)
+    }
+}
+
+/// Turn a failed HTTP response into a quota, auth or general provider error
+fn classify_failure(provider: &str, status: u16, body: &str) -> ImageSearchError {
+    let body_lower = body.to_lowercase();
+    let quota = ["quota", "ratelimitexceeded", "dailylimitexceeded", "rate limit", "out of call volume"]
+        .iter()
+        .any(|marker| body_lower.contains(marker));
+    let auth = ["api key not valid", "keyinvalid", "invalid subscription key", "access denied", "unauthorized"]
+        .iter()
+        .any(|marker| body_lower.contains(marker));
+
+    match status {
+        429 => ImageSearchError::QuotaExceeded(format!("{}: request quota exceeded, try again later", provider)),
+        403 if quota => ImageSearchError::QuotaExceeded(format!("{}: daily quota used up", provider)),
+        401 | 403 => ImageSearchError::AuthFailed(format!("{}: API key rejected (HTTP {})", provider, status)),
+        400 if auth => ImageSearchError::AuthFailed(format!("{}: API key not valid", provider)),
+        _ => ImageSearchError::Provider(format!("{}: HTTP {}", provider, status)),
+    }
+}
+
+fn result(provider: &str, title: &str, link: &str, thumbnail: &str, display: &str) -> GoogleImageResult {
+    GoogleImageResult {
+        title: title.to_string(),
+        link: link.to_string(),
+        thumbnail_link: thumbnail.to_string(),
+        display_link: display.to_string(),
+        provider: provider.to_string(),
+    }
+}
+
+fn parse_google(json: &serde_json::Value) -> Vec<GoogleImageResult> {
+    json["items"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    Some(result(
+                        "google",
+                        item["title"].as_str()?,
+                        item["link"].as_str()?,
+                        item["image"]["thumbnailLink"].as_str().unwrap_or(""),
+                        item["displayLink"].as_str().unwrap_or(""),
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_bing(json: &serde_json::Value) -> Vec<GoogleImageResult> {
+    json["value"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    Some(result(
+                        "bing",
+                        item["name"].as_str().unwrap_or(""),
+                        item["contentUrl"].as_str()?,
+                        item["thumbnailUrl"].as_str().unwrap_or(""),
+                        item["hostPageDisplayUrl"].as_str().unwrap_or(""),
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_searxng(json: &serde_json::Value) -> Vec<GoogleImageResult> {
+    json["results"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    let link = item["img_src"].as_str().filter(|l| !l.is_empty())?;
+                    let page = item["url"].as_str().unwrap_or("");
+                    let display = page.split("://").nth(1).unwrap_or(page).split('/').next().unwrap_or("");
+                    Some(result(
+                        "searxng",
+                        item["title"].as_str().unwrap_or(""),
+                        link,
+                        item["thumbnail_src"].as_str().filter(|t| !t.is_empty()).unwrap_or(link),
+                        display,
+                    ))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Try each provider in order until one returns results. When none does, an empty list is
+/// returned if any provider answered; otherwise the first provider's error, with the
+/// fallbacks' errors appended.
+pub async fn search_with_fallback(
+    providers: &[Result<ImageSearchProvider, ImageSearchError>],
+    query: &str,
+    limit: i32,
+) -> Result<Vec<GoogleImageResult>, ImageSearchError> {
+    let client = reqwest::Client::new();
+    let mut errors: Vec<ImageSearchError> = Vec::new();
+    let mut answered = false;
+
+    for provider in providers {
+        let outcome = match provider {
+            Ok(provider) => provider.search(&client, query, limit).await,
+            Err(e) => Err(e.clone()),
+        };
+        match outcome {
+            Ok(results) if !results.is_empty() => return Ok(results),
+            Ok(_) => answered = true,
+            Err(e) => {
+                log::warn!("Image search failed, trying next provider: {}", e);
+                errors.push(e);
+            }
+        }
+    }
+
+    if answered {
+        return Ok(Vec::new());
+    }
+    combine_errors(errors)
+}
+
+fn combine_errors(errors: Vec<ImageSearchError>) -> Result<Vec<GoogleImageResult>, ImageSearchError> {
+    let mut errors = errors.into_iter();
+    let first = match errors.next() {
+        Some(first) => first,
+        None => return Err(ImageSearchError::NotConfigured("No image search provider is enabled".to_string())),
+    };
+
+    let fallbacks: Vec<String> = errors.map(|e| e.message().to_string()).collect();
+    if fallbacks.is_empty() {
+        return Err(first);
+    }
+    let message = format!("{} (fallbacks also failed: {})", first.message(), fallbacks.join("; "));
+    Err(match first {
+        ImageSearchError::NotConfigured(_) => ImageSearchError::NotConfigured(message),
+        ImageSearchError::QuotaExceeded(_) => ImageSearchError::QuotaExceeded(message),
+        ImageSearchError::AuthFailed(_) => ImageSearchError::AuthFailed(message),
+        ImageSearchError::Network(_) => ImageSearchError::Network(message),
+        ImageSearchError::Provider(_) => ImageSearchError::Provider(message),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_failure() {
+        assert_eq!(classify_failure("google", 429, "").code(), "IMAGE_SEARCH_QUOTA_EXCEEDED");
+        assert_eq!(
+            classify_failure("google", 403, r#"{"error":{"errors":[{"reason":"dailyLimitExceeded"}]}}"#).code(),
+            "IMAGE_SEARCH_QUOTA_EXCEEDED"
+        );
+        assert_eq!(classify_failure("google", 403, "forbidden").code(), "IMAGE_SEARCH_AUTH_FAILED");
+        assert_eq!(
+            classify_failure("google", 400, r#"{"error":{"message":"API key not valid. Please pass a valid API key."}}"#).code(),
+            "IMAGE_SEARCH_AUTH_FAILED"
+        );
+        assert_eq!(classify_failure("bing", 500, "").code(), "IMAGE_SEARCH_PROVIDER_ERROR");
+    }
+
+    #[test]
+    fn test_parse_provider_results() {
+        let bing = serde_json::json!({ "value": [
+            { "name": "Rice bag", "contentUrl": "https://x/rice.jpg", "thumbnailUrl": "https://x/t.jpg", "hostPageDisplayUrl": "x.com" },
+            { "name": "No url" }
+        ]});
+        let results = parse_bing(&bing);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].provider, "bing");
+
+        let searxng = serde_json::json!({ "results": [
+            { "title": "Rice", "img_src": "https://y/rice.png", "url": "https://shop.example.com/p/1" }
+        ]});
+        let results = parse_searxng(&searxng);
+        assert_eq!(results[0].display_link, "shop.example.com");
+        assert_eq!(results[0].thumbnail_link, "https://y/rice.png");
+    }
+
+    #[test]
+    fn test_fallback_errors_keep_primary_kind() {
+        let err = combine_errors(vec![
+            ImageSearchError::QuotaExceeded("google: daily quota used up".to_string()),
+            ImageSearchError::NotConfigured("bing: Bing API key not configured".to_string()),
+        ])
+        .unwrap_err();
+        assert_eq!(err.code(), "IMAGE_SEARCH_QUOTA_EXCEEDED");
+        assert!(err.to_string().contains("fallbacks also failed: bing"));
+    }
+}
@@ -1,5 +1,7 @@
 /// FIFO Inventory Service
-/// Handles FIFO cost calculation, batch management, and inventory transactions
+/// Handles FIFO cost calculation, batch management, and inventory transactions.
+/// Under weighted average costing the batches are still kept for quantities and ages, but
+/// sales are costed at the product's running average_cost instead of the batch costs.
 
 use rusqlite::{Connection, params, OptionalExtension};
 use chrono::Utc;
@@ -7,6 +9,94 @@ use chrono::Utc;
 use crate::db::models::{
     InventoryBatch, InventoryTransaction, FifoCostBreakdown, FifoSaleResult,
 };
+use crate::services::quantity::{self, from_milli, to_milli};
+
+// =============================================
+// COSTING METHOD
+// =============================================
+
+/// app_settings key holding the costing method; changed only by convert_costing_method
+pub const COSTING_METHOD_KEY: &str = "costing_method";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostingMethod {
+    Fifo,
+    WeightedAverage,
+}
+
+impl CostingMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CostingMethod::Fifo => "fifo",
+            CostingMethod::WeightedAverage => "weighted_average",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "fifo" => Some(CostingMethod::Fifo),
+            "weighted_average" => Some(CostingMethod::WeightedAverage),
+            _ => None,
+        }
+    }
+}
+
+/// Active costing method (FIFO when unset)
+pub fn costing_method(conn: &Connection) -> CostingMethod {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?",
+        params![COSTING_METHOD_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| CostingMethod::parse(&value))
+    .unwrap_or(CostingMethod::Fifo)
+}
+
+/// Running average cost of a product, falling back to the average of its remaining batches
+/// when none has been recorded yet
+fn current_average_cost(conn: &Connection, product_id: i32) -> Result<f64, String> {
+    let average: Option<f64> = conn.query_row(
+        "SELECT COALESCE(average_cost,
+                         (SELECT SUM(quantity_remaining * unit_cost) / SUM(quantity_remaining)
+                          FROM inventory_batches
+                          WHERE product_id = products.id AND quantity_remaining > 0))
+         FROM products WHERE id = ?",
+        params![product_id],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to get average cost: {}", e))?;
+
+    Ok(average.unwrap_or(0.0))
+}
+
+/// Fold units coming into stock at `unit_cost` into the product's running average.
+/// Only under weighted average costing; call it before the units' batch is inserted.
+fn blend_average_cost(conn: &Connection, product_id: i32, quantity: f64, unit_cost: f64) -> Result<(), String> {
+    if quantity <= 0.0 || costing_method(conn) != CostingMethod::WeightedAverage {
+        return Ok(());
+    }
+
+    let on_hand: f64 = conn.query_row(
+        "SELECT COALESCE(SUM(quantity_remaining), 0) FROM inventory_batches
+         WHERE product_id = ? AND quantity_remaining > 0",
+        params![product_id],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to get batch total: {}", e))?;
+
+    let average = if on_hand > 0.0 {
+        let current = current_average_cost(conn, product_id)?;
+        (on_hand * current + quantity * unit_cost) / (on_hand + quantity)
+    } else {
+        unit_cost
+    };
+
+    conn.execute(
+        "UPDATE products SET average_cost = ? WHERE id = ?",
+        params![average, product_id],
+    ).map_err(|e| format!("Failed to update average cost: {}", e))?;
+
+    Ok(())
+}
 
 // =============================================
 // FIFO COST CALCULATION
@@ -17,7 +107,7 @@ use crate::db::models::{
 pub fn calculate_fifo_cogs(
     conn: &Connection,
     product_id: i32,
-    quantity: i32,
+    quantity: f64,
 ) -> Result<FifoSaleResult, String> {
     // Get all batches for this product, ordered by purchase date (FIFO)
     let mut stmt = conn.prepare(
@@ -39,7 +129,8 @@ pub fn calculate_fifo_cogs(
         })
     }).map_err(|e| format!("Failed to query batches: {}", e))?;
 
-    let mut remaining_to_deduct = quantity;
+    // Takes are counted in thousandths so fractional quantities empty a batch exactly
+    let mut remaining_to_deduct = to_milli(quantity);
     let mut total_cogs = 0.0;
     let mut breakdown: Vec<FifoCostBreakdown> = Vec::new();
     let mut batches_depleted: Vec<i32> = Vec::new();
@@ -51,12 +142,13 @@ pub fn calculate_fifo_cogs(
 
         let batch = batch_result.map_err(|e| format!("Failed to process batch: {}", e))?;
 
-        let quantity_to_use = remaining_to_deduct.min(batch.quantity_remaining);
-        let subtotal = quantity_to_use as f64 * batch.unit_cost;
+        let batch_remaining = to_milli(batch.quantity_remaining);
+        let quantity_to_use = remaining_to_deduct.min(batch_remaining);
+        let subtotal = from_milli(quantity_to_use) * batch.unit_cost;
 
         breakdown.push(FifoCostBreakdown {
             batch_id: batch.id,
-            quantity_used: quantity_to_use,
+            quantity_used: from_milli(quantity_to_use),
             unit_cost: batch.unit_cost,
             subtotal,
         });
@@ -65,13 +157,17 @@ pub fn calculate_fifo_cogs(
         remaining_to_deduct -= quantity_to_use;
 
         // Track if batch will be fully depleted
-        if quantity_to_use >= batch.quantity_remaining {
+        if quantity_to_use >= batch_remaining {
             batches_depleted.push(batch.id);
         }
     }
 
     if remaining_to_deduct > 0 {
-        log::warn!("Insufficient inventory batches for product {}. calculated partial COGS. Missing: {}", product_id, remaining_to_deduct);
+        log::warn!(
+            "Insufficient inventory batches for product {}. calculated partial COGS. Missing: {}",
+            product_id,
+            quantity::format_quantity(from_milli(remaining_to_deduct))
+        );
     }
 
     Ok(FifoSaleResult {
@@ -82,28 +178,55 @@ pub fn calculate_fifo_cogs(
 }
 
 /// Record a sale and update batches using FIFO
-/// Returns the total COGS
+/// Returns the total COGS: the consumed batches' costs, or the running average cost per unit
+/// under weighted average costing
 pub fn record_sale_fifo(
     conn: &Connection,
     product_id: i32,
-    quantity_sold: i32,
+    quantity_sold: f64,
     sale_date: &str,
     invoice_id: i32,
+    invoice_item_id: Option<i32>,
 ) -> Result<f64, String> {
     // Calculate FIFO cost first
     let fifo_result = calculate_fifo_cogs(conn, product_id, quantity_sold)?;
+    let average_cost = match costing_method(conn) {
+        CostingMethod::WeightedAverage => Some(current_average_cost(conn, product_id)?),
+        CostingMethod::Fifo => None,
+    };
+    let mut total_cogs = 0.0;
 
     // Now actually update the batches
     for breakdown in &fifo_result.breakdown {
-        let new_quantity = conn.query_row(
-            "SELECT quantity_remaining FROM inventory_batches WHERE id = ?",
+        let (new_quantity, po_item_id, purchase_date) = conn.query_row(
+            "SELECT quantity_remaining, po_item_id, purchase_date FROM inventory_batches WHERE id = ?",
             params![breakdown.batch_id],
-            |row| row.get::<_, i32>(0),
+            |row| Ok((row.get::<_, f64>(0)?, row.get::<_, Option<i32>>(1)?, row.get::<_, String>(2)?)),
         ).map_err(|e| format!("Failed to get batch quantity: {}", e))?;
 
-        let updated_quantity = new_quantity - breakdown.quantity_used;
+        let unit_cost = average_cost.unwrap_or(breakdown.unit_cost);
+        total_cogs += breakdown.quantity_used * unit_cost;
 
-        if updated_quantity <= 0 {
+        // Remember which batch the units came from; the batch row itself is deleted once empty
+        conn.execute(
+            "INSERT INTO batch_consumptions
+             (invoice_id, invoice_item_id, product_id, batch_id, po_item_id, quantity, unit_cost, purchase_date)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                invoice_id,
+                invoice_item_id,
+                product_id,
+                breakdown.batch_id,
+                po_item_id,
+                breakdown.quantity_used,
+                unit_cost,
+                purchase_date,
+            ],
+        ).map_err(|e| format!("Failed to record batch consumption: {}", e))?;
+
+        let updated_quantity = quantity::sub(new_quantity, breakdown.quantity_used);
+
+        if updated_quantity <= 0.0 {
             // Delete fully depleted batch
             conn.execute(
                 "DELETE FROM inventory_batches WHERE id = ?",
@@ -119,7 +242,7 @@ pub fn record_sale_fifo(
     }
 
     // Get updated stock quantity
-    let current_stock: i32 = conn.query_row(
+    let current_stock: f64 = conn.query_row(
         "SELECT stock_quantity FROM products WHERE id = ?",
         params![product_id],
         |row| row.get(0),
@@ -138,7 +261,7 @@ pub fn record_sale_fifo(
         params![
             product_id,
             -quantity_sold, // Negative for sales
-            fifo_result.total_cogs / quantity_sold as f64, // Average cost
+            total_cogs / quantity_sold, // Average cost
             invoice_id,
             balance_after,
             sale_date,
@@ -146,7 +269,7 @@ pub fn record_sale_fifo(
         ],
     ).map_err(|e| format!("Failed to create transaction: {}", e))?;
 
-    Ok(fifo_result.total_cogs)
+    Ok(total_cogs)
 }
 
 // =============================================
@@ -157,31 +280,33 @@ pub fn record_sale_fifo(
 pub fn record_purchase(
     conn: &Connection,
     product_id: i32,
-    quantity: i32,
+    quantity: f64,
     unit_cost: f64,
     po_item_id: Option<i32>,
     purchase_date: &str,
 ) -> Result<i32, String> {
     let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
+    blend_average_cost(conn, product_id, quantity, unit_cost)?;
+
     // Create inventory batch
     conn.execute(
         "INSERT INTO inventory_batches
-         (product_id, po_item_id, quantity_remaining, unit_cost, purchase_date, created_at)
-         VALUES (?, ?, ?, ?, ?, ?)",
-        params![product_id, po_item_id, quantity, unit_cost, purchase_date, now],
+         (product_id, po_item_id, quantity_remaining, original_quantity, unit_cost, purchase_date, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![product_id, po_item_id, quantity, quantity, unit_cost, purchase_date, now],
     ).map_err(|e| format!("Failed to create batch: {}", e))?;
 
     let batch_id = conn.last_insert_rowid() as i32;
 
     // Get current stock
-    let current_stock: i32 = conn.query_row(
+    let current_stock: f64 = conn.query_row(
         "SELECT stock_quantity FROM products WHERE id = ?",
         params![product_id],
         |row| row.get(0),
     ).map_err(|e| format!("Failed to get stock quantity: {}", e))?;
 
-    let balance_after = current_stock + quantity;
+    let balance_after = quantity::sum([current_stock, quantity]);
 
     // Create inventory transaction
     conn.execute(
@@ -203,16 +328,18 @@ pub fn record_purchase(
     Ok(batch_id)
 }
 
-/// Restore stock from a deleted invoice (Reverse FIFO Sale)
+/// Restore stock from a deleted invoice (Reverse FIFO Sale). `backordered` marks a line sold
+/// past the stock on hand: its units without a batch consumption never came out of a batch,
+/// so they only go back on stock_quantity.
 pub fn restore_stock_from_invoice(
     conn: &Connection,
     product_id: i32,
-    quantity: i32,
+    quantity: f64,
     invoice_id: i32,
+    backordered: bool,
 ) -> Result<(), String> {
     // 1. Find the original 'sale' transaction for this invoice to get the unit cost (COGS)
     // We expect one 'sale' transaction per product per invoice usually.
-    // If there are multiple (split transactions?), we aggregate?
     // Usually record_sale_fifo creates ONE 'sale' transaction per product line item.
     let transaction: Option<(i32, f64)> = conn.query_row(
         "SELECT id, unit_cost FROM inventory_transactions 
@@ -221,31 +348,73 @@ pub fn restore_stock_from_invoice(
         |row| Ok((row.get(0)?, row.get(1)?)),
     ).optional().map_err(|e| format!("Failed to find transaction: {}", e))?;
 
-    let (transaction_id, unit_cost) = match transaction {
-        Some(t) => t,
-        None => {
-            // Fallback: If no transaction found (maybe legacy data?), use current average cost or 0?
-            // Safer to use 0 or current stock cost?
-            // Let's use 0 ensures we don't inflate value artificially if unknown.
-            // But this effectively "gifts" stock back.
-            (0, 0.0)
-        }
-    };
+    // No transaction (legacy data): restock at cost 0 rather than inflate stock value
+    let (transaction_id, unit_cost) = transaction.unwrap_or((0, 0.0));
 
-    // 2. Create a "Restock" batch using the original cost
     let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
     let purchase_date = Utc::now().format("%Y-%m-%d").to_string();
 
-    conn.execute(
-        "INSERT INTO inventory_batches
-         (product_id, po_item_id, quantity_remaining, unit_cost, purchase_date, created_at)
-         VALUES (?, NULL, ?, ?, ?, ?)",
-        params![product_id, quantity, unit_cost, purchase_date, now],
-    ).map_err(|e| format!("Failed to create restock batch: {}", e))?;
+    // 2. Put the units back into batches matching the ones they were sold from (cost, PO line
+    // and purchase date, so FIFO order is kept). The latest consumptions are returned first.
+    let consumptions: Vec<(i32, Option<i32>, f64, f64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, po_item_id, quantity, unit_cost, purchase_date FROM batch_consumptions
+             WHERE invoice_id = ? AND product_id = ?
+             ORDER BY purchase_date DESC, id DESC",
+        ).map_err(|e| format!("Failed to prepare consumptions query: {}", e))?;
+
+        let rows = stmt.query_map(params![invoice_id, product_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+        }).map_err(|e| format!("Failed to query consumptions: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect consumptions: {}", e))?
+    };
+
+    let mut remaining = to_milli(quantity);
+    for (consumption_id, po_item_id, consumed, batch_cost, batch_date) in consumptions {
+        if remaining <= 0 {
+            break;
+        }
+        let consumed = to_milli(consumed);
+        let take_milli = remaining.min(consumed);
+        let take = from_milli(take_milli);
+
+        blend_average_cost(conn, product_id, take, batch_cost)?;
+        conn.execute(
+            "INSERT INTO inventory_batches
+             (product_id, po_item_id, quantity_remaining, original_quantity, unit_cost, purchase_date, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![product_id, po_item_id, take, take, batch_cost, batch_date, now],
+        ).map_err(|e| format!("Failed to create restock batch: {}", e))?;
+
+        if take_milli == consumed {
+            conn.execute("DELETE FROM batch_consumptions WHERE id = ?", params![consumption_id])
+        } else {
+            conn.execute(
+                "UPDATE batch_consumptions SET quantity = ? WHERE id = ?",
+                params![from_milli(consumed - take_milli), consumption_id],
+            )
+        }.map_err(|e| format!("Failed to update batch consumption: {}", e))?;
+
+        remaining -= take_milli;
+    }
+
+    // Anything sold without a recorded consumption comes back at the sale's average cost
+    if remaining > 0 && !backordered {
+        let remaining = from_milli(remaining);
+        blend_average_cost(conn, product_id, remaining, unit_cost)?;
+        conn.execute(
+            "INSERT INTO inventory_batches
+             (product_id, po_item_id, quantity_remaining, original_quantity, unit_cost, purchase_date, created_at)
+             VALUES (?, NULL, ?, ?, ?, ?, ?)",
+            params![product_id, remaining, remaining, unit_cost, purchase_date, now],
+        ).map_err(|e| format!("Failed to create restock batch: {}", e))?;
+    }
 
     // 3. Update Product Stock Quantity
     conn.execute(
-        "UPDATE products SET stock_quantity = stock_quantity + ? WHERE id = ?",
+        "UPDATE products SET stock_quantity = ROUND(stock_quantity + ?, 3), updated_at = datetime('now') WHERE id = ?",
         params![quantity, product_id],
     ).map_err(|e| format!("Failed to restock product: {}", e))?;
 
@@ -261,18 +430,280 @@ pub fn restore_stock_from_invoice(
     Ok(())
 }
 
+// =============================================
+// PURCHASE RETURNS
+// =============================================
+
+/// Take units returned to a supplier out of stock. They come out of the batches of
+/// `po_item_id` first (initial stock batches when None), then from the oldest remaining batches.
+pub fn record_purchase_return(
+    conn: &Connection,
+    product_id: i32,
+    quantity: f64,
+    unit_cost: f64,
+    po_item_id: Option<i32>,
+    return_id: i32,
+    return_date: &str,
+) -> Result<(), String> {
+    let current_stock: f64 = conn.query_row(
+        "SELECT stock_quantity FROM products WHERE id = ?",
+        params![product_id],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to get stock quantity: {}", e))?;
+
+    if to_milli(quantity) > to_milli(current_stock) {
+        return Err(format!(
+            "Cannot return {} units of product {}: only {} in stock",
+            quantity::format_quantity(quantity), product_id, quantity::format_quantity(current_stock)
+        ));
+    }
+
+    let batches: Vec<(i32, f64)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, quantity_remaining FROM inventory_batches
+             WHERE product_id = ? AND quantity_remaining > 0
+             ORDER BY (po_item_id IS ?) DESC, purchase_date ASC, id ASC",
+        ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
+
+        let rows = stmt.query_map(params![product_id, po_item_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| format!("Failed to query batches: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect batches: {}", e))?
+    };
+
+    let mut remaining = to_milli(quantity);
+    for (batch_id, batch_quantity) in batches {
+        if remaining <= 0 {
+            break;
+        }
+        let batch_quantity = to_milli(batch_quantity);
+        let take = remaining.min(batch_quantity);
+
+        if take == batch_quantity {
+            conn.execute("DELETE FROM inventory_batches WHERE id = ?", params![batch_id])
+        } else {
+            conn.execute(
+                "UPDATE inventory_batches SET quantity_remaining = ? WHERE id = ?",
+                params![from_milli(batch_quantity - take), batch_id],
+            )
+        }.map_err(|e| format!("Failed to update batch: {}", e))?;
+
+        remaining -= take;
+    }
+
+    if remaining > 0 {
+        log::warn!(
+            "Insufficient inventory batches for product {} on purchase return. Missing: {}",
+            product_id,
+            quantity::format_quantity(from_milli(remaining))
+        );
+    }
+
+    let balance_after = quantity::sub(current_stock, quantity);
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    conn.execute(
+        "UPDATE products SET stock_quantity = ?, updated_at = ? WHERE id = ?",
+        params![balance_after, now, product_id],
+    ).map_err(|e| format!("Failed to update product stock: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO inventory_transactions
+         (product_id, transaction_type, quantity_change, unit_cost, reference_type,
+          reference_id, balance_after, transaction_date, created_at)
+         VALUES (?, 'purchase_return', ?, ?, 'purchase_return', ?, ?, ?, ?)",
+        params![
+            product_id,
+            -quantity,
+            unit_cost,
+            return_id,
+            balance_after,
+            return_date,
+            now,
+        ],
+    ).map_err(|e| format!("Failed to create transaction: {}", e))?;
+
+    Ok(())
+}
+
+// =============================================
+// DELIVERY CHALLANS
+// =============================================
+
+/// Take units sent out on a delivery challan out of stock, oldest batches first. The batches
+/// they came from are kept in challan_batch_consumptions so restore_stock_from_challan can put
+/// them back where they were.
+pub fn record_challan_dispatch(
+    conn: &Connection,
+    product_id: i32,
+    quantity: f64,
+    challan_id: i32,
+    challan_item_id: i32,
+    dispatch_date: &str,
+) -> Result<(), String> {
+    let fifo_result = calculate_fifo_cogs(conn, product_id, quantity)?;
+    let average_cost = match costing_method(conn) {
+        CostingMethod::WeightedAverage => Some(current_average_cost(conn, product_id)?),
+        CostingMethod::Fifo => None,
+    };
+    let mut total_cost = 0.0;
+
+    for breakdown in &fifo_result.breakdown {
+        let (batch_quantity, po_item_id, purchase_date) = conn.query_row(
+            "SELECT quantity_remaining, po_item_id, purchase_date FROM inventory_batches WHERE id = ?",
+            params![breakdown.batch_id],
+            |row| Ok((row.get::<_, f64>(0)?, row.get::<_, Option<i32>>(1)?, row.get::<_, String>(2)?)),
+        ).map_err(|e| format!("Failed to get batch quantity: {}", e))?;
+
+        let unit_cost = average_cost.unwrap_or(breakdown.unit_cost);
+        total_cost += breakdown.quantity_used * unit_cost;
+
+        conn.execute(
+            "INSERT INTO challan_batch_consumptions
+             (challan_item_id, product_id, batch_id, po_item_id, quantity, unit_cost, purchase_date)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![
+                challan_item_id,
+                product_id,
+                breakdown.batch_id,
+                po_item_id,
+                breakdown.quantity_used,
+                unit_cost,
+                purchase_date,
+            ],
+        ).map_err(|e| format!("Failed to record batch consumption: {}", e))?;
+
+        let updated_quantity = quantity::sub(batch_quantity, breakdown.quantity_used);
+        if updated_quantity <= 0.0 {
+            conn.execute("DELETE FROM inventory_batches WHERE id = ?", params![breakdown.batch_id])
+        } else {
+            conn.execute(
+                "UPDATE inventory_batches SET quantity_remaining = ? WHERE id = ?",
+                params![updated_quantity, breakdown.batch_id],
+            )
+        }.map_err(|e| format!("Failed to update batch: {}", e))?;
+    }
+
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    conn.execute(
+        "UPDATE products SET stock_quantity = ROUND(stock_quantity - ?, 3), updated_at = ? WHERE id = ?",
+        params![quantity, now, product_id],
+    ).map_err(|e| format!("Failed to update product stock: {}", e))?;
+    let balance_after: f64 = conn.query_row(
+        "SELECT stock_quantity FROM products WHERE id = ?",
+        params![product_id],
+        |row| row.get(0),
+    ).map_err(|e| format!("Failed to get stock quantity: {}", e))?;
+
+    conn.execute(
+        "INSERT INTO inventory_transactions
+         (product_id, transaction_type, quantity_change, unit_cost, reference_type,
+          reference_id, balance_after, transaction_date, created_at)
+         VALUES (?, 'challan', ?, ?, 'challan', ?, ?, ?, ?)",
+        params![
+            product_id,
+            -quantity,
+            total_cost / quantity,
+            challan_id,
+            balance_after,
+            dispatch_date,
+            now,
+        ],
+    ).map_err(|e| format!("Failed to create transaction: {}", e))?;
+
+    Ok(())
+}
+
+/// Put a challan line's units back into stock, into batches matching the ones they were taken
+/// from, and drop the line's 'challan' transaction
+pub fn restore_stock_from_challan(
+    conn: &Connection,
+    product_id: i32,
+    quantity: f64,
+    challan_id: i32,
+    challan_item_id: i32,
+) -> Result<(), String> {
+    let transaction: Option<(i32, f64)> = conn.query_row(
+        "SELECT id, unit_cost FROM inventory_transactions
+         WHERE reference_type = 'challan' AND reference_id = ? AND product_id = ? AND transaction_type = 'challan'",
+        params![challan_id, product_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    ).optional().map_err(|e| format!("Failed to find transaction: {}", e))?;
+    let (transaction_id, unit_cost) = transaction.unwrap_or((0, 0.0));
+
+    let consumptions: Vec<(Option<i32>, f64, f64, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT po_item_id, quantity, unit_cost, purchase_date FROM challan_batch_consumptions
+             WHERE challan_item_id = ?
+             ORDER BY purchase_date DESC, id DESC",
+        ).map_err(|e| format!("Failed to prepare consumptions query: {}", e))?;
+
+        let rows = stmt.query_map(params![challan_item_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        }).map_err(|e| format!("Failed to query consumptions: {}", e))?;
+
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to collect consumptions: {}", e))?
+    };
+
+    let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let mut remaining = to_milli(quantity);
+    for (po_item_id, consumed, batch_cost, batch_date) in consumptions {
+        if remaining <= 0 {
+            break;
+        }
+        let take = from_milli(remaining.min(to_milli(consumed)));
+
+        blend_average_cost(conn, product_id, take, batch_cost)?;
+        conn.execute(
+            "INSERT INTO inventory_batches
+             (product_id, po_item_id, quantity_remaining, original_quantity, unit_cost, purchase_date, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![product_id, po_item_id, take, take, batch_cost, batch_date, now],
+        ).map_err(|e| format!("Failed to create restock batch: {}", e))?;
+
+        remaining -= to_milli(take);
+    }
+
+    // Units that went out without a batch behind them come back at the dispatch's average cost
+    if remaining > 0 {
+        let remaining = from_milli(remaining);
+        blend_average_cost(conn, product_id, remaining, unit_cost)?;
+        conn.execute(
+            "INSERT INTO inventory_batches
+             (product_id, po_item_id, quantity_remaining, original_quantity, unit_cost, purchase_date, created_at)
+             VALUES (?, NULL, ?, ?, ?, ?, ?)",
+            params![product_id, remaining, remaining, unit_cost, Utc::now().format("%Y-%m-%d").to_string(), now],
+        ).map_err(|e| format!("Failed to create restock batch: {}", e))?;
+    }
+
+    conn.execute("DELETE FROM challan_batch_consumptions WHERE challan_item_id = ?", params![challan_item_id])
+        .map_err(|e| format!("Failed to clear batch consumptions: {}", e))?;
+    conn.execute(
+        "UPDATE products SET stock_quantity = ROUND(stock_quantity + ?, 3), updated_at = datetime('now') WHERE id = ?",
+        params![quantity, product_id],
+    ).map_err(|e| format!("Failed to restock product: {}", e))?;
+    if transaction_id > 0 {
+        conn.execute("DELETE FROM inventory_transactions WHERE id = ?", params![transaction_id])
+            .map_err(|e| format!("Failed to delete transaction: {}", e))?;
+    }
+
+    Ok(())
+}
+
 // =============================================
 // INVENTORY VALUATION
 // =============================================
 
-/// Get current inventory value for a product using FIFO
+/// Get current inventory value for a product under the active costing method
 pub fn get_product_inventory_value(
     conn: &Connection,
     product_id: i32,
 ) -> Result<f64, String> {
     let value: f64 = conn.query_row(
         "SELECT COALESCE(SUM(quantity_remaining * unit_cost), 0.0)
-         FROM inventory_batches
+         FROM inventory_batch_costs
          WHERE product_id = ?",
         params![product_id],
         |row| row.get(0),
@@ -290,7 +721,7 @@ pub fn get_inventory_value(
         Some(pid) => get_product_inventory_value(conn, pid)?,
         None => conn.query_row(
             "SELECT COALESCE(SUM(quantity_remaining * unit_cost), 0.0)
-             FROM inventory_batches",
+             FROM inventory_batch_costs",
             [],
             |row| row.get(0),
         ).map_err(|e| format!("Failed to calculate total inventory value: {}", e))?,
@@ -299,12 +730,17 @@ pub fn get_inventory_value(
     Ok(value)
 }
 
-/// Get average cost per unit for a product
+/// Get average cost per unit for a product: the remaining batches' average under FIFO, the
+/// running average under weighted average costing
 pub fn get_average_cost(
     conn: &Connection,
     product_id: i32,
 ) -> Result<f64, String> {
-    let result: (Option<f64>, Option<i32>) = conn.query_row(
+    if costing_method(conn) == CostingMethod::WeightedAverage {
+        return current_average_cost(conn, product_id);
+    }
+
+    let result: (Option<f64>, Option<f64>) = conn.query_row(
         "SELECT SUM(quantity_remaining * unit_cost), SUM(quantity_remaining)
          FROM inventory_batches
          WHERE product_id = ?",
@@ -313,8 +749,8 @@ pub fn get_average_cost(
     ).map_err(|e| format!("Failed to calculate average cost: {}", e))?;
 
     match result {
-        (Some(total_value), Some(total_qty)) if total_qty > 0 => {
-            Ok(total_value / total_qty as f64)
+        (Some(total_value), Some(total_qty)) if total_qty > 0.0 => {
+            Ok(total_value / total_qty)
         }
         _ => Ok(0.0),
     }
@@ -360,30 +796,19 @@ pub fn get_product_transactions(
     product_id: i32,
     limit: Option<i32>,
 ) -> Result<Vec<InventoryTransaction>, String> {
-    let query = if let Some(lim) = limit {
-        format!(
-            "SELECT id, product_id, transaction_type, quantity_change, unit_cost,
-                    reference_type, reference_id, balance_after, transaction_date,
-                    notes, created_at
-             FROM inventory_transactions
-             WHERE product_id = ?
-             ORDER BY transaction_date DESC, id DESC
-             LIMIT {}",
-            lim
-        )
-    } else {
-        "SELECT id, product_id, transaction_type, quantity_change, unit_cost,
+    // A negative LIMIT means no limit in SQLite
+    let query = "SELECT id, product_id, transaction_type, quantity_change, unit_cost,
                 reference_type, reference_id, balance_after, transaction_date,
                 notes, created_at
          FROM inventory_transactions
-         WHERE product_id = ?
-         ORDER BY transaction_date DESC, id DESC".to_string()
-    };
+         WHERE product_id = ?1
+         ORDER BY transaction_date DESC, id DESC
+         LIMIT ?2";
 
-    let mut stmt = conn.prepare(&query)
+    let mut stmt = conn.prepare(query)
         .map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
-    let transactions = stmt.query_map(params![product_id], |row| {
+    let transactions = stmt.query_map(params![product_id, limit.unwrap_or(-1)], |row| {
         Ok(InventoryTransaction {
             id: row.get(0)?,
             product_id: row.get(1)?,
@@ -412,22 +837,22 @@ pub fn get_product_transactions(
 pub fn record_adjustment(
     conn: &Connection,
     product_id: i32,
-    quantity_change: i32, // Can be positive or negative
+    quantity_change: f64, // Can be positive or negative
     reason: &str,
     adjustment_date: &str,
 ) -> Result<(), String> {
     let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
     // Get current stock
-    let current_stock: i32 = conn.query_row(
+    let current_stock: f64 = conn.query_row(
         "SELECT stock_quantity FROM products WHERE id = ?",
         params![product_id],
         |row| row.get(0),
     ).map_err(|e| format!("Failed to get stock quantity: {}", e))?;
 
-    let balance_after = current_stock + quantity_change;
+    let balance_after = quantity::sum([current_stock, quantity_change]);
 
-    if balance_after < 0 {
+    if balance_after < 0.0 {
         return Err("Adjustment would result in negative stock".to_string());
     }
 
@@ -454,7 +879,7 @@ pub fn record_adjustment(
     ).map_err(|e| format!("Failed to update product stock: {}", e))?;
 
     // If it's a positive adjustment, create a batch
-    if quantity_change > 0 {
+    if quantity_change > 0.0 {
         let avg_cost = get_average_cost(conn, product_id).unwrap_or(0.0);
         record_purchase(conn, product_id, quantity_change, avg_cost, None, adjustment_date)?;
     }
@@ -471,13 +896,13 @@ pub fn validate_stock_consistency(
     conn: &Connection,
     product_id: i32,
 ) -> Result<bool, String> {
-    let product_stock: i32 = conn.query_row(
+    let product_stock: f64 = conn.query_row(
         "SELECT stock_quantity FROM products WHERE id = ?",
         params![product_id],
         |row| row.get(0),
     ).map_err(|e| format!("Failed to get product stock: {}", e))?;
 
-    let batch_total: i32 = conn.query_row(
+    let batch_total: f64 = conn.query_row(
         "SELECT COALESCE(SUM(quantity_remaining), 0)
          FROM inventory_batches
          WHERE product_id = ?",
@@ -485,7 +910,7 @@ pub fn validate_stock_consistency(
         |row| row.get(0),
     ).map_err(|e| format!("Failed to get batch total: {}", e))?;
 
-    Ok(product_stock == batch_total)
+    Ok(to_milli(product_stock) == to_milli(batch_total))
 }
 
 /// Get products with stock inconsistencies
@@ -495,7 +920,7 @@ pub fn get_inconsistent_products(conn: &Connection) -> Result<Vec<i32>, String>
          FROM products p
          LEFT JOIN inventory_batches ib ON p.id = ib.product_id
          GROUP BY p.id
-         HAVING p.stock_quantity != batch_total"
+         HAVING ROUND(p.stock_quantity, 3) != ROUND(batch_total, 3)"
     ).map_err(|e| format!("Failed to prepare statement: {}", e))?;
 
     let product_ids = stmt.query_map([], |row| row.get::<_, i32>(0))
@@ -509,9 +934,71 @@ pub fn get_inconsistent_products(conn: &Connection) -> Result<Vec<i32>, String>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{batch_quantity, TestDb};
+
+    fn insert_product(conn: &Connection, sku: &str) -> i32 {
+        conn.execute(
+            "INSERT INTO products (name, sku, price, stock_quantity) VALUES (?1, ?1, 0, 0)",
+            [sku],
+        )
+        .unwrap();
+        conn.last_insert_rowid() as i32
+    }
 
     #[test]
     fn test_fifo_calculation() {
-        // TODO: Add unit tests for FIFO logic
+        let db = TestDb::new();
+        let conn = db.conn();
+        let product_id = insert_product(&conn, "FIFO-1");
+        record_purchase(&conn, product_id, 5.0, 10.0, None, "2024-01-01").unwrap();
+        record_purchase(&conn, product_id, 5.0, 20.0, None, "2024-02-01").unwrap();
+
+        let result = calculate_fifo_cogs(&conn, product_id, 7.0).unwrap();
+        assert_eq!(result.total_cogs, 90.0);
+        assert_eq!(result.breakdown.len(), 2);
+        assert_eq!(result.batches_depleted.len(), 1);
+        // Calculating alone does not touch the batches
+        assert_eq!(batch_quantity(&conn, product_id), 10.0);
+    }
+
+    #[test]
+    fn test_sale_and_restore_round_trip() {
+        let db = TestDb::new();
+        let conn = db.conn();
+        let product_id = insert_product(&conn, "FIFO-2");
+        record_purchase(&conn, product_id, 5.0, 10.0, None, "2024-01-01").unwrap();
+        record_purchase(&conn, product_id, 5.0, 20.0, None, "2024-02-01").unwrap();
+        conn.execute("UPDATE products SET stock_quantity = 3 WHERE id = ?1", [product_id]).unwrap();
+        conn.execute("INSERT INTO invoices (id, invoice_number, total_amount) VALUES (42, 'INV-000042', 0)", []).unwrap();
+
+        let cogs = record_sale_fifo(&conn, product_id, 7.0, "2024-03-01", 42, None).unwrap();
+        assert_eq!(cogs, 90.0);
+        assert_eq!(batch_quantity(&conn, product_id), 3.0);
+        let consumed: f64 = conn
+            .query_row("SELECT SUM(quantity) FROM batch_consumptions WHERE invoice_id = 42", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(consumed, 7.0);
+
+        // Units go back into batches with their original costs and the sale transaction is voided
+        restore_stock_from_invoice(&conn, product_id, 7.0, 42, false).unwrap();
+        assert_eq!(batch_quantity(&conn, product_id), 10.0);
+        let stock: f64 = conn
+            .query_row("SELECT stock_quantity FROM products WHERE id = ?1", [product_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(stock, 10.0);
+        let result = calculate_fifo_cogs(&conn, product_id, 10.0).unwrap();
+        assert_eq!(result.total_cogs, 150.0);
+        let consumptions: i32 = conn
+            .query_row("SELECT COUNT(*) FROM batch_consumptions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(consumptions, 0);
+        let sales: i32 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM inventory_transactions WHERE product_id = ?1 AND transaction_type = 'sale'",
+                [product_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(sales, 0);
     }
 }
@@ -0,0 +1,248 @@
+/// Performance Metrics
+/// Every IPC command is timed by the invoke handler in lib.rs and recorded here: per-command
+/// totals since app start (or the last reset) plus a ring buffer of recent durations for the
+/// percentiles. Commands slower than SLOW_COMMAND_THRESHOLD are logged as they happen.
+///
+/// Synchronous commands run inside the invoke handler, so their durations are exact. Async
+/// commands only hand their work to the runtime there, so what's recorded for them is the
+/// dispatch time. Tauri doesn't pass a command's return value back to the invoke handler, so
+/// the failures counted here are invocations that were rejected before running (unknown command,
+/// maintenance lock); an Err returned by the command itself isn't visible at that point.
+///
+/// SQLite statements slower than SLOW_QUERY_THRESHOLD are logged with their shape: the statement
+/// text with literals replaced by `?`, so the log never holds customer data.
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Commands slower than this are logged
+pub const SLOW_COMMAND_THRESHOLD: Duration = Duration::from_millis(250);
+
+/// SQL statements slower than this are logged
+pub const SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Recent command durations kept for the percentiles
+const MAX_SAMPLES: usize = 5000;
+
+/// Longest statement shape written to the log
+const MAX_SHAPE_LENGTH: usize = 500;
+
+/// Timing for one command
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandStats {
+    pub command: String,
+    pub count: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    /// Percentiles over the command's recent invocations still in the ring buffer
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+    pub total_ms: f64,
+}
+
+/// Command timings since `since`, slowest p95 first
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceMetrics {
+    pub since: String,
+    pub slow_command_threshold_ms: u64,
+    pub commands: Vec<CommandStats>,
+}
+
+#[derive(Default)]
+struct Totals {
+    count: u64,
+    errors: u64,
+    max: Duration,
+    total: Duration,
+}
+
+struct Recorded {
+    since: chrono::DateTime<chrono::Utc>,
+    totals: HashMap<String, Totals>,
+    recent: VecDeque<(String, Duration)>,
+}
+
+impl Recorded {
+    fn new() -> Self {
+        Recorded { since: chrono::Utc::now(), totals: HashMap::new(), recent: VecDeque::new() }
+    }
+}
+
+/// Managed state holding the recorded command timings
+pub struct CommandMetrics {
+    recorded: Mutex<Recorded>,
+}
+
+impl Default for CommandMetrics {
+    fn default() -> Self {
+        CommandMetrics { recorded: Mutex::new(Recorded::new()) }
+    }
+}
+
+impl CommandMetrics {
+    /// Record one invocation; slow ones are also logged
+    pub fn record(&self, command: &str, duration: Duration, ok: bool) {
+        if duration >= SLOW_COMMAND_THRESHOLD {
+            log::warn!("Slow command {} took {} ms{}", command, duration.as_millis(), if ok { "" } else { " (failed)" });
+        }
+
+        let Ok(mut recorded) = self.recorded.lock() else { return };
+        let totals = recorded.totals.entry(command.to_string()).or_default();
+        totals.count += 1;
+        if !ok {
+            totals.errors += 1;
+        }
+        totals.max = totals.max.max(duration);
+        totals.total += duration;
+
+        if recorded.recent.len() == MAX_SAMPLES {
+            recorded.recent.pop_front();
+        }
+        recorded.recent.push_back((command.to_string(), duration));
+    }
+
+    /// Aggregated timings per command
+    pub fn snapshot(&self) -> Result<PerformanceMetrics, String> {
+        let recorded = self.recorded.lock().map_err(|e| e.to_string())?;
+
+        let mut recent: HashMap<&str, Vec<Duration>> = HashMap::new();
+        for (command, duration) in &recorded.recent {
+            recent.entry(command.as_str()).or_default().push(*duration);
+        }
+
+        let mut commands: Vec<CommandStats> = recorded
+            .totals
+            .iter()
+            .map(|(command, totals)| {
+                let mut durations = recent.remove(command.as_str()).unwrap_or_default();
+                durations.sort();
+                CommandStats {
+                    command: command.clone(),
+                    count: totals.count,
+                    errors: totals.errors,
+                    error_rate: totals.errors as f64 / totals.count as f64,
+                    p50_ms: millis(percentile(&durations, 50)),
+                    p95_ms: millis(percentile(&durations, 95)),
+                    max_ms: millis(totals.max),
+                    total_ms: millis(totals.total),
+                }
+            })
+            .collect();
+        commands.sort_by(|a, b| b.p95_ms.total_cmp(&a.p95_ms).then_with(|| a.command.cmp(&b.command)));
+
+        Ok(PerformanceMetrics {
+            since: recorded.since.to_rfc3339(),
+            slow_command_threshold_ms: SLOW_COMMAND_THRESHOLD.as_millis() as u64,
+            commands,
+        })
+    }
+
+    /// Forget everything recorded so far
+    pub fn reset(&self) -> Result<(), String> {
+        let mut recorded = self.recorded.lock().map_err(|e| e.to_string())?;
+        *recorded = Recorded::new();
+        Ok(())
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1_000_000.0).round() / 1000.0
+}
+
+/// Nearest-rank percentile of sorted durations (zero when there are none)
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// SQLite profile callback, installed on every pooled connection
+pub fn log_slow_statement(sql: &str, duration: Duration) {
+    if duration >= SLOW_QUERY_THRESHOLD {
+        log::warn!("Slow SQL ({} ms): {}", duration.as_millis(), statement_shape(sql));
+    }
+}
+
+/// The statement with whitespace collapsed and string and number literals replaced by `?`;
+/// bound parameters (`?`, `?1`, `:name`) are left as they are
+pub fn statement_shape(sql: &str) -> String {
+    let mut shape = String::with_capacity(sql.len().min(MAX_SHAPE_LENGTH));
+    let mut chars = sql.chars().peekable();
+    let mut previous = ' ';
+
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            // String literal ('' is an escaped quote)
+            while let Some(next) = chars.next() {
+                if next == '\'' {
+                    if chars.peek() == Some(&'\'') {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            shape.push('?');
+            previous = '?';
+        } else if c.is_ascii_digit() && !(previous.is_alphanumeric() || previous == '_' || previous == '?') {
+            while chars.peek().is_some_and(|n| n.is_ascii_alphanumeric() || *n == '.') {
+                chars.next();
+            }
+            shape.push('?');
+            previous = '?';
+        } else if c.is_whitespace() {
+            if previous != ' ' {
+                shape.push(' ');
+            }
+            previous = ' ';
+        } else {
+            shape.push(c);
+            previous = c;
+        }
+
+        if shape.len() >= MAX_SHAPE_LENGTH {
+            shape.push_str("...");
+            break;
+        }
+    }
+
+    shape.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_shape_hides_literals() {
+        assert_eq!(
+            statement_shape("SELECT * FROM customers\n   WHERE name = 'O''Brien' AND id > 42 AND phone = ?1 LIMIT 10"),
+            "SELECT * FROM customers WHERE name = ? AND id > ? AND phone = ?1 LIMIT ?"
+        );
+        assert_eq!(statement_shape("SELECT col2 FROM t2 WHERE x = -1.5"), "SELECT col2 FROM t2 WHERE x = -?");
+    }
+
+    #[test]
+    fn test_metrics_aggregate_and_reset() {
+        let metrics = CommandMetrics::default();
+        for ms in 1..=100 {
+            metrics.record("get_products", Duration::from_millis(ms), true);
+        }
+        metrics.record("get_products", Duration::from_millis(5), false);
+        metrics.record("get_customers", Duration::from_millis(3), true);
+
+        let snapshot = metrics.snapshot().unwrap();
+        assert_eq!(snapshot.commands[0].command, "get_products");
+        let products = &snapshot.commands[0];
+        assert_eq!((products.count, products.errors), (101, 1));
+        assert_eq!((products.p50_ms, products.p95_ms, products.max_ms), (50.0, 95.0, 100.0));
+        assert!((products.error_rate - 1.0 / 101.0).abs() < 1e-9);
+
+        metrics.reset().unwrap();
+        assert!(metrics.snapshot().unwrap().commands.is_empty());
+    }
+}
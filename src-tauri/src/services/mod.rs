@@ -1 +1,9 @@
 pub mod inventory_service;
+pub mod receipt_service;
+pub mod money;
+pub mod quantity;
+pub mod image_search;
+pub mod events;
+pub mod device;
+pub mod metrics;
+pub mod payment_shares;
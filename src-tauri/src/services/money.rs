@@ -0,0 +1,145 @@
+/// Money Service
+/// Amounts are stored and passed around as f64 rupees, but sums, differences and splits are
+/// done in integer paise so results are exact to the paisa (no 0.009999999 leftovers).
+
+/// Convert rupees to whole paise, rounding half away from zero
+pub fn to_paise(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+/// Convert paise back to rupees
+pub fn from_paise(paise: i64) -> f64 {
+    paise as f64 / 100.0
+}
+
+/// Round an amount to the paisa
+pub fn round_money(amount: f64) -> f64 {
+    from_paise(to_paise(amount))
+}
+
+/// Exact sum of amounts, each rounded to the paisa first
+pub fn sum<I: IntoIterator<Item = f64>>(amounts: I) -> f64 {
+    from_paise(amounts.into_iter().map(to_paise).sum())
+}
+
+/// Exact difference `a - b` to the paisa
+pub fn sub(a: f64, b: f64) -> f64 {
+    from_paise(to_paise(a) - to_paise(b))
+}
+
+/// Line total: unit price (rounded to the paisa) times quantity, rounded to the paisa
+pub fn line_total(unit_price: f64, quantity: f64) -> f64 {
+    let quantity = crate::services::quantity::round_quantity(quantity);
+    from_paise((to_paise(unit_price) as f64 * quantity).round() as i64)
+}
+
+/// `rate` percent of an amount, rounded to the paisa
+pub fn percent_of(amount: f64, rate: f64) -> f64 {
+    round_money(amount * rate / 100.0)
+}
+
+/// Whether `amount` is greater than `limit` by at least one paisa
+pub fn exceeds(amount: f64, limit: f64) -> bool {
+    to_paise(amount) > to_paise(limit)
+}
+
+/// Split `total` across `weights` in proportion, to the paisa. The shares always add up to
+/// exactly `total`; leftover paise go to the largest remainders. With no positive weight the
+/// whole amount goes to the last entry.
+pub fn allocate(total: f64, weights: &[f64]) -> Vec<f64> {
+    if weights.is_empty() {
+        return Vec::new();
+    }
+
+    let total_paise = to_paise(total);
+    let weight_sum: f64 = weights.iter().filter(|w| **w > 0.0).sum();
+    if weight_sum <= 0.0 {
+        let mut shares = vec![0.0; weights.len()];
+        shares[weights.len() - 1] = from_paise(total_paise);
+        return shares;
+    }
+
+    let exact: Vec<f64> = weights
+        .iter()
+        .map(|w| total_paise as f64 * w.max(0.0) / weight_sum)
+        .collect();
+    let mut paise: Vec<i64> = exact.iter().map(|e| e.floor() as i64).collect();
+
+    let mut leftover = total_paise - paise.iter().sum::<i64>();
+    let mut by_remainder: Vec<usize> = (0..weights.len()).collect();
+    by_remainder.sort_by(|a, b| {
+        let ra = exact[*a] - exact[*a].floor();
+        let rb = exact[*b] - exact[*b].floor();
+        rb.partial_cmp(&ra).unwrap_or(std::cmp::Ordering::Equal).then(a.cmp(b))
+    });
+    for index in by_remainder.into_iter().cycle() {
+        if leftover <= 0 {
+            break;
+        }
+        paise[index] += 1;
+        leftover -= 1;
+    }
+
+    paise.into_iter().map(from_paise).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sum_avoids_float_drift() {
+        assert_ne!(0.1 + 0.2, 0.3);
+        assert_eq!(sum([0.1, 0.2]), 0.3);
+        assert_eq!(sub(0.3, 0.1), 0.2);
+        assert_eq!(sub(100.0, 99.99), 0.01);
+    }
+
+    #[test]
+    fn test_large_invoice_total_is_exact() {
+        let total = sum((0..100).map(|_| line_total(9.99, 1.0)));
+        assert_eq!(total, 999.0);
+        assert_eq!(line_total(9.99, 100.0), 999.0);
+        assert_eq!(line_total(12.99, 3.5), 45.47);
+
+        let naive: f64 = (0..100).map(|_| 9.99).sum();
+        assert_ne!(naive, 999.0);
+    }
+
+    #[test]
+    fn test_paid_plus_pending_equals_total() {
+        let total = sum([33.33, 33.33, 33.34]);
+        let paid = sum([10.1, 20.2]);
+        let pending = sub(total, paid);
+        assert_eq!(pending, 69.7);
+        assert_eq!(sum([paid, pending]), total);
+    }
+
+    #[test]
+    fn test_exceeds_allows_exact_final_payment() {
+        let total = sum([0.1, 0.2, 99.7]);
+        let paid = sum([50.0, 20.0]);
+        assert!(!exceeds(paid + 30.0, total));
+        assert!(exceeds(paid + 30.01, total));
+    }
+
+    #[test]
+    fn test_allocate_adds_up_to_total() {
+        let shares = allocate(100.0, &[1.0, 1.0, 1.0]);
+        assert_eq!(shares, vec![33.34, 33.33, 33.33]);
+        assert_eq!(sum(shares), 100.0);
+
+        let shares = allocate(0.3, &[0.1, 0.2]);
+        assert_eq!(shares, vec![0.1, 0.2]);
+
+        let shares = allocate(10.0, &[0.0, 0.0]);
+        assert_eq!(shares, vec![0.0, 10.0]);
+        assert!(allocate(5.0, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_percent_of_rounds_to_paisa() {
+        assert_eq!(percent_of(9.99, 18.0), 1.8);
+        assert_eq!(percent_of(1234.56, 5.0), 61.73);
+    }
+}
@@ -0,0 +1,129 @@
+/// Payment Shares Service
+/// A supplier payment made against a whole PO (no product, no explicit allocations) is
+/// attributed to the PO's lines in proportion to their value. Shares are split in paise with
+/// money::allocate, so the lines of a PO always add back up to exactly the payment and every
+/// screen showing what was paid for a product agrees to the paisa.
+
+use rusqlite::Connection;
+
+use crate::services::money;
+
+/// The part of one PO-level payment attributed to a product
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentShare {
+    pub payment_id: i32,
+    pub share: f64,
+}
+
+/// Split a PO-level payment across the PO's line values (base currency), one share per line
+pub fn split_payment(amount: f64, line_values: &[f64]) -> Vec<f64> {
+    money::allocate(amount, line_values)
+}
+
+/// Shares of unallocated PO-level payments attributed to `product_id`, optionally for one
+/// supplier only, oldest payment first. A product on several lines of a PO gets the sum of
+/// their shares; payments whose share rounds to nothing are left out.
+pub fn product_payment_shares(
+    conn: &Connection,
+    supplier_id: Option<i32>,
+    product_id: i32,
+) -> Result<Vec<PaymentShare>, String> {
+    let mut payment_stmt = conn
+        .prepare(
+            "SELECT sp.id, sp.po_id, sp.amount
+             FROM supplier_payments sp
+             WHERE sp.product_id IS NULL
+               AND (?1 IS NULL OR sp.supplier_id = ?1)
+               AND EXISTS (SELECT 1 FROM purchase_order_items poi WHERE poi.po_id = sp.po_id AND poi.product_id = ?2)
+               AND NOT EXISTS (SELECT 1 FROM supplier_payment_allocations a WHERE a.payment_id = sp.id)
+             ORDER BY sp.paid_at, sp.id",
+        )
+        .map_err(|e| e.to_string())?;
+    let payments = payment_stmt
+        .query_map(rusqlite::params![supplier_id, product_id], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, f64>(2)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to load PO payments: {}", e))?;
+
+    let mut line_stmt = conn
+        .prepare(
+            "SELECT poi.product_id, poi.total_cost * po.exchange_rate
+             FROM purchase_order_items poi
+             JOIN purchase_orders po ON po.id = poi.po_id
+             WHERE poi.po_id = ?1
+             ORDER BY poi.id",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut shares = Vec::new();
+    for (payment_id, po_id, amount) in payments {
+        let lines = line_stmt
+            .query_map([po_id], |row| Ok((row.get::<_, i32>(0)?, row.get::<_, f64>(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to load PO lines: {}", e))?;
+        let values: Vec<f64> = lines.iter().map(|(_, value)| *value).collect();
+
+        let share = money::sum(
+            lines
+                .iter()
+                .zip(split_payment(amount, &values))
+                .filter(|((line_product, _), _)| *line_product == product_id)
+                .map(|(_, share)| share),
+        );
+        if share > 0.0 {
+            shares.push(PaymentShare { payment_id, share });
+        }
+    }
+    Ok(shares)
+}
+
+/// Total of product_payment_shares
+pub fn product_payment_share_total(
+    conn: &Connection,
+    supplier_id: Option<i32>,
+    product_id: i32,
+) -> Result<f64, String> {
+    Ok(money::sum(
+        product_payment_shares(conn, supplier_id, product_id)?
+            .into_iter()
+            .map(|share| share.share),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shares_always_sum_back_to_the_payment() {
+        // Small LCG so the cases are pseudo-random but the same on every run
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move |bound: u64| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) % bound
+        };
+
+        for _ in 0..2000 {
+            let amount = next(10_000_000) as f64 / 100.0;
+            let lines = 1 + next(12) as usize;
+            let values: Vec<f64> = (0..lines)
+                .map(|_| next(5_000_000) as f64 / 100.0 * (1.0 + next(300) as f64 / 1000.0))
+                .collect();
+
+            let shares = split_payment(amount, &values);
+            assert_eq!(shares.len(), lines);
+            let total: f64 = shares.iter().sum();
+            assert!(
+                (total - amount).abs() < 0.005,
+                "shares {:?} of {} sum to {}",
+                shares,
+                amount,
+                total
+            );
+            assert!(shares.iter().all(|share| *share >= 0.0 && money::round_money(*share) == *share));
+        }
+    }
+}
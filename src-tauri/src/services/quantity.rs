@@ -0,0 +1,113 @@
+/// Quantity Service
+/// Stock and line quantities are f64 so products sold by weight or length can hold 3.5 m, but
+/// sums, differences and FIFO takes are done in integer thousandths of a unit so results stay
+/// exact (no 3.0000000004 m left in a batch). Products counted in pieces keep whole quantities.
+
+/// Units a product can be stocked and sold in
+pub const UNITS: &[&str] = &["pcs", "kg", "g", "m", "L"];
+
+/// Unit of products created without one
+pub const DEFAULT_UNIT: &str = "pcs";
+
+/// Convert a quantity to whole thousandths, rounding half away from zero
+pub fn to_milli(quantity: f64) -> i64 {
+    (quantity * 1000.0).round() as i64
+}
+
+/// Convert thousandths back to a quantity
+pub fn from_milli(milli: i64) -> f64 {
+    milli as f64 / 1000.0
+}
+
+/// Round a quantity to the thousandth
+pub fn round_quantity(quantity: f64) -> f64 {
+    from_milli(to_milli(quantity))
+}
+
+/// Exact sum of quantities, each rounded to the thousandth first
+pub fn sum<I: IntoIterator<Item = f64>>(quantities: I) -> f64 {
+    from_milli(quantities.into_iter().map(to_milli).sum())
+}
+
+/// Exact difference `a - b` to the thousandth
+pub fn sub(a: f64, b: f64) -> f64 {
+    from_milli(to_milli(a) - to_milli(b))
+}
+
+/// Smallest quantity a product in `unit` can be sold or bought in: whole units unless the
+/// product allows fractions, and always whole pieces
+pub fn quantity_step(unit: &str, allow_fractional: bool) -> f64 {
+    if !allow_fractional {
+        return 1.0;
+    }
+    match unit {
+        "kg" => 0.001,
+        "m" | "L" => 0.01,
+        _ => 1.0,
+    }
+}
+
+/// Check a unit name and that it may be combined with fractional quantities
+pub fn validate_unit(unit: &str, allow_fractional: bool) -> Result<(), String> {
+    if !UNITS.contains(&unit) {
+        return Err(format!("Validation error: unit must be one of {}", UNITS.join(", ")));
+    }
+    if allow_fractional && quantity_step(unit, true) >= 1.0 {
+        return Err(format!("Validation error: products in {} are counted in whole units", unit));
+    }
+    Ok(())
+}
+
+/// Check that `quantity` is a whole number of the unit's steps, e.g. 3.5 m but not 3.505 m
+pub fn validate_quantity(quantity: f64, unit: &str, allow_fractional: bool) -> Result<(), String> {
+    if !quantity.is_finite() {
+        return Err("Validation error: quantity must be a number".to_string());
+    }
+    let step = to_milli(quantity_step(unit, allow_fractional));
+    let milli = to_milli(quantity);
+    if milli % step != 0 || (quantity - from_milli(milli)).abs() > 1e-9 {
+        return Err(format!(
+            "Validation error: {} {} is not a multiple of {} {}",
+            format_quantity(quantity),
+            unit,
+            format_quantity(from_milli(step)),
+            unit
+        ));
+    }
+    Ok(())
+}
+
+/// A quantity for display, without trailing zeros: "3", "3.5", "0.125"
+pub fn format_quantity(quantity: f64) -> String {
+    let formatted = format!("{:.3}", round_quantity(quantity));
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sums_stay_exact() {
+        let naive: f64 = [0.1, 0.2].iter().sum();
+        assert_ne!(naive, 0.3);
+        assert_eq!(sum([0.1, 0.2]), 0.3);
+        assert_eq!(sub(100.0, 3.3), 96.7);
+        assert_eq!(format_quantity(3.0000000004), "3");
+        assert_eq!(format_quantity(0.125), "0.125");
+    }
+
+    #[test]
+    fn test_steps_follow_unit() {
+        assert!(validate_quantity(3.5, "m", true).is_ok());
+        assert!(validate_quantity(3.505, "m", true).is_err());
+        assert!(validate_quantity(0.125, "kg", true).is_ok());
+        assert!(validate_quantity(3.5, "m", false).is_err());
+        assert!(validate_quantity(2.0, "pcs", false).is_ok());
+        assert!(validate_quantity(f64::NAN, "kg", true).is_err());
+
+        assert!(validate_unit("m", true).is_ok());
+        assert!(validate_unit("pcs", true).is_err());
+        assert!(validate_unit("ft", false).is_err());
+    }
+}
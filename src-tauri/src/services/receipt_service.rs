@@ -0,0 +1,388 @@
+/// Receipt Service
+/// Renders invoices into ESC/POS byte streams for 58mm/80mm thermal printers
+use crate::services::quantity::format_quantity;
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+
+const INIT: [u8; 2] = [ESC, b'@'];
+const ALIGN_LEFT: [u8; 3] = [ESC, b'a', 0];
+const ALIGN_CENTER: [u8; 3] = [ESC, b'a', 1];
+const BOLD_ON: [u8; 3] = [ESC, b'E', 1];
+const BOLD_OFF: [u8; 3] = [ESC, b'E', 0];
+const DOUBLE_SIZE_ON: [u8; 3] = [GS, b'!', 0x11];
+const DOUBLE_SIZE_OFF: [u8; 3] = [GS, b'!', 0x00];
+const FEED_AND_CUT: [u8; 7] = [ESC, b'd', 3, GS, b'V', 66, 0];
+
+/// A single printed item row
+#[derive(Debug, Clone)]
+pub struct ReceiptLine {
+    pub name: String,
+    pub quantity: f64,
+    pub unit_price: f64,
+    /// Discount taken off this line, printed under it
+    pub discount: f64,
+    /// Line value after its discount
+    pub amount: f64,
+}
+
+/// Everything printed on a receipt, already resolved from the invoice and settings
+#[derive(Debug, Clone)]
+pub struct ReceiptData {
+    pub company_name: String,
+    pub company_address: Option<String>,
+    pub company_phone: Option<String>,
+    pub invoice_number: String,
+    pub date: String,
+    pub customer_name: Option<String>,
+    pub items: Vec<ReceiptLine>,
+    pub subtotal: f64,
+    pub discount: f64,
+    pub cgst: f64,
+    pub sgst: f64,
+    pub igst: f64,
+    /// Rounding adjustment already included in `total`
+    pub round_off: f64,
+    pub total: f64,
+    pub payment_method: Option<String>,
+}
+
+/// Printer-specific layout options
+#[derive(Debug, Clone)]
+pub struct ReceiptOptions {
+    pub paper_width_mm: u32, // 58 or 80
+    pub charset: String,     // ESC/POS code page name, e.g. "PC437"
+    pub copies: u32,
+    pub footer: Option<String>,
+}
+
+impl Default for ReceiptOptions {
+    fn default() -> Self {
+        Self {
+            paper_width_mm: 80,
+            charset: "PC437".to_string(),
+            copies: 1,
+            footer: None,
+        }
+    }
+}
+
+impl ReceiptOptions {
+    /// Characters per line in the default font (Font A)
+    pub fn line_chars(&self) -> usize {
+        if self.paper_width_mm <= 58 {
+            32
+        } else {
+            48
+        }
+    }
+
+    /// ESC t code page number for the configured character set
+    fn code_page(&self) -> u8 {
+        match self.charset.to_uppercase().as_str() {
+            "PC850" => 2,
+            "PC860" => 3,
+            "PC863" => 4,
+            "PC865" => 5,
+            "WPC1252" => 16,
+            "PC866" => 17,
+            "PC852" => 18,
+            "PC858" => 19,
+            _ => 0, // PC437
+        }
+    }
+}
+
+/// Render a receipt into ESC/POS bytes, repeated (with a cut) for each copy
+pub fn render_receipt(data: &ReceiptData, options: &ReceiptOptions) -> Vec<u8> {
+    let width = options.line_chars();
+    let separator = "-".repeat(width);
+    let mut out = Vec::new();
+
+    for _ in 0..options.copies.max(1) {
+        out.extend_from_slice(&INIT);
+        out.extend_from_slice(&[ESC, b't', options.code_page()]);
+
+        // Header
+        out.extend_from_slice(&ALIGN_CENTER);
+        out.extend_from_slice(&BOLD_ON);
+        out.extend_from_slice(&DOUBLE_SIZE_ON);
+        for line in wrap_text(&data.company_name, width / 2) {
+            push_line(&mut out, &line);
+        }
+        out.extend_from_slice(&DOUBLE_SIZE_OFF);
+        out.extend_from_slice(&BOLD_OFF);
+        for detail in [&data.company_address, &data.company_phone].into_iter().flatten() {
+            for line in wrap_text(detail, width) {
+                push_line(&mut out, &line);
+            }
+        }
+        out.extend_from_slice(&ALIGN_LEFT);
+
+        push_line(&mut out, &separator);
+        push_line(&mut out, &two_columns(&format!("Invoice: {}", data.invoice_number), &data.date, width));
+        if let Some(customer) = data.customer_name.as_deref().filter(|c| !c.is_empty()) {
+            for line in wrap_text(&format!("Customer: {}", customer), width) {
+                push_line(&mut out, &line);
+            }
+        }
+
+        // Items
+        push_line(&mut out, &separator);
+        for line in item_row("Item", "Qty", "Rate", "Amount", width) {
+            push_line(&mut out, &line);
+        }
+        push_line(&mut out, &separator);
+        for item in &data.items {
+            let rows = item_row(
+                &item.name,
+                &format_quantity(item.quantity),
+                &format!("{:.2}", item.unit_price),
+                &format!("{:.2}", item.amount),
+                width,
+            );
+            for line in rows {
+                push_line(&mut out, &line);
+            }
+            if item.discount > 0.0 {
+                push_line(&mut out, &two_columns("  Discount", &format!("-{:.2}", item.discount), width));
+            }
+        }
+        push_line(&mut out, &separator);
+
+        // Totals with GST split
+        push_line(&mut out, &two_columns("Subtotal", &format!("{:.2}", data.subtotal), width));
+        if data.discount > 0.0 {
+            push_line(&mut out, &two_columns("Discount", &format!("-{:.2}", data.discount), width));
+        }
+        if data.igst > 0.0 {
+            push_line(&mut out, &two_columns("IGST", &format!("{:.2}", data.igst), width));
+        } else {
+            if data.cgst > 0.0 {
+                push_line(&mut out, &two_columns("CGST", &format!("{:.2}", data.cgst), width));
+            }
+            if data.sgst > 0.0 {
+                push_line(&mut out, &two_columns("SGST", &format!("{:.2}", data.sgst), width));
+            }
+        }
+        if data.round_off != 0.0 {
+            push_line(&mut out, &two_columns("Round Off", &format!("{:+.2}", data.round_off), width));
+        }
+        out.extend_from_slice(&BOLD_ON);
+        push_line(&mut out, &two_columns("TOTAL", &format!("Rs. {:.2}", data.total), width));
+        out.extend_from_slice(&BOLD_OFF);
+        if let Some(method) = data.payment_method.as_deref().filter(|m| !m.is_empty()) {
+            push_line(&mut out, &two_columns("Paid by", method, width));
+        }
+        push_line(&mut out, &separator);
+
+        if let Some(footer) = options.footer.as_deref().filter(|f| !f.trim().is_empty()) {
+            out.extend_from_slice(&ALIGN_CENTER);
+            for text in footer.lines() {
+                for line in wrap_text(text, width) {
+                    push_line(&mut out, &line);
+                }
+            }
+            out.extend_from_slice(&ALIGN_LEFT);
+        }
+
+        out.extend_from_slice(&FEED_AND_CUT);
+    }
+
+    out
+}
+
+/// Append a line of text, replacing characters the printer code page can't represent
+fn push_line(out: &mut Vec<u8>, text: &str) {
+    let printable: String = text
+        .replace('₹', "Rs.")
+        .chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '?' })
+        .collect();
+    out.extend_from_slice(printable.as_bytes());
+    out.push(b'\n');
+}
+
+/// Left label and right-aligned value; the value moves to its own line if both don't fit
+fn two_columns(left: &str, right: &str, width: usize) -> String {
+    let used = left.chars().count() + right.chars().count();
+    if used < width {
+        format!("{}{}{}", left, " ".repeat(width - used), right)
+    } else {
+        format!("{}\n{:>width$}", left, right, width = width)
+    }
+}
+
+/// Item row with the name wrapping inside its own column.
+/// The numeric columns are laid out first and never truncated; on narrow paper the
+/// name gets full-width lines above a right-aligned qty/rate/amount line.
+fn item_row(name: &str, qty: &str, rate: &str, amount: &str, width: usize) -> Vec<String> {
+    const MIN_NAME_WIDTH: usize = 10;
+
+    let numbers = format!("{:>4} {:>9} {:>10}", qty, rate, amount);
+    let name_width = width.saturating_sub(numbers.chars().count() + 1);
+
+    if name_width < MIN_NAME_WIDTH {
+        let mut rows = wrap_text(name, width);
+        rows.push(format!("{:>width$}", numbers, width = width));
+        return rows;
+    }
+
+    let mut rows = Vec::new();
+    for (index, line) in wrap_text(name, name_width).into_iter().enumerate() {
+        if index == 0 {
+            rows.push(format!("{:<name_width$} {}", line, numbers, name_width = name_width));
+        } else {
+            rows.push(line);
+        }
+    }
+    rows
+}
+
+/// Word-wrap text to a fixed width, hard-breaking words longer than a line
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for word in text.split_whitespace() {
+        let mut word = word.to_string();
+        while word.chars().count() > width {
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+            lines.push(word.chars().take(width).collect());
+            word = word.chars().skip(width).collect();
+        }
+        if word.is_empty() {
+            continue;
+        }
+
+        if current.is_empty() {
+            current = word;
+        } else if current.chars().count() + 1 + word.chars().count() <= width {
+            current.push(' ');
+            current.push_str(&word);
+        } else {
+            lines.push(std::mem::replace(&mut current, word));
+        }
+    }
+
+    if !current.is_empty() || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_receipt() -> ReceiptData {
+        ReceiptData {
+            company_name: "My Shop".to_string(),
+            company_address: None,
+            company_phone: None,
+            invoice_number: "INV-000001".to_string(),
+            date: "2024-01-15".to_string(),
+            customer_name: Some("Ravi".to_string()),
+            items: vec![ReceiptLine {
+                name: "Premium Basmati Rice Extra Long Grain 5kg".to_string(),
+                quantity: 2.0,
+                unit_price: 450.0,
+                discount: 0.0,
+                amount: 900.0,
+            }],
+            subtotal: 900.0,
+            discount: 0.0,
+            cgst: 22.5,
+            sgst: 22.5,
+            igst: 0.0,
+            round_off: 0.0,
+            total: 945.0,
+            payment_method: Some("Cash".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_render_receipt_snapshot_80mm() {
+        let options = ReceiptOptions {
+            footer: Some("Thank you!".to_string()),
+            ..Default::default()
+        };
+        let bytes = render_receipt(&sample_receipt(), &options);
+
+        let separator = format!("{}\n", "-".repeat(48));
+        let mut expected: Vec<u8> = Vec::new();
+        expected.extend_from_slice(b"\x1b@\x1bt\x00");
+        expected.extend_from_slice(b"\x1ba\x01\x1bE\x01\x1d!\x11My Shop\n\x1d!\x00\x1bE\x00");
+        expected.extend_from_slice(b"\x1ba\x00");
+        expected.extend_from_slice(separator.as_bytes());
+        expected.extend_from_slice(b"Invoice: INV-000001                   2024-01-15\n");
+        expected.extend_from_slice(b"Customer: Ravi\n");
+        expected.extend_from_slice(separator.as_bytes());
+        expected.extend_from_slice(b"Item                    Qty      Rate     Amount\n");
+        expected.extend_from_slice(separator.as_bytes());
+        expected.extend_from_slice(b"Premium Basmati Rice      2    450.00     900.00\n");
+        expected.extend_from_slice(b"Extra Long Grain 5kg\n");
+        expected.extend_from_slice(separator.as_bytes());
+        expected.extend_from_slice(b"Subtotal                                  900.00\n");
+        expected.extend_from_slice(b"CGST                                       22.50\n");
+        expected.extend_from_slice(b"SGST                                       22.50\n");
+        expected.extend_from_slice(b"\x1bE\x01TOTAL                                 Rs. 945.00\n\x1bE\x00");
+        expected.extend_from_slice(b"Paid by                                     Cash\n");
+        expected.extend_from_slice(separator.as_bytes());
+        expected.extend_from_slice(b"\x1ba\x01Thank you!\n\x1ba\x00");
+        expected.extend_from_slice(b"\x1bd\x03\x1dVB\x00");
+
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn test_narrow_paper_keeps_amounts_whole() {
+        let options = ReceiptOptions {
+            paper_width_mm: 58,
+            copies: 2,
+            ..Default::default()
+        };
+        let bytes = render_receipt(&sample_receipt(), &options);
+        let text = String::from_utf8_lossy(&bytes);
+
+        // Name is wrapped on its own lines and the numbers line stays intact
+        assert!(text.contains("Premium Basmati Rice Extra Long\nGrain 5kg\n"));
+        assert!(text.contains("      2    450.00     900.00\n"));
+        assert_eq!(text.matches("INV-000001").count(), 2);
+        // Lines that start with ESC/POS commands carry non-printing bytes; the plain ones must fit
+        assert!(text
+            .lines()
+            .filter(|line| !line.chars().any(|c| c.is_control()))
+            .all(|line| line.chars().count() <= 32));
+    }
+
+    #[test]
+    fn test_round_off_line_printed_when_non_zero() {
+        let mut data = sample_receipt();
+        let plain = String::from_utf8_lossy(&render_receipt(&data, &ReceiptOptions::default())).to_string();
+        assert!(!plain.contains("Round Off"));
+
+        data.round_off = -0.35;
+        let text = String::from_utf8_lossy(&render_receipt(&data, &ReceiptOptions::default())).to_string();
+        assert!(text.contains("Round Off"));
+        assert!(text.contains("-0.35\n"));
+    }
+
+    #[test]
+    fn test_line_discount_printed_under_its_item() {
+        let mut data = sample_receipt();
+        data.items[0].discount = 50.0;
+        data.items[0].amount = 850.0;
+        let text = String::from_utf8_lossy(&render_receipt(&data, &ReceiptOptions::default())).to_string();
+        assert!(text.contains("Premium Basmati Rice      2    450.00     850.00\nExtra Long Grain 5kg\n  Discount                                -50.00\n"));
+    }
+
+    #[test]
+    fn test_wrap_text_breaks_long_words() {
+        assert_eq!(wrap_text("ABCDEFGHIJ KL", 4), vec!["ABCD", "EFGH", "IJ", "KL"]);
+        assert_eq!(wrap_text("", 10), vec![""]);
+    }
+}
@@ -0,0 +1,132 @@
+/// Test Support
+/// Builds a real `Database` on a throwaway SQLite file with the full schema and all migrations
+/// applied, so tests can drive the `*_internal` functions the Tauri commands delegate to.
+
+use std::ops::Deref;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rusqlite::Connection;
+
+use crate::commands::invoices::{CreateInvoiceInput, CreateInvoiceItemInput};
+use crate::commands::products::CreateProductInput;
+use crate::db::connection::PooledConn;
+use crate::db::Database;
+
+static NEXT_DB: AtomicUsize = AtomicUsize::new(0);
+
+/// A migrated database in its own temp directory, removed again on drop
+pub struct TestDb {
+    db: Option<Database>,
+    dir: PathBuf,
+}
+
+impl TestDb {
+    pub fn new() -> Self {
+        let dir = std::env::temp_dir().join(format!(
+            "inventory_test_{}_{}",
+            std::process::id(),
+            NEXT_DB.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let db = Database::new(dir.join("inventory.db")).expect("Failed to create test database");
+        TestDb { db: Some(db), dir }
+    }
+
+    /// A pooled connection; deref it to `&mut Connection` for the transactional internals
+    pub fn conn(&self) -> PooledConn {
+        self.get_conn().expect("Failed to get test connection")
+    }
+}
+
+impl Deref for TestDb {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.db.as_ref().expect("Test database already dropped")
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        // Close the pool before deleting the files underneath it
+        self.db.take();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Insert a supplier with only a name and return its id
+pub fn insert_supplier(conn: &Connection, name: &str) -> i32 {
+    conn.execute("INSERT INTO suppliers (name) VALUES (?1)", [name])
+        .expect("Failed to insert supplier");
+    conn.last_insert_rowid() as i32
+}
+
+/// Insert a customer with only a name and return its id
+pub fn insert_customer(conn: &Connection, name: &str) -> i32 {
+    conn.execute("INSERT INTO customers (name) VALUES (?1)", [name])
+        .expect("Failed to insert customer");
+    conn.last_insert_rowid() as i32
+}
+
+/// Sum of `quantity_remaining` over a product's open batches
+pub fn batch_quantity(conn: &Connection, product_id: i32) -> f64 {
+    conn.query_row(
+        "SELECT COALESCE(SUM(quantity_remaining), 0) FROM inventory_batches WHERE product_id = ?1",
+        [product_id],
+        |row| row.get(0),
+    )
+    .expect("Failed to sum batches")
+}
+
+/// A whole-unit product named "Product <sku>" bought at `price` and sold at half as much again
+pub fn product_input(sku: &str, price: f64, stock: i32, supplier_id: Option<i32>) -> CreateProductInput {
+    CreateProductInput {
+        name: format!("Product {}", sku),
+        sku: sku.to_string(),
+        price,
+        selling_price: Some(price * 1.5),
+        stock_quantity: stock as f64,
+        supplier_id,
+        amount_paid: None,
+        category: None,
+        unit: None,
+        allow_fractional: false,
+    }
+}
+
+/// A cash sale of `(product_id, quantity, unit_price)` lines with every option left unset;
+/// override fields with struct update syntax
+pub fn invoice_input(customer_id: Option<i32>, items: Vec<(i32, i32, f64)>) -> CreateInvoiceInput {
+    CreateInvoiceInput {
+        customer_id,
+        items: items
+            .into_iter()
+            .map(|(product_id, quantity, unit_price)| CreateInvoiceItemInput {
+                product_id,
+                quantity: quantity as f64,
+                unit_price,
+                discount_amount: None,
+                tax_rate: None,
+                reservation_id: None,
+            })
+            .collect(),
+        tax_amount: None,
+        discount_amount: None,
+        payment_method: Some("Cash".to_string()),
+        state: None,
+        district: None,
+        town: None,
+        initial_paid: None,
+        price_tier_id: None,
+        gst_rate: None,
+        allow_over_limit: false,
+        approved_by: None,
+        due_date: None,
+        payments: None,
+        price_override_reason: None,
+        price_override_approval: None,
+        created_by: None,
+    }
+}
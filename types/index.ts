@@ -98,6 +98,7 @@ export type DashboardStats = {
   total_orders: number;
   low_stock_count: number;
   total_valuation: number;
+  overdue_total: number;
   recent_sales: DashboardSale[];
 };
 